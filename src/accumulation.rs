@@ -0,0 +1,66 @@
+#![allow(dead_code)]
+
+use raylib::prelude::*;
+
+use crate::camera::Camera;
+use crate::scene::SceneObject;
+
+/// Subpixel offsets cycle every this many frames; by then edges have converged.
+pub const JITTER_FRAMES: u32 = 16;
+
+/// Radical inverse of `index` in `base`, in [0, 1).
+pub fn halton(mut index: u32, base: u32) -> f32 {
+    let mut result = 0.0;
+    let mut fraction = 1.0 / base as f32;
+    while index > 0 {
+        result += (index % base) as f32 * fraction;
+        index /= base;
+        fraction /= base as f32;
+    }
+    result
+}
+
+/// Subpixel offset (in pixels, within ±0.5) for the `frame`-th accumulated frame.
+/// The first frame is not jittered, so it matches the normal image.
+pub fn jitter(frame: u32) -> Vector2 {
+    if frame == 0 {
+        return Vector2::zero();
+    }
+    let index = (frame - 1) % JITTER_FRAMES + 1;
+    Vector2::new(halton(index, 2) - 0.5, halton(index, 3) - 0.5)
+}
+
+/// Everything whose change invalidates the accumulated image: the camera, every
+/// body's transform, the simulation time and whatever `extra` main adds.
+pub fn view_state(camera: &Camera, objects: &[SceneObject], sim_time: f32, extra: &[f32]) -> Vec<f32> {
+    let mut state = vec![sim_time];
+    for v in [camera.eye, camera.target, camera.up] {
+        state.extend([v.x, v.y, v.z]);
+    }
+    for obj in objects {
+        let (t, r) = (obj.translation, obj.render_rotation());
+        state.extend([t.x, t.y, t.z, r.x, r.y, r.z, obj.scale, obj.axial_tilt, obj.precession_angle]);
+    }
+    state.extend_from_slice(extra);
+    state
+}
+
+/// Tells frames where nothing changed apart from the subpixel jitter.
+pub struct StillDetector {
+    last: Vec<f32>,
+}
+
+impl StillDetector {
+    pub fn new() -> Self {
+        StillDetector { last: Vec::new() }
+    }
+
+    /// Whether `state` matches the previous frame's with no input in between. Compared
+    /// exactly: any change at all restarts the accumulation.
+    pub fn is_still(&mut self, state: Vec<f32>, input: bool) -> bool {
+        let still = !input && state == self.last;
+        self.last = state;
+        still
+    }
+}
+
@@ -0,0 +1,172 @@
+#![allow(dead_code)]
+
+use crate::fragment::Fragment;
+use crate::vertex::Vertex;
+
+/// Free list of reusable buffers for one element type.
+pub struct Pool<T> {
+    free: Vec<Vec<T>>,
+    high_water: usize, // Largest buffer rented last frame
+    peak: usize,       // Largest buffer returned so far this frame
+}
+
+impl<T> Pool<T> {
+    pub fn new() -> Self {
+        Pool { free: Vec::new(), high_water: 0, peak: 0 }
+    }
+
+    /// Returns an empty buffer with room for at least `capacity` elements
+    /// (or last frame's high-water mark, whichever is larger).
+    pub fn rent(&mut self, capacity: usize) -> Vec<T> {
+        let mut buffer = self.free.pop().unwrap_or_default();
+        buffer.clear();
+        buffer.reserve(capacity.max(self.high_water));
+        buffer
+    }
+
+    /// Hands a buffer back so its allocation can be reused.
+    pub fn recycle(&mut self, mut buffer: Vec<T>) {
+        self.peak = self.peak.max(buffer.len());
+        buffer.clear();
+        self.free.push(buffer);
+    }
+
+    fn reset(&mut self) {
+        self.high_water = self.peak;
+        self.peak = 0;
+    }
+}
+
+/// Per-frame scratch buffers for the render pipeline. Buffers are rented during a
+/// frame and recycled when a stage is done, so steady-state frames reuse the same
+/// allocations instead of hitting the allocator for every object.
+pub struct FrameArena {
+    vertices: Pool<Vertex>,
+    triangles: Pool<[Vertex; 3]>,
+    fragments: Pool<Fragment>,
+}
+
+impl FrameArena {
+    pub fn new() -> Self {
+        FrameArena {
+            vertices: Pool::new(),
+            triangles: Pool::new(),
+            fragments: Pool::new(),
+        }
+    }
+
+    /// Call at the start of every frame.
+    pub fn reset(&mut self) {
+        self.vertices.reset();
+        self.triangles.reset();
+        self.fragments.reset();
+    }
+
+    pub fn vec<T: Pooled>(&mut self, capacity: usize) -> Vec<T> {
+        T::pool(self).rent(capacity)
+    }
+
+    pub fn recycle<T: Pooled>(&mut self, buffer: Vec<T>) {
+        T::pool(self).recycle(buffer);
+    }
+}
+
+/// Element types the arena keeps a pool for.
+pub trait Pooled: Sized {
+    fn pool(arena: &mut FrameArena) -> &mut Pool<Self>;
+}
+
+impl Pooled for Vertex {
+    fn pool(arena: &mut FrameArena) -> &mut Pool<Self> {
+        &mut arena.vertices
+    }
+}
+
+impl Pooled for [Vertex; 3] {
+    fn pool(arena: &mut FrameArena) -> &mut Pool<Self> {
+        &mut arena.triangles
+    }
+}
+
+impl Pooled for Fragment {
+    fn pool(arena: &mut FrameArena) -> &mut Pool<Self> {
+        &mut arena.fragments
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+    use std::f32::consts::PI;
+
+    use raylib::prelude::*;
+
+    use crate::headless::HeadlessTarget;
+    use crate::light::Light;
+    use crate::matrix::{create_projection_matrix, create_view_matrix, create_viewport_matrix};
+    use crate::pipeline::Pipeline;
+    use crate::{FrameUniforms, ObjectUniforms, Uniforms};
+
+    /// Counts allocations per thread, so tests running alongside don't add to them.
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    fn count() {
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            count();
+            unsafe { System.alloc(layout) }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            unsafe { System.dealloc(ptr, layout) }
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            count();
+            unsafe { System.realloc(ptr, layout, new_size) }
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    #[test]
+    fn steady_frames_do_not_allocate() {
+        const SIZE: u32 = 32;
+        let eye = Vector3::new(0.0, 0.0, 3.0);
+        let view = create_view_matrix(eye, Vector3::zero(), Vector3::new(0.0, 1.0, 0.0));
+        let projection = create_projection_matrix(PI / 3.0, 1.0, 0.1, 100.0);
+        let viewport = create_viewport_matrix(0.0, 0.0, SIZE as f32, SIZE as f32);
+        let frame = FrameUniforms::new(view, projection, viewport, eye);
+        let uniforms = Uniforms { frame, object: ObjectUniforms::new(Matrix::identity(), &frame) };
+        // A quad facing the camera
+        let corner = |x: f32, y: f32| Vertex::new(Vector3::new(x, y, 0.0), Vector3::new(0.0, 0.0, 1.0), Vector2::new(x, y));
+        let quad = [corner(-1.0, -1.0), corner(1.0, -1.0), corner(1.0, 1.0), corner(-1.0, -1.0), corner(1.0, 1.0), corner(-1.0, 1.0)];
+
+        let mut pipeline = Pipeline::new();
+        let light = Light::new(Vector3::new(0.0, 0.0, 5.0));
+        let mut target = HeadlessTarget::new(SIZE, SIZE, Vector3::new(1.0, 0.0, 1.0)); // Unlit, the quad shades black
+        let mut arena = FrameArena::new();
+        let mut draw_frame = |arena: &mut FrameArena| {
+            arena.reset();
+            pipeline.render(&mut target, &uniforms, &quad, &light, "quad", "moon", arena);
+            ALLOCATIONS.with(|n| n.get())
+        };
+
+        let before = ALLOCATIONS.with(|n| n.get());
+        let first = draw_frame(&mut arena);
+        assert!(first > before, "the first frame fills the pools");
+        let second = draw_frame(&mut arena);
+        assert_eq!(second, first, "the second frame allocated {} times", second - first);
+        assert!(target.coverage() > 0.2, "the quad was drawn");
+    }
+}
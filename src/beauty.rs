@@ -0,0 +1,79 @@
+#![allow(dead_code)]
+
+use raylib::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::input::InputState;
+
+/// Key that toggles beauty mode (the fill and rim lights).
+pub const BEAUTY_KEY: KeyboardKey = KeyboardKey::KEY_L;
+
+/// Fill-light rig settings, read from the `[beauty]` table of the scene file.
+/// Intensities are fractions of the sunlight each body receives.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BeautyConfig {
+    pub enabled: bool,
+    pub fill: f32,
+    pub fill_tint: [f32; 3],
+    pub rim: f32,
+    pub rim_tint: [f32; 3],
+}
+
+impl Default for BeautyConfig {
+    fn default() -> Self {
+        BeautyConfig { enabled: false, fill: 0.1, fill_tint: [0.75, 0.85, 1.0], rim: 0.25, rim_tint: [0.85, 0.9, 1.0] }
+    }
+}
+
+/// Lights of the rig for one frame, in world space. Neither is blocked by anything,
+/// so they add no shadows of their own.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FillLights {
+    pub fill_direction: Vector3, // Towards the fill light: from over the camera's shoulder
+    pub fill: Vector3,           // Tinted share of the sunlight
+    pub forward: Vector3,        // The camera's view direction; the rim light is behind the subject
+    pub rim: Vector3,            // Tinted share of the sunlight
+}
+
+/// Beauty mode: a cool fill light riding on the camera and a rim light from behind the
+/// subject on the side away from the sun, so night sides read in screenshots while the
+/// sun stays the obvious key light.
+pub struct Beauty {
+    pub enabled: bool,
+    pub config: BeautyConfig,
+}
+
+impl Beauty {
+    pub fn new(config: BeautyConfig) -> Self {
+        Beauty { enabled: config.enabled, config }
+    }
+
+    pub fn process_input(&mut self, input: &InputState) {
+        if input.is_key_pressed(BEAUTY_KEY) {
+            self.enabled = !self.enabled;
+        }
+    }
+
+    /// The rig for a camera looking along `forward` with the given `up` and `right`, or
+    /// None with beauty mode off.
+    pub fn lights(&self, forward: Vector3, up: Vector3, right: Vector3) -> Option<FillLights> {
+        if !self.enabled {
+            return None;
+        }
+        let tint = |t: [f32; 3], scale: f32| Vector3::new(t[0], t[1], t[2]) * scale.max(0.0);
+        Some(FillLights {
+            fill_direction: (up * 0.4 - right * 0.3 - forward).normalized(),
+            fill: tint(self.config.fill_tint, self.config.fill),
+            forward,
+            rim: tint(self.config.rim_tint, self.config.rim),
+        })
+    }
+
+    /// Overlay line while beauty mode is on.
+    pub fn status(&self) -> Option<String> {
+        self.enabled.then(|| {
+            format!("Beauty mode (L): fill {:.0}%  rim {:.0}%", self.config.fill * 100.0, self.config.rim * 100.0)
+        })
+    }
+}
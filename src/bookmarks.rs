@@ -0,0 +1,244 @@
+#![allow(dead_code)]
+
+use raylib::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+use crate::input::InputState;
+use crate::scene::SceneObject;
+use crate::sidecar::{load_state, save_state, sidecar_path};
+use crate::stereo::StereoMode;
+
+/// Key that toggles bookmark mode, where 1..9 recall bookmarks.
+pub const BOOKMARK_MODE_KEY: KeyboardKey = KeyboardKey::KEY_B;
+
+const SLOT_KEYS: [KeyboardKey; 9] = [
+    KeyboardKey::KEY_ONE,
+    KeyboardKey::KEY_TWO,
+    KeyboardKey::KEY_THREE,
+    KeyboardKey::KEY_FOUR,
+    KeyboardKey::KEY_FIVE,
+    KeyboardKey::KEY_SIX,
+    KeyboardKey::KEY_SEVEN,
+    KeyboardKey::KEY_EIGHT,
+    KeyboardKey::KEY_NINE,
+];
+
+/// Render switches a bookmark brings back along with the camera.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ViewSettings {
+    pub fov: f32, // Vertical field of view, in radians
+    pub stereo: StereoMode,
+    pub ev: f32,
+    pub hud: bool,
+    pub minimap: bool,
+    pub swap_shading: bool,
+}
+
+impl Default for ViewSettings {
+    fn default() -> Self {
+        ViewSettings { fov: std::f32::consts::PI / 3.0, stereo: StereoMode::Off, ev: 0.0, hud: true, minimap: true, swap_shading: false }
+    }
+}
+
+/// A stored camera pose. With a `body`, the pose is kept relative to it so recalling
+/// finds the body wherever its orbit has taken it; if the body is gone, the absolute
+/// pose from when it was stored is used instead.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub slot: u8, // 1 to 9
+    pub name: String,
+    pub eye: [f32; 3],
+    pub target: [f32; 3],
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub body_position: [f32; 3], // Where the body was when stored
+    #[serde(default)]
+    pub view: ViewSettings,
+}
+
+impl Bookmark {
+    /// Eye and target to fly to now.
+    pub fn pose(&self, objects: &[SceneObject]) -> (Vector3, Vector3) {
+        let (eye, target) = (vec3(self.eye), vec3(self.target));
+        match self.body.as_ref().and_then(|name| objects.iter().find(|o| &o.name == name)) {
+            Some(body) => {
+                let moved = body.translation - vec3(self.body_position);
+                (eye + moved, target + moved)
+            }
+            None => (eye, target),
+        }
+    }
+}
+
+fn vec3(v: [f32; 3]) -> Vector3 {
+    Vector3::new(v[0], v[1], v[2])
+}
+
+/// Bookmarks as written to the state file.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct BookmarkState {
+    bookmark: Vec<Bookmark>,
+}
+
+/// What the keys asked for this frame.
+pub enum BookmarkAction {
+    Store(u8),
+    Recall(u8),
+}
+
+/// Named camera snapshots in slots 1 to 9: Ctrl+digit stores one, and in bookmark
+/// mode (B) the digit alone recalls it.
+pub struct Bookmarks {
+    pub mode: bool,
+    bookmarks: Vec<Bookmark>, // Sorted by slot
+    path: String,             // State file
+}
+
+impl Bookmarks {
+    /// Starts from the bookmarks saved in `path`, if any.
+    pub fn load(path: String) -> Self {
+        let mut bookmarks = load_state::<BookmarkState>(&path).bookmark;
+        bookmarks.retain(|b| (1..=9).contains(&b.slot));
+        bookmarks.sort_by_key(|b| b.slot);
+        Bookmarks { mode: false, bookmarks, path }
+    }
+
+    pub fn save(&self) -> Result<(), AppError> {
+        save_state(&BookmarkState { bookmark: self.bookmarks.clone() }, &self.path, "bookmarks")
+    }
+
+    /// B toggles bookmark mode; Ctrl+1..9 stores, 1..9 recalls while in bookmark mode.
+    pub fn process_input(&mut self, input: &InputState) -> Option<BookmarkAction> {
+        if input.is_key_pressed(BOOKMARK_MODE_KEY) {
+            self.mode = !self.mode;
+        }
+        let ctrl = input.is_key_down(KeyboardKey::KEY_LEFT_CONTROL) || input.is_key_down(KeyboardKey::KEY_RIGHT_CONTROL);
+        let slot = SLOT_KEYS.iter().position(|&key| input.is_key_pressed(key))? as u8 + 1;
+        if ctrl {
+            Some(BookmarkAction::Store(slot))
+        } else if self.mode {
+            Some(BookmarkAction::Recall(slot))
+        } else {
+            None
+        }
+    }
+
+    /// Stores the pose in `slot`, attached to the body nearest `target` if the target
+    /// is on or near one. Keeps the slot's name when it is overwritten.
+    pub fn store(&mut self, slot: u8, eye: Vector3, target: Vector3, objects: &[SceneObject], view: ViewSettings) {
+        let body = objects
+            .iter()
+            .filter(|o| o.object_type != "ring" && (o.translation - target).length() <= o.radius * o.scale * 2.0)
+            .min_by(|a, b| (a.translation - target).length().total_cmp(&(b.translation - target).length()));
+        let name = self.get(slot).map_or_else(|| format!("bookmark {}", slot), |b| b.name.clone());
+        let bookmark = Bookmark {
+            slot,
+            name,
+            eye: [eye.x, eye.y, eye.z],
+            target: [target.x, target.y, target.z],
+            body: body.map(|b| b.name.clone()),
+            body_position: body.map_or([0.0; 3], |b| [b.translation.x, b.translation.y, b.translation.z]),
+            view,
+        };
+        self.bookmarks.retain(|b| b.slot != slot);
+        self.bookmarks.push(bookmark);
+        self.bookmarks.sort_by_key(|b| b.slot);
+    }
+
+    pub fn get(&self, slot: u8) -> Option<&Bookmark> {
+        self.bookmarks.iter().find(|b| b.slot == slot)
+    }
+
+    pub fn rename(&mut self, slot: u8, name: &str) -> Result<(), String> {
+        let bookmark = self.bookmarks.iter_mut().find(|b| b.slot == slot).ok_or_else(|| format!("no bookmark in slot {}", slot))?;
+        bookmark.name = name.to_string();
+        Ok(())
+    }
+
+    pub fn delete(&mut self, slot: u8) -> Result<(), String> {
+        let count = self.bookmarks.len();
+        self.bookmarks.retain(|b| b.slot != slot);
+        if self.bookmarks.len() == count {
+            return Err(format!("no bookmark in slot {}", slot));
+        }
+        Ok(())
+    }
+
+    /// One line per bookmark for the console.
+    pub fn list(&self) -> String {
+        if self.bookmarks.is_empty() {
+            return "no bookmarks (Ctrl+1..9 stores one)".to_string();
+        }
+        self.bookmarks
+            .iter()
+            .map(|b| match &b.body {
+                Some(body) => format!("{}: {} (at {})", b.slot, b.name, body),
+                None => format!("{}: {}", b.slot, b.name),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Overlay line while bookmark mode is on.
+    pub fn status(&self) -> Option<String> {
+        self.mode.then(|| {
+            let names: Vec<String> = self.bookmarks.iter().map(|b| format!("{} {}", b.slot, b.name)).collect();
+            format!("Bookmarks (B to leave): {}", if names.is_empty() { "none yet".to_string() } else { names.join("  ") })
+        })
+    }
+}
+
+/// Where the bookmarks of a scene are kept: `default.toml` uses `default.bookmarks.toml`.
+pub fn bookmarks_path(scene_path: &str) -> String {
+    sidecar_path(scene_path, "bookmarks")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("solar_system_{}.bookmarks.toml", name));
+        let _ = std::fs::remove_file(&path);
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn a_missing_body_falls_back_to_the_stored_pose() {
+        let mut bookmarks = Bookmarks::load(scratch_path("fallback"));
+        let mars = SceneObject::test_sphere("mars", "rocky1", Vector3::new(10.0, 0.0, 0.0), 1.0);
+        let eye = Vector3::new(10.0, 0.0, 5.0);
+        bookmarks.store(1, eye, mars.translation, std::slice::from_ref(&mars), ViewSettings::default());
+        let bookmark = bookmarks.get(1).unwrap();
+        assert_eq!(bookmark.body.as_deref(), Some("mars"));
+
+        // Following the body as it moves
+        let moved = SceneObject { translation: Vector3::new(0.0, 0.0, 10.0), ..mars };
+        assert_eq!(bookmark.pose(&[moved]), (Vector3::new(0.0, 0.0, 15.0), Vector3::new(0.0, 0.0, 10.0)));
+        // and where it was stored once the body is gone
+        assert_eq!(bookmark.pose(&[]), (eye, Vector3::new(10.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn renames_and_deletes_are_saved() {
+        let path = scratch_path("persist");
+        let mut bookmarks = Bookmarks::load(path.clone());
+        for slot in [1, 2] {
+            bookmarks.store(slot, Vector3::new(0.0, 0.0, slot as f32), Vector3::zero(), &[], ViewSettings::default());
+        }
+        bookmarks.rename(1, "overview").unwrap();
+        bookmarks.delete(2).unwrap();
+        assert!(bookmarks.delete(2).is_err());
+        bookmarks.save().unwrap();
+
+        let reloaded = Bookmarks::load(path.clone());
+        assert_eq!(reloaded.get(1).map(|b| b.name.as_str()), Some("overview"));
+        assert!(reloaded.get(2).is_none());
+        assert!(!std::path::Path::new(&format!("{}.tmp", path)).exists());
+        let _ = std::fs::remove_file(&path);
+    }
+}
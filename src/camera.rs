@@ -1,9 +1,21 @@
 #![allow(dead_code)]
 
 use raylib::prelude::*;
+use crate::input::InputState;
 use crate::matrix::create_view_matrix;
 use std::f32::consts::PI;
 
+/// Animated move of the orbit center from one point to another, optionally also
+/// swinging the camera around it to a new yaw, pitch and distance.
+pub struct Warp {
+    pub from: Vector3,
+    pub to: Vector3,
+    pub from_orbit: Vector3, // Yaw, pitch and distance at the start
+    pub to_orbit: Vector3,   // ... and at the end
+    pub elapsed: f32,
+    pub duration: f32,
+}
+
 pub struct Camera {
     // Camera position/orientation
     pub eye: Vector3,        // Camera position
@@ -19,6 +31,10 @@ pub struct Camera {
     pub rotation_speed: f32,
     pub zoom_speed: f32,
     pub pan_speed: f32,
+    pub min_distance: f32, // Closest the zoom gets to the target
+    pub fine_zoom: bool,   // Zoom by a share of the distance instead of `zoom_speed`, for bodies far smaller than a step
+
+    pub warp: Option<Warp>,
 }
 
 impl Camera {
@@ -44,6 +60,9 @@ impl Camera {
             rotation_speed: 0.05,
             zoom_speed: 0.5,
             pan_speed: 0.1,
+            min_distance: 0.5,
+            fine_zoom: false,
+            warp: None,
         }
     }
 
@@ -61,43 +80,107 @@ impl Camera {
         self.eye.z = self.target.z + self.distance * self.pitch.cos() * self.yaw.sin();
     }
 
+    /// Recompute yaw, pitch and distance after eye/target were set directly
+    pub fn sync_orbit(&mut self) {
+        let direction = self.eye - self.target;
+        self.distance = direction.length().max(0.001);
+        self.pitch = (direction.y / self.distance).clamp(-1.0, 1.0).asin();
+        self.yaw = direction.z.atan2(direction.x);
+    }
+
+    /// Move the orbit center to a new point, keeping yaw, pitch and distance
+    pub fn set_target(&mut self, target: Vector3) {
+        self.target = target;
+        self.update_eye_position();
+    }
+
+    /// Starts flying the orbit center to `target`; longer trips take a little longer.
+    pub fn warp_to(&mut self, target: Vector3) {
+        let orbit = Vector3::new(self.yaw, self.pitch, self.distance);
+        self.start_warp(target, orbit);
+    }
+
+    /// Starts flying to look at `target` from `eye`.
+    pub fn warp_to_pose(&mut self, eye: Vector3, target: Vector3) {
+        let direction = eye - target;
+        let distance = direction.length().max(0.001);
+        let pitch = (direction.y / distance).clamp(-1.0, 1.0).asin();
+        // Turn the short way round
+        let yaw = self.yaw + (direction.z.atan2(direction.x) - self.yaw + PI).rem_euclid(2.0 * PI) - PI;
+        self.start_warp(target, Vector3::new(yaw, pitch, distance));
+    }
+
+    fn start_warp(&mut self, target: Vector3, orbit: Vector3) {
+        let distance = (target - self.target).length();
+        self.warp = Some(Warp {
+            from: self.target,
+            to: target,
+            from_orbit: Vector3::new(self.yaw, self.pitch, self.distance),
+            to_orbit: orbit,
+            elapsed: 0.0,
+            duration: (0.6 + distance / 40.0).min(2.5),
+        });
+    }
+
+    /// Advances an active warp with ease-in/ease-out and returns its current speed as a
+    /// 0..1 intensity (0 when no warp is running or it just finished).
+    pub fn update_warp(&mut self, dt: f32) -> f32 {
+        let Some(warp) = &mut self.warp else {
+            return 0.0;
+        };
+        warp.elapsed += dt;
+        let t = (warp.elapsed / warp.duration).min(1.0);
+        let eased = t * t * (3.0 - 2.0 * t);
+        let target = warp.from + (warp.to - warp.from) * eased;
+        let orbit = warp.from_orbit + (warp.to_orbit - warp.from_orbit) * eased;
+        let done = t >= 1.0;
+        (self.yaw, self.pitch, self.distance) = (orbit.x, orbit.y, orbit.z);
+        self.set_target(target);
+        if done {
+            self.warp = None;
+            return 0.0;
+        }
+        (t * PI).sin()
+    }
+
     /// Get the view matrix for this camera
     pub fn get_view_matrix(&self) -> Matrix {
         create_view_matrix(self.eye, self.target, self.up)
     }
 
     /// Process keyboard input to control the camera
-    pub fn process_input(&mut self, window: &RaylibHandle) {
+    pub fn process_input(&mut self, input: &InputState) {
         // Rotation controls (yaw)
-        if window.is_key_down(KeyboardKey::KEY_A) {
+        if input.is_key_down(KeyboardKey::KEY_A) {
             self.yaw += self.rotation_speed;
             self.update_eye_position();
         }
-        if window.is_key_down(KeyboardKey::KEY_D) {
+        if input.is_key_down(KeyboardKey::KEY_D) {
             self.yaw -= self.rotation_speed;
             self.update_eye_position();
         }
 
         // Rotation controls (pitch)
-        if window.is_key_down(KeyboardKey::KEY_W) {
+        if input.is_key_down(KeyboardKey::KEY_W) {
             self.pitch += self.rotation_speed;
             self.update_eye_position();
         }
-        if window.is_key_down(KeyboardKey::KEY_S) {
+        if input.is_key_down(KeyboardKey::KEY_S) {
             self.pitch -= self.rotation_speed;
             self.update_eye_position();
         }
 
         // Zoom controls (distance from target) - arrow keys
-        if window.is_key_down(KeyboardKey::KEY_UP) {
-            self.distance -= self.zoom_speed;
-            if self.distance < 0.5 {
-                self.distance = 0.5; // Prevent camera from going too close
+        let zoom_step = if self.fine_zoom { self.distance * 0.05 } else { self.zoom_speed };
+        if input.is_key_down(KeyboardKey::KEY_UP) {
+            self.distance -= zoom_step;
+            if self.distance < self.min_distance {
+                self.distance = self.min_distance; // Prevent camera from going too close
             }
             self.update_eye_position();
         }
-        if window.is_key_down(KeyboardKey::KEY_DOWN) {
-            self.distance += self.zoom_speed;
+        if input.is_key_down(KeyboardKey::KEY_DOWN) {
+            self.distance += zoom_step;
             self.update_eye_position();
         }
 
@@ -122,35 +205,35 @@ impl Camera {
         );
 
         // Q/E keys for horizontal panning
-        if window.is_key_down(KeyboardKey::KEY_Q) {
+        if input.is_key_down(KeyboardKey::KEY_Q) {
             self.target.x -= right.x * self.pan_speed;
             self.target.z -= right.z * self.pan_speed;
             self.update_eye_position();
         }
-        if window.is_key_down(KeyboardKey::KEY_E) {
+        if input.is_key_down(KeyboardKey::KEY_E) {
             self.target.x += right.x * self.pan_speed;
             self.target.z += right.z * self.pan_speed;
             self.update_eye_position();
         }
 
         // Left/Right arrow keys for horizontal panning
-        if window.is_key_down(KeyboardKey::KEY_LEFT) {
+        if input.is_key_down(KeyboardKey::KEY_LEFT) {
             self.target.x -= right.x * self.pan_speed;
             self.target.z -= right.z * self.pan_speed;
             self.update_eye_position();
         }
-        if window.is_key_down(KeyboardKey::KEY_RIGHT) {
+        if input.is_key_down(KeyboardKey::KEY_RIGHT) {
             self.target.x += right.x * self.pan_speed;
             self.target.z += right.z * self.pan_speed;
             self.update_eye_position();
         }
 
         // Vertical panning
-        if window.is_key_down(KeyboardKey::KEY_R) {
+        if input.is_key_down(KeyboardKey::KEY_R) {
             self.target.y += self.pan_speed;
             self.update_eye_position();
         }
-        if window.is_key_down(KeyboardKey::KEY_F) {
+        if input.is_key_down(KeyboardKey::KEY_F) {
             self.target.y -= self.pan_speed;
             self.update_eye_position();
         }
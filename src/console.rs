@@ -0,0 +1,518 @@
+#![allow(dead_code)]
+
+use raylib::prelude::*;
+
+use crate::bookmarks::Bookmarks;
+use crate::camera::Camera;
+use crate::input::InputState;
+use crate::noise::{sub_seed, Noise};
+use crate::pipeline::Pipeline;
+use crate::params::ParamSet;
+use crate::ramp::RampSet;
+use crate::repro::Pose;
+use crate::scene::{bounding_radius, check_scale, MeshLibrary, RenderFlags, RingConfig, SceneFile, SceneObject};
+use crate::texture::MaterialConfig;
+use crate::validate::validate;
+
+const MAX_LOG_LINES: usize = 12;
+
+/// Shader kinds that can be spawned, plus a few friendlier aliases.
+const KINDS: [&str; 10] = [
+    "rocky1", "rocky2", "gassy1", "gassy2", "gassy3", "earth", "moon", "ring", "sun", "shuttle",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Spawn { kind: String, position: Vector3, scale: f32, name: Option<String> },
+    Remove { name: String },
+    Set { name: String, property: String, value: f32 },
+    SetFlag { name: String, flag: String, value: bool },
+    Teleport { subject: String, target: String },
+    Save { path: String },
+    Threads { count: usize },
+    Bookmarks,
+    RenameBookmark { slot: u8, name: String },
+    DeleteBookmark { slot: u8 },
+    List,
+    Validate,
+    Pose(Option<Pose>), // Prints the current pose line, or restores the one given
+    Help,
+}
+
+/// Debug console state: the line being typed and the scrollback log.
+pub struct Console {
+    pub open: bool,
+    pub input: String,
+    pub log: Vec<String>,
+}
+
+impl Console {
+    pub fn new() -> Self {
+        Console {
+            open: false,
+            input: String::new(),
+            log: Vec::new(),
+        }
+    }
+
+    pub fn print(&mut self, line: impl Into<String>) {
+        self.log.push(line.into());
+        if self.log.len() > MAX_LOG_LINES {
+            let extra = self.log.len() - MAX_LOG_LINES;
+            self.log.drain(0..extra);
+        }
+    }
+
+    /// Reads keyboard input for this frame. Returns a submitted line when Enter is pressed.
+    pub fn process_input(&mut self, input: &InputState) -> Option<String> {
+        if input.is_key_pressed(KeyboardKey::KEY_GRAVE) {
+            self.open = !self.open;
+        }
+        if !self.open {
+            return None;
+        }
+
+        for &c in input.chars() {
+            if c != '`' && c != '~' {
+                self.input.push(c);
+            }
+        }
+        if input.is_key_pressed(KeyboardKey::KEY_BACKSPACE) {
+            self.input.pop();
+        }
+        if input.is_key_pressed(KeyboardKey::KEY_ENTER) {
+            let line = std::mem::take(&mut self.input);
+            self.print(format!("> {}", line));
+            return Some(line);
+        }
+        None
+    }
+
+    pub fn draw(&self, d: &mut RaylibDrawHandle, width: i32) {
+        if !self.open {
+            return;
+        }
+        let line_height = 18;
+        let height = (self.log.len() as i32 + 1) * line_height + 10;
+        d.draw_rectangle(0, 0, width, height, Color::new(10, 10, 20, 200));
+        for (i, line) in self.log.iter().enumerate() {
+            d.draw_text(line, 8, 5 + i as i32 * line_height, 16, Color::LIGHTGRAY);
+        }
+        let prompt = format!("> {}_", self.input);
+        d.draw_text(&prompt, 8, 5 + self.log.len() as i32 * line_height, 16, Color::WHITE);
+    }
+}
+
+/// Splits a command line into tokens. Double quotes group words with spaces.
+pub fn tokenize(line: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+
+    for c in line.chars() {
+        match c {
+            '"' => {
+                // Closing quote always ends a token (even an empty one);
+                // an opening quote ends any word glued to it
+                if in_quotes || !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+                in_quotes = !in_quotes;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err("unterminated quote".to_string());
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    Ok(tokens)
+}
+
+fn parse_number(token: Option<&String>, what: &str) -> Result<f32, String> {
+    let token = token.ok_or_else(|| format!("missing {}", what))?;
+    token
+        .parse::<f32>()
+        .ok()
+        .filter(|v| v.is_finite())
+        .ok_or_else(|| format!("expected a number for {}, got '{}'", what, token))
+}
+
+fn parse_bool(token: &str) -> Result<bool, String> {
+    match token.to_lowercase().as_str() {
+        "on" | "true" | "1" => Ok(true),
+        "off" | "false" | "0" => Ok(false),
+        _ => Err(format!("expected on/off, got '{}'", token)),
+    }
+}
+
+/// Maps user-facing kind names (and aliases) onto the shader kinds used by `render`.
+pub fn resolve_kind(kind: &str) -> Option<&'static str> {
+    let kind = kind.to_lowercase();
+    let alias = match kind.as_str() {
+        "rocky" => "rocky1",
+        "lava" => "rocky2",
+        "gas" | "gassy" => "gassy1",
+        "uranus" => "gassy2",
+        other => other,
+    };
+    KINDS.iter().copied().find(|k| *k == alias)
+}
+
+/// Mesh used for each shader kind.
+pub fn mesh_for_kind(kind: &str) -> &'static str {
+    match kind {
+        "ring" => "assets/objects/ring.obj",
+        "sun" => "assets/objects/sun.obj",
+        "shuttle" => "assets/objects/SpaceShuttle.obj",
+        _ => "assets/objects/sphere.obj",
+    }
+}
+
+pub fn parse_command(line: &str) -> Result<Command, String> {
+    let tokens = tokenize(line)?;
+    let Some(verb) = tokens.first() else {
+        return Err("empty command".to_string());
+    };
+
+    match verb.to_lowercase().as_str() {
+        "spawn" => {
+            let kind = tokens.get(1).ok_or("usage: spawn <kind> <x> <y> <z> [scale <s>] [name <n>]")?;
+            let kind = resolve_kind(kind).ok_or_else(|| format!("unknown kind '{}'", kind))?;
+            let x = parse_number(tokens.get(2), "x")?;
+            let y = parse_number(tokens.get(3), "y")?;
+            let z = parse_number(tokens.get(4), "z")?;
+
+            let mut scale = 1.0;
+            let mut name = None;
+            let mut rest = tokens[5.min(tokens.len())..].iter();
+            while let Some(option) = rest.next() {
+                match option.to_lowercase().as_str() {
+                    "scale" => {
+                        scale = check_scale(parse_number(rest.next(), "scale")?)
+                            .inspect_err(|error| tracing::warn!("spawn {}: {}", kind, error))?;
+                    }
+                    "name" => {
+                        name = Some(rest.next().ok_or("missing name")?.clone());
+                    }
+                    other => return Err(format!("unknown spawn option '{}'", other)),
+                }
+            }
+
+            Ok(Command::Spawn {
+                kind: kind.to_string(),
+                position: Vector3::new(x, y, z),
+                scale,
+                name,
+            })
+        }
+        "remove" | "rm" => {
+            let name = tokens.get(1).ok_or("usage: remove <name>")?;
+            Ok(Command::Remove { name: name.clone() })
+        }
+        "set" => {
+            if tokens.len() != 4 {
+                return Err("usage: set <name> <spin|scale|x|y|z|flag> <value>".to_string());
+            }
+            let property = tokens[2].to_lowercase();
+            if RenderFlags::NAMES.contains(&property.as_str()) {
+                let value = parse_bool(&tokens[3])?;
+                return Ok(Command::SetFlag { name: tokens[1].clone(), flag: property, value });
+            }
+            if !["spin", "scale", "x", "y", "z"].contains(&property.as_str()) {
+                return Err(format!("unknown property '{}'", tokens[2]));
+            }
+            let value = parse_number(tokens.get(3), &property)?;
+            Ok(Command::Set { name: tokens[1].clone(), property, value })
+        }
+        "tp" => {
+            if tokens.len() != 3 {
+                return Err("usage: tp camera <name>".to_string());
+            }
+            Ok(Command::Teleport { subject: tokens[1].to_lowercase(), target: tokens[2].clone() })
+        }
+        "save" => {
+            if tokens.len() != 2 {
+                return Err("usage: save <path>".to_string());
+            }
+            Ok(Command::Save { path: tokens[1].clone() })
+        }
+        "threads" => {
+            let count = tokens.get(1).ok_or("usage: threads <n>")?;
+            match count.parse::<usize>() {
+                Ok(count) if count > 0 => Ok(Command::Threads { count }),
+                _ => Err(format!("invalid thread count '{}'", count)),
+            }
+        }
+        "bookmarks" => Ok(Command::Bookmarks),
+        "bookmark" => {
+            let usage = "usage: bookmark rename <1-9> <name> | bookmark delete <1-9>";
+            let slot = match tokens.get(2).map(|s| s.parse::<u8>()) {
+                Some(Ok(slot)) if (1..=9).contains(&slot) => slot,
+                _ => return Err(usage.to_string()),
+            };
+            match (tokens.get(1).map(|s| s.to_lowercase()).as_deref(), tokens.len()) {
+                (Some("rename"), 4) => Ok(Command::RenameBookmark { slot, name: tokens[3].clone() }),
+                (Some("delete"), 3) => Ok(Command::DeleteBookmark { slot }),
+                _ => Err(usage.to_string()),
+            }
+        }
+        "list" | "ls" => Ok(Command::List),
+        "validate" => Ok(Command::Validate),
+        "pose" => match tokens.len() {
+            1 => Ok(Command::Pose(None)),
+            2 => Ok(Command::Pose(Some(Pose::parse(&tokens[1])?))),
+            _ => Err("usage: pose [line]".to_string()),
+        },
+        "help" => Ok(Command::Help),
+        other => Err(format!("unknown command '{}'", other)),
+    }
+}
+
+fn find_index(objects: &[SceneObject], name: &str) -> Result<usize, String> {
+    objects
+        .iter()
+        .position(|o| o.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| format!("no body named '{}'", name))
+}
+
+fn unique_name(objects: &[SceneObject], base: &str) -> String {
+    let mut n = 1;
+    loop {
+        let candidate = format!("{}_{}", base, n);
+        if !objects.iter().any(|o| o.name.eq_ignore_ascii_case(&candidate)) {
+            return candidate;
+        }
+        n += 1;
+    }
+}
+
+/// Runs a parsed command against the live scene. Returns the message to print.
+pub fn execute(
+    command: Command,
+    objects: &mut Vec<SceneObject>,
+    camera: &mut Camera,
+    meshes: &mut MeshLibrary,
+    scene: &SceneFile,
+    pipeline: &mut Pipeline,
+    bookmarks: &mut Bookmarks,
+) -> Result<String, String> {
+    match command {
+        Command::Spawn { kind, position, scale, name } => {
+            let name = match name {
+                Some(name) if objects.iter().any(|o| o.name.eq_ignore_ascii_case(&name)) => {
+                    return Err(format!("a body named '{}' already exists", name));
+                }
+                Some(name) => name,
+                None => unique_name(objects, &kind),
+            };
+            let mesh = mesh_for_kind(&kind);
+            let vertices = meshes.get(mesh)?.clone();
+            let spin = if kind == "shuttle" { 0.0 } else { 1.2 };
+
+            objects.push(SceneObject {
+                name: name.clone(),
+                radius: bounding_radius(&vertices),
+                vertices,
+                mesh: mesh.to_string(),
+                object_type: kind.clone(),
+                parent: None,
+                translation: position,
+                rotation: Vector3::new(0.0, 0.0, 0.0),
+                scale,
+                spin,
+                mass: None,
+                axial_tilt: 0.0,
+                precession: 0.0,
+                orbit: None,
+                sphere: mesh.ends_with("sphere.obj") || mesh.ends_with("sun.obj"),
+                ring: (kind == "ring").then(RingConfig::default),
+                ring_profile: None,
+                material: MaterialConfig::default(),
+                flags: RenderFlags::default(),
+                noise: Noise::new(sub_seed(scene.seed, &name)),
+                planet: None,
+                params: ParamSet::default(),
+                ramps: RampSet::default(),
+                offset: position,
+                barycenter: position,
+                spin_angle: 0.0,
+                precession_angle: 0.0,
+                mean_anomaly: 0.0,
+            });
+            Ok(format!("spawned {} ({})", name, kind))
+        }
+        Command::Remove { name } => {
+            let index = find_index(objects, &name)?;
+            let removed = objects.remove(index);
+            Ok(format!("removed {}", removed.name))
+        }
+        Command::Set { name, property, value } => {
+            let index = find_index(objects, &name)?;
+            let obj = &mut objects[index];
+            match property.as_str() {
+                "spin" => obj.spin = value,
+                "scale" => obj.scale = check_scale(value).inspect_err(|error| tracing::warn!("set {} scale: {}", obj.name, error))?,
+                "x" => obj.offset.x = value,
+                "y" => obj.offset.y = value,
+                "z" => obj.offset.z = value,
+                _ => return Err(format!("unknown property '{}'", property)),
+            }
+            Ok(format!("{}.{} = {}", obj.name, property, value))
+        }
+        Command::SetFlag { name, flag, value } => {
+            let index = find_index(objects, &name)?;
+            let obj = &mut objects[index];
+            obj.flags.set(&flag, value)?;
+            Ok(format!("{}.{} = {}", obj.name, flag, if value { "on" } else { "off" }))
+        }
+        Command::Teleport { subject, target } => {
+            if subject != "camera" {
+                return Err(format!("can only teleport the camera, not '{}'", subject));
+            }
+            let index = find_index(objects, &target)?;
+            let obj = &objects[index];
+            camera.warp_to(obj.translation);
+            Ok(format!("camera warping to {}", obj.name))
+        }
+        Command::Save { path } => {
+            scene.with_objects(objects).save(&path)?;
+            Ok(format!("saved {} bodies to {}", objects.len(), path))
+        }
+        Command::Threads { count } => {
+            pipeline.set_thread_count(count)?;
+            Ok(format!("rendering on {} thread(s)", count))
+        }
+        Command::Bookmarks => Ok(bookmarks.list()),
+        Command::RenameBookmark { slot, name } => {
+            bookmarks.rename(slot, &name)?;
+            bookmarks.save()?;
+            Ok(format!("bookmark {} is now '{}'", slot, name))
+        }
+        Command::DeleteBookmark { slot } => {
+            bookmarks.delete(slot)?;
+            bookmarks.save()?;
+            Ok(format!("deleted bookmark {}", slot))
+        }
+        Command::List => {
+            let names: Vec<String> = objects
+                .iter()
+                .map(|o| format!("{} ({})", o.name, o.object_type))
+                .collect();
+            Ok(names.join(", "))
+        }
+        Command::Validate => {
+            let warnings = validate(objects, scene.depth_range.farthest());
+            if warnings.is_empty() {
+                return Ok("no problems found".to_string());
+            }
+            let lines: Vec<String> = warnings.iter().map(|w| format!("warning: {}", w)).collect();
+            Ok(lines.join("\n"))
+        }
+        // The clock and the field of view live in the main loop, which runs these itself
+        Command::Pose(_) => Err("pose isn't available here".to_string()),
+        Command::Help => Ok(
+            "spawn <kind> <x> <y> <z> [scale s] [name n] | remove <name> | set <name> <spin|scale|x|y|z> <v> | set <name> <flag> on|off | tp camera <name> | save <path> | threads <n> | bookmarks | bookmark rename|delete <1-9> [name] | list | validate | pose [line]"
+                .to_string(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenize_splits_on_whitespace() {
+        let tokens = tokenize("  spawn rocky   20 0 -10 ").unwrap();
+        assert_eq!(tokens, vec!["spawn", "rocky", "20", "0", "-10"]);
+    }
+
+    #[test]
+    fn tokenize_keeps_quoted_words_together() {
+        let tokens = tokenize("remove \"big moon\"").unwrap();
+        assert_eq!(tokens, vec!["remove", "big moon"]);
+        assert!(tokenize("remove \"big moon").is_err());
+    }
+
+    #[test]
+    fn parses_spawn_with_scale() {
+        let command = parse_command("spawn rocky 20 0 -10 scale 1.5").unwrap();
+        assert_eq!(
+            command,
+            Command::Spawn {
+                kind: "rocky1".to_string(),
+                position: Vector3::new(20.0, 0.0, -10.0),
+                scale: 1.5,
+                name: None,
+            }
+        );
+    }
+
+    #[test]
+    fn parses_spawn_with_name() {
+        let command = parse_command("spawn gassy3 0 0 0 name giant").unwrap();
+        match command {
+            Command::Spawn { kind, name, scale, .. } => {
+                assert_eq!(kind, "gassy3");
+                assert_eq!(name.as_deref(), Some("giant"));
+                assert_eq!(scale, 1.0);
+            }
+            other => panic!("unexpected command {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejects_bad_spawn_arguments() {
+        assert!(parse_command("spawn rocky 20 0").is_err());
+        assert!(parse_command("spawn rocky 20 zero -10").is_err());
+        assert!(parse_command("spawn comet 0 0 0").is_err());
+        assert!(parse_command("spawn rocky 0 0 0 scale -1").is_err());
+        assert!(parse_command("spawn rocky 0 0 0 scale 0").is_err());
+        assert!(parse_command("spawn rocky 0 0 0 scale nan").is_err());
+        assert!(parse_command("spawn rocky 0 0 0 scale 1e9").is_err());
+        assert!(parse_command("spawn rocky 0 0 0 colour red").is_err());
+    }
+
+    #[test]
+    fn parses_set_remove_tp_and_list() {
+        assert_eq!(
+            parse_command("set earth spin 0.1").unwrap(),
+            Command::Set { name: "earth".to_string(), property: "spin".to_string(), value: 0.1 }
+        );
+        assert_eq!(parse_command("remove earth").unwrap(), Command::Remove { name: "earth".to_string() });
+        assert_eq!(
+            parse_command("tp camera moon").unwrap(),
+            Command::Teleport { subject: "camera".to_string(), target: "moon".to_string() }
+        );
+        assert_eq!(parse_command("LIST").unwrap(), Command::List);
+        assert_eq!(parse_command("validate").unwrap(), Command::Validate);
+        assert_eq!(parse_command("save scene.toml").unwrap(), Command::Save { path: "scene.toml".to_string() });
+        assert_eq!(parse_command("threads 2").unwrap(), Command::Threads { count: 2 });
+        assert_eq!(
+            parse_command("bookmark rename 3 \"eclipse shot\"").unwrap(),
+            Command::RenameBookmark { slot: 3, name: "eclipse shot".to_string() }
+        );
+        assert_eq!(parse_command("bookmark delete 9").unwrap(), Command::DeleteBookmark { slot: 9 });
+        assert_eq!(parse_command("pose").unwrap(), Command::Pose(None));
+    }
+
+    #[test]
+    fn rejects_unknown_commands_and_properties() {
+        assert!(parse_command("").is_err());
+        assert!(parse_command("explode sun").is_err());
+        assert!(parse_command("set earth colour 1").is_err());
+        assert!(parse_command("set earth spin fast").is_err());
+        assert!(parse_command("threads 0").is_err());
+        assert!(parse_command("bookmark delete 0").is_err());
+        assert!(parse_command("pose v9,t=1").is_err());
+    }
+}
@@ -0,0 +1,194 @@
+#![allow(dead_code)]
+
+use raylib::prelude::*;
+
+use crate::scene::SceneObject;
+
+/// Occluder discs are shrunk by this much, since a tessellated sphere doesn't quite
+/// fill its bounding sphere's silhouette.
+const OCCLUDER_MARGIN: f32 = 0.95;
+
+/// Bounding sphere of a body as seen from the eye: direction to its center, distance,
+/// and angular radius of its silhouette.
+#[derive(Clone, Copy, Debug)]
+struct ViewCone {
+    direction: Vector3,
+    distance: f32,
+    radius: f32,
+    angle: f32,
+}
+
+impl ViewCone {
+    fn new(center: Vector3, radius: f32, eye: Vector3) -> Option<Self> {
+        let offset = center - eye;
+        let distance = offset.length();
+        if distance <= radius {
+            return None; // Eye inside the sphere
+        }
+        Some(ViewCone { direction: offset / distance, distance, radius, angle: (radius / distance).asin() })
+    }
+
+    /// Whether `other` is hidden: its silhouette lies inside this one's and all of it
+    /// is farther than this sphere's center, so past the circle where the view grazes it.
+    fn hides(&self, other: &ViewCone) -> bool {
+        if other.distance - other.radius <= self.distance {
+            return false;
+        }
+        let separation = self.direction.dot(other.direction).clamp(-1.0, 1.0).acos();
+        separation + other.angle <= self.angle * OCCLUDER_MARGIN
+    }
+}
+
+/// Indices of `objects` in drawing order: nearest first so depth testing rejects more
+/// of what follows, with bodies that don't write depth (translucent ones) last. Which
+/// body shows is always up to the depth test; this order only affects speed and the
+/// antialiased edges, and never depends on the order of the scene list.
+pub fn draw_order(objects: &[SceneObject], eye: Vector3) -> Vec<usize> {
+    let key = |o: &SceneObject| ((o.translation - eye).length() - o.radius * o.scale).max(0.0);
+    let mut order: Vec<usize> = (0..objects.len()).collect();
+    order.sort_by(|&a, &b| {
+        let (a, b) = (&objects[a], &objects[b]);
+        (!a.flags.depth_write)
+            .cmp(&!b.flags.depth_write)
+            .then(key(a).partial_cmp(&key(b)).unwrap_or(std::cmp::Ordering::Equal))
+    });
+    order
+}
+
+/// Conservative occlusion test against bodies already drawn this frame. Only opaque,
+/// depth-tested sphere bodies occlude, since their silhouette is a full disc.
+pub struct OcclusionCuller {
+    eye: Vector3,
+    occluders: Vec<ViewCone>,
+}
+
+impl OcclusionCuller {
+    pub fn new(eye: Vector3) -> Self {
+        OcclusionCuller { eye, occluders: Vec::new() }
+    }
+
+    /// True if `obj` is entirely behind a body drawn before it. Objects that ignore the
+    /// depth buffer are never culled.
+    pub fn is_hidden(&self, obj: &SceneObject) -> bool {
+        if !obj.flags.depth_test {
+            return false;
+        }
+        ViewCone::new(obj.translation, obj.radius * obj.scale, self.eye)
+            .is_some_and(|cone| self.occluders.iter().any(|o| o.hides(&cone)))
+    }
+
+    /// Records `obj` as drawn, so it can hide the bodies after it.
+    pub fn add(&mut self, obj: &SceneObject) {
+        if !(obj.sphere && obj.flags.depth_write && obj.flags.depth_test) {
+            return;
+        }
+        if let Some(cone) = ViewCone::new(obj.translation, obj.radius * obj.scale, self.eye) {
+            self.occluders.push(cone);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    use crate::depthrange::DepthMode;
+    use crate::framebuffer::{DepthTest, PixelTarget};
+    use crate::matrix::{create_model_matrix, create_projection_matrix, create_view_matrix, create_viewport_matrix};
+    use crate::sphere::{project_sphere, render_sphere};
+    use crate::{FrameUniforms, ObjectUniforms, Uniforms};
+
+    const SIZE: u32 = 64;
+
+    /// Color and depth buffer that blends and depth-tests like the framebuffer.
+    struct DepthTarget {
+        colors: Vec<Vector3>,
+        depth: Vec<f32>,
+    }
+
+    impl PixelTarget for DepthTarget {
+        fn size(&self) -> (u32, u32) {
+            (SIZE, SIZE)
+        }
+
+        fn blend_point(&mut self, x: i32, y: i32, color: Vector3, alpha: f32, depth: f32) -> bool {
+            if x < 0 || y < 0 || x >= SIZE as i32 || y >= SIZE as i32 || alpha <= 0.0 {
+                return false;
+            }
+            let index = (y as u32 * SIZE + x as u32) as usize;
+            let Some(write) = DepthTest::new(DepthMode::Classic).check(depth, self.depth[index], alpha) else {
+                return false;
+            };
+            if write {
+                self.depth[index] = depth;
+            }
+            self.colors[index] = color * alpha + self.colors[index] * (1.0 - alpha);
+            true
+        }
+    }
+
+    fn body(name: &str, kind: &str, position: Vector3, radius: f32) -> SceneObject {
+        SceneObject::test_sphere(name, kind, position, radius)
+    }
+
+    /// Ray-casts the bodies at `order`, the way the main loop draws small spheres.
+    fn render(objects: &[SceneObject], order: &[usize], eye: Vector3) -> DepthTarget {
+        let view = create_view_matrix(eye, Vector3::zero(), Vector3::new(0.0, 1.0, 0.0));
+        let projection = create_projection_matrix(PI / 3.0, 1.0, 0.1, 100.0);
+        let viewport = create_viewport_matrix(0.0, 0.0, SIZE as f32, SIZE as f32);
+        let frame = FrameUniforms { light_position: eye, ambient: 0.1, ..FrameUniforms::new(view, projection, viewport, eye) };
+
+        let mut target = DepthTarget { colors: vec![Vector3::zero(); (SIZE * SIZE) as usize], depth: vec![f32::INFINITY; (SIZE * SIZE) as usize] };
+        for &index in order {
+            let obj = &objects[index];
+            let model = create_model_matrix(obj.translation, obj.scale, obj.rotation);
+            let mut uniforms = Uniforms {
+                frame,
+                object: ObjectUniforms { irradiance: Vector3::one(), noise: obj.noise, ..ObjectUniforms::new(model, &frame) },
+            };
+            let sphere = project_sphere(obj.translation, obj.radius, &uniforms).expect("sphere in front of the camera");
+            uniforms.object.screen_center = sphere.center_px;
+            uniforms.object.screen_radius = sphere.radius_px;
+            render_sphere(&mut target, &uniforms, &sphere, &obj.object_type);
+        }
+        target
+    }
+
+    #[test]
+    fn insertion_order_does_not_change_the_image() {
+        // Drawn in both list orders, unsorted, so only the depth test decides what shows
+        let eye = Vector3::new(0.0, 0.0, 10.0);
+        let objects = [body("earth", "earth", Vector3::new(0.0, 0.0, -5.0), 3.0), body("moon", "moon", Vector3::new(0.5, 0.0, 2.0), 1.0)];
+        let earth_alone = render(&objects, &[0], eye);
+        let moon_alone = render(&objects, &[1], eye);
+
+        // Under the moon's center, and on the earth's disc well clear of the moon
+        let on_moon = (SIZE / 2 * SIZE + SIZE / 2 + 3) as usize;
+        let on_earth = (SIZE / 2 * SIZE + SIZE / 2 - 8) as usize;
+        for order in [[0, 1], [1, 0]] {
+            let target = render(&objects, &order, eye);
+            assert_eq!(target.colors[on_moon], moon_alone.colors[on_moon], "drawn in order {:?}", order);
+            assert_eq!(target.depth[on_moon], moon_alone.depth[on_moon], "drawn in order {:?}", order);
+            assert_eq!(target.colors[on_earth], earth_alone.colors[on_earth], "drawn in order {:?}", order);
+        }
+    }
+
+    #[test]
+    fn bodies_are_drawn_front_to_back() {
+        let eye = Vector3::new(0.0, 0.0, 10.0);
+        let mut glow = body("glow", "moon", Vector3::new(0.0, 0.0, 8.0), 0.5);
+        glow.flags.depth_write = false;
+        let objects = [
+            body("far", "moon", Vector3::new(0.0, 0.0, -20.0), 1.0),
+            body("near", "moon", Vector3::new(0.0, 0.0, 5.0), 1.0),
+            body("middle", "moon", Vector3::new(0.0, 0.0, 0.0), 1.0),
+            // Its center is farther than the far body's, but its surface isn't
+            body("giant", "gassy1", Vector3::new(30.0, 0.0, -2.0), 10.0),
+            glow,
+        ];
+        // Surfaces 4, 9, 22.3 and 29 from the eye; the body that doesn't write depth
+        // comes last though it's right by the eye
+        assert_eq!(draw_order(&objects, eye), vec![1, 2, 3, 0, 4]);
+    }
+}
@@ -0,0 +1,130 @@
+#![allow(dead_code)]
+
+use raylib::prelude::*;
+
+use crate::gizmo::{GizmoMode, GIZMO_KEY};
+use crate::input::InputState;
+
+/// Key that steps through "isolate object N" (then back to showing everything).
+pub const ISOLATE_KEY: KeyboardKey = KeyboardKey::KEY_F2;
+/// Key that cycles the shaded image, the object-ID buffer and the overdraw heatmap.
+pub const BUFFER_VIEW_KEY: KeyboardKey = KeyboardKey::KEY_F3;
+/// Key that turns output dithering on and off, to compare banding.
+pub const DITHER_KEY: KeyboardKey = KeyboardKey::KEY_F5;
+/// Key that shades every body with the other shading model (Phong / GGX), for A/B comparison.
+pub const SHADING_KEY: KeyboardKey = KeyboardKey::KEY_F7;
+
+/// What the frame shows in place of the shaded image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BufferView {
+    Shaded,
+    ObjectIds, // One flat color per body, from the object-ID buffer
+    Overdraw,  // How many times each pixel was written, as a heatmap
+}
+
+impl BufferView {
+    pub fn next(self) -> Self {
+        match self {
+            BufferView::Shaded => BufferView::ObjectIds,
+            BufferView::ObjectIds => BufferView::Overdraw,
+            BufferView::Overdraw => BufferView::Shaded,
+        }
+    }
+}
+
+/// Debug-only render switches.
+pub struct DebugView {
+    pub isolate: Option<usize>, // Index into the scene objects; only that one is drawn
+    pub buffer: BufferView,
+    pub dither: bool,
+    pub gizmo: GizmoMode, // Normals gizmo on the isolated object
+    pub swap_shading: bool,
+}
+
+impl DebugView {
+    pub fn new() -> Self {
+        DebugView { isolate: None, buffer: BufferView::Shaded, dither: true, gizmo: GizmoMode::Off, swap_shading: false }
+    }
+
+    pub fn process_input(&mut self, input: &InputState, object_count: usize) {
+        if input.is_key_pressed(ISOLATE_KEY) {
+            self.isolate = match self.isolate {
+                None if object_count > 0 => Some(0),
+                Some(i) if i + 1 < object_count => Some(i + 1),
+                _ => None,
+            };
+        }
+        if input.is_key_pressed(BUFFER_VIEW_KEY) {
+            self.buffer = self.buffer.next();
+        }
+        if input.is_key_pressed(DITHER_KEY) {
+            self.dither = !self.dither;
+        }
+        if input.is_key_pressed(GIZMO_KEY) {
+            self.gizmo = self.gizmo.next();
+        }
+        if input.is_key_pressed(SHADING_KEY) {
+            self.swap_shading = !self.swap_shading;
+        }
+    }
+
+    /// Whether the object at `index` should be drawn this frame.
+    pub fn is_visible(&self, index: usize) -> bool {
+        self.isolate.is_none_or(|i| i == index)
+    }
+}
+
+/// Object id stored in the framebuffer for the scene object at `index` (0 is the background).
+pub fn object_id(index: usize) -> u16 {
+    (index + 1).min(u16::MAX as usize) as u16
+}
+
+/// Distinct, stable color for an object id; black for the background.
+pub fn id_color(id: u16) -> Vector3 {
+    if id == 0 {
+        return Vector3::zero();
+    }
+    // Golden-ratio hue steps keep neighbouring ids far apart
+    let hue = (id as f32 * 0.618_034).fract() * 6.0;
+    let x = 1.0 - ((hue % 2.0) - 1.0).abs();
+    match hue as u32 {
+        0 => Vector3::new(1.0, x, 0.0),
+        1 => Vector3::new(x, 1.0, 0.0),
+        2 => Vector3::new(0.0, 1.0, x),
+        3 => Vector3::new(0.0, x, 1.0),
+        4 => Vector3::new(x, 0.0, 1.0),
+        _ => Vector3::new(1.0, 0.0, x),
+    }
+}
+
+/// Heatmap color for a pixel written `writes` times: black when never written, dark blue
+/// for once, through cyan, green and yellow, to red at 5 or more.
+pub fn heat_color(writes: u16) -> Vector3 {
+    const STEPS: [Vector3; 5] = [
+        Vector3 { x: 0.0, y: 0.05, z: 0.35 },
+        Vector3 { x: 0.0, y: 0.6, z: 0.9 },
+        Vector3 { x: 0.1, y: 0.85, z: 0.2 },
+        Vector3 { x: 1.0, y: 0.9, z: 0.1 },
+        Vector3 { x: 1.0, y: 0.1, z: 0.05 },
+    ];
+    match writes {
+        0 => Vector3::zero(),
+        n => STEPS[(n as usize - 1).min(STEPS.len() - 1)],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heat_runs_from_blue_to_red() {
+        assert_eq!(heat_color(0), Vector3::zero());
+        let once = heat_color(1);
+        assert!(once.z > once.x && once.z > once.y, "one write is blue");
+        let many = heat_color(5);
+        assert!(many.x > 0.9 && many.y < 0.2, "five writes are red");
+        assert_eq!(heat_color(40), many);
+        assert_eq!(BufferView::Shaded.next().next().next(), BufferView::Shaded);
+    }
+}
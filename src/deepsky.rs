@@ -0,0 +1,164 @@
+#![allow(dead_code)]
+
+use raylib::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::matrix::world_to_screen;
+use crate::noise::{sub_seed, Noise};
+use crate::FrameUniforms;
+
+/// What a deep-sky object looks like.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeepSkyKind {
+    Galaxy, // Spiral arms around a bright core
+    Nebula, // Soft cloud of two colors
+}
+
+/// A galaxy or nebula far outside the system, from a `[[deep_sky]]` entry of the scene
+/// file. Far, but not infinitely: it shifts a little against the stars as the camera
+/// crosses the system.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeepSkyConfig {
+    pub kind: DeepSkyKind,
+    pub position: [f32; 3],
+    pub radius: f32,        // World units
+    pub angle: f32,         // Degrees the sprite is turned on screen
+    pub tilt: f32,          // Galaxy: degrees its disc leans away from face-on, which squashes it into an ellipse
+    pub arms: u32,          // Galaxy: spiral arms
+    pub color: [f32; 3],    // Arms, or the nebula's main gas
+    pub secondary: [f32; 3], // Core, or the nebula's second gas
+    pub brightness: f32,
+    pub seed: u64,          // Mixed with the scene's seed, so two objects of a kind differ
+}
+
+impl Default for DeepSkyConfig {
+    fn default() -> Self {
+        DeepSkyConfig {
+            kind: DeepSkyKind::Galaxy,
+            position: [0.0, 400.0, -2500.0],
+            radius: 180.0,
+            angle: 0.0,
+            tilt: 50.0,
+            arms: 2,
+            color: [0.55, 0.65, 1.0],
+            secondary: [1.0, 0.85, 0.6],
+            brightness: 0.35,
+            seed: 0,
+        }
+    }
+}
+
+/// Largest sprite drawn, as a multiple of the frame's height; closer ones are skipped
+/// rather than filling the screen.
+const MAX_SCREEN_RADIUS: f32 = 1.5;
+
+/// Adds the deep-sky objects of `configs` to the frame through `add(x, y, color)`. Meant
+/// to run right after the sky and before any body: nothing is depth-tested or written,
+/// so the sprites cover the stars and every body drawn later covers them.
+pub fn draw_deep_sky(configs: &[DeepSkyConfig], seed: u64, frame: &FrameUniforms, mut add: impl FnMut(i32, i32, Vector3)) {
+    let right = Vector3::new(frame.view.m0, frame.view.m4, frame.view.m8);
+    let (width, height) = (frame.screen_size.x as i32, frame.screen_size.y as i32);
+    for (index, config) in configs.iter().enumerate() {
+        let position = Vector3::new(config.position[0], config.position[1], config.position[2]);
+        let (Some(center), Some(edge)) = (
+            world_to_screen(position, &frame.view, &frame.projection, &frame.viewport),
+            world_to_screen(position + right * config.radius, &frame.view, &frame.projection, &frame.viewport),
+        ) else {
+            continue;
+        };
+        let radius = (edge.x - center.x).hypot(edge.y - center.y);
+        if radius < 1.0 || radius > height as f32 * MAX_SCREEN_RADIUS {
+            continue;
+        }
+        let noise = Noise::new(sub_seed(seed ^ config.seed, &format!("deep_sky{}", index))).with_detail(frame.fbm_octaves as f32);
+        let (sin, cos) = config.angle.to_radians().sin_cos();
+        let squash = config.tilt.to_radians().cos().abs().max(0.1);
+        let (x0, x1) = (((center.x - radius) as i32).max(0), ((center.x + radius) as i32 + 1).min(width));
+        let (y0, y1) = (((center.y - radius) as i32).max(0), ((center.y + radius) as i32 + 1).min(height));
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let (dx, dy) = ((x as f32 + 0.5 - center.x) / radius, (y as f32 + 0.5 - center.y) / radius);
+                let p = Vector2::new(dx * cos + dy * sin, dy * cos - dx * sin);
+                let color = match config.kind {
+                    DeepSkyKind::Galaxy => galaxy(Vector2::new(p.x, p.y / squash), config, &noise),
+                    DeepSkyKind::Nebula => nebula(p, config, &noise),
+                };
+                if color.x + color.y + color.z > 1e-4 {
+                    add(x, y, color * config.brightness);
+                }
+            }
+        }
+    }
+}
+
+/// Spiral galaxy at `p`, in radii of the sprite over its disc: a gaussian bulge and an
+/// exponential disc, brighter along logarithmic spiral arms roughened with noise.
+fn galaxy(p: Vector2, config: &DeepSkyConfig, noise: &Noise) -> Vector3 {
+    let r = p.length();
+    if r >= 1.0 {
+        return Vector3::zero();
+    }
+    let arms = config.arms.max(1) as f32;
+    let phase = arms * p.y.atan2(p.x) - 4.0 * (r + 0.05).ln();
+    let arm = (0.5 + 0.5 * phase.cos()).powi(3);
+    let clumps = noise.fbm(p * 6.0, 0);
+    let disc = (-r / 0.3).exp() * (0.25 + 0.75 * arm * (0.4 + 1.2 * clumps)) * smoothstep(1.0, 0.6, r);
+    let bulge = (-(r / 0.12).powi(2)).exp();
+    rgb(config.color) * disc + rgb(config.secondary) * bulge
+}
+
+/// Nebula at `p`, in radii of the sprite: two layers of noise mixing its two colors,
+/// thinning out to nothing well inside the sprite's edge.
+fn nebula(p: Vector2, config: &DeepSkyConfig, noise: &Noise) -> Vector3 {
+    let r = p.length();
+    if r >= 1.0 {
+        return Vector3::zero();
+    }
+    let gas = noise.fbm(p * 2.5, 0);
+    let mix = noise.fbm(p * 4.0, 1);
+    let density = ((gas - 0.35) * 2.0).clamp(0.0, 1.0) * smoothstep(1.0, 0.2, r);
+    (rgb(config.color) * (1.0 - mix) + rgb(config.secondary) * mix) * density
+}
+
+fn rgb(c: [f32; 3]) -> Vector3 {
+    Vector3::new(c[0], c[1], c[2])
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matrix::{create_projection_matrix, create_view_matrix, create_viewport_matrix};
+
+    /// Screen columns the galaxy's pixels span, seen from `eye` looking down -Z.
+    fn columns(config: &DeepSkyConfig, eye: Vector3) -> (i32, i32) {
+        let view = create_view_matrix(eye, eye + Vector3::new(0.0, 0.0, -1.0), Vector3::new(0.0, 1.0, 0.0));
+        let projection = create_projection_matrix(1.0, 1.0, 0.1, 100.0);
+        let viewport = create_viewport_matrix(0.0, 0.0, 200.0, 200.0);
+        let frame = FrameUniforms::new(view, projection, viewport, eye);
+        let (mut min, mut max) = (i32::MAX, i32::MIN);
+        draw_deep_sky(std::slice::from_ref(config), 1, &frame, |x, _, color| {
+            assert!(color.x >= 0.0 && color.y >= 0.0 && color.z >= 0.0);
+            (min, max) = (min.min(x), max.max(x));
+        });
+        (min, max)
+    }
+
+    #[test]
+    fn galaxy_shifts_a_little_against_the_stars() {
+        let config = DeepSkyConfig { position: [0.0, 0.0, -2000.0], radius: 200.0, tilt: 0.0, ..DeepSkyConfig::default() };
+        let here = columns(&config, Vector3::zero());
+        assert!(here.0 > 60 && here.1 < 140 && here.1 - here.0 > 20, "{:?}", here);
+
+        // Across the system: the stars, at infinity, stay put; the galaxy moves a few pixels
+        let there = columns(&config, Vector3::new(100.0, 0.0, 0.0));
+        let shift = here.0 - there.0;
+        assert!((2..20).contains(&shift), "{:?} -> {:?}", here, there);
+    }
+}
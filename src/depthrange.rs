@@ -0,0 +1,211 @@
+#![allow(dead_code)]
+
+use raylib::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::scene::SceneObject;
+
+/// The near plane sits this fraction of the way to the nearest surface, and the far
+/// plane this much past the farthest one, so bodies moving during a frame stay inside.
+const NEAR_MARGIN: f32 = 0.8;
+const FAR_MARGIN: f32 = 1.25;
+/// A plane only moves in the direction that costs precision (near closer, far farther)
+/// right away; it moves back once the fitted value is off by more than this factor.
+const HYSTERESIS: f32 = 1.5;
+
+/// How depth is stored. `Classic` maps near to -1 and far to 1 and keeps the nearer
+/// value; `Reverse` maps near to 1 and far to 0 and keeps the greater, so the floats'
+/// finer steps close to 0 land on the distant bodies where classic depth runs out.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DepthMode {
+    #[default]
+    Classic,
+    Reverse,
+}
+
+impl DepthMode {
+    /// Value of a depth buffer with nothing drawn.
+    pub fn cleared(self) -> f32 {
+        match self {
+            DepthMode::Classic => f32::INFINITY,
+            DepthMode::Reverse => 0.0,
+        }
+    }
+
+    /// Whether depth `a` is in front of depth `b`.
+    pub fn closer(self, a: f32, b: f32) -> bool {
+        match self {
+            DepthMode::Classic => a < b,
+            DepthMode::Reverse => a > b,
+        }
+    }
+
+    /// Whether an NDC depth lies between the near and far planes.
+    pub fn in_range(self, depth: f32) -> bool {
+        match self {
+            DepthMode::Classic => (-1.0..=1.0).contains(&depth),
+            DepthMode::Reverse => (0.0..=1.0).contains(&depth),
+        }
+    }
+
+    /// Whether a depth buffer value is empty or on the far plane, i.e. shows the sky.
+    pub fn is_background(self, depth: f32) -> bool {
+        match self {
+            DepthMode::Classic => !depth.is_finite() || depth >= 1.0,
+            DepthMode::Reverse => !depth.is_finite() || depth <= 0.0,
+        }
+    }
+
+    /// NDC depth of the near plane.
+    pub fn near_ndc(self) -> f32 {
+        match self {
+            DepthMode::Classic => -1.0,
+            DepthMode::Reverse => 1.0,
+        }
+    }
+
+    /// 0 on the near plane to 1 on the far one, for depth images.
+    pub fn normalized(self, depth: f32) -> f32 {
+        match self {
+            DepthMode::Classic => depth.clamp(-1.0, 1.0) * 0.5 + 0.5,
+            DepthMode::Reverse => 1.0 - depth.clamp(0.0, 1.0),
+        }
+    }
+
+    /// `projection` (centered or off-axis, built the classic way) with its depth row
+    /// rewritten for this mode. View distance stays `m14 / (ndc + m10)` in both.
+    pub fn projection(self, projection: Matrix, near: f32, far: f32) -> Matrix {
+        match self {
+            DepthMode::Classic => projection,
+            DepthMode::Reverse => Matrix { m10: near / (far - near), m14: far * near / (far - near), ..projection },
+        }
+    }
+}
+
+/// Near and far planes, read from the `[depth_range]` table of the scene file. With
+/// `auto` the planes follow the visible bodies every frame between `min_near` and
+/// `max_far`; without it, `near` and `far` are used as they are. `mode` picks classic
+/// or reverse-Z depth.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DepthRangeConfig {
+    pub mode: DepthMode,
+    pub auto: bool,
+    pub near: f32,
+    pub far: f32,
+    pub min_near: f32,
+    pub max_far: f32,
+}
+
+impl DepthRangeConfig {
+    /// Farthest distance the far plane can reach.
+    pub fn farthest(&self) -> f32 {
+        if self.auto { self.max_far } else { self.far }
+    }
+}
+
+impl Default for DepthRangeConfig {
+    fn default() -> Self {
+        DepthRangeConfig { mode: DepthMode::Classic, auto: true, near: 0.1, far: 100.0, min_near: 0.01, max_far: 10000.0 }
+    }
+}
+
+/// Near and far planes of the projection. Fitting them to what's on screen keeps the
+/// depth buffer's precision where the bodies are, from a shuttle skimming a moon to
+/// the whole system at once. The sky isn't affected: it never writes depth, so it is
+/// behind everything at any far value.
+pub struct DepthRange {
+    pub config: DepthRangeConfig,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl DepthRange {
+    pub fn new(config: DepthRangeConfig) -> Self {
+        DepthRange { near: config.near, far: config.far, config }
+    }
+
+    /// Brackets the bounding spheres of the visible bodies in front of the camera at
+    /// `eye` looking along `forward`.
+    pub fn fit(&mut self, objects: &[SceneObject], eye: Vector3, forward: Vector3) {
+        if !self.config.auto {
+            self.near = self.config.near;
+            self.far = self.config.far;
+            return;
+        }
+        let min_near = self.config.min_near.max(1e-4);
+        let max_far = self.config.max_far.max(min_near * 2.0);
+
+        let mut nearest = f32::INFINITY;
+        let mut farthest: f32 = 0.0;
+        for obj in objects.iter().filter(|o| o.flags.visible) {
+            let depth = (obj.translation - eye).dot(forward);
+            let radius = obj.radius * obj.scale;
+            if depth + radius <= 0.0 {
+                continue; // Wholly behind the camera
+            }
+            nearest = nearest.min(depth - radius);
+            farthest = farthest.max(depth + radius);
+        }
+        if !nearest.is_finite() {
+            return; // Nothing in front to fit to; keep the planes where they are
+        }
+
+        let near = (nearest * NEAR_MARGIN).clamp(min_near, max_far * 0.5);
+        let far = (farthest * FAR_MARGIN).clamp(near * 2.0, max_far);
+        if near < self.near || near > self.near * HYSTERESIS {
+            self.near = near;
+        }
+        if far > self.far || far * HYSTERESIS < self.far {
+            self.far = far;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use crate::matrix::{create_projection_matrix, create_view_matrix, multiply_matrices, multiply_matrix_vector4};
+
+    /// NDC depth of `point` through the same view-projection product the renderer uses.
+    fn ndc_depth(view_projection: &Matrix, point: Vector3) -> f32 {
+        let clip = multiply_matrix_vector4(view_projection, &Vector4::new(point.x, point.y, point.z, 1.0));
+        clip.z / clip.w
+    }
+
+    /// How many representable f32 values lie between two positive depths.
+    fn ulps(a: f32, b: f32) -> i64 {
+        (a.to_bits() as i64 - b.to_bits() as i64).abs()
+    }
+
+    #[test]
+    fn reverse_z_resolves_surfaces_near_the_far_plane() {
+        let (near, far) = (50.0, 8000.0);
+        let eye = Vector3::new(1234.5, 300.25, -2000.75);
+        let mut rng = StdRng::seed_from_u64(175);
+        let mut classic_steps = i64::MAX;
+        let mut reverse_steps = i64::MAX;
+        for _ in 0..200 {
+            let dir = Vector3::new(rng.random_range(-1.0..1.0), rng.random_range(-0.3..0.3), rng.random_range(-1.0..1.0)).normalized();
+            let view = create_view_matrix(eye, eye + dir, Vector3::new(0.0, 1.0, 0.0));
+            // Two surfaces 0.1 apart at 90% of the far distance
+            let front = eye + dir * (far * 0.9);
+            let back = eye + dir * (far * 0.9 + 0.1);
+            for (mode, steps) in [(DepthMode::Classic, &mut classic_steps), (DepthMode::Reverse, &mut reverse_steps)] {
+                let projection = mode.projection(create_projection_matrix(1.0, 16.0 / 9.0, near, far), near, far);
+                let view_projection = multiply_matrices(&projection, &view);
+                let (a, b) = (ndc_depth(&view_projection, front), ndc_depth(&view_projection, back));
+                if mode == DepthMode::Reverse {
+                    assert!(mode.closer(a, b), "reverse-Z puts the farther surface in front ({} vs {})", a, b);
+                }
+                *steps = (*steps).min(if mode.closer(a, b) { ulps(a, b) } else { 0 });
+            }
+        }
+        assert!(classic_steps <= 4, "classic depth kept {} steps between the surfaces", classic_steps);
+        assert!(reverse_steps >= 100, "reverse-Z kept only {} steps between the surfaces", reverse_steps);
+    }
+}
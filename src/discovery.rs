@@ -0,0 +1,179 @@
+#![allow(dead_code)]
+
+use std::collections::{BTreeSet, HashMap};
+
+use raylib::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+use crate::framebuffer::Framebuffer;
+use crate::matrix::world_to_screen;
+use crate::scene::SceneObject;
+use crate::sidecar::{load_state, save_state, sidecar_path};
+use crate::ui::draw_world_label;
+use crate::Uniforms;
+
+/// Key that forgets every discovery, hiding the bodies again.
+pub const DISCOVERY_RESET_KEY: KeyboardKey = KeyboardKey::KEY_F10;
+
+/// Kind drawn in place of a body not discovered yet: a dim grey sphere.
+pub const PLACEHOLDER_KIND: &str = "placeholder";
+
+/// Discovery demo settings, read from the `[discovery]` table of the scene file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiscoveryConfig {
+    pub enabled: bool,
+    pub distance: f32,      // Coming this close to a body's surface discovers it
+    pub hold_seconds: f32,  // Keeping a body centered this long also discovers it
+    pub center_radius: f32, // How near the screen center counts as centered, as a fraction of the screen height
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        DiscoveryConfig { enabled: false, distance: 10.0, hold_seconds: 1.0, center_radius: 0.1 }
+    }
+}
+
+/// Bodies found so far, as written to the state file.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct DiscoveryState {
+    discovered: BTreeSet<String>,
+}
+
+/// Which bodies the viewer has found. Undiscovered bodies are drawn as placeholders
+/// with no label or orbit line until the camera gets near them or holds them centered.
+pub struct Discovery {
+    pub config: DiscoveryConfig,
+    discovered: BTreeSet<String>,
+    held: HashMap<String, f32>, // Seconds each body has been centered in a row
+    path: String,               // State file
+}
+
+impl Discovery {
+    /// Starts from the discoveries saved in `path`, if any.
+    pub fn load(config: DiscoveryConfig, path: String) -> Self {
+        let discovered = load_state::<DiscoveryState>(&path).discovered;
+        Discovery { config, discovered, held: HashMap::new(), path }
+    }
+
+    pub fn save(&self) -> Result<(), AppError> {
+        save_state(&DiscoveryState { discovered: self.discovered.clone() }, &self.path, "discoveries")
+    }
+
+    /// Whether `obj` is drawn in full. Everything is with the mode off; the sun and the
+    /// shuttle always are, and rings follow their planet.
+    pub fn is_discovered(&self, obj: &SceneObject) -> bool {
+        if !self.config.enabled || matches!(obj.object_type.as_str(), "sun" | "shuttle") {
+            return true;
+        }
+        let name = match (obj.object_type.as_str(), &obj.parent) {
+            ("ring", Some(parent)) => parent,
+            _ => &obj.name,
+        };
+        self.discovered.contains(name)
+    }
+
+    /// Discovers bodies near `eye` or held near the middle of the view. Returns the
+    /// names found this frame.
+    pub fn update(
+        &mut self,
+        objects: &[SceneObject],
+        eye: Vector3,
+        view: &Matrix,
+        projection: &Matrix,
+        viewport: &Matrix,
+        dt: f32,
+    ) -> Vec<String> {
+        if !self.config.enabled {
+            return Vec::new();
+        }
+        // The viewport maps NDC (0, 0) to the screen center and NDC y = 1 to the top edge
+        let center = Vector2::new(viewport.m12, viewport.m13);
+        let max_offset = self.config.center_radius * viewport.m5.abs() * 2.0;
+
+        let mut found = Vec::new();
+        for obj in objects {
+            if obj.object_type == "ring" || self.is_discovered(obj) {
+                continue;
+            }
+            let near = (obj.translation - eye).length() - obj.radius * obj.scale < self.config.distance;
+            let centered = world_to_screen(obj.translation, view, projection, viewport)
+                .is_some_and(|p| Vector2::new(p.x - center.x, p.y - center.y).length() < max_offset);
+
+            let held = self.held.entry(obj.name.clone()).or_insert(0.0);
+            *held = if centered { *held + dt } else { 0.0 };
+            if near || *held >= self.config.hold_seconds {
+                found.push(obj.name.clone());
+            }
+        }
+        for name in &found {
+            self.held.remove(name);
+            self.discovered.insert(name.clone());
+        }
+        found
+    }
+
+    /// Names of the discovered bodies (not rings or the shuttle) under their centers, as
+    /// world labels anchored on the side facing `eye`, so nearer bodies hide them.
+    pub fn draw_labels(&self, framebuffer: &mut Framebuffer, objects: &[SceneObject], uniforms: &Uniforms, eye: Vector3) {
+        if !self.config.enabled {
+            return;
+        }
+        for o in objects {
+            if o.object_type == "ring" || o.object_type == "shuttle" || !o.flags.visible || !self.is_discovered(o) {
+                continue;
+            }
+            // A little in front of the surface, so the body's own disc doesn't hide it
+            let front = o.translation + (eye - o.translation).normalized() * (o.radius * o.scale * 1.05);
+            draw_world_label(framebuffer, front, &o.name, uniforms, Vector3::new(0.96, 0.96, 0.96));
+        }
+    }
+
+    /// Hides every body again.
+    pub fn reset(&mut self) {
+        self.discovered.clear();
+        self.held.clear();
+    }
+}
+
+/// Where the discoveries of a scene are kept: `default.toml` uses `default.discovery.toml`.
+pub fn discovery_state_path(scene_path: &str) -> String {
+    sidecar_path(scene_path, "discovery")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use super::*;
+    use crate::matrix::{create_projection_matrix, create_view_matrix, create_viewport_matrix};
+
+    #[test]
+    fn coming_close_discovers_a_body_and_it_stays_found() {
+        let path = std::env::temp_dir().join("solar_system_reveal.discovery.toml").to_string_lossy().into_owned();
+        let _ = std::fs::remove_file(&path);
+        let config = DiscoveryConfig { enabled: true, distance: 5.0, ..DiscoveryConfig::default() };
+        let mars = SceneObject::test_sphere("mars", "rocky1", Vector3::new(0.0, 0.0, 0.0), 1.0);
+        let objects = [mars.clone()];
+        // Looking away from the body, so only the distance can discover it
+        let look = |eye: Vector3| create_view_matrix(eye, eye + Vector3::new(0.0, 1.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        let projection = create_projection_matrix(PI / 3.0, 1.0, 0.1, 100.0);
+        let viewport = create_viewport_matrix(0.0, 0.0, 100.0, 100.0);
+
+        let mut discovery = Discovery::load(config.clone(), path.clone());
+        let far = Vector3::new(0.0, 0.0, 20.0);
+        assert!(discovery.update(&objects, far, &look(far), &projection, &viewport, 0.1).is_empty());
+        assert!(!discovery.is_discovered(&mars));
+
+        let near = Vector3::new(0.0, 0.0, 5.5);
+        assert_eq!(discovery.update(&objects, near, &look(near), &projection, &viewport, 0.1), vec!["mars".to_string()]);
+        assert!(discovery.is_discovered(&mars));
+        discovery.save().unwrap();
+
+        let reloaded = Discovery::load(config, path.clone());
+        assert!(reloaded.is_discovered(&mars));
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,149 @@
+#![allow(dead_code)]
+
+use raylib::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::framebuffer::GlowTarget;
+use crate::matrix::view_ray;
+use crate::Uniforms;
+
+/// Zodiacal dust disc around the sun, read from the `[dust]` table of the scene file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DustConfig {
+    pub enabled: bool,
+    pub inner_radius: f32,
+    pub outer_radius: f32,
+    pub thickness: f32, // Scale height above/below the ecliptic plane
+    pub color: [f32; 3],
+    pub intensity: f32,
+    pub softness: f32, // Depth distance over which the dust fades into planet surfaces
+}
+
+impl Default for DustConfig {
+    fn default() -> Self {
+        DustConfig {
+            enabled: true,
+            inner_radius: 4.0,
+            outer_radius: 42.0,
+            thickness: 1.5,
+            color: [1.0, 0.9, 0.72],
+            intensity: 0.025,
+            softness: 2.0,
+        }
+    }
+}
+
+const STEPS: usize = 8;
+
+fn hash(x: f32, y: f32) -> f32 {
+    ((x * 127.1 + y * 311.7).sin() * 43758.547).fract().abs()
+}
+
+/// Smooth 2D value noise in [0, 1].
+fn value_noise(x: f32, y: f32) -> f32 {
+    let (ix, iy) = (x.floor(), y.floor());
+    let (fx, fy) = (x - ix, y - iy);
+    let (ux, uy) = (fx * fx * (3.0 - 2.0 * fx), fy * fy * (3.0 - 2.0 * fy));
+    let a = hash(ix, iy);
+    let b = hash(ix + 1.0, iy);
+    let c = hash(ix, iy + 1.0);
+    let d = hash(ix + 1.0, iy + 1.0);
+    a + (b - a) * ux + (c - a) * uy + (a - b - c + d) * ux * uy
+}
+
+/// Dust density at a world point: fades in past the inner radius, thins out towards
+/// the outer radius and falls off with height above the plane.
+fn density(p: Vector3, config: &DustConfig) -> f32 {
+    let r = (p.x * p.x + p.z * p.z).sqrt();
+    if r < config.inner_radius || r > config.outer_radius {
+        return 0.0;
+    }
+    let span = config.outer_radius - config.inner_radius;
+    let t = (r - config.inner_radius) / span;
+    let radial = (t / 0.15).min(1.0) * (1.0 - t).powi(2);
+
+    let h = p.y / config.thickness;
+    radial * (-h * h).exp()
+}
+
+/// Large-scale clumping of the dust in the plane, in [0.6, 1].
+fn clumps(x: f32, z: f32) -> f32 {
+    0.6 + 0.4 * (value_noise(x * 0.15, z * 0.15) * 0.7 + value_noise(x * 0.45, z * 0.45) * 0.3)
+}
+
+/// Adds the dust glow over the already rendered scene. Each pixel marches its view ray
+/// through the dust slab (|y| < 3 × thickness); every sample fades out as it gets close
+/// to the opaque surface stored in the depth buffer, soft-particle style, so planets
+/// passing through the disc never show a hard intersection line.
+pub fn draw_dust(target: &mut impl GlowTarget, uniforms: &Uniforms, eye: Vector3, config: &DustConfig) {
+    if !config.enabled || config.intensity <= 0.0 {
+        return;
+    }
+
+    let p = &uniforms.frame.projection;
+    let v = &uniforms.frame.view;
+    let vp = &uniforms.frame.viewport;
+    let right = Vector3::new(v.m0, v.m4, v.m8);
+    let up = Vector3::new(v.m1, v.m5, v.m9);
+    let back = Vector3::new(v.m2, v.m6, v.m10);
+
+    let slab = config.thickness * 3.0;
+    let color = Vector3::new(config.color[0], config.color[1], config.color[2]);
+
+    let (width, height) = target.size();
+    for y in 0..height as i32 {
+        for x in 0..width as i32 {
+            // View-space ray through the pixel center, and its world-space direction
+            let ndc_x = (x as f32 + 0.5 - vp.m12) / vp.m0;
+            let ndc_y = (y as f32 + 0.5 - vp.m13) / vp.m5;
+            let view_dir = view_ray(p, ndc_x, ndc_y).normalized();
+            let dir = right * view_dir.x + up * view_dir.y + back * view_dir.z;
+
+            // Segment of the ray inside the slab, limited to the disc's extent
+            let (t0, mut t1) = if dir.y.abs() < 1e-5 {
+                if eye.y.abs() > slab {
+                    continue;
+                }
+                (0.0, f32::INFINITY)
+            } else {
+                let a = (slab - eye.y) / dir.y;
+                let b = (-slab - eye.y) / dir.y;
+                (a.min(b).max(0.0), a.max(b))
+            };
+            t1 = t1.min(eye.length() + config.outer_radius);
+            if t1 <= t0 {
+                continue;
+            }
+
+            // Distance to the opaque surface behind this pixel, along the ray
+            let ndc_depth = target.depth_at(x, y);
+            let surface = if !target.depth_mode().is_background(ndc_depth) {
+                p.m14 / (ndc_depth + p.m10) / -view_dir.z
+            } else {
+                f32::INFINITY
+            };
+            t1 = t1.min(surface);
+            if t1 <= t0 {
+                continue;
+            }
+
+            let step = (t1 - t0) / STEPS as f32;
+            let mut glow = 0.0;
+            for i in 0..STEPS {
+                let t = t0 + step * (i as f32 + 0.5);
+                let soft = ((surface - t) / config.softness).clamp(0.0, 1.0);
+                if soft > 0.0 {
+                    glow += density(eye + dir * t, config) * soft * step;
+                }
+            }
+
+            if glow > 1e-4 {
+                // Clumping sampled once per pixel, where the ray is mid-slab
+                let mid = eye + dir * ((t0 + t1) * 0.5);
+                let k = (glow * clumps(mid.x, mid.z) * config.intensity).min(1.0);
+                target.add_color(x, y, Vector3::new(color.x * k, color.y * k, color.z * k));
+            }
+        }
+    }
+}
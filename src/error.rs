@@ -0,0 +1,63 @@
+#![allow(dead_code)]
+
+use std::fmt;
+
+use raylib::prelude::*;
+
+/// Key that hides or shows the asset error panel.
+pub const ERROR_PANEL_KEY: KeyboardKey = KeyboardKey::KEY_F4;
+
+/// Errors from loading or writing assets. None of them are fatal: main lists them
+/// in an on-screen panel and carries on with whatever did load.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AppError {
+    AssetMissing { path: String },
+    ObjParse { path: String, line: Option<usize> }, // First line that failed to parse, when it can be found
+    SceneConfig { msg: String },
+    SceneCheck { msg: String }, // Likely mistake in a scene that loaded, see `validate`
+    ImageWrite { path: String },
+    SceneWrite { path: String },
+    StateWrite { what: String, path: String }, // Progress kept next to a scene: discoveries, bookmarks
+    Replay { path: String, msg: String }, // Input replay that can't be recorded or played back
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::AssetMissing { path } => write!(f, "missing asset: {}", path),
+            AppError::ObjParse { path, line: Some(line) } => write!(f, "could not parse model {} (line {})", path, line),
+            AppError::ObjParse { path, line: None } => write!(f, "could not parse model {}", path),
+            AppError::SceneConfig { msg } => write!(f, "scene error: {}", msg),
+            AppError::SceneCheck { msg } => write!(f, "scene warning: {}", msg),
+            AppError::ImageWrite { path } => write!(f, "could not write image {}", path),
+            AppError::SceneWrite { path } => write!(f, "could not write scene {}", path),
+            AppError::StateWrite { what, path } => write!(f, "could not save {} to {}", what, path),
+            AppError::Replay { path, msg } => write!(f, "replay {}: {}", path, msg),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+// Console commands and scene reloads still report plain strings
+impl From<AppError> for String {
+    fn from(error: AppError) -> String {
+        error.to_string()
+    }
+}
+
+/// Lists the asset errors in a panel at the top of the window.
+pub fn draw_error_panel(d: &mut RaylibDrawHandle, errors: &[AppError], width: i32) {
+    let height = 34 + errors.len() as i32 * 20;
+    d.draw_rectangle(0, 0, width, height, Color::new(110, 70, 10, 220));
+    d.draw_text(
+        &format!("{} problem(s) loading the scene and its assets, using fallbacks (F4 hides this):", errors.len()),
+        10,
+        8,
+        16,
+        Color::WHITE,
+    );
+    for (i, error) in errors.iter().enumerate() {
+        d.draw_text(&format!("- {}", error), 20, 30 + i as i32 * 20, 16, Color::WHITE);
+    }
+}
@@ -0,0 +1,353 @@
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+
+use raylib::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::input::InputState;
+use crate::matrix::multiply_matrix_vector4;
+use crate::scene::SceneObject;
+
+/// Key that shows and hides the event log.
+pub const EVENT_LOG_KEY: KeyboardKey = KeyboardKey::KEY_I;
+
+/// Lines of the log shown at once; Page Up and Page Down scroll by half of them.
+const LOG_LINES: usize = 12;
+/// A conjunction or opposition ends once the bodies drift this many times the threshold
+/// apart, so one hovering at the threshold isn't logged over and over.
+const HYSTERESIS: f32 = 1.25;
+/// Sine of the angle from a ring plane within which a viewer counts as in the plane, so a
+/// scene laid out flat (every body on the ecliptic) never crosses it.
+const PLANE_EPSILON: f32 = 1e-4;
+
+/// Kinds of event the detector looks for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    SolarEclipse,      // A moon's shadow touches its planet
+    Conjunction,       // Two bodies close together in the focused body's sky
+    Opposition,        // A body opposite the sun in the focused body's sky
+    RingPlaneCrossing, // The sun or the focused body passes through a planet's ring plane
+}
+
+/// Event settings, read from the `[events]` table of the scene file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EventsConfig {
+    pub enabled: bool,
+    pub conjunction: f32,        // Degrees apart two bodies look at most for a conjunction, and off opposite the sun for an opposition
+    pub pause_on: Vec<EventKind>, // Kinds that pause the simulation the step they happen
+    pub max_entries: usize,      // Oldest entries are dropped past this
+}
+
+impl Default for EventsConfig {
+    fn default() -> Self {
+        EventsConfig { enabled: true, conjunction: 2.0, pause_on: Vec::new(), max_entries: 200 }
+    }
+}
+
+/// One entry of the log.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Event {
+    pub kind: EventKind,
+    pub time: f32, // Simulated seconds
+    pub text: String,
+}
+
+/// Watches the bodies every simulation step for eclipses, conjunctions, oppositions and
+/// ring-plane crossings, and logs each as it starts. Conjunctions and oppositions are
+/// seen from the focused body, the way they'd be seen from a planet.
+pub struct EventLog {
+    pub visible: bool,
+    pub config: EventsConfig,
+    entries: Vec<Event>,
+    scroll: usize,                // Entries scrolled back from the newest
+    active: Vec<String>,          // What was going on at the last step, so each is logged once as it starts
+    sides: HashMap<String, bool>, // Side of each ring plane each viewer was last on
+}
+
+impl EventLog {
+    pub fn new(config: EventsConfig) -> Self {
+        EventLog { visible: false, config, entries: Vec::new(), scroll: 0, active: Vec::new(), sides: HashMap::new() }
+    }
+
+    pub fn entries(&self) -> &[Event] {
+        &self.entries
+    }
+
+    /// I shows the log; Page Up and Page Down scroll it while it's shown.
+    pub fn process_input(&mut self, input: &InputState) {
+        if input.is_key_pressed(EVENT_LOG_KEY) {
+            self.visible = !self.visible;
+            self.scroll = 0;
+        }
+        if !self.visible {
+            return;
+        }
+        let last = self.entries.len().saturating_sub(LOG_LINES);
+        if input.is_key_pressed(KeyboardKey::KEY_PAGE_UP) {
+            self.scroll = (self.scroll + LOG_LINES / 2).min(last);
+        }
+        if input.is_key_pressed(KeyboardKey::KEY_PAGE_DOWN) {
+            self.scroll = self.scroll.saturating_sub(LOG_LINES / 2);
+        }
+    }
+
+    /// Forgets the log and what was going on, for a reloaded scene.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.active.clear();
+        self.sides.clear();
+        self.scroll = 0;
+    }
+
+    /// One simulation step at `time`, with `observer` the focused body's name. Returns
+    /// true when an event of a kind in `pause_on` started, to pause the simulation on it.
+    pub fn step(&mut self, objects: &[SceneObject], time: f32, observer: Option<&str>) -> bool {
+        if !self.config.enabled {
+            return false;
+        }
+        let mut found: Vec<(EventKind, String, String)> = Vec::new(); // (kind, key, text)
+        let find = |name: &str| objects.iter().find(|o| o.name == name && o.is_listed());
+        let sun = objects.iter().find(|o| o.object_type == "sun");
+        let observer = observer.and_then(find);
+
+        // Solar eclipses: the moon's penumbra, the cone around it that sees part of the sun
+        // hidden, reaching its planet
+        if let Some(sun) = sun {
+            for moon in objects.iter().filter(|o| o.is_listed() && o.object_type != "sun") {
+                let Some(planet) = moon.parent.as_deref().and_then(find) else {
+                    continue;
+                };
+                if in_penumbra(sun, moon, planet) {
+                    found.push((
+                        EventKind::SolarEclipse,
+                        format!("eclipse {} {}", moon.name, planet.name),
+                        format!("Solar eclipse on {}", display_name(&planet.name)),
+                    ));
+                }
+            }
+        }
+
+        if let Some(observer) = observer {
+            let limit = self.config.conjunction.to_radians();
+            let others: Vec<&SceneObject> = objects.iter().filter(|o| o.is_listed() && o.name != observer.name).collect();
+            let direction = |o: &SceneObject| (o.translation - observer.translation).normalized();
+            for (i, a) in others.iter().enumerate() {
+                for b in &others[i + 1..] {
+                    let key = format!("conjunction {} {} {}", observer.name, a.name, b.name);
+                    let separation = angle(direction(a), direction(b));
+                    if separation < limit || (self.active.contains(&key) && separation < limit * HYSTERESIS) {
+                        let text = format!(
+                            "Conjunction of {} and {} seen from {} ({:.1} deg)",
+                            display_name(&a.name),
+                            display_name(&b.name),
+                            display_name(&observer.name),
+                            separation.to_degrees()
+                        );
+                        found.push((EventKind::Conjunction, key, text));
+                    }
+                }
+            }
+            if let Some(sun) = sun.filter(|s| s.name != observer.name) {
+                for body in others.iter().filter(|o| o.name != sun.name) {
+                    let key = format!("opposition {} {}", observer.name, body.name);
+                    let off = std::f32::consts::PI - angle(direction(body), direction(sun));
+                    if off < limit || (self.active.contains(&key) && off < limit * HYSTERESIS) {
+                        let text = format!("{} at opposition seen from {}", display_name(&body.name), display_name(&observer.name));
+                        found.push((EventKind::Opposition, key, text));
+                    }
+                }
+            }
+        }
+
+        // Ring-plane crossings, by the sun (equinox on the planet) and by the focused body;
+        // each lasts a single step
+        let mut started: Vec<(EventKind, String)> = Vec::new();
+        for ring in objects.iter().filter(|o| o.object_type == "ring" && o.flags.visible) {
+            let Some(planet) = ring.parent.as_deref().and_then(find) else {
+                continue;
+            };
+            let axis = multiply_matrix_vector4(&ring.model_matrix(), &Vector4::new(0.0, 1.0, 0.0, 0.0));
+            let normal = Vector3::new(axis.x, axis.y, axis.z).normalized();
+            for viewer in [sun, observer].into_iter().flatten().filter(|v| v.name != planet.name) {
+                let to_viewer = viewer.translation - planet.translation;
+                let height = normal.dot(to_viewer) / to_viewer.length().max(1e-6);
+                if height.abs() < PLANE_EPSILON {
+                    continue;
+                }
+                let above = height > 0.0;
+                if self.sides.insert(format!("{} {}", ring.name, viewer.name), above).is_some_and(|was| was != above) {
+                    let text = format!("{} crosses {}'s ring plane", display_name(&viewer.name), display_name(&planet.name));
+                    started.push((EventKind::RingPlaneCrossing, text));
+                }
+            }
+        }
+
+        // The rest are logged as they start, and not again until they've ended
+        for (kind, key, text) in &found {
+            if !self.active.contains(key) {
+                started.push((*kind, text.clone()));
+            }
+        }
+        self.active = found.into_iter().map(|(_, key, _)| key).collect();
+        let pause = started.iter().any(|(kind, _)| self.config.pause_on.contains(kind));
+        for (kind, text) in started {
+            self.log(kind, time, text);
+        }
+        if pause {
+            // Show what stopped the clock
+            self.visible = true;
+            self.scroll = 0;
+        }
+        pause
+    }
+
+    fn log(&mut self, kind: EventKind, time: f32, text: String) {
+        tracing::info!("{}", text);
+        self.entries.push(Event { kind, time, text });
+        if self.entries.len() > self.config.max_entries.max(1) {
+            self.entries.remove(0);
+        }
+    }
+
+    /// Draws the log on the right edge of the window under the minimap, newest entry last.
+    pub fn draw(&self, d: &mut RaylibDrawHandle, width: i32) {
+        if !self.visible {
+            return;
+        }
+        let end = self.entries.len() - self.scroll.min(self.entries.len());
+        let shown = &self.entries[end.saturating_sub(LOG_LINES)..end];
+        let mut lines = vec![format!("Events: {} (I hides, PgUp/PgDn scroll)", self.entries.len())];
+        if self.entries.is_empty() {
+            lines.push("  nothing yet".to_string());
+        }
+        lines.extend(shown.iter().map(|e| format!("{:>9.1} s  {}", e.time, e.text)));
+
+        let top = 190;
+        let left = width - 10 - lines.iter().map(|l| measure_text(l, 10)).max().unwrap_or(0);
+        d.draw_rectangle(left - 4, top - 4, width - left, 14 * lines.len() as i32 + 6, Color::new(0, 0, 0, 170));
+        for (i, line) in lines.iter().enumerate() {
+            let color = if i == 0 { Color::LIGHTGRAY } else { Color::new(170, 220, 255, 255) };
+            d.draw_text(line, left, top + 14 * i as i32, 10, color);
+        }
+    }
+}
+
+/// Whether `planet` is at least partly inside the penumbra `moon` casts from `sun`: the
+/// cone of lines touching both spheres on opposite sides, widening past the moon by
+/// (sun radius + moon radius) / distance per unit.
+fn in_penumbra(sun: &SceneObject, moon: &SceneObject, planet: &SceneObject) -> bool {
+    let to_moon = moon.translation - sun.translation;
+    let distance = to_moon.length();
+    if distance < 1e-6 {
+        return false;
+    }
+    let axis = to_moon / distance;
+    let offset = planet.translation - moon.translation;
+    let behind = offset.dot(axis);
+    if behind <= 0.0 {
+        return false;
+    }
+    let (sun_radius, moon_radius) = (sun.radius * sun.scale, moon.radius * moon.scale);
+    let penumbra = moon_radius + behind * (sun_radius + moon_radius) / distance;
+    (offset - axis * behind).length() - planet.radius * planet.scale <= penumbra
+}
+
+/// Angle between two unit vectors, in radians.
+fn angle(a: Vector3, b: Vector3) -> f32 {
+    a.dot(b).clamp(-1.0, 1.0).acos()
+}
+
+/// A body's name for the log, capitalized: "earth" reads "Earth".
+fn display_name(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::{default_scene_objects, update_scene};
+    use crate::timestep::SIM_STEP;
+
+    #[test]
+    fn pauses_as_the_moons_shadow_touches_earth() {
+        let mut objects = default_scene_objects();
+        let mut events = EventLog::new(EventsConfig { pause_on: vec![EventKind::SolarEclipse], ..EventsConfig::default() });
+        let index = |name: &str| objects.iter().position(|o| o.name == name).unwrap();
+        let (sun, moon, earth) = (index("sun"), index("moon"), index("earth"));
+        // Only the moon's eclipses; pan's on gassy1 come round more often
+        let pan = index("pan");
+        objects[pan].flags.visible = false;
+
+        // Step until the eclipse starts: the shadow touches now and didn't a step earlier
+        let mut time = 0.0;
+        let mut was_touching = in_penumbra(&objects[sun], &objects[moon], &objects[earth]);
+        loop {
+            update_scene(&mut objects, SIM_STEP);
+            time += SIM_STEP;
+            let paused = events.step(&objects, time, None);
+            let touching = in_penumbra(&objects[sun], &objects[moon], &objects[earth]);
+            assert_eq!(paused, touching && !was_touching, "at {} s", time);
+            if paused {
+                break;
+            }
+            was_touching = touching;
+            assert!(time < 20.0, "no eclipse within two moon orbits");
+        }
+        assert_eq!(events.entries().last().unwrap().text, "Solar eclipse on Earth");
+        assert!(events.visible);
+
+        // Logged once while it lasts
+        update_scene(&mut objects, SIM_STEP);
+        assert!(!events.step(&objects, time + SIM_STEP, None));
+        assert_eq!(events.entries().len(), 1);
+    }
+
+    #[test]
+    fn conjunctions_oppositions_and_ring_crossings_are_logged_once() {
+        let mut objects = default_scene_objects();
+        let index = |objects: &[SceneObject], name: &str| objects.iter().position(|o| o.name == name).unwrap();
+        let (sun, earth, rocky1, gassy2, gassy3) =
+            (index(&objects, "sun"), index(&objects, "earth"), index(&objects, "rocky1"), index(&objects, "gassy2"), index(&objects, "gassy3"));
+        let (ring, gassy1) = (index(&objects, "ring"), index(&objects, "gassy1"));
+        // Spread out far away, then two lined up past earth and one on its night side
+        let count = objects.len();
+        for (i, obj) in objects.iter_mut().enumerate() {
+            let angle = i as f32 / count as f32 * std::f32::consts::TAU;
+            obj.translation = Vector3::new(angle.cos(), 1.0, angle.sin()) * 500.0;
+        }
+        objects[sun].translation = Vector3::zero();
+        objects[earth].translation = Vector3::new(30.0, 0.0, 0.0);
+        objects[rocky1].translation = Vector3::new(30.0, 0.0, 20.0);
+        objects[gassy2].translation = Vector3::new(30.0, 0.3, 40.0);
+        objects[gassy3].translation = Vector3::new(60.0, 0.0, 0.0);
+        // and a ring tilted out of the ecliptic
+        objects[gassy1].translation = Vector3::new(0.0, 0.0, -50.0);
+        objects[ring].translation = objects[gassy1].translation;
+        objects[ring].rotation = Vector3::new(0.4, 0.0, 0.0);
+
+        let mut events = EventLog::new(EventsConfig::default());
+        assert!(!events.step(&objects, 1.0, Some("earth")));
+        let texts: Vec<&str> = events.entries().iter().map(|e| e.text.as_str()).collect();
+        assert!(texts.contains(&"Conjunction of Rocky1 and Gassy2 seen from Earth (0.4 deg)"), "{:?}", texts);
+        assert!(texts.contains(&"Gassy3 at opposition seen from Earth"), "{:?}", texts);
+        let logged = events.entries().len();
+        events.step(&objects, 2.0, Some("earth"));
+        assert_eq!(events.entries().len(), logged);
+
+        // The sun passing through the ring's plane, pausing when asked to
+        events.config.pause_on = vec![EventKind::RingPlaneCrossing];
+        objects[sun].translation = Vector3::new(0.0, 30.0, 0.0);
+        events.step(&objects, 3.0, None);
+        objects[sun].translation = Vector3::new(0.0, -30.0, 0.0);
+        assert!(events.step(&objects, 4.0, None));
+        assert_eq!(events.entries().last().unwrap().text, "Sun crosses Gassy1's ring plane");
+        assert!(!events.step(&objects, 5.0, None));
+    }
+}
@@ -0,0 +1,130 @@
+#![allow(dead_code)]
+
+use raylib::prelude::*;
+
+use crate::framebuffer::Framebuffer;
+use crate::input::InputState;
+
+/// Keys that close and open the exposure by a third of a stop.
+pub const EV_DOWN_KEY: KeyboardKey = KeyboardKey::KEY_MINUS;
+pub const EV_UP_KEY: KeyboardKey = KeyboardKey::KEY_EQUAL;
+/// Key that toggles auto exposure.
+pub const AUTO_EXPOSURE_KEY: KeyboardKey = KeyboardKey::KEY_X;
+/// Key that toggles spot metering (the pixel under the crosshair) vs the whole frame.
+pub const SPOT_METER_KEY: KeyboardKey = KeyboardKey::KEY_C;
+
+const EV_STEP: f32 = 1.0 / 3.0;
+const EV_MIN: f32 = -8.0;
+const EV_MAX: f32 = 8.0;
+/// Metered luminance is exposed to this, like a camera's 18% grey card.
+const MIDDLE_GREY: f32 = 0.18;
+/// Stops per second auto exposure moves towards the meter.
+const ADAPT_RATE: f32 = 2.0;
+/// Auto exposure holds still while the meter is within this many stops, so jitter
+/// and noise don't keep nudging it.
+const ADAPT_DEADBAND: f32 = 0.1;
+/// Whole-frame metering samples a grid this many pixels across.
+const METER_GRID: u32 = 16;
+/// Samples darker than this are empty space and left out of the average.
+const METER_FLOOR: f32 = 0.02;
+
+/// Photographic exposure in EV stops: the renderer scales every color written to the
+/// image by 2^ev before it is clamped to 8 bits. Negative values darken (the sun's
+/// surface), positive ones brighten (the moon's night side under earthshine).
+pub struct Exposure {
+    pub ev: f32,
+    pub auto: bool,           // Follow the meter instead of the keys
+    pub spot: bool,           // Meter the crosshair pixel instead of the frame average
+    pub metered: Option<f32>, // EV the meter suggests for the last frame
+}
+
+impl Exposure {
+    pub fn new() -> Self {
+        Exposure { ev: 0.0, auto: false, spot: false, metered: None }
+    }
+
+    /// `-` and `=` step the EV (turning auto exposure off), X toggles auto exposure and
+    /// C spot metering.
+    pub fn process_input(&mut self, input: &InputState) {
+        if input.is_key_pressed(EV_DOWN_KEY) {
+            self.auto = false;
+            self.ev = (self.ev - EV_STEP).max(EV_MIN);
+        }
+        if input.is_key_pressed(EV_UP_KEY) {
+            self.auto = false;
+            self.ev = (self.ev + EV_STEP).min(EV_MAX);
+        }
+        if input.is_key_pressed(AUTO_EXPOSURE_KEY) {
+            self.auto = !self.auto;
+        }
+        if input.is_key_pressed(SPOT_METER_KEY) {
+            self.spot = !self.spot;
+        }
+    }
+
+    /// Linear scale applied to colors before they are quantized.
+    pub fn scale(&self) -> f32 {
+        self.ev.exp2()
+    }
+
+    /// Meters the finished frame and, with auto exposure on, moves the EV towards the
+    /// suggestion for the next one.
+    pub fn update(&mut self, framebuffer: &mut Framebuffer, dt: f32) {
+        let luma = if self.spot {
+            framebuffer.color_at(framebuffer.width as i32 / 2, framebuffer.height as i32 / 2).map(luminance)
+        } else {
+            average_luminance(framebuffer)
+        };
+        self.metered = luma.map(|luma| suggested_ev(self.ev, luma));
+
+        if let Some(target) = self.metered.filter(|_| self.auto) {
+            let error = target - self.ev;
+            if error.abs() > ADAPT_DEADBAND {
+                let step = error.signum() * (ADAPT_RATE * dt).min(error.abs());
+                self.ev = (self.ev + step).clamp(EV_MIN, EV_MAX);
+            }
+        }
+    }
+
+    /// HUD line: the EV in use and what the meter reads.
+    pub fn status(&self) -> String {
+        let mode = if self.auto { "auto" } else { "manual" };
+        let meter = if self.spot { "spot" } else { "average" };
+        match self.metered {
+            Some(metered) => format!("Exposure {:+.1} EV ({})  meter {:+.1} EV ({})", self.ev, mode, metered, meter),
+            None => format!("Exposure {:+.1} EV ({})", self.ev, mode),
+        }
+    }
+}
+
+/// Relative luminance of an 8-bit color, 0 to 1.
+fn luminance(color: Color) -> f32 {
+    (0.2126 * color.r as f32 + 0.7152 * color.g as f32 + 0.0722 * color.b as f32) / 255.0
+}
+
+/// Mean luminance over a grid of pixels across the frame, leaving out the black of
+/// space so a lone planet isn't blown out to light up the background.
+fn average_luminance(framebuffer: &mut Framebuffer) -> Option<f32> {
+    let mut sum = 0.0;
+    let mut count = 0;
+    for j in 0..METER_GRID {
+        for i in 0..METER_GRID {
+            let x = ((i as f32 + 0.5) / METER_GRID as f32 * framebuffer.width as f32) as i32;
+            let y = ((j as f32 + 0.5) / METER_GRID as f32 * framebuffer.height as f32) as i32;
+            if let Some(luma) = framebuffer.color_at(x, y).map(luminance).filter(|&l| l > METER_FLOOR) {
+                sum += luma;
+                count += 1;
+            }
+        }
+    }
+    (count > 0).then(|| sum / count as f32)
+}
+
+/// EV that would put a pixel shown at `displayed` luminance (exposed at `ev`) on middle
+/// grey. Black pixels carry no reading, so they ask for the brightest exposure.
+fn suggested_ev(ev: f32, displayed: f32) -> f32 {
+    if displayed <= 0.0 {
+        return EV_MAX;
+    }
+    (ev + (MIDDLE_GREY / displayed).log2()).clamp(EV_MIN, EV_MAX)
+}
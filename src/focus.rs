@@ -0,0 +1,82 @@
+#![allow(dead_code)]
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::input::InputState;
+
+/// Frames per second while the window is minimized, or out of focus when throttled.
+pub const BACKGROUND_FPS: f32 = 10.0;
+/// Longest frame time of the first frame after coming back to the window, so the orbits
+/// and the camera pick up where they were instead of jumping.
+const RESUME_DT: f32 = 1.0 / 30.0;
+
+/// Tracks whether the window is minimized or out of focus, from each frame's input, so
+/// frames nobody sees cost next to nothing.
+pub struct WindowFocus {
+    focused: bool,
+    minimized: bool,
+}
+
+impl WindowFocus {
+    pub fn new() -> Self {
+        WindowFocus { focused: true, minimized: false }
+    }
+
+    /// Takes this frame's window state. On the frame the window comes back (restored or
+    /// focused again) the frame time is clamped, whatever the window manager stalled for.
+    pub fn update(&mut self, input: &mut InputState) {
+        let back = (input.focused && !self.focused) || (!input.minimized && self.minimized);
+        if back {
+            input.dt = input.dt.min(RESUME_DT);
+        }
+        self.focused = input.focused;
+        self.minimized = input.minimized;
+    }
+
+    /// Whether this frame is worth drawing: a minimized window shows nothing.
+    pub fn draws(&self) -> bool {
+        !self.minimized
+    }
+
+    /// Sleeps out the rest of a background frame that began at `frame_start`: always when
+    /// minimized, and when out of focus if `throttle_unfocused` (a video wall's other
+    /// windows are never focused but still on show, so they keep their full rate).
+    pub fn idle(&self, frame_start: Instant, throttle_unfocused: bool) {
+        if self.minimized || (!self.focused && throttle_unfocused) {
+            let frame = Duration::from_secs_f32(1.0 / BACKGROUND_FPS);
+            if let Some(rest) = frame.checked_sub(frame_start.elapsed()) {
+                thread::sleep(rest);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coming_back_clamps_only_the_first_frame() {
+        let mut focus = WindowFocus::new();
+        let frame = |dt: f32, focused: bool, minimized: bool| {
+            let mut input = InputState::default();
+            (input.dt, input.focused, input.minimized) = (dt, focused, minimized);
+            input
+        };
+
+        let mut away = frame(0.1, false, true);
+        focus.update(&mut away);
+        assert!(!focus.draws());
+        assert_eq!(away.dt, 0.1, "time keeps going while minimized");
+
+        let mut back = frame(2.5, true, false);
+        focus.update(&mut back);
+        assert!(focus.draws());
+        assert_eq!(back.dt, RESUME_DT);
+
+        let mut next = frame(0.05, true, false);
+        focus.update(&mut next);
+        assert_eq!(next.dt, 0.05);
+    }
+}
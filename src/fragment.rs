@@ -6,6 +6,12 @@ pub struct Fragment {
     pub position: Vector2,
     pub color: Vector3,
     pub depth: f32,
+    pub normal: Vector3,         // Interpolated world-space normal
+    pub world_position: Vector3,
+    pub tex_coords: Vector2, // Interpolated vertex tex_coords
+    pub uv: Vector2,    // Spherical surface coordinates
+    pub duvdx: Vector2, // Change in uv one pixel to the right
+    pub duvdy: Vector2, // Change in uv one pixel down
 }
 
 impl Fragment {
@@ -14,6 +20,12 @@ impl Fragment {
             position: Vector2::new(x, y),
             color,
             depth,
+            normal: Vector3::new(0.0, 0.0, 0.0),
+            world_position: Vector3::new(0.0, 0.0, 0.0),
+            tex_coords: Vector2::new(0.0, 0.0),
+            uv: Vector2::new(0.0, 0.0),
+            duvdx: Vector2::new(0.0, 0.0),
+            duvdy: Vector2::new(0.0, 0.0),
         }
     }
 }
\ No newline at end of file
@@ -1,12 +1,147 @@
+use std::path::Path;
+use std::time::Instant;
+
 use raylib::prelude::*;
 
+use crate::depthrange::DepthMode;
+use crate::error::AppError;
+use crate::passes::ClearTarget;
+use crate::ui::draw_screen_text;
+
+/// Something the analytic renderers can draw blended, depth-tested pixels into:
+/// the framebuffer itself or an offscreen sprite.
+pub trait PixelTarget {
+    fn size(&self) -> (u32, u32);
+    fn blend_point(&mut self, x: i32, y: i32, color: Vector3, alpha: f32, depth: f32) -> bool;
+
+    /// Depth-tested opaque point.
+    fn point(&mut self, x: i32, y: i32, color: Vector3, depth: f32) -> bool {
+        self.blend_point(x, y, color, 1.0, depth)
+    }
+}
+
+/// The pixels `Framebuffer::smear_objects` changes, as (index, color), from the frame's
+/// `colors` and the object `ids` drawn into it, `width` pixels to a row.
+fn smeared_pixels(colors: &[Color], ids: &[u16], width: i32, velocities: &[Vector2], taps: u32) -> Vec<(usize, Color)> {
+    let height = colors.len() as i32 / width;
+    let taps = taps.max(2);
+    let mut smeared = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            let index = (y * width + x) as usize;
+            let id = ids[index];
+            let Some(&velocity) = velocities.get(id as usize).filter(|_| id != 0) else {
+                continue;
+            };
+            if velocity.x == 0.0 && velocity.y == 0.0 {
+                continue;
+            }
+            let mut sum = Vector3::zero();
+            let mut total = 0.0;
+            for k in 0..taps {
+                let t = k as f32 / (taps - 1) as f32;
+                let (sx, sy) = ((x as f32 - velocity.x * t).round() as i32, (y as f32 - velocity.y * t).round() as i32);
+                if sx < 0 || sy < 0 || sx >= width || sy >= height {
+                    continue;
+                }
+                let sample = (sy * width + sx) as usize;
+                let other = ids[sample];
+                if other != id && other != 0 {
+                    continue;
+                }
+                let weight = 1.0 - 0.5 * t;
+                let c = colors[sample];
+                sum += Vector3::new(c.r as f32, c.g as f32, c.b as f32) * weight;
+                total += weight;
+            }
+            let c = sum / total;
+            smeared.push((index, Color::new(c.x.round() as u8, c.y.round() as u8, c.z.round() as u8, 255)));
+        }
+    }
+    smeared
+}
+
+/// How a target tests fragments against its depth buffer and writes to it. The
+/// framebuffer and the offscreen targets all go through `check`, so they agree.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DepthTest {
+    pub mode: DepthMode,
+    pub test: bool,  // Compare against the stored depth
+    pub write: bool, // Store the depth of the fragments drawn
+}
+
+impl DepthTest {
+    pub fn new(mode: DepthMode) -> Self {
+        DepthTest { mode, test: true, write: true }
+    }
+
+    /// None when a fragment at `depth` covering `alpha` of the pixel is hidden behind
+    /// `stored`; else whether it writes its depth. Only mostly-covered pixels do, so
+    /// partial edges don't hide geometry drawn behind them later.
+    pub fn check(self, depth: f32, stored: f32, alpha: f32) -> Option<bool> {
+        if self.test && !self.mode.closer(depth, stored) {
+            return None;
+        }
+        Some(self.write && alpha >= 0.5)
+    }
+}
+
+/// A target the screen-space glows (the zodiacal dust) add light to and world labels are
+/// written into, tested by hand against what's already in its depth buffer: the
+/// framebuffer itself or an offscreen render.
+pub trait GlowTarget: PixelTarget {
+    /// Depth stored at (x, y); the cleared value off the target.
+    fn depth_at(&self, x: i32, y: i32) -> f32;
+    fn depth_mode(&self) -> DepthMode;
+    /// Adds `color` to the pixel, with no depth test or write.
+    fn add_color(&mut self, x: i32, y: i32, color: Vector3);
+    /// Replaces the pixel with `color`, with no depth test or write.
+    fn set_color(&mut self, x: i32, y: i32, color: Vector3);
+}
+
+/// 8×8 ordered-dither (Bayer) matrix: each value is a rank in 0..64.
+const BAYER_8X8: [u8; 64] = [
+     0, 32,  8, 40,  2, 34, 10, 42,
+    48, 16, 56, 24, 50, 18, 58, 26,
+    12, 44,  4, 36, 14, 46,  6, 38,
+    60, 28, 52, 20, 62, 30, 54, 22,
+     3, 35, 11, 43,  1, 33,  9, 41,
+    51, 19, 59, 27, 49, 17, 57, 25,
+    15, 47,  7, 39, 13, 45,  5, 37,
+    63, 31, 55, 23, 61, 29, 53, 21,
+];
+
+/// Converts a channel in 0..255 (as f32) to u8, adding `threshold` (in [0, 1)) before truncating.
+fn quantize(value: f32, threshold: f32) -> u8 {
+    (value + threshold).clamp(0.0, 255.0) as u8
+}
+
+/// Still frames averaged at most; later ones replace the oldest gradually.
+const MAX_ACCUMULATED: u32 = 64;
+
 pub struct Framebuffer {
-    pub width: u32,
+    pub width: u32,  // Internal render resolution
     pub height: u32,
+    pub output_width: u32, // Window size the image is upscaled to on present
+    pub output_height: u32,
     image: Image,
     background_color: Vector3,
+    background_gradient: Option<(Vector3, Vector3)>, // Top and bottom colors, lerped by row, instead of the flat color
+    color_clear_cost: Option<f32>, // Seconds the last color clear took at this size
     texture: Option<Texture2D>,
     depth_buffer: Vec<f32>,
+    object_ids: Vec<u16>, // Which object wrote each pixel; 0 = background
+    writes: Vec<u16>,     // Color writes to each pixel this frame, for the overdraw view
+    history: Option<Vec<Color>>, // Accumulated previous frames, for motion blur
+    accumulation: Vec<Vector3>, // Running average of still frames, for antialiasing
+    accumulated: u32,           // Frames in that average
+    current_object: u16,  // Id written by point/blend_point
+    depth_test: bool,     // Whether point/blend_point compare against the depth buffer
+    depth_write: bool,    // Whether they write depth (and the object id)
+    pub dither: bool,     // Ordered dithering when colors are quantized to 8 bits
+    pub exposure: f32,    // Scale applied to colors before they are clamped and quantized (2^EV)
+    pub depth_mode: DepthMode, // Classic or reverse-Z: what the depth buffer clears to and which depth wins
+    pub show_fps: bool,
 }
 
 impl Framebuffer {
@@ -17,28 +152,282 @@ impl Framebuffer {
         Framebuffer {
             width,
             height,
+            output_width: width,
+            output_height: height,
             image,
             background_color: Vector3::zero(),
+            background_gradient: None,
+            color_clear_cost: None,
             texture: None,
             depth_buffer,
+            object_ids: vec![0; buffer_size],
+            writes: vec![0; buffer_size],
+            current_object: 0,
+            depth_test: true,
+            depth_write: true,
+            history: None,
+            accumulation: Vec::new(),
+            accumulated: 0,
+            depth_mode: DepthMode::Classic,
+            show_fps: true,
+            dither: true,
+            exposure: 1.0,
+        }
+    }
+
+    /// Rounding threshold for quantizing at (x, y): a Bayer pattern spanning exactly one
+    /// output step when dithering, so smooth gradients don't band; plain truncation otherwise.
+    fn threshold(&self, x: i32, y: i32) -> f32 {
+        if self.dither {
+            (BAYER_8X8[((y & 7) * 8 + (x & 7)) as usize] as f32 + 0.5) / 64.0
+        } else {
+            0.0
         }
     }
 
+    fn to_color(&self, x: i32, y: i32, color: Vector3) -> Color {
+        let t = self.threshold(x, y);
+        let color = color * self.exposure;
+        Color::new(
+            quantize(color.x.clamp(0.0, 1.0) * 255.0, t),
+            quantize(color.y.clamp(0.0, 1.0) * 255.0, t),
+            quantize(color.z.clamp(0.0, 1.0) * 255.0, t),
+            255,
+        )
+    }
+
     pub fn init_texture(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread) {
-        self.texture = Some(rl.load_texture_from_image(thread, &self.image).unwrap());
+        let mut texture = rl.load_texture_from_image(thread, &self.image).unwrap();
+        texture.set_texture_filter(thread, TextureFilter::TEXTURE_FILTER_BILINEAR);
+        self.texture = Some(texture);
+    }
+
+    /// Sets the window size and the fraction of it the 3D pass renders at.
+    /// Reallocates the buffers (and later the texture) only when the internal size changes.
+    pub fn resize(&mut self, output_width: u32, output_height: u32, render_scale: f32) {
+        self.output_width = output_width.max(1);
+        self.output_height = output_height.max(1);
+        let width = ((self.output_width as f32 * render_scale).round() as u32).max(1);
+        let height = ((self.output_height as f32 * render_scale).round() as u32).max(1);
+        if width == self.width && height == self.height {
+            return;
+        }
+
+        self.width = width;
+        self.height = height;
+        self.image = Image::gen_image_color(width as i32, height as i32, Color::BLACK);
+        self.depth_buffer = vec![self.depth_mode.cleared(); (width * height) as usize];
+        self.object_ids = vec![0; (width * height) as usize];
+        self.writes = vec![0; (width * height) as usize];
+        self.history = None;
+        self.accumulated = 0;
+        self.color_clear_cost = None;
+        self.texture = None;
     }
 
+    /// Clears the color and everything else; see `clear_color` and `clear_depth`.
     pub fn clear(&mut self) {
-        let bg_color = Color::new(
-            (self.background_color.x * 255.0) as u8,
-            (self.background_color.y * 255.0) as u8,
-            (self.background_color.z * 255.0) as u8,
-            255,
-        );
-        self.image.clear_background(bg_color);
+        self.clear_color();
+        self.clear_depth();
+    }
+
+    /// Fills the color buffer with the background: the flat color, or the gradient when
+    /// one is set. Timed, so a frame that skips it knows what it saved.
+    pub fn clear_color(&mut self) {
+        let start = Instant::now();
+        let flat = |c: Vector3| Color::new((c.x * 255.0) as u8, (c.y * 255.0) as u8, (c.z * 255.0) as u8, 255);
+        match self.background_gradient {
+            Some((top, bottom)) => {
+                let rows = self.height as i32;
+                for y in 0..rows {
+                    let t = y as f32 / (rows - 1).max(1) as f32;
+                    self.image.draw_rectangle(0, y, self.width as i32, 1, flat(top + (bottom - top) * t));
+                }
+            }
+            None => self.image.clear_background(flat(self.background_color)),
+        }
+        self.color_clear_cost = Some(start.elapsed().as_secs_f32());
+    }
+
+    /// Seconds the color clear took the last time it ran at this size, or None if it
+    /// hasn't yet.
+    pub fn color_clear_cost(&self) -> Option<f32> {
+        self.color_clear_cost
+    }
+
+    /// Clears depth to the far plane, the object ids and the overdraw counts, leaving the
+    /// color for a pass that covers every pixel anyway.
+    pub fn clear_depth(&mut self) {
+        self.depth_buffer.fill(self.depth_mode.cleared());
+        self.object_ids.fill(0);
+        self.writes.fill(0);
+        self.current_object = 0;
+    }
+
+    /// NDC depth of the closest surface drawn at (x, y); the cleared value (infinity, or
+    /// 0 in reverse-Z) if nothing was drawn.
+    pub fn depth_at(&self, x: i32, y: i32) -> f32 {
+        if x >= 0 && y >= 0 && x < self.width as i32 && y < self.height as i32 {
+            self.depth_buffer[(y * self.width as i32 + x) as usize]
+        } else {
+            self.depth_mode.cleared()
+        }
+    }
+
+    /// Adds `color` to the pixel (additive blending, no depth test or write), for glows.
+    pub fn add_color(&mut self, x: i32, y: i32, color: Vector3) {
+        if x >= 0 && y >= 0 && x < self.width as i32 && y < self.height as i32 {
+            let dst = self.image.get_color(x, y);
+            let t = self.threshold(x, y);
+            let add = |src: f32, dst: u8| quantize(dst as f32 + src.max(0.0) * 255.0, t);
+            let pixel_color = Color::new(add(color.x, dst.r), add(color.y, dst.g), add(color.z, dst.b), 255);
+            self.image.draw_pixel(x, y, pixel_color);
+            self.count_write(x, y);
+        }
+    }
+
+    /// Depth-tested additive point that leaves the depth buffer untouched, for glowing
+    /// translucent effects drawn after the opaque scene.
+    pub fn add_point(&mut self, x: i32, y: i32, color: Vector3, depth: f32) {
+        if self.depth_mode.closer(depth, self.depth_at(x, y)) {
+            self.add_color(x, y, color);
+        }
+    }
+
+    /// Accumulation-buffer motion blur: blends the previous (already blurred) frames,
+    /// shifted by `shift` pixels along the screen-space camera motion, into the current
+    /// image with weight `amount`, then keeps the result as the new history.
+    pub fn motion_blur(&mut self, shift: Vector2, amount: f32) {
+        let current = self.image.get_image_data().to_vec();
+        let amount = amount.clamp(0.0, 0.95);
+        let (width, height) = (self.width as i32, self.height as i32);
+        let (dx, dy) = (shift.x.round() as i32, shift.y.round() as i32);
+
+        let blended = match &self.history {
+            Some(history) if history.len() == current.len() => {
+                let mut blended = current.clone();
+                for y in 0..height {
+                    for x in 0..width {
+                        let (sx, sy) = ((x - dx).clamp(0, width - 1), (y - dy).clamp(0, height - 1));
+                        let old = history[(sy * width + sx) as usize];
+                        let new = &mut blended[(y * width + x) as usize];
+                        let mix = |a: u8, b: u8| (a as f32 * (1.0 - amount) + b as f32 * amount) as u8;
+                        *new = Color::new(mix(new.r, old.r), mix(new.g, old.g), mix(new.b, old.b), 255);
+                    }
+                }
+                for y in 0..height {
+                    for x in 0..width {
+                        self.image.draw_pixel(x, y, blended[(y * width + x) as usize]);
+                    }
+                }
+                blended
+            }
+            _ => current,
+        };
+        self.history = Some(blended);
+    }
+
+    /// Per-object motion blur: every pixel of object `id` becomes a weighted average of
+    /// `taps` samples trailing behind it along `velocities[id]` (in pixels, zero for none),
+    /// heavier near the pixel. Samples on other objects are skipped so no body smears onto
+    /// another; samples on the background let a moving edge thin out against the sky.
+    pub fn smear_objects(&mut self, velocities: &[Vector2], taps: u32) {
+        if !velocities.iter().any(|v| v.x != 0.0 || v.y != 0.0) {
+            return;
+        }
+        let current = self.image.get_image_data().to_vec();
+        for (index, color) in smeared_pixels(&current, &self.object_ids, self.width as i32, velocities, taps) {
+            let (x, y) = (index as i32 % self.width as i32, index as i32 / self.width as i32);
+            self.image.draw_pixel(x, y, color);
+        }
+    }
+
+    /// Averages this frame into the still frames before it and shows the average. Past
+    /// `MAX_ACCUMULATED` frames the oldest ones fade out instead of the weights shrinking forever.
+    pub fn accumulate(&mut self) {
+        let current = self.image.get_image_data().to_vec();
+        if self.accumulated == 0 || self.accumulation.len() != current.len() {
+            self.accumulation = current.iter().map(|c| Vector3::new(c.r as f32, c.g as f32, c.b as f32)).collect();
+            self.accumulated = 1;
+            return;
+        }
+
+        self.accumulated = (self.accumulated + 1).min(MAX_ACCUMULATED);
+        let weight = 1.0 / self.accumulated as f32;
+        let width = self.width as i32;
+        for (i, c) in current.iter().enumerate() {
+            let average = &mut self.accumulation[i];
+            *average += (Vector3::new(c.r as f32, c.g as f32, c.b as f32) - *average) * weight;
+            let color = Color::new(average.x.round() as u8, average.y.round() as u8, average.z.round() as u8, 255);
+            self.image.draw_pixel(i as i32 % width, i as i32 / width, color);
+        }
+    }
+
+    /// Starts the average over from the next frame.
+    pub fn reset_accumulation(&mut self) {
+        self.accumulated = 0;
+    }
+
+    /// Frames in the current average; 0 right after a reset.
+    pub fn accumulated_frames(&self) -> u32 {
+        self.accumulated
+    }
+
+    /// Copy of the color buffer, row by row.
+    pub fn colors(&self) -> Vec<Color> {
+        self.image.get_image_data().to_vec()
+    }
 
-        // Clear depth buffer to far plane
-        self.depth_buffer.fill(f32::INFINITY);
+    /// The 8-bit color at (x, y), or `None` off the image.
+    pub fn color_at(&mut self, x: i32, y: i32) -> Option<Color> {
+        (x >= 0 && y >= 0 && x < self.width as i32 && y < self.height as i32).then(|| self.image.get_color(x, y))
+    }
+
+    /// Replaces the color buffer with `colors` (as returned by `colors`), leaving depth alone.
+    pub fn set_colors(&mut self, colors: &[Color]) {
+        let width = self.width as i32;
+        for (i, color) in colors.iter().enumerate() {
+            self.image.draw_pixel(i as i32 % width, i as i32 / width, *color);
+        }
+    }
+
+    /// Forgets the motion blur history so no ghost of earlier frames survives.
+    pub fn reset_history(&mut self) {
+        self.history = None;
+    }
+
+    /// Sets the id stored for the pixels drawn from now on (0 = background).
+    pub fn set_object_id(&mut self, id: u16) {
+        self.current_object = id;
+    }
+
+    /// Depth state used by point/blend_point until changed; both on by default.
+    pub fn set_depth_mode(&mut self, test: bool, write: bool) {
+        self.depth_test = test;
+        self.depth_write = write;
+    }
+
+    fn depth(&self) -> DepthTest {
+        DepthTest { mode: self.depth_mode, test: self.depth_test, write: self.depth_write }
+    }
+
+    /// Id of the object that drew the visible pixel at (x, y), or 0 for background / out of bounds.
+    pub fn object_id_at(&self, x: i32, y: i32) -> u16 {
+        if x >= 0 && y >= 0 && x < self.width as i32 && y < self.height as i32 {
+            self.object_ids[(y * self.width as i32 + x) as usize]
+        } else {
+            0
+        }
+    }
+
+    /// Replaces the color buffer with one distinct color per object id, for debugging.
+    pub fn show_object_ids<F: Fn(u16) -> Vector3>(&mut self, id_color: F) {
+        for y in 0..self.height as i32 {
+            for x in 0..self.width as i32 {
+                let color = id_color(self.object_id_at(x, y));
+                self.set_pixel(x, y, color);
+            }
+        }
     }
 
     pub fn point(&mut self, x: i32, y: i32, color: Vector3, depth: f32) -> bool {
@@ -46,28 +435,92 @@ impl Framebuffer {
             let index = (y * self.width as i32 + x) as usize;
 
             // Depth test: only draw if this fragment is closer
-            if depth < self.depth_buffer[index] {
-                self.depth_buffer[index] = depth;
-
-                let pixel_color = Color::new(
-                    (color.x.clamp(0.0, 1.0) * 255.0) as u8,
-                    (color.y.clamp(0.0, 1.0) * 255.0) as u8,
-                    (color.z.clamp(0.0, 1.0) * 255.0) as u8,
-                    255,
-                );
+            if let Some(write) = self.depth().check(depth, self.depth_buffer[index], 1.0) {
+                if write {
+                    self.depth_buffer[index] = depth;
+                    self.object_ids[index] = self.current_object;
+                }
+
+                let pixel_color = self.to_color(x, y, color);
                 self.image.draw_pixel(x, y, pixel_color);
+                self.writes[index] = self.writes[index].saturating_add(1);
                 return true;
             }
         }
         false
     }
 
+    /// Writes a pixel without depth testing, for overlays drawn after the scene.
+    pub fn set_pixel(&mut self, x: i32, y: i32, color: Vector3) {
+        if x >= 0 && y >= 0 && x < self.width as i32 && y < self.height as i32 {
+            let pixel_color = self.to_color(x, y, color);
+            self.image.draw_pixel(x, y, pixel_color);
+            self.count_write(x, y);
+        }
+    }
+
+    fn count_write(&mut self, x: i32, y: i32) {
+        let index = (y * self.width as i32 + x) as usize;
+        self.writes[index] = self.writes[index].saturating_add(1);
+    }
+
+    /// Color writes per pixel this frame, averaged over the whole frame (the sky counts).
+    pub fn average_overdraw(&self) -> f32 {
+        self.writes.iter().map(|&w| w as u64).sum::<u64>() as f32 / self.writes.len().max(1) as f32
+    }
+
+    /// Replaces the color buffer with each pixel's write count mapped through `heat`,
+    /// for finding overdraw. Written as is, without exposure or dithering.
+    pub fn show_overdraw<F: Fn(u16) -> Vector3>(&mut self, heat: F) {
+        for y in 0..self.height as i32 {
+            for x in 0..self.width as i32 {
+                let c = heat(self.writes[(y * self.width as i32 + x) as usize]) * 255.0;
+                self.image.draw_pixel(x, y, Color::new(c.x as u8, c.y as u8, c.z as u8, 255));
+            }
+        }
+    }
+
+    /// Writes the color buffer as `color.png` and the depth buffer as `depth.pgm`
+    /// (8-bit, near = black, far or empty = white) into `dir`.
+    pub fn export(&self, dir: &Path) -> Result<(), AppError> {
+        let color_path = dir.join("color.png");
+        if !self.image.export_image(&color_path.to_string_lossy()) {
+            return Err(AppError::ImageWrite { path: color_path.display().to_string() });
+        }
+
+        let mut depth = format!("P5\n{} {}\n255\n", self.width, self.height).into_bytes();
+        depth.extend(self.depth_buffer.iter().map(|&d| {
+            if self.depth_mode.is_background(d) {
+                255
+            } else {
+                (self.depth_mode.normalized(d) * 255.0) as u8
+            }
+        }));
+        let depth_path = dir.join("depth.pgm");
+        std::fs::write(&depth_path, depth).map_err(|_| AppError::ImageWrite { path: depth_path.display().to_string() })
+    }
+
     pub fn set_background_color(&mut self, color: Vector3) {
         self.background_color = color;
     }
 
-    pub fn swap_buffers(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread) {
+    /// Clears to a vertical gradient from `top` to `bottom` instead of the flat color;
+    /// None goes back to the flat color.
+    pub fn set_background_gradient(&mut self, gradient: Option<(Vector3, Vector3)>) {
+        self.background_gradient = gradient;
+    }
+
+    /// Uploads the image and presents it. `overlay` runs inside the draw pass so
+    /// callers can put raylib UI (text, panels) on top of the rendered frame.
+    pub fn swap_buffers<F>(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, overlay: F)
+    where
+        F: FnOnce(&mut RaylibDrawHandle),
+    {
         let fps = rl.get_fps();
+        if self.texture.is_none() {
+            // Dropped by resize(); recreate at the new internal size
+            self.init_texture(rl, thread);
+        }
         if let Some(texture) = &mut self.texture {
             let colors = self.image.get_image_data();
             // Safely cast the &[Color] slice to a &[u8] slice for the update function
@@ -81,10 +534,129 @@ impl Framebuffer {
 
             let mut d = rl.begin_drawing(thread);
             d.clear_background(Color::BLACK);
-            d.draw_texture(texture, 0, 0, Color::WHITE);
-            d.draw_text(&format!("FPS: {}", fps), 10, 10, 20, Color::PURPLE);
+            // Upscale the internal image to the window (bilinear filtered)
+            d.draw_texture_pro(
+                &*texture,
+                Rectangle::new(0.0, 0.0, self.width as f32, self.height as f32),
+                Rectangle::new(0.0, 0.0, self.output_width as f32, self.output_height as f32),
+                Vector2::zero(),
+                0.0,
+                Color::WHITE,
+            );
+            // Screen UI from here on: drawn over the presented frame, never hidden by the scene
+            if self.show_fps {
+                draw_screen_text(&mut d, &format!("FPS: {}", fps), 10, 10, 20, Color::PURPLE);
+            }
+            overlay(&mut d);
         } else {
             panic!("Framebuffer texture has not been initialized. Call init_texture after creating the RaylibHandle.");
         }
     }
-}
\ No newline at end of file
+}
+
+impl PixelTarget for Framebuffer {
+    fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Skips reading the pixel back, unlike a blend at full alpha.
+    fn point(&mut self, x: i32, y: i32, color: Vector3, depth: f32) -> bool {
+        Framebuffer::point(self, x, y, color, depth)
+    }
+
+    /// Depth-tested point blended over the existing pixel by `alpha` (coverage).
+    /// Depth is only written for mostly-covered pixels so partial edges don't
+    /// hide geometry drawn behind them later.
+    fn blend_point(&mut self, x: i32, y: i32, color: Vector3, alpha: f32, depth: f32) -> bool {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 || alpha <= 0.0 {
+            return false;
+        }
+        let index = (y * self.width as i32 + x) as usize;
+        let Some(write) = self.depth().check(depth, self.depth_buffer[index], alpha) else {
+            return false;
+        };
+        if write {
+            self.depth_buffer[index] = depth;
+            self.object_ids[index] = self.current_object;
+        }
+
+        let alpha = alpha.min(1.0);
+        let dst = self.image.get_color(x, y);
+        let t = self.threshold(x, y);
+        let blend = |src: f32, dst: u8| {
+            let src = (src * self.exposure).clamp(0.0, 1.0) * 255.0;
+            quantize(src * alpha + dst as f32 * (1.0 - alpha), t)
+        };
+        let pixel_color = Color::new(blend(color.x, dst.r), blend(color.y, dst.g), blend(color.z, dst.b), 255);
+        self.image.draw_pixel(x, y, pixel_color);
+        self.writes[index] = self.writes[index].saturating_add(1);
+        true
+    }
+}
+
+impl GlowTarget for Framebuffer {
+    fn depth_at(&self, x: i32, y: i32) -> f32 {
+        Framebuffer::depth_at(self, x, y)
+    }
+
+    fn depth_mode(&self) -> DepthMode {
+        self.depth_mode
+    }
+
+    fn add_color(&mut self, x: i32, y: i32, color: Vector3) {
+        Framebuffer::add_color(self, x, y, color)
+    }
+
+    fn set_color(&mut self, x: i32, y: i32, color: Vector3) {
+        Framebuffer::set_pixel(self, x, y, color)
+    }
+}
+
+impl ClearTarget for Framebuffer {
+    fn clear_depth(&mut self) {
+        Framebuffer::clear_depth(self)
+    }
+
+    fn clear_color(&mut self) {
+        Framebuffer::clear_color(self)
+    }
+
+    fn color_clear_cost(&self) -> Option<f32> {
+        Framebuffer::color_clear_cost(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smeared_bodies_stay_off_other_bodies() {
+        // A row: red body 1 moving right, then blue body 2 standing still, then the sky
+        let width = 12;
+        let ids: Vec<u16> = (0..width).map(|x| match x { 2..=5 => 1, 6..=8 => 2, _ => 0 }).collect();
+        let color = |id: u16| match id { 1 => Color::new(255, 0, 0, 255), 2 => Color::new(0, 0, 255, 255), _ => Color::new(0, 0, 0, 255) };
+        let colors: Vec<Color> = ids.iter().map(|&id| color(id)).collect();
+        let velocities = [Vector2::zero(), Vector2::new(6.0, 0.0), Vector2::zero()];
+
+        let smeared = smeared_pixels(&colors, &ids, width, &velocities, 8);
+        assert!(!smeared.is_empty());
+        for (index, c) in smeared {
+            assert_eq!(ids[index], 1, "pixel {} isn't body 1's", index);
+            assert_eq!(c.b, 0, "pixel {} took blue from the body beside it", index);
+        }
+    }
+
+    #[test]
+    fn depth_test_keeps_the_nearer_fragment_and_only_solid_ones_write() {
+        for (mode, near, far) in [(DepthMode::Classic, 0.2, 0.6), (DepthMode::Reverse, 0.6, 0.2)] {
+            let test = DepthTest::new(mode);
+            assert_eq!(test.check(near, mode.cleared(), 1.0), Some(true));
+            assert_eq!(test.check(near, far, 0.3), Some(false), "a thin edge draws but leaves the depth alone");
+            assert_eq!(test.check(far, near, 1.0), None);
+
+            assert_eq!(DepthTest { test: false, ..test }.check(far, near, 1.0), Some(true));
+            assert_eq!(DepthTest { write: false, ..test }.check(near, far, 1.0), Some(false));
+        }
+    }
+}
@@ -0,0 +1,123 @@
+#![allow(dead_code)]
+
+use raylib::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::scene::RingConfig;
+
+/// Sphere mesh used by generated bodies that don't name one.
+pub const GENERATED_MESH: &str = "assets/objects/sphere.obj";
+
+/// Families of surface the generic planet shader can produce. A body whose `kind`
+/// is one of these is generated from its parameters instead of a bespoke shader.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlanetKind {
+    Rocky,
+    Gas,
+    Ice,
+    Lava,
+}
+
+impl PlanetKind {
+    pub fn from_kind(kind: &str) -> Option<Self> {
+        match kind {
+            "rocky" => Some(PlanetKind::Rocky),
+            "gas" => Some(PlanetKind::Gas),
+            "ice" => Some(PlanetKind::Ice),
+            "lava" => Some(PlanetKind::Lava),
+            _ => None,
+        }
+    }
+}
+
+/// Generator settings of a body, written straight into its `[[body]]` table. Anything
+/// left out takes the kind's default.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlanetConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub palette: Option<[[f32; 3]; 3]>, // Low, mid and high colors of the surface
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub band_freq: Option<f32>, // Latitude bands around the planet (gas giants, faint strata on rock)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub noise_scale: Option<f32>, // Size of the terrain or swirl features; higher is finer
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crater_density: Option<f32>, // Share of surface cells with a crater, 0 to 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cloud_amount: Option<f32>, // Cloud cover, 0 to 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>, // Fixed noise seed instead of one from the scene seed and the name
+}
+
+/// Resolved inputs of the generic planet shader.
+///
+/// The bespoke planets can be approximated with, roughly:
+/// - `rocky1`: rocky, palette orange to cream, crater_density 0.3
+/// - `rocky2`: lava with a white-grey palette high end
+/// - `gassy1`: gas, band_freq 14 (the default)
+/// - `gassy2`: gas, lilac palette, band_freq 10, noise_scale 1.5, cloud_amount 0
+/// - `gassy3`: gas, cyan palette, band_freq 12
+/// - `moon`: rocky, grey palette, crater_density 0.5
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlanetParams {
+    pub kind: PlanetKind,
+    pub base_palette: [Vector3; 3],
+    pub band_freq: f32,
+    pub noise_scale: f32,
+    pub crater_density: f32,
+    pub cloud_amount: f32,
+    pub ring: Option<RingConfig>, // Ring added around the planet, as its own body
+    pub seed: Option<u64>,
+}
+
+impl PlanetParams {
+    /// The scene-file settings that resolve back to these parameters.
+    pub fn to_config(&self) -> PlanetConfig {
+        PlanetConfig {
+            palette: Some(self.base_palette.map(|c| [c.x, c.y, c.z])),
+            band_freq: Some(self.band_freq),
+            noise_scale: Some(self.noise_scale),
+            crater_density: Some(self.crater_density),
+            cloud_amount: Some(self.cloud_amount),
+            seed: self.seed,
+        }
+    }
+
+    /// Defaults for a kind, before the body's overrides.
+    pub fn for_kind(kind: PlanetKind) -> Self {
+        let (palette, band_freq, noise_scale, crater_density, cloud_amount) = match kind {
+            PlanetKind::Rocky => ([[0.35, 0.25, 0.2], [0.6, 0.45, 0.32], [0.85, 0.75, 0.6]], 0.0, 4.0, 0.35, 0.0),
+            PlanetKind::Gas => ([[0.5, 0.38, 0.28], [0.85, 0.72, 0.55], [0.97, 0.93, 0.85]], 14.0, 3.0, 0.0, 0.3),
+            PlanetKind::Ice => ([[0.45, 0.6, 0.75], [0.75, 0.85, 0.92], [0.96, 0.98, 1.0]], 0.0, 5.0, 0.15, 0.1),
+            PlanetKind::Lava => ([[0.08, 0.05, 0.05], [0.25, 0.12, 0.08], [1.0, 0.45, 0.08]], 0.0, 4.0, 0.1, 0.0),
+        };
+        PlanetParams {
+            kind,
+            base_palette: palette.map(|c| Vector3::new(c[0], c[1], c[2])),
+            band_freq,
+            noise_scale,
+            crater_density,
+            cloud_amount,
+            ring: None,
+            seed: None,
+        }
+    }
+
+    /// Parameters of a body of `kind`, or `None` when it isn't a generated kind.
+    pub fn resolve(kind: &str, config: &PlanetConfig, ring: Option<&RingConfig>) -> Option<Self> {
+        let default = PlanetParams::for_kind(PlanetKind::from_kind(kind)?);
+        Some(PlanetParams {
+            base_palette: config
+                .palette
+                .map(|p| p.map(|c| Vector3::new(c[0], c[1], c[2])))
+                .unwrap_or(default.base_palette),
+            band_freq: config.band_freq.unwrap_or(default.band_freq).max(0.0),
+            noise_scale: config.noise_scale.unwrap_or(default.noise_scale).max(0.1),
+            crater_density: config.crater_density.unwrap_or(default.crater_density).clamp(0.0, 1.0),
+            cloud_amount: config.cloud_amount.unwrap_or(default.cloud_amount).clamp(0.0, 1.0),
+            ring: ring.cloned(),
+            seed: config.seed,
+            ..default
+        })
+    }
+}
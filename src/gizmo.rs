@@ -0,0 +1,93 @@
+#![allow(dead_code)]
+
+use raylib::prelude::*;
+
+use crate::framebuffer::Framebuffer;
+use crate::line::polyline;
+use crate::scene::SceneObject;
+use crate::Uniforms;
+
+/// Key that cycles the normals gizmo: off, normals, normals and tangents.
+pub const GIZMO_KEY: KeyboardKey = KeyboardKey::KEY_F6;
+
+/// Upper bound on the number of normal lines drawn for one object.
+const MAX_LINES: usize = 500;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GizmoMode {
+    Off,
+    Normals,
+    NormalsAndTangents,
+}
+
+impl GizmoMode {
+    pub fn next(self) -> Self {
+        match self {
+            GizmoMode::Off => GizmoMode::Normals,
+            GizmoMode::Normals => GizmoMode::NormalsAndTangents,
+            GizmoMode::NormalsAndTangents => GizmoMode::Off,
+        }
+    }
+}
+
+fn transform_point(m: &Matrix, p: Vector3) -> Vector3 {
+    Vector3::new(
+        m.m0 * p.x + m.m4 * p.y + m.m8 * p.z + m.m12,
+        m.m1 * p.x + m.m5 * p.y + m.m9 * p.z + m.m13,
+        m.m2 * p.x + m.m6 * p.y + m.m10 * p.z + m.m14,
+    )
+}
+
+// Scale is uniform, so the model matrix without translation is fine for directions
+fn transform_direction(m: &Matrix, d: Vector3) -> Vector3 {
+    Vector3::new(
+        m.m0 * d.x + m.m4 * d.y + m.m8 * d.z,
+        m.m1 * d.x + m.m5 * d.y + m.m9 * d.z,
+        m.m2 * d.x + m.m6 * d.y + m.m10 * d.z,
+    )
+    .normalized()
+}
+
+/// Tangent along increasing longitude, the direction `u` grows on the planet maps.
+/// Vertices carry no tangents, so it's derived from the normal; at the poles it falls back to +X.
+fn tangent(normal: Vector3) -> Vector3 {
+    let t = Vector3::new(0.0, 1.0, 0.0).cross(normal);
+    if t.length() < 1e-4 {
+        Vector3::new(1.0, 0.0, 0.0)
+    } else {
+        t.normalized()
+    }
+}
+
+/// Draws a short line along the world-space normal (green-cyan) of every Nth vertex of
+/// `obj`, with N picked so at most `MAX_LINES` are drawn, plus the tangents (magenta)
+/// when asked, and RGB = XYZ axes at the object's origin. Lines go through the full
+/// pipeline and are depth-tested, so inward normals vanish inside the surface.
+pub fn draw_gizmo(framebuffer: &mut Framebuffer, uniforms: &Uniforms, obj: &SceneObject, mode: GizmoMode) {
+    if mode == GizmoMode::Off {
+        return;
+    }
+    let model = obj.model_matrix();
+    let size = obj.radius * obj.scale;
+    let length = size * 0.15;
+
+    let stride = obj.vertices.len().div_ceil(MAX_LINES).max(1);
+    for vertex in obj.vertices.iter().step_by(stride) {
+        let base = transform_point(&model, vertex.position);
+        let normal = transform_direction(&model, vertex.normal);
+        polyline(framebuffer, uniforms, &[base, base + normal * length], Vector3::new(0.2, 1.0, 0.8));
+        if mode == GizmoMode::NormalsAndTangents {
+            let tangent = tangent(normal);
+            polyline(framebuffer, uniforms, &[base, base + tangent * length], Vector3::new(1.0, 0.3, 0.9));
+        }
+    }
+
+    let axes = [
+        (Vector3::new(1.0, 0.0, 0.0), Vector3::new(1.0, 0.2, 0.2)),
+        (Vector3::new(0.0, 1.0, 0.0), Vector3::new(0.2, 1.0, 0.2)),
+        (Vector3::new(0.0, 0.0, 1.0), Vector3::new(0.3, 0.4, 1.0)),
+    ];
+    for (axis, color) in axes {
+        polyline(framebuffer, uniforms, &[obj.translation, obj.translation + axis * size * 1.5], color);
+    }
+}
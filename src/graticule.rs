@@ -0,0 +1,94 @@
+#![allow(dead_code)]
+
+use raylib::prelude::*;
+
+/// Degrees between two lines of latitude, and between two of longitude.
+const SPACING: f32 = 15.0;
+/// Angular radius of the north pole marker, in degrees.
+const POLE_MARKER: f32 = 3.0;
+
+const LINE_COLOR: Vector3 = Vector3 { x: 0.85, y: 0.9, z: 1.0 };
+const LINE_ALPHA: f32 = 0.5;
+const EQUATOR_COLOR: Vector3 = Vector3 { x: 1.0, y: 0.8, z: 0.2 };
+const MERIDIAN_COLOR: Vector3 = Vector3 { x: 0.3, y: 1.0, z: 0.4 };
+const POLE_COLOR: Vector3 = Vector3 { x: 1.0, y: 0.2, z: 0.2 };
+
+/// Draws a latitude/longitude grid over `color` at the surface point `uv` (as from
+/// `spherical_uv`): thin lines every `SPACING` degrees, the equator and the prime meridian
+/// highlighted, and a red dot on the north pole. `duvdx` and `duvdy` are the uv's change
+/// one pixel across and down, so the lines stay about a pixel wide at any distance.
+pub fn graticule(color: Vector3, uv: Vector2, duvdx: Vector2, duvdy: Vector2) -> Vector3 {
+    let lat = (0.5 - uv.y) * 180.0;
+    let lon = (uv.x - 0.5) * 360.0;
+    let lat_width = (duvdx.y.abs() + duvdy.y.abs()) * 180.0;
+    let lon_width = (duvdx.x.abs() + duvdy.x.abs()) * 360.0;
+    // Meridians crowd together towards the poles; fade them out before they fill the cap
+    let meridians = 1.0 - smoothstep(SPACING * 0.15, SPACING * 0.3, lon_width);
+
+    let parallel = line(off_grid(lat), lat_width);
+    let meridian = line(off_grid(lon), lon_width) * meridians;
+    let mut color = mix(color, LINE_COLOR, LINE_ALPHA * parallel.max(meridian));
+    color = mix(color, EQUATOR_COLOR, line(lat.abs(), lat_width));
+    color = mix(color, MERIDIAN_COLOR, line(lon.abs(), lon_width) * meridians);
+
+    let pole = 1.0 - smoothstep(POLE_MARKER - lat_width, POLE_MARKER, 90.0 - lat);
+    mix(color, POLE_COLOR, pole)
+}
+
+/// Degrees from `angle` to the nearest line of the grid.
+fn off_grid(angle: f32) -> f32 {
+    (angle - (angle / SPACING).round() * SPACING).abs()
+}
+
+/// Coverage of a line at `distance` degrees from the pixel, where a pixel spans `width`
+/// degrees: solid within half a pixel, gone a pixel out.
+fn line(distance: f32, width: f32) -> f32 {
+    let width = width.max(1e-6);
+    1.0 - smoothstep(0.5 * width, width, distance)
+}
+
+fn mix(a: Vector3, b: Vector3, t: f32) -> Vector3 {
+    a + (b - a) * t
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GREY: Vector3 = Vector3 { x: 0.2, y: 0.2, z: 0.2 };
+
+    /// Pixels a line of latitude covers down a column crossing it, with `pixel` degrees a
+    /// pixel, counted within 5° of it.
+    fn covered(latitude: f32, pixel: f32) -> usize {
+        let step = Vector2::new(0.0, pixel / 180.0);
+        let reach = (5.0 / pixel) as i32;
+        (-reach..reach)
+            .map(|i| Vector2::new(0.3, 0.5 - (latitude + i as f32 * pixel) / 180.0))
+            .filter(|&uv| graticule(GREY, uv, Vector2::zero(), step) != GREY)
+            .count()
+    }
+
+    #[test]
+    fn lines_fall_every_fifteen_degrees_and_stay_thin() {
+        let pixel = Vector2::new(0.2 / 360.0, 0.2 / 180.0);
+        let at = |lat: f32, lon: f32| graticule(GREY, Vector2::new(0.5 + lon / 360.0, 0.5 - lat / 180.0), pixel, pixel);
+        assert_eq!(at(7.0, 7.0), GREY, "between lines");
+        assert_ne!(at(30.0, 7.0), GREY);
+        assert_ne!(at(7.0, -45.0), GREY);
+        let near = |a: Vector3, b: Vector3| (a - b).length() < 1e-4;
+        assert!(near(at(0.0, 7.0), EQUATOR_COLOR));
+        assert!(near(at(7.0, 0.0), MERIDIAN_COLOR));
+        assert!(near(at(89.5, 7.0), POLE_COLOR));
+        assert!(!near(at(-89.5, 7.0), POLE_COLOR), "only the north pole is marked");
+
+        // A line is one or two pixels wide whether the body is near or far
+        for pixel in [0.01, 0.1, 1.0] {
+            assert!((1..=2).contains(&covered(30.0, pixel)), "{} pixels at {}°/px", covered(30.0, pixel), pixel);
+        }
+    }
+}
@@ -0,0 +1,151 @@
+#![allow(dead_code)]
+
+use raylib::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::framebuffer::Framebuffer;
+use crate::line::segment_fading;
+use crate::matrix::multiply_matrix_vector4;
+use crate::scene::SceneObject;
+use crate::Uniforms;
+use crate::input::InputState;
+
+/// Key that shows and hides the gravity grid.
+pub const GRAVITY_GRID_KEY: KeyboardKey = KeyboardKey::KEY_V;
+
+/// Gravity grid settings, read from the `[gravity_grid]` table of the scene file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GravityGridConfig {
+    pub enabled: bool,     // Shown at startup; V toggles it either way
+    pub extent: f32,       // Half the side of the square grid, centered on the origin
+    pub resolution: usize, // Cells along each side
+    pub depth: f32,        // How far a well sinks per unit of log mass: depth × ln(1 + mass)
+    pub width: f32,        // Radius of a well, in radii of its body
+    pub opacity: f32,      // Opacity of the lines up close
+    pub fade: f32,         // Distance from the camera at which the lines have faded out
+    pub color: [f32; 3],
+}
+
+impl Default for GravityGridConfig {
+    fn default() -> Self {
+        GravityGridConfig {
+            enabled: false,
+            extent: 75.0,
+            resolution: 96,
+            depth: 1.5,
+            width: 2.5,
+            opacity: 0.35,
+            fade: 140.0,
+            color: [0.35, 0.6, 1.0],
+        }
+    }
+}
+
+/// A body's dent in the sheet: center on the plane, depth at the center, squared radius.
+struct Well {
+    x: f32,
+    z: f32,
+    depth: f32,
+    radius_sq: f32,
+}
+
+/// The rubber-sheet picture of gravity: a grid over the ecliptic (y = 0) that sags under
+/// every body, deeper for heavier ones. Rebuilt every frame, so the dimples follow the
+/// planets around their orbits.
+pub struct GravityGrid {
+    pub visible: bool,
+    pub config: GravityGridConfig,
+}
+
+impl GravityGrid {
+    pub fn new(config: GravityGridConfig) -> Self {
+        GravityGrid { visible: config.enabled, config }
+    }
+
+    pub fn process_input(&mut self, input: &InputState) {
+        if input.is_key_pressed(GRAVITY_GRID_KEY) {
+            self.visible = !self.visible;
+        }
+    }
+
+    /// Wells of the sphere bodies. Depth grows with the log of the mass, so the sun makes
+    /// a deep funnel without flattening the planets' dimples to nothing next to it.
+    fn wells(&self, objects: &[SceneObject]) -> Vec<Well> {
+        objects
+            .iter()
+            .filter(|o| o.sphere && o.flags.visible)
+            .map(|o| {
+                let radius = o.radius * o.scale * self.config.width;
+                Well {
+                    x: o.translation.x,
+                    z: o.translation.z,
+                    depth: self.config.depth * o.mass().max(0.0).ln_1p(),
+                    radius_sq: (radius * radius).max(1e-4),
+                }
+            })
+            .collect()
+    }
+
+    /// How far the sheet has sunk at (x, z): each well falls off as 1 / (1 + d²/r²).
+    fn sink(wells: &[Well], x: f32, z: f32) -> f32 {
+        wells.iter().map(|w| w.depth / (1.0 + ((x - w.x).powi(2) + (z - w.z).powi(2)) / w.radius_sq)).sum()
+    }
+
+    /// Draws the grid as translucent, depth-tested lines fading with distance. Lines are
+    /// only drawn along cells that can be on screen.
+    pub fn draw(&self, framebuffer: &mut Framebuffer, uniforms: &Uniforms, objects: &[SceneObject]) {
+        if !self.visible {
+            return;
+        }
+        let n = self.config.resolution.clamp(1, 512);
+        let extent = self.config.extent.max(1.0);
+        let step = 2.0 * extent / n as f32;
+        let wells = self.wells(objects);
+
+        let mut points = Vec::with_capacity((n + 1) * (n + 1));
+        for j in 0..=n {
+            for i in 0..=n {
+                let (x, z) = (-extent + i as f32 * step, -extent + j as f32 * step);
+                points.push(Vector3::new(x, -Self::sink(&wells, x, z), z));
+            }
+        }
+        let at = |i: usize, j: usize| j * (n + 1) + i;
+
+        // Which side of the view volume each point is outside of; a cell whose corners are
+        // all past the same side can't be seen
+        let outside: Vec<u8> = points
+            .iter()
+            .map(|p| {
+                let c = multiply_matrix_vector4(&uniforms.frame.view_projection, &Vector4::new(p.x, p.y, p.z, 1.0));
+                (c.x < -c.w) as u8 | ((c.x > c.w) as u8) << 1 | ((c.y < -c.w) as u8) << 2 | ((c.y > c.w) as u8) << 3 | ((c.w <= 0.0) as u8) << 4
+            })
+            .collect();
+        let cell_visible = |i: usize, j: usize| {
+            i < n && j < n && outside[at(i, j)] & outside[at(i + 1, j)] & outside[at(i, j + 1)] & outside[at(i + 1, j + 1)] == 0
+        };
+
+        let fade = self.config.fade.max(1.0);
+        let eye = uniforms.frame.camera_pos;
+        let alpha = |p: Vector3| self.config.opacity * (1.0 - ((p - eye).length() / fade).clamp(0.0, 1.0)).powi(2);
+        let color = Vector3::new(self.config.color[0], self.config.color[1], self.config.color[2]);
+        let mut segment = |a: usize, b: usize| {
+            let alphas = (alpha(points[a]), alpha(points[b]));
+            if alphas.0 > 0.0 || alphas.1 > 0.0 {
+                segment_fading(framebuffer, uniforms, points[a], points[b], color, alphas);
+            }
+        };
+        for j in 0..=n {
+            for i in 0..n {
+                // Along x, between the cells on either side
+                if cell_visible(i, j) || (j > 0 && cell_visible(i, j - 1)) {
+                    segment(at(i, j), at(i + 1, j));
+                }
+                // Along z
+                if cell_visible(j, i) || (j > 0 && cell_visible(j - 1, i)) {
+                    segment(at(j, i), at(j, i + 1));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,660 @@
+#![allow(dead_code)]
+
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use raylib::prelude::*;
+
+use crate::arena::FrameArena;
+use crate::beauty::FillLights;
+use crate::culling::draw_order;
+use crate::deepsky::{draw_deep_sky, DeepSkyConfig};
+use crate::depthrange::DepthMode;
+use crate::dust::{draw_dust, DustConfig};
+use crate::framebuffer::{DepthTest, GlowTarget, PixelTarget};
+use crate::light::{Light, LightConfig, Occluder};
+use crate::matrix::{create_projection_matrix, create_view_matrix, create_viewport_matrix, view_ray, world_to_screen};
+use crate::noise::detail_budget;
+use crate::pipeline::Pipeline;
+use crate::scene::{ring_gaps, SceneObject};
+use crate::shaders::draw_sun_halo;
+use crate::skybox::{sample_cubemap, SkyReflection, Skybox};
+use crate::sphere::{project_sphere, render_sphere};
+use crate::texture::Material;
+use crate::tile::Tile;
+use crate::{FrameUniforms, ObjectUniforms, Uniforms};
+
+/// Float color and depth buffers with no window or GPU behind them. Colors are kept
+/// unclamped so NaNs and overflows from the shaders are still visible afterwards.
+pub struct HeadlessTarget {
+    pub width: u32,
+    pub height: u32,
+    pub clear_color: Vector3,
+    pub colors: Vec<Vector3>,
+    pub depth: Vec<f32>,
+    pub depth_mode: DepthMode,
+}
+
+impl HeadlessTarget {
+    pub fn new(width: u32, height: u32, clear_color: Vector3) -> Self {
+        let size = (width * height) as usize;
+        let depth_mode = DepthMode::Classic;
+        HeadlessTarget { width, height, clear_color, colors: vec![clear_color; size], depth: vec![depth_mode.cleared(); size], depth_mode }
+    }
+
+    /// Switches the depth convention, clearing the depth buffer to match.
+    pub fn set_depth_mode(&mut self, mode: DepthMode) {
+        self.depth_mode = mode;
+        self.depth.fill(mode.cleared());
+    }
+
+    /// Share of pixels that differ from the clear color, 0 to 1.
+    pub fn coverage(&self) -> f32 {
+        let drawn = self.colors.iter().filter(|&&c| c != self.clear_color).count();
+        drawn as f32 / self.colors.len() as f32
+    }
+
+    /// Pixels whose color has a NaN or infinite channel, as (x, y).
+    pub fn non_finite_pixels(&self) -> Vec<(u32, u32)> {
+        self.colors
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| !(c.x.is_finite() && c.y.is_finite() && c.z.is_finite()))
+            .map(|(i, _)| (i as u32 % self.width, i as u32 / self.width))
+            .collect()
+    }
+
+    /// Depth values split into clusters wherever sorted neighbours are more than `gap`
+    /// apart, leaving out the untouched background. Returns the number of pixels in each
+    /// cluster, nearest first.
+    pub fn depth_clusters(&self, gap: f32) -> Vec<usize> {
+        let mode = self.depth_mode;
+        let mut drawn: Vec<f32> = self.depth.iter().copied().filter(|&d| !mode.is_background(d)).collect();
+        drawn.sort_by(f32::total_cmp);
+        if mode == DepthMode::Reverse {
+            drawn.reverse();
+        }
+
+        let mut clusters = Vec::new();
+        let mut previous: Option<f32> = None;
+        for d in drawn {
+            match previous {
+                Some(p) if (d - p).abs() <= gap => *clusters.last_mut().expect("a cluster is open") += 1,
+                _ => clusters.push(1),
+            }
+            previous = Some(d);
+        }
+        clusters
+    }
+
+    /// Brightest luminance within `radius` pixels of `center`.
+    pub fn max_luminance_near(&self, center: Vector2, radius: f32) -> f32 {
+        let mut brightest: f32 = 0.0;
+        for (i, c) in self.colors.iter().enumerate() {
+            let (x, y) = ((i as u32 % self.width) as f32 + 0.5, (i as u32 / self.width) as f32 + 0.5);
+            if (x - center.x).hypot(y - center.y) <= radius {
+                brightest = brightest.max(0.2126 * c.x + 0.7152 * c.y + 0.0722 * c.z);
+            }
+        }
+        brightest
+    }
+}
+
+impl PixelTarget for HeadlessTarget {
+    fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn blend_point(&mut self, x: i32, y: i32, color: Vector3, alpha: f32, depth: f32) -> bool {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 || alpha <= 0.0 {
+            return false;
+        }
+        let index = (y as u32 * self.width + x as u32) as usize;
+        let Some(write) = DepthTest::new(self.depth_mode).check(depth, self.depth[index], alpha) else {
+            return false;
+        };
+        if write {
+            self.depth[index] = depth;
+        }
+        let alpha = alpha.min(1.0);
+        self.colors[index] = color * alpha + self.colors[index] * (1.0 - alpha);
+        true
+    }
+}
+
+impl GlowTarget for HeadlessTarget {
+    fn depth_at(&self, x: i32, y: i32) -> f32 {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return self.depth_mode.cleared();
+        }
+        self.depth[(y as u32 * self.width + x as u32) as usize]
+    }
+
+    fn depth_mode(&self) -> DepthMode {
+        self.depth_mode
+    }
+
+    fn add_color(&mut self, x: i32, y: i32, color: Vector3) {
+        if x >= 0 && y >= 0 && x < self.width as i32 && y < self.height as i32 {
+            self.colors[(y as u32 * self.width + x as u32) as usize] += color;
+        }
+    }
+
+    fn set_color(&mut self, x: i32, y: i32, color: Vector3) {
+        if x >= 0 && y >= 0 && x < self.width as i32 && y < self.height as i32 {
+            self.colors[(y as u32 * self.width + x as u32) as usize] = color;
+        }
+    }
+}
+
+/// Camera of a headless render.
+#[derive(Clone, Copy, Debug)]
+pub struct HeadlessCamera {
+    pub eye: Vector3,
+    pub target: Vector3,
+    pub up: Vector3,
+    pub fov_y: f32,
+    pub tile: Option<Tile>, // Renders one window of a video wall instead of the whole view
+    pub depth_mode: DepthMode,
+    pub near: f32,
+    pub far: f32,
+    pub jitter: Vector2, // Subpixel offset of the image, for supersampling
+}
+
+impl HeadlessCamera {
+    pub fn looking_at(eye: Vector3, target: Vector3) -> Self {
+        HeadlessCamera {
+            eye,
+            target,
+            up: Vector3::new(0.0, 1.0, 0.0),
+            fov_y: PI / 3.0,
+            tile: None,
+            depth_mode: DepthMode::Classic,
+            near: 0.1,
+            far: 1000.0,
+            jitter: Vector2::zero(),
+        }
+    }
+
+    /// Where `point` lands on a `width`×`height` render, if in front of the camera.
+    pub fn project(&self, point: Vector3, width: u32, height: u32) -> Option<Vector2> {
+        let (view, projection, viewport) = self.matrices(width, height);
+        world_to_screen(point, &view, &projection, &viewport).map(|p| Vector2::new(p.x, p.y))
+    }
+
+    /// View, projection and viewport matrices for a `width`×`height` target.
+    pub(crate) fn matrices(&self, width: u32, height: u32) -> (Matrix, Matrix, Matrix) {
+        let view = create_view_matrix(self.eye, self.target, self.up);
+        let aspect = width as f32 / height as f32;
+        let projection = match self.tile {
+            Some(tile) => tile.projection(self.fov_y, aspect, self.near, self.far),
+            None => create_projection_matrix(self.fov_y, aspect, self.near, self.far),
+        };
+        let projection = self.depth_mode.projection(projection, self.near, self.far);
+        let viewport = create_viewport_matrix(self.jitter.x, self.jitter.y, width as f32, height as f32);
+        (view, projection, viewport)
+    }
+}
+
+/// What an offscreen render draws besides the bodies. The tests leave it all off; a
+/// photo turns it on to match the window.
+#[derive(Clone, Copy)]
+pub struct RenderOptions<'a> {
+    pub sky: Option<&'a Skybox>,   // Sampled behind every pixel; black without
+    pub materials: &'a [Material], // Texture maps of the bodies by index, plain where missing
+    pub deep_sky: &'a [DeepSkyConfig], // Galaxies and nebulae in front of the sky
+    pub seed: u64,                 // The scene's seed, for the deep-sky objects
+    pub fill_lights: Option<FillLights>, // Beauty mode's camera-attached lights
+    pub sun_pulse: f32,            // Sun brightness multiplier from the modulation input
+    pub dust: Option<&'a DustConfig>, // Zodiacal glow over the bodies
+    pub reflection: Option<&'a Arc<SkyReflection>>, // Prefiltered sky the shuttle's hull mirrors
+}
+
+impl Default for RenderOptions<'_> {
+    fn default() -> Self {
+        RenderOptions { sky: None, materials: &[], deep_sky: &[], seed: 0, fill_lights: None, sun_pulse: 1.0, dust: None, reflection: None }
+    }
+}
+
+/// Renders `objects` front to back the way the main loop does, without the overlays:
+/// spheres are ray-cast at any size, everything else goes through the mesh pipeline.
+/// Lit by a sun at the first `sun` body, or the origin.
+pub fn render_scene(
+    objects: &[SceneObject],
+    camera: &HeadlessCamera,
+    light_config: &LightConfig,
+    width: u32,
+    height: u32,
+    time: f32,
+    options: &RenderOptions,
+) -> HeadlessTarget {
+    let (view, projection, viewport) = camera.matrices(width, height);
+    let sun = objects.iter().find(|o| o.object_type == "sun");
+    let mut light = Light::new(sun.map_or(Vector3::zero(), |o| o.translation));
+    light.animate(time, light_config);
+    light.intensity *= options.sun_pulse;
+    let mut frame = FrameUniforms {
+        time,
+        light_position: light.position,
+        sun_radius: sun.map_or(0.0, |o| o.radius * o.scale),
+        ambient: light_config.ambient,
+        fill_lights: options.fill_lights,
+        sun_pulse: options.sun_pulse,
+        depth_mode: camera.depth_mode,
+        ..FrameUniforms::new(view, projection, viewport, camera.eye)
+    };
+
+    let mut target = HeadlessTarget::new(width, height, Vector3::zero());
+    target.set_depth_mode(camera.depth_mode);
+    if let Some(sky) = options.sky {
+        frame.sky = sky.ambient(light_config.irradiance);
+        draw_sky(&mut target, sky, camera, &projection);
+    }
+    draw_deep_sky(options.deep_sky, options.seed, &frame, |x, y, color| target.colors[(y as u32 * width + x as u32) as usize] += color);
+    let occluders: Vec<(usize, Occluder)> = objects
+        .iter()
+        .enumerate()
+        .filter(|(_, o)| o.sphere && o.flags.visible && o.flags.cast_shadows)
+        .map(|(i, o)| (i, Occluder { center: o.translation, radius: o.radius * o.scale }))
+        .collect();
+    let mut pipeline = Pipeline::new();
+    let mut arena = FrameArena::new();
+    let mut sun_halo = None;
+    for index in draw_order(objects, camera.eye) {
+        let obj = &objects[index];
+        if !obj.flags.visible {
+            continue;
+        }
+        let model = obj.model_matrix();
+        let ring = obj.ring.clone().unwrap_or_default();
+        let mut uniforms = Uniforms {
+            frame,
+            object: ObjectUniforms {
+                irradiance: light.irradiance_at(obj.translation, light_config),
+                ring_radii: Vector2::new(ring.inner_radius, ring.outer_radius),
+                ring_gaps: ring_gaps(obj, objects),
+                ring_profile: obj.ring_profile.clone(),
+                screen_center: Vector2::new(width as f32 * 0.5, height as f32 * 0.5),
+                screen_radius: height as f32 * 0.5,
+                noise: obj.noise,
+                params: obj.params.clone(),
+                ramps: obj.ramps.clone(),
+                planet: obj.planet.clone(),
+                material: options.materials.get(index).cloned().unwrap_or_default(),
+                reflection: options.reflection.filter(|_| obj.object_type == "shuttle").cloned(),
+                occluders: if obj.flags.receive_shadows {
+                    occluders.iter().filter(|(i, _)| *i != index).map(|(_, o)| *o).collect()
+                } else {
+                    Vec::new()
+                },
+                ..ObjectUniforms::new(model, &frame)
+            },
+        };
+        let projected = project_sphere(obj.translation, obj.radius * obj.scale, &uniforms);
+        if let Some(sphere) = &projected {
+            uniforms.object.screen_center = sphere.center_px;
+            uniforms.object.screen_radius = sphere.radius_px;
+            uniforms.object.detail_budget = detail_budget(sphere.radius_px);
+        }
+        if obj.object_type == "sun" && obj.sphere {
+            sun_halo = projected.map(|sphere| (sphere, uniforms.object.params.clone()));
+        }
+        match projected.filter(|_| obj.sphere) {
+            Some(sphere) => render_sphere(&mut target, &uniforms, &sphere, &obj.object_type),
+            None => pipeline.render(&mut target, &uniforms, &obj.vertices, &light, &obj.name, &obj.object_type, &mut arena),
+        }
+    }
+    if let Some((sphere, params)) = &sun_halo {
+        draw_sun_halo(&mut target, sphere, params, frame.sun_pulse);
+    }
+    if let Some(dust) = options.dust {
+        let world_uniforms = Uniforms { frame, object: ObjectUniforms::new(Matrix::identity(), &frame) };
+        draw_dust(&mut target, &world_uniforms, camera.eye, dust);
+    }
+    target
+}
+
+/// Fills `target` with the sky seen through `camera`, one sample per pixel center.
+fn draw_sky(target: &mut HeadlessTarget, sky: &Skybox, camera: &HeadlessCamera, projection: &Matrix) {
+    let forward = (camera.target - camera.eye).normalized();
+    let right = forward.cross(camera.up).normalized();
+    let up = right.cross(forward).normalized();
+    let (width, height) = (target.width as f32, target.height as f32);
+    for y in 0..target.height {
+        for x in 0..target.width {
+            let ndc_x = 2.0 * (x as f32 + 0.5 - camera.jitter.x) / width - 1.0;
+            let ndc_y = 1.0 - 2.0 * (y as f32 + 0.5 - camera.jitter.y) / height;
+            let ray = view_ray(projection, ndc_x, ndc_y);
+            // View space looks down -Z
+            let dir = right * ray.x + up * ray.y - forward * ray.z;
+            target.colors[(y * target.width + x) as usize] = sample_cubemap(sky, dir.normalized());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::pipeline::{Stage, FOCUS_SAMPLES};
+    use crate::scene::{build_scene, default_scene, update_scene, MeshLibrary, SceneFile};
+    use crate::shaders::vertex_shader;
+    use crate::skybox::SkyboxFace;
+    use crate::triangle::is_front_facing;
+    use crate::ui::{glyph, GLYPH_WIDTH};
+    use crate::vertex::Vertex;
+
+    const WIDTH: u32 = 200;
+    const HEIGHT: u32 = 150;
+
+    /// The default scene from just outside the sun, with planets behind it.
+    fn render_default_scene() -> (HeadlessTarget, Vec<SceneObject>, HeadlessCamera) {
+        let (scene, objects) = default_scene();
+
+        let camera = HeadlessCamera::looking_at(Vector3::new(0.0, 2.0, 6.0), Vector3::zero());
+        (render_scene(&objects, &camera, &scene.light, WIDTH, HEIGHT, 0.0, &RenderOptions::default()), objects, camera)
+    }
+
+    #[test]
+    fn default_scene_renders_something_sensible() {
+        let (target, objects, camera) = render_default_scene();
+
+        let coverage = target.coverage();
+        assert!(coverage >= 0.2, "only {:.1}% of the pixels were drawn; is everything culled or off screen?", coverage * 100.0);
+
+        let bad = target.non_finite_pixels();
+        assert!(bad.is_empty(), "{} pixels are NaN or infinite, the first at {:?}", bad.len(), bad.first());
+
+        let clusters = target.depth_clusters(0.05);
+        assert!(
+            clusters.len() >= 2,
+            "the depth buffer holds a single cluster of values ({:?}); is the depth convention or the projection broken?",
+            clusters
+        );
+
+        let sun = objects.iter().find(|o| o.object_type == "sun").expect("the default scene has a sun");
+        let center = camera.project(sun.translation, WIDTH, HEIGHT).expect("the sun is in front of the camera");
+        let brightest = target.max_luminance_near(center, 10.0);
+        assert!(brightest > 0.9, "the sun's region peaks at luminance {:.2}, expected above 0.9", brightest);
+    }
+
+    #[test]
+    fn the_seed_decides_the_generated_scene() {
+        let (scene, _) = default_scene();
+        let camera = HeadlessCamera::looking_at(Vector3::new(0.0, 2.0, 6.0), Vector3::zero());
+        let render = |seed: u64| {
+            let scene = SceneFile { seed, ..scene.clone() };
+            let (mut objects, errors) = build_scene(&scene, &mut MeshLibrary::new());
+            assert!(errors.is_empty(), "{:?}", errors);
+            update_scene(&mut objects, 0.0);
+            let options = RenderOptions { seed, ..RenderOptions::default() };
+            render_scene(&objects, &camera, &scene.light, WIDTH, HEIGHT, 0.0, &options).colors
+        };
+
+        let first = render(scene.seed);
+        assert_eq!(first, render(scene.seed), "the same seed rendered two different scenes");
+        assert_ne!(first, render(scene.seed + 1), "another seed rendered the same scene");
+    }
+
+    #[test]
+    fn depth_clusters_leave_out_the_background() {
+        let mut target = HeadlessTarget::new(4, 1, Vector3::zero());
+        assert!(target.depth_clusters(0.05).is_empty());
+        target.point(0, 0, Vector3::one(), 0.3);
+        target.point(1, 0, Vector3::one(), 0.32);
+        target.point(2, 0, Vector3::one(), 0.8);
+        // A farther fragment loses to the nearer one already there
+        assert!(!target.point(2, 0, Vector3::one(), 0.9));
+        assert_eq!(target.depth_clusters(0.05), vec![2, 1]);
+    }
+
+    #[test]
+    fn adjacent_tiles_line_up_along_their_seam() {
+        let (scene, objects) = default_scene();
+
+        // The sun straddles the seam, so both tiles draw it
+        let whole = HeadlessCamera::looking_at(Vector3::new(0.0, 2.0, 6.0), Vector3::zero());
+        let tile = |column| HeadlessCamera { tile: Some(Tile { column, row: 0, columns: 2, rows: 1 }), ..whole };
+        let full = render_scene(&objects, &whole, &scene.light, WIDTH * 2, HEIGHT, 0.0, &RenderOptions::default());
+        let left = render_scene(&objects, &tile(0), &scene.light, WIDTH, HEIGHT, 0.0, &RenderOptions::default());
+        let right = render_scene(&objects, &tile(1), &scene.light, WIDTH, HEIGHT, 0.0, &RenderOptions::default());
+
+        // Each seam column must match the same column of the whole view
+        let column = |target: &HeadlessTarget, x: u32| -> Vec<Vector3> {
+            (0..target.height).map(|y| target.colors[(y * target.width + x) as usize]).collect()
+        };
+        let pairs = [(column(&left, WIDTH - 1), column(&full, WIDTH - 1)), (column(&right, 0), column(&full, WIDTH))];
+        for (tile_column, full_column) in &pairs {
+            let drawn = tile_column.iter().filter(|&&c| c != Vector3::zero()).count();
+            assert!(drawn > 0, "nothing was drawn along the seam");
+            let mismatched = tile_column.iter().zip(full_column).filter(|(a, b)| (**a - **b).length() > 0.02).count();
+            assert!(mismatched <= 1, "{} of {} seam pixels differ from the whole view", mismatched, HEIGHT);
+        }
+    }
+
+    #[test]
+    fn pathological_scales_render_without_nans() {
+        let (scene, mut objects) = default_scene();
+        // Past the checks, as a script or a hand-edited save could leave them
+        for (obj, scale) in objects.iter_mut().zip([0.0, 1e4, 1e-12, f32::MAX]) {
+            obj.scale = scale;
+        }
+        update_scene(&mut objects, 0.0);
+
+        let camera = HeadlessCamera::looking_at(Vector3::new(0.0, 2.0, 6.0), Vector3::zero());
+        let start = std::time::Instant::now();
+        let target = render_scene(&objects, &camera, &scene.light, WIDTH, HEIGHT, 0.0, &RenderOptions::default());
+        let elapsed = start.elapsed();
+        let bad = target.non_finite_pixels();
+        assert!(bad.is_empty(), "{} pixels are NaN or infinite, the first at {:?}", bad.len(), bad.first());
+        assert!(elapsed.as_secs_f32() < 10.0, "the frame took {:?}", elapsed);
+    }
+
+    /// Cells along each side of a face of the conformance cube.
+    const CELLS: usize = 16;
+    const CONFORMANCE_SIZE: u32 = 256;
+
+    /// A face of the conformance cube: its label, outward normal, the directions that
+    /// are right and up when it's seen from outside, and its color.
+    struct CubeFace {
+        label: &'static str,
+        normal: Vector3,
+        right: Vector3,
+        up: Vector3,
+        color: Vector3,
+    }
+
+    /// Right is worked out by hand rather than with `cross`, so a wrong-handed
+    /// convention anywhere can't cancel out in the test.
+    fn cube_faces() -> [CubeFace; 6] {
+        let (x, y, z) = (Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        let face = |label, normal, right, up, color| CubeFace { label, normal, right, up, color };
+        [
+            face("+X", x, -z, y, Vector3::new(0.8, 0.1, 0.1)),
+            face("-X", -x, z, y, Vector3::new(0.4, 0.05, 0.05)),
+            face("+Y", y, x, -z, Vector3::new(0.1, 0.8, 0.1)),
+            face("-Y", -y, x, z, Vector3::new(0.05, 0.4, 0.05)),
+            face("+Z", z, x, y, Vector3::new(0.1, 0.1, 0.8)),
+            face("-Z", -z, -x, y, Vector3::new(0.05, 0.05, 0.4)),
+        ]
+    }
+
+    /// What a face shows from outside, rows top first: its label in white at the top
+    /// left and an L in yellow at the bottom left, on the face's color.
+    fn face_picture(face: &CubeFace) -> Vec<Vec<Vector3>> {
+        let mut cells = vec![vec![face.color; CELLS]; CELLS];
+        for (i, c) in face.label.chars().enumerate() {
+            for (row, bits) in glyph(c).iter().enumerate() {
+                for column in 0..GLYPH_WIDTH as usize {
+                    if bits & (1 << (GLYPH_WIDTH as usize - 1 - column)) != 0 {
+                        cells[2 + row][2 + i * 6 + column] = Vector3::one();
+                    }
+                }
+            }
+        }
+        let yellow = Vector3::new(1.0, 1.0, 0.0);
+        for k in 0..4 {
+            cells[11 + k][2] = yellow;
+            cells[14][2 + k] = yellow;
+        }
+        cells
+    }
+
+    /// Corner (column, row) of a face's cell grid, from its top left corner.
+    fn face_point(face: &CubeFace, column: usize, row: usize) -> Vector3 {
+        let step = 2.0 / CELLS as f32;
+        face.normal + face.right * (-1.0 + column as f32 * step) + face.up * (1.0 - row as f32 * step)
+    }
+
+    /// The cube from -1 to 1, two triangles per cell, wound counter-clockwise seen from
+    /// outside. Returns the triangles of each face separately.
+    fn conformance_cube() -> Vec<Vec<Vertex>> {
+        cube_faces()
+            .iter()
+            .map(|face| {
+                let picture = face_picture(face);
+                let mut vertices = Vec::new();
+                for (row, cells) in picture.iter().enumerate() {
+                    for (column, &color) in cells.iter().enumerate() {
+                        let [top_left, top_right] = [column, column + 1].map(|c| face_point(face, c, row));
+                        let [bottom_left, bottom_right] = [column, column + 1].map(|c| face_point(face, c, row + 1));
+                        for p in [bottom_left, bottom_right, top_right, bottom_left, top_right, top_left] {
+                            vertices.push(Vertex::new_with_color(p, color));
+                        }
+                    }
+                }
+                vertices
+            })
+            .collect()
+    }
+
+    fn conformance_uniforms(camera: &HeadlessCamera) -> Uniforms {
+        let (view, projection, viewport) = camera.matrices(CONFORMANCE_SIZE, CONFORMANCE_SIZE);
+        let frame = FrameUniforms::new(view, projection, viewport, camera.eye);
+        Uniforms { frame, object: ObjectUniforms::new(Matrix::identity(), &frame) }
+    }
+
+    #[test]
+    fn labeled_cube_reads_unmirrored_from_every_axis() {
+        let cube = conformance_cube();
+        let all: Vec<Vertex> = cube.concat();
+        let mut pipeline = Pipeline::new();
+        pipeline.set_enabled(Stage::Shade, false); // Fragments keep the cells' colors
+        let light = Light::new(Vector3::zero());
+        let mut arena = FrameArena::new();
+
+        // A pinhole camera 4 units out sees the face 3 units away; worked out directly
+        // instead of through the matrices under test
+        let half = CONFORMANCE_SIZE as f32 * 0.5;
+        let pixels_per_unit = half / (3.0 * (PI / 6.0).tan());
+        for (index, face) in cube_faces().iter().enumerate() {
+            let camera = HeadlessCamera { up: face.up, ..HeadlessCamera::looking_at(face.normal * 4.0, Vector3::zero()) };
+            let uniforms = conformance_uniforms(&camera);
+            let mut target = HeadlessTarget::new(CONFORMANCE_SIZE, CONFORMANCE_SIZE, Vector3::zero());
+            pipeline.render(&mut target, &uniforms, &all, &light, "cube", "conformance", &mut arena);
+
+            let mut wrong = Vec::new();
+            for (row, cells) in face_picture(face).iter().enumerate() {
+                for (column, &expected) in cells.iter().enumerate() {
+                    let a = -1.0 + (column as f32 + 0.5) * 2.0 / CELLS as f32;
+                    let b = 1.0 - (row as f32 + 0.5) * 2.0 / CELLS as f32;
+                    let (x, y) = ((half + a * pixels_per_unit) as u32, (half - b * pixels_per_unit) as u32);
+                    let got = target.colors[(y * CONFORMANCE_SIZE + x) as usize];
+                    if (got - expected).length() > 0.05 {
+                        wrong.push((column, row));
+                    }
+                }
+            }
+            assert!(
+                wrong.is_empty(),
+                "face {} doesn't read as drawn from outside ({} cells wrong, the first at column/row {:?}); is the image mirrored or rotated?",
+                face.label,
+                wrong.len(),
+                wrong[0]
+            );
+
+            // The face towards the camera winds front-facing on screen, the one behind it
+            // (its neighbour in `cube_faces`) doesn't
+            let front_facing = |tri: &[Vertex]| {
+                let [a, b, c] = [0, 1, 2].map(|k| vertex_shader(&tri[k], &uniforms).transformed_position);
+                is_front_facing(a, b, c)
+            };
+            assert!(cube[index].chunks_exact(3).all(front_facing), "face {} winds clockwise on screen", face.label);
+            assert!(!cube[index ^ 1].chunks_exact(3).any(front_facing), "the face behind {} faces the camera", face.label);
+        }
+    }
+
+    #[test]
+    fn supersampled_edges_blend_and_back_faces_stay_hidden() {
+        // Solid-colored faces, seen from a corner so the silhouette runs diagonally
+        let cube: Vec<Vertex> = cube_faces()
+            .iter()
+            .zip(conformance_cube())
+            .flat_map(|(face, vertices)| vertices.into_iter().map(|v| Vertex::new_with_color(v.position, face.color)))
+            .collect();
+        let camera = HeadlessCamera::looking_at(Vector3::new(3.0, 2.2, 4.0), Vector3::new(0.1, 0.0, 0.0));
+        let uniforms = conformance_uniforms(&camera);
+        let light = Light::new(Vector3::zero());
+        let mut arena = FrameArena::new();
+        let render = |samples: u32, arena: &mut FrameArena| {
+            let mut pipeline = Pipeline::new();
+            pipeline.set_enabled(Stage::Shade, false);
+            pipeline.set_samples(samples);
+            let mut target = HeadlessTarget::new(CONFORMANCE_SIZE, CONFORMANCE_SIZE, Vector3::zero());
+            pipeline.render(&mut target, &uniforms, &cube, &light, "cube", "conformance", arena);
+            target.colors
+        };
+        let single = render(1, &mut arena);
+        let super4 = render(FOCUS_SAMPLES, &mut arena);
+        let is_face = |c: Vector3| cube_faces().iter().any(|face| (face.color - c).length() < 1e-3);
+
+        // One sample per pixel: every pixel is a face or the background
+        assert!(single.iter().all(|&c| c == Vector3::zero() || is_face(c)));
+        // Four: the silhouette's pixels are part covered, between a face and the background
+        let partial = super4.iter().filter(|&&c| c != Vector3::zero() && !is_face(c)).count();
+        assert!(partial > 50, "{} part-covered pixels", partial);
+
+        // Away from edges both agree; a back face bleeding in would show here
+        let size = CONFORMANCE_SIZE as usize;
+        for y in 1..size - 1 {
+            for x in 1..size - 1 {
+                let at = |dx: usize, dy: usize| single[(y + dy - 1) * size + x + dx - 1];
+                if (0..3).all(|dy| (0..3).all(|dx| at(dx, dy) == at(1, 1))) {
+                    let got = super4[y * size + x];
+                    assert!((got - at(1, 1)).length() < 1e-3, "{:?} at {},{} instead of {:?}", got, x, y, at(1, 1));
+                }
+            }
+        }
+    }
+
+    /// Sky face whose pixels say which face (red) and quadrant (green: 0 top left, 1 top
+    /// right, 2 bottom left, 3 bottom right) they are.
+    fn marked_sky_face(face: u8) -> SkyboxFace {
+        SkyboxFace { width: 2, height: 2, pixels: (0..4).map(|q| [face * 40, q * 60, 0]).collect() }
+    }
+
+    #[test]
+    fn sky_faces_read_unmirrored_from_inside() {
+        let sky = Skybox::new(
+            marked_sky_face(0),
+            marked_sky_face(1),
+            marked_sky_face(2),
+            marked_sky_face(3),
+            marked_sky_face(4),
+            marked_sky_face(5),
+        );
+        let options = RenderOptions { sky: Some(&sky), ..RenderOptions::default() };
+        let (x, y, z) = (Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        // (face, looking along, up); right, left, top, bottom, front and back
+        let views = [(0, x, y), (1, -x, y), (2, y, -z), (3, -y, z), (4, z, y), (5, -z, y)];
+        for (face, forward, up) in views {
+            let camera = HeadlessCamera { up, ..HeadlessCamera::looking_at(Vector3::zero(), forward) };
+            let target = render_scene(&[], &camera, &LightConfig::default(), 64, 64, 0.0, &options);
+            for (quadrant, (px, py)) in [(16, 16), (48, 16), (16, 48), (48, 48)].into_iter().enumerate() {
+                let got = target.colors[py * 64 + px] * 255.0;
+                let expected = Vector3::new(face as f32 * 40.0, quadrant as f32 * 60.0, 0.0);
+                assert!((got - expected).length() < 0.5, "sky face {} quadrant {} shows {:?}", face, quadrant, got);
+            }
+        }
+    }
+}
@@ -0,0 +1,169 @@
+#![allow(dead_code)]
+
+use raylib::prelude::*;
+
+use crate::input::InputState;
+use crate::quality::{QualityController, QUALITY_LEVELS};
+
+/// Key that shows/hides the HUD.
+pub const HUD_KEY: KeyboardKey = KeyboardKey::KEY_F1;
+
+const GRAPH_SAMPLES: usize = 240;
+const BUDGET_60: f32 = 1000.0 / 60.0; // ms
+const BUDGET_30: f32 = 1000.0 / 30.0; // ms
+
+/// Ring buffer of recent frame times, in milliseconds.
+pub struct FrameTimeGraph {
+    samples: [f32; GRAPH_SAMPLES],
+    head: usize, // Next slot to write
+    count: usize,
+}
+
+impl FrameTimeGraph {
+    pub fn new() -> Self {
+        FrameTimeGraph {
+            samples: [0.0; GRAPH_SAMPLES],
+            head: 0,
+            count: 0,
+        }
+    }
+
+    pub fn push(&mut self, frame_ms: f32) {
+        self.samples[self.head] = frame_ms;
+        self.head = (self.head + 1) % GRAPH_SAMPLES;
+        self.count = (self.count + 1).min(GRAPH_SAMPLES);
+    }
+
+    /// Samples from oldest to newest.
+    pub fn iter(&self) -> impl Iterator<Item = f32> + '_ {
+        let start = (self.head + GRAPH_SAMPLES - self.count) % GRAPH_SAMPLES;
+        (0..self.count).map(move |i| self.samples[(start + i) % GRAPH_SAMPLES])
+    }
+
+    /// Index (oldest = 0) and value of the slowest frame in the window.
+    pub fn worst(&self) -> Option<(usize, f32)> {
+        self.iter()
+            .enumerate()
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+    }
+
+    /// Draws the strip with its bottom-left corner at (x, bottom).
+    pub fn draw(&self, d: &mut RaylibDrawHandle, x: i32, bottom: i32, height: i32) {
+        let bar_width = 2;
+        let width = GRAPH_SAMPLES as i32 * bar_width;
+        let max_ms = 50.0; // Top of the graph
+        let to_y = |ms: f32| bottom - ((ms / max_ms).min(1.0) * height as f32) as i32;
+
+        d.draw_rectangle(x, bottom - height, width, height, Color::new(0, 0, 0, 150));
+
+        for (i, ms) in self.iter().enumerate() {
+            let color = if ms <= BUDGET_60 {
+                Color::GREEN
+            } else if ms <= BUDGET_30 {
+                Color::YELLOW
+            } else {
+                Color::RED
+            };
+            let top = to_y(ms);
+            d.draw_rectangle(x + i as i32 * bar_width, top, bar_width, bottom - top, color);
+        }
+
+        // Budget guide lines
+        for (ms, label) in [(BUDGET_60, "16.6"), (BUDGET_30, "33.3")] {
+            let y = to_y(ms);
+            d.draw_line(x, y, x + width, y, Color::new(255, 255, 255, 120));
+            d.draw_text(label, x + width + 4, y - 5, 10, Color::LIGHTGRAY);
+        }
+
+        if let Some((i, ms)) = self.worst() {
+            let bar_x = x + i as i32 * bar_width;
+            let label_x = bar_x.min(x + width - 70);
+            d.draw_text(&format!("{:.1} ms", ms), label_x, to_y(ms) - 12, 10, Color::WHITE);
+        }
+    }
+}
+
+/// Per-frame counters shown in the HUD.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FrameStats {
+    pub objects_drawn: usize,
+    pub objects_culled: usize, // Skipped because a nearer body hides them
+    pub accumulated_frames: u32, // Still frames averaged into the image; 1 while anything moves
+    pub sky_bytes: usize,
+    pub texture_bytes: usize,
+    pub threads: usize, // Render worker threads; 1 is the serial path
+    pub depth_range: (f32, f32), // Near and far planes this frame
+    pub overdraw: f32,           // Average color writes per pixel
+    pub oversized_triangles: usize, // Triangles larger than the frame, clipped to it
+    pub focus_samples: u32,         // Samples per pixel along each axis on the focused body; 0 or 1 when off
+    pub color_clears_skipped: u64,  // Color clears left out since the scene started, with the sky covering every pixel
+    pub clear_saved_ms: f32,        // Time those saved this frame
+}
+
+/// On-screen diagnostics drawn over the rendered frame.
+pub struct Hud {
+    pub visible: bool,
+    pub frame_times: FrameTimeGraph,
+}
+
+impl Hud {
+    pub fn new() -> Self {
+        Hud {
+            visible: true,
+            frame_times: FrameTimeGraph::new(),
+        }
+    }
+
+    pub fn process_input(&mut self, input: &InputState) {
+        if input.is_key_pressed(HUD_KEY) {
+            self.visible = !self.visible;
+        }
+    }
+
+    /// Records the duration of the last frame in seconds.
+    pub fn record_frame(&mut self, dt: f32) {
+        self.frame_times.push(dt * 1000.0);
+    }
+
+    pub fn draw(&self, d: &mut RaylibDrawHandle, _width: i32, height: i32, quality: &QualityController, stats: &FrameStats) {
+        if !self.visible {
+            return;
+        }
+        self.frame_times.draw(d, 10, height - 40, 60);
+
+        let level = quality.current();
+        let text = format!(
+            "Quality {}/{}  render {:.0}%  work {:.1} ms  threads {}",
+            QUALITY_LEVELS.len() - quality.level,
+            QUALITY_LEVELS.len(),
+            level.render_scale * 100.0,
+            quality.average_ms(),
+            stats.threads,
+        );
+        d.draw_text(&text, 10, height - 120, 10, Color::LIGHTGRAY);
+        let mut objects = format!(
+            "Objects {} drawn, {} culled  depth {:.3}..{:.0}  overdraw {:.2}x",
+            stats.objects_drawn, stats.objects_culled, stats.depth_range.0, stats.depth_range.1, stats.overdraw
+        );
+        if stats.oversized_triangles > 0 {
+            objects += &format!("  {} triangles too large, clipped", stats.oversized_triangles);
+        }
+        if stats.focus_samples > 1 {
+            objects += &format!("  focus {}x{} AA", stats.focus_samples, stats.focus_samples);
+        }
+        d.draw_text(&objects, 10, height - 134, 10, Color::LIGHTGRAY);
+        let mut memory = format!(
+            "Memory sky {:.1} MB, textures {:.1} MB",
+            stats.sky_bytes as f32 / (1024.0 * 1024.0),
+            stats.texture_bytes as f32 / (1024.0 * 1024.0),
+        );
+        if stats.color_clears_skipped > 0 {
+            memory += &format!("  color clears skipped {} ({:.2} ms saved)", stats.color_clears_skipped, stats.clear_saved_ms);
+        }
+        d.draw_text(&memory, 10, height - 148, 10, Color::LIGHTGRAY);
+        if stats.accumulated_frames > 1 {
+            let accumulated = format!("Antialiasing {} still frames", stats.accumulated_frames);
+            d.draw_text(&accumulated, 10, height - 176, 10, Color::LIGHTGRAY);
+        }
+    }
+}
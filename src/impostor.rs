@@ -0,0 +1,241 @@
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+
+use raylib::prelude::*;
+
+use crate::framebuffer::PixelTarget;
+use crate::params::ParamSet;
+use crate::ramp::RampSet;
+use crate::sphere::{render_sphere, ScreenSphere};
+use crate::{FrameUniforms, ObjectUniforms, Uniforms};
+
+/// Spheres smaller than this on screen (radius in pixels) are drawn from a cached sprite.
+pub const IMPOSTOR_MAX_RADIUS_PX: f32 = 6.0;
+
+/// Small offscreen color + alpha buffer.
+pub struct Sprite {
+    pub width: u32,
+    pub height: u32,
+    colors: Vec<Vector3>,
+    alpha: Vec<f32>,
+}
+
+impl Sprite {
+    pub fn new(width: u32, height: u32) -> Self {
+        let size = (width * height) as usize;
+        Sprite {
+            width,
+            height,
+            colors: vec![Vector3::zero(); size],
+            alpha: vec![0.0; size],
+        }
+    }
+
+    /// Draws the sprite with its top-left corner at (x, y), blending by alpha.
+    /// The whole sprite is depth-tested at a single representative depth.
+    pub fn blit(&self, target: &mut impl PixelTarget, x: i32, y: i32, depth: f32) {
+        for j in 0..self.height {
+            for i in 0..self.width {
+                let index = (j * self.width + i) as usize;
+                let alpha = self.alpha[index];
+                if alpha > 0.0 {
+                    target.blend_point(x + i as i32, y + j as i32, self.colors[index], alpha, depth);
+                }
+            }
+        }
+    }
+}
+
+impl PixelTarget for Sprite {
+    fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn blend_point(&mut self, x: i32, y: i32, color: Vector3, alpha: f32, _depth: f32) -> bool {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return false;
+        }
+        let index = (y as u32 * self.width + x as u32) as usize;
+        if alpha <= self.alpha[index] {
+            return false;
+        }
+        self.colors[index] = color;
+        self.alpha[index] = alpha.min(1.0);
+        true
+    }
+}
+
+/// A body rendered once into a sprite, plus the conditions it was rendered under.
+struct Impostor {
+    sprite: Sprite,
+    radius_px: f32,
+    to_camera: Vector3,  // Unit direction from the body to the eye at capture time
+    to_light: Vector3,   // Unit direction from the body to the light at capture time
+    irradiance: Vector3,
+    object_type: String, // Kind it was shaded as, which can change (e.g. when discovered)
+    fill_lit: bool,      // Shaded with beauty mode's fill lights
+    sun_pulse: f32,      // The sun's brightness multiplier at capture time
+    params: ParamSet,    // Shader values and ramps it was shaded with, tweakable at runtime
+    ramps: RampSet,
+    age: u32,            // Frames since the sprite was rendered
+}
+
+/// Cached sprites for distant bodies, keyed by body name.
+pub struct ImpostorCache {
+    impostors: HashMap<String, Impostor>,
+    pub max_age: u32,          // Re-render at least this often (frames), e.g. to follow spin
+    pub angle_threshold: f32,  // Radians the view or light direction may drift before re-rendering
+}
+
+impl ImpostorCache {
+    pub fn new() -> Self {
+        ImpostorCache {
+            impostors: HashMap::new(),
+            max_age: 15,
+            angle_threshold: 0.05,
+        }
+    }
+
+    /// Forgets bodies that no longer exist.
+    pub fn retain<F: Fn(&str) -> bool>(&mut self, keep: F) {
+        self.impostors.retain(|name, _| keep(name));
+    }
+
+    /// Forgets every sprite, e.g. when the shader parameters they were rendered with changed.
+    pub fn clear(&mut self) {
+        self.impostors.clear();
+    }
+
+    /// Draws `sphere` from its cached sprite, re-rendering the sprite first if
+    /// the view, the lighting or its size changed too much since the last capture,
+    /// or its shader's values did.
+    pub fn draw(
+        &mut self,
+        target: &mut impl PixelTarget,
+        uniforms: &Uniforms,
+        sphere: &ScreenSphere,
+        name: &str,
+        object_type: &str,
+        eye: Vector3,
+    ) {
+        let to_camera = (eye - sphere.center_world).normalized();
+        let to_light = (uniforms.frame.light_position - sphere.center_world).normalized();
+        let cos_threshold = self.angle_threshold.cos();
+
+        let stale = match self.impostors.get(name) {
+            None => true,
+            Some(imp) => {
+                imp.age >= self.max_age
+                    || imp.to_camera.dot(to_camera) < cos_threshold
+                    || imp.to_light.dot(to_light) < cos_threshold
+                    || (imp.radius_px - sphere.radius_px).abs() > imp.radius_px * 0.1
+                    || relative_change(imp.irradiance, uniforms.object.irradiance) > 0.05
+                    || imp.object_type != object_type
+                    || imp.fill_lit != uniforms.frame.fill_lights.is_some()
+                    || (imp.sun_pulse - uniforms.frame.sun_pulse).abs() > 0.05
+                    || imp.params != uniforms.object.params
+                    || imp.ramps != uniforms.object.ramps
+            }
+        };
+
+        if stale {
+            let sprite = render_impostor(uniforms, sphere, object_type);
+            self.impostors.insert(
+                name.to_string(),
+                Impostor {
+                    sprite,
+                    radius_px: sphere.radius_px,
+                    to_camera,
+                    to_light,
+                    irradiance: uniforms.object.irradiance,
+                    object_type: object_type.to_string(),
+                    fill_lit: uniforms.frame.fill_lights.is_some(),
+                    sun_pulse: uniforms.frame.sun_pulse,
+                    params: uniforms.object.params.clone(),
+                    ramps: uniforms.object.ramps.clone(),
+                    age: 0,
+                },
+            );
+        }
+
+        let imp = self.impostors.get_mut(name).unwrap();
+        imp.age += 1;
+
+        // The sprite is rendered with the sphere centered in it
+        let half = imp.sprite.width as f32 / 2.0;
+        let x = (sphere.center_px.x - half).round() as i32;
+        let y = (sphere.center_px.y - half).round() as i32;
+        imp.sprite.blit(target, x, y, sphere.front_depth);
+    }
+}
+
+fn relative_change(a: Vector3, b: Vector3) -> f32 {
+    let reference = a.length().max(1e-4);
+    (b - a).length() / reference
+}
+
+/// Offscreen mini-render: ray-casts the sphere into a sprite just big enough to
+/// hold it, by shifting the viewport so the sphere's center lands mid-sprite.
+fn render_impostor(uniforms: &Uniforms, sphere: &ScreenSphere, object_type: &str) -> Sprite {
+    let size = (sphere.radius_px * 2.0 + 4.0).ceil() as u32;
+    let half = size as f32 / 2.0;
+
+    let mut viewport = uniforms.frame.viewport;
+    viewport.m12 -= sphere.center_px.x - half;
+    viewport.m13 -= sphere.center_px.y - half;
+
+    let sprite_uniforms = Uniforms {
+        frame: FrameUniforms { viewport, ..uniforms.frame },
+        object: ObjectUniforms { screen_center: Vector2::new(half, half), ..uniforms.object.clone() },
+    };
+    let local = ScreenSphere {
+        center_world: sphere.center_world,
+        center_view: sphere.center_view,
+        radius: sphere.radius,
+        center_px: Vector2::new(half, half),
+        radius_px: sphere.radius_px,
+        front_depth: sphere.front_depth,
+    };
+
+    let mut sprite = Sprite::new(size, size);
+    render_sphere(&mut sprite, &sprite_uniforms, &local, object_type);
+    sprite
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use super::*;
+    use crate::headless::HeadlessTarget;
+    use crate::matrix::{create_projection_matrix, create_view_matrix, create_viewport_matrix};
+    use crate::sphere::project_sphere;
+
+    #[test]
+    fn changed_shader_values_re_render_the_sprite() {
+        let eye = Vector3::new(0.0, 0.0, 40.0);
+        let view = create_view_matrix(eye, Vector3::zero(), Vector3::new(0.0, 1.0, 0.0));
+        let projection = create_projection_matrix(PI / 3.0, 1.0, 0.1, 100.0);
+        let viewport = create_viewport_matrix(0.0, 0.0, 64.0, 64.0);
+        let frame = FrameUniforms { light_position: eye, ..FrameUniforms::new(view, projection, viewport, eye) };
+        let mut uniforms = Uniforms { frame, object: ObjectUniforms { irradiance: Vector3::one(), ..ObjectUniforms::new(Matrix::identity(), &frame) } };
+        let sphere = project_sphere(Vector3::zero(), 1.0, &uniforms).expect("the body is in front of the camera");
+        uniforms.object.screen_center = sphere.center_px;
+        uniforms.object.screen_radius = sphere.radius_px;
+
+        let mut cache = ImpostorCache::new();
+        let mut target = HeadlessTarget::new(64, 64, Vector3::zero());
+        let age = |cache: &ImpostorCache| cache.impostors["jupiter"].age;
+        cache.draw(&mut target, &uniforms, &sphere, "jupiter", "gassy1", eye);
+        cache.draw(&mut target, &uniforms, &sphere, "jupiter", "gassy1", eye);
+        assert_eq!(age(&cache), 2, "an unchanged body reuses its sprite");
+
+        uniforms.object.params.set("band_freq", 22.0);
+        cache.draw(&mut target, &uniforms, &sphere, "jupiter", "gassy1", eye);
+        assert_eq!(age(&cache), 1, "a tweaked band frequency kept the old sprite");
+
+        cache.clear();
+        assert!(cache.impostors.is_empty());
+    }
+}
@@ -0,0 +1,228 @@
+#![allow(dead_code)]
+
+use std::io::{self, Read, Write};
+
+use raylib::prelude::*;
+
+/// Keys the app can read, in the order of their bits in `InputState`: keys missing here
+/// always read as up. New keys go at the end, so the bits of the others stay put.
+const KEYS: [KeyboardKey; 82] = [
+    KeyboardKey::KEY_A, KeyboardKey::KEY_B, KeyboardKey::KEY_C, KeyboardKey::KEY_D, KeyboardKey::KEY_E,
+    KeyboardKey::KEY_F, KeyboardKey::KEY_G, KeyboardKey::KEY_H, KeyboardKey::KEY_I, KeyboardKey::KEY_J,
+    KeyboardKey::KEY_K, KeyboardKey::KEY_L, KeyboardKey::KEY_M, KeyboardKey::KEY_N, KeyboardKey::KEY_O,
+    KeyboardKey::KEY_P, KeyboardKey::KEY_Q, KeyboardKey::KEY_R, KeyboardKey::KEY_S, KeyboardKey::KEY_T,
+    KeyboardKey::KEY_U, KeyboardKey::KEY_V, KeyboardKey::KEY_W, KeyboardKey::KEY_X, KeyboardKey::KEY_Y,
+    KeyboardKey::KEY_Z,
+    KeyboardKey::KEY_ZERO, KeyboardKey::KEY_ONE, KeyboardKey::KEY_TWO, KeyboardKey::KEY_THREE, KeyboardKey::KEY_FOUR,
+    KeyboardKey::KEY_FIVE, KeyboardKey::KEY_SIX, KeyboardKey::KEY_SEVEN, KeyboardKey::KEY_EIGHT, KeyboardKey::KEY_NINE,
+    KeyboardKey::KEY_F1, KeyboardKey::KEY_F2, KeyboardKey::KEY_F3, KeyboardKey::KEY_F4, KeyboardKey::KEY_F5,
+    KeyboardKey::KEY_F6, KeyboardKey::KEY_F7, KeyboardKey::KEY_F8, KeyboardKey::KEY_F9, KeyboardKey::KEY_F10,
+    KeyboardKey::KEY_F11, KeyboardKey::KEY_F12,
+    KeyboardKey::KEY_UP, KeyboardKey::KEY_DOWN, KeyboardKey::KEY_LEFT, KeyboardKey::KEY_RIGHT,
+    KeyboardKey::KEY_PAGE_UP, KeyboardKey::KEY_PAGE_DOWN, KeyboardKey::KEY_HOME, KeyboardKey::KEY_END,
+    KeyboardKey::KEY_SPACE, KeyboardKey::KEY_ENTER, KeyboardKey::KEY_TAB, KeyboardKey::KEY_BACKSPACE,
+    KeyboardKey::KEY_ESCAPE, KeyboardKey::KEY_INSERT, KeyboardKey::KEY_DELETE,
+    KeyboardKey::KEY_GRAVE, KeyboardKey::KEY_MINUS, KeyboardKey::KEY_EQUAL, KeyboardKey::KEY_LEFT_BRACKET,
+    KeyboardKey::KEY_RIGHT_BRACKET, KeyboardKey::KEY_BACKSLASH, KeyboardKey::KEY_SEMICOLON, KeyboardKey::KEY_APOSTROPHE,
+    KeyboardKey::KEY_COMMA, KeyboardKey::KEY_PERIOD, KeyboardKey::KEY_SLASH,
+    KeyboardKey::KEY_LEFT_SHIFT, KeyboardKey::KEY_RIGHT_SHIFT, KeyboardKey::KEY_LEFT_CONTROL,
+    KeyboardKey::KEY_RIGHT_CONTROL, KeyboardKey::KEY_LEFT_ALT, KeyboardKey::KEY_RIGHT_ALT,
+    KeyboardKey::KEY_KP_ADD, KeyboardKey::KEY_KP_SUBTRACT,
+];
+
+/// Mouse buttons, in the order of their bits.
+const BUTTONS: [MouseButton; 3] = [MouseButton::MOUSE_BUTTON_LEFT, MouseButton::MOUSE_BUTTON_RIGHT, MouseButton::MOUSE_BUTTON_MIDDLE];
+
+fn key_bit(key: KeyboardKey) -> u128 {
+    KEYS.iter().position(|k| *k == key).map_or(0, |i| 1 << i)
+}
+
+fn button_bit(button: MouseButton) -> u8 {
+    BUTTONS.iter().position(|b| *b == button).map_or(0, |i| 1 << i)
+}
+
+/// One frame of input: the keyboard and mouse as the app reads them, the frame time, the
+/// window size and whether it's focused or minimized. Everything that reacts to input reads it from here instead of the
+/// window, so a frame can come from the devices or from a replay alike.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InputState {
+    pub dt: f32,
+    pub screen_width: i32,
+    pub screen_height: i32,
+    pub focused: bool,
+    pub minimized: bool,
+    keys_down: u128,
+    keys_pressed: u128,
+    any_key: bool, // Some key went down this frame, listed in KEYS or not
+    buttons_down: u8,
+    buttons_pressed: u8,
+    mouse: (f32, f32),
+    chars: Vec<char>, // Typed text, in order
+}
+
+impl InputState {
+    /// Reads this frame's input from the devices, emptying raylib's key and character
+    /// queues.
+    pub fn capture(window: &mut RaylibHandle) -> Self {
+        let mut state = InputState {
+            dt: window.get_frame_time(),
+            screen_width: window.get_screen_width(),
+            screen_height: window.get_screen_height(),
+            focused: window.is_window_focused(),
+            minimized: window.is_window_minimized(),
+            ..InputState::default()
+        };
+        for (i, key) in KEYS.iter().enumerate() {
+            state.keys_down |= (window.is_key_down(*key) as u128) << i;
+            state.keys_pressed |= (window.is_key_pressed(*key) as u128) << i;
+        }
+        for (i, button) in BUTTONS.iter().enumerate() {
+            state.buttons_down |= (window.is_mouse_button_down(*button) as u8) << i;
+            state.buttons_pressed |= (window.is_mouse_button_pressed(*button) as u8) << i;
+        }
+        let mouse = window.get_mouse_position();
+        state.mouse = (mouse.x, mouse.y);
+        while window.get_key_pressed().is_some() {
+            state.any_key = true;
+        }
+        while let Some(c) = window.get_char_pressed() {
+            state.chars.push(c);
+        }
+        state
+    }
+
+    pub fn is_key_down(&self, key: KeyboardKey) -> bool {
+        self.keys_down & key_bit(key) != 0
+    }
+
+    pub fn is_key_pressed(&self, key: KeyboardKey) -> bool {
+        self.keys_pressed & key_bit(key) != 0
+    }
+
+    /// Whether any key at all went down this frame.
+    pub fn any_key_pressed(&self) -> bool {
+        self.any_key || self.keys_pressed != 0
+    }
+
+    pub fn is_mouse_button_down(&self, button: MouseButton) -> bool {
+        self.buttons_down & button_bit(button) != 0
+    }
+
+    pub fn is_mouse_button_pressed(&self, button: MouseButton) -> bool {
+        self.buttons_pressed & button_bit(button) != 0
+    }
+
+    pub fn get_mouse_position(&self) -> Vector2 {
+        Vector2::new(self.mouse.0, self.mouse.1)
+    }
+
+    /// Characters typed this frame, in order.
+    pub fn chars(&self) -> &[char] {
+        &self.chars
+    }
+
+    /// Appends the frame to a replay: fixed-size fields, little endian, then the typed
+    /// characters with their count first.
+    pub fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(&self.dt.to_le_bytes())?;
+        out.write_all(&(self.screen_width as u16).to_le_bytes())?;
+        out.write_all(&(self.screen_height as u16).to_le_bytes())?;
+        out.write_all(&self.keys_down.to_le_bytes())?;
+        out.write_all(&self.keys_pressed.to_le_bytes())?;
+        let window = self.focused as u8 | (self.minimized as u8) << 1;
+        out.write_all(&[self.any_key as u8, self.buttons_down, self.buttons_pressed, window])?;
+        out.write_all(&self.mouse.0.to_le_bytes())?;
+        out.write_all(&self.mouse.1.to_le_bytes())?;
+        let chars = &self.chars[..self.chars.len().min(u8::MAX as usize)];
+        out.write_all(&[chars.len() as u8])?;
+        for c in chars {
+            out.write_all(&(*c as u32).to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Reads a frame written by `write`. None at the end of the input; an error if it
+    /// ends halfway through a frame.
+    pub fn read(input: &mut impl Read) -> io::Result<Option<Self>> {
+        let mut dt = [0; 4];
+        match input.read_exact(&mut dt) {
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            result => result?,
+        }
+        let mut bytes = |n: usize| -> io::Result<Vec<u8>> {
+            let mut buffer = vec![0; n];
+            input.read_exact(&mut buffer)?;
+            Ok(buffer)
+        };
+        let u16_at = |b: &[u8]| u16::from_le_bytes([b[0], b[1]]) as i32;
+        let f32_at = |b: &[u8]| f32::from_le_bytes([b[0], b[1], b[2], b[3]]);
+        let u128_at = |b: &[u8]| u128::from_le_bytes(b.try_into().expect("16 bytes"));
+
+        let size = bytes(4)?;
+        let keys = bytes(32)?;
+        let flags = bytes(4)?;
+        let mouse = bytes(8)?;
+        let count = bytes(1)?[0] as usize;
+        let chars = bytes(4 * count)?
+            .chunks(4)
+            .map(|c| char::from_u32(u32::from_le_bytes([c[0], c[1], c[2], c[3]])).unwrap_or('?'))
+            .collect();
+        Ok(Some(InputState {
+            dt: f32::from_le_bytes(dt),
+            screen_width: u16_at(&size[0..2]),
+            screen_height: u16_at(&size[2..4]),
+            focused: flags[3] & 1 != 0,
+            minimized: flags[3] & 2 != 0,
+            keys_down: u128_at(&keys[0..16]),
+            keys_pressed: u128_at(&keys[16..32]),
+            any_key: flags[0] != 0,
+            buttons_down: flags[1],
+            buttons_pressed: flags[2],
+            mouse: (f32_at(&mouse[0..4]), f32_at(&mouse[4..8])),
+            chars,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_reads_back_as_written() {
+        let frame = InputState {
+            dt: 1.0 / 60.0,
+            screen_width: 800,
+            screen_height: 600,
+            focused: true,
+            minimized: false,
+            keys_down: key_bit(KeyboardKey::KEY_W) | key_bit(KeyboardKey::KEY_LEFT_CONTROL),
+            keys_pressed: key_bit(KeyboardKey::KEY_F12),
+            any_key: true,
+            buttons_down: button_bit(MouseButton::MOUSE_BUTTON_LEFT),
+            buttons_pressed: 0,
+            mouse: (412.5, 97.25),
+            chars: vec!['t', 'p', ' ', 'é'],
+        };
+        let mut log = Vec::new();
+        frame.write(&mut log).unwrap();
+        InputState::default().write(&mut log).unwrap();
+
+        let mut reader = log.as_slice();
+        let read = InputState::read(&mut reader).unwrap().expect("a frame");
+        assert_eq!(read, frame);
+        assert!(read.is_key_down(KeyboardKey::KEY_W) && !read.is_key_down(KeyboardKey::KEY_S));
+        assert!(read.is_key_pressed(KeyboardKey::KEY_F12));
+        assert!(read.is_mouse_button_down(MouseButton::MOUSE_BUTTON_LEFT));
+        assert_eq!(InputState::read(&mut reader).unwrap(), Some(InputState::default()));
+        assert_eq!(InputState::read(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn frame_cut_short_is_an_error() {
+        let mut log = Vec::new();
+        InputState::default().write(&mut log).unwrap();
+        log.truncate(log.len() - 5);
+        assert!(InputState::read(&mut log.as_slice()).is_err());
+    }
+}
@@ -1,13 +1,305 @@
 #![allow(dead_code)]
 
+use std::f32::consts::PI;
+
 use raylib::prelude::*;
+use serde::{Deserialize, Serialize};
 
 pub struct Light {
     pub position: Vector3,
+    pub color: Vector3,
+    pub intensity: f32,
 }
 
 impl Light {
     pub fn new(position: Vector3) -> Self {
-        Light { position }
+        Light {
+            position,
+            color: Vector3::new(1.0, 1.0, 1.0),
+            intensity: 1.0,
+        }
+    }
+
+    /// Updates intensity and color temperature from the solar activity curve at `time` seconds.
+    pub fn animate(&mut self, time: f32, config: &LightConfig) {
+        let activity = solar_activity(time * config.activity_speed);
+        self.intensity = config.intensity * (1.0 + config.activity_amplitude * (activity * 2.0 - 1.0));
+        let kelvin = config.temperature_min + (config.temperature_max - config.temperature_min) * activity;
+        self.color = kelvin_to_rgb(kelvin);
+    }
+
+    /// Light arriving at `point`, falling off with 1/r² relative to the reference distance.
+    pub fn irradiance_at(&self, point: Vector3, config: &LightConfig) -> Vector3 {
+        let dx = point.x - self.position.x;
+        let dy = point.y - self.position.y;
+        let dz = point.z - self.position.z;
+        let dist_sq = (dx * dx + dy * dy + dz * dz).max(1e-4);
+        let falloff = (config.reference_distance * config.reference_distance / dist_sq).min(config.max_irradiance);
+        let e = self.intensity * falloff;
+        Vector3::new(self.color.x * e, self.color.y * e, self.color.z * e)
+    }
+}
+
+/// A body that can block sunlight, as its bounding sphere.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Occluder {
+    pub center: Vector3,
+    pub radius: f32,
+}
+
+/// Area where two discs of radii `r1` and `r2` whose centers are `d` apart overlap.
+pub fn disc_overlap(r1: f32, r2: f32, d: f32) -> f32 {
+    let (r1, r2, d) = (r1.max(0.0), r2.max(0.0), d.abs());
+    if d >= r1 + r2 {
+        return 0.0;
+    }
+    if d <= (r1 - r2).abs() {
+        return PI * r1.min(r2).powi(2);
+    }
+    // Two circular segments, one cut from each disc by the chord through both intersections
+    let a1 = ((d * d + r1 * r1 - r2 * r2) / (2.0 * d * r1)).clamp(-1.0, 1.0).acos();
+    let a2 = ((d * d + r2 * r2 - r1 * r1) / (2.0 * d * r2)).clamp(-1.0, 1.0).acos();
+    let kite = ((-d + r1 + r2) * (d + r1 - r2) * (d - r1 + r2) * (d + r1 + r2)).max(0.0).sqrt();
+    r1 * r1 * a1 + r2 * r2 * a2 - 0.5 * kite
+}
+
+/// Fraction of the sun's disc (radius `sun_radius` around `light`) seen from `point`.
+/// Each occluder hides as much of it as its own disc overlaps, as seen from there: no
+/// sun in the umbra, a soft penumbra around it and, behind an occluder that looks
+/// smaller than the sun, an antumbra where only a ring of the sun is covered. A
+/// `sun_radius` of 0 is a point light with hard shadows. Occluders that contain the
+/// light (the sun's own sphere) are ignored.
+pub fn sun_visibility(point: Vector3, light: Vector3, sun_radius: f32, occluders: &[Occluder]) -> f32 {
+    let to_light = light - point;
+    let distance = to_light.length();
+    if distance < 1e-6 {
+        return 1.0;
+    }
+    let dir = to_light / distance;
+    let sun_angle = (sun_radius / distance).clamp(0.0, 1.0).asin();
+    let mut visible = 1.0;
+    for o in occluders {
+        if (light - o.center).length() <= o.radius {
+            continue;
+        }
+        let v = o.center - point;
+        let t = v.dot(dir);
+        if t <= 0.0 || t >= distance {
+            continue;
+        }
+        let occluder_distance = v.length();
+        if occluder_distance <= o.radius {
+            return 0.0;
+        }
+        let occluder_angle = (o.radius / occluder_distance).asin();
+        let separation = (t / occluder_distance).clamp(-1.0, 1.0).acos();
+        if sun_angle <= 0.0 {
+            if separation < occluder_angle {
+                return 0.0;
+            }
+            continue;
+        }
+        let covered = disc_overlap(sun_angle, occluder_angle, separation) / (PI * sun_angle * sun_angle);
+        visible *= 1.0 - covered.min(1.0);
+    }
+    visible
+}
+
+/// Soft dark blob on a body's surface right under a craft flying low over it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ContactShadow {
+    pub center: Vector3,     // Center of the body the shadow falls on
+    pub direction: Vector3,  // Unit vector from that center towards the craft
+    pub angular_radius: f32, // Radius of the blob seen from the body's center, in radians
+    pub strength: f32,       // Darkening at the middle of the blob, 0 to 1
+}
+
+impl ContactShadow {
+    /// Light left at `point` on the body: 1 outside the blob, falling off smoothly to
+    /// `1 - strength` under the craft.
+    pub fn factor(&self, point: Vector3) -> f32 {
+        let to_point = point - self.center;
+        let length = to_point.length();
+        if length <= 0.0 {
+            return 1.0;
+        }
+        let angle = (to_point.dot(self.direction) / length).clamp(-1.0, 1.0).acos();
+        let t = (angle / self.angular_radius.max(1e-4)).min(1.0);
+        let falloff = 1.0 - t * t * (3.0 - 2.0 * t);
+        1.0 - self.strength * falloff
+    }
+}
+
+/// Secondary light reflected off another body (e.g. earthshine on the moon).
+/// Diffuse only: it never adds a specular highlight.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BounceLight {
+    pub position: Vector3,
+    pub irradiance: Vector3,
+}
+
+/// Earthshine reaching `moon` from `earth`. The earth's illuminated fraction as seen
+/// from the moon comes from the sun-earth-moon angle: full earth when the moon sits
+/// between the earth and the sun, new earth when it's behind the earth.
+pub fn earthshine(sun: Vector3, earth: Vector3, moon: Vector3, earth_irradiance: Vector3, config: &LightConfig) -> BounceLight {
+    let to_sun = (sun - earth).normalized();
+    let to_moon = (moon - earth).normalized();
+    let lit_fraction = (1.0 + to_sun.dot(to_moon)) * 0.5;
+
+    // Blue-grey tint of sunlight scattered by oceans and atmosphere
+    let tint = Vector3::new(0.55, 0.65, 0.85);
+    let k = config.earthshine * lit_fraction;
+    BounceLight {
+        position: earth,
+        irradiance: Vector3::new(
+            earth_irradiance.x * tint.x * k,
+            earth_irradiance.y * tint.y * k,
+            earth_irradiance.z * tint.z * k,
+        ),
+    }
+}
+
+/// Tunables for the animated sun, read from the `[light]` table of the scene file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LightConfig {
+    pub intensity: f32,
+    pub activity_amplitude: f32, // Fraction of intensity the activity curve adds or removes
+    pub activity_speed: f32,     // Activity curve cycles per second (roughly)
+    pub temperature_min: f32,    // Kelvin at minimum activity
+    pub temperature_max: f32,    // Kelvin at maximum activity
+    pub reference_distance: f32, // Distance at which irradiance equals `intensity`
+    pub max_irradiance: f32,     // Cap on the 1/r² boost for bodies very close to the sun
+    pub ambient: f32,    // Scale of the light from the skybox on unlit sides
+    pub irradiance: bool, // Ambient from the side of the sky each surface faces; off, a two-tone fill from the sky's upper and lower halves
+    pub earthshine: f32, // Fraction of the earth's sunlight bounced onto the moon when the earth is full
+}
+
+impl Default for LightConfig {
+    fn default() -> Self {
+        LightConfig {
+            intensity: 1.0,
+            activity_amplitude: 0.25,
+            activity_speed: 0.2,
+            temperature_min: 5000.0,
+            temperature_max: 6000.0,
+            reference_distance: 35.0,
+            max_irradiance: 2.5,
+            ambient: 0.6,
+            irradiance: true,
+            earthshine: 0.12,
+        }
+    }
+}
+
+fn hash1(n: f32) -> f32 {
+    (n.sin() * 43758.547).fract().abs()
+}
+
+/// Smooth 1D value noise with two octaves, in [0, 1].
+pub fn solar_activity(t: f32) -> f32 {
+    let octave = |t: f32| {
+        let i = t.floor();
+        let f = t - i;
+        let u = f * f * (3.0 - 2.0 * f);
+        hash1(i) * (1.0 - u) + hash1(i + 1.0) * u
+    };
+    (octave(t) * 0.7 + octave(t * 2.3 + 17.0) * 0.3).clamp(0.0, 1.0)
+}
+
+/// Approximate blackbody color for a temperature in Kelvin (1000K - 40000K),
+/// normalized to [0, 1] per channel.
+pub fn kelvin_to_rgb(kelvin: f32) -> Vector3 {
+    let t = kelvin.clamp(1000.0, 40000.0) / 100.0;
+
+    let r = if t <= 66.0 {
+        255.0
+    } else {
+        329.698_73 * (t - 60.0).powf(-0.133_204_76)
+    };
+    let g = if t <= 66.0 {
+        99.470_8 * t.ln() - 161.119_57
+    } else {
+        288.122_17 * (t - 60.0).powf(-0.075_514_85)
+    };
+    let b = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.517_73 * (t - 10.0).ln() - 305.044_8
+    };
+
+    Vector3::new(
+        (r / 255.0).clamp(0.0, 1.0),
+        (g / 255.0).clamp(0.0, 1.0),
+        (b / 255.0).clamp(0.0, 1.0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-4
+    }
+
+    #[test]
+    fn disc_overlap_matches_known_cases() {
+        // Apart, touching from outside, one inside the other, touching from inside
+        assert_eq!(disc_overlap(1.0, 0.5, 2.0), 0.0);
+        assert!(close(disc_overlap(1.0, 0.5, 1.5), 0.0));
+        assert!(close(disc_overlap(1.0, 0.5, 0.2), PI * 0.25));
+        assert!(close(disc_overlap(1.0, 0.5, 0.5), PI * 0.25));
+        // Same disc
+        assert!(close(disc_overlap(0.3, 0.3, 0.0), PI * 0.09));
+        // Two unit discs, each through the other's center: 2π/3 - √3/2
+        assert!(close(disc_overlap(1.0, 1.0, 1.0), 2.0 * PI / 3.0 - 3f32.sqrt() / 2.0));
+        // Unit disc and a disc of radius √2 centered on its rim, crossing at right angles: π - 1
+        assert!(close(disc_overlap(1.0, 2f32.sqrt(), 1.0), PI - 1.0));
+        // Symmetric in the two radii
+        assert!(close(disc_overlap(0.7, 0.4, 0.6), disc_overlap(0.4, 0.7, 0.6)));
+    }
+
+    // A sun of radius 2 at the origin and a point 30 away along +X, with an occluder in between
+    const SUN_RADIUS: f32 = 2.0;
+
+    fn visibility_behind(occluder: Occluder, sideways: f32) -> f32 {
+        sun_visibility(Vector3::new(30.0, sideways, 0.0), Vector3::zero(), SUN_RADIUS, &[occluder])
+    }
+
+    #[test]
+    fn shadow_has_umbra_penumbra_and_antumbra() {
+        // Big occluder close to the point: the sun is hidden, then partly, then not at all
+        let big = Occluder { center: Vector3::new(26.0, 0.0, 0.0), radius: 1.0 };
+        assert_eq!(visibility_behind(big, 0.0), 0.0);
+        let partial = visibility_behind(big, 1.0);
+        assert!(partial > 0.0 && partial < 1.0, "{}", partial);
+        assert_eq!(visibility_behind(big, 2.0), 1.0);
+
+        // Small occluder far from the point: it looks smaller than the sun and only dims it
+        let small = Occluder { center: Vector3::new(10.0, 0.0, 0.0), radius: 0.2 };
+        let sun_angle = (SUN_RADIUS / 30.0f32).asin();
+        let occluder_angle = (0.2f32 / 20.0).asin();
+        let annular = visibility_behind(small, 0.0);
+        assert!(close(annular, 1.0 - (occluder_angle / sun_angle).powi(2)), "{}", annular);
+
+        // A point sun gives the old hard shadow
+        assert_eq!(sun_visibility(Vector3::new(30.0, 0.9, 0.0), Vector3::zero(), 0.0, &[big]), 0.0);
+        assert_eq!(sun_visibility(Vector3::new(30.0, 1.5, 0.0), Vector3::zero(), 0.0, &[big]), 1.0);
+    }
+
+    #[test]
+    fn penumbra_widens_with_the_occluders_distance() {
+        // Sideways distance over which the light goes from 5% to 95%
+        let penumbra_width = |gap: f32| {
+            let occluder = Occluder { center: Vector3::new(30.0 - gap, 0.0, 0.0), radius: 0.5 };
+            let offsets: Vec<f32> = (0..4000).map(|i| i as f32 * 0.001).collect();
+            let first = |level: f32| *offsets.iter().find(|y| visibility_behind(occluder, **y) > level).unwrap();
+            first(0.95) - first(0.05)
+        };
+        let (near, far) = (penumbra_width(2.0), penumbra_width(6.0));
+        assert!(near > 0.05 && far > 2.0 * near, "{} {}", near, far);
     }
 }
@@ -0,0 +1,355 @@
+#![allow(dead_code)]
+
+use raylib::prelude::*;
+
+use crate::framebuffer::Framebuffer;
+use crate::input::InputState;
+use crate::line::polyline_additive;
+use crate::matrix::{view_ray, world_to_screen};
+use crate::measure::MeasureConfig;
+use crate::scene::SceneObject;
+use crate::Uniforms;
+
+/// Key that shows and hides the light travel panel.
+pub const LIGHT_PANEL_KEY: KeyboardKey = KeyboardKey::KEY_Y;
+/// Key that sends a wavefront of light out from the focused body.
+pub const WAVEFRONT_KEY: KeyboardKey = KeyboardKey::KEY_U;
+
+/// Seconds real light takes to cross one AU.
+pub const REAL_AU_SECONDS: f32 = 499.0;
+
+/// Wavefronts fade out over this fraction of the system's size once past its edge.
+const FADE: f32 = 0.25;
+const SHELL_COLOR: Vector3 = Vector3 { x: 1.0, y: 0.95, z: 0.6 };
+/// Glow of the shell seen face-on; towards its limb, where the line of sight runs along
+/// it, it brightens up to `MAX_LIMB` times.
+const SHELL_GLOW: f32 = 0.03;
+const MAX_LIMB: f32 = 8.0;
+/// Brightness of the circle where the shell crosses the orbital plane.
+const RING_GLOW: f32 = 0.6;
+const RING_SEGMENTS: usize = 96;
+/// Most wavefronts out at once, each a ray-cast over its shell's stretch of the screen;
+/// sending another drops the oldest.
+const MAX_WAVEFRONTS: usize = 3;
+/// Bodies listed in the panel, nearest first.
+const PANEL_BODIES: usize = 10;
+
+/// Simulated seconds light takes to cover `distance` scene units at the scene's scale,
+/// or None when `[measure] au` isn't set.
+pub fn light_seconds(distance: f32, config: &MeasureConfig) -> Option<f32> {
+    let au = config.au.filter(|&au| au > 0.0)?;
+    Some(distance / au * config.light_seconds)
+}
+
+/// "light 8.3 s (8 min 19 s real)" for `distance` scene units, or None without a scale.
+pub fn format_light_time(distance: f32, config: &MeasureConfig) -> Option<String> {
+    let seconds = light_seconds(distance, config)?;
+    let real = (light_seconds(distance, &MeasureConfig { light_seconds: REAL_AU_SECONDS, ..config.clone() })? + 0.5) as u32;
+    let real = match real {
+        0..60 => format!("{} s", real),
+        60..3600 => format!("{} min {} s", real / 60, real % 60),
+        _ => format!("{} h {} min", real / 3600, real / 60 % 60),
+    };
+    Some(format!("light {:.1} s ({} real)", seconds, real))
+}
+
+/// A shell of light sent out from a body, growing at the scene's speed of light.
+struct Wavefront {
+    source: String,
+    origin: Vector3, // Where the body was when it left; light doesn't follow its source
+    emitted: f32,    // Simulated time it left
+    edge: f32,       // Distance from the origin to the farthest body, where it starts to fade
+    reached: Vec<(String, f32)>, // Bodies it has passed, with the simulated seconds it took
+}
+
+impl Wavefront {
+    fn radius(&self, time: f32, speed: f32) -> f32 {
+        (time - self.emitted).max(0.0) * speed
+    }
+
+    /// 1 until the shell leaves the system, then down to 0 over `FADE` of its size.
+    fn fade(&self, radius: f32) -> f32 {
+        (1.0 - (radius - self.edge) / (self.edge * FADE).max(1e-3)).clamp(0.0, 1.0)
+    }
+}
+
+/// Light travel times between bodies, for teaching how long light takes across the
+/// system: a panel with the time from the focused body to the others, and wavefronts
+/// sent out from it that sweep across the system at the scene's speed of light.
+pub struct LightTravel {
+    pub panel: bool,
+    wavefronts: Vec<Wavefront>,
+    message: Option<String>, // Why the last wavefront couldn't be sent
+}
+
+impl LightTravel {
+    pub fn new() -> Self {
+        LightTravel { panel: false, wavefronts: Vec::new(), message: None }
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.wavefronts.is_empty()
+    }
+
+    /// Y shows the panel; U sends a wavefront out from `source` at simulated `time` and
+    /// shows the panel too, so its arrivals can be read off.
+    pub fn process_input(&mut self, input: &InputState, source: Option<&SceneObject>, objects: &[SceneObject], time: f32, config: &MeasureConfig) {
+        if input.is_key_pressed(LIGHT_PANEL_KEY) {
+            self.panel = !self.panel;
+        }
+        if input.is_key_pressed(WAVEFRONT_KEY) {
+            self.message = match source {
+                Some(source) => self.emit(source, objects, time, config).err(),
+                None => Some("no body in focus".to_string()),
+            };
+            self.panel = true;
+        }
+    }
+
+    /// Sends a wavefront out from where `source` is now.
+    pub fn emit(&mut self, source: &SceneObject, objects: &[SceneObject], time: f32, config: &MeasureConfig) -> Result<(), String> {
+        if light_seconds(1.0, config).is_none() {
+            return Err("set [measure] au in the scene file to time light".to_string());
+        }
+        let edge = objects.iter().map(|o| (o.translation - source.translation).length() + o.radius * o.scale).fold(0.0, f32::max);
+        if self.wavefronts.len() >= MAX_WAVEFRONTS {
+            self.wavefronts.remove(0);
+        }
+        self.wavefronts.push(Wavefront {
+            source: source.name.clone(),
+            origin: source.translation,
+            emitted: time,
+            edge,
+            reached: Vec::new(),
+        });
+        Ok(())
+    }
+
+    pub fn clear(&mut self) {
+        self.wavefronts.clear();
+    }
+
+    /// One simulation step at `time`: notes the bodies each wavefront has reached since the
+    /// last, and drops the ones faded out past the system's edge.
+    pub fn step(&mut self, objects: &[SceneObject], time: f32, config: &MeasureConfig) {
+        let Some(speed) = speed(config) else {
+            self.wavefronts.clear();
+            return;
+        };
+        for wavefront in &mut self.wavefronts {
+            let radius = wavefront.radius(time, speed);
+            for obj in objects.iter().filter(|o| o.is_listed() && o.name != wavefront.source) {
+                if (obj.translation - wavefront.origin).length() <= radius && !wavefront.reached.iter().any(|(name, _)| name == &obj.name) {
+                    wavefront.reached.push((obj.name.clone(), time - wavefront.emitted));
+                }
+            }
+        }
+        self.wavefronts.retain(|w| w.fade(w.radius(time, speed)) > 0.0);
+    }
+
+    /// Draws the wavefronts at simulated `time` seen from `eye`: each a faint additive
+    /// shell, brightest along its limb, and a brighter circle where it crosses the orbital
+    /// plane. Depth-tested against the bodies, and nothing written to depth.
+    pub fn draw(&self, framebuffer: &mut Framebuffer, uniforms: &Uniforms, eye: Vector3, time: f32, config: &MeasureConfig) {
+        let Some(speed) = speed(config) else {
+            return;
+        };
+        for wavefront in &self.wavefronts {
+            let radius = wavefront.radius(time, speed);
+            let fade = wavefront.fade(radius);
+            if radius <= 0.0 || fade <= 0.0 {
+                continue;
+            }
+            shell(framebuffer, uniforms, eye, wavefront.origin, radius, fade);
+            let circle: Vec<Vector3> = (0..=RING_SEGMENTS)
+                .map(|i| {
+                    let angle = i as f32 / RING_SEGMENTS as f32 * std::f32::consts::TAU;
+                    wavefront.origin + Vector3::new(angle.cos(), 0.0, angle.sin()) * radius
+                })
+                .collect();
+            polyline_additive(framebuffer, uniforms, &circle, SHELL_COLOR * (RING_GLOW * fade));
+        }
+    }
+
+    /// Text of the panel: light time from `focused` to the nearest other bodies, then each
+    /// wavefront's age and the bodies it has reached so far.
+    pub fn panel_lines(&self, focused: Option<&SceneObject>, objects: &[SceneObject], time: f32, config: &MeasureConfig) -> Vec<String> {
+        let mut lines = Vec::new();
+        match focused {
+            Some(focused) => {
+                lines.push(format!("Light from {} (Y hides, U sends a wavefront)", focused.name));
+                let mut others: Vec<(&SceneObject, f32)> = objects
+                    .iter()
+                    .filter(|o| o.is_listed() && o.name != focused.name)
+                    .map(|o| (o, (o.translation - focused.translation).length()))
+                    .collect();
+                others.sort_by(|a, b| a.1.total_cmp(&b.1));
+                for (obj, distance) in others.into_iter().take(PANEL_BODIES) {
+                    let light = format_light_time(distance, config).unwrap_or_else(|| "set [measure] au to time light".to_string());
+                    lines.push(format!("  {}: {}", obj.name, light));
+                }
+            }
+            None => lines.push("Light travel (Y hides): no body in focus".to_string()),
+        }
+        if let Some(message) = &self.message {
+            lines.push(format!("Can't send a wavefront: {}", message));
+        }
+        for wavefront in &self.wavefronts {
+            let mut line = format!("Wavefront from {}: {:.1} s", wavefront.source, time - wavefront.emitted);
+            for (i, (name, seconds)) in wavefront.reached.iter().enumerate() {
+                line += &format!("{}{} {:.1} s", if i == 0 { ", reached " } else { ", " }, name, seconds);
+            }
+            lines.push(line);
+        }
+        lines
+    }
+
+    /// Draws the panel in the bottom-right corner of the window.
+    pub fn draw_panel(&self, d: &mut RaylibDrawHandle, width: i32, height: i32, lines: &[String]) {
+        if !self.panel {
+            return;
+        }
+        let top = height - 20 - 14 * lines.len() as i32;
+        let left = width - 10 - lines.iter().map(|l| measure_text(l, 10)).max().unwrap_or(0);
+        d.draw_rectangle(left - 4, top - 4, width - left, 14 * lines.len() as i32 + 6, Color::new(0, 0, 0, 170));
+        for (i, line) in lines.iter().enumerate() {
+            d.draw_text(line, left, top + 14 * i as i32, 10, Color::new(255, 242, 153, 255));
+        }
+    }
+}
+
+/// Scene units light covers in a simulated second.
+fn speed(config: &MeasureConfig) -> Option<f32> {
+    light_seconds(1.0, config).filter(|&s| s > 0.0).map(|s| 1.0 / s)
+}
+
+/// Pixels a sphere of `radius` around `center` can cover, as (min x, min y, max x, max y):
+/// the screen bounds of the cube around it, or the whole screen once the cube reaches
+/// behind the eye.
+fn shell_bounds(uniforms: &Uniforms, center: Vector3, radius: f32, width: u32, height: u32) -> (i32, i32, i32, i32) {
+    let whole = (0, 0, width as i32 - 1, height as i32 - 1);
+    let (p, v, vp) = (&uniforms.frame.projection, &uniforms.frame.view, &uniforms.frame.viewport);
+    let (mut min, mut max) = (Vector2::new(f32::INFINITY, f32::INFINITY), Vector2::new(f32::NEG_INFINITY, f32::NEG_INFINITY));
+    for corner in 0..8 {
+        let sign = |bit: i32| if corner & bit == 0 { -radius } else { radius };
+        let Some(screen) = world_to_screen(center + Vector3::new(sign(1), sign(2), sign(4)), v, p, vp) else {
+            return whole;
+        };
+        min = Vector2::new(min.x.min(screen.x), min.y.min(screen.y));
+        max = Vector2::new(max.x.max(screen.x), max.y.max(screen.y));
+    }
+    (
+        (min.x.floor() as i32).max(whole.0),
+        (min.y.floor() as i32).max(whole.1),
+        (max.x.ceil() as i32).min(whole.2),
+        (max.y.ceil() as i32).min(whole.3),
+    )
+}
+
+/// Ray-casts a sphere of `radius` around `center` and adds its glow at both crossings of
+/// the view ray of every pixel it can cover.
+fn shell(framebuffer: &mut Framebuffer, uniforms: &Uniforms, eye: Vector3, center: Vector3, radius: f32, fade: f32) {
+    let (p, v, vp) = (&uniforms.frame.projection, &uniforms.frame.view, &uniforms.frame.viewport);
+    let (right, up, back) = (Vector3::new(v.m0, v.m4, v.m8), Vector3::new(v.m1, v.m5, v.m9), Vector3::new(v.m2, v.m6, v.m10));
+    let offset = eye - center;
+    let c = offset.dot(offset) - radius * radius;
+    let (min_x, min_y, max_x, max_y) = shell_bounds(uniforms, center, radius, framebuffer.width, framebuffer.height);
+    for y in min_y..=max_y {
+        for x in min_x..=max_x {
+            let ray = view_ray(p, (x as f32 + 0.5 - vp.m12) / vp.m0, (y as f32 + 0.5 - vp.m13) / vp.m5);
+            let dir = (right * ray.x + up * ray.y + back * ray.z).normalized();
+            let b = offset.dot(dir);
+            let discriminant = b * b - c;
+            if discriminant <= 0.0 {
+                continue;
+            }
+            let root = discriminant.sqrt();
+            for t in [-b - root, -b + root] {
+                if t <= 0.0 {
+                    continue;
+                }
+                let point = eye + dir * t;
+                let Some(screen) = world_to_screen(point, v, p, vp) else {
+                    continue;
+                };
+                let facing = dir.dot((point - center) / radius).abs().max(1.0 / MAX_LIMB);
+                framebuffer.add_point(x, y, SHELL_COLOR * (SHELL_GLOW * fade / facing), screen.z);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::default_scene;
+
+    #[test]
+    fn wavefront_reaches_earth_after_the_listed_light_time() {
+        let (scene, objects) = default_scene();
+        let config = &scene.measure;
+        let sun = objects.iter().find(|o| o.object_type == "sun").expect("a sun");
+        let earth = objects.iter().find(|o| o.object_type == "earth").expect("an earth");
+        let distance = (earth.translation - sun.translation).length();
+        let expected = light_seconds(distance, config).expect("the default scene sets au");
+
+        let mut light = LightTravel::new();
+        light.emit(sun, &objects, 10.0, config).unwrap();
+        let lines = light.panel_lines(Some(sun), &objects, 10.0, config);
+        assert!(lines.iter().any(|l| l.contains(&earth.name) && l.contains(&format!("light {:.1} s", expected))), "{:?}", lines);
+
+        // Bodies stay put here; stepping in 1/120 s, earth is reached within a step of the listed time
+        let mut time = 10.0;
+        while light.wavefronts[0].reached.iter().all(|(name, _)| name != &earth.name) {
+            time += 1.0 / 120.0;
+            light.step(&objects, time, config);
+            assert!(time < 10.0 + expected * 2.0, "never reached {}", earth.name);
+        }
+        let (_, took) = light.wavefronts[0].reached.iter().find(|(name, _)| name == &earth.name).unwrap();
+        assert!((took - expected).abs() <= 1.0 / 120.0 + 1e-3, "{} s, expected {} s", took, expected);
+
+        // and once past the edge of the system it fades out and is dropped
+        light.step(&objects, 10.0 + expected * 1000.0, config);
+        assert!(!light.is_active());
+        assert!(LightTravel::new().emit(sun, &objects, 0.0, &MeasureConfig { au: None, ..config.clone() }).is_err());
+    }
+
+    #[test]
+    fn shells_are_cast_over_their_own_stretch_of_the_screen() {
+        use crate::matrix::{create_projection_matrix, create_view_matrix, create_viewport_matrix};
+        use crate::{FrameUniforms, ObjectUniforms};
+
+        let eye = Vector3::new(0.0, 0.0, 50.0);
+        let view = create_view_matrix(eye, Vector3::zero(), Vector3::new(0.0, 1.0, 0.0));
+        let projection = create_projection_matrix(std::f32::consts::PI / 3.0, 1.0, 0.1, 1000.0);
+        let viewport = create_viewport_matrix(0.0, 0.0, 200.0, 200.0);
+        let frame = FrameUniforms::new(view, projection, viewport, eye);
+        let uniforms = Uniforms { frame, object: ObjectUniforms::new(Matrix::identity(), &frame) };
+
+        // A small shell far off sits in a small box around its center
+        let (min_x, min_y, max_x, max_y) = shell_bounds(&uniforms, Vector3::zero(), 2.0, 200, 200);
+        assert!(min_x > 80 && max_x < 120 && min_y > 80 && max_y < 120, "{:?}", (min_x, min_y, max_x, max_y));
+        // and one around the eye covers the whole screen
+        assert_eq!(shell_bounds(&uniforms, Vector3::zero(), 60.0, 200, 200), (0, 0, 199, 199));
+    }
+
+    #[test]
+    fn sending_past_the_limit_drops_the_oldest_wavefront() {
+        let (scene, objects) = default_scene();
+        let sun = objects.iter().find(|o| o.object_type == "sun").expect("a sun");
+        let mut light = LightTravel::new();
+        for time in 0..=MAX_WAVEFRONTS {
+            light.emit(sun, &objects, time as f32, &scene.measure).unwrap();
+        }
+        assert_eq!(light.wavefronts.len(), MAX_WAVEFRONTS);
+        assert_eq!(light.wavefronts[0].emitted, 1.0);
+    }
+
+    #[test]
+    fn real_light_time_reads_in_minutes() {
+        let config = MeasureConfig { au: Some(10.0), ..MeasureConfig::default() };
+        assert_eq!(format_light_time(10.0, &config).unwrap(), "light 8.3 s (8 min 19 s real)");
+        assert_eq!(format_light_time(0.1, &config).unwrap(), "light 0.1 s (5 s real)");
+        assert!(format_light_time(10.0, &MeasureConfig::default()).is_none());
+    }
+}
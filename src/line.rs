@@ -1,8 +1,11 @@
 #![allow(dead_code)]
 
 use crate::fragment::Fragment;
+use crate::framebuffer::{Framebuffer, PixelTarget};
+use crate::matrix::multiply_matrix_vector4;
 use crate::vertex::Vertex;
-use raylib::math::Vector3;
+use crate::Uniforms;
+use raylib::math::{Vector3, Vector4};
 
 pub fn line(a: &Vertex, b: &Vertex) -> Vec<Fragment> {
     let mut fragments = Vec::new();
@@ -22,9 +25,13 @@ pub fn line(a: &Vertex, b: &Vertex) -> Vec<Fragment> {
     let sy = if y0 < y1 { 1 } else { -1 };
 
     let mut err = if dx > dy { dx / 2 } else { -dy / 2 };
+    let steps = dx.max(dy).max(1) as f32;
+    let mut step = 0;
 
     loop {
-        let z = start.z + (end.z - start.z) * (x0 - start.x as i32) as f32 / (end.x - start.x) as f32;
+        // Interpolate depth by progress along the major axis (also works for vertical lines)
+        let z = start.z + (end.z - start.z) * step as f32 / steps;
+        step += 1;
         // For now, we'll just use white for the line color.
         // A more advanced implementation would interpolate the vertex colors.
         fragments.push(Fragment::new(x0 as f32, y0 as f32, Vector3::new(1.0, 1.0, 1.0), z));
@@ -44,3 +51,145 @@ pub fn line(a: &Vertex, b: &Vertex) -> Vec<Fragment> {
 
     fragments
 }
+
+/// Antialiased line between screen positions `a` and `b` (x, y in pixels, z = depth),
+/// `thickness` pixels wide with round caps. Calls `plot(x, y, coverage, depth)` for every
+/// pixel the line touches inside `width` × `height`; coverage is in (0, 1] and depth is
+/// interpolated along the line. Walks the major axis so long lines only visit a thin band.
+pub fn raster_line<F: FnMut(i32, i32, f32, f32)>(a: Vector3, b: Vector3, thickness: f32, width: u32, height: u32, mut plot: F) {
+    let half = (thickness * 0.5).max(0.5);
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let length_sq = dx * dx + dy * dy;
+
+    // Coverage from the distance of the pixel center to the segment, and depth at the closest point
+    let mut sample = |x: i32, y: i32| {
+        let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+        let t = if length_sq > 0.0 { (((px - a.x) * dx + (py - a.y) * dy) / length_sq).clamp(0.0, 1.0) } else { 0.0 };
+        let (cx, cy) = (a.x + dx * t, a.y + dy * t);
+        let distance = ((px - cx).powi(2) + (py - cy).powi(2)).sqrt();
+        let coverage = (half + 0.5 - distance).min(1.0);
+        if coverage > 0.0 {
+            plot(x, y, coverage, a.z + (b.z - a.z) * t);
+        }
+    };
+
+    let (w, h) = (width as i32, height as i32);
+    let reach = half + 1.0;
+    if dx.abs() >= dy.abs() {
+        // Mostly horizontal: one short column of pixels per x
+        let slope = if dx != 0.0 { dy / dx } else { 0.0 };
+        let span = reach * (1.0 + slope * slope).sqrt();
+        let x_start = ((a.x.min(b.x) - reach).floor() as i32).max(0);
+        let x_end = ((a.x.max(b.x) + reach).ceil() as i32).min(w - 1);
+        for x in x_start..=x_end {
+            let along = (x as f32 + 0.5 - a.x).clamp(dx.min(0.0), dx.max(0.0));
+            let cy = a.y + along * slope;
+            let y_start = ((cy - span).floor() as i32).max(0);
+            let y_end = ((cy + span).ceil() as i32).min(h - 1);
+            for y in y_start..=y_end {
+                sample(x, y);
+            }
+        }
+    } else {
+        let slope = dx / dy;
+        let span = reach * (1.0 + slope * slope).sqrt();
+        let y_start = ((a.y.min(b.y) - reach).floor() as i32).max(0);
+        let y_end = ((a.y.max(b.y) + reach).ceil() as i32).min(h - 1);
+        for y in y_start..=y_end {
+            let along = (y as f32 + 0.5 - a.y).clamp(dy.min(0.0), dy.max(0.0));
+            let cx = a.x + along * slope;
+            let x_start = ((cx - span).floor() as i32).max(0);
+            let x_end = ((cx + span).ceil() as i32).min(w - 1);
+            for x in x_start..=x_end {
+                sample(x, y);
+            }
+        }
+    }
+}
+
+/// Projects a world-space segment to the screen, clipping it against the near plane
+/// first so segments passing beside or behind the camera keep their visible part.
+fn project_segment(uniforms: &Uniforms, a: Vector3, b: Vector3) -> Option<(Vector3, Vector3)> {
+    let clip = |p: Vector3| {
+        let view = multiply_matrix_vector4(&uniforms.frame.view, &Vector4::new(p.x, p.y, p.z, 1.0));
+        multiply_matrix_vector4(&uniforms.frame.projection, &view)
+    };
+    let (mut ca, mut cb) = (clip(a), clip(b));
+
+    // Signed distance to the near plane in clip space (z = -w); inside when positive
+    let (da, db) = (ca.z + ca.w, cb.z + cb.w);
+    if da < 0.0 && db < 0.0 {
+        return None;
+    }
+    let lerp = |p: Vector4, q: Vector4, t: f32| {
+        Vector4::new(p.x + (q.x - p.x) * t, p.y + (q.y - p.y) * t, p.z + (q.z - p.z) * t, p.w + (q.w - p.w) * t)
+    };
+    if da < 0.0 {
+        ca = lerp(ca, cb, da / (da - db));
+    } else if db < 0.0 {
+        cb = lerp(cb, ca, db / (db - da));
+    }
+
+    let to_screen = |c: Vector4| {
+        let w = c.w.max(1e-6);
+        let ndc = Vector4::new(c.x / w, c.y / w, c.z / w, 1.0);
+        let screen = multiply_matrix_vector4(&uniforms.frame.viewport, &ndc);
+        Vector3::new(screen.x, screen.y, screen.z)
+    };
+    Some((to_screen(ca), to_screen(cb)))
+}
+
+/// Draws a world-space polyline into the framebuffer, antialiased and depth-tested
+/// against the scene.
+pub fn polyline(framebuffer: &mut Framebuffer, uniforms: &Uniforms, points: &[Vector3], color: Vector3) {
+    polyline_thick(framebuffer, uniforms, points, color, 1.0);
+}
+
+/// `polyline` with a line width in pixels.
+pub fn polyline_thick(framebuffer: &mut Framebuffer, uniforms: &Uniforms, points: &[Vector3], color: Vector3, thickness: f32) {
+    let (width, height) = framebuffer.size();
+    for pair in points.windows(2) {
+        if let Some((a, b)) = project_segment(uniforms, pair[0], pair[1]) {
+            raster_line(a, b, thickness, width, height, |x, y, coverage, depth| {
+                framebuffer.blend_point(x, y, color, coverage, depth);
+            });
+        }
+    }
+}
+
+/// `polyline` fading in along its length: transparent at the first point, opaque at
+/// the last. Depth-tested, but only the opaque half writes depth.
+pub fn polyline_fading(framebuffer: &mut Framebuffer, uniforms: &Uniforms, points: &[Vector3], color: Vector3) {
+    let last = points.len().saturating_sub(1).max(1) as f32;
+    for (i, pair) in points.windows(2).enumerate() {
+        segment_fading(framebuffer, uniforms, pair[0], pair[1], color, (i as f32 / last, (i + 1) as f32 / last));
+    }
+}
+
+/// One world-space segment whose opacity goes from `alphas.0` at `a` to `alphas.1` at
+/// `b`, antialiased and depth-tested. Only pixels at least half opaque write depth.
+pub fn segment_fading(framebuffer: &mut Framebuffer, uniforms: &Uniforms, a: Vector3, b: Vector3, color: Vector3, alphas: (f32, f32)) {
+    let (width, height) = framebuffer.size();
+    let (alpha_a, alpha_b) = alphas;
+    if let Some((a, b)) = project_segment(uniforms, a, b) {
+        let length = (b.x - a.x).hypot(b.y - a.y).max(1e-3);
+        raster_line(a, b, 1.0, width, height, |x, y, coverage, depth| {
+            // How far along the segment this pixel is, for the alpha
+            let t = (((x as f32 + 0.5 - a.x) * (b.x - a.x) + (y as f32 + 0.5 - a.y) * (b.y - a.y)) / (length * length)).clamp(0.0, 1.0);
+            framebuffer.blend_point(x, y, color, coverage * (alpha_a + (alpha_b - alpha_a) * t), depth);
+        });
+    }
+}
+
+/// Like `polyline`, but added on top of the scene without writing depth, so faint
+/// guide lines read as translucent and never hide what's behind them.
+pub fn polyline_additive(framebuffer: &mut Framebuffer, uniforms: &Uniforms, points: &[Vector3], color: Vector3) {
+    let (width, height) = framebuffer.size();
+    for pair in points.windows(2) {
+        if let Some((a, b)) = project_segment(uniforms, pair[0], pair[1]) {
+            raster_line(a, b, 1.0, width, height, |x, y, coverage, depth| {
+                framebuffer.add_point(x, y, color * coverage, depth);
+            });
+        }
+    }
+}
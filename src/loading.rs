@@ -0,0 +1,121 @@
+#![allow(dead_code)]
+
+use std::collections::BTreeSet;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use raylib::prelude::*;
+
+use crate::error::AppError;
+use crate::noise::sub_seed;
+use crate::scene::{MeshLibrary, SceneFile};
+use crate::skybox::{procedural_face, Skybox, SkyboxFace};
+
+/// Skybox images, in the order they're loaded. Top and bottom are swapped on purpose
+/// to match how the cubemap is sampled.
+const SKYBOX_FACES: [&str; 6] = [
+    "assets/skybox/right.png",
+    "assets/skybox/left.png",
+    "assets/skybox/bottom.png",
+    "assets/skybox/top.png",
+    "assets/skybox/front.png",
+    "assets/skybox/back.png",
+];
+
+/// Everything loaded before the first frame.
+pub struct LoadedAssets {
+    pub scene_file: SceneFile,
+    pub skybox: Skybox,
+    pub meshes: MeshLibrary, // Meshes of the scene's bodies that loaded; the rest fail again (and are reported) when the scene is built
+    pub errors: Vec<AppError>,
+}
+
+/// Messages from the loader thread.
+pub enum LoadEvent {
+    /// About to load `name`; `done` of `total` assets are finished.
+    Progress { name: String, done: usize, total: usize },
+    Finished(Box<LoadedAssets>),
+}
+
+impl LoadedAssets {
+    /// What's used when the loader stops without finishing (it panicked): the empty
+    /// scene and procedural sky faces, with `error` listed.
+    pub fn fallback(seed: Option<u64>, error: AppError) -> Self {
+        let mut scene_file = SceneFile::default();
+        scene_file.seed = seed.unwrap_or(scene_file.seed);
+        let mut faces = SKYBOX_FACES.iter().map(|path| procedural_face(512, sub_seed(scene_file.seed, path), &scene_file.skybox.stars));
+        let mut next_face = || faces.next().expect("six skybox faces");
+        let skybox = Skybox::new(next_face(), next_face(), next_face(), next_face(), next_face(), next_face());
+        LoadedAssets { scene_file, skybox, meshes: MeshLibrary::new(), errors: vec![error] }
+    }
+}
+
+fn load_skybox_face(path: &str, max_size: i32) -> Result<SkyboxFace, AppError> {
+    let image = Image::load_image(path).map_err(|_| AppError::AssetMissing { path: path.to_string() })?;
+    Ok(SkyboxFace::from_image(&image, max_size))
+}
+
+/// Loads the scene file, skybox and meshes on a background thread so the window can
+/// keep drawing a progress bar. Failures never stop the load: they're collected in
+/// `LoadedAssets::errors` and replaced with fallbacks (an empty scene, procedural sky faces).
+/// `seed`, when given, replaces the scene file's.
+pub fn spawn_loader(scene_path: String, seed: Option<u64>) -> Receiver<LoadEvent> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || load_all(&scene_path, seed, &sender));
+    receiver
+}
+
+fn load_all(scene_path: &str, seed: Option<u64>, sender: &Sender<LoadEvent>) {
+    let mut errors = Vec::new();
+    // The receiver only goes away if the window was closed mid-load
+    let progress = |name: &str, done: usize, total: usize| {
+        let _ = sender.send(LoadEvent::Progress { name: name.to_string(), done, total });
+    };
+
+    progress(scene_path, 0, 1 + SKYBOX_FACES.len());
+    let mut scene_file = SceneFile::load(scene_path).unwrap_or_else(|error| {
+        errors.push(error);
+        SceneFile::default()
+    });
+    scene_file.seed = seed.unwrap_or(scene_file.seed);
+
+    let mesh_paths: BTreeSet<&str> = scene_file.bodies.iter().map(|b| b.mesh.as_str()).collect();
+    let total = 1 + SKYBOX_FACES.len() + mesh_paths.len();
+    let mut done = 1;
+
+    // The six faces decode (and downscale) in parallel; large ones dominate the startup time
+    progress("assets/skybox", done, total);
+    let max_size = scene_file.skybox.max_face_size;
+    let loaded: Vec<Result<SkyboxFace, AppError>> = thread::scope(|scope| {
+        let handles: Vec<_> = SKYBOX_FACES
+            .iter()
+            .map(|path| scope.spawn(move || load_skybox_face(path, max_size)))
+            .collect();
+        handles
+            .into_iter()
+            .zip(SKYBOX_FACES)
+            .map(|(handle, path)| handle.join().unwrap_or_else(|_| Err(AppError::AssetMissing { path: path.to_string() })))
+            .collect()
+    });
+    let mut faces = Vec::with_capacity(SKYBOX_FACES.len());
+    for (result, path) in loaded.into_iter().zip(SKYBOX_FACES) {
+        faces.push(result.unwrap_or_else(|error| {
+            errors.push(error);
+            procedural_face(512, sub_seed(scene_file.seed, path), &scene_file.skybox.stars)
+        }));
+        done += 1;
+    }
+    let mut faces = faces.into_iter();
+    let mut next_face = || faces.next().expect("six skybox faces");
+    let skybox = Skybox::new(next_face(), next_face(), next_face(), next_face(), next_face(), next_face());
+
+    let mut meshes = MeshLibrary::new();
+    meshes.set_options(&scene_file.meshes);
+    for path in &mesh_paths {
+        progress(path, done, total);
+        let _ = meshes.get(path);
+        done += 1;
+    }
+
+    let _ = sender.send(LoadEvent::Finished(Box::new(LoadedAssets { scene_file, skybox, meshes, errors })));
+}
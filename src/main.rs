@@ -4,309 +4,1046 @@ mod line;
 mod vertex;
 mod fragment;
 mod shaders;
+mod shuttle;
 mod obj;
 mod matrix;
+mod measure;
+mod minimap;
+mod motionblur;
+mod modulation;
 mod camera;
 mod light;
+mod noise;
 mod planetshaders;
 mod skybox;
+mod accumulation;
+mod arena;
+mod beauty;
+mod bookmarks;
+mod console;
+mod culling;
+mod debugview;
+mod deepsky;
+mod depthrange;
+mod discovery;
+mod dust;
+mod error;
+mod events;
+mod exposure;
+mod focus;
+mod hud;
+mod impostor;
+mod generator;
+mod gizmo;
+mod graticule;
+mod gravitygrid;
+mod headless;
+mod input;
+mod loading;
+mod orbit;
+mod orbitview;
+mod params;
+mod photo;
+mod ramp;
+mod plume;
+mod pipeline;
+mod replay;
+mod rings;
+mod quality;
+mod scene;
+mod scenemenu;
+mod sidecar;
+mod soundscape;
+mod stereo;
+mod sphere;
+mod surface;
+mod texture;
+mod threads;
+mod timestep;
+mod tile;
+mod trails;
+mod ui;
+mod tweak;
+mod validate;
+mod lighttime;
+mod passes;
+mod realscale;
+mod repro;
 
-use crate::matrix::{create_model_matrix, create_projection_matrix, create_viewport_matrix};
+use crate::matrix::{create_projection_matrix, create_view_matrix, create_viewport_matrix, multiply_matrices, view_ray, world_to_screen};
 use crate::camera::Camera;
-use crate::light::Light;
+use crate::light::{earthshine, BounceLight, ContactShadow, Light, Occluder};
 use framebuffer::Framebuffer;
 use vertex::Vertex;
-use triangle::triangle;
+use fragment::Fragment;
 use crate::shaders::*;
-use obj::Obj;
 use raylib::prelude::*;
-use std::thread;
-use std::time::Duration;
+use std::time::Instant;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::EnvFilter;
 use std::f32::consts::PI;
 use crate::planetshaders::*;
+use crate::noise::{detail_budget, Noise, MAX_FBM_OCTAVES};
 use std::fs::File;
 use std::io::BufReader;
-use crate::skybox::{SkyboxFace, Skybox, image_to_colors, sample_cubemap};
+use std::sync::mpsc::TryRecvError;
+use std::sync::Arc;
+use crate::skybox::{sample_cubemap_level, SkyIrradiance, SkyReflection};
+use crate::accumulation::{jitter, view_state, StillDetector};
+use crate::arena::FrameArena;
+use crate::console::Console;
+use crate::bookmarks::{bookmarks_path, BookmarkAction, Bookmarks, ViewSettings};
+use crate::discovery::{discovery_state_path, Discovery, DISCOVERY_RESET_KEY, PLACEHOLDER_KIND};
+use crate::events::EventLog;
+use crate::exposure::Exposure;
+use crate::focus::WindowFocus;
+use crate::motionblur::ObjectMotionBlur;
+use crate::dust::draw_dust;
+use crate::error::{draw_error_panel, AppError, ERROR_PANEL_KEY};
+use crate::deepsky::draw_deep_sky;
+use crate::debugview::{heat_color, id_color, object_id, BufferView, DebugView};
+use crate::culling::{draw_order, OcclusionCuller};
+use crate::hud::{FrameStats, Hud};
+use crate::loading::{spawn_loader, LoadEvent, LoadedAssets};
+use crate::measure::MeasureTool;
+use crate::lighttime::LightTravel;
+use crate::passes::{clear_frame, frame_passes, Pass, PassOrder};
+use crate::minimap::Minimap;
+use crate::orbitview::{draw_orbit_planes, ORBIT_PLANE_KEY};
+use crate::generator::PlanetParams;
+use crate::gizmo::draw_gizmo;
+use crate::graticule::graticule;
+use crate::gravitygrid::GravityGrid;
+use crate::headless::HeadlessCamera;
+use crate::pipeline::{focus_samples, Pipeline};
+use crate::plume::draw_plume;
+use crate::quality::{LodThresholds, QualityController};
+use crate::realscale::{RealScale, MIN_CAMERA_DISTANCE, REAL_SCALE_PATH};
+use crate::repro::{Pose, REPRO_KEY, REPRO_PATH};
+use crate::replay::InputSource;
+use crate::scenemenu::{SceneMenu, DEFAULT_SCENE};
+use crate::soundscape::{SoundEvent, Soundscape, SoundscapeConfig};
+use crate::shuttle::{Collider, Shuttle, PHYSICS_KEY, PILOT_KEY};
+use crate::surface::{SurfaceMode, SURFACE_KEY};
+use crate::impostor::ImpostorCache;
+use crate::stereo::{Stereo, StereoMode};
+use crate::timestep::SimClock;
+use crate::rings::RingProfile;
+use crate::params::{ParamSet, ShaderParamsFile, SHADER_PARAMS_PATH};
+use crate::ramp::RampSet;
+use crate::beauty::{Beauty, FillLights};
+use crate::modulation::ModulationSource;
+use crate::photo::{PhotoEffects, PhotoJob, PhotoMode};
+use crate::depthrange::{DepthMode, DepthRange};
+use crate::tile::Tile;
+use crate::trails::Trails;
+use crate::tweak::TweakPanel;
+use crate::validate::validate;
+use crate::sphere::{project_sphere, render_sphere};
+use crate::texture::{Material, TextureCache};
+use crate::scene::{build_scene, apply_scene, ring_gaps, saved_scene_path, SceneWatcher, PAUSE_KEY, SAVE_SCENE_KEY};
 
-pub struct Uniforms {
-    pub model_matrix: Matrix,
-    pub view_matrix: Matrix,
-    pub projection_matrix: Matrix,
-    pub viewport_matrix: Matrix,
+/// Shader inputs shared by every object in a frame, computed once per frame.
+#[derive(Clone, Copy)]
+pub struct FrameUniforms {
+    pub view: Matrix,
+    pub projection: Matrix,
+    pub viewport: Matrix,
+    pub view_projection: Matrix, // projection * view
+    pub camera_pos: Vector3,
+    pub time: f32,               // Simulation time in seconds, for animated effects
+    pub light_position: Vector3,
+    pub sun_radius: f32,         // Radius of the sun's sphere, which softens shadow edges; 0 for a point light
+    pub ambient: f32,            // Scale of the sky's irradiance used as ambient light
+    pub sky: SkyIrradiance,
+    pub screen_size: Vector2,    // Size of the frame being rendered, in pixels
+    pub fill_lights: Option<FillLights>, // Beauty mode's camera-attached lights
+    pub sun_pulse: f32,          // Sun brightness multiplier from the modulation input, 1 when neutral
+    pub depth_mode: DepthMode,   // How `projection` maps depth, and which stored depth is nearer
+    pub fbm_octaves: u32,        // Most fbm octaves the quality level lets the shaders use
 }
 
-struct SceneObject {
-    vertices: Vec<Vertex>,
-    object_type: String,
-    translation: Vector3,
-    rotation: Vector3,
-    scale: f32
+impl FrameUniforms {
+    pub fn new(view: Matrix, projection: Matrix, viewport: Matrix, camera_pos: Vector3) -> Self {
+        FrameUniforms {
+            view,
+            projection,
+            viewport,
+            view_projection: multiply_matrices(&projection, &view),
+            camera_pos,
+            time: 0.0,
+            light_position: Vector3::zero(),
+            sun_radius: 0.0,
+            ambient: 0.0,
+            sky: SkyIrradiance::constant(Vector3::one()),
+            screen_size: Vector2::new(viewport.m0.abs() * 2.0, viewport.m5.abs() * 2.0),
+            fill_lights: None,
+            sun_pulse: 1.0,
+            depth_mode: DepthMode::Classic,
+            fbm_octaves: MAX_FBM_OCTAVES,
+        }
+    }
 }
 
-fn render(
-    framebuffer: &mut Framebuffer,
-    uniforms: &Uniforms,
-    vertex_array: &[Vertex],
-    light: &Light,
-    object_type: &str
-) {
-    // Build an object-specific model matrix and compose a per-object Uniforms
-    //let model_matrix = create_model_matrix(translation, scale, rotation);
-    // let object_uniforms = Uniforms {
-    //     model_matrix,
-    //     view_matrix: uniforms.view_matrix,
-    //     projection_matrix: uniforms.projection_matrix,
-    //     viewport_matrix: uniforms.viewport_matrix,
-    // };
-
-    // Vertex Shader Stage
-    let mut transformed_vertices = Vec::with_capacity(vertex_array.len());
-    for vertex in vertex_array {
-        let transformed = vertex_shader(vertex, &uniforms);
-        transformed_vertices.push(transformed);
-    }
+/// Shader inputs of one object.
+#[derive(Clone)]
+pub struct ObjectUniforms {
+    pub model: Matrix,
+    pub mvp: Matrix,                 // projection * view * model, for positions
+    pub normal_matrix: Matrix,       // Inverse transpose of the model matrix
+    pub material: Material,          // Texture maps, when the body has any
+    pub irradiance: Vector3,         // Sunlight reaching this body (color * intensity * 1/r²)
+    pub ring_radii: Vector2,         // Inner/outer model-space radius, for ring objects
+    pub ring_gaps: Vec<Vector2>,     // Lanes cleared by shepherd moons: (radius, half width) in ring model space
+    pub ring_profile: Option<Arc<RingProfile>>, // Baked color and density of a multi-ring system
+    pub bounce: Option<BounceLight>, // Secondary light, e.g. earthshine on the moon
+    pub screen_center: Vector2,      // Object's projected center, in pixels
+    pub screen_radius: f32,          // Object's projected bounding radius, in pixels
+    pub detail_budget: f32,          // fbm octaves the body resolves at that size, see `detail_budget`
+    pub occluders: Vec<Occluder>,    // Bodies that can eclipse the sun for this one
+    pub contact_shadow: Option<ContactShadow>, // Blob under the shuttle when it flies low over this body
+    pub noise: Noise,                // The body's seeded procedural noise
+    pub params: ParamSet,            // Values the body's shader reads instead of its built-in constants
+    pub ramps: RampSet,              // Color ramps the body's shader samples instead of its built-in palettes
+    pub planet: Option<PlanetParams>, // Inputs of the generic planet shader, for generated kinds
+    pub graticule: bool,             // Latitude/longitude lines over the shaded surface
+    pub reflection: Option<Arc<SkyReflection>>, // Prefiltered sky mirrored by the shuttle's hull
+}
 
-    for vertex in &mut transformed_vertices {
-        match object_type {
-            "rocky1" => rocky_planet_vertex_shader(vertex),
-            "rocky2" => hot_cold_rocky_planet_vertex_shader(vertex),
-            "gassy1" => gassy_planet_vertex_shader(vertex),
-            "gassy2" => uranus_like_vertex_shader(vertex),
-            "gassy3" => cyan_redband_gas_vertex_shader(vertex),
-            "moon"  => moon_vertex_shader(vertex),
-            "ring"  => ring_vertex_shader(vertex),
-            "sun"  => sun_vertex_shader(vertex),
-            "earth" => earth_planet_vertex_shader(vertex),
-            "shuttle" => shuttle_vertex_shader(vertex),
-            _ => {}
+impl ObjectUniforms {
+    /// Uniforms for a model matrix drawn in `frame`, with everything else neutral.
+    pub fn new(model: Matrix, frame: &FrameUniforms) -> Self {
+        ObjectUniforms {
+            model,
+            mvp: multiply_matrices(&frame.view_projection, &model),
+            normal_matrix: model.inverted().transposed(),
+            material: Material::default(),
+            irradiance: Vector3::zero(),
+            ring_radii: Vector2::zero(),
+            ring_gaps: Vec::new(),
+            ring_profile: None,
+            bounce: None,
+            screen_center: Vector2::zero(),
+            screen_radius: 0.0,
+            detail_budget: MAX_FBM_OCTAVES as f32,
+            occluders: Vec::new(),
+            contact_shadow: None,
+            noise: Noise::default(),
+            params: ParamSet::default(),
+            ramps: RampSet::default(),
+            planet: None,
+            graticule: false,
+            reflection: None,
         }
     }
+}
+
+#[derive(Clone)]
+pub struct Uniforms {
+    pub frame: FrameUniforms,
+    pub object: ObjectUniforms,
+}
 
+impl Uniforms {
+    /// The body's noise with as many octaves as both its size on screen and the quality
+    /// level allow. The vertex colors and the fragment masks all sample this, so they
+    /// agree on where e.g. the coastlines are.
+    pub fn surface_noise(&self) -> Noise {
+        self.object.noise.with_detail(self.object.detail_budget.min(self.frame.fbm_octaves as f32))
+    }
+}
 
-    
-    // Primitive Assembly Stage
-    let mut triangles = Vec::new();
-    for i in (0..transformed_vertices.len()).step_by(3) {
-        if i + 2 < transformed_vertices.len() {
-            triangles.push([
-                transformed_vertices[i].clone(),
-                transformed_vertices[i + 1].clone(),
-                transformed_vertices[i + 2].clone(),
-            ]);
+/// Per-kind procedural coloring, run after the vertex shader.
+pub fn planet_vertex_shader(vertex: &mut Vertex, uniforms: &Uniforms, object_type: &str) {
+    let noise = uniforms.surface_noise();
+    match object_type {
+        "rocky1" => rocky_planet_vertex_shader(vertex, &noise, &uniforms.object.params, &uniforms.object.ramps),
+        "rocky2" => hot_cold_rocky_planet_vertex_shader(vertex, &noise, &uniforms.object.params, &uniforms.object.ramps),
+        "gassy1" => gassy_planet_vertex_shader(vertex, &noise, &uniforms.object.params, &uniforms.object.ramps),
+        "gassy2" => uranus_like_vertex_shader(vertex, &noise, &uniforms.object.params, &uniforms.object.ramps),
+        "gassy3" => cyan_redband_gas_vertex_shader(vertex, &noise, &uniforms.object.params, &uniforms.object.ramps),
+        "moon"  => moon_vertex_shader(vertex, &noise, &uniforms.object.params),
+        "ring"  => ring_vertex_shader(vertex, uniforms.object.ring_radii.x, uniforms.object.ring_radii.y),
+        "sun"  => sun_vertex_shader(vertex, &noise, &uniforms.object.params),
+        "earth" => earth_planet_vertex_shader(vertex, &noise, &uniforms.object.params, &uniforms.object.ramps),
+        "shuttle" => shuttle_vertex_shader(vertex, &noise),
+        "placeholder" => placeholder_vertex_shader(vertex),
+        _ => {
+            if let Some(planet) = &uniforms.object.planet {
+                generated_planet_vertex_shader(vertex, planet, &noise);
+            }
         }
     }
+}
 
-    // Rasterization Stage
-    let mut fragments = Vec::new();
-    for tri in &triangles {
-        fragments.extend(triangle(&tri[0], &tri[1], &tri[2], light));
+/// Coverage of a shaded fragment (1 = opaque); only rings have see-through parts.
+pub fn fragment_alpha(fragment: &Fragment, uniforms: &Uniforms, object_type: &str) -> f32 {
+    match object_type {
+        "ring" => ring_alpha(fragment, uniforms),
+        _ => 1.0,
     }
+}
 
-    // Fragment Processing Stage
-    for fragment in fragments {
-        // Run fragment shader to compute final color
-        let final_color = match object_type {
-            "sun"  => sun_fragment_shader(&fragment, &uniforms),
-            "rocky1" => rocky_fragment_shader(&fragment, &uniforms),
-            "rocky2" => rocky_fragment_shader(&fragment, &uniforms),
-            "gassy1" => gas_giant_fragment_shader(&fragment, &uniforms),
-            "gassy2" => gas_giant_fragment_shader(&fragment, &uniforms),
-            "gassy3" => gas_giant_fragment_shader(&fragment, &uniforms),
-            "earth" => earth_fragment_shader(&fragment, &uniforms),
-            "moon"  => moon_fragment_shader(&fragment, &uniforms),
-            "ring"  => ring_fragment_shader(&fragment, &uniforms),
-            //"shuttle" => shuttle_chrome_fragment_shader(&fragment, &uniforms),
-            _       => rocky_fragment_shader(&fragment, &uniforms), // default
-        };
-
-        framebuffer.point(
-            fragment.position.x as i32,
-            fragment.position.y as i32,
-            final_color,
-            fragment.depth            
-        );
-        
+/// Runs the per-kind fragment shader and lighting for one fragment.
+pub fn shade_fragment(fragment: &Fragment, uniforms: &Uniforms, object_type: &str) -> Vector3 {
+    // Run fragment shader to compute final color
+    let color = match object_type {
+        "sun"  => sun_fragment_shader(fragment, uniforms),
+        "rocky1" => rocky_plates_fragment_shader(fragment, uniforms),
+        "rocky2" => rocky_fragment_shader(fragment, uniforms),
+        "gassy1" => gas_giant_fragment_shader(fragment, uniforms),
+        "gassy2" => gas_giant_fragment_shader(fragment, uniforms),
+        "gassy3" => gas_giant_fragment_shader(fragment, uniforms),
+        "earth" => earth_fragment_shader(fragment, uniforms),
+        "moon"  => moon_fragment_shader(fragment, uniforms),
+        "ring"  => ring_fragment_shader(fragment, uniforms),
+        "placeholder" => fragment.color,
+        "gas" => gas_giant_fragment_shader(fragment, uniforms),
+        _       => rocky_fragment_shader(fragment, uniforms), // default
+    };
+
+    let color = material_color(color, fragment, &uniforms.object.material);
+
+    // The sun is emissive; everything else is lit by it
+    let lit = match object_type {
+        "sun" => color,
+        "ring" => ring_scattering(color, fragment, uniforms),
+        "shuttle" => {
+            let lit = material_lighting(surface_lighting(color, fragment, uniforms), fragment, uniforms);
+            shuttle_chrome_fragment_shader(lit, fragment, uniforms)
+        }
+        "earth" => {
+            let lit = material_lighting(surface_lighting(color, fragment, uniforms), fragment, uniforms);
+            earth_specular(lit, fragment, uniforms)
+        }
+        _ => material_lighting(surface_lighting(color, fragment, uniforms), fragment, uniforms),
+    };
+    let lit = sunset_band(lit, color, fragment, uniforms, object_type);
+    let lit = match &uniforms.object.contact_shadow {
+        Some(shadow) => lit * shadow.factor(fragment.world_position),
+        None => lit,
+    };
+    if uniforms.object.graticule {
+        graticule(lit, fragment.uv, fragment.duvdx, fragment.duvdy)
+    } else {
+        lit
     }
+}
 
-    
+/// Scene file from `--scene <path>`, if given.
+fn scene_path_from_args() -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    args.iter()
+        .position(|a| a == "--scene")
+        .and_then(|i| args.get(i + 1).cloned())
 }
 
-fn load_skybox_face(path: &str) -> SkyboxFace {
-    let image = Image::load_image(path).expect("No pude cargar skybox face");
-    let width = image.width;
-    let height = image.height;
-
-    // Aquí necesitarás obtener los datos de la imagen como píxeles (Color u8 -> Vector3 f32)
-    let data: Vec<Vector3> = image_to_colors(&image); // función que tú implementas
-
-    let mut pixels = Vec::with_capacity((width * height) as usize);
-    for c in data {
-        pixels.push(Vector3::new(
-            c.x,
-            c.y,
-            c.z,
-        ));
+/// Seed from `--seed <n>`, which overrides the scene file's `seed`.
+fn seed_from_args() -> Result<Option<u64>, String> {
+    let args: Vec<String> = std::env::args().collect();
+    let Some(i) = args.iter().position(|a| a == "--seed") else {
+        return Ok(None);
+    };
+    match args.get(i + 1).map(|seed| seed.parse()) {
+        Some(Ok(seed)) => Ok(Some(seed)),
+        _ => Err("--seed needs a non-negative integer".to_string()),
     }
+}
+
+/// Dark blue-ish, shading down to near black behind the loading bar and the scene menu.
+fn set_menu_background(framebuffer: &mut Framebuffer) {
+    framebuffer.set_background_color(Vector3::new(0.2, 0.2, 0.4));
+    framebuffer.set_background_gradient(Some((Vector3::new(0.24, 0.24, 0.46), Vector3::new(0.05, 0.05, 0.12))));
+}
+
+/// Draws a progress bar (into the framebuffer) with the asset being loaded until the
+/// loader finishes. Returns None if the window is closed first; a loader that stops
+/// without finishing gives the fallback assets, with the failure listed.
+fn show_loading_screen(
+    window: &mut RaylibHandle,
+    thread: &RaylibThread,
+    framebuffer: &mut Framebuffer,
+    scene_path: &str,
+    seed: Option<u64>,
+) -> Option<Box<LoadedAssets>> {
+    let events = spawn_loader(scene_path.to_string(), seed);
+    let mut current = String::from("starting");
+    let mut fraction = 0.0;
+    while !window.window_should_close() {
+        loop {
+            match events.try_recv() {
+                Ok(LoadEvent::Progress { name, done, total }) => {
+                    current = name;
+                    fraction = done as f32 / total.max(1) as f32;
+                }
+                Ok(LoadEvent::Finished(assets)) => return Some(assets),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    let msg = format!("loading stopped at {} before it finished", current);
+                    return Some(Box::new(LoadedAssets::fallback(seed, AppError::SceneConfig { msg })));
+                }
+            }
+        }
+
+        let width = window.get_screen_width();
+        let height = window.get_screen_height();
+        framebuffer.resize(width as u32, height as u32, 1.0);
+        framebuffer.clear();
 
-    SkyboxFace { width, height, pixels }
+        // Bar outline and fill, centered
+        let (w, h) = (framebuffer.width as i32, framebuffer.height as i32);
+        let (bar_w, bar_h) = (w / 2, 16);
+        let (x0, y0) = ((w - bar_w) / 2, h / 2 - bar_h / 2);
+        let filled = (bar_w as f32 * fraction) as i32;
+        for y in y0..y0 + bar_h {
+            for x in x0..x0 + bar_w {
+                let edge = y == y0 || y == y0 + bar_h - 1 || x == x0 || x == x0 + bar_w - 1;
+                if edge {
+                    framebuffer.set_pixel(x, y, Vector3::new(0.6, 0.6, 0.7));
+                } else if x - x0 < filled {
+                    framebuffer.set_pixel(x, y, Vector3::new(0.95, 0.75, 0.35));
+                }
+            }
+        }
+
+        let label = format!("Loading {} ({:.0}%)", current, fraction * 100.0);
+        framebuffer.swap_buffers(window, thread, |d| {
+            let text_width = measure_text(&label, 16);
+            d.draw_text(&label, (width - text_width) / 2, height / 2 + 20, 16, Color::WHITE);
+        });
+    }
+    None
+}
+
+/// The scene menu on its own, before any scene is loaded. Returns the picked scene, or
+/// None if the window is closed first.
+fn choose_scene(
+    window: &mut RaylibHandle,
+    thread: &RaylibThread,
+    framebuffer: &mut Framebuffer,
+    input_source: &mut InputSource,
+    menu: &mut SceneMenu,
+) -> Option<String> {
+    while !window.window_should_close() {
+        let input = input_source.next(window);
+        menu.open = true; // Nothing to go back to, so it can't be closed
+        if let Some(path) = menu.process_input(&input) {
+            return Some(path);
+        }
+        let (width, height) = (input.screen_width, input.screen_height);
+        framebuffer.resize(width as u32, height as u32, 1.0);
+        framebuffer.clear();
+        framebuffer.swap_buffers(window, thread, |d| menu.draw(d, width, height));
+    }
+    None
 }
 
 fn main() {
+    // RUST_LOG=debug logs per-object counts, RUST_LOG=trace adds per-stage timings
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .with_span_events(FmtSpan::CLOSE)
+        .init();
+
+    let args: Vec<String> = std::env::args().collect();
+    let mut pipeline = Pipeline::from_args(&args).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    });
+    // One window of a video wall: renders its share of the view with an off-axis projection
+    let tile = Tile::from_args(&args).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    });
+    // Render passes sorted from what each reads and writes; a misordered pass stops here, named
+    let passes = frame_passes().resolve().expect("Invalid render pass order");
+    tracing::debug!("render passes: {}", passes);
+    // `--pose` starts paused on the view of a pose line, in its scene and with its seed
+    let start_pose = Pose::from_args(&args).unwrap_or_else(|error| {
+        eprintln!("--pose: {}", error);
+        std::process::exit(1);
+    });
+    // `--record-input` logs every frame's input, `--replay` plays a log back with the scene and seed it was recorded with
+    let mut scene_arg = scene_path_from_args();
+    let mut seed_override = seed_from_args().unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    });
+    if let Some(pose) = &start_pose {
+        scene_arg = Some(pose.scene.clone());
+        seed_override = Some(pose.seed);
+    }
+    let mut input_source = InputSource::from_args(&args, &mut scene_arg, &mut seed_override).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    });
+    if start_pose.is_some() && input_source.is_replaying() {
+        eprintln!("--pose and --replay can't be used together");
+        std::process::exit(1);
+    }
+
     let window_width = 800;
     let window_height = 600;
 
     let (mut window, thread) = raylib::init()
         .size(window_width, window_height)
         .title("Rust Graphics - Renderer Example")
+        .resizable()
         .log_level(TraceLogLevel::LOG_WARNING) // Suppress INFO messages
         .build();
 
     let mut framebuffer = Framebuffer::new(window_width as u32, window_height as u32);
-    framebuffer.set_background_color(Vector3::new(0.2, 0.2, 0.4)); // Dark blue-ish
+    set_menu_background(&mut framebuffer);
 
     // Initialize the texture inside the framebuffer
     framebuffer.init_texture(&mut window, &thread);
 
+    // Music plays on across scene switches; failing to load it is listed with each scene's errors
+    let stream_handle = rodio::OutputStreamBuilder::open_default_stream()
+        .expect("open default audio stream");
+    let mut startup_errors = Vec::new();
+    let music_path = "assets/music/cherry_galaxy_remix.mp3";
+    let _sink = match File::open(music_path) {
+        Ok(file) => rodio::play(stream_handle.mixer(), BufReader::new(file))
+            .inspect_err(|error| tracing::warn!(%error, "could not play {}", music_path))
+            .ok(),
+        Err(_) => {
+            startup_errors.push(AppError::AssetMissing { path: music_path.to_string() });
+            None
+        }
+    };
+
+    // Drone, rumble, chimes and whoosh over the music; silent without the `audio` feature
+    let mut soundscape = Soundscape::new(stream_handle.mixer(), SoundscapeConfig::default());
+
+    // The scene from `--scene`, else the one picked from a menu when there's a choice
+    let mut scene_path = match scene_arg {
+        Some(path) => path,
+        None => {
+            let mut menu = SceneMenu::new(None);
+            if menu.scenes.len() > 1 {
+                let Some(path) = choose_scene(&mut window, &thread, &mut framebuffer, &mut input_source, &mut menu) else {
+                    return; // Window closed in the menu
+                };
+                path
+            } else {
+                DEFAULT_SCENE.to_string()
+            }
+        }
+    };
+    // Every scene starts from scratch: nothing but the window, the pipeline and the sound carries over
+    let mut start_pose = start_pose;
+    while let SceneExit::Switch(next) = run_scene(
+        &mut window,
+        &thread,
+        &mut framebuffer,
+        &mut pipeline,
+        &mut soundscape,
+        &mut input_source,
+        tile,
+        &passes,
+        &scene_path,
+        seed_override,
+        start_pose.take(),
+        &startup_errors,
+    ) {
+        tracing::info!("switching to scene {}", next);
+        scene_path = next;
+    }
+}
+
+/// How a scene ended: the window was closed, or another scene was picked from the menu.
+enum SceneExit {
+    Quit,
+    Switch(String),
+}
+
+/// Loads the scene at `scene_path` and runs it until the window closes or the scene
+/// menu switches to another one. Everything the scene sets up is dropped on return.
+#[allow(clippy::too_many_arguments)]
+fn run_scene(
+    window: &mut RaylibHandle,
+    thread: &RaylibThread,
+    framebuffer: &mut Framebuffer,
+    pipeline: &mut Pipeline,
+    soundscape: &mut Soundscape,
+    input_source: &mut InputSource,
+    tile: Option<Tile>,
+    passes: &PassOrder,
+    scene_path: &str,
+    seed_override: Option<u64>,
+    start_pose: Option<Pose>,
+    startup_errors: &[AppError],
+) -> SceneExit {
     // Camera setup
     let camera_position = Vector3::new(0.0, 5.0, 100.0);
     let camera_target = Vector3::new(0.0, 0.0, 0.0);
     let camera_up = Vector3::new(0.0, 1.0, 0.0);
     let mut camera = Camera::new(camera_position, camera_target, camera_up);
 
-    // Projection setup
-    let fov_y = PI / 3.0; // 60 degrees
-    let aspect = window_width as f32 / window_height as f32;
-    let near = 0.1;
-    let far = 100.0;
+    // Frame limiter: waits only for what's left of each 1/60 s frame
+    window.set_target_fps(60);
 
-    // Model setup (rotating model at origin)
-    let mut rotation_y = 0.0f32;
-    let rotation_speed = 0.02; // Radians per frame
+    // Projection setup (the aspect ratio follows the window each frame)
+    let mut fov_y = PI / 3.0; // 60 degrees, until a bookmark sets another
 
     // Light setup (place light at the origin so it matches the sun position)
-    let light = Light::new(Vector3::new(0.0, 0.0, 0.0));
-
-    let skybox = Skybox {
-        right:  load_skybox_face("assets/skybox/right.png"),
-        left:   load_skybox_face("assets/skybox/left.png"),
-        top:    load_skybox_face("assets/skybox/bottom.png"),
-        bottom: load_skybox_face("assets/skybox/top.png"),
-        front:  load_skybox_face("assets/skybox/front.png"),
-        back:   load_skybox_face("assets/skybox/back.png"),
-    };
+    let mut light = Light::new(Vector3::new(0.0, 0.0, 0.0));
+    let mut sim_clock = SimClock::new();
+    let mut paused = false;
+    let mut still_frames = StillDetector::new();
 
-    let shuttle_obj = Obj::load("assets/objects/SpaceShuttle.obj").expect("Failed to load obj");
-    let planet_obj = Obj::load("assets/objects/sphere.obj").expect("Failed to load obj");
-    let sun_obj = Obj::load("assets/objects/sun.obj").expect("Failed to load obj");
-    let ring_obj = Obj::load("assets/objects/ring.obj").expect("Failed to load obj");
-    
-    let shuttle = SceneObject {
-        vertices: shuttle_obj.get_vertex_array(),
-        object_type: "shuttle".to_string(),
-        translation: Vector3::new(0.0, 0.0, 70.0),
-        rotation: Vector3::new(0.0, 0.0, 0.0),
-        scale: 1.0
+    // Assets load on a background thread while the window shows a progress bar.
+    // Failures don't stop the program: they're listed on screen and replaced with a
+    // fallback (procedural sky faces, an empty scene, no music)
+    set_menu_background(framebuffer);
+    let Some(assets) = show_loading_screen(window, thread, framebuffer, scene_path, seed_override) else {
+        return SceneExit::Quit; // Window closed while loading
     };
+    let LoadedAssets { mut scene_file, skybox, mut meshes, errors } = *assets;
+    // Wherever the sky isn't drawn, the clear shows its average color instead
+    framebuffer.set_background_color(skybox.average);
+    framebuffer.set_background_gradient(None);
+    let mut asset_errors = errors;
+    asset_errors.extend(startup_errors.iter().cloned());
 
-    let planet_gassy_1 = SceneObject {
-        vertices: planet_obj.get_vertex_array(),
-        object_type: "gassy1".to_string(),
-        translation: Vector3::new(18.0, 0.0, -20.0),
-        rotation: Vector3::new(0.0, 0.0, 0.0),
-        scale: 1.8
-    };
+    let (mut scene_objects, scene_errors) = build_scene(&scene_file, &mut meshes);
+    asset_errors.extend(scene_errors);
+    let warnings = validate(&scene_objects, scene_file.depth_range.farthest());
+    asset_errors.extend(warnings.iter().map(|warning| AppError::SceneCheck { msg: warning.to_string() }));
+    let mut scene_watcher = SceneWatcher::new(scene_path);
+    let mut shader_params = ShaderParamsFile::load(SHADER_PARAMS_PATH);
+    let mut reflection = Arc::new(SkyReflection::new());
+    let mut textures = TextureCache::new(scene_file.textures.clone());
+    let mut discovery = Discovery::load(scene_file.discovery.clone(), discovery_state_path(scene_path));
+    let mut bookmarks = Bookmarks::load(bookmarks_path(scene_path));
+    let mut lod = LodThresholds::default();
+    let mut real_scale = RealScale::load(REAL_SCALE_PATH).unwrap_or_else(|error| {
+        asset_errors.push(error);
+        RealScale::default()
+    });
+    soundscape.reset(scene_file.soundscape.clone());
+    let mut reload_error: Option<String> = None;
 
-    let ring = SceneObject {
-        vertices: ring_obj.get_vertex_array(),
-        object_type: "ring".to_string(),
-        translation: Vector3::new(18.0, 0.0, -20.0),
-        rotation: Vector3::new(0.0, 0.0, 0.0),
-        scale: 1.8
-    };
+    for error in &asset_errors {
+        tracing::warn!("{}", error);
+    }
+    let mut show_errors = !asset_errors.is_empty();
 
-    let planet_gassy_2 = SceneObject {
-        vertices: planet_obj.get_vertex_array(),
-        object_type: "gassy2".to_string(),
-        translation: Vector3::new(28.0, 0.0, 5.0),
-        rotation: Vector3::new(0.0, 0.0, 0.0),
-        scale: 0.8
-    };
+    let mut console = Console::new();
+    let mut scene_menu = SceneMenu::new(Some(scene_path));
+    let mut arena = FrameArena::new();
+    let mut hud = Hud::new();
+    let mut impostors = ImpostorCache::new();
+    let mut shuttle = Shuttle::new();
+    let mut surface = SurfaceMode::new();
+    let mut minimap = Minimap::new();
+    let mut stereo = Stereo::new();
+    let mut exposure = Exposure::new();
+    let mut trails = Trails::new(scene_file.trails.clone());
+    let mut gravity_grid = GravityGrid::new(scene_file.gravity_grid.clone());
+    let mut beauty = Beauty::new(scene_file.beauty.clone());
+    let mut modulation = ModulationSource::new(scene_file.modulation.clone());
+    let mut photo = PhotoMode::new(scene_file.photo.clone());
+    let mut depth_range = DepthRange::new(scene_file.depth_range.clone());
+    let mut quality = QualityController::new(60.0);
+    quality.enabled = !input_source.is_deterministic();
+    let mut replaying = input_source.is_replaying();
+    let mut debug_view = DebugView::new();
+    let mut measure = MeasureTool::new();
+    let mut light_travel = LightTravel::new();
+    // The sky writes every pixel, so with it in the passes the color is never cleared under it
+    let sky_covers = passes.eye.contains(&Pass::Sky);
+    let mut color_clears_skipped: u64 = 0;
+    let mut events = EventLog::new(scene_file.events.clone());
+    let mut tweak_panel = TweakPanel::new();
+    let mut previous_view: Option<(Matrix, Vector3)> = None; // Last frame's view matrix and forward
+    let mut warping = false; // Whether the camera was warping last frame
+    let mut isolated = debug_view.isolate; // Last frame's isolated body
+    let mut window_focus = WindowFocus::new();
+    let mut object_blur = ObjectMotionBlur::new();
 
-    let planet_gassy_3 = SceneObject {
-        vertices: planet_obj.get_vertex_array(),
-        object_type: "gassy3".to_string(),
-        translation: Vector3::new(0.0, 0.0, 40.0),
-        rotation: Vector3::new(0.0, 0.0, 0.0),
-        scale: 1.0
-    };
+    // `--pose`: the view from a bug report, held still so it stays on that frame
+    if let Some(pose) = start_pose {
+        for note in pose.restore(&mut scene_objects, &mut camera, &mut sim_clock) {
+            tracing::warn!("--pose: {}", note);
+            console.print(format!("warning: {}", note));
+        }
+        fov_y = pose.fov.to_radians();
+        paused = true;
+        console.print("started on the --pose view, paused".to_string());
+    }
 
-    let planet_rocky_1 = SceneObject {
-        vertices: planet_obj.get_vertex_array(),
-        object_type: "rocky1".to_string(),
-        translation: Vector3::new(-16.0, 0.0, 0.0),
-        rotation: Vector3::new(0.0, 0.0, 0.0),
-        scale: 1.2
-    };
+    while !window.window_should_close() {
+        let frame_start = Instant::now();
+        pipeline.begin_frame();
+        textures.begin_frame();
+        let mut input = input_source.next(window);
+        window_focus.update(&mut input);
+        if replaying && !input_source.is_replaying() {
+            replaying = false;
+            paused = true;
+            console.print("replay finished: paused on its last frame, the keyboard and mouse are back".to_string());
+        }
+        let dt = input.dt;
+        hud.record_frame(dt);
 
-    let planet_rocky_2 = SceneObject {
-        vertices: planet_obj.get_vertex_array(),
-        object_type: "rocky2".to_string(),
-        // Rocky planet to the left of the origin
-        translation: Vector3::new(-50.0, 0.0, 22.0),
-        rotation: Vector3::new(0.0, 0.0, 0.0),
-        scale: 1.0
-    };
+        // The window can be resized; the 3D pass renders at a fraction of it set by the quality level
+        let window_width = input.screen_width;
+        let window_height = input.screen_height;
+        let level = quality.current();
+        framebuffer.resize(window_width as u32, window_height as u32, level.render_scale);
+        pipeline.set_centroid_color(level.centroid_color);
+        framebuffer.dither = debug_view.dither;
+        framebuffer.exposure = exposure.scale();
+        framebuffer.depth_mode = depth_range.config.mode;
+        framebuffer.show_fps = !photo.active;
+        let render_width = framebuffer.width as i32;
+        let render_height = framebuffer.height as i32;
+        let aspect = render_width as f32 / render_height as f32;
 
-    let earth = SceneObject {
-        vertices: planet_obj.get_vertex_array(),
-        object_type: "earth".to_string(),
-        translation: Vector3::new(10.0, 0.0, -27.0),
-        rotation: Vector3::new(0.0, 0.0, 0.0),
-        scale: 1.2
-    };
+        let mut tweaked = false;
 
+        // Scene menu (Tab): loading another scene returns, dropping everything this one set up.
+        // Keys belong to the menu for the whole frame it was open in, so the Enter that picks
+        // (or re-picks the running scene) doesn't also reach photo mode
+        let menu_was_open = scene_menu.open;
+        let picked = if console.open { None } else { scene_menu.process_input(&input) };
+        if let Some(path) = picked.filter(|path| path != scene_path) {
+            return SceneExit::Switch(path);
+        }
+        let in_menu = menu_was_open || scene_menu.open;
 
-    let moon = SceneObject {
-        vertices: planet_obj.get_vertex_array(),
-        object_type: "moon".to_string(),
-        translation: Vector3::new(15.0, -2.0, -60.0),
-        rotation: Vector3::new(0.0, 0.0, 0.0),
-        scale: 0.5,
-    };
+        // Debug console (toggle with ~); camera keys are ignored while typing or in a menu
+        let line = if in_menu { None } else { console.process_input(&input) };
+        if let Some(line) = line {
+            let result = console::parse_command(&line).and_then(|command| match command {
+                // True-scale sizes would be saved as the scene's own
+                console::Command::Save { .. } if real_scale.active => Err("leave true scale (Z) before saving".to_string()),
+                console::Command::Pose(None) => {
+                    let selected = tweak::selected(&scene_objects, debug_view.isolate, camera.target).and_then(|i| scene_objects.get(i));
+                    Ok(Pose::capture(scene_path, scene_file.seed, &camera, fov_y, &sim_clock, selected).to_string())
+                }
+                console::Command::Pose(Some(pose)) if pose.scene != scene_path || pose.seed != scene_file.seed => {
+                    Err(format!("that pose is for {} with seed {}; start with --pose to load it", pose.scene, pose.seed))
+                }
+                console::Command::Pose(Some(pose)) => {
+                    shuttle.piloting = false;
+                    surface.leave(&mut camera);
+                    let notes = pose.restore(&mut scene_objects, &mut camera, &mut sim_clock);
+                    fov_y = pose.fov.to_radians();
+                    paused = true;
+                    Ok(notes.iter().map(|note| format!("warning: {}\n", note)).collect::<String>() + "pose restored, paused")
+                }
+                command => console::execute(command, &mut scene_objects, &mut camera, &mut meshes, &scene_file, pipeline, &mut bookmarks),
+            });
+            match result {
+                Ok(message) => message.lines().for_each(|line| console.print(line)),
+                Err(error) => console.print(format!("error: {}", error)),
+            }
+        }
 
-    let sun = SceneObject {
-        vertices: sun_obj.get_vertex_array(),
-        object_type: "sun".to_string(),
-        translation: light.position,
-        rotation: Vector3::new(0.0, 0.0, 0.0),
-        scale: 2.5,
-    };
+        // Photo mode (K): frozen time, no overlays and a slow camera to frame a shot; Enter
+        // renders it offscreen at a multiple of the window size
+        if !console.open && !in_menu && photo.process_input(&input, &mut camera) {
+            let view = HeadlessCamera {
+                up: camera.up,
+                fov_y,
+                tile,
+                depth_mode: depth_range.config.mode,
+                near: depth_range.near,
+                far: depth_range.far,
+                ..HeadlessCamera::looking_at(camera.eye, camera.target)
+            };
+            let materials = scene_objects.iter().map(|o| textures.material(&o.material, &o.object_type)).collect();
+            let window_size = (window_width as u32, window_height as u32);
+            // The photo renders on its own, so it takes the shader parameters file's values with it
+            let mut objects = scene_objects.clone();
+            for obj in &mut objects {
+                obj.params = shader_params.for_body(&obj.object_type, &obj.params);
+            }
+            // and the frame's lights and glows, so it looks like the window
+            let forward = (camera.target - camera.eye).normalized();
+            let right = forward.cross(camera.up).normalized();
+            let effects = PhotoEffects {
+                fill_lights: beauty.lights(forward, right.cross(forward).normalized(), right),
+                sun_pulse: modulation.sun_pulse(),
+                dust: scene_file.dust.clone(),
+                reflection: reflection.clone(),
+            };
+            photo.start(PhotoJob::new(
+                objects,
+                view,
+                scene_file.light.clone(),
+                sim_clock.render_time,
+                materials,
+                effects,
+                exposure.scale(),
+                window_size,
+                &photo.config,
+            ));
+        }
+        if photo.active && shuttle.piloting {
+            shuttle.piloting = false;
+            camera.sync_orbit();
+        }
 
-    let mut scene_objects = vec![
-        planet_rocky_1,
-        planet_rocky_2,
-        planet_gassy_1,
-        planet_gassy_2,
-        planet_gassy_3,
-        earth,
-        moon,
-        ring,
-        sun,
-        shuttle, 
-    ];
+        // Piloting mode (P): the shuttle flies and the camera chases it
+        if !console.open && !in_menu && !photo.active && input.is_key_pressed(PILOT_KEY) {
+            if shuttle.piloting {
+                shuttle.piloting = false;
+                camera.sync_orbit();
+            } else if let Some(obj) = scene_objects.iter().find(|o| o.object_type == "shuttle") {
+                surface.leave(&mut camera);
+                shuttle.take_control(obj.translation, &camera);
+            }
+        }
 
+        // Surface mode (H): stand on the selected body and look around
+        if !console.open && !in_menu && !photo.active && !shuttle.piloting && input.is_key_pressed(SURFACE_KEY) {
+            if surface.is_active() {
+                surface.take_off(&scene_objects, &mut camera);
+            } else if let Some(obj) = tweak::selected(&scene_objects, debug_view.isolate, camera.target).and_then(|i| scene_objects.get(i)) {
+                match surface.land(obj, &camera) {
+                    Ok(()) => console.print(format!("standing on {}", obj.name)),
+                    Err(error) => console.print(error),
+                }
+            }
+        }
 
-    let stream_handle = rodio::OutputStreamBuilder::open_default_stream()
-        .expect("open default audio stream");
+        // Process camera (or shuttle) input
+        if !console.open && !in_menu {
+            if shuttle.piloting {
+                shuttle.process_input(&input, dt);
+                if input.is_key_pressed(PHYSICS_KEY) {
+                    shuttle.physics = !shuttle.physics;
+                }
+            } else if surface.is_active() {
+                surface.process_input(&input, &scene_objects, dt);
+            } else {
+                camera.process_input(&input);
+            }
+            hud.process_input(&input);
+            minimap.process_input(&input);
+            stereo.process_input(&input);
+            exposure.process_input(&input);
+            trails.process_input(&input);
+            gravity_grid.process_input(&input);
+            beauty.process_input(&input);
+            sim_clock.process_input(&input);
+            debug_view.process_input(&input, scene_objects.len());
+            tweak_panel.process_input(&input);
+            // Shader parameter sliders (F11) for the selected body; clicks on them stay off the scene
+            let selected = tweak::selected(&scene_objects, debug_view.isolate, camera.target);
+            if tweak_panel.hovered(&input, selected.and_then(|i| scene_objects.get(i)), &shader_params) {
+                tweaked = tweak_panel.update(&input, selected.and_then(|i| scene_objects.get_mut(i)), &shader_params);
+            } else {
+                measure.process_input(&input);
+            }
+            let focused = selected.and_then(|i| scene_objects.get(i));
+            light_travel.process_input(&input, focused, &scene_objects, sim_clock.render_time, &scene_file.measure);
+            // Insert: the exact view as one line, for bug reports; `--pose` or the console's `pose` bring it back
+            if input.is_key_pressed(REPRO_KEY) {
+                let pose = Pose::capture(scene_path, scene_file.seed, &camera, fov_y, &sim_clock, focused);
+                let line = pose.to_string();
+                if let Err(error) = window.set_clipboard_text(&line) {
+                    tracing::warn!("could not copy the pose: {}", error);
+                }
+                match pose.write() {
+                    Ok(()) => console.print(format!("copied to the clipboard and {}: {}", REPRO_PATH, line)),
+                    Err(error) => console.print(format!("error: {}", error)),
+                }
+            }
+            events.process_input(&input);
+            // True scale (Z): real proportions, with a camera that can get close to bodies a thousandth the size
+            if real_scale.process_input(&input, &mut scene_objects, &mut depth_range.config, &mut lod) {
+                camera.fine_zoom = real_scale.active;
+                camera.min_distance = if real_scale.active { MIN_CAMERA_DISTANCE } else { 0.5 };
+                framebuffer.depth_mode = depth_range.config.mode;
+                trails.clear();
+                light_travel.clear();
+                tweaked = true;
+                console.print(if real_scale.active { "true scale: real sizes and distances" } else { "back to the scene's layout" }.to_string());
+            }
+            if input.is_key_pressed(DISCOVERY_RESET_KEY) && discovery.config.enabled {
+                discovery.reset();
+                if let Err(error) = discovery.save() {
+                    tracing::warn!("{}", error);
+                }
+                console.print("discoveries reset".to_string());
+            }
+            if input.is_key_pressed(PAUSE_KEY) {
+                paused = !paused;
+            }
+            // Bookmarks: Ctrl+1..9 stores the view, 1..9 in bookmark mode (B) flies back to it
+            match bookmarks.process_input(&input) {
+                Some(BookmarkAction::Store(_)) if real_scale.active => {
+                    console.print("bookmarks can't be stored at true scale; its views are fixed presets".to_string());
+                }
+                Some(BookmarkAction::Recall(slot)) if real_scale.active => match real_scale.preset_pose(slot, &scene_objects) {
+                    Ok((eye, target)) => {
+                        if shuttle.piloting {
+                            shuttle.piloting = false;
+                            camera.sync_orbit();
+                        }
+                        camera.warp_to_pose(eye, target);
+                    }
+                    Err(error) => console.print(error),
+                },
+                Some(BookmarkAction::Store(slot)) => {
+                    let view = ViewSettings {
+                        fov: fov_y,
+                        stereo: stereo.mode,
+                        ev: exposure.ev,
+                        hud: hud.visible,
+                        minimap: minimap.visible,
+                        swap_shading: debug_view.swap_shading,
+                    };
+                    bookmarks.store(slot, camera.eye, camera.target, &scene_objects, view);
+                    if let Err(error) = bookmarks.save() {
+                        tracing::warn!("{}", error);
+                    }
+                    console.print(format!("stored bookmark {}", slot));
+                }
+                Some(BookmarkAction::Recall(slot)) => {
+                    if let Some(bookmark) = bookmarks.get(slot) {
+                        if shuttle.piloting {
+                            shuttle.piloting = false;
+                            camera.sync_orbit();
+                        }
+                        let (eye, target) = bookmark.pose(&scene_objects);
+                        camera.warp_to_pose(eye, target);
+                        let view = &bookmark.view;
+                        fov_y = view.fov.clamp(0.1, 3.0);
+                        stereo.mode = view.stereo;
+                        exposure.ev = view.ev;
+                        exposure.auto = false;
+                        hud.visible = view.hud;
+                        minimap.visible = view.minimap;
+                        debug_view.swap_shading = view.swap_shading;
+                    }
+                }
+                None => {}
+            }
+            if input.is_key_pressed(ERROR_PANEL_KEY) && !asset_errors.is_empty() {
+                show_errors = !show_errors;
+            }
+            // Live edits go next to the scene file rather than over its comments
+            if input.is_key_pressed(SAVE_SCENE_KEY) && real_scale.active {
+                console.print("leave true scale (Z) before saving".to_string());
+            } else if input.is_key_pressed(SAVE_SCENE_KEY) {
+                let path = saved_scene_path(scene_path);
+                match scene_file.with_objects(&scene_objects).save(&path) {
+                    Ok(()) => {
+                        tracing::info!("scene saved to {}", path);
+                        console.print(format!("saved scene to {}", path));
+                    }
+                    Err(error) => {
+                        tracing::warn!("{}", error);
+                        console.print(format!("error: {}", error));
+                    }
+                }
+            }
+        }
 
-    let file = BufReader::new(File::open("assets/music/cherry_galaxy_remix.mp3").unwrap());
-    let sink = rodio::play(&stream_handle.mixer(), file).unwrap();
+        // Hot-reload the scene file when it changes on disk
+        if let Some(result) = scene_watcher.poll() {
+            // The file's layout replaces the scaled one
+            if real_scale.active {
+                real_scale.disable(&mut scene_objects, &mut depth_range.config, &mut lod);
+                camera.fine_zoom = false;
+                camera.min_distance = 0.5;
+            }
+            match result.map_err(String::from).and_then(|mut next| {
+                next.seed = seed_override.unwrap_or(next.seed);
+                apply_scene(&mut scene_objects, &scene_file, &next, &mut meshes)?;
+                Ok(next)
+            }) {
+                Ok(next) => {
+                    textures.set_config(next.textures.clone());
+                    discovery.config = next.discovery.clone();
+                    depth_range.config = next.depth_range.clone();
+                    trails.config = next.trails.clone();
+                    beauty.config = next.beauty.clone();
+                    modulation.config = next.modulation.clone();
+                    photo.config = next.photo.clone();
+                    gravity_grid.config = next.gravity_grid.clone();
+                    soundscape.config = next.soundscape.clone();
+                    events.config = next.events.clone();
+                    trails.clear();
+                    light_travel.clear();
+                    events.clear();
+                    object_blur.reset();
+                    scene_file = next;
+                    reload_error = None;
+                    for warning in validate(&scene_objects, scene_file.depth_range.farthest()) {
+                        tracing::warn!("{}", warning);
+                        console.print(format!("warning: {}", warning));
+                    }
+                }
+                Err(error) => reload_error = Some(error),
+            }
+        }
 
-    while !window.window_should_close() {
-        // Process camera input
-        camera.process_input(&window);
+        // and the shader constants when their file is saved, redrawing even a still view
+        if shader_params.poll() {
+            impostors.clear();
+            tweaked = true;
+        }
+
+        // Prefilter the sky for the shuttle's reflections, a face a frame, sharper as it goes
+        if !reflection.is_complete() && Arc::make_mut(&mut reflection).refine(&skybox) {
+            tweaked = true;
+        }
 
-        if let Some(shuttle_obj) = scene_objects.iter_mut().find(|o| o.object_type == "shuttle") {
+        // Advance orbits and spins in fixed steps (frozen while paused), drawn interpolated between steps
+        // Eclipses, conjunctions and the like are looked for every step, seen from the focused body
+        let observer = tweak::selected(&scene_objects, debug_view.isolate, camera.target).map(|i| scene_objects[i].name.clone());
+        sim_clock.advance_with(&mut scene_objects, dt, paused || photo.active, |objects, time| {
+            trails.record(objects, time);
+            light_travel.step(objects, time, &scene_file.measure);
+            if events.step(objects, time, observer.as_deref()) {
+                paused = true;
+            }
+            !paused
+        });
+        let sim_time = sim_clock.render_time;
+        impostors.retain(|name| scene_objects.iter().any(|o| o.name == name));
+
+        // Solar activity: intensity and color temperature drift over time
+        light.animate(sim_time, &scene_file.light);
+        // and the external modulation input (e.g. an audio level) makes the sun throb
+        modulation.poll(dt);
+        light.intensity *= modulation.sun_pulse();
+        // The sun's size, which blurs the edges of eclipse shadows
+        let sun_radius = scene_objects.iter().find(|o| o.object_type == "sun").map_or(0.0, |o| o.radius * o.scale);
+
+        if shuttle.piloting {
+            let colliders: Vec<Collider> = scene_objects
+                .iter()
+                .filter(|o| o.object_type != "shuttle" && o.object_type != "ring")
+                .map(|o| Collider {
+                    center: o.translation,
+                    radius: o.radius * o.scale,
+                    mass: o.mass(),
+                })
+                .collect();
+
+            if let Some(shuttle_obj) = scene_objects.iter_mut().find(|o| o.object_type == "shuttle") {
+                shuttle.radius = shuttle_obj.radius * shuttle_obj.scale;
+                shuttle.step(dt, &colliders);
+                shuttle_obj.translation = shuttle.position;
+                shuttle_obj.rotation = shuttle.rotation();
+            }
+            shuttle.follow_camera(&mut camera);
+        } else if surface.is_active() {
+            // A warp (tp camera, a bookmark) takes the camera back to orbit mode
+            if camera.warp.is_some() {
+                surface.leave(&mut camera);
+            } else {
+                surface.place_camera(&scene_objects, &mut camera);
+            }
+        } else if let Some(shuttle_obj) = scene_objects.iter_mut().find(|o| o.object_type == "shuttle") {
             // Camera eye (position) and target define the viewing direction
             let cam_pos = camera.eye;
             let cam_target = camera.target;
@@ -335,116 +1072,558 @@ fn main() {
             );
         }
 
-        // Update model rotation
-        rotation_y += rotation_speed;
+        // Camera warp (tp camera <body>): 0..1 while flying, drives the hyperspace effect
+        let warp = if shuttle.piloting { 0.0 } else { camera.update_warp(dt) };
 
-        // Clear framebuffer (color + depth) at the start of the frame
-        framebuffer.clear();
+        // Sounds: events from this frame, then the drone by the distance to the sun and
+        // the rumble by the throttle
+        if camera.warp.is_some() != warping {
+            warping = camera.warp.is_some();
+            // Body motion is measured afresh on either side of a warp
+            object_blur.reset();
+            soundscape.emit(if warping { SoundEvent::WarpStart } else { SoundEvent::WarpEnd });
+        }
+        if debug_view.isolate != isolated {
+            isolated = debug_view.isolate;
+            if isolated.is_some() {
+                soundscape.emit(SoundEvent::Selected);
+            }
+        }
+        let sun_distance = scene_objects.iter().find(|o| o.object_type == "sun").map(|o| (o.translation - camera.eye).length());
+        soundscape.update(sun_distance, if shuttle.piloting { shuttle.throttle } else { 0.0 }, dt);
 
-        let cam_pos = camera.eye;
-        let cam_target = camera.target;
+        // Minimized: the scene keeps moving at a few ticks a second, but nothing is drawn.
+        // An empty frame still goes through raylib, which is where it polls the window.
+        if !window_focus.draws() {
+            window.begin_drawing(thread).clear_background(Color::BLACK);
+            window_focus.idle(frame_start, tile.is_none());
+            continue;
+        }
 
-        // Base de la cámara
-        let mut forward = Vector3::new(
-            cam_target.x - cam_pos.x,
-            cam_target.y - cam_pos.y,
-            cam_target.z - cam_pos.z,
-        );
-        forward.normalize();
-        let mut right = forward.cross(camera_up);
-        right.normalize();
-        
-        let mut up = right.cross(forward);
-        up.normalize();
-
-        for y in 0..window_height {
-            for x in 0..window_width {
-                // Coordenadas Normalized Device Coordinates (NDC) en [-1, 1]
-                let ndc_x = (2.0 * x as f32 / window_width as f32) - 1.0;
-                let ndc_y = 1.0 - (2.0 * y as f32 / window_height as f32);
-
-                // Dirección en espacio de cámara
-                let tan_half_fov = (fov_y * 0.5).tan();
-                let dir_cam = Vector3::new(
-                    ndc_x * aspect * tan_half_fov,
-                    ndc_y * tan_half_fov,
-                    -1.0, // mirando hacia -Z en espacio de cámara
-                );
-
-                // Transformar a espacio mundo usando la base de la cámara
-                let dir_world = {
-                    let dx = right.x * dir_cam.x + up.x * dir_cam.y + forward.x * dir_cam.z;
-                    let dy = right.y * dir_cam.x + up.y * dir_cam.y + forward.y * dir_cam.z;
-                    let dz = right.z * dir_cam.x + up.z * dir_cam.y + forward.z * dir_cam.z;
-                    let mut dir_world = Vector3::new(dx, dy, dz);
-                    dir_world.normalize();
-                    dir_world
-                };
+        // While nothing moves, each frame is offset by a different subpixel amount and averaged
+        // with the ones before, antialiasing the still image. Any change or keypress starts over.
+        let pressed = input.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) || input.any_key_pressed();
+        let extra = [render_width as f32, render_height as f32, stereo.ipd, stereo.mode as u8 as f32, exposure.ev, modulation.value];
+        if !still_frames.is_still(view_state(&camera, &scene_objects, sim_time, &extra), pressed) || tweaked {
+            framebuffer.reset_accumulation();
+        }
+        let jitter = jitter(framebuffer.accumulated_frames());
 
-                let sky_color = sample_cubemap(&skybox, dir_world);
+        // Shared by both eyes in stereo, with near and far fitted around what's in view
+        depth_range.fit(&scene_objects, camera.eye, (camera.target - camera.eye).normalized());
+        let projection_matrix = depth_range.config.mode.projection(
+            match tile {
+                Some(tile) => tile.projection(fov_y, aspect, depth_range.near, depth_range.far),
+                None => create_projection_matrix(fov_y, aspect, depth_range.near, depth_range.far),
+            },
+            depth_range.near,
+            depth_range.far,
+        );
+        let viewport_matrix = create_viewport_matrix(jitter.x, jitter.y, render_width as f32, render_height as f32);
+        let view_matrix = camera.get_view_matrix();
+        // One sky resolution for the whole frame, about a texel per pixel: a 4k face on a
+        // small window would otherwise be read at four times the needed density
+        let sky_level = skybox.level_for(&projection_matrix, render_height);
+        let forward = (camera.target - camera.eye).normalized();
 
-                // Fondo con depth=1.0 (máximo), los objetos con menor depth lo sobreescriben
-                framebuffer.point(
-                    x as i32,
-                    y as i32,
-                    sky_color,
-                    100.0,
-                );
+        // Discovery mode: bodies the camera flies near or holds centered are revealed for good
+        let found = discovery.update(&scene_objects, camera.eye, &view_matrix, &projection_matrix, &viewport_matrix, dt);
+        if !found.is_empty() {
+            soundscape.emit(SoundEvent::Discovered);
+            console.print(format!("discovered {}", found.join(", ")));
+            if let Err(error) = discovery.save() {
+                tracing::warn!("{}", error);
             }
         }
 
-        // Matrices that are global for this frame (camera and projection)
-        let view_matrix = camera.get_view_matrix();
-        let projection_matrix = create_projection_matrix(fov_y, aspect, near, far);
-        let viewport_matrix = create_viewport_matrix(0.0, 0.0, window_width as f32, window_height as f32);
+        // The scene is drawn once per eye (just once with stereo off), left eye first
+        let eye_offsets = stereo.eye_offsets();
+        let mut stats = FrameStats::default();
+        for (eye_index, &eye_offset) in eye_offsets.iter().enumerate() {
+            let eye = Stereo::eye_position(camera.eye, camera.target, camera.up, eye_offset);
+            let cam_pos = eye;
+            let cam_target = camera.target;
 
-        for obj in &scene_objects {
-            // Apply global rotation to planets, but keep the shuttle stable relative to camera
-            let rotation = if obj.object_type == "shuttle" {
-                obj.rotation
-            } else {
-                Vector3::new(
-                    obj.rotation.x,
-                    obj.rotation.y + rotation_y,
-                    obj.rotation.z,
-                )
+            // Base de la cámara
+            let mut forward = Vector3::new(
+                cam_target.x - cam_pos.x,
+                cam_target.y - cam_pos.y,
+                cam_target.z - cam_pos.z,
+            );
+            forward.normalize();
+            let mut right = forward.cross(camera.up);
+            right.normalize();
+        
+            let mut up = right.cross(forward);
+            up.normalize();
+
+            // This eye's camera (projection and viewport are shared by both eyes)
+            let view_matrix = create_view_matrix(eye, camera.target, camera.up);
+            let frame = FrameUniforms {
+                time: sim_time,
+                light_position: light.position,
+                sun_radius,
+                ambient: scene_file.light.ambient,
+                sky: skybox.ambient(scene_file.light.irradiance),
+                fill_lights: beauty.lights(forward, up, right),
+                sun_pulse: modulation.sun_pulse(),
+                depth_mode: depth_range.config.mode,
+                fbm_octaves: level.fbm_octaves.clamp(1, MAX_FBM_OCTAVES),
+                ..FrameUniforms::new(view_matrix, projection_matrix, viewport_matrix, eye)
+            };
+            // World-space overlays (dust, lines) use the frame's camera with no model transform
+            let world_uniforms = Uniforms {
+                frame,
+                object: ObjectUniforms {
+                    screen_center: Vector2::new(render_width as f32 * 0.5, render_height as f32 * 0.5),
+                    screen_radius: render_height as f32 * 0.5,
+                    ..ObjectUniforms::new(Matrix::identity(), &frame)
+                },
             };
 
-            // Per-object model matrix using its own translation, rotation, and scale
-            let model_matrix = create_model_matrix(obj.translation, obj.scale, rotation);
+            // Passes in the order `frame_passes` resolved; see passes.rs for what each touches.
+            // The HUD shows the last eye's counts
+            stats = FrameStats::default();
+            for &pass in &passes.eye {
+                match pass {
+                    Pass::Clear => {
+                        // Cleared at least once at each size, to time what skipping it saves
+                        if clear_frame(framebuffer, sky_covers, &mut stats) {
+                            color_clears_skipped += 1;
+                        }
+                        arena.reset();
+                    }
+                    Pass::Sky => {
+                        // During a warp the sky is sampled along the camera's rotation since last frame so stars streak
+                        let streak = match previous_view {
+                            Some((_, previous_forward)) if warp > 0.0 => (previous_forward - forward) * (3.0 * warp),
+                            _ => Vector3::zero(),
+                        };
+                        let streak_samples = if warp > 0.0 { 4 } else { 1 };
 
-            let uniforms = Uniforms {
-                model_matrix,
-                view_matrix,
-                projection_matrix,
-                viewport_matrix,
-            };
+                        // At lower quality the sky is sampled once per step×step block
+                        let sky_step = level.skybox_step.max(1) as usize;
+                        for y in (0..render_height).step_by(sky_step) {
+                            for x in (0..render_width).step_by(sky_step) {
+                                // Coordenadas Normalized Device Coordinates (NDC) en [-1, 1], en el centro del bloque
+                                let half_step = (sky_step as f32 - 1.0) * 0.5;
+                                let ndc_x = (2.0 * (x as f32 + half_step - jitter.x) / render_width as f32) - 1.0;
+                                let ndc_y = 1.0 - (2.0 * (y as f32 + half_step - jitter.y) / render_height as f32);
 
-            render(
-                &mut framebuffer,
-                &uniforms,
-                obj.vertices.as_slice(),
-                &light,
-                &obj.object_type,
-            );
+                                // Dirección en espacio de cámara (mirando hacia -Z), también con proyección descentrada
+                                let dir_cam = view_ray(&projection_matrix, ndc_x, ndc_y);
+
+                                // Transformar a espacio mundo usando la base de la cámara; -Z de vista es `forward`
+                                let dir_world = {
+                                    let dx = right.x * dir_cam.x + up.x * dir_cam.y - forward.x * dir_cam.z;
+                                    let dy = right.y * dir_cam.x + up.y * dir_cam.y - forward.y * dir_cam.z;
+                                    let dz = right.z * dir_cam.x + up.z * dir_cam.y - forward.z * dir_cam.z;
+                                    let mut dir_world = Vector3::new(dx, dy, dz);
+                                    dir_world.normalize();
+                                    dir_world
+                                };
+
+                                let mut sky_color = Vector3::zero();
+                                for i in 0..streak_samples {
+                                    let offset = streak * (i as f32 / streak_samples as f32);
+                                    sky_color += sample_cubemap_level(&skybox, dir_world + offset, sky_level);
+                                }
+                                sky_color /= streak_samples as f32;
+
+                                // El fondo no escribe profundidad: el depth buffer queda en el valor de borrado (`DepthMode::cleared`) y
+                                // cualquier objeto lo sobreescribe, sin mezclar escalas con el NDC de los objetos
+                                for by in y..(y + sky_step as i32).min(render_height) {
+                                    for bx in x..(x + sky_step as i32).min(render_width) {
+                                        framebuffer.set_pixel(bx, by, sky_color);
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    Pass::DeepSky => {
+                        // Galaxies and nebulae between the sky and the bodies
+                        draw_deep_sky(&scene_file.deep_sky, scene_file.seed, &frame, |x, y, color| framebuffer.add_color(x, y, color));
+                    }
+                    Pass::Bodies => {
+                        // Sphere bodies that cast shadows, as (index, bounding sphere)
+                        let shadow_casters: Vec<(usize, Occluder)> = scene_objects
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, o)| o.sphere && o.flags.visible && o.flags.cast_shadows)
+                            .map(|(i, o)| (i, Occluder { center: o.translation, radius: o.radius * o.scale }))
+                            .collect();
+                        // Blob under the shuttle on the body it skims over
+                        let contact_shadow = if shuttle.piloting { shuttle.contact_shadow(&scene_objects) } else { None };
+                        // The body the camera looks at, supersampled when it's big on screen
+                        let focused = tweak::selected(&scene_objects, debug_view.isolate, camera.target).filter(|&i| {
+                            let obj = &scene_objects[i];
+                            (obj.translation - camera.target).length() <= obj.radius * obj.scale
+                        });
+
+                        // Front to back, skipping bodies hidden behind nearer ones (e.g. behind the sun)
+                        let mut culler = OcclusionCuller::new(eye);
+                        // The sun's glow goes over everything drawn behind it, once the bodies are done
+                        let mut sun_halo = None;
+                        for index in draw_order(&scene_objects, eye) {
+                            let obj = &scene_objects[index];
+                            if !debug_view.is_visible(index) || !obj.flags.visible {
+                                continue;
+                            }
+                            if culler.is_hidden(obj) {
+                                stats.objects_culled += 1;
+                                continue;
+                            }
+                            culler.add(obj);
+                            // Undiscovered bodies are drawn as plain grey placeholders
+                            let discovered = discovery.is_discovered(obj);
+                            let kind = if discovered { obj.object_type.as_str() } else { PLACEHOLDER_KIND };
+                            stats.objects_drawn += 1;
+                            framebuffer.set_object_id(object_id(index));
+                            framebuffer.set_depth_mode(obj.flags.depth_test, obj.flags.depth_write);
+
+                            // Per-object model matrix using its own translation, rotation, and scale
+                            let model_matrix = obj.model_matrix();
+                            let ring = obj.ring.clone().unwrap_or_default();
+
+                            // Moons get earthshine from their earth (the parent if it is one, else any earth)
+                            let bounce = if obj.object_type == "moon" {
+                                scene_objects
+                                    .iter()
+                                    .find(|o| o.object_type == "earth" && obj.parent.as_deref() == Some(o.name.as_str()))
+                                    .or_else(|| scene_objects.iter().find(|o| o.object_type == "earth"))
+                                    .map(|earth| {
+                                        let earth_irradiance = light.irradiance_at(earth.translation, &scene_file.light);
+                                        earthshine(light.position, earth.translation, obj.translation, earth_irradiance, &scene_file.light)
+                                    })
+                            } else {
+                                None
+                            };
+
+                            let mut uniforms = Uniforms {
+                                frame,
+                                object: ObjectUniforms {
+                                    irradiance: light.irradiance_at(obj.translation, &scene_file.light),
+                                    ring_radii: Vector2::new(ring.inner_radius, ring.outer_radius),
+                                    ring_gaps: ring_gaps(obj, &scene_objects),
+                                    ring_profile: obj.ring_profile.clone(),
+                                    bounce,
+                                    screen_center: Vector2::new(render_width as f32 * 0.5, render_height as f32 * 0.5),
+                                    screen_radius: render_height as f32 * 0.5,
+                                    occluders: if obj.flags.receive_shadows {
+                                        shadow_casters.iter().filter(|(i, _)| *i != index).map(|(_, o)| *o).collect()
+                                    } else {
+                                        Vec::new()
+                                    },
+                                    contact_shadow: contact_shadow.filter(|(i, _)| *i == index).map(|(_, shadow)| shadow),
+                                    noise: obj.noise,
+                                    params: shader_params.for_body(&obj.object_type, &obj.params),
+                                    ramps: obj.ramps.clone(),
+                                    planet: if discovered { obj.planet.clone() } else { None },
+                                    graticule: obj.flags.graticule && obj.sphere,
+                                    reflection: (obj.object_type == "shuttle").then(|| reflection.clone()),
+                                    ..ObjectUniforms::new(model_matrix, &frame)
+                                },
+                            };
+
+                            // Projected bounding sphere, for screen-space shader effects (the whole screen when too close)
+                            let projected = project_sphere(obj.translation, obj.radius * obj.scale, &uniforms);
+                            if let Some(sphere) = &projected {
+                                uniforms.object.screen_center = sphere.center_px;
+                                uniforms.object.screen_radius = sphere.radius_px;
+                                uniforms.object.detail_budget = detail_budget(sphere.radius_px);
+                            }
+                            let radius_px = projected.as_ref().map(|sphere| sphere.radius_px);
+                            if kind == "sun" && obj.sphere {
+                                sun_halo = projected.map(|sphere| (sphere, uniforms.object.params.clone()));
+                            }
+
+                            // Tiny spheres use cached sprites, small ones are ray-cast; everything else goes through the mesh pipeline
+                            let screen_sphere = if obj.sphere { projected } else { None };
+
+                            // Texture maps are loaded the first frame the body may be on screen
+                            let on_screen = screen_sphere.as_ref().is_none_or(|s| {
+                                s.center_px.x + s.radius_px >= 0.0
+                                    && s.center_px.x - s.radius_px < render_width as f32
+                                    && s.center_px.y + s.radius_px >= 0.0
+                                    && s.center_px.y - s.radius_px < render_height as f32
+                            });
+                            if on_screen && discovered {
+                                uniforms.object.material = textures.material(&obj.material, &obj.object_type);
+                                if debug_view.swap_shading {
+                                    uniforms.object.material.shading.model = uniforms.object.material.shading.model.other();
+                                }
+                            }
+
+                            match screen_sphere {
+                                Some(sphere) if sphere.radius_px < lod.impostor_px * level.lod_bias => {
+                                    impostors.draw(framebuffer, &uniforms, &sphere, &obj.name, kind, eye);
+                                }
+                                Some(sphere) if sphere.radius_px < lod.raycast_px * level.lod_bias => {
+                                    render_sphere(framebuffer, &uniforms, &sphere, kind);
+                                }
+                                _ => {
+                                    let samples = match radius_px {
+                                        Some(radius_px) if focused == Some(index) => focus_samples(radius_px, render_height as u32),
+                                        _ => 1,
+                                    };
+                                    stats.focus_samples = stats.focus_samples.max(samples);
+                                    pipeline.set_samples(samples);
+                                    pipeline.render(framebuffer, &uniforms, obj.vertices.as_slice(), &light, &obj.name, kind, &mut arena);
+                                    pipeline.set_samples(1);
+                                }
+                            }
+                        }
+
+                        framebuffer.set_object_id(0);
+                        framebuffer.set_depth_mode(true, true);
+                        if let Some((sphere, params)) = &sun_halo {
+                            draw_sun_halo(framebuffer, sphere, params, frame.sun_pulse);
+                        }
+                        // Textures that failed to load this frame join the error panel
+                        let texture_errors = textures.take_errors();
+                        if !texture_errors.is_empty() {
+                            asset_errors.extend(texture_errors);
+                            show_errors = true;
+                        }
+                    }
+                    Pass::ObjectBlur => {
+                        // Fast bodies (at high time scales) streak along their motion on screen
+                        object_blur.apply(framebuffer, &scene_objects, &view_matrix, &projection_matrix, &viewport_matrix);
+                    }
+                    Pass::Picking => {
+                        // Clicks are resolved against this frame's object-ID buffer, before anything else draws
+                        measure.resolve_click(framebuffer, &scene_objects, &world_uniforms, eye, &stereo, eye_index);
+                    }
+                    // Zodiacal dust glow, faded against the depth of everything drawn so far
+                    Pass::Dust if debug_view.isolate.is_none() => {
+                        draw_dust(framebuffer, &world_uniforms, eye, &scene_file.dust);
+                    }
+                    // Engine exhaust while thrusting, added over everything opaque
+                    Pass::Plume if shuttle.piloting && shuttle.throttle > 0.0 => {
+                        let shuttle_obj = scene_objects.iter().enumerate().find(|(_, o)| o.object_type == "shuttle");
+                        if let Some((index, obj)) = shuttle_obj
+                            && debug_view.is_visible(index)
+                        {
+                            let uniforms = Uniforms {
+                                frame,
+                                object: ObjectUniforms::new(obj.model_matrix(), &frame),
+                            };
+                            draw_plume(framebuffer, &uniforms, &light, shuttle.throttle, eye);
+                        }
+                    }
+                    Pass::Guides => {
+                        // Predicted coast path while flying with gravity
+                        if shuttle.piloting && shuttle.physics {
+                            line::polyline(framebuffer, &world_uniforms, &shuttle.predicted_path, Vector3::new(0.3, 0.9, 1.0));
+                        }
+
+                        // Orbit paths, planes and directions while O is held
+                        if !console.open && !in_menu && !photo.active && input.is_key_down(ORBIT_PLANE_KEY) {
+                            draw_orbit_planes(framebuffer, &world_uniforms, &scene_objects, debug_view.isolate, |o| discovery.is_discovered(o));
+                        }
+
+                        // Rubber-sheet gravity wells under the bodies, while V has it on
+                        if !photo.active {
+                            gravity_grid.draw(framebuffer, &world_uniforms, &scene_objects);
+                        }
+
+                        // Where the bodies have been lately
+                        if debug_view.isolate.is_none() {
+                            trails.draw(framebuffer, &world_uniforms, &scene_objects, |o| discovery.is_discovered(o));
+                        }
+                    }
+                    // Gizmos, measurements and labels are left out of photos
+                    Pass::Annotations if !photo.active => {
+                        // Normals and axes of the isolated object
+                        if let Some(obj) = debug_view.isolate.and_then(|i| scene_objects.get(i)) {
+                            draw_gizmo(framebuffer, &world_uniforms, obj, debug_view.gizmo);
+                        }
+
+                        measure.draw(framebuffer, &scene_objects, &world_uniforms);
+                        light_travel.draw(framebuffer, &world_uniforms, eye, sim_time, &scene_file.measure);
+
+                        // World-anchored labels go into the frame, depth-tested against the bodies in front
+                        discovery.draw_labels(framebuffer, &scene_objects, &world_uniforms, eye);
+                        measure.draw_labels(framebuffer, &scene_objects, &world_uniforms, &scene_file.measure);
+                    }
+                    Pass::BufferView => {
+                        stats.overdraw = framebuffer.average_overdraw();
+                        match debug_view.buffer {
+                            BufferView::Shaded => {}
+                            BufferView::ObjectIds => framebuffer.show_object_ids(id_color),
+                            BufferView::Overdraw => framebuffer.show_overdraw(heat_color),
+                        }
+                    }
+                    Pass::StereoLeft if eye_index + 1 < eye_offsets.len() => {
+                        stereo.store_left(framebuffer.colors());
+                    }
+                    // Nothing to do this frame
+                    Pass::Dust | Pass::Plume | Pass::Annotations | Pass::StereoLeft => {}
+                    // Once every eye is drawn, below
+                    Pass::StereoComposite | Pass::WarpBlur | Pass::Accumulate | Pass::ExposureMeter | Pass::Minimap | Pass::Dump => {}
+                }
+            }
+
+            // for obj in &scene_objects {
+            //     let model_matrix = obj.model_matrix();
+            //     let uniforms = Uniforms {
+            //         model_matrix,
+            //         view_matrix,
+            //         projection_matrix,
+            //         viewport_matrix,
+            //     };
+            //     render(framebuffer, &uniforms, obj.vertices.as_slice(), &light, obj.color);
+            // }
         }
+        for &pass in &passes.frame {
+            match pass {
+                Pass::StereoComposite => {
+                    if stereo.mode != StereoMode::Off
+                        && let Some(merged) = stereo.composite(&framebuffer.colors(), render_width as usize, render_height as usize)
+                    {
+                        framebuffer.set_colors(&merged);
+                    }
+                }
+                Pass::WarpBlur => {
+                    // Hyperspace blur: smear earlier frames along the screen-space camera motion.
+                    // The history is dropped as soon as the warp ends so nothing ghosts afterwards.
+                    if warp > 0.0 {
+                        let shift = previous_view
+                            .and_then(|(previous, _)| {
+                                let ahead = camera.eye + forward * 50.0;
+                                world_to_screen(ahead, &previous, &projection_matrix, &viewport_matrix)
+                            })
+                            .map(|p| Vector2::new(render_width as f32 * 0.5 - p.x, render_height as f32 * 0.5 - p.y))
+                            .unwrap_or(Vector2::zero());
+                        framebuffer.motion_blur(shift, 0.8 * warp);
+                    } else {
+                        framebuffer.reset_history();
+                    }
+                }
+                Pass::Accumulate => framebuffer.accumulate(),
+                Pass::ExposureMeter => exposure.update(framebuffer, dt),
+                Pass::Minimap => {
+                    // Minimap goes on top of the 3D scene
+                    let (viewer, heading) = if shuttle.piloting {
+                        (shuttle.position, shuttle.forward())
+                    } else {
+                        (camera.eye, camera.target - camera.eye)
+                    };
+                    if !photo.active {
+                        minimap.draw(framebuffer, &scene_objects, viewer, heading, camera.target);
+                    }
+                }
+                Pass::Dump => {
+                    if let Err(error) = pipeline.end_frame(framebuffer) {
+                        tracing::error!("frame dump failed: {}", error);
+                    }
+                }
+                // Per-eye passes ran above
+                Pass::Clear
+                | Pass::Sky
+                | Pass::DeepSky
+                | Pass::Bodies
+                | Pass::ObjectBlur
+                | Pass::Picking
+                | Pass::Dust
+                | Pass::Plume
+                | Pass::Guides
+                | Pass::Annotations
+                | Pass::BufferView
+                | Pass::StereoLeft => {}
+            }
+        }
+        previous_view = Some((view_matrix, forward));
+        object_blur.end_frame(&scene_objects);
+        stats.accumulated_frames = framebuffer.accumulated_frames();
+        stats.sky_bytes = skybox.memory_bytes() + reflection.memory_bytes();
+        stats.threads = pipeline.thread_count();
+        stats.oversized_triangles = pipeline.oversized_triangles();
+        stats.depth_range = (depth_range.near, depth_range.far);
+        stats.texture_bytes = textures.used_bytes();
+        stats.color_clears_skipped = color_clears_skipped;
 
-        // for obj in &scene_objects {
-        //     let model_matrix = create_model_matrix(obj.translation, obj.scale, obj.rotation);
-        //     let uniforms = Uniforms {
-        //         model_matrix,
-        //         view_matrix,
-        //         projection_matrix,
-        //         viewport_matrix,
-        //     };
-        //     render(&mut framebuffer, &uniforms, obj.vertices.as_slice(), &light, obj.color);
-        // }
+        // Adapt quality to the time this frame took, before the frame limiter waits
+        quality.update(frame_start.elapsed().as_secs_f32(), dt);
 
-        // Call the encapsulated swap_buffers function
-        framebuffer.swap_buffers(&mut window, &thread);
+        // One pass of a photo being rendered; the preview keeps running between passes
+        if let Some(message) = photo.update(&skybox, &scene_file.deep_sky, scene_file.seed) {
+            tracing::info!("{}", message);
+            console.print(message);
+        }
 
-        
+        let selected = tweak::selected(&scene_objects, debug_view.isolate, camera.target);
+        let light_lines = light_travel.panel_lines(selected.and_then(|i| scene_objects.get(i)), &scene_objects, sim_time, &scene_file.measure);
 
-        thread::sleep(Duration::from_millis(16));
+        // Call the encapsulated swap_buffers function
+        framebuffer.swap_buffers(window, thread, |d| {
+            if photo.active {
+                photo.draw(d, window_width, window_height);
+                scene_menu.draw(d, window_width, window_height);
+                return;
+            }
+            // Brief flash when the shuttle bumps into something
+            if shuttle.impact > 0.0 {
+                let alpha = (shuttle.impact / 0.25 * 90.0) as u8;
+                d.draw_rectangle(0, 0, window_width, window_height, Color::new(255, 255, 255, alpha));
+            }
+            hud.draw(d, window_width, window_height, &quality, &stats);
+            if hud.visible {
+                d.draw_text(&exposure.status(), 10, window_height - 162, 10, Color::LIGHTGRAY);
+                // Seasons: where the sun stands overhead on each body with a tilted axis
+                for (i, obj) in scene_objects.iter().filter(|o| o.axial_tilt != 0.0).enumerate() {
+                    let latitude = obj.subsolar_latitude(light.position).to_degrees();
+                    let text = format!("{}: subsolar latitude {:+.1} deg", obj.name, latitude);
+                    d.draw_text(&text, 10, window_height - 190 - 14 * i as i32, 10, Color::LIGHTGRAY);
+                }
+            }
+            if exposure.spot {
+                let (cx, cy) = (window_width / 2, window_height / 2);
+                d.draw_line(cx - 8, cy, cx + 8, cy, Color::YELLOW);
+                d.draw_line(cx, cy - 8, cx, cy + 8, Color::YELLOW);
+            }
+            if let Some(status) = measure.status() {
+                d.draw_text(&status, 10, 60, 16, Color::YELLOW);
+            }
+            if let Some(status) = stereo.status() {
+                d.draw_text(&status, 10, 84, 16, Color::SKYBLUE);
+            }
+            let bookmark_status = if real_scale.active { bookmarks.mode.then(|| real_scale.preset_status()) } else { bookmarks.status() };
+            if let Some(status) = bookmark_status {
+                d.draw_text(&status, 10, 132, 16, Color::GOLD);
+            }
+            if let Some(status) = beauty.status() {
+                d.draw_text(&status, 10, 156, 16, Color::SKYBLUE);
+            }
+            if let Some(status) = surface.status() {
+                d.draw_text(&status, 10, 180, 16, Color::LIME);
+            }
+            if let Some(status) = real_scale.status() {
+                d.draw_text(&status, 10, 204, 16, Color::ORANGE);
+            }
+            if paused {
+                d.draw_text("Paused (Space)", 10, 108, 16, Color::LIGHTGRAY);
+            } else if let Some(status) = sim_clock.status() {
+                d.draw_text(&status, 10, 108, 16, Color::LIGHTGRAY);
+            }
+            if let Some(obj) = debug_view.isolate.and_then(|i| scene_objects.get(i)) {
+                d.draw_text(&format!("Isolated: {} ({})", obj.name, obj.object_type), 10, 36, 20, Color::ORANGE);
+            }
+            if let Some(error) = &reload_error {
+                d.draw_rectangle(0, window_height - 30, window_width, 30, Color::new(120, 20, 20, 220));
+                d.draw_text(&format!("Scene reload failed: {}", error), 10, window_height - 24, 16, Color::WHITE);
+            }
+            if show_errors {
+                draw_error_panel(d, &asset_errors, window_width);
+            }
+            light_travel.draw_panel(d, window_width, window_height, &light_lines);
+            events.draw(d, window_width);
+            tweak_panel.draw(d, selected.and_then(|i| scene_objects.get(i)), &shader_params);
+            console.draw(d, window_width);
+            scene_menu.draw(d, window_width, window_height);
+        });
+        // Out of focus the frame rate drops too, except on a video wall
+        window_focus.idle(frame_start, tile.is_none());
     }
+    SceneExit::Quit
 }
@@ -1,5 +1,17 @@
 #![allow(dead_code)]
 
+//! Matrix conventions used by the software renderer:
+//! - Vectors are column vectors and transforms are applied as `M * v`
+//!   (see `multiply_matrix_vector4`).
+//! - `new_matrix4`/`new_matrix3` take their arguments in row-major order, as the
+//!   matrix is written on paper, and store them in raylib's column-major `Matrix`.
+//! - raylib's `a * b` is `MatrixMultiply(a, b)`, which applies `a` first and `b`
+//!   second, i.e. it computes `b·a` in math notation. Compositions below are written
+//!   in application order.
+//! - Model matrices are T·R·S with R = Rx·Ry·Rz (Z rotation applied first).
+//! - The camera looks down -Z; projection maps z = -near to NDC -1 and z = -far to +1.
+//! - The viewport flips Y so NDC (-1, 1) is the top-left pixel corner.
+
 use raylib::prelude::*;
 
 /// Creates a 4x4 matrix from 16 float values, specified in traditional row-major order.
@@ -69,6 +81,7 @@ pub fn create_model_matrix(translation: Vector3, scale: f32, rotation: Vector3)
         0.0,   0.0,    0.0, 1.0
     );
 
+    // Applied Z first, then Y, then X (Rx·Ry·Rz)
     let rotation_matrix = rotation_matrix_z * rotation_matrix_y * rotation_matrix_x;
 
     // Scaling matrix
@@ -87,7 +100,21 @@ pub fn create_model_matrix(translation: Vector3, scale: f32, rotation: Vector3)
         0.0, 0.0, 0.0, 1.0
     );
 
-    translation_matrix * rotation_matrix * scale_matrix
+    // raylib's `a * b` is MatrixMultiply(a, b), which applies `a` first and `b` second
+    // (it computes b·a in column-vector notation). Writing the product as S * R * T
+    // therefore yields T·R·S: scale, then rotate, then translate into the world.
+    scale_matrix * rotation_matrix * translation_matrix
+}
+
+/// Model matrix of a body whose spin axis leans `tilt` radians from +Y, towards +X
+/// turned `precession` radians around +Y. `rotation` (spin included) is applied in
+/// the body's own frame first, so the tilt stays fixed in world space.
+pub fn create_tilted_model_matrix(translation: Vector3, scale: f32, rotation: Vector3, tilt: f32, precession: f32) -> Matrix {
+    let body = create_model_matrix(Vector3::zero(), scale, rotation);
+    let lean = create_model_matrix(Vector3::zero(), 1.0, Vector3::new(0.0, 0.0, -tilt));
+    let turn = create_model_matrix(Vector3::zero(), 1.0, Vector3::new(0.0, precession, 0.0));
+    let place = create_model_matrix(translation, 1.0, Vector3::zero());
+    body * lean * turn * place
 }
 
 /// Creates a view matrix using camera position, target, and up vector
@@ -150,6 +177,23 @@ pub fn create_projection_matrix(fov_y: f32, aspect: f32, near: f32, far: f32) ->
     )
 }
 
+/// Creates an off-axis perspective projection from the edges of the view window on the
+/// near plane, in view space; `create_projection_matrix` is the centered special case.
+pub fn create_frustum_matrix(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> Matrix {
+    new_matrix4(
+        2.0 * near / (right - left), 0.0, (right + left) / (right - left), 0.0,
+        0.0, 2.0 * near / (top - bottom), (top + bottom) / (top - bottom), 0.0,
+        0.0, 0.0, -(far + near) / (far - near), -(2.0 * far * near) / (far - near),
+        0.0, 0.0, -1.0, 0.0,
+    )
+}
+
+/// View-space direction (not normalized, z = -1) of the ray through an NDC point, for
+/// centered and off-axis projections alike.
+pub fn view_ray(projection: &Matrix, ndc_x: f32, ndc_y: f32) -> Vector3 {
+    Vector3::new((ndc_x + projection.m8) / projection.m0, (ndc_y + projection.m9) / projection.m5, -1.0)
+}
+
 /// Creates a viewport matrix to transform NDC coordinates to screen space
 /// x, y: Viewport position (typically 0, 0)
 /// width, height: Viewport dimensions in pixels
@@ -173,4 +217,214 @@ pub fn multiply_matrix_vector4(matrix: &Matrix, vector: &Vector4) -> Vector4 {
         matrix.m2 * vector.x + matrix.m6 * vector.y + matrix.m10 * vector.z + matrix.m14 * vector.w,
         matrix.m3 * vector.x + matrix.m7 * vector.y + matrix.m11 * vector.z + matrix.m15 * vector.w,
     )
-}
\ No newline at end of file
+}
+
+/// Product `a * b` (applies `b` first, then `a`), e.g. projection * view.
+pub fn multiply_matrices(a: &Matrix, b: &Matrix) -> Matrix {
+    // Columns of the result are `a` applied to the columns of `b`
+    let column = |x: f32, y: f32, z: f32, w: f32| multiply_matrix_vector4(a, &Vector4::new(x, y, z, w));
+    let c0 = column(b.m0, b.m1, b.m2, b.m3);
+    let c1 = column(b.m4, b.m5, b.m6, b.m7);
+    let c2 = column(b.m8, b.m9, b.m10, b.m11);
+    let c3 = column(b.m12, b.m13, b.m14, b.m15);
+    Matrix {
+        m0: c0.x, m1: c0.y, m2: c0.z, m3: c0.w,
+        m4: c1.x, m5: c1.y, m6: c1.z, m7: c1.w,
+        m8: c2.x, m9: c2.y, m10: c2.z, m11: c2.w,
+        m12: c3.x, m13: c3.y, m14: c3.z, m15: c3.w,
+    }
+}
+
+/// Projects a world-space point to screen pixels (x, y) and NDC depth (z).
+/// Returns None for points behind the camera.
+pub fn world_to_screen(point: Vector3, view: &Matrix, projection: &Matrix, viewport: &Matrix) -> Option<Vector3> {
+    let view_position = multiply_matrix_vector4(view, &Vector4::new(point.x, point.y, point.z, 1.0));
+    let clip = multiply_matrix_vector4(projection, &view_position);
+    if clip.w <= 0.0 {
+        return None;
+    }
+    let ndc = Vector4::new(clip.x / clip.w, clip.y / clip.w, clip.z / clip.w, 1.0);
+    let screen = multiply_matrix_vector4(viewport, &ndc);
+    Some(Vector3::new(screen.x, screen.y, screen.z))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::f32::consts::{FRAC_PI_2, PI};
+
+    const EPS: f32 = 1e-4;
+
+    fn assert_vec4(actual: Vector4, expected: Vector4) {
+        assert!(
+            (actual.x - expected.x).abs() < EPS
+                && (actual.y - expected.y).abs() < EPS
+                && (actual.z - expected.z).abs() < EPS
+                && (actual.w - expected.w).abs() < EPS,
+            "expected {:?}, got {:?}",
+            expected,
+            actual
+        );
+    }
+
+    fn transform_point(m: &Matrix, x: f32, y: f32, z: f32) -> Vector4 {
+        multiply_matrix_vector4(m, &Vector4::new(x, y, z, 1.0))
+    }
+
+    #[test]
+    fn new_matrix4_is_row_major_input() {
+        let m = new_matrix4(
+            1.0, 2.0, 3.0, 4.0,
+            5.0, 6.0, 7.0, 8.0,
+            9.0, 10.0, 11.0, 12.0,
+            13.0, 14.0, 15.0, 16.0,
+        );
+        // Row i of the result is the dot product of row i with v
+        let v = Vector4::new(1.0, 0.0, -1.0, 2.0);
+        assert_vec4(multiply_matrix_vector4(&m, &v), Vector4::new(6.0, 14.0, 22.0, 30.0));
+    }
+
+    #[test]
+    fn new_matrix3_embeds_in_upper_left() {
+        let m = new_matrix3(
+            0.0, -1.0, 0.0,
+            1.0, 0.0, 0.0,
+            0.0, 0.0, 2.0,
+        );
+        assert_vec4(transform_point(&m, 1.0, 2.0, 3.0), Vector4::new(-2.0, 1.0, 6.0, 1.0));
+    }
+
+    #[test]
+    fn identity_leaves_vectors_unchanged() {
+        let identity = new_matrix3(1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0);
+        let mut rng = StdRng::seed_from_u64(112);
+        for _ in 0..1000 {
+            let v = Vector4::new(
+                rng.random_range(-1000.0..1000.0),
+                rng.random_range(-1000.0..1000.0),
+                rng.random_range(-1000.0..1000.0),
+                rng.random_range(-1000.0..1000.0),
+            );
+            let result = multiply_matrix_vector4(&identity, &v);
+            assert_eq!((result.x, result.y, result.z, result.w), (v.x, v.y, v.z, v.w));
+        }
+    }
+
+    #[test]
+    fn raylib_mul_applies_left_operand_first() {
+        let translate = create_model_matrix(Vector3::new(1.0, 0.0, 0.0), 1.0, Vector3::zero());
+        let scale = create_model_matrix(Vector3::zero(), 3.0, Vector3::zero());
+        let m = translate * scale;
+        // Translate first, then scale: (0 + 1) * 3
+        assert_vec4(transform_point(&m, 0.0, 0.0, 0.0), Vector4::new(3.0, 0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn mvp_matches_applying_each_matrix_in_turn() {
+        let model = create_model_matrix(Vector3::new(3.0, -1.0, 2.0), 1.5, Vector3::new(0.3, 1.1, -0.4));
+        let view = create_model_matrix(Vector3::new(0.0, -5.0, -100.0), 1.0, Vector3::new(0.05, 0.2, 0.0));
+        let projection = create_projection_matrix(PI / 3.0, 4.0 / 3.0, 0.1, 100.0);
+        let mvp = multiply_matrices(&multiply_matrices(&projection, &view), &model);
+
+        let mut rng = StdRng::seed_from_u64(144);
+        for _ in 0..1000 {
+            let p = Vector4::new(rng.random_range(-2.0..2.0), rng.random_range(-2.0..2.0), rng.random_range(-2.0..2.0), 1.0);
+            let stepwise = multiply_matrix_vector4(&projection, &multiply_matrix_vector4(&view, &multiply_matrix_vector4(&model, &p)));
+            let combined = multiply_matrix_vector4(&mvp, &p);
+            assert!((stepwise.x - combined.x).abs() < 1e-3 && (stepwise.w - combined.w).abs() < 1e-3, "{:?} vs {:?}", stepwise, combined);
+        }
+    }
+
+    #[test]
+    fn model_matrix_is_translate_rotate_scale() {
+        let m = create_model_matrix(
+            Vector3::new(10.0, 0.0, 0.0),
+            2.0,
+            Vector3::new(0.0, FRAC_PI_2, 0.0),
+        );
+        // Scale to (2,0,0), rotate +90° about Y to (0,0,-2), translate to (10,0,-2)
+        assert_vec4(transform_point(&m, 1.0, 0.0, 0.0), Vector4::new(10.0, 0.0, -2.0, 1.0));
+        // (0,1,0) is on the rotation axis: only scale and translation apply
+        assert_vec4(transform_point(&m, 0.0, 1.0, 0.0), Vector4::new(10.0, 2.0, 0.0, 1.0));
+        // (0,0,1) rotates onto +X
+        assert_vec4(transform_point(&m, 0.0, 0.0, 1.0), Vector4::new(12.0, 0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn model_rotation_applies_z_then_y_then_x() {
+        let m = create_model_matrix(Vector3::zero(), 1.0, Vector3::new(FRAC_PI_2, 0.0, FRAC_PI_2));
+        // Rz takes +Y to -X, then Rx leaves it there
+        assert_vec4(transform_point(&m, 0.0, 1.0, 0.0), Vector4::new(-1.0, 0.0, 0.0, 1.0));
+
+        let m = create_model_matrix(Vector3::zero(), 1.0, Vector3::new(0.0, FRAC_PI_2, FRAC_PI_2));
+        // Rz takes +Y to -X, then Ry takes -X to +Z
+        assert_vec4(transform_point(&m, 0.0, 1.0, 0.0), Vector4::new(0.0, 0.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn projection_maps_near_and_far_planes() {
+        let (near, far) = (0.1, 100.0);
+        let m = create_projection_matrix(PI / 3.0, 4.0 / 3.0, near, far);
+
+        let clip = transform_point(&m, 0.0, 0.0, -near);
+        assert!((clip.z / clip.w + 1.0).abs() < EPS);
+        assert!((clip.w - near).abs() < EPS);
+
+        let clip = transform_point(&m, 0.0, 0.0, -far);
+        assert!((clip.z / clip.w - 1.0).abs() < EPS);
+        assert!((clip.w - far).abs() < 1e-2);
+    }
+
+    #[test]
+    fn projection_maps_fov_edge_to_ndc_edge() {
+        let fov_y = PI / 3.0;
+        let aspect = 2.0;
+        let m = create_projection_matrix(fov_y, aspect, 0.1, 100.0);
+        let z = -5.0;
+        let half_height = (fov_y / 2.0).tan() * -z;
+
+        let clip = transform_point(&m, 0.0, half_height, z);
+        assert!((clip.y / clip.w - 1.0).abs() < EPS);
+
+        let clip = transform_point(&m, half_height * aspect, 0.0, z);
+        assert!((clip.x / clip.w - 1.0).abs() < EPS);
+    }
+
+    #[test]
+    fn frustum_maps_window_edges_to_ndc_edges() {
+        let (left, right, bottom, top, near) = (-0.1, 0.3, 0.05, 0.2, 0.1);
+        let m = create_frustum_matrix(left, right, bottom, top, near, 100.0);
+        let z = -4.0;
+        let scale = -z / near;
+
+        let clip = transform_point(&m, left * scale, bottom * scale, z);
+        assert!((clip.x / clip.w + 1.0).abs() < EPS && (clip.y / clip.w + 1.0).abs() < EPS);
+        let clip = transform_point(&m, right * scale, top * scale, z);
+        assert!((clip.x / clip.w - 1.0).abs() < EPS && (clip.y / clip.w - 1.0).abs() < EPS);
+
+        let ray = view_ray(&m, 1.0, 1.0);
+        assert!((ray.x - right / near).abs() < EPS && (ray.y - top / near).abs() < EPS);
+    }
+
+    #[test]
+    fn viewport_maps_ndc_corners_to_screen() {
+        let m = create_viewport_matrix(0.0, 0.0, 800.0, 600.0);
+        assert_vec4(transform_point(&m, -1.0, -1.0, 0.5), Vector4::new(0.0, 600.0, 0.5, 1.0));
+        assert_vec4(transform_point(&m, 1.0, 1.0, 0.5), Vector4::new(800.0, 0.0, 0.5, 1.0));
+        assert_vec4(transform_point(&m, 0.0, 0.0, 0.0), Vector4::new(400.0, 300.0, 0.0, 1.0));
+
+        let offset = create_viewport_matrix(10.0, 20.0, 100.0, 50.0);
+        assert_vec4(transform_point(&offset, -1.0, 1.0, 0.0), Vector4::new(10.0, 20.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn view_matrix_moves_eye_to_origin_looking_down_negative_z() {
+        let eye = Vector3::new(0.0, 0.0, 10.0);
+        let m = create_view_matrix(eye, Vector3::zero(), Vector3::new(0.0, 1.0, 0.0));
+        assert_vec4(transform_point(&m, 0.0, 0.0, 10.0), Vector4::new(0.0, 0.0, 0.0, 1.0));
+        assert_vec4(transform_point(&m, 0.0, 0.0, 0.0), Vector4::new(0.0, 0.0, -10.0, 1.0));
+        assert_vec4(transform_point(&m, 1.0, 2.0, 10.0), Vector4::new(1.0, 2.0, 0.0, 1.0));
+    }
+}
@@ -0,0 +1,185 @@
+#![allow(dead_code)]
+
+use raylib::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::framebuffer::Framebuffer;
+use crate::lighttime::format_light_time;
+use crate::line::polyline;
+use crate::matrix::view_ray;
+use crate::scene::SceneObject;
+use crate::stereo::Stereo;
+use crate::ui::draw_world_label;
+use crate::Uniforms;
+use crate::input::InputState;
+
+/// Key that turns measurement mode on and off.
+pub const MEASURE_KEY: KeyboardKey = KeyboardKey::KEY_M;
+
+/// Unit settings for the measurement tool, read from the `[measure]` table of the scene file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MeasureConfig {
+    pub au: Option<f32>, // Scene units per astronomical unit; also shows distances in AU when set
+    pub light_seconds: f32, // Simulated seconds light takes to cross an AU (499 in real time)
+}
+
+impl Default for MeasureConfig {
+    fn default() -> Self {
+        // A real minute of light travel to the simulated second
+        MeasureConfig { au: None, light_seconds: 499.0 / 60.0 }
+    }
+}
+
+/// One end of a measurement: a body (followed as it moves) or a fixed point on the ecliptic.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Anchor {
+    Body(String),
+    Point(Vector3),
+}
+
+impl Anchor {
+    pub fn position(&self, objects: &[SceneObject]) -> Option<Vector3> {
+        match self {
+            Anchor::Body(name) => objects.iter().find(|o| &o.name == name).map(|o| o.translation),
+            Anchor::Point(p) => Some(*p),
+        }
+    }
+}
+
+pub struct Measurement {
+    pub from: Anchor,
+    pub to: Anchor,
+}
+
+/// Click two bodies (or a body and a spot on the ecliptic plane) to measure the distance
+/// between them; the line and label follow the bodies along their orbits.
+pub struct MeasureTool {
+    pub active: bool,
+    pub measurements: Vec<Measurement>,
+    pending: Option<Anchor>, // First end of the measurement being placed
+    click: Option<Vector2>,  // Window position of a click not resolved yet
+}
+
+impl MeasureTool {
+    pub fn new() -> Self {
+        MeasureTool {
+            active: false,
+            measurements: Vec::new(),
+            pending: None,
+            click: None,
+        }
+    }
+
+    /// M toggles the mode; in it, left click places ends and Backspace removes the last measurement.
+    pub fn process_input(&mut self, input: &InputState) {
+        if input.is_key_pressed(MEASURE_KEY) {
+            self.active = !self.active;
+            self.pending = None;
+        }
+        if !self.active {
+            return;
+        }
+        if input.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
+            self.click = Some(input.get_mouse_position());
+        }
+        if input.is_key_pressed(KeyboardKey::KEY_BACKSPACE) && self.pending.take().is_none() {
+            self.measurements.pop();
+        }
+    }
+
+    /// Turns a pending click into an anchor, using this frame's object-ID buffer to find
+    /// the body under the cursor. Empty space resolves onto the ecliptic plane (y = 0).
+    /// With stereo on it runs for each eye, and only the eye drawn where the click landed
+    /// takes it.
+    pub fn resolve_click(&mut self, framebuffer: &Framebuffer, objects: &[SceneObject], uniforms: &Uniforms, eye: Vector3, stereo: &Stereo, eye_index: usize) {
+        let Some(click) = self.click else {
+            return;
+        };
+        // Window pixels to internal render pixels, then into the clicked eye's buffer
+        let x = click.x * framebuffer.width as f32 / framebuffer.output_width as f32;
+        let y = click.y * framebuffer.height as f32 / framebuffer.output_height as f32;
+        let (clicked_eye, x) = stereo.pick(x, framebuffer.width);
+        if clicked_eye != eye_index {
+            return;
+        }
+        self.click = None;
+
+        let id = framebuffer.object_id_at(x as i32, y as i32);
+        let anchor = match objects.get((id as usize).wrapping_sub(1)) {
+            Some(obj) if id > 0 => Anchor::Body(obj.name.clone()),
+            _ => match ecliptic_point(x, y, uniforms, eye) {
+                Some(point) => Anchor::Point(point),
+                None => return,
+            },
+        };
+
+        match self.pending.take() {
+            None => self.pending = Some(anchor),
+            Some(from) => self.measurements.push(Measurement { from, to: anchor }),
+        }
+    }
+
+    /// Draws every measurement line into the 3D scene.
+    pub fn draw(&self, framebuffer: &mut Framebuffer, objects: &[SceneObject], uniforms: &Uniforms) {
+        let color = Vector3::new(1.0, 0.9, 0.2);
+        for m in &self.measurements {
+            if let (Some(a), Some(b)) = (m.from.position(objects), m.to.position(objects)) {
+                polyline(framebuffer, uniforms, &[a, b], color);
+            }
+        }
+    }
+
+    /// Distance labels at the midpoints, hidden behind nearer bodies.
+    pub fn draw_labels(&self, framebuffer: &mut Framebuffer, objects: &[SceneObject], uniforms: &Uniforms, config: &MeasureConfig) {
+        for m in &self.measurements {
+            let (Some(a), Some(b)) = (m.from.position(objects), m.to.position(objects)) else {
+                continue;
+            };
+            let distance = (b - a).length();
+            let text = match format_light_time(distance, config) {
+                Some(light) => format!("{}  {}", format_distance(distance, config), light),
+                None => format_distance(distance, config),
+            };
+            draw_world_label(framebuffer, (a + b) * 0.5, &text, uniforms, Vector3::new(1.0, 0.95, 0.2));
+        }
+    }
+
+    /// Status line for the overlay while the mode is on.
+    pub fn status(&self) -> Option<String> {
+        if !self.active {
+            return None;
+        }
+        Some(match &self.pending {
+            None => "Measure: click a body or a point on the ecliptic (Backspace removes the last)".to_string(),
+            Some(Anchor::Body(name)) => format!("Measure: from {}, click the other end", name),
+            Some(Anchor::Point(_)) => "Measure: from a point, click the other end".to_string(),
+        })
+    }
+}
+
+pub fn format_distance(distance: f32, config: &MeasureConfig) -> String {
+    match config.au {
+        Some(au) if au > 0.0 => format!("{:.2} u ({:.3} AU)", distance, distance / au),
+        _ => format!("{:.2} u", distance),
+    }
+}
+
+/// Where the view ray through internal pixel (x, y) meets the plane y = 0.
+fn ecliptic_point(x: f32, y: f32, uniforms: &Uniforms, eye: Vector3) -> Option<Vector3> {
+    let p = &uniforms.frame.projection;
+    let v = &uniforms.frame.view;
+    let vp = &uniforms.frame.viewport;
+    let ndc_x = (x + 0.5 - vp.m12) / vp.m0;
+    let ndc_y = (y + 0.5 - vp.m13) / vp.m5;
+    let view_dir = view_ray(p, ndc_x, ndc_y);
+    let dir = Vector3::new(v.m0, v.m4, v.m8) * view_dir.x
+        + Vector3::new(v.m1, v.m5, v.m9) * view_dir.y
+        + Vector3::new(v.m2, v.m6, v.m10) * view_dir.z;
+
+    if dir.y.abs() < 1e-6 {
+        return None;
+    }
+    let t = -eye.y / dir.y;
+    (t > 0.0).then(|| eye + dir * t)
+}
@@ -0,0 +1,178 @@
+#![allow(dead_code)]
+
+use raylib::prelude::*;
+
+use crate::framebuffer::Framebuffer;
+use crate::input::InputState;
+use crate::line::line;
+use crate::scene::SceneObject;
+use crate::vertex::Vertex;
+
+/// Key that shows/hides the minimap.
+pub const MINIMAP_KEY: KeyboardKey = KeyboardKey::KEY_N;
+
+/// Schematic color for a body kind on the minimap.
+pub fn kind_color(kind: &str) -> Vector3 {
+    match kind {
+        "sun" => Vector3::new(1.0, 0.85, 0.3),
+        "rocky1" => Vector3::new(0.9, 0.65, 0.4),
+        "rocky2" => Vector3::new(1.0, 0.35, 0.1),
+        "gassy1" => Vector3::new(0.85, 0.5, 0.9),
+        "gassy2" => Vector3::new(0.7, 0.6, 0.95),
+        "gassy3" => Vector3::new(0.5, 0.85, 0.95),
+        "earth" => Vector3::new(0.25, 0.5, 1.0),
+        "moon" => Vector3::new(0.75, 0.75, 0.75),
+        "ring" => Vector3::new(0.6, 0.6, 0.65),
+        "rocky" => Vector3::new(0.7, 0.55, 0.4),
+        "gas" => Vector3::new(0.9, 0.75, 0.55),
+        "ice" => Vector3::new(0.75, 0.9, 1.0),
+        "lava" => Vector3::new(1.0, 0.3, 0.05),
+        _ => Vector3::new(1.0, 1.0, 1.0),
+    }
+}
+
+/// Top-down (looking down -Y) schematic of the system in a corner of the framebuffer.
+pub struct Minimap {
+    pub visible: bool,
+    pub size: i32,   // Width and height in pixels
+    pub margin: i32, // Distance from the screen edges
+}
+
+impl Minimap {
+    pub fn new() -> Self {
+        Minimap { visible: true, size: 160, margin: 10 }
+    }
+
+    pub fn process_input(&mut self, input: &InputState) {
+        if input.is_key_pressed(MINIMAP_KEY) {
+            self.visible = !self.visible;
+        }
+    }
+
+    /// Draws the minimap into the top-right corner. `viewer` is the camera (or shuttle)
+    /// position and `heading` its forward direction; `target` is highlighted.
+    pub fn draw(
+        &self,
+        framebuffer: &mut Framebuffer,
+        objects: &[SceneObject],
+        viewer: Vector3,
+        heading: Vector3,
+        target: Vector3,
+    ) {
+        if !self.visible {
+            return;
+        }
+
+        let left = framebuffer.width as i32 - self.size - self.margin;
+        let top = self.margin;
+        let half = self.size as f32 / 2.0;
+
+        // Fit everything (bodies, viewer and target) around the sun at the center
+        let extent = objects
+            .iter()
+            .filter(|o| o.object_type != "shuttle")
+            .map(|o| planar_distance(o.translation))
+            .fold(planar_distance(viewer).max(planar_distance(target)), f32::max)
+            .max(1.0)
+            * 1.1;
+        let to_map = |p: Vector3| {
+            (
+                left as f32 + half + p.x / extent * (half - 4.0),
+                top as f32 + half + p.z / extent * (half - 4.0),
+            )
+        };
+
+        // Background and border
+        let background = Vector3::new(0.02, 0.02, 0.06);
+        let border = Vector3::new(0.5, 0.5, 0.6);
+        for y in top..top + self.size {
+            for x in left..left + self.size {
+                let edge = x == left || y == top || x == left + self.size - 1 || y == top + self.size - 1;
+                framebuffer.set_pixel(x, y, if edge { border } else { background });
+            }
+        }
+
+        // Markers are clipped inside the border, so none spill over the 3D view
+        let area = MapArea { left: left + 1, top: top + 1, right: left + self.size - 1, bottom: top + self.size - 1 };
+
+        // Camera target
+        let (tx, ty) = to_map(target);
+        draw_ring(framebuffer, &area, tx, ty, 5.0, Vector3::new(1.0, 1.0, 1.0));
+
+        for obj in objects {
+            if obj.object_type == "shuttle" || obj.object_type == "ring" {
+                continue;
+            }
+            let (x, y) = to_map(obj.translation);
+            let radius = if obj.object_type == "sun" { 3.5 } else { 2.0 };
+            fill_disc(framebuffer, &area, x, y, radius, kind_color(&obj.object_type));
+        }
+
+        // Viewer as an arrow pointing along the heading
+        let (vx, vy) = to_map(viewer);
+        let mut dir = (heading.x, heading.z);
+        let len = (dir.0 * dir.0 + dir.1 * dir.1).sqrt();
+        dir = if len > 1e-4 { (dir.0 / len, dir.1 / len) } else { (0.0, -1.0) };
+        let tip = (vx + dir.0 * 7.0, vy + dir.1 * 7.0);
+        let back_left = (vx - dir.0 * 4.0 - dir.1 * 4.0, vy - dir.1 * 4.0 + dir.0 * 4.0);
+        let back_right = (vx - dir.0 * 4.0 + dir.1 * 4.0, vy - dir.1 * 4.0 - dir.0 * 4.0);
+        let arrow = Vector3::new(0.3, 1.0, 0.4);
+        draw_line(framebuffer, &area, tip, back_left, arrow);
+        draw_line(framebuffer, &area, back_left, back_right, arrow);
+        draw_line(framebuffer, &area, back_right, tip, arrow);
+    }
+}
+
+/// Pixels of the framebuffer the markers may cover: `left..right` by `top..bottom`.
+struct MapArea {
+    left: i32,
+    top: i32,
+    right: i32,
+    bottom: i32,
+}
+
+impl MapArea {
+    fn plot(&self, framebuffer: &mut Framebuffer, x: i32, y: i32, color: Vector3) {
+        if (self.left..self.right).contains(&x) && (self.top..self.bottom).contains(&y) {
+            framebuffer.set_pixel(x, y, color);
+        }
+    }
+}
+
+fn planar_distance(p: Vector3) -> f32 {
+    (p.x * p.x + p.z * p.z).sqrt()
+}
+
+fn fill_disc(framebuffer: &mut Framebuffer, area: &MapArea, cx: f32, cy: f32, radius: f32, color: Vector3) {
+    let r = radius.ceil() as i32;
+    for dy in -r..=r {
+        for dx in -r..=r {
+            if (dx * dx + dy * dy) as f32 <= radius * radius {
+                area.plot(framebuffer, cx as i32 + dx, cy as i32 + dy, color);
+            }
+        }
+    }
+}
+
+fn draw_ring(framebuffer: &mut Framebuffer, area: &MapArea, cx: f32, cy: f32, radius: f32, color: Vector3) {
+    let steps = 24;
+    for i in 0..steps {
+        let a0 = i as f32 / steps as f32 * std::f32::consts::TAU;
+        let a1 = (i + 1) as f32 / steps as f32 * std::f32::consts::TAU;
+        draw_line(
+            framebuffer,
+            area,
+            (cx + radius * a0.cos(), cy + radius * a0.sin()),
+            (cx + radius * a1.cos(), cy + radius * a1.sin()),
+            color,
+        );
+    }
+}
+
+fn draw_line(framebuffer: &mut Framebuffer, area: &MapArea, a: (f32, f32), b: (f32, f32), color: Vector3) {
+    let a = Vertex { transformed_position: Vector3::new(a.0, a.1, 0.0), ..Vertex::default() };
+    let b = Vertex { transformed_position: Vector3::new(b.0, b.1, 0.0), ..Vertex::default() };
+    for fragment in line(&a, &b) {
+        area.plot(framebuffer, fragment.position.x as i32, fragment.position.y as i32, color);
+    }
+}
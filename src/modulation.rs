@@ -0,0 +1,66 @@
+#![allow(dead_code)]
+
+use std::fs;
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+
+/// Value of the modulation input when nothing drives it.
+pub const NEUTRAL: f32 = 0.5;
+
+/// External modulation input, read from the `[modulation]` table of the scene file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModulationConfig {
+    pub enabled: bool,
+    pub path: String,     // File holding a single number from 0 to 1, rewritten by another process
+    pub decay: f32,       // Seconds to ease most of the way back to neutral once the input is lost
+    pub stale_after: f32, // Seconds without a rewrite after which the file counts as lost
+    pub sun: f32,         // How far the sun's brightness follows the input: 0 not at all, 1 from 0x to 2x
+}
+
+impl Default for ModulationConfig {
+    fn default() -> Self {
+        ModulationConfig { enabled: false, path: "modulation.txt".to_string(), decay: 1.0, stale_after: 2.0, sun: 0.6 }
+    }
+}
+
+/// A 0..1 value fed from outside the app, e.g. the amplitude from an audio analyzer,
+/// polled from a file every frame. A missing, stale or unreadable file doesn't snap the
+/// value back: it eases towards `NEUTRAL`, where nothing it drives is changed.
+pub struct ModulationSource {
+    pub config: ModulationConfig,
+    pub value: f32,
+}
+
+impl ModulationSource {
+    pub fn new(config: ModulationConfig) -> Self {
+        ModulationSource { config, value: NEUTRAL }
+    }
+
+    /// The latest value in the file, or None if it can't be used.
+    fn read(&self) -> Option<f32> {
+        let metadata = fs::metadata(&self.config.path).ok()?;
+        let age = metadata.modified().ok().and_then(|t| SystemTime::now().duration_since(t).ok());
+        if age.is_some_and(|age| age.as_secs_f32() > self.config.stale_after) {
+            return None;
+        }
+        let value: f32 = fs::read_to_string(&self.config.path).ok()?.trim().parse().ok()?;
+        value.is_finite().then(|| value.clamp(0.0, 1.0))
+    }
+
+    /// Takes the file's value as it is, so the effects stay in sync with the source, or
+    /// decays towards neutral over `dt` seconds without one.
+    pub fn poll(&mut self, dt: f32) {
+        let latest = if self.config.enabled { self.read() } else { None };
+        self.value = match latest {
+            Some(value) => value,
+            None => NEUTRAL + (self.value - NEUTRAL) * (-dt / self.config.decay.max(1e-3)).exp(),
+        };
+    }
+
+    /// Brightness multiplier for the sun: 1 at neutral, from `1 - sun` to `1 + sun`.
+    pub fn sun_pulse(&self) -> f32 {
+        (1.0 + self.config.sun * (self.value - NEUTRAL) * 2.0).max(0.0)
+    }
+}
@@ -0,0 +1,95 @@
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+
+use raylib::prelude::*;
+
+use crate::debugview::object_id;
+use crate::framebuffer::Framebuffer;
+use crate::matrix::world_to_screen;
+use crate::scene::SceneObject;
+
+/// Longest streak, in render pixels; faster bodies are clamped to it.
+const MAX_STREAK: f32 = 24.0;
+/// Motion past this many pixels in one frame is a jump (a reload, a body moved from the
+/// console) rather than movement, and isn't blurred.
+const MAX_MOTION: f32 = 400.0;
+/// Samples along each streak.
+const TAPS: u32 = 8;
+
+/// Per-object motion blur for fast bodies, mostly at high time scales: each body is
+/// smeared along how far its center moved on screen since last frame. Only the bodies'
+/// own motion counts, measured with this frame's camera for both positions, so turning
+/// the camera blurs nothing; the warp has its own blur for that.
+pub struct ObjectMotionBlur {
+    previous: HashMap<String, Vector3>, // Each body's center last frame, by name
+}
+
+impl ObjectMotionBlur {
+    pub fn new() -> Self {
+        ObjectMotionBlur { previous: HashMap::new() }
+    }
+
+    /// Forgets where the bodies were, so the next frame doesn't blur (after a reload or a pause).
+    pub fn reset(&mut self) {
+        self.previous.clear();
+    }
+
+    /// Screen-space motion of each object since last frame, indexed by object id: the
+    /// displacement of its center, clamped to `MAX_STREAK`, or zero below a pixel. The
+    /// shuttle rides along with the camera, so it never blurs.
+    pub fn velocities(&self, objects: &[SceneObject], view: &Matrix, projection: &Matrix, viewport: &Matrix) -> Vec<Vector2> {
+        let mut velocities = vec![Vector2::zero(); objects.len() + 1];
+        for (index, obj) in objects.iter().enumerate() {
+            if obj.object_type == "shuttle" {
+                continue;
+            }
+            let Some(&before) = self.previous.get(&obj.name) else {
+                continue;
+            };
+            let (Some(now), Some(then)) = (
+                world_to_screen(obj.translation, view, projection, viewport),
+                world_to_screen(before, view, projection, viewport),
+            ) else {
+                continue;
+            };
+            velocities[object_id(index) as usize] = streak(Vector2::new(now.x - then.x, now.y - then.y));
+        }
+        velocities
+    }
+
+    /// Smears the bodies drawn into `framebuffer` with this eye's camera.
+    pub fn apply(&self, framebuffer: &mut Framebuffer, objects: &[SceneObject], view: &Matrix, projection: &Matrix, viewport: &Matrix) {
+        let velocities = self.velocities(objects, view, projection, viewport);
+        framebuffer.smear_objects(&velocities, TAPS);
+    }
+
+    /// Remembers where every body is now, once the frame is drawn.
+    pub fn end_frame(&mut self, objects: &[SceneObject]) {
+        self.previous = objects.iter().map(|o| (o.name.clone(), o.translation)).collect();
+    }
+}
+
+/// Streak for a motion of `delta` pixels: none under a pixel or past a jump, and at most
+/// `MAX_STREAK` long.
+fn streak(delta: Vector2) -> Vector2 {
+    let length = delta.length();
+    if !(1.0..=MAX_MOTION).contains(&length) {
+        return Vector2::zero();
+    }
+    delta * (length.min(MAX_STREAK) / length)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn streaks_start_at_a_pixel_and_are_clamped() {
+        assert_eq!(streak(Vector2::new(0.6, 0.0)), Vector2::zero());
+        assert_eq!(streak(Vector2::new(3.0, 4.0)), Vector2::new(3.0, 4.0));
+        let long = streak(Vector2::new(0.0, -100.0));
+        assert!((long.y + MAX_STREAK).abs() < 1e-4 && long.x == 0.0);
+        assert_eq!(streak(Vector2::new(MAX_MOTION * 2.0, 0.0)), Vector2::zero());
+    }
+}
@@ -0,0 +1,132 @@
+#![allow(dead_code)]
+
+use raylib::prelude::Vector2;
+
+/// Most octaves the fbm noise ever uses; the quality level can lower it per frame
+/// (`FrameUniforms::fbm_octaves`).
+pub const MAX_FBM_OCTAVES: u32 = 4;
+/// Projected radius, in pixels, at which a body still resolves the first fbm octave;
+/// each doubling of the radius resolves one more.
+const DETAIL_RADIUS_PX: f32 = 4.0;
+
+// Simple hash-based noise in 2D, in [0, 1)
+pub fn hash2(p: Vector2) -> f32 {
+    let n = p.x * 157.0 + p.y * 113.0;
+    (n.sin() * 43758.547).fract().abs()
+}
+
+/// Mean of the octaves an fbm skipped, `amp` being the amplitude the next one would
+/// have had: their amplitudes add up to amp * 2 - 0.5^MAX_FBM_OCTAVES, around a mean of 0.5.
+pub fn skipped_octaves_mean(amp: f32) -> f32 {
+    (amp * 2.0 - 0.5f32.powi(MAX_FBM_OCTAVES as i32)) * 0.5
+}
+
+// Very cheap fractal noise (fbm)
+pub fn fbm(uv: Vector2) -> f32 {
+    fbm_detail(uv, MAX_FBM_OCTAVES as f32)
+}
+
+/// fbm limited to `detail_budget` octaves, which may be fractional: the last octave
+/// is faded in by the fraction, so a budget changing smoothly with distance never pops.
+pub fn fbm_detail(uv: Vector2, detail_budget: f32) -> f32 {
+    let budget = detail_budget.clamp(1.0, MAX_FBM_OCTAVES as f32);
+    let mut value = 0.0;
+    let mut amp = 0.5;
+    let mut freq = 1.0;
+
+    for octave in 0..budget.ceil() as u32 {
+        let fade = (budget - octave as f32).min(1.0);
+        let p = Vector2::new(uv.x * freq, uv.y * freq);
+        value += (0.5 + (hash2(p) - 0.5) * fade) * amp;
+        freq *= 2.0;
+        amp *= 0.5;
+    }
+
+    // Skipped octaves contribute their mean so brightness doesn't change with quality
+    value + skipped_octaves_mean(amp)
+}
+
+/// Octaves of fbm a body resolves at `radius_px` projected pixels: 1 for a dot, up to
+/// all of them for a body filling a good part of the screen.
+pub fn detail_budget(radius_px: f32) -> f32 {
+    ((radius_px / DETAIL_RADIUS_PX).max(1.0).log2() + 1.0).min(MAX_FBM_OCTAVES as f32)
+}
+
+fn splitmix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// Seed for one named thing (a body, a sky face) derived from the global seed. Uses
+/// FNV-1a on the name so it's the same on every machine and build.
+pub fn sub_seed(seed: u64, name: &str) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in name.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    splitmix64(seed ^ hash)
+}
+
+/// Seeded view of the noise functions. Every call site picks its own `channel`, so
+/// layers of one body (plate jitter, craters, clouds...) don't line up with each other,
+/// and the seed moves all of them to a different part of the noise domain.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Noise {
+    seed: u64,
+    detail_budget: f32, // Octaves `fbm` may use, see `fbm_detail`
+}
+
+impl Default for Noise {
+    fn default() -> Self {
+        Noise::new(0)
+    }
+}
+
+impl Noise {
+    pub fn new(seed: u64) -> Self {
+        Noise { seed, detail_budget: MAX_FBM_OCTAVES as f32 }
+    }
+
+    /// The same noise with `fbm` limited to `detail_budget` octaves.
+    pub fn with_detail(self, detail_budget: f32) -> Self {
+        Noise { detail_budget, ..self }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Domain offset for `channel`, in [0, 256)². Kept small so `hash2` stays precise in f32.
+    pub fn offset(&self, channel: u32) -> Vector2 {
+        let bits = splitmix64(self.seed ^ (channel as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+        let unit = |b: u64| (b & 0xffff_ffff) as f32 / 4_294_967_296.0;
+        Vector2::new(unit(bits) * 256.0, unit(bits >> 32) * 256.0)
+    }
+
+    pub fn hash(&self, p: Vector2, channel: u32) -> f32 {
+        hash2(p + self.offset(channel))
+    }
+
+    pub fn fbm(&self, uv: Vector2, channel: u32) -> f32 {
+        fbm_detail(uv + self.offset(channel), self.detail_budget)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fewer_octaves_keep_the_same_mean() {
+        let samples: Vec<Vector2> = (0..4096).map(|i| Vector2::new((i % 64) as f32 * 0.37, (i / 64) as f32 * 0.53)).collect();
+        let mean = |budget: f32| samples.iter().map(|&p| fbm_detail(p, budget)).sum::<f32>() / samples.len() as f32;
+        let full = mean(MAX_FBM_OCTAVES as f32);
+        for budget in [1.0, 2.0, 2.5, 3.0] {
+            assert!((mean(budget) - full).abs() < 0.02, "{} octaves: {} vs {}", budget, mean(budget), full);
+        }
+        assert!(samples.iter().all(|&p| (0.0..1.0).contains(&hash2(p))));
+    }
+}
@@ -1,21 +1,70 @@
+use crate::error::AppError;
 use crate::vertex::Vertex;
 use raylib::math::{Vector2, Vector3};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
 use tobj;
 
+/// Normals and texture coordinates closer than this count as the same when welding.
+const ATTRIBUTE_TOLERANCE: f32 = 1e-4;
+
+/// Clean-up settings of one model, read from its `[meshes."<path>"]` table in the scene file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MeshConfig {
+    pub weld: f32,         // Positions closer than this are merged; 0 turns welding off
+    pub smooth_angle: f32, // Smooths normals across edges gentler than this many degrees, keeping sharper ones hard; 0 keeps the file's
+    pub flip_v: bool,      // Turns texture coordinates upside down (v becomes 1 - v); only maps read with `uv = "mesh"` use them
+    pub auto_flip_v: bool, // Flips them when v runs up the model, as Blender exports it; a guess, so off by default
+}
+
+impl Default for MeshConfig {
+    fn default() -> Self {
+        MeshConfig { weld: 1e-5, smooth_angle: 0.0, flip_v: false, auto_flip_v: false }
+    }
+}
+
+/// What `validate_and_weld` found and did, for the loading log.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WeldReport {
+    pub vertices_before: usize,
+    pub vertices_after: usize,
+    pub degenerates_removed: usize,
+    pub open_edges: usize,         // Used by a single triangle: holes, cracks and T-junctions
+    pub non_manifold_edges: usize, // Shared by more than two triangles
+}
+
+impl fmt::Display for WeldReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} -> {} vertices, {} degenerate triangles removed, {} open and {} non-manifold edges",
+            self.vertices_before, self.vertices_after, self.degenerates_removed, self.open_edges, self.non_manifold_edges
+        )
+    }
+}
+
 pub struct Obj {
     pub vertices: Vec<Vertex>,
     pub indices: Vec<u32>,
 }
 
 impl Obj {
-    pub fn load(path: &str) -> Result<Self, tobj::LoadError> {
-        let (models, _materials) = tobj::load_obj(path, &tobj::GPU_LOAD_OPTIONS)?;
+    pub fn load(path: &str) -> Result<Self, AppError> {
+        let text = fs::read_to_string(path).map_err(|_| AppError::AssetMissing { path: path.to_string() })?;
+        let (models, _materials) = tobj::load_obj(path, &tobj::GPU_LOAD_OPTIONS).map_err(|_| AppError::ObjParse {
+            path: path.to_string(),
+            line: first_bad_line(&text),
+        })?;
 
         let mut vertices = Vec::new();
         let mut indices = Vec::new();
 
         for model in models {
             let mesh = &model.mesh;
+            let base = vertices.len() as u32;
             let num_vertices = mesh.positions.len() / 3;
 
             for i in 0..num_vertices {
@@ -43,7 +92,15 @@ impl Obj {
 
                 vertices.push(Vertex::new(position, normal, tex_coords));
             }
-            indices.extend_from_slice(&mesh.indices);
+            // Front faces wind counter-clockwise seen from outside, like the rest of the
+            // renderer; exporters don't all agree, so models wound the other way are flipped
+            let mut model_indices = mesh.indices.clone();
+            if wound_clockwise(&vertices[base as usize..], &model_indices) {
+                for tri in model_indices.chunks_exact_mut(3) {
+                    tri.swap(1, 2);
+                }
+            }
+            indices.extend(model_indices.iter().map(|i| i + base));
         }
 
         Ok(Obj { vertices, indices })
@@ -56,4 +113,355 @@ impl Obj {
         }
         vertex_array
     }
+
+    /// Turns the texture coordinates upside down. OBJ files (Blender's among them) put
+    /// v = 0 at the bottom of the image; the texture sampler reads v = 0 as the top row.
+    pub fn flip_v(&mut self) {
+        for vertex in &mut self.vertices {
+            vertex.tex_coords.y = 1.0 - vertex.tex_coords.y;
+        }
+    }
+
+    /// Guesses whether v runs up the model (+Y), i.e. the file puts v = 0 at the bottom
+    /// and needs `flip_v` to show textures the right way up: the correlation between v and
+    /// height, over the vertices. None when the two are barely related (or there are no
+    /// texture coordinates), where there's nothing to go by.
+    pub fn v_runs_up(&self) -> Option<bool> {
+        let n = self.vertices.len() as f32;
+        let mean = |f: &dyn Fn(&Vertex) -> f32| self.vertices.iter().map(f).sum::<f32>() / n.max(1.0);
+        let (mean_v, mean_y) = (mean(&|v| v.tex_coords.y), mean(&|v| v.position.y));
+        let (mut cov, mut var_v, mut var_y) = (0.0, 0.0, 0.0);
+        for vertex in &self.vertices {
+            let (dv, dy) = (vertex.tex_coords.y - mean_v, vertex.position.y - mean_y);
+            (cov, var_v, var_y) = (cov + dv * dy, var_v + dv * dv, var_y + dy * dy);
+        }
+        let correlation = cov / (var_v * var_y).sqrt().max(1e-12);
+        (correlation.abs() >= 0.5).then_some(correlation > 0.0)
+    }
+
+    /// Merges vertices whose positions are within `epsilon` (and whose normals and texture
+    /// coordinates match), drops triangles left with no area and rebuilds the index buffer.
+    /// With `epsilon` at 0 nothing is merged, but the mesh is still checked.
+    pub fn validate_and_weld(&mut self, epsilon: f32) -> WeldReport {
+        let mut report = WeldReport { vertices_before: self.vertices.len(), ..WeldReport::default() };
+        let positions = position_ids(&self.vertices, epsilon);
+
+        // Vertices at one position that also agree on their other attributes become one
+        let mut remap: Vec<u32> = (0..self.vertices.len() as u32).collect();
+        if epsilon > 0.0 {
+            let mut at_position: HashMap<u32, Vec<u32>> = HashMap::new();
+            for i in 0..self.vertices.len() {
+                let candidates = at_position.entry(positions[i]).or_default();
+                let v = &self.vertices[i];
+                match candidates.iter().find(|&&c| same_attributes(&self.vertices[c as usize], v)) {
+                    Some(&c) => remap[i] = c,
+                    None => candidates.push(i as u32),
+                }
+            }
+        }
+
+        let mut indices = Vec::with_capacity(self.indices.len());
+        for tri in self.indices.chunks_exact(3) {
+            let tri = [remap[tri[0] as usize], remap[tri[1] as usize], remap[tri[2] as usize]];
+            let [a, b, c] = tri.map(|i| positions[i as usize]);
+            let [pa, pb, pc] = tri.map(|i| self.vertices[positions[i as usize] as usize].position);
+            let area = (pb - pa).cross(pc - pa).length();
+            if a == b || b == c || a == c || area <= f32::EPSILON * (pb - pa).length() * (pc - pa).length() {
+                report.degenerates_removed += 1;
+            } else {
+                indices.extend(tri);
+            }
+        }
+
+        // Edges between positions, in either direction, and how many triangles share them
+        let mut edges: HashMap<(u32, u32), u32> = HashMap::new();
+        for tri in indices.chunks_exact(3) {
+            for k in 0..3 {
+                let (a, b) = (positions[tri[k] as usize], positions[tri[(k + 1) % 3] as usize]);
+                *edges.entry((a.min(b), a.max(b))).or_default() += 1;
+            }
+        }
+        report.open_edges = edges.values().filter(|&&n| n == 1).count();
+        report.non_manifold_edges = edges.values().filter(|&&n| n > 2).count();
+
+        // Keep the vertices still in use, snapped onto the position they were merged into
+        let mut kept: Vec<Option<u32>> = vec![None; self.vertices.len()];
+        let mut vertices = Vec::new();
+        for index in indices.iter_mut() {
+            let i = *index as usize;
+            *index = *kept[i].get_or_insert_with(|| {
+                let mut v = self.vertices[i].clone();
+                v.position = self.vertices[positions[i] as usize].position;
+                vertices.push(v);
+                vertices.len() as u32 - 1
+            });
+        }
+        self.vertices = vertices;
+        self.indices = indices;
+        report.vertices_after = self.vertices.len();
+        report
+    }
+
+    /// Replaces the normals with the area-weighted average of the faces around each
+    /// position, leaving edges sharper than `crease_angle` degrees hard: faces around a
+    /// position share a normal when a path of smooth edges joins them. Best after
+    /// `validate_and_weld`, so faces on both sides of a former seam see each other.
+    pub fn smooth_normals(&mut self, crease_angle: f32) {
+        let positions = position_ids(&self.vertices, 0.0);
+        let corners: Vec<[u32; 3]> = self.indices.chunks_exact(3).map(|t| [0, 1, 2].map(|k| positions[t[k] as usize])).collect();
+        let faces: Vec<Vector3> = self
+            .indices
+            .chunks_exact(3)
+            .map(|t| {
+                let [a, b, c] = [0, 1, 2].map(|k| self.vertices[t[k] as usize].position);
+                (b - a).cross(c - a)
+            })
+            .collect();
+        let mut around: HashMap<u32, Vec<usize>> = HashMap::new();
+        for (face, tri) in corners.iter().enumerate() {
+            for &p in tri {
+                around.entry(p).or_default().push(face);
+            }
+        }
+
+        // Around each position, faces joined by smooth edges through it form groups,
+        // and every corner of a group gets the group's normal
+        let min_cos = crease_angle.to_radians().cos();
+        let mut corner_normals = vec![Vector3::zero(); self.indices.len()];
+        for (&p, fan) in &around {
+            let mut group: Vec<usize> = (0..fan.len()).collect();
+            let root = |group: &[usize], mut i: usize| {
+                while group[i] != i {
+                    i = group[i];
+                }
+                i
+            };
+            for i in 0..fan.len() {
+                for j in i + 1..fan.len() {
+                    let (f, g) = (fan[i], fan[j]);
+                    let shares_edge = corners[f].iter().any(|&q| q != p && corners[g].contains(&q));
+                    if shares_edge && faces[f].normalized().dot(faces[g].normalized()) >= min_cos {
+                        let (a, b) = (root(&group, i), root(&group, j));
+                        group[a.max(b)] = a.min(b);
+                    }
+                }
+            }
+            let mut sums = vec![Vector3::zero(); fan.len()];
+            for i in 0..fan.len() {
+                sums[root(&group, i)] += faces[fan[i]];
+            }
+            for (i, &f) in fan.iter().enumerate() {
+                let k = corners[f].iter().position(|&q| q == p).expect("the face has a corner here");
+                corner_normals[f * 3 + k] = sums[root(&group, i)].normalized();
+            }
+        }
+
+        // Corners of one vertex that end up with the same normal share it again
+        let mut split: HashMap<(u32, [u32; 3]), u32> = HashMap::new();
+        let mut vertices = Vec::new();
+        let mut indices = Vec::with_capacity(self.indices.len());
+        for (&index, normal) in self.indices.iter().zip(corner_normals) {
+            let key = (index, [normal.x, normal.y, normal.z].map(f32::to_bits));
+            indices.push(*split.entry(key).or_insert_with(|| {
+                let mut v = self.vertices[index as usize].clone();
+                v.normal = normal;
+                v.transformed_normal = normal;
+                vertices.push(v);
+                vertices.len() as u32 - 1
+            }));
+        }
+        self.vertices = vertices;
+        self.indices = indices;
+    }
+}
+
+/// For each vertex, the first vertex at its position: within `epsilon` of it, or exactly
+/// at it when `epsilon` is 0. Positions are bucketed in cells `epsilon` wide, so only
+/// the neighbouring cells need searching.
+fn position_ids(vertices: &[Vertex], epsilon: f32) -> Vec<u32> {
+    if epsilon <= 0.0 {
+        let mut first: HashMap<[u32; 3], u32> = HashMap::new();
+        return (0..vertices.len() as u32)
+            .map(|i| {
+                let p = vertices[i as usize].position;
+                *first.entry([p.x, p.y, p.z].map(f32::to_bits)).or_insert(i)
+            })
+            .collect();
+    }
+    let cell = |p: Vector3| [p.x, p.y, p.z].map(|c| (c / epsilon).floor() as i64);
+    let mut cells: HashMap<[i64; 3], Vec<u32>> = HashMap::new();
+    let mut ids = Vec::with_capacity(vertices.len());
+    for (i, v) in vertices.iter().enumerate() {
+        let [x, y, z] = cell(v.position);
+        let mut found = None;
+        'search: for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let candidates = cells.get(&[x + dx, y + dy, z + dz]).map_or(&[][..], Vec::as_slice);
+                    found = candidates.iter().copied().find(|&c| (vertices[c as usize].position - v.position).length() <= epsilon);
+                    if found.is_some() {
+                        break 'search;
+                    }
+                }
+            }
+        }
+        ids.push(found.unwrap_or_else(|| {
+            cells.entry([x, y, z]).or_default().push(i as u32);
+            i as u32
+        }));
+    }
+    ids
+}
+
+fn same_attributes(a: &Vertex, b: &Vertex) -> bool {
+    (a.normal - b.normal).length() <= ATTRIBUTE_TOLERANCE && (a.tex_coords - b.tex_coords).length() <= ATTRIBUTE_TOLERANCE
+}
+
+/// Whether most triangles wind clockwise against their vertex normals or, for a model
+/// without normals, enclose a negative volume.
+fn wound_clockwise(vertices: &[Vertex], indices: &[u32]) -> bool {
+    let mut votes = 0i64;
+    let mut volume = 0.0;
+    for tri in indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|k| &vertices[tri[k] as usize]);
+        let face = (b.position - a.position).cross(c.position - a.position);
+        let agreement = face.dot(a.normal + b.normal + c.normal);
+        votes += (agreement > 0.0) as i64 - (agreement < 0.0) as i64;
+        volume += a.position.dot(b.position.cross(c.position));
+    }
+    if votes != 0 { votes < 0 } else { volume < 0.0 }
+}
+
+/// 1-based number of the first `v`/`vn`/`vt`/`f` line with a value that doesn't parse.
+fn first_bad_line(text: &str) -> Option<usize> {
+    text.lines()
+        .position(|line| {
+            let mut tokens = line.split_whitespace();
+            let keyword = tokens.next();
+            let values: Vec<&str> = tokens.collect();
+            match keyword {
+                Some("v" | "vn" | "vt") => values.is_empty() || values.iter().any(|v| v.parse::<f32>().is_err()),
+                // Faces are "i", "i/t", "i//n" or "i/t/n", with at least three corners
+                Some("f") => {
+                    values.len() < 3
+                        || values.iter().any(|v| {
+                            v.split('/')
+                                .enumerate()
+                                .any(|(k, i)| !(k > 0 && i.is_empty()) && i.parse::<i64>().is_err())
+                        })
+                }
+                _ => false,
+            }
+        })
+        .map(|i| i + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signed_volume(obj: &Obj) -> f32 {
+        obj.get_vertex_array().chunks_exact(3).map(|t| t[0].position.dot(t[1].position.cross(t[2].position))).sum()
+    }
+
+    #[test]
+    fn clockwise_models_are_flipped_to_counter_clockwise() {
+        // A tetrahedron with every face wound clockwise seen from outside
+        let path = std::env::temp_dir().join("solar_system_clockwise.obj");
+        let text = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 0 0 1\nf 1 2 3\nf 1 4 2\nf 1 3 4\nf 2 4 3\n";
+        fs::write(&path, text).unwrap();
+        let obj = Obj::load(path.to_str().unwrap()).expect("the tetrahedron loads");
+        assert!(signed_volume(&obj) > 0.0, "the tetrahedron still winds clockwise");
+    }
+
+    #[test]
+    fn shipped_models_wind_counter_clockwise() {
+        for name in ["sphere", "ring", "sun", "SpaceShuttle"] {
+            let obj = Obj::load(&format!("assets/objects/{}.obj", name)).expect("model loads");
+            assert!(!wound_clockwise(&obj.vertices, &obj.indices), "{} winds clockwise", name);
+        }
+    }
+
+    /// Edges between faces less than `crease_angle` apart whose two triangles disagree on
+    /// the normal at a shared end: the edges a shading seam would run along.
+    fn seam_edges(obj: &Obj, crease_angle: f32) -> usize {
+        let positions = position_ids(&obj.vertices, 0.0);
+        // Each edge's triangles, with their vertices at its two ends
+        type Side = (usize, u32, u32);
+        let mut sides: HashMap<(u32, u32), Vec<Side>> = HashMap::new();
+        for (t, tri) in obj.indices.chunks_exact(3).enumerate() {
+            for k in 0..3 {
+                let (a, b) = (tri[k], tri[(k + 1) % 3]);
+                let (pa, pb) = (positions[a as usize], positions[b as usize]);
+                let side = if pa < pb { (t, a, b) } else { (t, b, a) };
+                sides.entry((pa.min(pb), pa.max(pb))).or_default().push(side);
+            }
+        }
+        let face = |t: usize| {
+            let [a, b, c] = [0, 1, 2].map(|k| obj.vertices[obj.indices[t * 3 + k] as usize].position);
+            (b - a).cross(c - a).normalized()
+        };
+        let normal = |i: u32| obj.vertices[i as usize].normal;
+        sides
+            .values()
+            .filter(|s| s.len() == 2)
+            .filter(|s| face(s[0].0).dot(face(s[1].0)) >= crease_angle.to_radians().cos())
+            .filter(|s| normal(s[0].1).dot(normal(s[1].1)) < 0.999 || normal(s[0].2).dot(normal(s[1].2)) < 0.999)
+            .count()
+    }
+
+    #[test]
+    fn blender_sphere_is_flipped_right_way_up() {
+        // Exported from Blender: v = 0 at the south pole
+        let mut obj = Obj::load("assets/objects/sphere.obj").expect("model loads");
+        assert_eq!(obj.v_runs_up(), Some(true));
+        obj.flip_v();
+        assert_eq!(obj.v_runs_up(), Some(false));
+        let north = obj.vertices.iter().max_by(|a, b| a.position.y.total_cmp(&b.position.y)).unwrap();
+        assert!(north.tex_coords.y < 0.1, "the north pole reads the top of the texture: {:?}", north.tex_coords);
+
+        let flat = Obj { vertices: vec![Vertex::new(Vector3::new(0.0, 1.0, 0.0), Vector3::zero(), Vector2::zero()); 3], indices: vec![0, 1, 2] };
+        assert_eq!(flat.v_runs_up(), None, "no texture coordinates to go by");
+    }
+
+    #[test]
+    fn welding_merges_duplicates_and_drops_degenerates() {
+        // A unit quad whose two triangles don't share vertices, one corner 1e-6 off,
+        // plus a triangle with two corners at the same spot
+        let up = Vector3::new(0.0, 0.0, 1.0);
+        let corner = |x: f32, y: f32| Vertex::new(Vector3::new(x, y, 0.0), up, Vector2::zero());
+        let dirty = || Obj {
+            vertices: vec![
+                corner(0.0, 0.0),
+                corner(1.0, 0.0),
+                corner(1.0, 1.0),
+                corner(0.0, 0.0),
+                corner(1.000001, 1.0),
+                corner(0.0, 1.0),
+                corner(1.0, 0.0),
+            ],
+            indices: vec![0, 1, 2, 3, 4, 5, 0, 1, 6],
+        };
+
+        let mut obj = dirty();
+        let report = obj.validate_and_weld(1e-5);
+        let expected = WeldReport { vertices_before: 7, vertices_after: 4, degenerates_removed: 1, open_edges: 4, non_manifold_edges: 0 };
+        assert_eq!(report, expected);
+        assert_eq!(obj.indices.len(), 6);
+
+        // Off, the stray corner stays apart and splits the diagonal
+        let mut obj = dirty();
+        let report = obj.validate_and_weld(0.0);
+        assert_eq!((report.vertices_after, report.degenerates_removed, report.open_edges), (6, 1, 6));
+    }
+
+    #[test]
+    fn welded_shuttle_has_no_shading_seams() {
+        let mut obj = Obj::load("assets/objects/SpaceShuttle.obj").expect("model loads");
+        assert!(seam_edges(&obj, 30.0) > 0);
+        let report = obj.validate_and_weld(MeshConfig::default().weld);
+        assert!(report.vertices_after < report.vertices_before && report.degenerates_removed > 0, "{}", report);
+        obj.smooth_normals(30.0);
+        assert_eq!(seam_edges(&obj, 30.0), 0);
+    }
 }
@@ -0,0 +1,92 @@
+#![allow(dead_code)]
+
+use std::f32::consts::PI;
+
+use raylib::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Keplerian orbit around the body's parent (or the origin). With no inclination
+/// it lies in the XZ plane (the ecliptic).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Orbit {
+    pub semi_major_axis: f32,
+    #[serde(default)]
+    pub eccentricity: f32,
+    pub period: f32, // Seconds of simulation time per revolution; negative runs it backwards
+    #[serde(default)]
+    pub phase: f32, // Mean anomaly at t = 0, in radians
+    #[serde(default)]
+    pub inclination: f32, // Tilt of the orbital plane from the ecliptic, in radians
+    #[serde(default)]
+    pub ascending_node: f32, // Longitude of the ascending node, from +X, in radians
+    #[serde(default)]
+    pub retrograde: bool, // Runs clockwise seen from +Y (same as a negative period)
+    #[serde(default)]
+    pub mass_ratio: f32, // Body mass / parent mass; above 0 both circle their barycenter
+}
+
+impl Orbit {
+    /// Advances a mean anomaly by `dt` seconds, wrapped to [0, 2pi).
+    pub fn advance(&self, mean_anomaly: f32, dt: f32) -> f32 {
+        if self.period.abs() < 1e-6 {
+            return mean_anomaly;
+        }
+        let direction = if self.retrograde { -1.0 } else { 1.0 };
+        (mean_anomaly + direction * 2.0 * PI * dt / self.period).rem_euclid(2.0 * PI)
+    }
+
+    /// Position relative to the focus for a given mean anomaly.
+    pub fn position(&self, mean_anomaly: f32) -> Vector3 {
+        let e = self.eccentricity.clamp(0.0, 0.99);
+        let a = self.semi_major_axis;
+        let b = a * (1.0 - e * e).sqrt();
+        let ecc_anomaly = solve_kepler(mean_anomaly, e);
+
+        // Counter-clockwise seen from +Y, starting on the +X axis at perihelion
+        self.orient(Vector3::new(
+            a * (ecc_anomaly.cos() - e),
+            0.0,
+            -b * ecc_anomaly.sin(),
+        ))
+    }
+
+    /// Rotates a point of the flat (XZ) orbit into the orbit's plane: tilted by the
+    /// inclination around the X axis, then turned by the ascending node around Y,
+    /// so the line of nodes ends up at `ascending_node` from +X.
+    pub fn orient(&self, p: Vector3) -> Vector3 {
+        let (si, ci) = self.inclination.sin_cos();
+        let tilted = Vector3::new(p.x, p.y * ci - p.z * si, p.y * si + p.z * ci);
+
+        let (sn, cn) = self.ascending_node.sin_cos();
+        Vector3::new(
+            tilted.x * cn + tilted.z * sn,
+            tilted.y,
+            -tilted.x * sn + tilted.z * cn,
+        )
+    }
+
+    /// Shares of the separation covered by the body and by its parent around their
+    /// barycenter (they add up to 1; the parent's is 0 for a massless body).
+    pub fn barycentric_shares(&self) -> (f32, f32) {
+        let mu = self.mass_ratio.max(0.0);
+        (1.0 / (1.0 + mu), mu / (1.0 + mu))
+    }
+
+    /// Unit normal of the orbital plane, on the side the body circles counter-clockwise around.
+    pub fn normal(&self) -> Vector3 {
+        let n = self.orient(Vector3::new(0.0, 1.0, 0.0));
+        let backwards = self.retrograde != (self.period < 0.0);
+        if backwards { -n } else { n }
+    }
+}
+
+/// Solves Kepler's equation M = E - e sin(E) for the eccentric anomaly E.
+pub fn solve_kepler(mean_anomaly: f32, eccentricity: f32) -> f32 {
+    let mut e_anomaly = if eccentricity > 0.8 { PI } else { mean_anomaly };
+    for _ in 0..8 {
+        let f = e_anomaly - eccentricity * e_anomaly.sin() - mean_anomaly;
+        let f_prime = 1.0 - eccentricity * e_anomaly.cos();
+        e_anomaly -= f / f_prime;
+    }
+    e_anomaly
+}
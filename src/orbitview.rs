@@ -0,0 +1,107 @@
+#![allow(dead_code)]
+
+use std::f32::consts::PI;
+
+use raylib::prelude::*;
+
+use crate::framebuffer::Framebuffer;
+use crate::line::{polyline, polyline_additive, polyline_thick};
+use crate::minimap::kind_color;
+use crate::orbit::Orbit;
+use crate::scene::SceneObject;
+use crate::Uniforms;
+
+/// Key to hold to see every orbit's path, plane and direction of motion.
+pub const ORBIT_PLANE_KEY: KeyboardKey = KeyboardKey::KEY_O;
+
+const PATH_SEGMENTS: usize = 128;
+const GRID_RINGS: usize = 4;
+const GRID_SPOKES: usize = 12;
+
+/// Where a body's orbit is centered (its parent's barycenter, or the origin, plus its
+/// offset), and the shares of the orbit the body and its parent cover around it.
+fn orbit_center(obj: &SceneObject, orbit: &Orbit, objects: &[SceneObject]) -> (Vector3, (f32, f32)) {
+    match obj.parent.as_ref().and_then(|p| objects.iter().find(|o| &o.name == p)) {
+        Some(parent) => (parent.barycenter + obj.offset, orbit.barycentric_shares()),
+        None => (obj.offset, (1.0, 0.0)),
+    }
+}
+
+/// Draws, for every orbiting body, its path, a faint grid over its orbital plane and an
+/// arrowhead at the body pointing the way it moves. Retrograde orbits are drawn in orange,
+/// and the path of the `selected` body (index into `objects`) 2 px thick. Bodies for
+/// which `shown` is false (e.g. not discovered yet) are skipped.
+pub fn draw_orbit_planes<F: Fn(&SceneObject) -> bool>(
+    framebuffer: &mut Framebuffer,
+    uniforms: &Uniforms,
+    objects: &[SceneObject],
+    selected: Option<usize>,
+    shown: F,
+) {
+    for (index, obj) in objects.iter().enumerate() {
+        let Some(orbit) = &obj.orbit else {
+            continue;
+        };
+        if !shown(obj) {
+            continue;
+        }
+        let (center, (own_share, parent_share)) = orbit_center(obj, orbit, objects);
+        let backwards = orbit.retrograde != (orbit.period < 0.0);
+        let color = if backwards { Vector3::new(1.0, 0.55, 0.2) } else { kind_color(&obj.object_type) };
+
+        // Plane grid: concentric circles and spokes, reaching a bit past the orbit
+        let extent = orbit.semi_major_axis * 1.25;
+        let faint = color * 0.12;
+        for ring in 1..=GRID_RINGS {
+            let r = extent * ring as f32 / GRID_RINGS as f32;
+            let circle: Vec<Vector3> = (0..=PATH_SEGMENTS)
+                .map(|i| {
+                    let a = 2.0 * PI * i as f32 / PATH_SEGMENTS as f32;
+                    center + orbit.orient(Vector3::new(r * a.cos(), 0.0, r * a.sin()))
+                })
+                .collect();
+            polyline_additive(framebuffer, uniforms, &circle, faint);
+        }
+        for spoke in 0..GRID_SPOKES {
+            let a = 2.0 * PI * spoke as f32 / GRID_SPOKES as f32;
+            let tip = orbit.orient(Vector3::new(extent * a.cos(), 0.0, extent * a.sin()));
+            polyline_additive(framebuffer, uniforms, &[center, center + tip], faint);
+        }
+
+        // The path itself, and the parent's opposite path around the barycenter when it wobbles
+        let path = |share: f32| -> Vec<Vector3> {
+            (0..=PATH_SEGMENTS)
+                .map(|i| center + orbit.position(2.0 * PI * i as f32 / PATH_SEGMENTS as f32) * share)
+                .collect()
+        };
+        let thickness = if selected == Some(index) { 2.0 } else { 1.0 };
+        polyline_thick(framebuffer, uniforms, &path(own_share), color * 0.6, thickness);
+        if parent_share > 0.0 {
+            polyline_thick(framebuffer, uniforms, &path(-parent_share), color * 0.6, thickness);
+        }
+
+        draw_direction(framebuffer, uniforms, orbit, center, own_share, obj.mean_anomaly, color);
+    }
+}
+
+/// Arrowhead just ahead of the body, along its velocity, lying in the orbital plane.
+fn draw_direction(
+    framebuffer: &mut Framebuffer,
+    uniforms: &Uniforms,
+    orbit: &Orbit,
+    center: Vector3,
+    share: f32,
+    mean_anomaly: f32,
+    color: Vector3,
+) {
+    let step = if orbit.retrograde != (orbit.period < 0.0) { -0.05 } else { 0.05 };
+    let here = orbit.position(mean_anomaly) * share;
+    let ahead = orbit.position(mean_anomaly + step) * share;
+    let forward = (ahead - here).normalized();
+    let side = orbit.normal().cross(forward).normalized();
+
+    let size = orbit.semi_major_axis * share * 0.06;
+    let tip = center + here + forward * (size * 2.0);
+    let back = tip - forward * size;
+    polyline(framebuffer, uniforms, &[back + side * (size * 0.6), tip, back - side * (size * 0.6)], color);
+}
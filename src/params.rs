@@ -0,0 +1,248 @@
+#![allow(dead_code)]
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::time::{Duration, Instant, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+/// File of shader-wide parameter values, watched while running.
+pub const SHADER_PARAMS_PATH: &str = "assets/shaders_params.toml";
+
+/// Named numbers a body's bespoke shader reads in place of its built-in constants,
+/// from the body's `params` table (e.g. `params = { band_freq = 22.0 }`). Names left
+/// out keep the shader's default.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ParamSet(BTreeMap<String, f32>);
+
+impl ParamSet {
+    pub fn get(&self, name: &str, default: f32) -> f32 {
+        self.0.get(name).copied().unwrap_or(default)
+    }
+
+    pub fn set(&mut self, name: &str, value: f32) {
+        self.0.insert(name.to_string(), value);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// These values laid over `base`: names set here win, the rest come from `base`.
+    pub fn over(&self, base: &ParamSet) -> ParamSet {
+        let mut merged = base.clone();
+        merged.0.extend(self.0.iter().map(|(name, value)| (name.clone(), *value)));
+        merged
+    }
+}
+
+/// A parameter a shader reads from its `ParamSet`, with the value it uses when unset
+/// and the range the tweak panel offers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ParamSpec {
+    pub name: &'static str,
+    pub default: f32,
+    pub min: f32,
+    pub max: f32,
+}
+
+const fn spec(name: &'static str, default: f32, min: f32, max: f32) -> ParamSpec {
+    ParamSpec { name, default, min, max }
+}
+
+const GASSY1_PARAMS: [ParamSpec; 3] = [
+    spec("band_freq", 14.0, 2.0, 40.0),
+    spec("swirl", 0.2, 0.0, 0.5),       // How much the noise brightens and darkens the bands
+    spec("spot_size", 0.22, 0.0, 0.5),  // Radius of the great spot, in uv
+];
+const GASSY2_PARAMS: [ParamSpec; 2] = [
+    spec("band_freq", 10.0, 2.0, 40.0),
+    spec("band_contrast", 0.08, 0.0, 0.4),
+];
+const GASSY3_PARAMS: [ParamSpec; 2] = [
+    spec("band_freq", 12.0, 2.0, 40.0),
+    spec("red_band_width", 0.08, 0.0, 0.3), // Half width of the equatorial band, in uv
+];
+
+const ROCKY1_PARAMS: [ParamSpec; 3] = [
+    spec("plate_radius", 0.55, 0.2, 0.8),  // Radius of a plate, in plate cells
+    spec("edge_width", 0.06, 0.0, 0.2),    // Width of the dark rim around the plates
+    spec("crater_radius", 0.06, 0.0, 0.2), // Smallest crater, in crater cells; up to half again larger
+];
+const ROCKY2_PARAMS: [ParamSpec; 2] = [
+    spec("lava_threshold", 0.8, 0.3, 1.0), // Patch noise above which the crust gives way to lava
+    spec("soot", 0.6, 0.0, 1.0),           // How dark the soot around the lava gets
+];
+const MOON_PARAMS: [ParamSpec; 2] = [
+    spec("crater_threshold", 0.65, 0.0, 1.0), // Cells with noise above this hold a crater
+    spec("deep_threshold", 0.8, 0.0, 1.0),    // and above this, a deep one
+];
+const SUN_PARAMS: [ParamSpec; 9] = [
+    spec("granulation", 20.0, 2.0, 60.0), // Granules around the equator
+    spec("brightness", 1.8, 0.5, 4.0),    // How white-hot the disc is; rolls off into white rather than clipping
+    spec("limb_red", 0.5, 0.0, 1.0),      // Limb-darkening coefficient u of each channel: the edge keeps 1 - u
+    spec("limb_green", 0.62, 0.0, 1.0),
+    spec("limb_blue", 0.78, 0.0, 1.0),
+    spec("rim", 0.8, 0.0, 1.0),           // Strength of the chromosphere's rim
+    spec("rim_width", 0.06, 0.0, 0.3),    // Width of the rim, in μ; never under a pixel or so
+    spec("halo", 0.5, 0.0, 2.0),          // Glow just outside the edge
+    spec("halo_width", 0.25, 0.0, 2.0),   // How far the glow reaches, in sun radii; never under a couple of pixels
+];
+
+const EARTH_PARAMS: [ParamSpec; 5] = [
+    spec("sunset_width", 0.25, 0.0, 0.6),    // How far the sunset band reaches into the day side, in N·L
+    spec("refraction", 0.06, 0.0, 0.2),      // How far light bends past the terminator, in N·L
+    spec("sea_level", 0.5, 0.3, 0.7),        // Continent noise above which there's land
+    spec("ice_line", 0.55, 0.3, 1.0),        // |sin latitude| where the ice caps start
+    spec("cloud_threshold", 0.7, 0.4, 1.0),  // Cloud noise above which clouds form
+];
+
+/// Parameters the bespoke shader of `kind` reads, in panel order.
+pub fn shader_params(kind: &str) -> &'static [ParamSpec] {
+    match kind {
+        "gassy1" => &GASSY1_PARAMS,
+        "gassy2" => &GASSY2_PARAMS,
+        "gassy3" => &GASSY3_PARAMS,
+        "earth" => &EARTH_PARAMS,
+        "rocky1" => &ROCKY1_PARAMS,
+        "rocky2" => &ROCKY2_PARAMS,
+        "moon" => &MOON_PARAMS,
+        "sun" => &SUN_PARAMS,
+        _ => &[],
+    }
+}
+
+/// `params.get` with the default from `shader_params`, for shaders that list their parameters there.
+pub fn param(params: &ParamSet, kind: &str, name: &str) -> f32 {
+    let default = shader_params(kind).iter().find(|p| p.name == name).map_or(0.0, |p| p.default);
+    params.get(name, default)
+}
+
+/// Shader-wide parameter values from `SHADER_PARAMS_PATH`, one table per shader kind
+/// (`[rocky1]`, `[gassy2]`, ...), under each body's own `params`. Polled once a second
+/// and re-read when saved, so constants can be tuned without rebuilding.
+pub struct ShaderParamsFile {
+    pub path: String,
+    sets: BTreeMap<String, ParamSet>,
+    modified: Option<SystemTime>,
+    last_check: Instant,
+}
+
+impl ShaderParamsFile {
+    /// Reads `path`; a missing file leaves every shader on its built-in values.
+    pub fn load(path: &str) -> Self {
+        let mut file = ShaderParamsFile { path: path.to_string(), sets: BTreeMap::new(), modified: None, last_check: Instant::now() };
+        file.reload();
+        file
+    }
+
+    /// Values for the shader of `kind`.
+    pub fn get(&self, kind: &str) -> &ParamSet {
+        static EMPTY: ParamSet = ParamSet(BTreeMap::new());
+        self.sets.get(kind).unwrap_or(&EMPTY)
+    }
+
+    /// What the shader of `kind` reads for a body whose own values are `own`.
+    pub fn for_body(&self, kind: &str, own: &ParamSet) -> ParamSet {
+        own.over(self.get(kind))
+    }
+
+    /// Re-reads the file when it changed since the last poll; returns whether it did.
+    pub fn poll(&mut self) -> bool {
+        if self.last_check.elapsed() < Duration::from_secs(1) {
+            return false;
+        }
+        self.last_check = Instant::now();
+        let modified = fs::metadata(&self.path).and_then(|m| m.modified()).ok();
+        if modified.is_none() || modified == self.modified {
+            return false;
+        }
+        self.reload();
+        true
+    }
+
+    fn reload(&mut self) {
+        self.modified = fs::metadata(&self.path).and_then(|m| m.modified()).ok();
+        let Ok(text) = fs::read_to_string(&self.path) else {
+            return;
+        };
+        for warning in self.apply(&text) {
+            tracing::warn!("{}: {}", self.path, warning);
+        }
+    }
+
+    /// Takes the values in `text`. Entries that aren't numbers keep their previous value,
+    /// and names no shader reads are ignored; each is reported. A file that doesn't parse
+    /// changes nothing.
+    fn apply(&mut self, text: &str) -> Vec<String> {
+        let table: toml::Table = match toml::from_str(text) {
+            Ok(table) => table,
+            Err(error) => return vec![error.to_string()],
+        };
+        let mut warnings = Vec::new();
+        let mut sets = BTreeMap::new();
+        for (kind, entries) in &table {
+            let Some(entries) = entries.as_table() else {
+                warnings.push(format!("{} is not a table", kind));
+                continue;
+            };
+            let mut set = ParamSet::default();
+            for (name, value) in entries {
+                if !shader_params(kind).iter().any(|p| p.name == name) {
+                    warnings.push(format!("{}.{} is not a parameter of that shader", kind, name));
+                    continue;
+                }
+                let number = value.as_float().or_else(|| value.as_integer().map(|i| i as f64));
+                match number {
+                    Some(number) => set.set(name, number as f32),
+                    None => {
+                        warnings.push(format!("{}.{} = {} is not a number, keeping the previous value", kind, name, value));
+                        if let Some(&previous) = self.sets.get(kind).and_then(|s| s.0.get(name)) {
+                            set.set(name, previous);
+                        }
+                    }
+                }
+            }
+            sets.insert(kind.clone(), set);
+        }
+        self.sets = sets;
+        warnings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bad_entries_keep_previous_values() {
+        let mut file = ShaderParamsFile { path: String::new(), sets: BTreeMap::new(), modified: None, last_check: Instant::now() };
+        assert!(file.apply("[rocky1]\nplate_radius = 0.45\nedge_width = 0.1\n").is_empty());
+        assert_eq!(param(file.get("rocky1"), "rocky1", "plate_radius"), 0.45);
+
+        let warnings = file.apply("[rocky1]\nplate_radius = \"big\"\nplate_radious = 0.3\n[moon]\ncrater_threshold = 1\n");
+        assert_eq!(warnings.len(), 2, "{:?}", warnings);
+        assert_eq!(param(file.get("rocky1"), "rocky1", "plate_radius"), 0.45);
+        assert_eq!(param(file.get("rocky1"), "rocky1", "edge_width"), 0.06, "removed entries go back to the default");
+        assert_eq!(param(file.get("moon"), "moon", "crater_threshold"), 1.0);
+
+        assert_eq!(file.apply("[rocky1\n").len(), 1);
+        assert_eq!(param(file.get("moon"), "moon", "crater_threshold"), 1.0);
+
+        // The bundled file lists every parameter at its default
+        let warnings = file.apply(include_str!("../assets/shaders_params.toml"));
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        for kind in ["rocky1", "rocky2", "gassy1", "gassy2", "gassy3", "earth", "moon", "sun"] {
+            for spec in shader_params(kind) {
+                assert_eq!(file.get(kind).get(spec.name, f32::NAN), spec.default, "{}.{}", kind, spec.name);
+            }
+        }
+
+        // A body's own values win over the file's
+        let mut own = ParamSet::default();
+        own.set("plate_radius", 0.3);
+        let merged = own.over(file.get("rocky1"));
+        assert_eq!(merged.get("plate_radius", 0.0), 0.3);
+    }
+}
@@ -0,0 +1,345 @@
+#![allow(dead_code)]
+
+use std::fmt;
+
+use crate::hud::FrameStats;
+
+/// Buffers of the frame that passes read and write.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Resource {
+    Color,
+    Depth,
+    ObjectIds,
+    History,      // Earlier frames kept for the warp blur
+    Accumulation, // Still frames averaged for antialiasing
+}
+
+impl Resource {
+    pub fn name(self) -> &'static str {
+        match self {
+            Resource::Color => "color",
+            Resource::Depth => "depth",
+            Resource::ObjectIds => "object ids",
+            Resource::History => "history",
+            Resource::Accumulation => "accumulation",
+        }
+    }
+
+    /// Whether the buffer carries over from the last frame, so it can be read before
+    /// anything this frame writes it.
+    fn persists(self) -> bool {
+        matches!(self, Resource::History | Resource::Accumulation)
+    }
+}
+
+/// Everything drawn into the framebuffer, in no particular order; `PassGraph` works the
+/// order out. The HUD and the text overlays go straight to the window afterwards.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pass {
+    Clear,           // Color, depth and object ids back to empty
+    Sky,             // Skybox background
+    DeepSky,         // Galaxies and nebulae over the sky
+    Bodies,          // Every body, front to back
+    ObjectBlur,      // Fast bodies smeared along their motion
+    Picking,         // Measurement clicks resolved against the object ids
+    Dust,            // Zodiacal glow, faded against depth
+    Plume,           // Shuttle exhaust
+    Guides,          // Coast path, orbit planes, gravity grid, trails
+    Annotations,     // Gizmos, measurements, light wavefronts and labels
+    BufferView,      // Debug views of the object ids or overdraw
+    StereoLeft,      // Left eye kept for the stereo composite
+    StereoComposite, // Both eyes merged
+    WarpBlur,        // Hyperspace smear along the camera's motion
+    Accumulate,      // Still frames averaged
+    ExposureMeter,   // Auto exposure measures the image
+    Minimap,
+    Dump,            // `--dump-frame` writes the buffers out
+}
+
+/// Whether a pass runs once per eye (once with stereo off) or once per frame, after
+/// every eye is drawn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scope {
+    Eye,
+    Frame,
+}
+
+struct PassDecl {
+    pass: Pass,
+    scope: Scope,
+    reads: Vec<Resource>,
+    writes: Vec<Resource>,
+    after: Vec<Pass>,
+}
+
+/// Render passes declared with the buffers they read and write and the passes they
+/// must follow, sorted into an order once at startup. Two passes touching the same
+/// buffer (at least one writing it) must be ordered one way or the other through
+/// `after`; when they aren't, or a pass reads a buffer nothing before it writes, the
+/// graph refuses to resolve and names the passes, instead of drawing a subtly wrong
+/// image.
+pub struct PassGraph {
+    passes: Vec<PassDecl>,
+}
+
+/// The resolved order: the per-eye passes, then the per-frame ones.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PassOrder {
+    pub eye: Vec<Pass>,
+    pub frame: Vec<Pass>,
+}
+
+impl fmt::Display for PassOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = |passes: &[Pass]| passes.iter().map(|p| format!("{:?}", p)).collect::<Vec<_>>().join(" -> ");
+        write!(f, "per eye: {}; per frame: {}", names(&self.eye), names(&self.frame))
+    }
+}
+
+impl PassGraph {
+    pub fn new() -> Self {
+        PassGraph { passes: Vec::new() }
+    }
+
+    pub fn add_pass(&mut self, pass: Pass, scope: Scope, reads: &[Resource], writes: &[Resource], after: &[Pass]) {
+        self.passes.push(PassDecl { pass, scope, reads: reads.to_vec(), writes: writes.to_vec(), after: after.to_vec() });
+    }
+
+    /// Sorts the passes so each runs after the ones it names, keeping the declaration
+    /// order where nothing decides it, and checks every buffer's users are ordered.
+    pub fn resolve(&self) -> Result<PassOrder, String> {
+        let count = self.passes.len();
+        let index = |pass: Pass| self.passes.iter().position(|p| p.pass == pass);
+
+        // Edges: every pass after the ones it names, and per-frame passes after all per-eye ones
+        let mut before = vec![Vec::new(); count];
+        for (i, decl) in self.passes.iter().enumerate() {
+            if self.passes[..i].iter().any(|p| p.pass == decl.pass) {
+                return Err(format!("pass {:?} is declared twice", decl.pass));
+            }
+            for &dependency in &decl.after {
+                let j = index(dependency).ok_or_else(|| format!("pass {:?} runs after {:?}, which isn't declared", decl.pass, dependency))?;
+                if decl.scope == Scope::Eye && self.passes[j].scope == Scope::Frame {
+                    return Err(format!("pass {:?} runs once per eye but after {:?}, which runs once per frame", decl.pass, dependency));
+                }
+                before[i].push(j);
+            }
+            if decl.scope == Scope::Frame {
+                before[i].extend((0..count).filter(|&j| self.passes[j].scope == Scope::Eye));
+            }
+        }
+
+        // Kahn's algorithm, always taking the earliest declared pass that's ready
+        let mut placed = vec![false; count];
+        let mut order = Vec::with_capacity(count);
+        while order.len() < count {
+            let Some(next) = (0..count).find(|&i| !placed[i] && before[i].iter().all(|&j| placed[j])) else {
+                let stuck: Vec<String> = (0..count).filter(|&i| !placed[i]).map(|i| format!("{:?}", self.passes[i].pass)).collect();
+                return Err(format!("passes {} depend on each other in a cycle", stuck.join(", ")));
+            };
+            placed[next] = true;
+            order.push(next);
+        }
+
+        // Which passes each one is (transitively) after
+        let mut ancestors = vec![vec![false; count]; count];
+        for &i in &order {
+            for &j in &before[i] {
+                ancestors[i][j] = true;
+                let inherited = ancestors[j].clone();
+                for (k, &is_ancestor) in inherited.iter().enumerate() {
+                    ancestors[i][k] |= is_ancestor;
+                }
+            }
+        }
+        let ordered = |a: usize, b: usize| ancestors[a][b] || ancestors[b][a];
+
+        for (a, first) in self.passes.iter().enumerate() {
+            for (b, second) in self.passes.iter().enumerate().skip(a + 1) {
+                let clash = first.writes.iter().find(|r| second.writes.contains(r) || second.reads.contains(r))
+                    .or_else(|| first.reads.iter().find(|r| second.writes.contains(r)));
+                if let Some(resource) = clash
+                    && !ordered(a, b)
+                {
+                    return Err(format!(
+                        "passes {:?} and {:?} both use {} (at least one writes it) but neither is declared after the other",
+                        first.pass,
+                        second.pass,
+                        resource.name()
+                    ));
+                }
+            }
+        }
+        for (i, decl) in self.passes.iter().enumerate() {
+            for resource in decl.reads.iter().filter(|r| !r.persists()) {
+                let written = self.passes.iter().enumerate().any(|(j, p)| ancestors[i][j] && p.writes.contains(resource));
+                if !written {
+                    return Err(format!("pass {:?} reads {}, but no pass before it writes it", decl.pass, resource.name()));
+                }
+            }
+        }
+
+        let (eye, frame): (Vec<usize>, Vec<usize>) = order.into_iter().partition(|&i| self.passes[i].scope == Scope::Eye);
+        Ok(PassOrder {
+            eye: eye.into_iter().map(|i| self.passes[i].pass).collect(),
+            frame: frame.into_iter().map(|i| self.passes[i].pass).collect(),
+        })
+    }
+}
+
+/// The passes of a frame in the app, with what each touches.
+pub fn frame_passes() -> PassGraph {
+    use Pass::*;
+    use Resource::*;
+    let mut graph = PassGraph::new();
+    graph.add_pass(Clear, Scope::Eye, &[], &[Color, Depth, ObjectIds], &[]);
+    graph.add_pass(Sky, Scope::Eye, &[], &[Color], &[Clear]);
+    graph.add_pass(DeepSky, Scope::Eye, &[Color], &[Color], &[Sky]);
+    graph.add_pass(Bodies, Scope::Eye, &[Depth], &[Color, Depth, ObjectIds], &[DeepSky]);
+    graph.add_pass(ObjectBlur, Scope::Eye, &[Color, ObjectIds], &[Color], &[Bodies]);
+    // Before anything else writes object ids, so clicks land on the bodies
+    graph.add_pass(Picking, Scope::Eye, &[ObjectIds, Depth], &[], &[Bodies]);
+    graph.add_pass(Dust, Scope::Eye, &[Depth], &[Color], &[ObjectBlur]);
+    graph.add_pass(Plume, Scope::Eye, &[Depth], &[Color], &[Dust]);
+    graph.add_pass(Guides, Scope::Eye, &[Depth], &[Color, Depth, ObjectIds], &[Plume, Picking]);
+    graph.add_pass(Annotations, Scope::Eye, &[Depth], &[Color, Depth, ObjectIds], &[Guides]);
+    graph.add_pass(BufferView, Scope::Eye, &[ObjectIds], &[Color], &[Annotations]);
+    graph.add_pass(StereoLeft, Scope::Eye, &[Color], &[], &[BufferView]);
+    graph.add_pass(StereoComposite, Scope::Frame, &[Color], &[Color], &[]);
+    graph.add_pass(WarpBlur, Scope::Frame, &[Color, History], &[Color, History], &[StereoComposite]);
+    graph.add_pass(Accumulate, Scope::Frame, &[Color, Accumulation], &[Color, Accumulation], &[WarpBlur]);
+    graph.add_pass(ExposureMeter, Scope::Frame, &[Color], &[], &[Accumulate]);
+    // Over the averaged image, and left out of what the exposure meters
+    graph.add_pass(Minimap, Scope::Frame, &[], &[Color], &[ExposureMeter]);
+    graph.add_pass(Dump, Scope::Frame, &[Color, Depth], &[], &[Minimap]);
+    graph
+}
+
+/// The buffers `Pass::Clear` empties.
+pub trait ClearTarget {
+    fn clear_depth(&mut self);
+    fn clear_color(&mut self);
+    /// Seconds the color clear took the last time it ran at this size, if it has.
+    fn color_clear_cost(&self) -> Option<f32>;
+}
+
+/// Runs `Pass::Clear`: depth always, color unless the sky pass paints over every pixel
+/// anyway and the clear has already been timed once at this size. A skipped clear adds
+/// what it would have cost to `stats`; returns whether it was skipped.
+pub fn clear_frame(target: &mut impl ClearTarget, sky_covers: bool, stats: &mut FrameStats) -> bool {
+    target.clear_depth();
+    match target.color_clear_cost() {
+        Some(cost) if sky_covers => {
+            stats.clear_saved_ms += cost * 1000.0;
+            true
+        }
+        _ => {
+            target.clear_color();
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Pass::*;
+    use Resource::*;
+
+    #[test]
+    fn frame_passes_resolve_in_drawing_order() {
+        let order = frame_passes().resolve().expect("the app's passes resolve");
+        let position = |pass| order.eye.iter().position(|&p| p == pass).unwrap();
+        assert_eq!(order.eye[0], Clear);
+        assert!(position(Sky) < position(Bodies) && position(Bodies) < position(Dust));
+        assert!(position(Picking) < position(Guides));
+        assert_eq!(order.frame, vec![StereoComposite, WarpBlur, Accumulate, ExposureMeter, Minimap, Dump]);
+        assert!(order.to_string().starts_with("per eye: Clear -> Sky -> DeepSky -> Bodies"));
+    }
+
+    #[test]
+    fn misplaced_passes_are_named() {
+        // A pass the frame already has, declared a second time
+        let mut graph = frame_passes();
+        graph.add_pass(Plume, Scope::Eye, &[Depth], &[Color], &[Bodies]);
+        assert_eq!(graph.resolve().unwrap_err(), "pass Plume is declared twice");
+
+        // The frame dump put back after the averaging but not after the minimap, which it
+        // could then write out with or without
+        let mut graph = frame_passes();
+        graph.passes.retain(|p| p.pass != Dump);
+        graph.add_pass(Dump, Scope::Frame, &[Color, Depth], &[], &[Accumulate]);
+        assert_eq!(
+            graph.resolve().unwrap_err(),
+            "passes Minimap and Dump both use color (at least one writes it) but neither is declared after the other"
+        );
+
+        let mut graph = PassGraph::new();
+        graph.add_pass(Clear, Scope::Eye, &[], &[Color, Depth], &[]);
+        graph.add_pass(Bodies, Scope::Eye, &[Depth], &[Color, Depth], &[Clear]);
+        graph.add_pass(Dust, Scope::Eye, &[Depth], &[Color], &[Bodies]);
+        graph.add_pass(Plume, Scope::Eye, &[Depth], &[Color], &[Bodies]);
+        let error = graph.resolve().unwrap_err();
+        assert!(error.contains("Dust and Plume both use color"), "{}", error);
+
+        let mut graph = PassGraph::new();
+        graph.add_pass(Dust, Scope::Eye, &[Depth], &[Color], &[]);
+        assert_eq!(graph.resolve().unwrap_err(), "pass Dust reads depth, but no pass before it writes it");
+
+        let mut graph = PassGraph::new();
+        graph.add_pass(Sky, Scope::Eye, &[], &[Color], &[Clear]);
+        assert!(graph.resolve().unwrap_err().contains("isn't declared"));
+
+        let mut graph = PassGraph::new();
+        graph.add_pass(Sky, Scope::Eye, &[], &[Color], &[DeepSky]);
+        graph.add_pass(DeepSky, Scope::Eye, &[], &[Color], &[Sky]);
+        assert!(graph.resolve().unwrap_err().contains("cycle"));
+    }
+
+    #[derive(Default)]
+    struct CountingTarget {
+        depth_clears: u32,
+        color_clears: u32,
+    }
+
+    impl ClearTarget for CountingTarget {
+        fn clear_depth(&mut self) {
+            self.depth_clears += 1;
+        }
+
+        fn clear_color(&mut self) {
+            self.color_clears += 1;
+        }
+
+        fn color_clear_cost(&self) -> Option<f32> {
+            (self.color_clears > 0).then_some(0.002)
+        }
+    }
+
+    #[test]
+    fn sky_frames_skip_the_color_clear_once_timed() {
+        let sky_covers = frame_passes().resolve().unwrap().eye.contains(&Sky);
+        assert!(sky_covers);
+
+        // The first frame at a size still clears, to time it; later ones skip it
+        let mut target = CountingTarget::default();
+        let mut stats = FrameStats::default();
+        let mut skipped = 0;
+        for _ in 0..3 {
+            if clear_frame(&mut target, sky_covers, &mut stats) {
+                skipped += 1;
+            }
+        }
+        assert_eq!((target.depth_clears, target.color_clears, skipped), (3, 1, 2));
+        assert!((stats.clear_saved_ms - 4.0).abs() < 1e-3, "{}", stats.clear_saved_ms);
+
+        // Without the sky nothing else covers the background, so every frame clears
+        let mut target = CountingTarget::default();
+        let mut stats = FrameStats::default();
+        for _ in 0..3 {
+            assert!(!clear_frame(&mut target, false, &mut stats));
+        }
+        assert_eq!(target.color_clears, 3);
+        assert_eq!(stats.clear_saved_ms, 0.0);
+    }
+}
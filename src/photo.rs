@@ -0,0 +1,306 @@
+#![allow(dead_code)]
+
+use std::fs;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use raylib::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::beauty::FillLights;
+use crate::camera::Camera;
+use crate::deepsky::DeepSkyConfig;
+use crate::dust::DustConfig;
+use crate::error::AppError;
+use crate::headless::{render_scene, HeadlessCamera, RenderOptions};
+use crate::input::InputState;
+use crate::light::LightConfig;
+use crate::scene::SceneObject;
+use crate::skybox::{SkyReflection, Skybox};
+use crate::texture::Material;
+
+/// Key that enters and leaves photo mode.
+pub const PHOTO_KEY: KeyboardKey = KeyboardKey::KEY_K;
+/// In photo mode: shows and hides the rule-of-thirds grid.
+pub const GRID_KEY: KeyboardKey = KeyboardKey::KEY_J;
+/// In photo mode: renders the current view at full quality and saves it.
+pub const RENDER_KEY: KeyboardKey = KeyboardKey::KEY_ENTER;
+
+/// Photo mode settings, read from the `[photo]` table of the scene file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PhotoConfig {
+    pub scale: u32,   // Size of a photo as a multiple of the window: 4 turns 800×600 into 3200×2400
+    pub samples: u32, // Supersampling: samples×samples jittered renders are averaged per pixel
+    pub slow: f32,    // Camera speed in photo mode, as a fraction of the usual one
+    pub grid: bool,   // Whether the grid is shown when photo mode starts
+    pub dir: String,  // Folder the photos are saved in
+}
+
+impl Default for PhotoConfig {
+    fn default() -> Self {
+        PhotoConfig { scale: 4, samples: 2, slow: 0.2, grid: true, dir: "photos".to_string() }
+    }
+}
+
+/// The window's per-frame effects when a photo is asked for, so it lights and glows
+/// the same as the frame on screen.
+#[derive(Clone)]
+pub struct PhotoEffects {
+    pub fill_lights: Option<FillLights>,
+    pub sun_pulse: f32,
+    pub dust: DustConfig,
+    pub reflection: Arc<SkyReflection>,
+}
+
+/// A photo being rendered: the view as it was when it was asked for, rendered offscreen
+/// one supersampling pass per frame so the window can show how far along it is.
+pub struct PhotoJob {
+    objects: Vec<SceneObject>,
+    camera: HeadlessCamera,
+    light: LightConfig,
+    time: f32,
+    materials: Vec<Material>,
+    effects: PhotoEffects,
+    exposure: f32, // Scale applied before the colors are clamped, as on screen
+    width: u32,
+    height: u32,
+    samples: u32,
+    sum: Vec<Vector3>,
+    passes: u32, // Passes rendered so far
+    path: String,
+}
+
+impl PhotoJob {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        objects: Vec<SceneObject>,
+        camera: HeadlessCamera,
+        light: LightConfig,
+        time: f32,
+        materials: Vec<Material>,
+        effects: PhotoEffects,
+        exposure: f32,
+        window_size: (u32, u32),
+        config: &PhotoConfig,
+    ) -> Self {
+        let scale = config.scale.max(1);
+        let (width, height) = (window_size.0 * scale, window_size.1 * scale);
+        let stamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |t| t.as_secs());
+        PhotoJob {
+            objects,
+            camera,
+            light,
+            time,
+            materials,
+            effects,
+            exposure,
+            width,
+            height,
+            samples: config.samples.max(1),
+            sum: vec![Vector3::zero(); (width * height) as usize],
+            passes: 0,
+            path: format!("{}/photo_{}.png", config.dir, stamp),
+        }
+    }
+
+    fn total_passes(&self) -> u32 {
+        self.samples * self.samples
+    }
+
+    /// 0 to 1.
+    pub fn progress(&self) -> f32 {
+        self.passes as f32 / self.total_passes() as f32
+    }
+
+    /// Renders the next pass at the best quality: every noise octave, ray-cast spheres at
+    /// any size and the sky at every pixel. Returns the saved path after the last one.
+    pub fn step(&mut self, sky: &Skybox, deep_sky: &[DeepSkyConfig], seed: u64) -> Option<Result<String, AppError>> {
+        self.render_pass(sky, deep_sky, seed);
+        (self.passes == self.total_passes()).then(|| self.save())
+    }
+
+    fn render_pass(&mut self, sky: &Skybox, deep_sky: &[DeepSkyConfig], seed: u64) {
+        let n = self.samples;
+        let (i, j) = (self.passes % n, self.passes / n);
+        let offset = |k: u32| (k as f32 + 0.5) / n as f32 - 0.5;
+        let camera = HeadlessCamera { jitter: Vector2::new(offset(i), offset(j)), ..self.camera };
+        let pass = render_scene(&self.objects, &camera, &self.light, self.width, self.height, self.time, &self.options(sky, deep_sky, seed));
+        for (sum, color) in self.sum.iter_mut().zip(&pass.colors) {
+            *sum += *color;
+        }
+        self.passes += 1;
+    }
+
+    fn options<'a>(&'a self, sky: &'a Skybox, deep_sky: &'a [DeepSkyConfig], seed: u64) -> RenderOptions<'a> {
+        RenderOptions {
+            sky: Some(sky),
+            materials: &self.materials,
+            deep_sky,
+            seed,
+            fill_lights: self.effects.fill_lights,
+            sun_pulse: self.effects.sun_pulse,
+            dust: Some(&self.effects.dust),
+            reflection: Some(&self.effects.reflection),
+        }
+    }
+
+    /// The passes rendered so far, averaged.
+    fn average(&self) -> Vec<Vector3> {
+        let passes = self.passes.max(1) as f32;
+        self.sum.iter().map(|&c| c / passes).collect()
+    }
+
+    fn save(&self) -> Result<String, AppError> {
+        let error = || AppError::ImageWrite { path: self.path.clone() };
+        if let Some(dir) = std::path::Path::new(&self.path).parent() {
+            fs::create_dir_all(dir).map_err(|_| error())?;
+        }
+        let channel = |v: f32| ((v * self.exposure).clamp(0.0, 1.0) * 255.0 + 0.5) as u8;
+        let mut image = Image::gen_image_color(self.width as i32, self.height as i32, Color::BLACK);
+        for (i, c) in self.average().iter().enumerate() {
+            let (x, y) = ((i as u32 % self.width) as i32, (i as u32 / self.width) as i32);
+            image.draw_pixel(x, y, Color::new(channel(c.x), channel(c.y), channel(c.z), 255));
+        }
+        if !image.export_image(&self.path) {
+            return Err(error());
+        }
+        Ok(self.path.clone())
+    }
+}
+
+/// Photo mode: the simulation stops, the HUD and every overlay are hidden and the camera
+/// moves slowly enough to frame a shot, optionally over a rule-of-thirds grid. The
+/// window shows a preview; `RENDER_KEY` renders the same view offscreen at a multiple
+/// of its resolution and saves it as a PNG.
+pub struct PhotoMode {
+    pub active: bool,
+    pub grid: bool,
+    pub config: PhotoConfig,
+    pub job: Option<PhotoJob>,
+    saved: Option<String>,           // What happened to the last photo
+    speeds: Option<(f32, f32, f32)>, // The camera's rotation, zoom and pan speeds before photo mode
+}
+
+impl PhotoMode {
+    pub fn new(config: PhotoConfig) -> Self {
+        PhotoMode { active: false, grid: config.grid, config, job: None, saved: None, speeds: None }
+    }
+
+    /// Enters or leaves photo mode, slowing `camera` down while in it. Returns true
+    /// when a render is asked for and none is running.
+    pub fn process_input(&mut self, input: &InputState, camera: &mut Camera) -> bool {
+        if input.is_key_pressed(PHOTO_KEY) {
+            self.active = !self.active;
+            if self.active {
+                self.grid = self.config.grid;
+                self.saved = None;
+                self.speeds = Some((camera.rotation_speed, camera.zoom_speed, camera.pan_speed));
+                let slow = self.config.slow.clamp(0.01, 1.0);
+                camera.rotation_speed *= slow;
+                camera.zoom_speed *= slow;
+                camera.pan_speed *= slow;
+            } else if let Some((rotation, zoom, pan)) = self.speeds.take() {
+                (camera.rotation_speed, camera.zoom_speed, camera.pan_speed) = (rotation, zoom, pan);
+            }
+        }
+        if !self.active {
+            return false;
+        }
+        if input.is_key_pressed(GRID_KEY) {
+            self.grid = !self.grid;
+        }
+        input.is_key_pressed(RENDER_KEY) && self.job.is_none()
+    }
+
+    pub fn start(&mut self, job: PhotoJob) {
+        self.saved = None;
+        self.job = Some(job);
+    }
+
+    /// Advances the running render by one pass. Returns what to report once it's done.
+    pub fn update(&mut self, sky: &Skybox, deep_sky: &[DeepSkyConfig], seed: u64) -> Option<String> {
+        let result = self.job.as_mut()?.step(sky, deep_sky, seed)?;
+        self.job = None;
+        let message = match result {
+            Ok(path) => format!("saved photo to {}", path),
+            Err(error) => format!("error: {}", error),
+        };
+        self.saved = Some(message.clone());
+        Some(message)
+    }
+
+    /// Grid, progress bar and hints, over the preview.
+    pub fn draw(&self, d: &mut RaylibDrawHandle, width: i32, height: i32) {
+        if !self.active {
+            return;
+        }
+        if self.grid {
+            let color = Color::new(255, 255, 255, 90);
+            for k in 1..3 {
+                d.draw_line(width * k / 3, 0, width * k / 3, height, color);
+                d.draw_line(0, height * k / 3, width, height * k / 3, color);
+            }
+        }
+        if let Some(job) = &self.job {
+            let (bar_w, bar_h) = (width / 2, 16);
+            let (x, y) = ((width - bar_w) / 2, height - 60);
+            d.draw_rectangle(x, y, bar_w, bar_h, Color::new(0, 0, 0, 170));
+            d.draw_rectangle(x, y, (bar_w as f32 * job.progress()) as i32, bar_h, Color::new(242, 191, 89, 255));
+            d.draw_rectangle_lines(x, y, bar_w, bar_h, Color::LIGHTGRAY);
+            let label = format!("Rendering {}x{} ({:.0}%)", job.width, job.height, job.progress() * 100.0);
+            d.draw_text(&label, x, y + 20, 16, Color::WHITE);
+            return;
+        }
+        let hint = match &self.saved {
+            Some(message) => message.clone(),
+            None => format!("Photo mode (K): Enter renders {}x, J grid", self.config.scale.max(1)),
+        };
+        d.draw_text(&hint, 10, height - 30, 16, Color::LIGHTGRAY);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::scene::default_scene;
+    use crate::skybox::{procedural_face, StarfieldConfig};
+
+    #[test]
+    fn averaged_passes_converge_on_the_single_pass_image() {
+        let (scene, objects) = default_scene();
+        let camera = HeadlessCamera::looking_at(Vector3::new(0.0, 3.0, 12.0), Vector3::zero());
+        let face = |seed| procedural_face(16, seed, &StarfieldConfig::default());
+        let sky = Skybox::new(face(1), face(2), face(3), face(4), face(5), face(6));
+        let effects = PhotoEffects {
+            fill_lights: Some(FillLights {
+                fill_direction: Vector3::new(0.0, 0.3, 1.0).normalized(),
+                fill: Vector3::new(0.2, 0.25, 0.3),
+                forward: Vector3::new(0.0, -0.24, -0.97),
+                rim: Vector3::new(0.3, 0.3, 0.35),
+            }),
+            sun_pulse: 1.4,
+            dust: DustConfig { intensity: 0.2, ..DustConfig::default() },
+            reflection: Arc::new(SkyReflection::new()),
+        };
+        let config = PhotoConfig { scale: 1, samples: 3, ..PhotoConfig::default() };
+        let mut job = PhotoJob::new(objects.clone(), camera, scene.light.clone(), 0.0, Vec::new(), effects, 1.0, (120, 90), &config);
+        for _ in 0..job.total_passes() {
+            job.render_pass(&sky, &scene.deep_sky, scene.seed);
+        }
+        let average = job.average();
+
+        // Mean per-pixel difference from one unjittered render
+        let difference = |options: &RenderOptions| {
+            let single = render_scene(&objects, &camera, &scene.light, 120, 90, 0.0, options);
+            let total: f32 = single.colors.iter().zip(&average).map(|(a, b)| (*a - *b).length()).sum();
+            total / average.len() as f32
+        };
+        let with_effects = difference(&job.options(&sky, &scene.deep_sky, scene.seed));
+        let without = difference(&RenderOptions { sky: Some(&sky), deep_sky: &scene.deep_sky, seed: scene.seed, ..RenderOptions::default() });
+        // Only the antialiased edges differ
+        assert!(with_effects < 0.08, "the photo is {} away from the frame it was taken of", with_effects);
+        assert!(without > with_effects * 5.0, "the effects barely change the image: {} vs {}", without, with_effects);
+    }
+}
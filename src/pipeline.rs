@@ -0,0 +1,511 @@
+#![allow(dead_code)]
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+
+use raylib::prelude::*;
+use tracing::{debug, debug_span, trace_span};
+
+use crate::arena::FrameArena;
+use crate::depthrange::DepthMode;
+use crate::fragment::Fragment;
+use crate::framebuffer::{Framebuffer, PixelTarget};
+use crate::light::Light;
+use crate::shaders::vertex_shader;
+use crate::threads::RenderThreads;
+use crate::triangle::triangle_into;
+use crate::vertex::Vertex;
+use crate::{fragment_alpha, planet_vertex_shader, shade_fragment, Uniforms};
+
+/// Stages of the mesh pipeline, in the order they run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    VertexTransform, // Model/view/projection/viewport + per-kind vertex coloring
+    Assemble,        // Group vertices into triangles
+    Clip,            // Drop triangles behind the camera or entirely off screen
+    Rasterize,       // Triangles to fragments
+    Shade,           // Fragment shaders and lighting
+    Blend,           // Depth test and write into the framebuffer
+}
+
+impl Stage {
+    pub const ALL: [Stage; 6] = [
+        Stage::VertexTransform,
+        Stage::Assemble,
+        Stage::Clip,
+        Stage::Rasterize,
+        Stage::Shade,
+        Stage::Blend,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Stage::VertexTransform => "vertex",
+            Stage::Assemble => "assemble",
+            Stage::Clip => "clip",
+            Stage::Rasterize => "rasterize",
+            Stage::Shade => "shade",
+            Stage::Blend => "blend",
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Stage, String> {
+        let name = name.to_lowercase();
+        Stage::ALL
+            .into_iter()
+            .find(|s| s.name() == name || (name == "vertextransform" && *s == Stage::VertexTransform))
+            .ok_or_else(|| format!("unknown stage '{}' (vertex, assemble, clip, rasterize, shade, blend)", name))
+    }
+}
+
+/// Intermediate data collected while dumping a frame.
+struct FrameDump {
+    dir: PathBuf,
+    summary: String,
+}
+
+/// The mesh render path split into named stages. Each stage runs inside a `tracing`
+/// span (`RUST_LOG=trace` prints per-object, per-stage timings), can be disabled,
+/// and one frame's intermediate buffers can be written to disk. Fragment shading is
+/// spread over `threads`.
+pub struct Pipeline {
+    disabled: Vec<Stage>,
+    dump_frame: Option<u64>,
+    frame: u64,
+    dump: Option<FrameDump>,
+    threads: RenderThreads,
+    centroid_color: bool, // Re-run the planet vertex shader at each triangle's centroid
+    oversized: usize,     // Triangles larger than the target, clipped to it, this frame
+    samples: u32,         // Samples per pixel along each axis for the next objects
+    nearest: Vec<u32>,    // Per sample: index of the nearest fragment, reused between objects
+}
+
+/// Samples per pixel along each axis for the body the camera looks at.
+pub const FOCUS_SAMPLES: u32 = 2;
+/// Projected radius, in render pixels, past which the focused body is supersampled.
+pub const FOCUS_MIN_RADIUS_PX: f32 = 40.0;
+
+/// Samples per pixel along each axis for the focused body at `radius_px` on a frame
+/// `height` pixels tall: 2×2 once it's big enough for its jaggies to show, and back to 1
+/// once it's bigger than the frame, where there's little edge left to smooth and 4× the
+/// pixels to shade.
+pub fn focus_samples(radius_px: f32, height: u32) -> u32 {
+    if (FOCUS_MIN_RADIUS_PX..=height as f32).contains(&radius_px) { FOCUS_SAMPLES } else { 1 }
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Pipeline {
+            disabled: Vec::new(),
+            dump_frame: None,
+            frame: 0,
+            dump: None,
+            threads: RenderThreads::new(1).expect("one thread needs no pool"),
+            centroid_color: true,
+            oversized: 0,
+            samples: 1,
+            nearest: Vec::new(),
+        }
+    }
+
+    /// Reads `--disable-stage <stage>` (repeatable), `--dump-frame <n>` and `--threads <n>`.
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let mut pipeline = Pipeline::new();
+        pipeline.threads = RenderThreads::from_args(args)?;
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--disable-stage" => {
+                    let stage = iter.next().ok_or("--disable-stage needs a stage name")?;
+                    pipeline.set_enabled(Stage::parse(stage)?, false);
+                }
+                "--dump-frame" => {
+                    let frame = iter.next().ok_or("--dump-frame needs a frame number")?;
+                    let frame = frame.parse().map_err(|_| format!("invalid frame number '{}'", frame))?;
+                    pipeline.dump_frame = Some(frame);
+                }
+                _ => {}
+            }
+        }
+        Ok(pipeline)
+    }
+
+    pub fn is_enabled(&self, stage: Stage) -> bool {
+        !self.disabled.contains(&stage)
+    }
+
+    pub fn set_enabled(&mut self, stage: Stage, enabled: bool) {
+        self.disabled.retain(|s| *s != stage);
+        if !enabled {
+            self.disabled.push(stage);
+        }
+    }
+
+    /// Rebuilds the worker pool; 1 shades on the main thread.
+    pub fn set_thread_count(&mut self, count: usize) -> Result<(), String> {
+        self.threads.set_thread_count(count)
+    }
+
+    pub fn thread_count(&self) -> usize {
+        self.threads.count()
+    }
+
+    /// Whether rasterized triangles also get their vertex color evaluated at the centroid.
+    pub fn set_centroid_color(&mut self, enabled: bool) {
+        self.centroid_color = enabled;
+    }
+
+    /// Samples per pixel along each axis for the objects rendered from now on; above 1
+    /// they're rasterized at that multiple of the target's size and box-filtered down.
+    pub fn set_samples(&mut self, samples: u32) {
+        self.samples = samples.max(1);
+    }
+
+    /// Triangles rasterized this frame that were larger than the whole target and were
+    /// clipped to it; many of them point at a body with an absurd scale.
+    pub fn oversized_triangles(&self) -> usize {
+        self.oversized
+    }
+
+    /// Call once at the start of every frame; starts collecting if this is the frame to dump.
+    pub fn begin_frame(&mut self) {
+        self.frame += 1;
+        self.oversized = 0;
+        if self.dump_frame == Some(self.frame) {
+            self.dump = Some(FrameDump {
+                dir: PathBuf::from(format!("frame_dump_{}", self.frame)),
+                summary: format!("frame {}\nobject\tvertices\ttriangles\tclipped\tfragments\n", self.frame),
+            });
+        }
+    }
+
+    /// Call once at the end of every frame, after everything is drawn. Writes the dump
+    /// (per-object counts, post-clip triangle lists, color and depth buffers) if one was collected.
+    pub fn end_frame(&mut self, framebuffer: &Framebuffer) -> Result<(), String> {
+        let Some(dump) = self.dump.take() else {
+            return Ok(());
+        };
+        fs::create_dir_all(&dump.dir).map_err(|e| e.to_string())?;
+        fs::write(dump.dir.join("summary.tsv"), &dump.summary).map_err(|e| e.to_string())?;
+        framebuffer.export(&dump.dir)?;
+        debug!(dir = %dump.dir.display(), "frame dumped");
+        Ok(())
+    }
+
+    /// Draws one mesh through the staged pipeline into the framebuffer or any other target.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render<T: PixelTarget>(
+        &mut self,
+        framebuffer: &mut T,
+        uniforms: &Uniforms,
+        vertex_array: &[Vertex],
+        light: &Light,
+        name: &str,
+        object_type: &str,
+        arena: &mut FrameArena,
+    ) {
+        let _object = debug_span!("object", name, kind = object_type).entered();
+
+        // Vertex Shader Stage
+        let mut transformed_vertices = arena.vec::<Vertex>(vertex_array.len());
+        if self.is_enabled(Stage::VertexTransform) {
+            let _span = trace_span!("stage", stage = "vertex").entered();
+            for vertex in vertex_array {
+                let mut transformed = vertex_shader(vertex, uniforms);
+                planet_vertex_shader(&mut transformed, uniforms, object_type);
+                transformed_vertices.push(transformed);
+            }
+        }
+
+        // Primitive Assembly Stage
+        let mut triangles = arena.vec::<[Vertex; 3]>(transformed_vertices.len() / 3);
+        if self.is_enabled(Stage::Assemble) {
+            let _span = trace_span!("stage", stage = "assemble").entered();
+            for tri in transformed_vertices.chunks_exact(3) {
+                triangles.push([tri[0].clone(), tri[1].clone(), tri[2].clone()]);
+            }
+        }
+        let assembled = triangles.len();
+
+        // Clipping Stage
+        if self.is_enabled(Stage::Clip) {
+            let _span = trace_span!("stage", stage = "clip").entered();
+            let (width, height) = framebuffer.size();
+            let mode = uniforms.frame.depth_mode;
+            triangles.retain(|tri| is_visible(tri, width as f32, height as f32, mode));
+        }
+
+        // Rasterization Stage (supersampled: on a grid `samples` times finer, with the
+        // fragments then put back in pixels so screen-space shading lines up)
+        let mut fragments = arena.vec::<Fragment>(0);
+        let mut oversized = 0;
+        let samples = self.samples;
+        if self.is_enabled(Stage::Rasterize) {
+            let _span = trace_span!("stage", stage = "rasterize").entered();
+            let (width, height) = framebuffer.size();
+            let size = (width * samples, height * samples);
+            for tri in &triangles {
+                let centroid = self.centroid_color.then(|| centroid_color(tri, uniforms, object_type));
+                let [a, b, c] = if samples > 1 { scaled(tri, samples as f32) } else { tri.clone() };
+                oversized += triangle_into(&a, &b, &c, light, centroid, size, &mut fragments) as usize;
+            }
+            if samples > 1 {
+                for fragment in fragments.iter_mut() {
+                    fragment.position /= samples as f32;
+                }
+            }
+        }
+        self.oversized += oversized;
+
+        debug!(
+            vertices = transformed_vertices.len(),
+            triangles = assembled,
+            clipped = assembled - triangles.len(),
+            oversized,
+            samples,
+            fragments = fragments.len(),
+        );
+        if let Some(dump) = &mut self.dump {
+            dump.record(name, &transformed_vertices, assembled, &triangles, fragments.len());
+        }
+        arena.recycle(transformed_vertices);
+        arena.recycle(triangles);
+
+        // Fragment Processing Stage (disabled: fragments keep the interpolated vertex color)
+        if self.is_enabled(Stage::Shade) {
+            let _span = trace_span!("stage", stage = "shade").entered();
+            self.shade(&mut fragments, uniforms, object_type);
+        }
+
+        // Blend Stage
+        if self.is_enabled(Stage::Blend) && samples > 1 {
+            let _span = trace_span!("stage", stage = "blend").entered();
+            self.resolve(framebuffer, &fragments, uniforms, object_type);
+        } else if self.is_enabled(Stage::Blend) {
+            let _span = trace_span!("stage", stage = "blend").entered();
+            for fragment in &fragments {
+                let (x, y) = (fragment.position.x as i32, fragment.position.y as i32);
+                let alpha = fragment_alpha(fragment, uniforms, object_type);
+                if alpha >= 1.0 {
+                    framebuffer.point(x, y, fragment.color, fragment.depth);
+                } else {
+                    framebuffer.blend_point(x, y, fragment.color, alpha, fragment.depth);
+                }
+            }
+        }
+        arena.recycle(fragments);
+    }
+
+    /// Box-filters supersampled `fragments` into the target. Each sample keeps its nearest
+    /// fragment, so the object's far side never bleeds through; each pixel then gets the
+    /// average of its covered samples, with the fraction covered as alpha and the nearest
+    /// sample's depth. Fully covered pixels are written like any other, edge pixels blend
+    /// over whatever is behind them.
+    fn resolve<T: PixelTarget>(&mut self, target: &mut T, fragments: &[Fragment], uniforms: &Uniforms, object_type: &str) {
+        if fragments.is_empty() {
+            return;
+        }
+        let samples = self.samples as usize;
+        let mode = uniforms.frame.depth_mode;
+        let sample = |f: &Fragment| ((f.position.x * samples as f32) as usize, (f.position.y * samples as f32) as usize);
+
+        // Bounding rectangle of the object on screen, in samples
+        let (mut x0, mut y0, mut x1, mut y1) = (usize::MAX, usize::MAX, 0, 0);
+        for fragment in fragments {
+            let (x, y) = sample(fragment);
+            (x0, y0, x1, y1) = (x0.min(x), y0.min(y), x1.max(x), y1.max(y));
+        }
+        (x0, y0) = (x0 / samples * samples, y0 / samples * samples);
+        let width = (x1 / samples + 1) * samples - x0;
+        let height = (y1 / samples + 1) * samples - y0;
+
+        self.nearest.clear();
+        self.nearest.resize(width * height, u32::MAX);
+        for (index, fragment) in fragments.iter().enumerate() {
+            let (x, y) = sample(fragment);
+            let slot = &mut self.nearest[(y - y0) * width + (x - x0)];
+            if *slot == u32::MAX || mode.closer(fragment.depth, fragments[*slot as usize].depth) {
+                *slot = index as u32;
+            }
+        }
+
+        let full = (samples * samples) as f32;
+        for py in (0..height).step_by(samples) {
+            for px in (0..width).step_by(samples) {
+                let (mut color, mut coverage, mut depth) = (Vector3::zero(), 0.0, mode.cleared());
+                for sy in py..py + samples {
+                    for &slot in &self.nearest[sy * width + px..sy * width + px + samples] {
+                        let Some(fragment) = fragments.get(slot as usize) else {
+                            continue;
+                        };
+                        let alpha = fragment_alpha(fragment, uniforms, object_type).min(1.0);
+                        color += fragment.color * alpha;
+                        coverage += alpha;
+                        if mode.closer(fragment.depth, depth) {
+                            depth = fragment.depth;
+                        }
+                    }
+                }
+                if coverage <= 0.0 {
+                    continue;
+                }
+                let (x, y) = (((x0 + px) / samples) as i32, ((y0 + py) / samples) as i32);
+                let alpha = coverage / full;
+                if alpha >= 1.0 - 1e-4 {
+                    target.point(x, y, color / coverage, depth);
+                } else {
+                    target.blend_point(x, y, color / coverage, alpha, depth);
+                }
+            }
+        }
+    }
+
+    /// Fragment shaders over `fragments`, in parallel when there is a pool.
+    fn shade(&self, fragments: &mut [Fragment], uniforms: &Uniforms, object_type: &str) {
+        self.threads.for_each_mut(fragments, |fragment| {
+            fragment.color = shade_fragment(fragment, uniforms, object_type);
+        });
+    }
+}
+
+impl FrameDump {
+    fn record(&mut self, name: &str, vertices: &[Vertex], assembled: usize, triangles: &[[Vertex; 3]], fragments: usize) {
+        let _ = writeln!(
+            self.summary,
+            "{}\t{}\t{}\t{}\t{}",
+            name,
+            vertices.len(),
+            assembled,
+            assembled - triangles.len(),
+            fragments
+        );
+
+        // Screen-space triangles that survived clipping, one per line
+        let mut list = String::new();
+        for tri in triangles {
+            for v in tri {
+                let p = v.transformed_position;
+                let _ = write!(list, "{:.3} {:.3} {:.5}\t", p.x, p.y, p.z);
+            }
+            list.push('\n');
+        }
+        let dir = self.dir.join("triangles");
+        if fs::create_dir_all(&dir).is_ok() {
+            let _ = fs::write(dir.join(format!("{}.tsv", name)), list);
+        }
+    }
+}
+
+/// `tri` on a grid `samples` times finer than the screen.
+fn scaled(tri: &[Vertex; 3], samples: f32) -> [Vertex; 3] {
+    tri.clone().map(|mut v| {
+        v.transformed_position.x *= samples;
+        v.transformed_position.y *= samples;
+        v
+    })
+}
+
+/// The color the planet vertex shader gives the middle of `tri`. Kinds without one
+/// keep the average of the corners, which leaves the triangle's blend linear.
+fn centroid_color(tri: &[Vertex; 3], uniforms: &Uniforms, object_type: &str) -> Vector3 {
+    let third = 1.0 / 3.0;
+    let mut centroid = Vertex {
+        position: (tri[0].position + tri[1].position + tri[2].position) * third,
+        normal: (tri[0].normal + tri[1].normal + tri[2].normal) * third,
+        tex_coords: (tri[0].tex_coords + tri[1].tex_coords + tri[2].tex_coords) * third,
+        color: (tri[0].color + tri[1].color + tri[2].color) * third,
+        ..tri[0].clone()
+    };
+    planet_vertex_shader(&mut centroid, uniforms, object_type);
+    centroid.color
+}
+
+/// Trivial reject: a triangle is dropped if any vertex is outside the depth range
+/// (behind the camera or past the far plane) or all three are off the same screen edge.
+fn is_visible(tri: &[Vertex; 3], width: f32, height: f32, mode: DepthMode) -> bool {
+    let p = tri.each_ref().map(|v| v.transformed_position);
+    if p.iter().any(|p| !p.x.is_finite() || !p.y.is_finite() || !mode.in_range(p.z)) {
+        return false;
+    }
+    let off_left = p.iter().all(|p| p.x < 0.0);
+    let off_right = p.iter().all(|p| p.x >= width);
+    let off_top = p.iter().all(|p| p.y < 0.0);
+    let off_bottom = p.iter().all(|p| p.y >= height);
+    !(off_left || off_right || off_top || off_bottom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    use crate::matrix::{create_model_matrix, create_projection_matrix, create_view_matrix, create_viewport_matrix};
+    use crate::noise::Noise;
+    use crate::planetshaders::spherical_uv;
+    use crate::{FrameUniforms, ObjectUniforms};
+
+    const SIZE: u32 = 48;
+
+    /// One fragment per pixel of a unit sphere filling most of the frame.
+    fn sphere_fragments() -> Vec<Fragment> {
+        let mut fragments = Vec::new();
+        for y in 0..SIZE {
+            for x in 0..SIZE {
+                let u = (x as f32 + 0.5) / SIZE as f32 * 2.0 - 1.0;
+                let v = (y as f32 + 0.5) / SIZE as f32 * 2.0 - 1.0;
+                let d = u * u + v * v;
+                if d >= 1.0 {
+                    continue;
+                }
+                let normal = Vector3::new(u, -v, (1.0 - d).sqrt());
+                fragments.push(Fragment {
+                    normal,
+                    world_position: normal,
+                    uv: spherical_uv(normal),
+                    ..Fragment::new(x as f32, y as f32, Vector3::new(0.5, 0.5, 0.5), 0.5)
+                });
+            }
+        }
+        fragments
+    }
+
+    fn shade_with(threads: usize, kind: &str) -> Vec<Vector3> {
+        let eye = Vector3::new(0.0, 0.0, 3.0);
+        let view = create_view_matrix(eye, Vector3::zero(), Vector3::new(0.0, 1.0, 0.0));
+        let projection = create_projection_matrix(PI / 3.0, 1.0, 0.1, 100.0);
+        let viewport = create_viewport_matrix(0.0, 0.0, SIZE as f32, SIZE as f32);
+        let frame = FrameUniforms {
+            light_position: Vector3::new(5.0, 3.0, 5.0),
+            ambient: 0.1,
+            time: 2.5,
+            ..FrameUniforms::new(view, projection, viewport, eye)
+        };
+        let model = create_model_matrix(Vector3::zero(), 1.0, Vector3::zero());
+        let uniforms = Uniforms {
+            frame,
+            object: ObjectUniforms { irradiance: Vector3::one(), noise: Noise::new(7), ..ObjectUniforms::new(model, &frame) },
+        };
+
+        let mut pipeline = Pipeline::new();
+        pipeline.set_thread_count(threads).unwrap();
+        let mut fragments = sphere_fragments();
+        pipeline.shade(&mut fragments, &uniforms, kind);
+        fragments.iter().map(|f| f.color).collect()
+    }
+
+    #[test]
+    fn thread_count_does_not_change_the_image() {
+        for kind in ["earth", "gassy1", "sun", "moon"] {
+            let serial = shade_with(1, kind);
+            assert!(!serial.is_empty());
+            assert_eq!(serial, shade_with(4, kind), "{}", kind);
+        }
+    }
+
+    #[test]
+    fn rejects_zero_threads() {
+        assert!(Pipeline::new().set_thread_count(0).is_err());
+        assert!(Pipeline::from_args(&["--threads".to_string(), "none".to_string()]).is_err());
+    }
+}
@@ -3,6 +3,10 @@ use std::f32::consts::PI;
 
 use raylib::prelude::{Vector2, Vector3};
 
+use crate::generator::{PlanetKind, PlanetParams};
+use crate::noise::Noise;
+use crate::params::{param, ParamSet};
+use crate::ramp::RampSet;
 use crate::vertex::Vertex;
 
 // ------------------------
@@ -44,43 +48,13 @@ fn normalize3(v: Vector3) -> Vector3 {
     }
 }
 
-fn saturate_vec3(v: Vector3) -> Vector3 {
-    Vector3::new(
-        clamp(v.x, 0.0, 1.0),
-        clamp(v.y, 0.0, 1.0),
-        clamp(v.z, 0.0, 1.0),
-    )
-}
-
 fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
     let t = clamp((x - edge0) / (edge1 - edge0), 0.0, 1.0);
     t * t * (3.0 - 2.0 * t)
 }
 
-// Simple hash-based noise in 2D
-fn hash2(p: Vector2) -> f32 {
-    let n = p.x * 157.0 + p.y * 113.0;
-    (n.sin() * 43758.5453).fract()
-}
-
-// Very cheap fractal noise (fbm)
-fn fbm(uv: Vector2) -> f32 {
-    let mut value = 0.0;
-    let mut amp = 0.5;
-    let mut freq = 1.0;
-
-    for _ in 0..4 {
-        let p = Vector2::new(uv.x * freq, uv.y * freq);
-        value += hash2(p) * amp;
-        freq *= 2.0;
-        amp *= 0.5;
-    }
-
-    value
-}
-
 // Convert a normal to [0,1]x[0,1] spherical UV
-fn spherical_uv(n: Vector3) -> Vector2 {
+pub fn spherical_uv(n: Vector3) -> Vector2 {
     let n = normalize3(n);
     let lon = n.z.atan2(n.x); // [-pi, pi]
     let lat = n.y.asin();     // [-pi/2, pi/2]
@@ -91,33 +65,81 @@ fn spherical_uv(n: Vector3) -> Vector2 {
     Vector2::new(u, v)
 }
 
+// Celdas de igual área: la longitud como en `spherical_uv` y, hacia abajo, (1 - sin(lat)) / 2.
+// En UV normales las celdas se achican hacia los polos y los cráteres se amontonan ahí;
+// con pasos iguales de sin(lat) todas las celdas cubren la misma superficie.
+pub fn equal_area_uv(n: Vector3) -> Vector2 {
+    let n = normalize3(n);
+    Vector2::new(0.5 + n.z.atan2(n.x) / (2.0 * PI), 0.5 - 0.5 * n.y)
+}
+
+// Inversa de `equal_area_uv`
+pub fn equal_area_dir(p: Vector2) -> Vector3 {
+    let lon = (p.x - 0.5) * 2.0 * PI;
+    let y = clamp(1.0 - 2.0 * p.y, -1.0, 1.0);
+    let r = (1.0 - y * y).sqrt();
+    Vector3::new(r * lon.cos(), y, r * lon.sin())
+}
+
+// Celda de `n` en una grilla de grid.x × grid.y celdas de igual área
+pub fn equal_area_cell(n: Vector3, grid: Vector2) -> Vector2 {
+    let p = equal_area_uv(n);
+    Vector2::new((p.x * grid.x).floor().min(grid.x - 1.0), (p.y * grid.y).floor().min(grid.y - 1.0))
+}
+
+// Punto de la esfera en la posición `local` ([0,1]²) dentro de una celda
+fn cell_point(cell: Vector2, local: Vector2, grid: Vector2) -> Vector3 {
+    equal_area_dir(Vector2::new((cell.x + local.x) / grid.x, (cell.y + local.y) / grid.y))
+}
+
+// Lado de una celda "promedio", en radianes: para medir distancias en celdas sin importar la latitud
+fn cell_size(grid: Vector2) -> f32 {
+    (4.0 * PI / (grid.x * grid.y)).sqrt()
+}
+
+// Celdas de cráteres de los planetas generados
+const GENERATED_CRATER_GRID: Vector2 = Vector2 { x: 24.0, y: 12.0 };
+
+// Cráter de un planeta generado en `cell`, si le toca uno: centro en la esfera y radio angular
+pub fn generated_crater(cell: Vector2, density: f32, noise: &Noise) -> Option<(Vector3, f32)> {
+    if noise.hash(cell, 2) >= density {
+        return None;
+    }
+    let local = Vector2::new(0.3 + 0.4 * noise.hash(cell, 3), 0.3 + 0.4 * noise.hash(cell, 4));
+    let radius = (0.15 + 0.2 * noise.hash(cell, 5)) * cell_size(GENERATED_CRATER_GRID);
+    Some((cell_point(cell, local, GENERATED_CRATER_GRID), radius))
+}
+
 // =======================================================
 // SHADERS
-// Cada función modifica v.color en función de su normal
+// Cada función modifica v.color en función de su normal. El color queda en
+// lineal y sin recortar a [0, 1]: se interpola así y lo recortan el fragment
+// shader o el framebuffer al final, para no marcar los bordes de los triángulos.
 // =======================================================
 
+// Rampa por defecto del planeta tipo Urano, por latitud (0 arriba, 1 abajo): del
+// púrpura suave al más profundo hacia el ecuador, y del púrpura suave al lila claro después
+const URANUS_LATITUDE_RAMP: [(f32, Vector3); 4] = [
+    (0.0, Vector3 { x: 0.65, y: 0.55, z: 0.92 }), // púrpura suave
+    (0.5, Vector3 { x: 0.50, y: 0.40, z: 0.85 }), // púrpura más profundo
+    (0.5, Vector3 { x: 0.65, y: 0.55, z: 0.92 }),
+    (1.0, Vector3 { x: 0.78, y: 0.72, z: 0.98 }), // lila claro
+];
+
 // 🪐 Planeta tipo Urano: púrpura / lila pálido con bandas muy suaves
-pub fn uranus_like_vertex_shader(v: &mut Vertex) {
+pub fn uranus_like_vertex_shader(v: &mut Vertex, noise: &Noise, params: &ParamSet, ramps: &RampSet) {
     let n = normalize3(v.normal);
     let uv = spherical_uv(n);
 
-    // Paleta base más púrpura / lila
-    let base_top    = Vector3::new(0.78, 0.72, 0.98); // lila claro
-    let base_mid    = Vector3::new(0.65, 0.55, 0.92); // púrpura suave
-    let base_bottom = Vector3::new(0.50, 0.40, 0.85); // púrpura más profundo
-
     // Gradiente vertical suave para que no sea completamente plano
     let t_lat = clamp(uv.y, 0.0, 1.0);
-    let mut base_color = if t_lat < 0.5 {
-        mix_vec3(base_mid, base_bottom, t_lat * 2.0)
-    } else {
-        mix_vec3(base_mid, base_top, (t_lat - 0.5) * 2.0)
-    };
+    let mut base_color = ramps.sample("latitude", t_lat, &URANUS_LATITUDE_RAMP);
 
     // Bandas extremadamente suaves en la componente de brillo
-    let band_freq = 10.0;
+    let band_freq = param(params, "gassy2", "band_freq");
+    let band_contrast = param(params, "gassy2", "band_contrast");
     let band = (uv.y * band_freq).sin() * 0.5 + 0.5; // 0..1
-    let band_strength = mix(0.92, 1.08, band);
+    let band_strength = mix(1.0 - band_contrast, 1.0 + band_contrast, band);
 
     base_color = Vector3::new(
         base_color.x * band_strength,
@@ -126,31 +148,37 @@ pub fn uranus_like_vertex_shader(v: &mut Vertex) {
     );
 
     // Un poco de ruido muy suave para romper la uniformidad
-    let noise = fbm(Vector2::new(uv.x * 3.0, uv.y * 3.0));
-    let noise_mix = mix(0.96, 1.04, noise);
+    let grain = noise.fbm(Vector2::new(uv.x * 3.0, uv.y * 3.0), 0);
+    let noise_mix = mix(0.96, 1.04, grain);
     base_color = Vector3::new(
         base_color.x * noise_mix,
         base_color.y * noise_mix,
         base_color.z * noise_mix,
     );
 
-    v.color = saturate_vec3(base_color);
+    v.color = base_color;
 }
 
+// Rampas por defecto del gigante celeste: bandas de celeste oscuro a claro, y la banda
+// ecuatorial de un solo rojo
+const CYAN_BANDS_RAMP: [(f32, Vector3); 2] = [
+    (0.0, Vector3 { x: 0.50, y: 0.78, z: 0.90 }),
+    (1.0, Vector3 { x: 0.75, y: 0.92, z: 0.98 }),
+];
+const RED_BAND_RAMP: [(f32, Vector3); 1] = [(0.0, Vector3 { x: 0.90, y: 0.20, z: 0.15 })];
+
 // 🌀 Gigante gaseoso celeste con una banda roja en el ecuador
-pub fn cyan_redband_gas_vertex_shader(v: &mut Vertex) {
+pub fn cyan_redband_gas_vertex_shader(v: &mut Vertex, noise: &Noise, params: &ParamSet, ramps: &RampSet) {
     let n = normalize3(v.normal);
     let uv = spherical_uv(n);
 
     // Capa 1: gas celeste con bandas suaves
-    let band_freq = 12.0;
+    let band_freq = param(params, "gassy3", "band_freq");
     let base_bands = (uv.y * band_freq).sin() * 0.5 + 0.5; // 0..1
-    let cyan_light = Vector3::new(0.75, 0.92, 0.98);
-    let cyan_dark  = Vector3::new(0.50, 0.78, 0.90);
-    let mut color  = mix_vec3(cyan_dark, cyan_light, base_bands);
+    let mut color = ramps.sample("bands", base_bands, &CYAN_BANDS_RAMP);
 
     // Un poco de ruido para rompre la perfección de las bandas
-    let swirl = fbm(Vector2::new(uv.x * 5.0, uv.y * 8.0));
+    let swirl = noise.fbm(Vector2::new(uv.x * 5.0, uv.y * 8.0), 0);
     let swirl_intensity = mix(0.9, 1.1, swirl);
     color = Vector3::new(
         color.x * swirl_intensity,
@@ -161,46 +189,47 @@ pub fn cyan_redband_gas_vertex_shader(v: &mut Vertex) {
     // Capa 2: banda roja en el ecuador
     // uv.y ~ 0.5 es el ecuador, usamos smoothstep para hacer una franja relativamente delgada
     let equator_dist = (uv.y - 0.5).abs();
-    let band_mask = smoothstep(0.08, 0.0, equator_dist); // 1 cerca del ecuador, 0 lejos
-    let red_band_color = Vector3::new(0.90, 0.20, 0.15);
+    let band_mask = smoothstep(param(params, "gassy3", "red_band_width"), 0.0, equator_dist); // 1 cerca del ecuador, 0 lejos
+    let red_band_color = ramps.sample("red_band", band_mask, &RED_BAND_RAMP);
 
     color = mix_vec3(color, red_band_color, band_mask * 0.9);
 
-    v.color = saturate_vec3(color);
+    v.color = color;
 }
 
+// Rampas por defecto de la lava (por temperatura) y de la corteza: la lava va de rojo
+// anaranjado a puntos casi amarillos, ya con el boost hacia el rojo aplicado
+const LAVA_RAMP: [(f32, Vector3); 2] = [
+    (0.0, Vector3 { x: 1.0, y: 0.2125, z: 0.04 }),
+    (1.0, Vector3 { x: 1.0, y: 0.8075, z: 0.36 }),
+];
+const CRUST_RAMP: [(f32, Vector3); 2] = [
+    (0.0, Vector3 { x: 0.95, y: 0.96, z: 0.99 }), // hielo blanco
+    (1.0, Vector3 { x: 0.75, y: 0.78, z: 0.82 }), // hielo gris
+];
+
 // 🪨 Planeta tipo "lava bajo hielo": parches de lava naranja con corteza blanca/gris
-pub fn hot_cold_rocky_planet_vertex_shader(v: &mut Vertex) {
+pub fn hot_cold_rocky_planet_vertex_shader(v: &mut Vertex, noise: &Noise, params: &ParamSet, ramps: &RampSet) {
     let n = normalize3(v.normal);
     let uv = spherical_uv(n);
 
     // Capa 1: mapa base de parches (dónde hay lava vs corteza)
-    let field = fbm(Vector2::new(uv.x * 3.0 + 2.0, uv.y * 3.0 + 5.0));
-    let lava_mask = smoothstep(0.80, 0.80, field); // 0 = corteza, 1 = lava
+    let field = noise.fbm(Vector2::new(uv.x * 3.0, uv.y * 3.0), 0);
+    let lava_threshold = param(params, "rocky2", "lava_threshold");
+    let lava_mask = smoothstep(lava_threshold, lava_threshold, field); // 0 = corteza, 1 = lava
 
     // Borde de transición (anillo)
     let inner = smoothstep(0.70, 0.90, field);
     let outer = smoothstep(0.30, 0.75, field);
     let edge_ring = clamp(outer - inner, 0.0, 1.0);
 
-    // Capa 2: lava brillante (más roja y dominante)
-    let lava_base = Vector3::new(1.0, 0.25, 0.05); // rojo/naranja más intenso
-    let lava_hot  = Vector3::new(1.0, 0.95, 0.45); // puntos muy calientes casi amarillos
-    let lava_detail = fbm(Vector2::new(uv.x * 18.0, uv.y * 18.0));
-    let mut lava_color = mix_vec3(lava_base, lava_hot, lava_detail);
-
-    // Pequeño boost extra hacia rojo en las zonas de lava
-    lava_color = Vector3::new(
-        lava_color.x,
-        lava_color.y * 0.85,
-        lava_color.z * 0.8,
-    );
+    // Capa 2: lava brillante (más roja y dominante), de la rampa temperatura → color
+    let lava_temperature = noise.fbm(Vector2::new(uv.x * 18.0, uv.y * 18.0), 1);
+    let lava_color = ramps.sample("lava", lava_temperature, &LAVA_RAMP);
 
     // Capa 3: corteza blanca/gris
-    let ice_white = Vector3::new(0.95, 0.96, 0.99);
-    let ice_grey  = Vector3::new(0.75, 0.78, 0.82);
-    let crust_detail = fbm(Vector2::new(uv.x * 10.0, uv.y * 10.0));
-    let crust_color = mix_vec3(ice_white, ice_grey, crust_detail);
+    let crust_detail = noise.fbm(Vector2::new(uv.x * 10.0, uv.y * 10.0), 2);
+    let crust_color = ramps.sample("crust", crust_detail, &CRUST_RAMP);
 
     // Mezcla lava vs corteza (lava un poco más dominante)
     let lava_influence = clamp(lava_mask * 1.0, 0.0, 1.0);
@@ -211,38 +240,40 @@ pub fn hot_cold_rocky_planet_vertex_shader(v: &mut Vertex) {
     color = mix_vec3(color, crack_color, edge_ring * 0.9);
 
     // Capa 5: hollín / suciedad cerca de zonas de lava
-    let soot_noise = fbm(Vector2::new(uv.x * 8.0 + 7.0, uv.y * 14.0 + 3.0));
+    let soot_noise = noise.fbm(Vector2::new(uv.x * 8.0, uv.y * 14.0), 3);
     let soot_mask = edge_ring * smoothstep(0.4, 0.8, soot_noise);
     let soot_color = Vector3::new(0.12, 0.12, 0.14);
-    color = mix_vec3(color, soot_color, soot_mask * 0.6);
+    color = mix_vec3(color, soot_color, soot_mask * param(params, "rocky2", "soot"));
 
-    v.color = saturate_vec3(color);
+    v.color = color;
 }
 
 
 // 🌞 Estrella / Sol: superficie caliente con granulación
-pub fn sun_vertex_shader(v: &mut Vertex) {
+pub fn sun_vertex_shader(v: &mut Vertex, noise: &Noise, params: &ParamSet) {
     let n = normalize3(v.normal);
     let uv = spherical_uv(n);
 
     // Granulación en la superficie
-    let motion = Vector2::new(uv.x * 20.0, uv.y * 20.0);
-    let granulation = fbm(motion); // 0..1
+    let granulation_scale = param(params, "sun", "granulation");
+    let motion = Vector2::new(uv.x * granulation_scale, uv.y * granulation_scale);
+    let granulation = noise.fbm(motion, 0); // 0..1
 
     let hot_core = Vector3::new(1.0, 0.95, 0.6);
     let hot_edges = Vector3::new(1.0, 0.7, 0.15);
-    let mut color = mix_vec3(hot_edges, hot_core, granulation);
-
-    // Oscurecer un poco hacia el borde de la esfera (limb darkening)
-    let facing = clamp(n.z * 0.5 + 0.5, 0.0, 1.0);
-    let intensity = mix(0.7, 1.4, facing);
-    color = Vector3::new(color.x * intensity, color.y * intensity, color.z * intensity);
 
-    v.color = saturate_vec3(color);
+    // El oscurecimiento hacia el borde (limb darkening) depende de la vista: se calcula por fragmento
+    v.color = mix_vec3(hot_edges, hot_core, granulation);
 }
 
+// Rampa por defecto altura → terreno de las placas, estilo cartoon
+const ROCKY_TERRAIN_RAMP: [(f32, Vector3); 2] = [
+    (0.0, Vector3 { x: 0.88, y: 0.65, z: 0.42 }), // naranja suave
+    (1.0, Vector3 { x: 0.96, y: 0.78, z: 0.54 }), // beige anaranjado claro
+];
+
 // 🪨 Planeta rocoso tipo "galleta": placas grandes anaranjadas con bordes oscuros y cráteres
-pub fn rocky_planet_vertex_shader(v: &mut Vertex) {
+pub fn rocky_planet_vertex_shader(v: &mut Vertex, noise: &Noise, params: &ParamSet, ramps: &RampSet) {
     let n = normalize3(v.normal);
     let uv = spherical_uv(n);
 
@@ -254,8 +285,8 @@ pub fn rocky_planet_vertex_shader(v: &mut Vertex) {
     let local = Vector2::new(plate_uv.x.fract(), plate_uv.y.fract());
 
     // Centro pseudo-aleatorio de la placa dentro de la celda
-    let jitter_x = hash2(cell) * 0.3 - 0.15;
-    let jitter_y = hash2(Vector2::new(cell.x + 23.0, cell.y + 7.0)) * 0.3 - 0.15;
+    let jitter_x = noise.hash(cell, 0) * 0.3 - 0.15;
+    let jitter_y = noise.hash(cell, 1) * 0.3 - 0.15;
     let center = Vector2::new(0.5 + jitter_x, 0.5 + jitter_y);
 
     // Distancia al centro de la placa en coordenadas locales
@@ -264,8 +295,8 @@ pub fn rocky_planet_vertex_shader(v: &mut Vertex) {
     let dist = (dx * dx + dy * dy).sqrt();
 
     // Radio base de la placa y grosor del borde
-    let plate_radius = 0.55;
-    let edge_width = 0.06;
+    let plate_radius = param(params, "rocky1", "plate_radius");
+    let edge_width = param(params, "rocky1", "edge_width");
 
     // Máscara interior de la placa (1 = dentro de la placa)
     let plate_mask = smoothstep(plate_radius, plate_radius - edge_width * 1.5, dist);
@@ -274,15 +305,13 @@ pub fn rocky_planet_vertex_shader(v: &mut Vertex) {
     let edge_outer = smoothstep(plate_radius + edge_width * 0.3, plate_radius - edge_width * 0.2, dist);
     let edge_ring = clamp(edge_outer - edge_inner, 0.0, 1.0);
 
-    // Colores base de la roca estilo cartoon
-    let plate_light = Vector3::new(0.96, 0.78, 0.54); // beige anaranjado claro
-    let plate_mid   = Vector3::new(0.88, 0.65, 0.42); // naranja suave
-    let gap_color   = Vector3::new(0.22, 0.10, 0.08); // color entre placas (grieta oscura)
+    // Color entre placas (grieta oscura)
+    let gap_color   = Vector3::new(0.22, 0.10, 0.08);
 
-    // Variación de color por placa usando ruido basado en la celda
-    let plate_noise = hash2(Vector2::new(cell.x + 11.0, cell.y + 19.0));
-    let plate_t = clamp(plate_noise * 1.2, 0.0, 1.0);
-    let mut plate_color = mix_vec3(plate_mid, plate_light, plate_t);
+    // Altura de cada placa según el ruido de su celda, coloreada con la rampa del terreno
+    let plate_noise = noise.hash(cell, 2);
+    let plate_height = clamp(plate_noise * 1.2, 0.0, 1.0);
+    let mut plate_color = ramps.sample("terrain", plate_height, &ROCKY_TERRAIN_RAMP);
 
     // Sombreado suave dentro de la placa (más claro en el centro)
     let center_shade = 1.0 - clamp(dist / (plate_radius + 0.1), 0.0, 1.0);
@@ -303,22 +332,26 @@ pub fn rocky_planet_vertex_shader(v: &mut Vertex) {
     // --------------------------------
     // Cráteres pequeños dentro de las placas
     // --------------------------------
-    // Usamos otra capa de ruido en la celda para decidir dónde hay cráteres
-    let crater_seed = hash2(Vector2::new(cell.x + 5.0, cell.y + 37.0));
+    // Van en su propia grilla de igual área (del mismo tamaño que la de placas) para
+    // que no se amontonen en los polos; otra capa de ruido decide qué celdas tienen
+    let crater_grid = Vector2::new(6.0, 4.0);
+    let crater_base = equal_area_cell(n, crater_grid);
+    let crater_seed = noise.hash(crater_base, 3);
+    let min_crater_radius = param(params, "rocky1", "crater_radius");
 
     // Solo generamos cráteres si el fragmento está dentro de la placa
     if plate_mask > 0.5 && crater_seed > 0.35 {
         // Hasta 3 posibles cráteres por celda
         for i in 0..3 {
-            let offset_x = hash2(Vector2::new(cell.x + 31.0 + i as f32 * 13.0, cell.y + 17.0)) * 0.8 + 0.1;
-            let offset_y = hash2(Vector2::new(cell.x + 47.0 + i as f32 * 29.0, cell.y + 3.0)) * 0.8 + 0.1;
-            let crater_center = Vector2::new(offset_x, offset_y);
+            let crater_cell = Vector2::new(crater_base.x + i as f32 * 13.0, crater_base.y);
+            let offset_x = noise.hash(crater_cell, 4) * 0.8 + 0.1;
+            let offset_y = noise.hash(crater_cell, 5) * 0.8 + 0.1;
+            let crater_center = cell_point(crater_base, Vector2::new(offset_x, offset_y), crater_grid);
 
-            let cdx = local.x - crater_center.x;
-            let cdy = local.y - crater_center.y;
-            let cdist = (cdx * cdx + cdy * cdy).sqrt();
+            // Distancia sobre la esfera, en lados de celda
+            let cdist = length3(n - crater_center) / cell_size(crater_grid);
 
-            let crater_radius = 0.06 + hash2(Vector2::new(cell.x + 59.0 + i as f32 * 7.0, cell.y + 41.0)) * 0.03;
+            let crater_radius = min_crater_radius * (1.0 + noise.hash(crater_cell, 6) * 0.5);
             let crater_edge = crater_radius * 1.4;
 
             let crater_mask = smoothstep(crater_radius, crater_radius * 0.4, cdist);
@@ -338,78 +371,163 @@ pub fn rocky_planet_vertex_shader(v: &mut Vertex) {
         }
     }
 
-    v.color = saturate_vec3(color);
+    v.color = color;
 }
 
+// Rampas por defecto del gigante gaseoso: bandas de oscuras a claras, y la mancha (de su
+// borde a su centro) de un solo naranja
+const GAS_BANDS_RAMP: [(f32, Vector3); 2] = [
+    (0.0, Vector3 { x: 0.5, y: 0.4, z: 0.3 }),
+    (1.0, Vector3 { x: 0.9, y: 0.8, z: 0.65 }),
+];
+const GAS_SPOT_RAMP: [(f32, Vector3); 1] = [(0.0, Vector3 { x: 1.0, y: 0.6, z: 0.3 })];
+
 // 🪐 Gigante gaseoso: bandas y gran mancha
-pub fn gassy_planet_vertex_shader(v: &mut Vertex) {
+pub fn gassy_planet_vertex_shader(v: &mut Vertex, noise: &Noise, params: &ParamSet, ramps: &RampSet) {
     let n = normalize3(v.normal);
     let uv = spherical_uv(n);
 
     // Capa 1: bandas latitudinales suavizadas
-    let band_freq = 14.0;
+    let band_freq = param(params, "gassy1", "band_freq");
     let base_bands = (uv.y * band_freq).sin() * 0.5 + 0.5; // 0..1
-    let band_light = Vector3::new(0.9, 0.8, 0.65);
-    let band_dark = Vector3::new(0.5, 0.4, 0.3);
-    let mut color = mix_vec3(band_dark, band_light, base_bands);
+    let mut color = ramps.sample("bands", base_bands, &GAS_BANDS_RAMP);
 
     // Capa 2: ruido para romper las bandas perfectas
-    let swirl = fbm(Vector2::new(uv.x * 6.0, uv.y * 10.0));
-    let swirl_intensity = mix(0.8, 1.2, swirl);
+    let swirl = noise.fbm(Vector2::new(uv.x * 6.0, uv.y * 10.0), 0);
+    let swirl_amount = param(params, "gassy1", "swirl");
+    let swirl_intensity = mix(1.0 - swirl_amount, 1.0 + swirl_amount, swirl);
     color = Vector3::new(color.x * swirl_intensity, color.y * swirl_intensity, color.z * swirl_intensity);
 
     // Capa 3: segunda frecuencia de bandas
     let band2 = (uv.y * band_freq * 2.5 + uv.x * 2.0).sin() * 0.5 + 0.5;
-    let extra = mix_vec3(band_dark, band_light, band2);
+    let extra = ramps.sample("bands", band2, &GAS_BANDS_RAMP);
     color = mix_vec3(color, extra, 0.3);
 
     // Capa 4: \"gran mancha\" tipo Júpiter
-    let spot_center = Vector2::new(0.25, 0.55);
+    let spot_center = Vector2::new(noise.hash(Vector2::zero(), 1), 0.4 + 0.2 * noise.hash(Vector2::zero(), 2));
     let dx = uv.x - spot_center.x;
     let dy = uv.y - spot_center.y;
     let dist = (dx * dx + dy * dy).sqrt();
-    let spot_mask = smoothstep(0.22, 0.0, dist); // 1 en el centro, 0 afuera
-    let spot_color = Vector3::new(1.0, 0.6, 0.3);
+    let spot_mask = smoothstep(param(params, "gassy1", "spot_size"), 0.0, dist); // 1 en el centro, 0 afuera
+    let spot_color = ramps.sample("spot", spot_mask, &GAS_SPOT_RAMP);
     color = mix_vec3(color, spot_color, spot_mask * 0.9);
 
-    v.color = saturate_vec3(color);
+    v.color = color;
+}
+
+// 🎲 Planeta generado: una superficie a partir de `PlanetParams`, para definir cuerpos
+// nuevos sólo desde el archivo de escena
+pub fn generated_planet_vertex_shader(v: &mut Vertex, params: &PlanetParams, noise: &Noise) {
+    let n = normalize3(v.normal);
+    let uv = spherical_uv(n);
+    let [low, mid, high] = params.base_palette;
+    let scale = params.noise_scale;
+
+    let mut color = match params.kind {
+        PlanetKind::Gas => {
+            // Bandas de latitud deformadas por remolinos
+            let swirl = noise.fbm(Vector2::new(uv.x * scale * 1.5, uv.y * scale * 4.0), 0);
+            let phase = uv.y * params.band_freq + (swirl - 0.5) * 1.5;
+            let band = (phase * PI).sin() * 0.5 + 0.5;
+            let mut c = mix_vec3(low, mid, band);
+            let streak = noise.fbm(Vector2::new(uv.x * scale * 2.0, uv.y * params.band_freq.max(1.0) * 2.0), 1);
+            // Vetas claras más finas, más marcadas con más nubes
+            c = mix_vec3(c, high, smoothstep(0.55, 0.85, streak) * clamp(params.cloud_amount * 2.0, 0.0, 1.0));
+            c
+        }
+        _ => {
+            // Terreno: tierras bajas, medias y altas según el ruido
+            let terrain = noise.fbm(Vector2::new(uv.x * scale * 2.0, uv.y * scale), 0);
+            let detail = noise.fbm(Vector2::new(uv.x * scale * 8.0, uv.y * scale * 4.0), 1);
+            let height = terrain * 0.8 + detail * 0.2;
+            let mut c = if height < 0.5 {
+                mix_vec3(low, mid, smoothstep(0.2, 0.5, height))
+            } else {
+                mix_vec3(mid, high, smoothstep(0.5, 0.8, height))
+            };
+            // Estratos suaves si hay bandas
+            if params.band_freq > 0.0 {
+                let strata = (uv.y * params.band_freq * PI).sin() * 0.5 + 0.5;
+                c = mix_vec3(c, mid, strata * 0.15);
+            }
+            c
+        }
+    };
+
+    // Grietas: líneas donde un segundo ruido cruza 0.5; brillan en la lava, oscurecen el hielo
+    if matches!(params.kind, PlanetKind::Ice | PlanetKind::Lava) {
+        let field = noise.fbm(Vector2::new(uv.x * scale * 3.0, uv.y * scale * 1.5), 6);
+        let crack = smoothstep(0.04, 0.0, (field - 0.5).abs());
+        color = match params.kind {
+            PlanetKind::Lava => mix_vec3(color, high, crack),
+            _ => mix_vec3(color, low * 0.6, crack * 0.7),
+        };
+    }
+
+    // Cráteres: uno por celda en una fracción de las celdas, con borde claro y fondo oscuro
+    if params.crater_density > 0.0 && params.kind != PlanetKind::Gas {
+        let cell = equal_area_cell(n, GENERATED_CRATER_GRID);
+        if let Some((center, radius)) = generated_crater(cell, params.crater_density, noise) {
+            let d = length3(n - center) / radius;
+            let floor = smoothstep(1.0, 0.7, d);
+            let rim = smoothstep(0.7, 0.95, d) * smoothstep(1.2, 0.95, d);
+            color = mix_vec3(color, low * 0.7, floor * 0.6);
+            color = mix_vec3(color, high, rim * 0.35);
+        }
+    }
+
+    // Nubes blancas sobre todo lo demás
+    if params.cloud_amount > 0.0 && params.kind != PlanetKind::Gas {
+        let clouds = noise.fbm(Vector2::new(uv.x * scale * 1.5, uv.y * scale * 3.0), 7);
+        let cover = smoothstep(1.0 - params.cloud_amount, 1.0 - params.cloud_amount * 0.5, clouds);
+        color = mix_vec3(color, Vector3::new(0.95, 0.95, 0.97), cover * 0.85);
+    }
+
+    v.color = color;
+}
+
+// ❔ Cuerpo sin descubrir: esfera gris apagada, sin detalle
+pub fn placeholder_vertex_shader(v: &mut Vertex) {
+    v.color = Vector3::new(0.3, 0.3, 0.32);
 }
 
 // 🌑 Luna: gris con cráteres
-pub fn moon_vertex_shader(v: &mut Vertex) {
+pub fn moon_vertex_shader(v: &mut Vertex, noise: &Noise, params: &ParamSet) {
     let n = normalize3(v.normal);
     let uv = spherical_uv(n);
 
-    let rough = fbm(Vector2::new(uv.x * 6.0, uv.y * 6.0));
+    let rough = noise.fbm(Vector2::new(uv.x * 6.0, uv.y * 6.0), 0);
 
     let base_grey = Vector3::new(0.7, 0.7, 0.7);
     let dark_grey = Vector3::new(0.3, 0.3, 0.35);
     let mut color = mix_vec3(dark_grey, base_grey, rough);
 
-    // Celdas para cráteres
-    let cell = Vector2::new((uv.x * 16.0).floor(), (uv.y * 8.0).floor());
-    let crater_noise = hash2(cell);
+    // Celdas para cráteres, de igual área para que los polos no queden salpicados
+    let cell = equal_area_cell(n, Vector2::new(16.0, 8.0));
+    let crater_noise = noise.hash(cell, 1);
 
-    if crater_noise > 0.8 {
+    if crater_noise > param(params, "moon", "deep_threshold") {
         // Cráter profundo
         color = mix_vec3(color, dark_grey, 0.8);
-    } else if crater_noise > 0.65 {
+    } else if crater_noise > param(params, "moon", "crater_threshold") {
         // Cráter más suave
         color = mix_vec3(color, dark_grey, 0.5);
     }
 
-    v.color = saturate_vec3(color);
+    v.color = color;
 }
 
 // 💿 Anillo: disco con bandas concéntricas
-pub fn ring_vertex_shader(v: &mut Vertex) {
+pub fn ring_vertex_shader(v: &mut Vertex, inner_radius: f32, outer_radius: f32) {
     // Suponemos que el anillo está en el plano XZ centrado en el origen en espacio modelo.
     let x = v.position.x;
     let z = v.position.z;
     let r = (x * x + z * z).sqrt();
 
-    // Normalizar radio aproximado a 0..1 con una constante (ajusta si tu modelo es distinto)
-    let t = clamp(r * 0.02, 0.0, 1.0);
+    // Coordenada radial: 0 en el borde interior, 1 en el exterior.
+    // Se guarda en tex_coords.x para que el fragment shader la use.
+    let t = clamp((r - inner_radius) / (outer_radius - inner_radius).max(1e-4), 0.0, 1.0);
+    v.tex_coords.x = t;
 
     let base_inner = Vector3::new(0.95, 0.9, 0.8);
     let base_outer = Vector3::new(0.6, 0.55, 0.5);
@@ -431,30 +549,82 @@ pub fn ring_vertex_shader(v: &mut Vertex) {
     let angle_noise = (angle * 10.0).sin() * 0.5 + 0.5;
     color = mix_vec3(color, bright, angle_noise * 0.15);
 
-    v.color = saturate_vec3(color);
+    v.color = color;
 }
 
+/// Máscaras de la tierra en [0, 1] (0 = nada, 1 = total), compartidas por el vertex
+/// shader y el brillo especular del fragment stage para que coincidan.
+pub struct EarthMasks {
+    pub land: f32,
+    pub ice: f32,
+    pub clouds: f32,
+}
+
+pub fn earth_masks(uv: Vector2, noise: &Noise, params: &ParamSet) -> EarthMasks {
+    // Ruido de baja frecuencia para dibujar "continentes", con transición suave alrededor del nivel del mar
+    let continents = noise.fbm(Vector2::new(uv.x * 3.0, uv.y * 3.0), 0);
+    let sea_level = param(params, "earth", "sea_level");
+    let land = smoothstep(sea_level, sea_level + 0.05, continents);
+
+    // |y| de la normal, a partir de la latitud: grande en los polos
+    let pole_factor = ((0.5 - uv.y) * PI).sin().abs();
+    let ice_line = param(params, "earth", "ice_line");
+    let ice = smoothstep(ice_line, ice_line + 0.25, pole_factor);
+
+    // Ruido más de alta frecuencia para nubes
+    let cloud_noise = noise.fbm(Vector2::new(uv.x * 12.0, uv.y * 12.0), 1);
+    let cloud_threshold = param(params, "earth", "cloud_threshold");
+    let clouds = smoothstep(cloud_threshold, cloud_threshold + 0.18, cloud_noise);
+
+    EarthMasks { land, ice, clouds }
+}
+
+// Colores base de tierra
+const TROPICAL: Vector3 = Vector3 { x: 0.02, y: 0.35, z: 0.05 };  // verde muy saturado
+const TEMPERATE: Vector3 = Vector3 { x: 0.15, y: 0.40, z: 0.10 }; // verde más suave
+const TUNDRA: Vector3 = Vector3 { x: 0.60, y: 0.60, z: 0.55 };    // grisáceo / rocoso
+// Selva con un 40% de arena: la variante ecuatorial
+const TROPICAL_DESERT: Vector3 = Vector3 { x: 0.312, y: 0.47, z: 0.19 };
+
+// Rampas por defecto de la tierra. Latitud (0 en el ecuador, 1 en los polos) → clima,
+// en bandas de bordes duros; muy cerca de los polos la nieve domina en otra capa
+const EARTH_CLIMATE_RAMP: [(f32, Vector3); 6] = [
+    (0.0, TROPICAL),
+    (0.25, TROPICAL),
+    (0.25, TEMPERATE),
+    (0.80, TEMPERATE),
+    (0.80, TUNDRA),
+    (1.0, TUNDRA),
+];
+const EARTH_CLIMATE_VARIANT_RAMP: [(f32, Vector3); 6] = [
+    (0.0, TROPICAL_DESERT),
+    (0.25, TROPICAL_DESERT),
+    (0.25, TROPICAL),
+    (0.55, TROPICAL),
+    (0.55, TUNDRA),
+    (1.0, TUNDRA),
+];
+const EARTH_OCEAN_RAMP: [(f32, Vector3); 2] = [
+    (0.0, Vector3 { x: 0.02, y: 0.08, z: 0.25 }), // azul profundo
+    (1.0, Vector3 { x: 0.00, y: 0.35, z: 0.60 }), // azul más claro / turquesa
+];
+
 // 🌍 Planeta Tierra: océanos, continentes, desiertos, polos de hielo y nubes
-pub fn earth_planet_vertex_shader(v: &mut Vertex){
+pub fn earth_planet_vertex_shader(v: &mut Vertex, noise: &Noise, params: &ParamSet, ramps: &RampSet) {
     let n = normalize3(v.normal);
     let uv = spherical_uv(n); // uv.x = longitud, uv.y = latitud mapeada
 
     // ------------------------
     // Capa 1: Océanos
     // ------------------------
-    let ocean_noise = fbm(Vector2::new(uv.x * 8.0, uv.y * 8.0)); // detalle fino
-    let ocean_deep   = Vector3::new(0.02, 0.08, 0.25); // azul profundo
-    let ocean_shallow= Vector3::new(0.00, 0.35, 0.60); // azul más claro / turquesa
-    let mut base_color = mix_vec3(ocean_deep, ocean_shallow, ocean_noise);
+    let ocean_noise = noise.fbm(Vector2::new(uv.x * 8.0, uv.y * 8.0), 2); // detalle fino
+    let mut base_color = ramps.sample("ocean", ocean_noise, &EARTH_OCEAN_RAMP);
 
     // ------------------------
     // Capa 2: Continentes (máscara de tierra)
     // ------------------------
-    // Ruido de baja frecuencia para dibujar "continentes"
-    let continents = fbm(Vector2::new(uv.x * 3.0 + 10.0, uv.y * 3.0 + 5.0));
-
-    // Hacer una transición suave alrededor del umbral
-    let land_mask = smoothstep(0.50, 0.55, continents); // 0 = agua, 1 = tierra
+    let masks = earth_masks(uv, noise, params);
+    let land_mask = masks.land; // 0 = agua, 1 = tierra
 
     // ------------------------
     // Capa 3: Tipos de terreno (selva, zonas templadas, desierto)
@@ -463,27 +633,19 @@ pub fn earth_planet_vertex_shader(v: &mut Vertex){
     let lat = (uv.y - 0.5).abs() * 2.0; 
     let lat_clamped = clamp(lat, 0.0, 1.0);
 
-    // Colores base de tierra
-    let tropical     = Vector3::new(0.02, 0.35, 0.05);  // verde muy saturado
-    let temperate    = Vector3::new(0.15, 0.40, 0.10);  // verde más suave
-    let desert       = Vector3::new(0.75, 0.65, 0.40);  // arena
-    let tundra       = Vector3::new(0.60, 0.60, 0.55);  // grisáceo / rocoso
-
-    // Bandas climáticas aproximadas según latitud
-    let land_color = if lat_clamped < 0.25 {
-        // Zona ecuatorial: mezcla selva + algo de desierto
-        let mix_desert = fbm(Vector2::new(uv.x * 6.0, uv.y * 6.0));
-        mix_vec3(tropical, desert, mix_desert * 0.4)
+    // Bandas climáticas según latitud: cada punto mezcla el clima de su banda con su
+    // variante (selva con desierto, templado con selva, templado con tundra) según el
+    // ruido propio de la banda
+    let climate = ramps.sample("climate", lat_clamped, &EARTH_CLIMATE_RAMP);
+    let variant = ramps.sample("climate_variant", lat_clamped, &EARTH_CLIMATE_VARIANT_RAMP);
+    let variation = if lat_clamped < 0.25 {
+        noise.fbm(Vector2::new(uv.x * 6.0, uv.y * 6.0), 3)
     } else if lat_clamped < 0.55 {
-        // Zonas templadas
-        mix_vec3(temperate, tropical, fbm(Vector2::new(uv.x * 4.0, uv.y * 4.0)))
-    } else if lat_clamped < 0.80 {
-        // Transición a tundra
-        mix_vec3(temperate, tundra, fbm(Vector2::new(uv.x * 4.0, uv.y * 8.0)))
+        noise.fbm(Vector2::new(uv.x * 4.0, uv.y * 4.0), 4)
     } else {
-        // Muy cercano a los polos, dejamos que la nieve domine en la siguiente capa
-        tundra
+        noise.fbm(Vector2::new(uv.x * 4.0, uv.y * 8.0), 5)
     };
+    let land_color = mix_vec3(climate, variant, variation);
 
     // Mezclar océanos y tierra según land_mask
     base_color = mix_vec3(base_color, land_color, land_mask);
@@ -491,8 +653,7 @@ pub fn earth_planet_vertex_shader(v: &mut Vertex){
     // ------------------------
     // Capa 4: Polos de hielo
     // ------------------------
-    let pole_factor = clamp(n.y.abs(), 0.0, 1.0); // |y| grande en los polos
-    let ice_mask = smoothstep(0.55, 0.80, pole_factor); // 0 = sin hielo, 1 = hielo sólido
+    let ice_mask = masks.ice; // 0 = sin hielo, 1 = hielo sólido
     let ice_color = Vector3::new(0.95, 0.98, 1.0);
 
     base_color = mix_vec3(base_color, ice_color, ice_mask);
@@ -500,9 +661,7 @@ pub fn earth_planet_vertex_shader(v: &mut Vertex){
     // ------------------------
     // Capa 5: Nubes
     // ------------------------
-    // Ruido más de alta frecuencia para nubes
-    let cloud_noise = fbm(Vector2::new(uv.x * 12.0 + 20.0, uv.y * 12.0 + 30.0));
-    let cloud_mask = smoothstep(0.70, 0.88, cloud_noise); // zonas donde hay nubes
+    let cloud_mask = masks.clouds; // zonas donde hay nubes
 
     let cloud_color = Vector3::new(1.0, 1.0, 1.0);
     // Mezclar nubes con el color base (las nubes se ven como velos blancos)
@@ -519,12 +678,12 @@ pub fn earth_planet_vertex_shader(v: &mut Vertex){
         base_color.z * brightness,
     );
 
-    v.color = saturate_vec3(final_color);
+    v.color = final_color;
 }
 
 
 // 🚀 Shuttle shader: mint hull with dark accents and light-grey panels
-pub fn shuttle_vertex_shader(v: &mut Vertex) {
+pub fn shuttle_vertex_shader(v: &mut Vertex, noise: &Noise) {
     let n = normalize3(v.normal);
     let uv = spherical_uv(n);
 
@@ -576,12 +735,12 @@ pub fn shuttle_vertex_shader(v: &mut Vertex) {
     // ------------------------
     // Capa 4: "ventanas" o detalles oscuros
     // ------------------------
-    let window_seed = hash2(cell);
+    let window_seed = noise.hash(cell, 0);
 
     if window_seed > 0.55 && underside < 0.4 {
         // Posición pseudo-aleatoria de una ventana en la celda
-        let w_cx = 0.25 + hash2(Vector2::new(cell.x + 13.0, cell.y + 5.0)) * 0.5;
-        let w_cy = 0.35 + hash2(Vector2::new(cell.x + 31.0, cell.y + 9.0)) * 0.25;
+        let w_cx = 0.25 + noise.hash(cell, 1) * 0.5;
+        let w_cy = 0.35 + noise.hash(cell, 2) * 0.25;
 
         let dx = local.x - w_cx;
         let dy = local.y - w_cy;
@@ -600,5 +759,67 @@ pub fn shuttle_vertex_shader(v: &mut Vertex) {
     let brightness = mix(0.85, 1.10, facing);
     color = Vector3::new(color.x * brightness, color.y * brightness, color.z * brightness);
 
-    v.color = saturate_vec3(color);
-}
\ No newline at end of file
+    v.color = color;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn equal_area_round_trips() {
+        for n in [Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.3, 0.8, -0.5), Vector3::new(-0.2, -0.9, 0.1)] {
+            let back = equal_area_dir(equal_area_uv(n));
+            assert!((back - n.normalized()).length() < 1e-4, "{:?} -> {:?}", n, back);
+        }
+    }
+
+    #[test]
+    fn craters_are_as_dense_at_the_poles_as_at_the_equator() {
+        // Two bands of the same area: |sin(lat)| > 0.7 (polar caps) and |sin(lat)| < 0.3
+        let (mut polar, mut equatorial) = (0, 0);
+        for seed in 0..60 {
+            let noise = Noise::new(seed);
+            for row in 0..GENERATED_CRATER_GRID.y as i32 {
+                for col in 0..GENERATED_CRATER_GRID.x as i32 {
+                    let cell = Vector2::new(col as f32, row as f32);
+                    if let Some((center, _)) = generated_crater(cell, 0.5, &noise) {
+                        match center.y.abs() {
+                            y if y > 0.7 => polar += 1,
+                            y if y < 0.3 => equatorial += 1,
+                            _ => {}
+                        }
+                    }
+                }
+            }
+        }
+        let ratio = polar as f32 / equatorial as f32;
+        assert!(equatorial > 1000 && (ratio - 1.0).abs() < 0.1, "polar {} vs equatorial {}", polar, equatorial);
+    }
+
+    #[test]
+    fn earth_masks_follow_their_params() {
+        let noise = Noise::new(3);
+        let totals = |params: &ParamSet| {
+            let (mut land, mut ice, mut clouds) = (0.0, 0.0, 0.0);
+            for i in 0..64 {
+                for j in 0..32 {
+                    let masks = earth_masks(Vector2::new((i as f32 + 0.5) / 64.0, (j as f32 + 0.5) / 32.0), &noise, params);
+                    land += masks.land;
+                    ice += masks.ice;
+                    clouds += masks.clouds;
+                }
+            }
+            (land, ice, clouds)
+        };
+        let (land, ice, clouds) = totals(&ParamSet::default());
+        let mut raised = ParamSet::default();
+        raised.set("sea_level", 0.55);
+        raised.set("ice_line", 0.7);
+        raised.set("cloud_threshold", 0.8);
+        let (less_land, less_ice, fewer_clouds) = totals(&raised);
+        assert!(less_land < land * 0.9, "land {} -> {}", land, less_land);
+        assert!(less_ice < ice * 0.9, "ice {} -> {}", ice, less_ice);
+        assert!(fewer_clouds < clouds * 0.9, "clouds {} -> {}", clouds, fewer_clouds);
+    }
+}
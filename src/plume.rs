@@ -0,0 +1,105 @@
+#![allow(dead_code)]
+
+use raylib::prelude::*;
+
+use crate::fragment::Fragment;
+use crate::framebuffer::Framebuffer;
+use crate::light::Light;
+use crate::shaders::vertex_shader;
+use crate::triangle::triangle_into;
+use crate::vertex::Vertex;
+use crate::Uniforms;
+
+/// Engine nozzles in shuttle-local space (the nose points down -Z, so they face +Z).
+pub const ENGINE_OFFSET: Vector3 = Vector3 { x: 0.0, y: 0.35, z: 2.2 };
+
+const QUADS: usize = 10;
+const MAX_LENGTH: f32 = 3.5;
+const NOZZLE_RADIUS: f32 = 0.45;
+
+fn hash1(n: f32) -> f32 {
+    (n.sin() * 43758.547).fract().abs()
+}
+
+/// Smooth 1D value noise in [0, 1], for flicker.
+fn flicker(t: f32) -> f32 {
+    let i = t.floor();
+    let f = t - i;
+    let u = f * f * (3.0 - 2.0 * f);
+    hash1(i) * (1.0 - u) + hash1(i + 1.0) * u
+}
+
+/// Builds the exhaust plume in shuttle-local space: a cone of camera-facing quads behind
+/// the engines, longer and brighter with more thrust. Each quad is a fan around a bright
+/// bluish core that fades to transparent (black, since the plume is added) orange edges.
+/// `eye_local` is the camera position in shuttle-local space. Empty when there is no thrust.
+pub fn plume_vertices(throttle: f32, time: f32, eye_local: Vector3) -> Vec<Vertex> {
+    let thrust = throttle.clamp(0.0, 1.0);
+    if thrust <= 0.0 {
+        return Vec::new();
+    }
+
+    let flick = 0.8 + 0.4 * flicker(time * 25.0);
+    let length = MAX_LENGTH * thrust * flick;
+    let brightness = (0.4 + 0.6 * thrust) * flick;
+
+    let core = Vector3::new(0.55, 0.75, 1.0);
+    let edge = Vector3::new(1.0, 0.45, 0.1);
+
+    let mut vertices = Vec::with_capacity(QUADS * 12);
+    for i in 0..QUADS {
+        let t = (i as f32 + 0.5) / QUADS as f32;
+        let center = ENGINE_OFFSET + Vector3::new(0.0, 0.0, t * length);
+
+        // Narrows towards the tip, with a little per-quad jitter
+        let jitter = 0.85 + 0.3 * flicker(time * 40.0 + i as f32 * 7.3);
+        let size = NOZZLE_RADIUS * (1.0 - t * 0.7) * jitter;
+
+        // Bluish core near the nozzle turning orange and fading along the plume
+        let fade = (1.0 - t) * brightness / QUADS as f32 * 4.0;
+        let center_color = lerp(core, edge, t) * fade;
+        let edge_color = edge * (fade * 0.15);
+
+        // Billboard axes facing the camera
+        let to_eye = (eye_local - center).normalized();
+        let reference = if to_eye.y.abs() > 0.9 { Vector3::new(1.0, 0.0, 0.0) } else { Vector3::new(0.0, 1.0, 0.0) };
+        let right = reference.cross(to_eye).normalized() * size;
+        let up = to_eye.cross(right).normalized() * size;
+
+        let corners = [center - right - up, center + right - up, center + right + up, center - right + up];
+        for k in 0..4 {
+            vertices.push(Vertex::new_with_color(center, center_color));
+            vertices.push(Vertex::new_with_color(corners[k], edge_color));
+            vertices.push(Vertex::new_with_color(corners[(k + 1) % 4], edge_color));
+        }
+    }
+    vertices
+}
+
+fn lerp(a: Vector3, b: Vector3, t: f32) -> Vector3 {
+    a + (b - a) * t
+}
+
+/// Draws the plume with the shuttle's model matrix in `uniforms`: depth-tested against
+/// the scene but not writing depth, and added on top of what's already there.
+pub fn draw_plume(framebuffer: &mut Framebuffer, uniforms: &Uniforms, light: &Light, throttle: f32, eye: Vector3) {
+    let inverse = uniforms.object.model.inverted();
+    let eye_local = Vector3::new(
+        inverse.m0 * eye.x + inverse.m4 * eye.y + inverse.m8 * eye.z + inverse.m12,
+        inverse.m1 * eye.x + inverse.m5 * eye.y + inverse.m9 * eye.z + inverse.m13,
+        inverse.m2 * eye.x + inverse.m6 * eye.y + inverse.m10 * eye.z + inverse.m14,
+    );
+
+    let vertices: Vec<Vertex> = plume_vertices(throttle, uniforms.frame.time, eye_local)
+        .iter()
+        .map(|v| vertex_shader(v, uniforms))
+        .collect();
+
+    let mut fragments: Vec<Fragment> = Vec::new();
+    for tri in vertices.chunks_exact(3) {
+        triangle_into(&tri[0], &tri[1], &tri[2], light, None, (framebuffer.width, framebuffer.height), &mut fragments);
+    }
+    for fragment in &fragments {
+        framebuffer.add_point(fragment.position.x as i32, fragment.position.y as i32, fragment.color, fragment.depth);
+    }
+}
@@ -0,0 +1,115 @@
+#![allow(dead_code)]
+
+use crate::impostor::IMPOSTOR_MAX_RADIUS_PX;
+use crate::sphere::RAYCAST_MAX_RADIUS_PX;
+
+/// On-screen radii (in pixels, before `lod_bias`) below which a sphere body is drawn
+/// from a cached sprite, or ray-cast, instead of rasterized from its mesh.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LodThresholds {
+    pub impostor_px: f32,
+    pub raycast_px: f32,
+}
+
+impl Default for LodThresholds {
+    fn default() -> Self {
+        LodThresholds { impostor_px: IMPOSTOR_MAX_RADIUS_PX, raycast_px: RAYCAST_MAX_RADIUS_PX }
+    }
+}
+
+/// One step of the quality ladder.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QualityLevel {
+    pub render_scale: f32, // Internal 3D resolution relative to the window
+    pub skybox_step: u32,  // The skybox is sampled once per step×step pixel block
+    pub fbm_octaves: u32,  // Octaves used by the procedural noise in the planet shaders
+    pub lod_bias: f32,     // Multiplies the on-screen size below which bodies use impostors / ray casting
+    pub centroid_color: bool, // Evaluates vertex colors again at triangle centroids
+}
+
+/// From best (index 0) to cheapest.
+pub const QUALITY_LEVELS: [QualityLevel; 5] = [
+    QualityLevel { render_scale: 1.0, skybox_step: 1, fbm_octaves: 4, lod_bias: 1.0, centroid_color: true },
+    QualityLevel { render_scale: 0.85, skybox_step: 1, fbm_octaves: 4, lod_bias: 1.25, centroid_color: true },
+    QualityLevel { render_scale: 0.75, skybox_step: 2, fbm_octaves: 3, lod_bias: 1.5, centroid_color: true },
+    QualityLevel { render_scale: 0.6, skybox_step: 2, fbm_octaves: 3, lod_bias: 2.0, centroid_color: false },
+    QualityLevel { render_scale: 0.5, skybox_step: 3, fbm_octaves: 2, lod_bias: 2.5, centroid_color: false },
+];
+
+/// Picks a quality level each frame to keep the frame's work under the target time.
+/// Drops quickly when over budget and only climbs back after a sustained stretch of
+/// cheap frames, so it doesn't oscillate between two levels.
+pub struct QualityController {
+    pub enabled: bool,
+    pub target_ms: f32,
+    pub level: usize,
+    average_ms: f32, // Exponential moving average of the work time
+    over_budget: f32, // Seconds spent continuously over budget
+    under_budget: f32, // Seconds spent continuously well under budget
+    cooldown: f32,   // Seconds before the next change is allowed
+}
+
+impl QualityController {
+    pub fn new(target_fps: f32) -> Self {
+        QualityController {
+            enabled: true,
+            target_ms: 1000.0 / target_fps,
+            level: 0,
+            average_ms: 0.0,
+            over_budget: 0.0,
+            under_budget: 0.0,
+            cooldown: 0.0,
+        }
+    }
+
+    pub fn current(&self) -> QualityLevel {
+        QUALITY_LEVELS[self.level]
+    }
+
+    pub fn average_ms(&self) -> f32 {
+        self.average_ms
+    }
+
+    /// Feeds the time the last frame spent working (excluding the frame limiter's wait)
+    /// and `dt`, the wall-clock length of the frame, both in seconds.
+    pub fn update(&mut self, work: f32, dt: f32) {
+        let work_ms = work * 1000.0;
+        self.average_ms = if self.average_ms == 0.0 {
+            work_ms
+        } else {
+            self.average_ms + (work_ms - self.average_ms) * 0.1
+        };
+
+        if !self.enabled {
+            return;
+        }
+
+        self.cooldown = (self.cooldown - dt).max(0.0);
+        if self.average_ms > self.target_ms * 0.9 {
+            self.over_budget += dt;
+            self.under_budget = 0.0;
+        } else if self.average_ms < self.target_ms * 0.5 {
+            self.under_budget += dt;
+            self.over_budget = 0.0;
+        } else {
+            self.over_budget = 0.0;
+            self.under_budget = 0.0;
+        }
+
+        if self.cooldown > 0.0 {
+            return;
+        }
+        if self.over_budget > 0.5 && self.level + 1 < QUALITY_LEVELS.len() {
+            self.change_level(self.level + 1);
+        } else if self.under_budget > 3.0 && self.level > 0 {
+            self.change_level(self.level - 1);
+        }
+    }
+
+    fn change_level(&mut self, level: usize) {
+        self.level = level;
+        self.over_budget = 0.0;
+        self.under_budget = 0.0;
+        self.cooldown = 1.0;
+    }
+}
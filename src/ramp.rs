@@ -0,0 +1,137 @@
+#![allow(dead_code)]
+
+use std::collections::BTreeMap;
+
+use raylib::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A color gradient: stops of (t, color) sorted by t, linearly interpolated between and
+/// held flat past the first and last. Two stops at the same t make a hard edge. In the
+/// scene file it is a list of `[t, r, g, b]`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(from = "Vec<[f32; 4]>", into = "Vec<[f32; 4]>")]
+pub struct ColorRamp {
+    stops: Vec<(f32, Vector3)>,
+}
+
+impl ColorRamp {
+    pub fn new(stops: &[(f32, Vector3)]) -> Self {
+        let mut stops = stops.to_vec();
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        ColorRamp { stops }
+    }
+
+    pub fn stops(&self) -> &[(f32, Vector3)] {
+        &self.stops
+    }
+
+    /// Color at `t`; black for a ramp with no stops.
+    pub fn sample(&self, t: f32) -> Vector3 {
+        sample_stops(&self.stops, t)
+    }
+}
+
+impl From<Vec<[f32; 4]>> for ColorRamp {
+    fn from(stops: Vec<[f32; 4]>) -> Self {
+        let stops: Vec<(f32, Vector3)> = stops.iter().map(|s| (s[0], Vector3::new(s[1], s[2], s[3]))).collect();
+        ColorRamp::new(&stops)
+    }
+}
+
+impl From<ColorRamp> for Vec<[f32; 4]> {
+    fn from(ramp: ColorRamp) -> Self {
+        ramp.stops.iter().map(|(t, c)| [*t, c.x, c.y, c.z]).collect()
+    }
+}
+
+/// Color of sorted `stops` at `t`, as `ColorRamp::sample`. Shaders keep their default
+/// ramps as constant stop lists and sample them through this.
+pub fn sample_stops(stops: &[(f32, Vector3)], t: f32) -> Vector3 {
+    let next = stops.iter().position(|s| s.0 > t).unwrap_or(stops.len());
+    match next {
+        _ if stops.is_empty() => Vector3::zero(),
+        0 => stops[0].1,
+        n if n == stops.len() => stops[n - 1].1,
+        n => {
+            let (a, b) = (stops[n - 1], stops[n]);
+            let f = (t - a.0) / (b.0 - a.0).max(1e-6);
+            a.1 * (1.0 - f) + b.1 * f
+        }
+    }
+}
+
+/// Named ramps from a body's `ramps` table that replace its shader's built-in palettes,
+/// e.g. `ramps = { bands = [[0.0, 0.1, 0.2, 0.6], [1.0, 0.5, 0.7, 0.95]] }`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RampSet(BTreeMap<String, ColorRamp>);
+
+impl RampSet {
+    pub fn get(&self, name: &str) -> Option<&ColorRamp> {
+        self.0.get(name)
+    }
+
+    pub fn set(&mut self, name: &str, ramp: ColorRamp) {
+        self.0.insert(name.to_string(), ramp);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The ramp called `name` at `t`, or the shader's `default` stops when the body
+    /// doesn't set it.
+    pub fn sample(&self, name: &str, t: f32, default: &[(f32, Vector3)]) -> Vector3 {
+        match self.0.get(name) {
+            Some(ramp) => ramp.sample(t),
+            None => sample_stops(default, t),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Vector3, b: Vector3) -> bool {
+        (a - b).length() < 1e-5
+    }
+
+    #[test]
+    fn ramp_interpolates_between_stops() {
+        let ramp = ColorRamp::new(&[
+            (1.0, Vector3::new(0.0, 0.0, 1.0)),
+            (0.0, Vector3::new(1.0, 0.0, 0.0)),
+            (0.5, Vector3::new(0.0, 1.0, 0.0)),
+        ]);
+        assert!(close(ramp.sample(0.0), Vector3::new(1.0, 0.0, 0.0)));
+        assert!(close(ramp.sample(0.25), Vector3::new(0.5, 0.5, 0.0)));
+        assert!(close(ramp.sample(0.5), Vector3::new(0.0, 1.0, 0.0)));
+        assert!(close(ramp.sample(0.875), Vector3::new(0.0, 0.25, 0.75)));
+    }
+
+    #[test]
+    fn ramp_clamps_outside_its_stops() {
+        let ramp = ColorRamp::new(&[(0.2, Vector3::new(0.1, 0.2, 0.3)), (0.8, Vector3::new(0.9, 0.8, 0.7))]);
+        assert!(close(ramp.sample(-5.0), Vector3::new(0.1, 0.2, 0.3)));
+        assert!(close(ramp.sample(0.0), Vector3::new(0.1, 0.2, 0.3)));
+        assert!(close(ramp.sample(1.0), Vector3::new(0.9, 0.8, 0.7)));
+        assert!(close(ramp.sample(f32::INFINITY), Vector3::new(0.9, 0.8, 0.7)));
+        assert!(close(ColorRamp::default().sample(0.5), Vector3::zero()));
+    }
+
+    #[test]
+    fn repeated_stop_makes_a_hard_edge() {
+        let stops = [(0.0, Vector3::zero()), (0.5, Vector3::zero()), (0.5, Vector3::one()), (1.0, Vector3::one())];
+        assert!(close(sample_stops(&stops, 0.499), Vector3::zero()));
+        assert!(close(sample_stops(&stops, 0.5), Vector3::one()));
+    }
+
+    #[test]
+    fn ramp_reads_stops_from_the_scene_file() {
+        let ramps: RampSet = toml::from_str("bands = [[1.0, 0.5, 0.7, 0.95], [0.0, 0.1, 0.2, 0.6]]").unwrap();
+        let bands = ramps.get("bands").expect("bands is set");
+        assert!(close(bands.sample(0.5), Vector3::new(0.3, 0.45, 0.775)));
+        assert!(close(ramps.sample("spot", 0.5, &[(0.0, Vector3::one())]), Vector3::one()));
+    }
+}
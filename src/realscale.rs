@@ -0,0 +1,301 @@
+#![allow(dead_code)]
+
+use std::collections::BTreeMap;
+use std::fs;
+
+use raylib::prelude::*;
+use serde::Deserialize;
+
+use crate::depthrange::{DepthMode, DepthRangeConfig};
+use crate::error::AppError;
+use crate::input::InputState;
+use crate::quality::LodThresholds;
+use crate::scene::{update_scene, SceneObject};
+
+/// Key that switches between the artistic layout and true proportions.
+pub const REAL_SCALE_KEY: KeyboardKey = KeyboardKey::KEY_Z;
+/// Outside `assets/scenes`, so the scene menu doesn't offer it as a scene.
+pub const REAL_SCALE_PATH: &str = "assets/real_scale.toml";
+/// Closest the camera zooms to its target at true scale, where the earth is 0.0015 across.
+pub const MIN_CAMERA_DISTANCE: f32 = 2e-4;
+
+/// The near plane may come this close relative to the smallest body's radius.
+const NEAR_PER_RADIUS: f32 = 0.05;
+/// Room past the farthest orbit for the far plane.
+const FAR_MARGIN: f32 = 1.25;
+/// How far outside the `from` body a preset without a distance puts the eye, in its radii.
+const OUTSIDE: f32 = 1.5;
+
+/// What a body's radius and its distance from its parent are multiplied by.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Multipliers {
+    pub radius: f32,
+    pub distance: f32,
+}
+
+impl Default for Multipliers {
+    fn default() -> Self {
+        Multipliers { radius: 1.0, distance: 1.0 }
+    }
+}
+
+/// A camera view of the true-scale system, recalled like a bookmark. The eye looks at
+/// `target` from `distance` of its radii away, along `direction`, or from the side of
+/// `from` (just outside it when there's no distance).
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct Preset {
+    pub name: String,
+    pub target: String,
+    #[serde(default)]
+    pub from: Option<String>,
+    #[serde(default)]
+    pub direction: Option<[f32; 3]>,
+    #[serde(default)]
+    pub distance: Option<f32>,
+}
+
+/// `real_scale.toml`: multipliers by body name, a default for bodies it doesn't list,
+/// and the presets.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct RealScaleFile {
+    pub default: Multipliers,
+    pub bodies: BTreeMap<String, Multipliers>,
+    pub presets: Vec<Preset>,
+}
+
+/// What a body looked like before the switch, to put it back exactly.
+struct Original {
+    name: String,
+    scale: f32,
+    semi_major_axis: Option<f32>,
+    offset: Vector3,
+    visible: bool,
+}
+
+/// True-scale mode (Z): bodies take their real radii and orbital distances relative to
+/// each other, so the sun is 109 earths across and the earth sits 215 solar radii out.
+/// The depth range is widened to match and switched to reverse-Z, and the impostor
+/// sprites are turned off; switching back restores the scene's own layout.
+#[derive(Default)]
+pub struct RealScale {
+    pub active: bool,
+    pub file: RealScaleFile,
+    originals: Vec<Original>,
+    depth: Option<DepthRangeConfig>,
+    lod: Option<LodThresholds>,
+}
+
+impl RealScale {
+    pub fn load(path: &str) -> Result<Self, AppError> {
+        let text = fs::read_to_string(path).map_err(|_| AppError::AssetMissing { path: path.to_string() })?;
+        let file = toml::from_str(&text).map_err(|e| AppError::SceneConfig { msg: format!("{}: {}", path, e) })?;
+        Ok(RealScale { file, ..RealScale::default() })
+    }
+
+    /// Z switches the mode; returns whether it did.
+    pub fn process_input(&mut self, input: &InputState, objects: &mut [SceneObject], depth: &mut DepthRangeConfig, lod: &mut LodThresholds) -> bool {
+        if !input.is_key_pressed(REAL_SCALE_KEY) {
+            return false;
+        }
+        if self.active {
+            self.disable(objects, depth, lod);
+        } else {
+            self.enable(objects, depth, lod);
+        }
+        true
+    }
+
+    fn multipliers(&self, obj: &SceneObject, objects: &[SceneObject]) -> Multipliers {
+        if let Some(multipliers) = self.file.bodies.get(&obj.name) {
+            return *multipliers;
+        }
+        // Rings grow with their planet and stay centered on it
+        if obj.object_type == "ring"
+            && let Some(parent) = obj.parent.as_ref().and_then(|name| objects.iter().find(|o| &o.name == name))
+        {
+            return Multipliers { radius: self.multipliers(parent, objects).radius, distance: 1.0 };
+        }
+        self.file.default
+    }
+
+    pub fn enable(&mut self, objects: &mut [SceneObject], depth: &mut DepthRangeConfig, lod: &mut LodThresholds) {
+        if self.active {
+            return;
+        }
+        let multipliers: Vec<Multipliers> = objects.iter().map(|o| self.multipliers(o, objects)).collect();
+        self.originals = objects
+            .iter()
+            .map(|o| Original {
+                name: o.name.clone(),
+                scale: o.scale,
+                semi_major_axis: o.orbit.as_ref().map(|orbit| orbit.semi_major_axis),
+                offset: o.offset,
+                visible: o.flags.visible,
+            })
+            .collect();
+        for (obj, m) in objects.iter_mut().zip(&multipliers) {
+            // The shuttle is parked in front of the camera, and would dwarf every planet
+            if obj.object_type == "shuttle" {
+                obj.flags.visible = false;
+                continue;
+            }
+            obj.scale *= m.radius;
+            obj.offset *= m.distance;
+            if let Some(orbit) = obj.orbit.as_mut() {
+                orbit.semi_major_axis *= m.distance;
+            }
+        }
+        update_scene(objects, 0.0);
+
+        // Planes from close to the smallest body out past the farthest orbit
+        let bodies = || objects.iter().filter(|o| o.object_type != "shuttle" && o.flags.visible);
+        let smallest = bodies().map(|o| o.radius * o.scale).fold(f32::INFINITY, f32::min);
+        // A body never gets farther than its orbit's major axis from where it is now
+        let reach = |o: &SceneObject| o.translation.length() + o.orbit.as_ref().map_or(0.0, |orbit| 2.0 * orbit.semi_major_axis) + o.radius * o.scale;
+        let farthest = bodies().map(reach).fold(0.0, f32::max);
+        self.depth = Some(depth.clone());
+        if smallest.is_finite() {
+            depth.min_near = depth.min_near.min(smallest * NEAR_PER_RADIUS);
+            depth.near = depth.near.min(smallest * NEAR_PER_RADIUS);
+        }
+        depth.max_far = depth.max_far.max(farthest * FAR_MARGIN);
+        depth.far = depth.far.max(farthest * FAR_MARGIN);
+        // Classic depth can't tell surfaces apart over that range
+        depth.mode = DepthMode::Reverse;
+        // Nearly every body is a few pixels or less across now: a sprite snaps it to whole
+        // pixels and goes stale with every zoom step, so those are ray-cast instead
+        self.lod = Some(*lod);
+        lod.impostor_px = 0.0;
+        self.active = true;
+    }
+
+    pub fn disable(&mut self, objects: &mut [SceneObject], depth: &mut DepthRangeConfig, lod: &mut LodThresholds) {
+        if !self.active {
+            return;
+        }
+        for (i, original) in self.originals.iter().enumerate() {
+            // Bodies spawned or removed in the meantime shift the list
+            let index = if objects.get(i).is_some_and(|o| o.name == original.name) {
+                Some(i)
+            } else {
+                objects.iter().position(|o| o.name == original.name)
+            };
+            let Some(obj) = index.map(|i| &mut objects[i]) else {
+                continue;
+            };
+            obj.scale = original.scale;
+            obj.offset = original.offset;
+            obj.flags.visible = original.visible;
+            if let (Some(orbit), Some(a)) = (obj.orbit.as_mut(), original.semi_major_axis) {
+                orbit.semi_major_axis = a;
+            }
+        }
+        self.originals.clear();
+        update_scene(objects, 0.0);
+        if let Some(saved) = self.depth.take() {
+            *depth = saved;
+        }
+        if let Some(saved) = self.lod.take() {
+            *lod = saved;
+        }
+        self.active = false;
+    }
+
+    /// Eye and target of preset `slot` (1 to 9) in the current layout.
+    pub fn preset_pose(&self, slot: u8, objects: &[SceneObject]) -> Result<(Vector3, Vector3), String> {
+        let preset = self.file.presets.get(slot as usize - 1).ok_or_else(|| format!("no true-scale preset {}", slot))?;
+        let find = |name: &str| objects.iter().find(|o| o.name == name).ok_or_else(|| format!("preset '{}': no body named '{}'", preset.name, name));
+        let target = find(&preset.target)?;
+        let (center, radius) = (target.translation, target.radius * target.scale);
+
+        let eye = match (&preset.from, preset.direction) {
+            (Some(from), _) => {
+                let from = find(from)?;
+                let towards = (center - from.translation).normalized();
+                match preset.distance {
+                    Some(distance) => center - towards * distance * radius,
+                    None => from.translation + towards * from.radius * from.scale * OUTSIDE,
+                }
+            }
+            (None, direction) => {
+                let direction = direction.map_or(Vector3::new(0.0, 0.0, 1.0), |d| Vector3::new(d[0], d[1], d[2]).normalized());
+                center + direction * preset.distance.unwrap_or(4.0) * radius
+            }
+        };
+        Ok((eye, center))
+    }
+
+    /// Overlay line while the mode is on.
+    pub fn status(&self) -> Option<String> {
+        self.active.then(|| "True scale (Z to leave): sizes and distances in real proportions, B for preset views".to_string())
+    }
+
+    /// Bookmark mode's line while the mode is on: the presets instead of the bookmarks.
+    pub fn preset_status(&self) -> String {
+        let names: Vec<String> = self.file.presets.iter().take(9).enumerate().map(|(i, p)| format!("{} {}", i + 1, p.name)).collect();
+        format!("True-scale views (B to leave): {}", if names.is_empty() { "none".to_string() } else { names.join("  ") })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::default_scene_objects;
+
+    fn radius(objects: &[SceneObject], name: &str) -> f32 {
+        let obj = objects.iter().find(|o| o.name == name).unwrap();
+        obj.radius * obj.scale
+    }
+
+    fn distance(objects: &[SceneObject], a: &str, b: &str) -> f32 {
+        let find = |name: &str| objects.iter().find(|o| o.name == name).unwrap().translation;
+        (find(a) - find(b)).length()
+    }
+
+    #[test]
+    fn true_scale_follows_real_ratios_and_switches_back() {
+        let mut objects = default_scene_objects();
+        let before: Vec<(f32, Vector3)> = objects.iter().map(|o| (o.scale, o.translation)).collect();
+        let mut depth = DepthRangeConfig::default();
+        let mut lod = LodThresholds::default();
+        let mut real_scale = RealScale::load(REAL_SCALE_PATH).expect("real_scale.toml loads");
+
+        real_scale.enable(&mut objects, &mut depth, &mut lod);
+        let ratio = |a: f32, b: f32, expected: f32| assert!((a / b / expected - 1.0).abs() < 0.02, "{} instead of {}", a / b, expected);
+        ratio(radius(&objects, "sun"), radius(&objects, "earth"), 109.2);
+        ratio(distance(&objects, "earth", "sun"), radius(&objects, "sun"), 215.0);
+        ratio(distance(&objects, "moon", "earth"), radius(&objects, "earth"), 60.3);
+        assert!(depth.max_far >= distance(&objects, "rocky2", "sun"));
+        assert_eq!(depth.mode, DepthMode::Reverse);
+        // Bodies a few pixels across are ray-cast rather than drawn from sprites
+        assert_eq!(lod.impostor_px, 0.0);
+        assert_eq!(lod.raycast_px, LodThresholds::default().raycast_px);
+
+        real_scale.disable(&mut objects, &mut depth, &mut lod);
+        assert_eq!(depth, DepthRangeConfig::default());
+        assert_eq!(lod, LodThresholds::default());
+        for (obj, (scale, translation)) in objects.iter().zip(&before) {
+            assert_eq!(obj.scale, *scale, "{}", obj.name);
+            assert!((obj.translation - *translation).length() < 1e-3, "{} moved", obj.name);
+        }
+    }
+
+    #[test]
+    fn earth_is_a_dot_from_the_sun() {
+        let mut objects = default_scene_objects();
+        let mut real_scale = RealScale::load(REAL_SCALE_PATH).unwrap();
+        real_scale.enable(&mut objects, &mut DepthRangeConfig::default(), &mut LodThresholds::default());
+        let slot = |name: &str| real_scale.file.presets.iter().position(|p| p.name == name).unwrap() as u8 + 1;
+
+        // Radius on a 600 pixel tall window with a 60° field of view
+        let (eye, target) = real_scale.preset_pose(slot("earth from the sun"), &objects).unwrap();
+        let radius_px = radius(&objects, "earth") / (target - eye).length() / (std::f32::consts::PI / 6.0).tan() * 300.0;
+        assert!(radius_px < 0.5, "the earth is {} pixels across", radius_px * 2.0);
+
+        let (eye, target) = real_scale.preset_pose(slot("earth from moon distance"), &objects).unwrap();
+        assert!(((target - eye).length() / radius(&objects, "earth") - 60.3).abs() < 0.01);
+        assert!(real_scale.preset_pose(9, &objects).is_err());
+    }
+}
@@ -0,0 +1,240 @@
+#![allow(dead_code)]
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+
+use raylib::prelude::*;
+
+use crate::error::AppError;
+use crate::input::InputState;
+
+/// First bytes of every replay file.
+const MAGIC: &[u8; 4] = b"RPLY";
+/// Layout of the header and the frames; bumped whenever either changes.
+const FORMAT_VERSION: u16 = 2;
+
+/// Top of a replay: the build that recorded it and the `--scene` and `--seed` the run
+/// was started with, which the replay starts with too.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReplayHeader {
+    pub app_version: String,
+    pub scene: Option<String>,
+    pub seed: Option<u64>,
+}
+
+impl ReplayHeader {
+    fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        let text = |out: &mut dyn Write, s: &str| -> io::Result<()> {
+            out.write_all(&(s.len() as u16).to_le_bytes())?;
+            out.write_all(s.as_bytes())
+        };
+        out.write_all(MAGIC)?;
+        out.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        text(out, &self.app_version)?;
+        out.write_all(&[self.scene.is_some() as u8])?;
+        text(out, self.scene.as_deref().unwrap_or(""))?;
+        out.write_all(&[self.seed.is_some() as u8])?;
+        out.write_all(&self.seed.unwrap_or(0).to_le_bytes())
+    }
+
+    /// Reads and checks a header: files from another format or another build of the
+    /// app are refused, since their frames wouldn't play back the same.
+    fn read(input: &mut impl Read) -> Result<Self, String> {
+        let truncated = |_| "the file ends inside its header".to_string();
+        let mut magic = [0; 4];
+        input.read_exact(&mut magic).map_err(truncated)?;
+        if &magic != MAGIC {
+            return Err("not a replay file".to_string());
+        }
+        let mut u16_bytes = [0; 2];
+        input.read_exact(&mut u16_bytes).map_err(truncated)?;
+        let format = u16::from_le_bytes(u16_bytes);
+        if format != FORMAT_VERSION {
+            return Err(format!("replay format {} can't be read by this build (format {})", format, FORMAT_VERSION));
+        }
+        let mut text = |input: &mut dyn Read| -> Result<String, String> {
+            input.read_exact(&mut u16_bytes).map_err(truncated)?;
+            let mut bytes = vec![0; u16::from_le_bytes(u16_bytes) as usize];
+            input.read_exact(&mut bytes).map_err(truncated)?;
+            String::from_utf8(bytes).map_err(|_| "the header has invalid text".to_string())
+        };
+        let app_version = text(input)?;
+        if app_version != env!("CARGO_PKG_VERSION") {
+            return Err(format!(
+                "recorded with version {} of the app, this is version {}; record it again with this build",
+                app_version,
+                env!("CARGO_PKG_VERSION")
+            ));
+        }
+        let mut flag = [0; 1];
+        input.read_exact(&mut flag).map_err(truncated)?;
+        let scene = text(input)?;
+        let scene = (flag[0] != 0).then_some(scene);
+        input.read_exact(&mut flag).map_err(truncated)?;
+        let mut seed = [0; 8];
+        input.read_exact(&mut seed).map_err(truncated)?;
+        let seed = (flag[0] != 0).then_some(u64::from_le_bytes(seed));
+        Ok(ReplayHeader { app_version, scene, seed })
+    }
+}
+
+/// Where each frame's input comes from: the devices, the devices with every frame
+/// appended to a replay file, or a replay file instead of the devices.
+pub enum InputSource {
+    Live,
+    Record { out: BufWriter<File>, path: String },
+    Replay { input: BufReader<File>, path: String, frames: u64 },
+}
+
+impl InputSource {
+    /// `--record-input <path>` or `--replay <path>`. A replay starts the run it recorded:
+    /// its scene and seed replace `scene` and `seed`.
+    pub fn from_args(args: &[String], scene: &mut Option<String>, seed: &mut Option<u64>) -> Result<Self, AppError> {
+        let value = |flag: &str| args.iter().position(|a| a == flag).map(|i| args.get(i + 1).cloned());
+        let error = |path: &str, msg: String| AppError::Replay { path: path.to_string(), msg };
+        match (value("--record-input"), value("--replay")) {
+            (Some(_), Some(_)) => Err(error("arguments", "--record-input and --replay can't be used together".to_string())),
+            (Some(None), _) | (_, Some(None)) => Err(error("arguments", "--record-input and --replay need a file".to_string())),
+            (Some(Some(path)), None) => {
+                let file = File::create(&path).map_err(|e| error(&path, e.to_string()))?;
+                let mut out = BufWriter::new(file);
+                let header = ReplayHeader { app_version: env!("CARGO_PKG_VERSION").to_string(), scene: scene.clone(), seed: *seed };
+                header.write(&mut out).map_err(|e| error(&path, e.to_string()))?;
+                tracing::info!("recording input to {}", path);
+                Ok(InputSource::Record { out, path })
+            }
+            (None, Some(Some(path))) => {
+                let file = File::open(&path).map_err(|e| error(&path, e.to_string()))?;
+                let mut input = BufReader::new(file);
+                let header = ReplayHeader::read(&mut input).map_err(|msg| error(&path, msg))?;
+                (*scene, *seed) = (header.scene, header.seed);
+                tracing::info!("replaying input from {}", path);
+                Ok(InputSource::Replay { input, path, frames: 0 })
+            }
+            (None, None) => Ok(InputSource::Live),
+        }
+    }
+
+    /// Whether frames are recorded or replayed. The adaptive quality is held still then,
+    /// since it follows how fast the machine is rather than the input.
+    pub fn is_deterministic(&self) -> bool {
+        !matches!(self, InputSource::Live)
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        matches!(self, InputSource::Replay { .. })
+    }
+
+    /// This frame's input. A replay that runs out hands over to the devices, and a
+    /// recording that can't be written stops.
+    pub fn next(&mut self, window: &mut RaylibHandle) -> InputState {
+        self.next_from(|| InputState::capture(window))
+    }
+
+    /// `next`, with `capture` reading the devices.
+    fn next_from(&mut self, mut capture: impl FnMut() -> InputState) -> InputState {
+        match self {
+            InputSource::Live => capture(),
+            InputSource::Record { out, path } => {
+                let state = capture();
+                if let Err(error) = state.write(out).and_then(|_| out.flush()) {
+                    tracing::warn!("stopped recording to {}: {}", path, error);
+                    *self = InputSource::Live;
+                }
+                state
+            }
+            InputSource::Replay { input, path, frames } => match InputState::read(input) {
+                Ok(Some(state)) => {
+                    *frames += 1;
+                    // Keep the devices' queues empty, so nothing pressed during the replay leaks out after it
+                    capture();
+                    state
+                }
+                end => {
+                    match end {
+                        Err(error) => tracing::warn!("replay {} is cut short after {} frames: {}", path, frames, error),
+                        _ => tracing::info!("replay {} finished after {} frames", path, frames),
+                    }
+                    *self = InputSource::Live;
+                    capture()
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header() -> ReplayHeader {
+        ReplayHeader { app_version: env!("CARGO_PKG_VERSION").to_string(), scene: Some("assets/scenes/default.toml".to_string()), seed: Some(42) }
+    }
+
+    #[test]
+    fn header_reads_back_as_written() {
+        let mut bytes = Vec::new();
+        header().write(&mut bytes).unwrap();
+        assert_eq!(ReplayHeader::read(&mut bytes.as_slice()), Ok(header()));
+
+        let bare = ReplayHeader { scene: None, seed: None, ..header() };
+        let mut bytes = Vec::new();
+        bare.write(&mut bytes).unwrap();
+        assert_eq!(ReplayHeader::read(&mut bytes.as_slice()), Ok(bare));
+    }
+
+    #[test]
+    fn replays_from_other_builds_are_refused() {
+        let mut bytes = Vec::new();
+        ReplayHeader { app_version: "0.0.0-other".to_string(), ..header() }.write(&mut bytes).unwrap();
+        let error = ReplayHeader::read(&mut bytes.as_slice()).unwrap_err();
+        assert!(error.contains("0.0.0-other") && error.contains(env!("CARGO_PKG_VERSION")), "{}", error);
+
+        let mut bytes = Vec::new();
+        header().write(&mut bytes).unwrap();
+        bytes[4] = FORMAT_VERSION as u8 + 1;
+        assert!(ReplayHeader::read(&mut bytes.as_slice()).unwrap_err().contains("format"));
+
+        assert_eq!(ReplayHeader::read(&mut &b"PNG..."[..]), Err("not a replay file".to_string()));
+        assert!(ReplayHeader::read(&mut &b"RP"[..]).is_err());
+    }
+
+    #[test]
+    fn a_finished_replay_hands_over_to_the_devices() {
+        let path = std::env::temp_dir().join("solar_system_handover.replay").to_string_lossy().into_owned();
+        let recorded = |dt: f32| {
+            let mut frame = InputState::default();
+            (frame.dt, frame.screen_width, frame.screen_height) = (dt, 800, 600);
+            frame
+        };
+        let mut out = File::create(&path).unwrap();
+        header().write(&mut out).unwrap();
+        recorded(0.1).write(&mut out).unwrap();
+        recorded(0.2).write(&mut out).unwrap();
+        drop(out);
+
+        let (mut scene, mut seed) = (None, None);
+        let args = ["solar_system".to_string(), "--replay".to_string(), path.clone()];
+        let mut source = InputSource::from_args(&args, &mut scene, &mut seed).unwrap();
+        assert_eq!((scene, seed), (header().scene, header().seed));
+
+        // The devices are read every frame but ignored while the replay lasts
+        let mut captures = 0;
+        let mut devices = || {
+            captures += 1;
+            let mut live = InputState::default();
+            live.dt = 1.0;
+            live
+        };
+        assert_eq!(source.next_from(&mut devices).dt, 0.1);
+        assert_eq!(source.next_from(&mut devices).dt, 0.2);
+        assert!(source.is_replaying());
+
+        // Past the last frame they take over, and the main loop pauses on seeing it stop replaying
+        assert_eq!(source.next_from(&mut devices).dt, 1.0);
+        assert!(!source.is_replaying() && !source.is_deterministic());
+        assert_eq!(source.next_from(&mut devices).dt, 1.0);
+        assert_eq!(captures, 4);
+        let _ = std::fs::remove_file(&path);
+    }
+}
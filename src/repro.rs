@@ -0,0 +1,287 @@
+#![allow(dead_code)]
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+
+use raylib::prelude::*;
+
+use crate::camera::Camera;
+use crate::scene::SceneObject;
+use crate::timestep::SimClock;
+
+/// Key that copies the pose line to the clipboard and writes it to `REPRO_PATH`.
+pub const REPRO_KEY: KeyboardKey = KeyboardKey::KEY_INSERT;
+pub const REPRO_PATH: &str = "repro.txt";
+
+/// Version written at the start of the line; a line of another version is refused
+/// rather than half read.
+const VERSION: u32 = 1;
+const FIELDS: [&str; 13] = ["scene", "seed", "t", "speed", "yaw", "pitch", "dist", "target", "fov", "body", "pos", "rot", "scale"];
+/// A restored body further than this from where the pose has it (relative to the camera
+/// distance) is reported, since the scene file has likely changed since.
+const DRIFT: f32 = 1e-3;
+
+/// Where the selected body was and how it was turned.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BodyPose {
+    pub name: String,
+    pub translation: Vector3,
+    pub rotation: Vector3, // Including its spin
+    pub scale: f32,
+}
+
+/// Everything needed to see the same frame again: the scene and seed, the simulated
+/// time and its speed, the orbit camera and the field of view, plus the selected body
+/// as a check that the scene hasn't changed in between. It's written as one line with
+/// no spaces, so it pastes into an issue, a shell or the console as it is:
+///
+/// `v1,scene=assets/scenes/default.toml,seed=0,t=12.5,speed=1,yaw=0.3,pitch=0.2,dist=12,target=1:0:-2,fov=60,body=gassy1,pos=1:0:-2,rot=0:1.5:0,scale=1.8`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pose {
+    pub scene: String,
+    pub seed: u64,
+    pub time: f32,
+    pub time_scale: f32,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub distance: f32,
+    pub target: Vector3,
+    pub fov: f32, // Vertical field of view, in degrees
+    pub body: Option<BodyPose>,
+}
+
+impl Pose {
+    /// The current view. The orbit is taken from the eye and target, so it's right in
+    /// the shuttle and on a surface too.
+    pub fn capture(scene: &str, seed: u64, camera: &Camera, fov: f32, clock: &SimClock, body: Option<&SceneObject>) -> Self {
+        let direction = camera.eye - camera.target;
+        let distance = direction.length().max(0.001);
+        Pose {
+            scene: scene.to_string(),
+            seed,
+            time: clock.render_time,
+            time_scale: clock.time_scale,
+            yaw: direction.z.atan2(direction.x),
+            pitch: (direction.y / distance).clamp(-1.0, 1.0).asin(),
+            distance,
+            target: camera.target,
+            fov: fov.to_degrees(),
+            body: body.map(|o| BodyPose { name: o.name.clone(), translation: o.translation, rotation: o.render_rotation(), scale: o.scale }),
+        }
+    }
+
+    /// `--pose <line>`, if given.
+    pub fn from_args(args: &[String]) -> Result<Option<Self>, String> {
+        match args.iter().position(|a| a == "--pose") {
+            Some(i) => {
+                let line = args.get(i + 1).ok_or("--pose needs a pose line (copied with Insert)")?;
+                Pose::parse(line).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let mut fields = line.trim().split(',');
+        let version = fields.next().and_then(|v| v.strip_prefix('v')).and_then(|v| v.parse::<u32>().ok());
+        match version {
+            None => return Err(format!("not a pose line: it starts with v{}, followed by key=value fields", VERSION)),
+            Some(version) if version != VERSION => {
+                return Err(format!("pose line version {} can't be read, this build reads version {}", version, VERSION));
+            }
+            _ => {}
+        }
+
+        let mut values: BTreeMap<&str, &str> = BTreeMap::new();
+        for field in fields {
+            let (key, value) = field.split_once('=').ok_or_else(|| format!("malformed field '{}', expected key=value", field))?;
+            if !FIELDS.contains(&key) {
+                return Err(format!("unknown field '{}'", key));
+            }
+            if values.insert(key, value).is_some() {
+                return Err(format!("field '{}' is given twice", key));
+            }
+        }
+        let get = |key: &str| values.get(key).copied().ok_or_else(|| format!("missing field '{}'", key));
+        let number = |key: &str| -> Result<f32, String> {
+            let value = get(key)?;
+            value.parse::<f32>().ok().filter(|x| x.is_finite()).ok_or_else(|| format!("field '{}': '{}' isn't a number", key, value))
+        };
+        let vector = |key: &str| -> Result<Vector3, String> {
+            let value = get(key)?;
+            let parts: Vec<f32> = value.split(':').filter_map(|x| x.parse::<f32>().ok().filter(|x| x.is_finite())).collect();
+            match parts[..] {
+                [x, y, z] if value.split(':').count() == 3 => Ok(Vector3::new(x, y, z)),
+                _ => Err(format!("field '{}': '{}' isn't three numbers joined by ':'", key, value)),
+            }
+        };
+
+        let seed = get("seed")?;
+        let body = if values.contains_key("body") {
+            Some(BodyPose { name: decode(get("body")?)?, translation: vector("pos")?, rotation: vector("rot")?, scale: number("scale")? })
+        } else if ["pos", "rot", "scale"].iter().any(|k| values.contains_key(k)) {
+            return Err("fields 'pos', 'rot' and 'scale' need a 'body'".to_string());
+        } else {
+            None
+        };
+        let pose = Pose {
+            scene: decode(get("scene")?)?,
+            seed: seed.parse().map_err(|_| format!("field 'seed': '{}' isn't a non-negative integer", seed))?,
+            time: number("t")?,
+            time_scale: number("speed")?,
+            yaw: number("yaw")?,
+            pitch: number("pitch")?,
+            distance: number("dist")?,
+            target: vector("target")?,
+            fov: number("fov")?,
+            body,
+        };
+        if pose.distance <= 0.0 {
+            return Err(format!("field 'dist': {} isn't above 0", pose.distance));
+        }
+        if pose.time_scale <= 0.0 {
+            return Err(format!("field 'speed': {} isn't above 0", pose.time_scale));
+        }
+        if !(1.0..=170.0).contains(&pose.fov) {
+            return Err(format!("field 'fov': {} degrees is out of range", pose.fov));
+        }
+        Ok(pose)
+    }
+
+    /// Moves the clock and the camera to the pose. Returns what doesn't match: the body
+    /// missing or somewhere else than the pose has it.
+    pub fn restore(&self, objects: &mut [SceneObject], camera: &mut Camera, clock: &mut SimClock) -> Vec<String> {
+        clock.seek(objects, self.time);
+        clock.time_scale = self.time_scale;
+        camera.warp = None;
+        camera.yaw = self.yaw;
+        camera.pitch = self.pitch;
+        camera.distance = self.distance;
+        camera.set_target(self.target);
+
+        let Some(body) = &self.body else {
+            return Vec::new();
+        };
+        let Some(obj) = objects.iter().find(|o| o.name == body.name) else {
+            return vec![format!("the pose's body {} isn't in this scene", body.name)];
+        };
+        let mut notes = Vec::new();
+        let moved = (obj.translation - body.translation).length();
+        if moved > self.distance * DRIFT {
+            notes.push(format!("{} is {:.4} from where the pose has it; the scene may have changed", body.name, moved));
+        }
+        if obj.scale != body.scale {
+            notes.push(format!("{} has scale {} instead of the pose's {}", body.name, obj.scale, body.scale));
+        }
+        notes
+    }
+
+    /// Writes the line to `REPRO_PATH`.
+    pub fn write(&self) -> Result<(), String> {
+        fs::write(REPRO_PATH, format!("{}\n", self)).map_err(|e| format!("could not write {}: {}", REPRO_PATH, e))
+    }
+}
+
+impl fmt::Display for Pose {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let vector = |v: Vector3| format!("{}:{}:{}", v.x, v.y, v.z);
+        write!(
+            f,
+            "v{},scene={},seed={},t={},speed={},yaw={},pitch={},dist={},target={},fov={}",
+            VERSION,
+            encode(&self.scene),
+            self.seed,
+            self.time,
+            self.time_scale,
+            self.yaw,
+            self.pitch,
+            self.distance,
+            vector(self.target),
+            self.fov
+        )?;
+        if let Some(body) = &self.body {
+            write!(f, ",body={},pos={},rot={},scale={}", encode(&body.name), vector(body.translation), vector(body.rotation), body.scale)?;
+        }
+        Ok(())
+    }
+}
+
+/// Percent-escapes everything but letters, digits and `._/-`, so paths and names with
+/// spaces or commas keep the line in one piece.
+fn encode(text: &str) -> String {
+    let mut out = String::new();
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric() || b"._/-".contains(&byte) {
+            out.push(byte as char);
+        } else {
+            out.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    out
+}
+
+fn decode(text: &str) -> Result<String, String> {
+    let mut bytes = Vec::new();
+    let mut rest = text.bytes();
+    while let Some(byte) = rest.next() {
+        if byte != b'%' {
+            bytes.push(byte);
+            continue;
+        }
+        let hex: Vec<u8> = rest.by_ref().take(2).collect();
+        let value = std::str::from_utf8(&hex).ok().and_then(|h| u8::from_str_radix(h, 16).ok()).filter(|_| hex.len() == 2);
+        bytes.push(value.ok_or_else(|| format!("bad escape in '{}'", text))?);
+    }
+    String::from_utf8(bytes).map_err(|_| format!("'{}' isn't valid text", text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::default_scene_objects;
+
+    #[test]
+    fn pose_round_trips_and_restores_the_view() {
+        let mut objects = default_scene_objects();
+        let mut clock = SimClock::new();
+        clock.time_scale = 4.0;
+        for _ in 0..90 {
+            clock.advance(&mut objects, 1.0 / 60.0, false);
+        }
+        let mut camera = Camera::new(Vector3::new(3.0, 2.0, 40.0), Vector3::zero(), Vector3::new(0.0, 1.0, 0.0));
+        let ring = objects.iter().find(|o| o.name == "ring").unwrap();
+        camera.set_target(ring.translation);
+        let pose = Pose::capture("assets/my scenes/rings, v2.toml", 7, &camera, 0.9, &clock, Some(ring));
+
+        let line = pose.to_string();
+        assert!(!line.contains(' '), "{}", line);
+        assert_eq!(Pose::parse(&line), Ok(pose.clone()));
+
+        // A fresh start lands on the same frame
+        let mut fresh = default_scene_objects();
+        let mut fresh_clock = SimClock::new();
+        let mut fresh_camera = Camera::new(Vector3::new(0.0, 5.0, 100.0), Vector3::zero(), Vector3::new(0.0, 1.0, 0.0));
+        let notes = pose.restore(&mut fresh, &mut fresh_camera, &mut fresh_clock);
+        assert!(notes.is_empty(), "{:?}", notes);
+        assert!((fresh_camera.eye - camera.eye).length() < 1e-3);
+        assert_eq!(fresh_camera.target, camera.target);
+        assert_eq!(fresh_clock.time_scale, 4.0);
+    }
+
+    #[test]
+    fn malformed_lines_are_refused_with_the_reason() {
+        let good = "v1,scene=assets/scenes/default.toml,seed=0,t=1.5,speed=1,yaw=0.5,pitch=0.2,dist=12,target=1:0:-2,fov=60";
+        assert!(Pose::parse(good).is_ok());
+        let error = |line: &str| Pose::parse(line).unwrap_err();
+        assert!(error("hello").starts_with("not a pose line"));
+        assert!(error(&good.replace("v1,", "v2,")).contains("version 2"));
+        assert_eq!(error(&good.replace(",seed=0", "")), "missing field 'seed'");
+        assert_eq!(error(&good.replace("dist=12", "dist=far")), "field 'dist': 'far' isn't a number");
+        assert!(error(&good.replace("1:0:-2", "1:0")).contains("three numbers"));
+        assert_eq!(error(&format!("{},zoom=2", good)), "unknown field 'zoom'");
+        assert_eq!(error(&format!("{},t=2", good)), "field 't' is given twice");
+        assert!(error(&format!("{},pos=1:2:3", good)).contains("need a 'body'"));
+        assert!(error(&good.replace("default", "de%zzfault")).contains("bad escape"));
+    }
+}
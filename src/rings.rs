@@ -0,0 +1,171 @@
+#![allow(dead_code)]
+
+use std::f32::consts::PI;
+
+use raylib::prelude::*;
+
+use crate::scene::RingConfig;
+use crate::vertex::Vertex;
+
+/// Mesh paths of the form `generated:ring:<inner>:<outer>` are built by `ring_mesh`
+/// instead of being loaded from an OBJ file.
+const RING_MESH_PREFIX: &str = "generated:ring:";
+const RING_SEGMENTS: usize = 128;
+const RING_BANDS: usize = 16;
+/// Texels in a baked ring profile, from the inner edge to the outer one.
+pub const RING_PROFILE_SIZE: usize = 1024;
+
+/// Default colors of a ring with no `colors` keys, the same as `ring_vertex_shader`'s base.
+const DEFAULT_INNER_COLOR: Vector3 = Vector3 { x: 0.95, y: 0.9, z: 0.8 };
+const DEFAULT_OUTER_COLOR: Vector3 = Vector3 { x: 0.6, y: 0.55, z: 0.5 };
+
+fn mix_vec3(a: Vector3, b: Vector3, t: f32) -> Vector3 {
+    a * (1.0 - t) + b * t
+}
+
+/// Mesh path of a generated flat ring between `inner` and `outer`.
+pub fn ring_mesh_path(inner: f32, outer: f32) -> String {
+    format!("{}{}:{}", RING_MESH_PREFIX, inner, outer)
+}
+
+/// Radii of a generated ring mesh path, or None for any other path.
+pub fn parse_ring_mesh_path(path: &str) -> Option<(f32, f32)> {
+    let (inner, outer) = path.strip_prefix(RING_MESH_PREFIX)?.split_once(':')?;
+    Some((inner.parse().ok()?, outer.parse().ok()?))
+}
+
+/// Flat annulus in the XZ plane between `inner` and `outer`, facing +Y, as a triangle
+/// list. Split into bands so the radial coordinate interpolates closely across it.
+pub fn ring_mesh(inner: f32, outer: f32) -> Vec<Vertex> {
+    let point = |band: usize, segment: usize| {
+        let r = inner + (outer - inner) * band as f32 / RING_BANDS as f32;
+        let a = 2.0 * PI * segment as f32 / RING_SEGMENTS as f32;
+        let t = band as f32 / RING_BANDS as f32;
+        Vertex::new(Vector3::new(r * a.cos(), 0.0, r * a.sin()), Vector3::new(0.0, 1.0, 0.0), Vector2::new(t, 0.0))
+    };
+    let mut vertices = Vec::with_capacity(RING_BANDS * RING_SEGMENTS * 6);
+    for band in 0..RING_BANDS {
+        for segment in 0..RING_SEGMENTS {
+            let (a, b) = (point(band, segment), point(band, segment + 1));
+            let (c, d) = (point(band + 1, segment), point(band + 1, segment + 1));
+            vertices.extend([a.clone(), c.clone(), b.clone(), b, c, d]);
+        }
+    }
+    vertices
+}
+
+/// A multi-ring system authored as data in a body's `ring` table: gaps as (center,
+/// width) and keys as (t, value), where t runs from 0 at the inner edge to 1 at the
+/// outer one and radii are in the ring's model space.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RingParams {
+    pub inner: f32,
+    pub outer: f32,
+    pub gaps: Vec<(f32, f32)>,
+    pub color_keys: Vec<(f32, Vector3)>,
+    pub opacity_profile: Vec<(f32, f32)>,
+}
+
+impl RingParams {
+    /// The profile part of `config`, or None for a plain ring with none of `gaps`,
+    /// `colors` or `opacity` (drawn with the built-in bands).
+    pub fn from_config(config: &RingConfig) -> Option<Self> {
+        if config.gaps.is_empty() && config.colors.is_empty() && config.opacity.is_empty() {
+            return None;
+        }
+        let mut color_keys: Vec<(f32, Vector3)> = config.colors.iter().map(|k| (k[0], Vector3::new(k[1], k[2], k[3]))).collect();
+        let mut opacity_profile: Vec<(f32, f32)> = config.opacity.iter().map(|k| (k[0], k[1].clamp(0.0, 1.0))).collect();
+        color_keys.sort_by(|a, b| a.0.total_cmp(&b.0));
+        opacity_profile.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Some(RingParams {
+            inner: config.inner_radius,
+            outer: config.outer_radius,
+            gaps: config.gaps.iter().map(|g| (g[0], g[1].max(0.0))).collect(),
+            color_keys,
+            opacity_profile,
+        })
+    }
+
+    /// Color at radial coordinate `t`, between the nearest keys.
+    fn color(&self, t: f32) -> Vector3 {
+        if self.color_keys.is_empty() {
+            return mix_vec3(DEFAULT_INNER_COLOR, DEFAULT_OUTER_COLOR, t);
+        }
+        interpolate(&self.color_keys, t, mix_vec3)
+    }
+
+    /// Density at `t` before the gaps are cut, 1 with no opacity keys.
+    fn opacity(&self, t: f32) -> f32 {
+        if self.opacity_profile.is_empty() {
+            return 1.0;
+        }
+        interpolate(&self.opacity_profile, t, |a, b, f| a + (b - a) * f)
+    }
+}
+
+/// Piecewise-linear value of sorted `keys` at `t`, held flat past the first and last.
+fn interpolate<T: Copy, F: Fn(T, T, f32) -> T>(keys: &[(f32, T)], t: f32, lerp: F) -> T {
+    let next = keys.iter().position(|k| k.0 > t).unwrap_or(keys.len());
+    match next {
+        0 => keys[0].1,
+        n if n == keys.len() => keys[n - 1].1,
+        n => {
+            let (a, b) = (keys[n - 1], keys[n]);
+            lerp(a.1, b.1, (t - a.0) / (b.0 - a.0).max(1e-6))
+        }
+    }
+}
+
+/// Color and density of a ring baked into a 1D lookup texture, so the ring shaders
+/// take one sample per fragment whatever the number of gaps and keys.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RingProfile {
+    texels: Vec<(Vector3, f32)>,
+}
+
+impl RingProfile {
+    /// Bakes `params`. Each texel's density is box-filtered against the gaps, so a gap
+    /// keeps its exact place and width even when it is only a few texels wide.
+    pub fn bake(params: &RingParams) -> Self {
+        let span = (params.outer - params.inner).max(1e-4);
+        let texel = span / RING_PROFILE_SIZE as f32;
+        let texels = (0..RING_PROFILE_SIZE)
+            .map(|i| {
+                let t = (i as f32 + 0.5) / RING_PROFILE_SIZE as f32;
+                let r = params.inner + t * span;
+                let open = params.gaps.iter().fold(1.0, |open, &(center, width)| {
+                    let overlap = ((r + texel * 0.5).min(center + width * 0.5) - (r - texel * 0.5).max(center - width * 0.5)).max(0.0);
+                    open * (1.0 - overlap / texel)
+                });
+                (params.color(t), params.opacity(t) * open)
+            })
+            .collect();
+        RingProfile { texels }
+    }
+
+    /// Color and density at radial coordinate `t`, filtered between neighbouring texels.
+    pub fn sample(&self, t: f32) -> (Vector3, f32) {
+        let x = (t.clamp(0.0, 1.0) * RING_PROFILE_SIZE as f32 - 0.5).clamp(0.0, (RING_PROFILE_SIZE - 1) as f32);
+        let i = (x as usize).min(RING_PROFILE_SIZE - 2);
+        let f = x - i as f32;
+        let (a, b) = (self.texels[i], self.texels[i + 1]);
+        (mix_vec3(a.0, b.0, f), a.1 + (b.1 - a.1) * f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_gap_is_empty_at_its_radius_and_solid_beside_it() {
+        // Saturn's rings in its radii, with the Cassini division
+        let params = RingParams { inner: 1.24, outer: 2.27, gaps: vec![(1.99, 0.08)], color_keys: Vec::new(), opacity_profile: Vec::new() };
+        let profile = RingProfile::bake(&params);
+        let at = |r: f32| profile.sample((r - params.inner) / (params.outer - params.inner)).1;
+        assert!(at(1.99) < 0.01, "the division lets {} through", at(1.99));
+        for r in [1.90, 2.08] {
+            assert!(at(r) > 0.99, "the ring at {} is only {} dense", r, at(r));
+        }
+    }
+}
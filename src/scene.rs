@@ -0,0 +1,886 @@
+#![allow(dead_code)]
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use raylib::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::beauty::BeautyConfig;
+use crate::deepsky::DeepSkyConfig;
+use crate::depthrange::DepthRangeConfig;
+use crate::discovery::DiscoveryConfig;
+use crate::dust::DustConfig;
+use crate::error::AppError;
+use crate::events::EventsConfig;
+use crate::generator::{PlanetConfig, PlanetKind, PlanetParams, GENERATED_MESH};
+use crate::gravitygrid::GravityGridConfig;
+use crate::light::LightConfig;
+use crate::matrix::{create_tilted_model_matrix, multiply_matrix_vector4};
+use crate::measure::MeasureConfig;
+use crate::noise::{sub_seed, Noise};
+use crate::obj::{MeshConfig, Obj};
+use crate::modulation::ModulationConfig;
+use crate::photo::PhotoConfig;
+use crate::orbit::Orbit;
+use crate::params::ParamSet;
+use crate::ramp::RampSet;
+use crate::skybox::SkyboxConfig;
+use crate::soundscape::SoundscapeConfig;
+use crate::rings::{parse_ring_mesh_path, ring_mesh, ring_mesh_path, RingParams, RingProfile};
+use crate::sidecar::sidecar_path;
+use crate::texture::{MaterialConfig, TextureConfig};
+use crate::trails::TrailConfig;
+use crate::vertex::Vertex;
+
+/// Key that saves the live scene next to the loaded scene file.
+pub const SAVE_SCENE_KEY: KeyboardKey = KeyboardKey::KEY_F8;
+
+/// Key that freezes and resumes orbits, spins and the sun's activity.
+pub const PAUSE_KEY: KeyboardKey = KeyboardKey::KEY_SPACE;
+
+#[derive(Clone)]
+pub struct SceneObject {
+    pub name: String,
+    pub vertices: Vec<Vertex>,
+    pub mesh: String,
+    pub object_type: String,
+    pub parent: Option<String>,
+    pub translation: Vector3,
+    pub rotation: Vector3,
+    pub scale: f32,
+    pub spin: f32, // Radians per second around the Y axis
+    pub mass: Option<f32>, // Relative mass from the scene file; see `mass`
+    pub axial_tilt: f32, // Radians the spin axis leans from +Y, fixed in space as the body orbits
+    pub precession: f32, // Radians per second the tilted axis turns around +Y
+    pub orbit: Option<Orbit>,
+    pub sphere: bool,  // Mesh is a sphere and can be ray-cast when small on screen
+    pub ring: Option<RingConfig>,
+    pub ring_profile: Option<Arc<RingProfile>>, // Baked from `ring` when it has gaps or keys
+    pub material: MaterialConfig, // Texture maps (loaded through the texture cache) and shading
+    pub flags: RenderFlags,
+    pub radius: f32,   // Bounding radius of the mesh before scaling
+    pub noise: Noise,  // Procedural noise seeded from the scene seed and the body's name
+    pub planet: Option<PlanetParams>, // Generator parameters, for bodies of a generated kind
+    pub params: ParamSet, // Values its bespoke shader reads instead of built-in constants
+    pub ramps: RampSet,   // Color ramps its bespoke shader samples instead of built-in palettes
+
+    // Runtime state, kept across hot reloads
+    pub offset: Vector3,    // Position from the scene file, relative to the parent
+    pub barycenter: Vector3, // Where the body would be without its moons pulling on it; children orbit this
+    pub spin_angle: f32,
+    pub precession_angle: f32, // Direction the tilted axis leans towards, from +X around +Y
+    pub mean_anomaly: f32,
+}
+
+impl SceneObject {
+    /// Whether panels and logs name this body: not rings (they go with their planet) or
+    /// the shuttle, and not hidden ones.
+    pub fn is_listed(&self) -> bool {
+        self.object_type != "ring" && self.object_type != "shuttle" && self.flags.visible
+    }
+
+    /// Relative mass: the scene file's `mass`, or scale³ as if every body had the same density.
+    pub fn mass(&self) -> f32 {
+        self.mass.unwrap_or(self.scale * self.scale * self.scale)
+    }
+
+    /// Rotation used for rendering: the authored orientation plus the accumulated spin.
+    pub fn render_rotation(&self) -> Vector3 {
+        Vector3::new(self.rotation.x, self.rotation.y + self.spin_angle, self.rotation.z)
+    }
+
+    /// Model matrix: the spinning body, then its axial tilt. The tilt is applied in
+    /// world space, after the spin, so the axis keeps pointing the same way all orbit.
+    pub fn model_matrix(&self) -> Matrix {
+        create_tilted_model_matrix(self.translation, self.scale, self.render_rotation(), self.axial_tilt, self.precession_angle)
+    }
+
+    /// World direction of the spin axis (the north pole). The spin turns about the +Y
+    /// left after the authored X rotation, so only the Z rotation and the tilt move it.
+    pub fn spin_axis(&self) -> Vector3 {
+        let rotation = Vector3::new(0.0, 0.0, self.rotation.z);
+        let m = create_tilted_model_matrix(Vector3::zero(), 1.0, rotation, self.axial_tilt, self.precession_angle);
+        let axis = multiply_matrix_vector4(&m, &Vector4::new(0.0, 1.0, 0.0, 0.0));
+        Vector3::new(axis.x, axis.y, axis.z).normalized()
+    }
+
+    /// Latitude, in radians, of the point on this body with the sun overhead.
+    pub fn subsolar_latitude(&self, sun: Vector3) -> f32 {
+        let to_sun = (sun - self.translation).normalized();
+        self.spin_axis().dot(to_sun).clamp(-1.0, 1.0).asin()
+    }
+
+    /// A bare sphere of `radius` at `position`, not orbiting or spinning, for tests.
+    #[cfg(test)]
+    pub(crate) fn test_sphere(name: &str, kind: &str, position: Vector3, radius: f32) -> Self {
+        SceneObject {
+            name: name.to_string(),
+            vertices: Vec::new(),
+            mesh: String::new(),
+            object_type: kind.to_string(),
+            parent: None,
+            translation: position,
+            rotation: Vector3::zero(),
+            scale: 1.0,
+            spin: 0.0,
+            mass: None,
+            axial_tilt: 0.0,
+            precession: 0.0,
+            orbit: None,
+            sphere: true,
+            ring: None,
+            ring_profile: None,
+            material: MaterialConfig::default(),
+            flags: RenderFlags::default(),
+            radius,
+            noise: Noise::new(7),
+            planet: None,
+            params: ParamSet::default(),
+            ramps: RampSet::default(),
+            offset: position,
+            barycenter: position,
+            spin_angle: 0.0,
+            precession_angle: 0.0,
+            mean_anomaly: 0.0,
+        }
+    }
+}
+
+/// Inner and outer radius of a ring mesh in model space, used to map each vertex
+/// to a radial coordinate (0 at the inner edge, 1 at the outer edge). `gaps`, `colors`
+/// and `opacity` describe a multi-ring system, see `RingParams`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RingConfig {
+    pub inner_radius: f32,
+    pub outer_radius: f32,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub gaps: Vec<[f32; 2]>, // (center radius, width)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub colors: Vec<[f32; 4]>, // (t, r, g, b)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub opacity: Vec<[f32; 2]>, // (t, opacity)
+}
+
+impl Default for RingConfig {
+    // Radii of assets/objects/ring.obj
+    fn default() -> Self {
+        RingConfig { inner_radius: 1.875, outer_radius: 3.125, gaps: Vec::new(), colors: Vec::new(), opacity: Vec::new() }
+    }
+}
+
+/// Baked lookup of a ring with a profile, None for plain rings and other bodies.
+fn ring_profile(ring: Option<&RingConfig>) -> Option<Arc<RingProfile>> {
+    RingParams::from_config(ring?).map(|params| Arc::new(RingProfile::bake(&params)))
+}
+
+/// Per-object switches for the render passes, from the body's `flags` table in the
+/// scene file or `set <name> <flag> on|off` in the console.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenderFlags {
+    pub visible: bool,
+    pub cast_shadows: bool,    // Blocks sunlight from other bodies (sphere bodies only)
+    pub receive_shadows: bool, // Darkened where another body blocks the sun
+    pub affected_by_fog: bool, // Reserved: kept and settable, but there's no fog pass to read it yet
+    pub depth_write: bool,
+    pub depth_test: bool,
+    pub emissive_bloom: bool, // Reserved: contributes to bloom once there's a bloom pass
+    pub graticule: bool,      // Latitude/longitude lines over the surface (sphere bodies only)
+}
+
+impl Default for RenderFlags {
+    fn default() -> Self {
+        RenderFlags {
+            visible: true,
+            cast_shadows: true,
+            receive_shadows: true,
+            affected_by_fog: true,
+            depth_write: true,
+            depth_test: true,
+            emissive_bloom: true,
+            graticule: false,
+        }
+    }
+}
+
+impl RenderFlags {
+    pub const NAMES: [&'static str; 8] = [
+        "visible",
+        "cast_shadows",
+        "receive_shadows",
+        "affected_by_fog",
+        "depth_write",
+        "depth_test",
+        "emissive_bloom",
+        "graticule",
+    ];
+
+    pub fn set(&mut self, name: &str, value: bool) -> Result<(), String> {
+        let flag = match name {
+            "visible" => &mut self.visible,
+            "cast_shadows" => &mut self.cast_shadows,
+            "receive_shadows" => &mut self.receive_shadows,
+            "affected_by_fog" => &mut self.affected_by_fog,
+            "depth_write" => &mut self.depth_write,
+            "depth_test" => &mut self.depth_test,
+            "emissive_bloom" => &mut self.emissive_bloom,
+            "graticule" => &mut self.graticule,
+            _ => return Err(format!("unknown flag '{}'", name)),
+        };
+        *flag = value;
+        Ok(())
+    }
+}
+
+fn is_zero(value: &f32) -> bool {
+    *value == 0.0
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+/// Range a body's scale is kept in: at 0 its model matrix has no inverse and NaNs spread
+/// through its normals, and far past the system's size it only makes huge triangles.
+pub const SCALE_RANGE: (f32, f32) = (1e-3, 1e3);
+
+/// `scale`, or why a body can't have it.
+pub fn check_scale(scale: f32) -> Result<f32, String> {
+    let (min, max) = SCALE_RANGE;
+    if !scale.is_finite() {
+        return Err(format!("scale {} is not a finite number", scale));
+    }
+    if !(min..=max).contains(&scale) {
+        return Err(format!("scale {} is outside {}..{}", scale, min, max));
+    }
+    Ok(scale)
+}
+
+/// The nearest usable scale: clamped into `SCALE_RANGE`, or 1 for NaN and infinities.
+pub fn clamp_scale(scale: f32) -> f32 {
+    if scale.is_finite() { scale.clamp(SCALE_RANGE.0, SCALE_RANGE.1) } else { 1.0 }
+}
+
+/// One `[[body]]` entry of a scene file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BodyConfig {
+    #[serde(default)]
+    pub name: String, // Generated from the kind when left out
+    pub kind: String,
+    #[serde(default)]
+    pub mesh: String, // Optional for generated kinds, which default to a sphere
+    #[serde(default)]
+    pub parent: Option<String>,
+    #[serde(default)]
+    pub position: [f32; 3],
+    #[serde(default)]
+    pub rotation: [f32; 3],
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+    #[serde(default)]
+    pub spin: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mass: Option<f32>, // Relative mass, for gravity; scale³ (uniform density) when left out
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub axial_tilt: f32,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub precession: f32,
+    #[serde(default)]
+    pub orbit: Option<Orbit>,
+    #[serde(default)]
+    pub sphere: bool,
+    #[serde(default)]
+    pub ring: Option<RingConfig>,
+    #[serde(default)]
+    pub material: MaterialConfig,
+    #[serde(default)]
+    pub flags: RenderFlags,
+    #[serde(default, skip_serializing_if = "ParamSet::is_empty")]
+    pub params: ParamSet, // Shader parameters of bespoke kinds, see `shader_params`
+    #[serde(default, skip_serializing_if = "RampSet::is_empty")]
+    pub ramps: RampSet, // Color ramps of bespoke kinds (`bands`, `climate`, `lava`, ...)
+    #[serde(flatten)]
+    pub planet: PlanetConfig, // Generator settings (`band_freq`, `seed`, ...) for generated kinds
+}
+
+impl BodyConfig {
+    /// The body's noise: its own `seed` when set, else the scene seed mixed with its name.
+    fn noise(&self, scene_seed: u64) -> Noise {
+        Noise::new(self.planet.seed.unwrap_or_else(|| sub_seed(scene_seed, &self.name)))
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SceneFile {
+    #[serde(default)]
+    pub light: LightConfig,
+    #[serde(default)]
+    pub dust: DustConfig,
+    #[serde(default)]
+    pub measure: MeasureConfig,
+    #[serde(default)]
+    pub textures: TextureConfig,
+    #[serde(default)]
+    pub skybox: SkyboxConfig,
+    #[serde(default)]
+    pub discovery: DiscoveryConfig,
+    #[serde(default)]
+    pub trails: TrailConfig,
+    #[serde(default)]
+    pub depth_range: DepthRangeConfig,
+    #[serde(default)]
+    pub beauty: BeautyConfig,
+    #[serde(default)]
+    pub modulation: ModulationConfig,
+    #[serde(default)]
+    pub photo: PhotoConfig,
+    #[serde(default)]
+    pub gravity_grid: GravityGridConfig,
+    #[serde(default)]
+    pub soundscape: SoundscapeConfig,
+    #[serde(default)]
+    pub events: EventsConfig,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub meshes: BTreeMap<String, MeshConfig>, // Clean-up settings per OBJ path; unlisted models get the defaults
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deep_sky: Vec<DeepSkyConfig>, // Galaxies and nebulae far outside the system
+    #[serde(default)]
+    pub seed: u64, // Seeds all procedural content; `--seed` overrides it
+    #[serde(default, rename = "body")]
+    pub bodies: Vec<BodyConfig>,
+}
+
+impl SceneFile {
+    pub fn load(path: &str) -> Result<Self, AppError> {
+        let text = fs::read_to_string(path).map_err(|_| AppError::AssetMissing { path: path.to_string() })?;
+        Self::parse(&text).map_err(|e| AppError::SceneConfig { msg: format!("{}: {}", path, e) })
+    }
+
+    /// This scene's settings with `objects`, as they are now, for its bodies.
+    pub fn with_objects(&self, objects: &[SceneObject]) -> SceneFile {
+        SceneFile { bodies: objects.iter().map(body_config).collect(), ..self.clone() }
+    }
+
+    /// Writes the scene in the format `load` reads. Fields keep their declaration
+    /// order, so saving twice gives the same file and edits diff cleanly.
+    pub fn save(&self, path: &str) -> Result<(), AppError> {
+        let text = self.to_toml().map_err(|_| AppError::SceneWrite { path: path.to_string() })?;
+        fs::write(path, text).map_err(|_| AppError::SceneWrite { path: path.to_string() })
+    }
+
+    pub fn to_toml(&self) -> Result<String, String> {
+        let mut value = toml::Value::try_from(self).map_err(|e| e.to_string())?;
+        shorten_floats(&mut value);
+        toml::to_string(&value).map_err(|e| e.to_string())
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut scene: SceneFile = toml::from_str(text).map_err(|e| e.to_string())?;
+        for body in &mut scene.bodies {
+            if let Err(error) = check_scale(body.scale) {
+                body.scale = clamp_scale(body.scale);
+                tracing::warn!("body '{}': {}, using {}", body.name, error, body.scale);
+            }
+        }
+        scene.fill_generated();
+
+        for (i, body) in scene.bodies.iter().enumerate() {
+            if scene.bodies[..i].iter().any(|b| b.name == body.name) {
+                return Err(format!("duplicate body name '{}'", body.name));
+            }
+            if let Some(parent) = &body.parent
+                && !scene.bodies.iter().any(|b| &b.name == parent)
+            {
+                return Err(format!("body '{}' has unknown parent '{}'", body.name, parent));
+            }
+        }
+        Ok(scene)
+    }
+
+    /// Fills in what generated bodies leave out: a name, the sphere mesh, and a ring
+    /// body of their own when they have a `ring` table. Rings with no mesh, and the
+    /// generated rings with a profile, get a flat ring mesh fitted to their radii.
+    fn fill_generated(&mut self) {
+        let mut rings = Vec::new();
+        // Rings already written out (e.g. by `save`) aren't added twice
+        let names: Vec<String> = self.bodies.iter().map(|b| b.name.clone()).collect();
+        for (i, body) in self.bodies.iter_mut().enumerate() {
+            if body.name.is_empty() {
+                body.name = format!("{}{}", body.kind, i + 1);
+            }
+            if body.kind == "ring"
+                && body.mesh.is_empty()
+                && let Some(ring) = &body.ring
+            {
+                body.mesh = ring_mesh_path(ring.inner_radius, ring.outer_radius);
+            }
+            if PlanetKind::from_kind(&body.kind).is_none() {
+                continue;
+            }
+            if body.mesh.is_empty() {
+                body.mesh = GENERATED_MESH.to_string();
+                body.sphere = true;
+            }
+            let ring_name = format!("{}-ring", body.name);
+            if let Some(ring) = &body.ring
+                && !names.contains(&ring_name)
+            {
+                rings.push(BodyConfig {
+                    name: ring_name,
+                    kind: "ring".to_string(),
+                    mesh: match RingParams::from_config(ring) {
+                        Some(params) => ring_mesh_path(params.inner, params.outer),
+                        None => "assets/objects/ring.obj".to_string(),
+                    },
+                    parent: Some(body.name.clone()),
+                    position: [0.0; 3],
+                    rotation: body.rotation,
+                    scale: body.scale,
+                    spin: body.spin,
+                    mass: None,
+                    axial_tilt: body.axial_tilt,
+                    precession: body.precession,
+                    orbit: None,
+                    sphere: false,
+                    ring: Some(ring.clone()),
+                    material: MaterialConfig::default(),
+                    flags: body.flags.clone(),
+                    params: ParamSet::default(),
+                    ramps: RampSet::default(),
+                    planet: PlanetConfig::default(),
+                });
+            }
+        }
+        self.bodies.extend(rings);
+    }
+}
+
+/// Mesh vertex arrays keyed by OBJ path, loaded the first time they're needed. OBJs are
+/// welded and checked on load, with the scene's `[meshes]` settings.
+pub struct MeshLibrary {
+    meshes: HashMap<String, Vec<Vertex>>,
+    options: BTreeMap<String, MeshConfig>,
+}
+
+impl MeshLibrary {
+    pub fn new() -> Self {
+        MeshLibrary { meshes: HashMap::new(), options: BTreeMap::new() }
+    }
+
+    /// Takes a scene's mesh settings; models whose settings changed load again.
+    pub fn set_options(&mut self, options: &BTreeMap<String, MeshConfig>) {
+        self.meshes.retain(|path, _| self.options.get(path) == options.get(path));
+        self.options = options.clone();
+    }
+
+    pub fn get(&mut self, path: &str) -> Result<&Vec<Vertex>, AppError> {
+        if !self.meshes.contains_key(path) {
+            let vertices = match parse_ring_mesh_path(path) {
+                Some((inner, outer)) => ring_mesh(inner, outer),
+                None => {
+                    let mut obj = Obj::load(path)?;
+                    let options = self.options.get(path).cloned().unwrap_or_default();
+                    if options.flip_v || (options.auto_flip_v && obj.v_runs_up() == Some(true)) {
+                        tracing::info!("flipping the texture coordinates of {} upside down", path);
+                        obj.flip_v();
+                    }
+                    let report = obj.validate_and_weld(options.weld);
+                    if options.smooth_angle > 0.0 {
+                        obj.smooth_normals(options.smooth_angle);
+                    }
+                    tracing::info!("loaded {}: {}", path, report);
+                    obj.get_vertex_array()
+                }
+            };
+            self.meshes.insert(path.to_string(), vertices);
+        }
+        Ok(&self.meshes[path])
+    }
+}
+
+/// Distance from the mesh origin to its farthest vertex.
+pub fn bounding_radius(vertices: &[Vertex]) -> f32 {
+    vertices.iter().map(|v| v.position.length()).fold(0.0, f32::max)
+}
+
+fn vec3(v: [f32; 3]) -> Vector3 {
+    Vector3::new(v[0], v[1], v[2])
+}
+
+/// Rewrites every float as the shortest decimal that reads back as the same `f32`,
+/// so a saved `0.2` stays `0.2` instead of its exact `f64` widening.
+fn shorten_floats(value: &mut toml::Value) {
+    match value {
+        toml::Value::Float(f) => *f = (*f as f32).to_string().parse().unwrap_or(*f),
+        toml::Value::Array(items) => items.iter_mut().for_each(shorten_floats),
+        toml::Value::Table(table) => table.iter_mut().for_each(|(_, v)| shorten_floats(v)),
+        _ => {}
+    }
+}
+
+/// Where `SAVE_SCENE_KEY` writes: `default.toml` saves to `default.saved.toml`.
+pub fn saved_scene_path(scene_path: &str) -> String {
+    sidecar_path(scene_path, "saved")
+}
+
+/// The `[[body]]` entry that rebuilds `obj` as it is now, runtime edits included.
+fn body_config(obj: &SceneObject) -> BodyConfig {
+    BodyConfig {
+        name: obj.name.clone(),
+        kind: obj.object_type.clone(),
+        mesh: obj.mesh.clone(),
+        parent: obj.parent.clone(),
+        position: [obj.offset.x, obj.offset.y, obj.offset.z],
+        rotation: [obj.rotation.x, obj.rotation.y, obj.rotation.z],
+        scale: obj.scale,
+        spin: obj.spin,
+        mass: obj.mass,
+        axial_tilt: obj.axial_tilt,
+        precession: obj.precession,
+        orbit: obj.orbit.clone(),
+        sphere: obj.sphere,
+        ring: obj.ring.clone(),
+        material: obj.material.clone(),
+        flags: obj.flags.clone(),
+        params: obj.params.clone(),
+        ramps: obj.ramps.clone(),
+        planet: obj.planet.as_ref().map(PlanetParams::to_config).unwrap_or_default(),
+    }
+}
+
+fn build_object(body: &BodyConfig, meshes: &mut MeshLibrary, seed: u64) -> Result<SceneObject, AppError> {
+    let vertices = meshes.get(&body.mesh)?.clone();
+    let mean_anomaly = body.orbit.as_ref().map(|o| o.phase).unwrap_or(0.0);
+
+    Ok(SceneObject {
+        name: body.name.clone(),
+        radius: bounding_radius(&vertices),
+        vertices,
+        mesh: body.mesh.clone(),
+        object_type: body.kind.clone(),
+        parent: body.parent.clone(),
+        translation: vec3(body.position),
+        rotation: vec3(body.rotation),
+        scale: body.scale,
+        spin: body.spin,
+        mass: body.mass,
+        axial_tilt: body.axial_tilt,
+        precession: body.precession,
+        orbit: body.orbit.clone(),
+        sphere: body.sphere,
+        ring: body.ring.clone(),
+        ring_profile: ring_profile(body.ring.as_ref()),
+        material: body.material.clone(),
+        flags: body.flags.clone(),
+        noise: body.noise(seed),
+        planet: PlanetParams::resolve(&body.kind, &body.planet, body.ring.as_ref()),
+        params: body.params.clone(),
+        ramps: body.ramps.clone(),
+        offset: vec3(body.position),
+        barycenter: vec3(body.position),
+        spin_angle: 0.0,
+        precession_angle: 0.0,
+        mean_anomaly,
+    })
+}
+
+/// Builds the objects of a freshly loaded scene file, leaving out (and reporting) bodies whose assets fail to load.
+pub fn build_scene(scene: &SceneFile, meshes: &mut MeshLibrary) -> (Vec<SceneObject>, Vec<AppError>) {
+    meshes.set_options(&scene.meshes);
+    let mut objects = Vec::new();
+    let mut errors = Vec::new();
+    for body in &scene.bodies {
+        match build_object(body, meshes, scene.seed) {
+            Ok(object) => objects.push(object),
+            Err(error) => errors.push(error),
+        }
+    }
+    (objects, errors)
+}
+
+/// Applies a reloaded scene file to the live objects in place.
+///
+/// Bodies are matched by name. Matching bodies keep their orbital phase and spin
+/// angle; bodies that were in `previous` but not in `next` are removed, while bodies
+/// created at runtime (e.g. from the console) are left alone.
+pub fn apply_scene(
+    objects: &mut Vec<SceneObject>,
+    previous: &SceneFile,
+    next: &SceneFile,
+    meshes: &mut MeshLibrary,
+) -> Result<(), String> {
+    // Load any new meshes first so a bad path leaves the scene untouched
+    meshes.set_options(&next.meshes);
+    for body in &next.bodies {
+        meshes.get(&body.mesh)?;
+    }
+
+    objects.retain(|o| {
+        let was_in_file = previous.bodies.iter().any(|b| b.name == o.name);
+        !was_in_file || next.bodies.iter().any(|b| b.name == o.name)
+    });
+
+    for body in &next.bodies {
+        match objects.iter_mut().find(|o| o.name == body.name) {
+            Some(obj) => {
+                if obj.mesh != body.mesh || previous.meshes.get(&body.mesh) != next.meshes.get(&body.mesh) {
+                    obj.vertices = meshes.get(&body.mesh)?.clone();
+                    obj.radius = bounding_radius(&obj.vertices);
+                    obj.mesh = body.mesh.clone();
+                }
+                obj.object_type = body.kind.clone();
+                obj.parent = body.parent.clone();
+                obj.offset = vec3(body.position);
+                obj.rotation = vec3(body.rotation);
+                obj.scale = body.scale;
+                obj.spin = body.spin;
+                obj.mass = body.mass;
+                obj.axial_tilt = body.axial_tilt;
+                obj.precession = body.precession;
+                obj.sphere = body.sphere;
+                if obj.ring != body.ring {
+                    obj.ring_profile = ring_profile(body.ring.as_ref());
+                }
+                obj.ring = body.ring.clone();
+                obj.material = body.material.clone();
+                obj.flags = body.flags.clone();
+                obj.noise = body.noise(next.seed);
+                obj.planet = PlanetParams::resolve(&body.kind, &body.planet, body.ring.as_ref());
+                obj.params = body.params.clone();
+                obj.ramps = body.ramps.clone();
+                if obj.orbit.is_none() {
+                    obj.mean_anomaly = body.orbit.as_ref().map(|o| o.phase).unwrap_or(0.0);
+                }
+                obj.orbit = body.orbit.clone();
+            }
+            None => objects.push(build_object(body, meshes, next.seed)?),
+        }
+    }
+    Ok(())
+}
+
+/// Advances spins and orbits by `dt` seconds and recomputes world translations.
+/// Parents should appear before their children in the list.
+///
+/// Children orbit their parent's barycenter. A child with a `mass_ratio` covers only
+/// its share of the separation and pushes the parent the other way, so the pair
+/// circles their common barycenter, which stays on the parent's own orbit. Children
+/// without an orbit (rings) sit on the parent itself and wobble with it.
+pub fn update_scene(objects: &mut [SceneObject], dt: f32) {
+    for i in 0..objects.len() {
+        let parent = objects[i]
+            .parent
+            .as_ref()
+            .and_then(|p| objects.iter().position(|o| &o.name == p));
+        let parent_center = parent.map(|p| objects[p].barycenter);
+
+        let obj = &mut objects[i];
+        obj.spin_angle += obj.spin * dt;
+        obj.precession_angle += obj.precession * dt;
+
+        let (separation, shares) = match &obj.orbit {
+            Some(orbit) => {
+                obj.mean_anomaly = orbit.advance(obj.mean_anomaly, dt);
+                (orbit.position(obj.mean_anomaly), orbit.barycentric_shares())
+            }
+            None => (Vector3::zero(), (1.0, 0.0)),
+        };
+        let (own_share, parent_share) = if parent.is_some() { shares } else { (1.0, 0.0) };
+
+        let center = parent_center.unwrap_or(Vector3::zero()) + obj.offset;
+        obj.translation = center + separation * own_share;
+        obj.barycenter = obj.translation;
+
+        if let Some(p) = parent
+            && parent_share > 0.0
+        {
+            objects[p].translation -= separation * parent_share;
+        }
+    }
+
+    // Children that don't orbit (rings) are fixed to the body, not its barycenter, so
+    // they follow its wobble; only now, with every moon's pull applied, is it final
+    for i in 0..objects.len() {
+        if objects[i].orbit.is_some() {
+            continue;
+        }
+        let parent = objects[i].parent.as_ref().and_then(|p| objects.iter().position(|o| &o.name == p));
+        if let Some(p) = parent {
+            let position = objects[p].translation + objects[i].offset;
+            objects[i].translation = position;
+            objects[i].barycenter = position;
+        }
+    }
+}
+
+/// Half width of a shepherd's lane, in radii of the moon.
+const SHEPHERD_LANE: f32 = 2.0;
+
+/// Lanes that shepherd moons clear in `ring`, as (radius, half width) in the ring's
+/// model space: moons of the ring's planet (or of the ring itself) currently within
+/// its radii. The lane is a few times wider than the moon.
+pub fn ring_gaps(ring: &SceneObject, objects: &[SceneObject]) -> Vec<Vector2> {
+    let Some(config) = &ring.ring else {
+        return Vec::new();
+    };
+    let scale = ring.scale.max(1e-4);
+    objects
+        .iter()
+        .filter(|o| o.name != ring.name && o.object_type != "ring")
+        .filter(|o| o.parent.as_deref() == Some(ring.name.as_str()) || (o.parent.is_some() && o.parent == ring.parent))
+        .filter_map(|o| {
+            let radius = (o.translation - ring.translation).length() / scale;
+            let half_width = SHEPHERD_LANE * o.radius * o.scale / scale;
+            (radius + half_width > config.inner_radius && radius - half_width < config.outer_radius)
+                .then(|| Vector2::new(radius, half_width))
+        })
+        .collect()
+}
+
+/// Polls a scene file's modification time once per second.
+pub struct SceneWatcher {
+    pub path: String,
+    modified: Option<SystemTime>,
+    last_check: Instant,
+}
+
+impl SceneWatcher {
+    pub fn new(path: &str) -> Self {
+        SceneWatcher {
+            path: path.to_string(),
+            modified: fs::metadata(path).and_then(|m| m.modified()).ok(),
+            last_check: Instant::now(),
+        }
+    }
+
+    /// Returns the re-parsed file when it changed since the last poll.
+    pub fn poll(&mut self) -> Option<Result<SceneFile, AppError>> {
+        if self.last_check.elapsed() < Duration::from_secs(1) {
+            return None;
+        }
+        self.last_check = Instant::now();
+
+        let modified = fs::metadata(&self.path).and_then(|m| m.modified()).ok();
+        if modified.is_none() || modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        Some(SceneFile::load(&self.path))
+    }
+}
+
+/// The default scene, built cleanly and placed at time 0, for tests across modules.
+#[cfg(test)]
+pub(crate) fn default_scene() -> (SceneFile, Vec<SceneObject>) {
+    let scene = SceneFile::load("assets/scenes/default.toml").expect("default scene loads");
+    let mut meshes = MeshLibrary::new();
+    let (mut objects, errors) = build_scene(&scene, &mut meshes);
+    assert!(errors.is_empty(), "default scene has errors: {:?}", errors);
+    update_scene(&mut objects, 0.0);
+    (scene, objects)
+}
+
+/// Just the bodies of `default_scene`.
+#[cfg(test)]
+pub(crate) fn default_scene_objects() -> Vec<SceneObject> {
+    default_scene().1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fragment::Fragment;
+    use crate::shaders::ring_alpha;
+    use crate::{FrameUniforms, ObjectUniforms, Uniforms};
+
+    /// The scene's objects with empty meshes, so no OBJ file is read.
+    fn objects_of(scene: &SceneFile) -> Vec<SceneObject> {
+        let mut meshes = MeshLibrary::new();
+        for body in &scene.bodies {
+            meshes.meshes.insert(body.mesh.clone(), Vec::new());
+        }
+        let (objects, errors) = build_scene(scene, &mut meshes);
+        assert!(errors.is_empty());
+        objects
+    }
+
+    #[test]
+    fn rings_follow_their_planets_wobble() {
+        let mut objects = default_scene_objects();
+        let pan = objects.iter_mut().find(|o| o.name == "pan").unwrap();
+        pan.orbit.as_mut().expect("pan orbits gassy1").mass_ratio = 0.1;
+        for _ in 0..50 {
+            update_scene(&mut objects, 0.1);
+        }
+        let find = |name: &str| objects.iter().find(|o| o.name == name).unwrap();
+        let (planet, ring) = (find("gassy1"), find("ring"));
+        assert!((planet.translation - planet.barycenter).length() > 1e-4, "pan pulls gassy1 off its barycenter");
+        assert!((ring.translation - planet.translation).length() < 1e-5);
+    }
+
+    #[test]
+    fn saved_scene_loads_back_identical() {
+        let loaded = SceneFile::parse(include_str!("../assets/scenes/default.toml")).unwrap();
+        let mut objects = objects_of(&loaded);
+        // Live edits, as made from the console
+        objects[1].offset.x = 12.5;
+        objects[1].spin = 0.3;
+        objects[1].flags.cast_shadows = false;
+
+        let saved = loaded.with_objects(&objects).to_toml().unwrap();
+        let reloaded = SceneFile::parse(&saved).unwrap();
+        assert_eq!(reloaded, loaded.with_objects(&objects));
+        assert_eq!(reloaded.bodies[1].position[0], 12.5);
+
+        let resaved = reloaded.with_objects(&objects_of(&reloaded)).to_toml().unwrap();
+        assert_eq!(saved, resaved);
+    }
+
+    #[test]
+    fn a_shepherd_clears_a_lane_at_its_radius() {
+        let saturn = SceneObject::test_sphere("saturn", "gassy1", Vector3::zero(), 1.0);
+        let mut ring = SceneObject::test_sphere("ring", "ring", Vector3::zero(), 1.0);
+        ring.parent = Some("saturn".to_string());
+        ring.scale = 2.0;
+        ring.ring = Some(RingConfig { inner_radius: 1.2, outer_radius: 2.3, ..RingConfig::default() });
+        let mut pan = SceneObject::test_sphere("pan", "moon", Vector3::new(3.6, 0.0, 0.0), 0.05);
+        pan.parent = Some("saturn".to_string());
+        let mut titan = SceneObject::test_sphere("titan", "moon", Vector3::new(10.0, 0.0, 0.0), 0.5);
+        titan.parent = Some("saturn".to_string());
+
+        // Pan sits 1.8 out in the ring's model space; Titan is well past the ring
+        let gaps = ring_gaps(&ring, &[saturn, ring.clone(), pan, titan]);
+        assert_eq!(gaps.len(), 1, "{:?}", gaps);
+        assert!((gaps[0].x - 1.8).abs() < 1e-5 && (gaps[0].y - SHEPHERD_LANE * 0.05 / 2.0).abs() < 1e-5, "{:?}", gaps[0]);
+
+        let frame = FrameUniforms::new(Matrix::identity(), Matrix::identity(), Matrix::identity(), Vector3::zero());
+        let object = ObjectUniforms { ring_radii: Vector2::new(1.2, 2.3), ring_gaps: gaps.clone(), ..ObjectUniforms::new(Matrix::identity(), &frame) };
+        let uniforms = Uniforms { frame, object };
+        let alpha = |r: f32| {
+            let mut fragment = Fragment::new(0.0, 0.0, Vector3::one(), 0.0);
+            fragment.tex_coords.x = (r - 1.2) / (2.3 - 1.2);
+            ring_alpha(&fragment, &uniforms)
+        };
+        assert!(alpha(1.8) < 0.01, "the lane lets {} through", alpha(1.8));
+        for r in [1.8 - gaps[0].y * 1.1, 1.8 + gaps[0].y * 1.1] {
+            assert!(alpha(r) > 0.99, "the ring at {} is only {} dense", r, alpha(r));
+        }
+    }
+
+    #[test]
+    fn pathological_scales_are_clamped_on_load() {
+        let mut scene = SceneFile::parse(include_str!("../assets/scenes/default.toml")).unwrap();
+        for (body, scale) in scene.bodies.iter_mut().zip([0.0, -1.0, f32::NAN, 1e9, f32::INFINITY]) {
+            body.scale = scale;
+        }
+        let text = scene.to_toml().unwrap();
+        let reloaded = SceneFile::parse(&text).unwrap();
+        let scales: Vec<f32> = reloaded.bodies.iter().take(5).map(|b| b.scale).collect();
+        assert_eq!(scales, vec![SCALE_RANGE.0, SCALE_RANGE.0, 1.0, SCALE_RANGE.1, 1.0]);
+        assert!(check_scale(2.5).is_ok());
+    }
+}
@@ -0,0 +1,134 @@
+#![allow(dead_code)]
+
+use std::fs;
+
+use raylib::prelude::*;
+
+use crate::input::InputState;
+
+/// Key that opens and closes the scene menu over a running scene.
+pub const SCENE_MENU_KEY: KeyboardKey = KeyboardKey::KEY_TAB;
+/// Folder scanned for scene files.
+pub const SCENE_DIR: &str = "assets/scenes";
+/// Scene loaded when there's nothing to choose from.
+pub const DEFAULT_SCENE: &str = "assets/scenes/default.toml";
+
+/// Files saved next to a scene that aren't scenes themselves.
+const STATE_SUFFIXES: [&str; 2] = [".discovery.toml", ".bookmarks.toml"];
+
+/// Scene files in `dir`, sorted by name: every `*.toml` but the discovery and bookmark
+/// state kept next to them.
+pub fn find_scenes(dir: &str) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut scenes: Vec<String> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file() && path.extension().is_some_and(|e| e == "toml"))
+        .filter_map(|path| path.to_str().map(str::to_string))
+        .filter(|path| !STATE_SUFFIXES.iter().any(|suffix| path.ends_with(suffix)))
+        .collect();
+    scenes.sort();
+    scenes
+}
+
+/// File name of a scene without its folder and extension, as the menu lists it.
+fn scene_name(path: &str) -> &str {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    name.strip_suffix(".toml").unwrap_or(name)
+}
+
+/// Keyboard menu of the scenes in `SCENE_DIR`: shown at startup when there's more than
+/// one, and over a running scene with `SCENE_MENU_KEY` to switch to another.
+pub struct SceneMenu {
+    pub open: bool,
+    pub scenes: Vec<String>,
+    current: Option<String>, // The scene running now, marked in the list
+    selected: usize,
+}
+
+impl SceneMenu {
+    /// Menu listing the scenes with `current` marked as running, or none at startup.
+    pub fn new(current: Option<&str>) -> Self {
+        let mut menu = SceneMenu { open: false, scenes: Vec::new(), current: current.map(str::to_string), selected: 0 };
+        menu.refresh();
+        menu
+    }
+
+    /// Rescans the folder, so scenes added while running show up, and selects the
+    /// running scene (or the default one).
+    fn refresh(&mut self) {
+        self.scenes = find_scenes(SCENE_DIR);
+        let current = self.current.as_deref().unwrap_or(DEFAULT_SCENE);
+        self.selected = self.scenes.iter().position(|s| s == current).unwrap_or(0);
+    }
+
+    /// Opens and closes the menu and moves the selection. Returns the scene picked with
+    /// Enter, which closes the menu.
+    pub fn process_input(&mut self, input: &InputState) -> Option<String> {
+        if input.is_key_pressed(SCENE_MENU_KEY) {
+            self.open = !self.open;
+            if self.open {
+                self.refresh();
+            }
+        }
+        if !self.open || self.scenes.is_empty() {
+            return None;
+        }
+        let count = self.scenes.len();
+        if input.is_key_pressed(KeyboardKey::KEY_DOWN) {
+            self.selected = (self.selected + 1) % count;
+        }
+        if input.is_key_pressed(KeyboardKey::KEY_UP) {
+            self.selected = (self.selected + count - 1) % count;
+        }
+        if !input.is_key_pressed(KeyboardKey::KEY_ENTER) {
+            return None;
+        }
+        self.open = false;
+        Some(self.scenes[self.selected].clone())
+    }
+
+    /// The list, centered over whatever is on screen.
+    pub fn draw(&self, d: &mut RaylibDrawHandle, width: i32, height: i32) {
+        if !self.open {
+            return;
+        }
+        let row = 24;
+        let (panel_w, panel_h) = (320, 70 + row * self.scenes.len().max(1) as i32);
+        let (x, y) = ((width - panel_w) / 2, (height - panel_h) / 2);
+        d.draw_rectangle(x, y, panel_w, panel_h, Color::new(10, 12, 30, 230));
+        d.draw_rectangle_lines(x, y, panel_w, panel_h, Color::LIGHTGRAY);
+        d.draw_text("Scenes", x + 16, y + 12, 20, Color::WHITE);
+        if self.scenes.is_empty() {
+            d.draw_text(&format!("No scenes in {}", SCENE_DIR), x + 16, y + 44, 16, Color::LIGHTGRAY);
+        }
+        for (i, scene) in self.scenes.iter().enumerate() {
+            let top = y + 44 + row * i as i32;
+            if i == self.selected {
+                d.draw_rectangle(x + 8, top - 4, panel_w - 16, row, Color::new(242, 191, 89, 90));
+            }
+            let marker = if self.current.as_ref() == Some(scene) { " (running)" } else { "" };
+            d.draw_text(&format!("{}{}", scene_name(scene), marker), x + 16, top, 16, Color::WHITE);
+        }
+        d.draw_text("Up/Down, Enter to load, Tab to close", x + 16, y + panel_h - 22, 10, Color::LIGHTGRAY);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::SceneFile;
+
+    #[test]
+    fn bundled_scenes_are_listed_and_load() {
+        let scenes = find_scenes(SCENE_DIR);
+        assert!(scenes.iter().any(|s| s == DEFAULT_SCENE), "{:?}", scenes);
+        assert!(scenes.len() >= 4, "{:?}", scenes);
+        for scene in &scenes {
+            assert!(!STATE_SUFFIXES.iter().any(|suffix| scene.ends_with(suffix)));
+            let file = SceneFile::load(scene).unwrap_or_else(|e| panic!("{}", e));
+            assert!(file.bodies.iter().any(|b| b.kind == "sun"), "{} has no sun", scene);
+        }
+    }
+}
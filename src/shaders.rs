@@ -1,7 +1,14 @@
 use raylib::prelude::*;
 use crate::vertex::Vertex;
 use crate::fragment::Fragment;
+use crate::framebuffer::GlowTarget;
+use crate::sphere::ScreenSphere;
 use crate::Uniforms;
+use crate::noise::{hash2, skipped_octaves_mean};
+use crate::planetshaders::earth_masks;
+use crate::texture::{Material, Shading, ShadingModel};
+use crate::light::sun_visibility;
+use crate::params::{param, ParamSet};
 
 use crate::matrix::multiply_matrix_vector4;
 
@@ -16,14 +23,11 @@ pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
     1.0
   );
 
-  // Apply Model transformation
-  let world_position = multiply_matrix_vector4(&uniforms.model_matrix, &position_vec4);
+  // Model, View and Projection at once (precomputed per object)
+  let clip_position = multiply_matrix_vector4(&uniforms.object.mvp, &position_vec4);
 
-  // Apply View transformation (camera)
-  let view_position = multiply_matrix_vector4(&uniforms.view_matrix, &world_position);
-
-  // Apply Projection transformation (perspective)
-  let clip_position = multiply_matrix_vector4(&uniforms.projection_matrix, &view_position);
+  // World position, for lighting
+  let world_position = multiply_matrix_vector4(&uniforms.object.model, &position_vec4);
 
   // Perform perspective division to get NDC (Normalized Device Coordinates)
   let ndc = if clip_position.w != 0.0 {
@@ -36,14 +40,12 @@ pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
       Vector3::new(clip_position.x, clip_position.y, clip_position.z)
   };
 
-  // Apply Viewport transformation to get screen coordinates
-  let ndc_vec4 = Vector4::new(ndc.x, ndc.y, ndc.z, 1.0);
-  let screen_position = multiply_matrix_vector4(&uniforms.viewport_matrix, &ndc_vec4);
-
+  // Apply Viewport transformation to get screen coordinates (just a scale and offset)
+  let viewport = &uniforms.frame.viewport;
   let transformed_position = Vector3::new(
-      screen_position.x,
-      screen_position.y,
-      screen_position.z,
+      ndc.x * viewport.m0 + viewport.m12,
+      ndc.y * viewport.m5 + viewport.m13,
+      ndc.z * viewport.m10 + viewport.m14,
   );
 
   // Create a new Vertex with the transformed position
@@ -53,18 +55,18 @@ pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
     tex_coords: vertex.tex_coords,
     color: vertex.color,
     transformed_position,
-    transformed_normal: transform_normal(&vertex.normal, &uniforms.model_matrix),
+    transformed_normal: transform_normal(&vertex.normal, &uniforms.object.normal_matrix),
+    world_position: Vector3::new(world_position.x, world_position.y, world_position.z),
   }
 }
 
-fn transform_normal(normal: &Vector3, model_matrix: &Matrix) -> Vector3 {
+fn transform_normal(normal: &Vector3, normal_matrix: &Matrix) -> Vector3 {
     // Convert normal to homogeneous coordinates (w=0 for direction vectors)
     let normal_vec4 = Vector4::new(normal.x, normal.y, normal.z, 0.0);
 
-    // Transform the normal by the model matrix.
-    // For non-uniform scaling, the inverse transpose of the model matrix should be used.
-    // For uniform scaling (as in this project), transforming by the model matrix is sufficient.
-    let transformed_normal_vec4 = multiply_matrix_vector4(model_matrix, &normal_vec4);
+    // Transform the normal by the inverse transpose of the model matrix, which keeps it
+    // perpendicular to the surface even under non-uniform scaling.
+    let transformed_normal_vec4 = multiply_matrix_vector4(normal_matrix, &normal_vec4);
 
     // Convert back to Vector3 and normalize
     let mut transformed_normal = Vector3::new(
@@ -72,6 +74,13 @@ fn transform_normal(normal: &Vector3, model_matrix: &Matrix) -> Vector3 {
         transformed_normal_vec4.y,
         transformed_normal_vec4.z,
     );
+    // A degenerate model matrix (a body at scale 0) has no inverse: keep the model-space
+    // normal rather than spreading NaNs through the lighting
+    let length = transformed_normal.length();
+    if !length.is_finite() || length < 1e-20 {
+        let length = normal.length();
+        return if length.is_finite() && length > 0.0 { *normal / length } else { Vector3::new(0.0, 1.0, 0.0) };
+    }
     transformed_normal.normalize();
     transformed_normal
 }
@@ -115,50 +124,420 @@ fn saturate_vec3(v: Vector3) -> Vector3 {
     )
 }
 
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = clamp((x - edge0) / (edge1 - edge0), 0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Antialiased step: 0 below `edge`, 1 above, blurred over `fwidth` (how much `x`
+/// changes across one pixel) so the transition never gets sharper than a pixel.
+pub fn filtered_step(edge: f32, x: f32, fwidth: f32) -> f32 {
+    let half = (fwidth * 0.5).max(1e-5);
+    smoothstep(edge - half, edge + half, x)
+}
+
+/// Sum of the absolute per-pixel changes of a quantity with gradient (`scale_u`, `scale_v`) in uv.
+fn uv_fwidth(fragment: &Fragment, scale_u: f32, scale_v: f32) -> f32 {
+    (fragment.duvdx.x * scale_u).abs() + (fragment.duvdx.y * scale_v).abs()
+        + (fragment.duvdy.x * scale_u).abs() + (fragment.duvdy.y * scale_v).abs()
+}
+
+/// fbm of up to `octaves` octaves that drops those finer than a pixel. `fwidth` is the
+/// per-pixel change of `uv`.
+fn filtered_fbm(uv: Vector2, fwidth: f32, octaves: u32) -> f32 {
+    let mut value = 0.0;
+    let mut amp = 0.5;
+    let mut freq = 1.0;
+
+    for _ in 0..octaves {
+        // Fade an octave out as its cells approach pixel size, replacing it with its mean
+        let fade = 1.0 - clamp(fwidth * freq * 2.0 - 0.5, 0.0, 1.0);
+        let p = Vector2::new(uv.x * freq, uv.y * freq);
+        value += mix(0.5, hash2(p), fade) * amp;
+        freq *= 2.0;
+        amp *= 0.5;
+    }
+
+    // Octaves skipped at lower quality settings contribute their mean
+    value + skipped_octaves_mean(amp)
+}
+
 // ------------------------
-// Planet-specific fragment shaders
+// Lighting
 // ------------------------
 
-// 🌞 Sun / star: add a soft radial glow and slight color burn
-pub fn sun_fragment_shader(fragment: &Fragment, _uniforms: &Uniforms) -> Vector3 {
-    let base = fragment.color;
-    let pos = fragment.position;
+/// Diffuse lighting from the sun using the per-body irradiance in the uniforms.
+pub fn lambert_lighting(color: Vector3, fragment: &Fragment, uniforms: &Uniforms) -> Vector3 {
+    let mut to_light = Vector3::new(
+        uniforms.frame.light_position.x - fragment.world_position.x,
+        uniforms.frame.light_position.y - fragment.world_position.y,
+        uniforms.frame.light_position.z - fragment.world_position.z,
+    );
+    to_light.normalize();
+    let mut n = fragment.normal;
+    n.normalize();
+
+    let mut diffuse = (n.x * to_light.x + n.y * to_light.y + n.z * to_light.z).max(0.0);
+    if diffuse > 0.0 && !uniforms.object.occluders.is_empty() {
+        diffuse *= sun_visibility(fragment.world_position, uniforms.frame.light_position, uniforms.frame.sun_radius, &uniforms.object.occluders);
+    }
+    let lit = light_color(color, diffuse, sky_ambient(n, uniforms), uniforms);
+
+    // Secondary "bounce" light (earthshine): diffuse only, mostly visible on the night side
+    bounce_lighting(lit, color, n, fragment, uniforms)
+}
+
+/// Sunlight on an opaque surface with the body's shading model: Lambert plus a
+/// Blinn-Phong highlight, or Cook-Torrance GGX.
+pub fn surface_lighting(color: Vector3, fragment: &Fragment, uniforms: &Uniforms) -> Vector3 {
+    let shading = uniforms.object.material.shading;
+    let lit = match shading.model {
+        ShadingModel::Phong => {
+            let lit = lambert_lighting(color, fragment, uniforms);
+            // Roughness to Blinn-Phong exponent, the usual 2/α² - 2 with α = roughness²
+            let alpha = shading.roughness * shading.roughness;
+            let shininess = (2.0 / (alpha * alpha) - 2.0).clamp(1.0, 2000.0);
+            let strength = (1.0 - shading.roughness).powi(2);
+            if strength < 0.02 {
+                lit
+            } else {
+                saturate_vec3(lit + sun_glint(fragment, uniforms, shininess) * strength)
+            }
+        }
+        ShadingModel::Ggx => ggx_lighting(color, fragment, uniforms, &shading),
+    };
+    fill_lighting(lit, color, fragment, uniforms)
+}
+
+/// Beauty mode's fill and rim lights, as shares of the sunlight the body receives. The
+/// rim light sits behind the subject on the side away from the sun and only catches
+/// the silhouette, so the terminator still shows where the sun is.
+fn fill_lighting(lit: Vector3, color: Vector3, fragment: &Fragment, uniforms: &Uniforms) -> Vector3 {
+    let Some(rig) = uniforms.frame.fill_lights else {
+        return lit;
+    };
+    let n = fragment.normal.normalized();
+    let fill = n.dot(rig.fill_direction).max(0.0);
+
+    let to_sun = (uniforms.frame.light_position - fragment.world_position).normalized();
+    let sun_side = to_sun - rig.forward * to_sun.dot(rig.forward);
+    let to_rim = if sun_side.length() > 1e-3 { (rig.forward - sun_side.normalized()).normalized() } else { rig.forward };
+    let to_eye = (uniforms.frame.camera_pos - fragment.world_position).normalized();
+    let edge = (1.0 - n.dot(to_eye).max(0.0)).powi(2);
+    let rim = n.dot(to_rim).max(0.0) * edge;
+
+    let light = rig.fill * fill + rig.rim * rim;
+    let e = uniforms.object.irradiance;
+    saturate_vec3(Vector3::new(
+        lit.x + color.x * light.x * e.x,
+        lit.y + color.y * light.y * e.y,
+        lit.z + color.z * light.z * e.z,
+    ))
+}
+
+/// Cook-Torrance specular (GGX distribution, Smith-Schlick geometry, Fresnel-Schlick)
+/// over a Lambert diffuse that only gets the light the specular didn't reflect. Both
+/// terms are scaled by π so a rough dielectric is as bright as `lambert_lighting`.
+fn ggx_lighting(color: Vector3, fragment: &Fragment, uniforms: &Uniforms, shading: &Shading) -> Vector3 {
+    let n = fragment.normal.normalized();
+    let to_light = (uniforms.frame.light_position - fragment.world_position).normalized();
+    let to_eye = (uniforms.frame.camera_pos - fragment.world_position).normalized();
+    let e = uniforms.object.irradiance;
+    let sky = sky_ambient(n, uniforms);
+    let ambient = Vector3::new(color.x * sky.x, color.y * sky.y, color.z * sky.z);
+
+    let mut n_dot_l = n.dot(to_light);
+    if n_dot_l > 0.0 && !uniforms.object.occluders.is_empty() {
+        n_dot_l *= sun_visibility(fragment.world_position, uniforms.frame.light_position, uniforms.frame.sun_radius, &uniforms.object.occluders);
+    }
+    let n_dot_v = n.dot(to_eye).max(1e-4);
+    if n_dot_l <= 0.0 {
+        return bounce_lighting(saturate_vec3(ambient), color, n, fragment, uniforms);
+    }
 
-    // Approximate screen center (adjust if your resolution changes)
-    let cx = 400.0;
-    let cy = 300.0;
+    let half = (to_light + to_eye).normalized();
+    let n_dot_h = n.dot(half).max(0.0);
+    let v_dot_h = to_eye.dot(half).max(0.0);
 
-    let dx = pos.x - cx;
-    let dy = pos.y - cy;
-    let r = (dx * dx + dy * dy).sqrt();
+    let alpha = shading.roughness * shading.roughness;
+    let a2 = alpha * alpha;
+    let denom = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    let d = a2 / (std::f32::consts::PI * denom * denom);
 
-    // Glow stronger near center, fading outward
-    let glow = clamp(1.0 - r / 350.0, 0.0, 1.0);
-    let glow2 = glow * glow;
+    let k = (shading.roughness + 1.0).powi(2) / 8.0;
+    let g = (n_dot_l / (n_dot_l * (1.0 - k) + k)) * (n_dot_v / (n_dot_v * (1.0 - k) + k));
+
+    let f0 = mix_vec3(Vector3::new(0.04, 0.04, 0.04), color, shading.metalness);
+    let f = f0 + (Vector3::one() - f0) * (1.0 - v_dot_h).powi(5);
+
+    let specular = f * (d * g / (4.0 * n_dot_l * n_dot_v) * std::f32::consts::PI);
+    let kd = (Vector3::one() - f) * (1.0 - shading.metalness);
+    let diffuse = Vector3::new(kd.x * color.x, kd.y * color.y, kd.z * color.z);
+
+    let reflected = (diffuse + specular) * n_dot_l;
+    let lit = ambient + Vector3::new(reflected.x * e.x, reflected.y * e.y, reflected.z * e.z);
+    bounce_lighting(saturate_vec3(lit), color, n, fragment, uniforms)
+}
+
+/// Adds the secondary "bounce" light (earthshine), diffuse only, to an already lit color.
+fn bounce_lighting(lit: Vector3, color: Vector3, n: Vector3, fragment: &Fragment, uniforms: &Uniforms) -> Vector3 {
+    match uniforms.object.bounce {
+        Some(bounce) => {
+            let to_bounce = (bounce.position - fragment.world_position).normalized();
+            let d = n.dot(to_bounce).max(0.0);
+            let e = bounce.irradiance;
+            saturate_vec3(Vector3::new(
+                lit.x + color.x * e.x * d,
+                lit.y + color.y * e.y * d,
+                lit.z + color.z * e.z * d,
+            ))
+        }
+        None => lit,
+    }
+}
+
+/// Lighting for thin, translucent bodies (rings) that scatter light regardless of the surface normal.
+pub fn scattered_lighting(color: Vector3, uniforms: &Uniforms) -> Vector3 {
+    light_color(color, 0.5, uniforms.frame.sky.average() * ambient_scale(uniforms), uniforms)
+}
+
+/// Ambient light on a surface with normal `n`: the skybox's irradiance from that side.
+fn sky_ambient(n: Vector3, uniforms: &Uniforms) -> Vector3 {
+    uniforms.frame.sky.at(n) * ambient_scale(uniforms)
+}
+
+/// How much of the sky's light the body takes in: its material's own `ambient`, or the scene's.
+fn ambient_scale(uniforms: &Uniforms) -> f32 {
+    uniforms.object.material.shading.ambient.unwrap_or(uniforms.frame.ambient)
+}
+
+/// Base color from the body's texture maps: the day map, with the cloud map laid over
+/// it (or over the procedural `color` when there's no day map).
+pub fn material_color(color: Vector3, fragment: &Fragment, material: &Material) -> Vector3 {
+    let uv = material.uv(fragment);
+    let surface = match &material.day {
+        Some(day) => day.sample(uv, material.wrap),
+        None => color,
+    };
+    match &material.clouds {
+        Some(clouds) => {
+            let cover = clouds.sample(uv, material.wrap).x;
+            surface * (1.0 - cover) + Vector3::new(cover, cover, cover)
+        }
+        None => surface,
+    }
+}
+
+/// Adds the texture effects that depend on lighting to an already lit color: night
+/// lights where the sun doesn't reach and a sun glint where the specular mask is set.
+pub fn material_lighting(lit: Vector3, fragment: &Fragment, uniforms: &Uniforms) -> Vector3 {
+    let material = &uniforms.object.material;
+    if material.night.is_none() && material.specular.is_none() {
+        return lit;
+    }
+    let n = fragment.normal.normalized();
+    let to_light = (uniforms.frame.light_position - fragment.world_position).normalized();
+    let diffuse = n.dot(to_light);
+    let uv = material.uv(fragment);
+    let mut color = lit;
+
+    if let Some(night) = &material.night {
+        let darkness = clamp(-diffuse * 4.0 + 0.2, 0.0, 1.0);
+        color += night.sample(uv, material.wrap) * darkness;
+    }
+
+    if let Some(specular) = &material.specular {
+        color += sun_glint(fragment, uniforms, 120.0) * (specular.sample(uv, material.wrap).x * 1.5);
+    }
+    saturate_vec3(color)
+}
+
+/// Blinn-Phong reflection of the sun towards the camera, in received sunlight.
+/// Higher `shininess` makes a smaller, sharper spot.
+fn sun_glint(fragment: &Fragment, uniforms: &Uniforms, shininess: f32) -> Vector3 {
+    let n = fragment.normal.normalized();
+    let to_light = (uniforms.frame.light_position - fragment.world_position).normalized();
+    let diffuse = n.dot(to_light);
+    if diffuse <= 0.0 {
+        return Vector3::zero();
+    }
+    let visibility = sun_visibility(fragment.world_position, uniforms.frame.light_position, uniforms.frame.sun_radius, &uniforms.object.occluders);
+    let to_eye = (uniforms.frame.camera_pos - fragment.world_position).normalized();
+    let half = (to_light + to_eye).normalized();
+    let k = n.dot(half).max(0.0).powf(shininess) * diffuse.min(0.2) * 5.0 * visibility; // Fades in at the terminator
+    Vector3::new(uniforms.object.irradiance.x * k, uniforms.object.irradiance.y * k, uniforms.object.irradiance.z * k)
+}
 
-    let boosted = Vector3::new(
-        base.x * (1.0 + 1.8 * glow2),
-        base.y * (1.0 + 1.2 * glow2),
-        base.z * (1.0 + 0.6 * glow2),
+/// Earth's procedural specular: a tight sun glint on open water, where clouds don't
+/// cover it, and a broad faint sheen on the ice caps. Land stays diffuse. Bodies with
+/// a loaded specular mask get their glint from `material_lighting` instead, and a day
+/// map alone doesn't match the procedural continents, so both skip this.
+pub fn earth_specular(lit: Vector3, fragment: &Fragment, uniforms: &Uniforms) -> Vector3 {
+    if uniforms.object.material.specular.is_some() || uniforms.object.material.day.is_some() {
+        return lit;
+    }
+    let masks = earth_masks(fragment.uv, &uniforms.surface_noise(), &uniforms.object.params);
+    let water = (1.0 - masks.land) * (1.0 - masks.ice) * (1.0 - masks.clouds * 0.55);
+    let glint = sun_glint(fragment, uniforms, 200.0) * (water * 1.5);
+    let sheen = sun_glint(fragment, uniforms, 6.0) * (masks.ice * 0.12);
+    saturate_vec3(lit + glint + sheen)
+}
+
+/// Reddened sunlight of sunrise and sunset along the terminator of a body with air.
+/// Light grazing the atmosphere crosses so much of it that only the warm end is left,
+/// and refraction bends some past the geometric terminator. The body's `sunset_width`
+/// (in N·L; 0, the default for most kinds, leaves airless bodies alone) sets how far
+/// into the day side the band reaches, `refraction` how far into the night, and the
+/// `sunset` ramp its colors from the night edge to the day edge. The tint is strongest
+/// when the terminator is seen edge-on, across the disk.
+pub fn sunset_band(lit: Vector3, color: Vector3, fragment: &Fragment, uniforms: &Uniforms, object_type: &str) -> Vector3 {
+    let params = &uniforms.object.params;
+    let width = param(params, object_type, "sunset_width");
+    if width <= 0.0 {
+        return lit;
+    }
+    let refraction = param(params, object_type, "refraction").max(1e-3);
+    let n = fragment.normal.normalized();
+    let to_light = (uniforms.frame.light_position - fragment.world_position).normalized();
+    let to_eye = (uniforms.frame.camera_pos - fragment.world_position).normalized();
+    let n_dot_l = n.dot(to_light);
+    if n_dot_l <= -refraction || n_dot_l >= width {
+        return lit;
+    }
+
+    let t = (n_dot_l + refraction) / (width + refraction);
+    let tint = uniforms.object.ramps.sample("sunset", t, &SUNSET_RAMP);
+    let along = 1.0 - to_eye.dot(to_light).abs();
+    let strength = smoothstep(-refraction, 0.0, n_dot_l) * (1.0 - smoothstep(0.0, width, n_dot_l)) * mix(0.4, 1.0, along);
+
+    // Lambert with its corner at the terminator rounded off over ±refraction
+    let soft = if n_dot_l < refraction { (n_dot_l + refraction).powi(2) / (4.0 * refraction) } else { n_dot_l };
+    let visibility = if uniforms.object.occluders.is_empty() {
+        1.0
+    } else {
+        sun_visibility(fragment.world_position, uniforms.frame.light_position, uniforms.frame.sun_radius, &uniforms.object.occluders)
+    };
+    let e = uniforms.object.irradiance;
+    let sunlight = Vector3::new(color.x * e.x, color.y * e.y, color.z * e.z) * visibility;
+
+    // Only the sunlight is reddened; ambient and night-side light stay as they are
+    let filtered = mix_vec3(Vector3::one(), tint, strength) - Vector3::one();
+    let direct = sunlight * n_dot_l.max(0.0);
+    let refracted = sunlight * (soft - n_dot_l.max(0.0));
+    saturate_vec3(
+        lit + Vector3::new(direct.x * filtered.x, direct.y * filtered.y, direct.z * filtered.z)
+            + Vector3::new(refracted.x * tint.x, refracted.y * tint.y, refracted.z * tint.z),
+    )
+}
+
+/// Default `sunset` ramp: deep red where the sun has just set, orange, then plain daylight.
+const SUNSET_RAMP: [(f32, Vector3); 3] = [
+    (0.0, Vector3 { x: 0.6, y: 0.12, z: 0.04 }),
+    (0.45, Vector3 { x: 1.0, y: 0.45, z: 0.15 }),
+    (1.0, Vector3 { x: 1.0, y: 1.0, z: 1.0 }),
+];
+
+fn light_color(color: Vector3, diffuse: f32, ambient: Vector3, uniforms: &Uniforms) -> Vector3 {
+    let e = uniforms.object.irradiance;
+    saturate_vec3(Vector3::new(
+        color.x * (ambient.x + e.x * diffuse),
+        color.y * (ambient.y + e.y * diffuse),
+        color.z * (ambient.z + e.z * diffuse),
+    ))
+}
+
+// ------------------------
+// Planet-specific fragment shaders
+// ------------------------
+
+/// Fragment position relative to the object's projected center, in units of its
+/// projected radius (1 = the silhouette of its bounding sphere), so screen-space
+/// effects keep their look at any resolution or render scale.
+fn object_offset(fragment: &Fragment, uniforms: &Uniforms) -> Vector2 {
+    let radius = uniforms.object.screen_radius.max(1.0);
+    Vector2::new(
+        (fragment.position.x - uniforms.object.screen_center.x) / radius,
+        (fragment.position.y - uniforms.object.screen_center.y) / radius,
+    )
+}
+
+/// Color of the chromosphere's rim, the pinkish red of hydrogen, a little brighter than the
+/// darkened limb around it.
+const CHROMOSPHERE_COLOR: Vector3 = Vector3 { x: 1.4, y: 0.77, z: 0.63 };
+/// Color of the glow around the sun, the orange of its darkened limb.
+const HALO_COLOR: Vector3 = Vector3 { x: 1.0, y: 0.62, z: 0.3 };
+
+// 🌞 Sun / star: limb darkening from the view angle, reddening towards the edge, and the chromosphere's thin rim
+pub fn sun_fragment_shader(fragment: &Fragment, uniforms: &Uniforms) -> Vector3 {
+    let base = fragment.color;
+    let params = &uniforms.object.params;
+
+    // μ: cosine between the surface and the line of sight, 1 at the disc's center and 0 on its edge
+    let to_eye = (uniforms.frame.camera_pos - fragment.world_position).normalized();
+    let mu = clamp(fragment.normal.normalized().dot(to_eye), 0.0, 1.0);
+
+    // Linear limb-darkening law I(μ)/I(1) = 1 - u(1 - μ); blue darkens most, so the limb reddens
+    let limb = |u: f32| 1.0 - clamp(u, 0.0, 1.0) * (1.0 - mu);
+    // Brightness rolls the disc off into white instead of multiplying it past 1, where the
+    // clip would flatten most of the darkening; a pulse from the modulation input still can
+    let brightness = param(params, "sun", "brightness").max(1e-3);
+    let tone = |c: f32| (1.0 - (-c * uniforms.frame.sun_pulse * brightness).exp()) / (1.0 - (-brightness).exp());
+    let disc = Vector3::new(
+        tone(base.x * limb(param(params, "sun", "limb_red"))),
+        tone(base.y * limb(param(params, "sun", "limb_green"))),
+        tone(base.z * limb(param(params, "sun", "limb_blue"))),
     );
 
-    saturate_vec3(boosted)
+    // Chromosphere: a narrow band in μ just inside the silhouette. A pixel and a half in
+    // from the edge μ is about √(3 / radius in pixels), so the band stays a crisp pixel or
+    // two wide on a small sun instead of vanishing between pixel centers
+    let pixel_mu = (3.0 / uniforms.object.screen_radius.max(1.0)).sqrt();
+    let width = param(params, "sun", "rim_width").max(pixel_mu);
+    let rim = (1.0 - smoothstep(0.0, width, mu)) * clamp(param(params, "sun", "rim"), 0.0, 1.0);
+
+    saturate_vec3(mix_vec3(disc, CHROMOSPHERE_COLOR * uniforms.frame.sun_pulse, rim))
+}
+
+/// Soft glow just outside the sun's silhouette, added over whatever lies behind it: the
+/// light scattered around the disc. It fades exponentially with the distance past the
+/// edge over `halo_width` sun radii (at least two pixels), and skips pixels where
+/// something nearer than the sun was drawn.
+pub fn draw_sun_halo(target: &mut impl GlowTarget, sphere: &ScreenSphere, params: &ParamSet, sun_pulse: f32) {
+    let strength = param(params, "sun", "halo") * sun_pulse;
+    if strength <= 0.0 {
+        return;
+    }
+    let radius = sphere.radius_px;
+    let width = (param(params, "sun", "halo_width") * radius).max(2.0);
+    // Past five widths the glow is under 1% of its strength
+    let extent = radius + width * 5.0;
+    let (w, h) = target.size();
+    let min_x = (sphere.center_px.x - extent).floor().max(0.0) as i32;
+    let max_x = (sphere.center_px.x + extent).ceil().min(w as f32 - 1.0) as i32;
+    let min_y = (sphere.center_px.y - extent).floor().max(0.0) as i32;
+    let max_y = (sphere.center_px.y + extent).ceil().min(h as f32 - 1.0) as i32;
+
+    let mode = target.depth_mode();
+    for y in min_y..=max_y {
+        for x in min_x..=max_x {
+            let past_edge = (x as f32 + 0.5 - sphere.center_px.x).hypot(y as f32 + 0.5 - sphere.center_px.y) - radius;
+            if past_edge <= 0.0 || mode.closer(target.depth_at(x, y), sphere.front_depth) {
+                continue;
+            }
+            target.add_color(x, y, HALO_COLOR * (strength * (-past_edge / width).exp()));
+        }
+    }
 }
 
 // 🪨 Rocky planet: add gentle vignette and contrast to make terrain pop
-pub fn rocky_fragment_shader(fragment: &Fragment, _uniforms: &Uniforms) -> Vector3 {
+pub fn rocky_fragment_shader(fragment: &Fragment, uniforms: &Uniforms) -> Vector3 {
     let base = fragment.color;
-    let pos = fragment.position;
 
-    // Use distance from center of the object on screen approximately
-    let cx = 400.0;
-    let cy = 300.0;
-    let dx = pos.x - cx;
-    let dy = pos.y - cy;
-    let r = (dx * dx + dy * dy).sqrt();
+    // Distance from the center of the object on screen
+    let r = object_offset(fragment, uniforms).length();
 
-    // Vignette: a bit darker further from center
-    let vignette = mix(1.0, 0.7, clamp(r / 450.0, 0.0, 1.0));
+    // Vignette: a bit darker towards the limb
+    let vignette = mix(1.0, 0.7, clamp(r, 0.0, 1.0));
 
     // Simple contrast curve
     let mut c = base;
@@ -171,10 +550,38 @@ pub fn rocky_fragment_shader(fragment: &Fragment, _uniforms: &Uniforms) -> Vecto
     saturate_vec3(c)
 }
 
+// 🪨 Plated rocky planet: crisp, antialiased seams between the plates of the vertex shader
+pub fn rocky_plates_fragment_shader(fragment: &Fragment, uniforms: &Uniforms) -> Vector3 {
+    let c = rocky_fragment_shader(fragment, uniforms);
+
+    // Same plate layout as rocky_planet_vertex_shader
+    let (scale_u, scale_v) = (6.0, 4.0);
+    let plate_uv = Vector2::new(fragment.uv.x * scale_u, fragment.uv.y * scale_v);
+    let cell = Vector2::new(plate_uv.x.floor(), plate_uv.y.floor());
+    let local = Vector2::new(plate_uv.x.fract(), plate_uv.y.fract());
+    let jitter_x = uniforms.object.noise.hash(cell, 0) * 0.3 - 0.15;
+    let jitter_y = uniforms.object.noise.hash(cell, 1) * 0.3 - 0.15;
+    let dx = local.x - (0.5 + jitter_x);
+    let dy = local.y - (0.5 + jitter_y);
+    let dist = (dx * dx + dy * dy).sqrt();
+
+    // Seam where the distance crosses the plate radius
+    let plate_radius = param(&uniforms.object.params, "rocky1", "plate_radius");
+    let seam_half_width = 0.02;
+    let fw = uv_fwidth(fragment, scale_u, scale_v);
+    let seam = 1.0 - filtered_step(seam_half_width, (dist - plate_radius).abs(), fw);
+    // Thin seams on a tiny planet average out instead of flickering
+    let strength = 0.6 * (1.0 - clamp(fw * 4.0 - 0.5, 0.0, 1.0));
+
+    let edge_color = Vector3::new(0.15, 0.07, 0.05);
+    saturate_vec3(mix_vec3(c, edge_color, seam * strength))
+}
+
 // 🪐 Gas giant: emphasize bands with subtle screen-space waves
-pub fn gas_giant_fragment_shader(fragment: &Fragment, _uniforms: &Uniforms) -> Vector3 {
+pub fn gas_giant_fragment_shader(fragment: &Fragment, uniforms: &Uniforms) -> Vector3 {
     let base = fragment.color;
-    let pos = fragment.position;
+    // Screen position in units of a 600-pixel-tall screen, so the waves keep their size at any resolution
+    let pos = fragment.position * (600.0 / uniforms.frame.screen_size.y.max(1.0));
 
     // Wave pattern along y (vertical) + a small x-dependent swirl
     let wave = (pos.y / 25.0).sin() * 0.5 + 0.5;
@@ -183,10 +590,27 @@ pub fn gas_giant_fragment_shader(fragment: &Fragment, _uniforms: &Uniforms) -> V
     let band_boost = mix(0.8, 1.3, wave);
     let swirl_mix = mix(0.9, 1.1, swirl);
 
+    // Thin latitude bands on the surface, with contrast faded out as they approach
+    // a pixel in width so small planets don't shimmer
+    let band_freq = 36.0;
+    let fw = uv_fwidth(fragment, 0.0, band_freq);
+    let turbulence_uv = Vector2::new(fragment.uv.x * 6.0, fragment.uv.y * 24.0) + uniforms.object.noise.offset(7);
+    let turbulence = filtered_fbm(turbulence_uv, uv_fwidth(fragment, 6.0, 24.0), uniforms.frame.fbm_octaves);
+    let phase = fragment.uv.y * band_freq + (turbulence - 0.5) * 0.6;
+    let thin = (phase * 2.0 * std::f32::consts::PI).sin() * 0.5 + 0.5;
+    let thin = mix(0.5, thin, 1.0 - clamp(fw * 2.0, 0.0, 1.0));
+
+    // Dark belt edges, widened to at least a pixel
+    let belt = (phase.rem_euclid(1.0) - 0.5).abs();
+    let belt_line = 1.0 - filtered_step(0.04, belt, fw);
+    let belt_strength = 0.25 * (1.0 - clamp(fw * 2.0 - 0.5, 0.0, 1.0));
+
+    let detail = mix(0.92, 1.08, thin) * (1.0 - belt_line * belt_strength);
+
     let mut c = Vector3::new(
-        base.x * band_boost * swirl_mix,
-        base.y * band_boost,
-        base.z * band_boost * swirl_mix,
+        base.x * band_boost * swirl_mix * detail,
+        base.y * band_boost * detail,
+        base.z * band_boost * swirl_mix * detail,
     );
 
     // Slight color shift towards magenta in darker areas
@@ -197,18 +621,12 @@ pub fn gas_giant_fragment_shader(fragment: &Fragment, _uniforms: &Uniforms) -> V
 }
 
 // 🌍 Earth-like planet: soft atmospheric haze and subtle glow on bright areas
-pub fn earth_fragment_shader(fragment: &Fragment, _uniforms: &Uniforms) -> Vector3 {
+pub fn earth_fragment_shader(fragment: &Fragment, uniforms: &Uniforms) -> Vector3 {
     let base = fragment.color;
-    let pos = fragment.position;
-
-    let cx = 400.0;
-    let cy = 300.0;
-    let dx = pos.x - cx;
-    let dy = pos.y - cy;
-    let r = (dx * dx + dy * dy).sqrt();
+    let r = object_offset(fragment, uniforms).length();
 
     // "Atmospheric" fade at the edge: mix with space blue
-    let edge = clamp(r / 350.0, 0.0, 1.0);
+    let edge = clamp(r, 0.0, 1.0);
     let atmosphere_color = Vector3::new(0.05, 0.12, 0.25);
     let with_atmo = mix_vec3(base, atmosphere_color, edge * 0.35);
 
@@ -222,12 +640,11 @@ pub fn earth_fragment_shader(fragment: &Fragment, _uniforms: &Uniforms) -> Vecto
 }
 
 // 🌑 Moon: harsher contrast and subtle specular-like highlight
-pub fn moon_fragment_shader(fragment: &Fragment, _uniforms: &Uniforms) -> Vector3 {
+pub fn moon_fragment_shader(fragment: &Fragment, uniforms: &Uniforms) -> Vector3 {
     let base = fragment.color;
-    let pos = fragment.position;
 
-    // Simple directional light approximation using screen-space x
-    let light_dir = (pos.x / 200.0).sin() * 0.5 + 0.5;
+    // Simple directional light approximation across the moon's disc
+    let light_dir = (object_offset(fragment, uniforms).x * 2.0).sin() * 0.5 + 0.5;
 
     // High contrast grey
     let mut c = base;
@@ -243,83 +660,203 @@ pub fn moon_fragment_shader(fragment: &Fragment, _uniforms: &Uniforms) -> Vector
     saturate_vec3(c)
 }
 
-// 💿 Ring: fade edges and add fine radial band variation
-pub fn ring_fragment_shader(fragment: &Fragment, _uniforms: &Uniforms) -> Vector3 {
+// 💿 Ring: bands, gaps and edge fade from the radial coordinate the vertex stage
+// stores in tex_coords.x (0 = inner edge, 1 = outer edge), so they stay on the geometry
+pub fn ring_fragment_shader(fragment: &Fragment, uniforms: &Uniforms) -> Vector3 {
     let base = fragment.color;
-    let pos = fragment.position;
+    let t = clamp(fragment.tex_coords.x, 0.0, 1.0);
 
-    let cx = 400.0;
-    let cy = 300.0;
-    let dx = pos.x - cx;
-    let dy = pos.y - cy;
-    let r = (dx * dx + dy * dy).sqrt();
-
-    // Fade at inner/outer edges
-    let inner = 80.0;
-    let outer = 260.0;
-    let t = clamp((r - inner) / (outer - inner), 0.0, 1.0);
+    // A multi-ring system takes its color from the baked profile; its density goes in the alpha
+    if let Some(profile) = &uniforms.object.ring_profile {
+        return profile.sample(t).0;
+    }
 
     // Fine radial bands
-    let band1 = (r / 6.0).sin() * 0.5 + 0.5;
-    let band2 = (r / 3.0).cos() * 0.5 + 0.5;
+    let band1 = (t * 60.0).sin() * 0.5 + 0.5;
+    let band2 = (t * 130.0).cos() * 0.5 + 0.5;
     let band_mix = 0.6 * band1 + 0.4 * band2;
-
     let band_color = mix_vec3(base, Vector3::new(0.9, 0.9, 0.95), band_mix * 0.3);
 
-    // Vignette fade
-    let fade = clamp(1.0 - (t - 0.5).abs() * 1.8, 0.0, 1.0);
-    let color_final = Vector3::new(
-        band_color.x * fade,
-        band_color.y * fade,
-        band_color.z * fade,
-    );
+    let k = ring_density(t);
+    saturate_vec3(Vector3::new(band_color.x * k, band_color.y * k, band_color.z * k))
+}
 
-    saturate_vec3(color_final)
+/// How much ring material there is at radial coordinate `t` (0 = inner edge, 1 = outer
+/// edge), 0 to 1: a dark division two thirds of the way out, a fainter one near the outer
+/// edge, and a fade towards both edges.
+pub fn ring_density(t: f32) -> f32 {
+    let t = clamp(t, 0.0, 1.0);
+    let main_gap = smoothstep(0.015, 0.035, (t - 0.64).abs());
+    let outer_gap = smoothstep(0.005, 0.015, (t - 0.88).abs());
+    let gaps = mix(0.12, 1.0, main_gap) * mix(0.5, 1.0, outer_gap);
+    let fade = smoothstep(0.0, 0.12, t) * smoothstep(1.0, 0.9, t);
+    fade * gaps
 }
 
+/// Brightness of ring particles lit from the sunlit side, and looking into the sun through the ring.
+const RING_BACKSCATTER: f32 = 1.0;
+const RING_FORWARD_SCATTER: f32 = 3.0;
+
+/// Ring lighting with the particles' phase function: dusty rings scatter sunlight mostly
+/// forwards, so seen backlit (looking towards the sun through them) they glow brighter and
+/// warmer than from the sunlit side. Sparse parts let more of that light through; the
+/// densest bands shade themselves.
+pub fn ring_scattering(color: Vector3, fragment: &Fragment, uniforms: &Uniforms) -> Vector3 {
+    let to_camera = (uniforms.frame.camera_pos - fragment.world_position).normalized();
+    let to_sun = (uniforms.frame.light_position - fragment.world_position).normalized();
+    // 0 with the sun behind the camera, 1 with the sun straight behind the ring
+    let forward = ((1.0 - to_camera.dot(to_sun)) * 0.5).powi(4);
+
+    let density = match &uniforms.object.ring_profile {
+        Some(profile) => profile.sample(fragment.tex_coords.x).1,
+        None => ring_density(fragment.tex_coords.x),
+    };
+    let gain = mix(RING_BACKSCATTER, RING_FORWARD_SCATTER * (1.0 - 0.6 * density), forward);
+    let tint = mix_vec3(Vector3::new(1.0, 1.0, 1.0), Vector3::new(1.0, 0.88, 0.7), forward);
+
+    let lit = scattered_lighting(color, uniforms);
+    Vector3::new(lit.x * gain * tint.x, lit.y * gain * tint.y, lit.z * gain * tint.z)
+}
 
-// 🚀 Chrome-like shuttle fragment shader: shiny, high-contrast reflections
-pub fn shuttle_chrome_fragment_shader(fragment: &Fragment, _uniforms: &Uniforms) -> Vector3 {
-    let base = fragment.color;
-    let pos = fragment.position;
-
-    // Approximate screen center (same as other shaders; adjust if your resolution changes)
-    let cx = 400.0;
-    let cy = 300.0;
-    let dx = (pos.x - cx) / 400.0;
-    let dy = (pos.y - cy) / 300.0;
-    let r = (dx * dx + dy * dy).sqrt();
-
-    // Start from a chrome-tinted version of the shuttle base color:
-    // push a bit towards a neutral light grey metal.
-    let chrome_tint = Vector3::new(0.82, 0.84, 0.88);
-    let mut color = mix_vec3(base, chrome_tint, 0.6);
-
-    // Screen-space "reflection stripes" across the hull
-    let stripe = ((pos.x * 0.035) + (pos.y * 0.02)).sin() * 0.5 + 0.5; // 0..1
-    let stripe2 = ((pos.x * 0.07) - (pos.y * 0.03)).cos() * 0.5 + 0.5; // 0..1
-    let stripe_mix = 0.6 * stripe + 0.4 * stripe2;
-
-    // Strong highlight band where view angle is grazing (like a chrome rim)
-    let highlight_band = clamp(1.0 - (r - 0.25).abs() * 3.0, 0.0, 1.0);
-    let highlight_strength = (stripe_mix * highlight_band).powf(1.5);
-
-    // Darken away from the viewer to give a glossy falloff
-    let shadow = clamp(r * 1.3, 0.0, 1.0);
-
-    // Base brightness factor: dark shadows + strong highlights
-    let brightness = mix(0.25, 1.0, 1.0 - shadow) + highlight_strength * 1.5;
-
-    color = Vector3::new(
-        color.x * brightness,
-        color.y * brightness,
-        color.z * brightness,
-    );
+/// Coverage of a ring fragment: see-through in the lanes shepherd moons clear, with soft
+/// edges, and as dense as the baked profile of a multi-ring system says.
+pub fn ring_alpha(fragment: &Fragment, uniforms: &Uniforms) -> f32 {
+    let radii = uniforms.object.ring_radii;
+    let t = clamp(fragment.tex_coords.x, 0.0, 1.0);
+    let r = radii.x + t * (radii.y - radii.x);
+    let density = uniforms.object.ring_profile.as_ref().map_or(1.0, |profile| profile.sample(t).1);
+    uniforms
+        .object
+        .ring_gaps
+        .iter()
+        .fold(density, |alpha, gap| alpha * smoothstep(gap.y * 0.5, gap.y, (r - gap.x).abs()))
+}
 
-    // Add a cool-toned reflection color on the brightest parts
-    let reflection_color = Vector3::new(0.90, 0.95, 1.0);
-    let reflection_mask = clamp(highlight_strength * 1.4, 0.0, 1.0);
-    color = mix_vec3(color, reflection_color, reflection_mask);
+// 🚀 Chrome shuttle: the lit hull with the sky mirrored in it. The view ray is reflected
+// about the normal and looks up the prefiltered sky, blurrier the rougher the panels;
+// metalness decides how much of the hull is mirror and tints the reflection with its
+// color, and every surface reflects more at grazing angles (Fresnel-Schlick). Only the
+// sky is reflected, not the planets. Without a prefiltered sky yet, its irradiance
+// stands in as a very blurry one.
+pub fn shuttle_chrome_fragment_shader(lit: Vector3, fragment: &Fragment, uniforms: &Uniforms) -> Vector3 {
+    let shading = uniforms.object.material.shading;
+    let n = fragment.normal.normalized();
+    let to_eye = (uniforms.frame.camera_pos - fragment.world_position).normalized();
+    let view = -to_eye;
+    let reflected = view - n * (2.0 * view.dot(n));
+
+    let sky = uniforms
+        .object
+        .reflection
+        .as_ref()
+        .and_then(|reflection| reflection.sample(reflected, shading.roughness))
+        .unwrap_or_else(|| uniforms.frame.sky.at(reflected));
+
+    let f0 = mix_vec3(Vector3::new(0.04, 0.04, 0.04), fragment.color, shading.metalness);
+    let grazing = (1.0 - n.dot(to_eye).max(0.0)).powi(5) * (1.0 - shading.roughness);
+    let fresnel = f0 + (Vector3::one() - f0) * grazing;
+    let mirror = Vector3::new(sky.x * fresnel.x, sky.y * fresnel.y, sky.z * fresnel.z);
+    saturate_vec3(lit * (1.0 - shading.metalness) + mirror)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::HeadlessTarget;
+    use crate::noise::{Noise, MAX_FBM_OCTAVES};
+    use crate::skybox::SkyIrradiance;
+    use crate::{FrameUniforms, ObjectUniforms};
+
+    #[test]
+    fn earth_glint_stays_off_the_vertex_colors_land() {
+        // Sun and camera straight above the fragment, so open water glints at full strength
+        let frame = FrameUniforms {
+            light_position: Vector3::new(0.0, 0.0, 10.0),
+            ..FrameUniforms::new(Matrix::identity(), Matrix::identity(), Matrix::identity(), Vector3::new(0.0, 0.0, 10.0))
+        };
+        // A distant earth: its colors resolve two octaves, fewer than the frame allows
+        let object = ObjectUniforms { irradiance: Vector3::one(), detail_budget: 2.0, noise: Noise::new(7), ..ObjectUniforms::new(Matrix::identity(), &frame) };
+        let uniforms = Uniforms { frame, object };
+        assert_eq!(uniforms.frame.fbm_octaves, MAX_FBM_OCTAVES);
+        let vertex_noise = uniforms.surface_noise();
+
+        let lit = Vector3::new(0.1, 0.1, 0.1);
+        let (mut coast_pixels, mut water_glints) = (0, 0);
+        for i in 0..96 {
+            for j in 0..48 {
+                // Mid latitudes, clear of the ice caps
+                let uv = Vector2::new((i as f32 + 0.5) / 96.0, 0.35 + 0.3 * (j as f32 + 0.5) / 48.0);
+                let fragment = Fragment { normal: Vector3::new(0.0, 0.0, 1.0), world_position: Vector3::new(0.0, 0.0, 1.0), uv, ..Fragment::new(0.0, 0.0, lit, 0.5) };
+                let masks = earth_masks(uv, &vertex_noise, &uniforms.object.params);
+                let shaded = earth_specular(lit, &fragment, &uniforms);
+                if masks.land == 1.0 {
+                    assert_eq!(shaded, lit, "glint on land at {:?}", uv);
+                    // Land the full-detail noise would have called water
+                    if earth_masks(uv, &uniforms.object.noise, &uniforms.object.params).land == 0.0 {
+                        coast_pixels += 1;
+                    }
+                } else if masks.land == 0.0 && masks.clouds < 0.01 {
+                    assert!(shaded.x > lit.x, "no glint on open water at {:?}", uv);
+                    water_glints += 1;
+                }
+            }
+        }
+        assert!(coast_pixels > 0 && water_glints > 0, "{} coast pixels, {} glints", coast_pixels, water_glints);
+    }
 
-    saturate_vec3(color)
-}
\ No newline at end of file
+    #[test]
+    fn sun_limb_darkens_blue_most() {
+        // A sun 300 pixels across in radius, seen from far away
+        let eye = Vector3::new(0.0, 0.0, 1e4);
+        let frame = FrameUniforms::new(Matrix::identity(), Matrix::identity(), Matrix::identity(), eye);
+        let uniforms = Uniforms { frame, object: ObjectUniforms { screen_radius: 300.0, ..ObjectUniforms::new(Matrix::identity(), &frame) } };
+        let core = Vector3::new(1.0, 0.95, 0.6);
+        let at = |mu: f32| {
+            let normal = Vector3::new((1.0 - mu * mu).sqrt(), 0.0, mu);
+            sun_fragment_shader(&Fragment { normal, world_position: normal, ..Fragment::new(0.0, 0.0, core, 0.5) }, &uniforms)
+        };
+        let (center, limb) = (at(1.0), at(0.2));
+        // Every channel darkens, none stays clipped at white
+        assert!(limb.x < center.x - 0.1 && limb.y < center.y - 0.1 && limb.z < center.z - 0.1, "{:?} -> {:?}", center, limb);
+        assert!(limb.z / center.z < limb.x / center.x, "blue keeps {} and red {}", limb.z / center.z, limb.x / center.x);
+    }
+
+    #[test]
+    fn sun_halo_fades_outside_the_edge_behind_nearer_bodies() {
+        let mut target = HeadlessTarget::new(64, 64, Vector3::zero());
+        let sphere = ScreenSphere {
+            center_world: Vector3::zero(),
+            center_view: Vector3::new(0.0, 0.0, -10.0),
+            radius: 1.0,
+            center_px: Vector2::new(32.0, 32.0),
+            radius_px: 8.0,
+            front_depth: 0.5,
+        };
+        // A body in front of the sun covers a pixel just above its edge
+        let covered = (21 * 64 + 32) as usize;
+        target.depth[covered] = 0.2;
+        draw_sun_halo(&mut target, &sphere, &ParamSet::default(), 1.0);
+
+        let glow = |x: usize, y: usize| target.colors[y * 64 + x].x;
+        assert_eq!(glow(32, 32), 0.0, "the halo is drawn over the disc");
+        assert_eq!(target.colors[covered], Vector3::zero(), "the halo shows through a nearer body");
+        let outward = [glow(41, 32), glow(43, 32), glow(46, 32), glow(49, 32)];
+        assert!(outward.windows(2).all(|w| w[0] > w[1]) && outward[3] > 0.0, "{:?}", outward);
+    }
+
+    #[test]
+    fn a_bodys_own_ambient_replaces_the_scenes() {
+        let frame = FrameUniforms {
+            ambient: 0.6,
+            sky: SkyIrradiance::constant(Vector3::new(0.5, 0.5, 0.5)),
+            ..FrameUniforms::new(Matrix::identity(), Matrix::identity(), Matrix::identity(), Vector3::zero())
+        };
+        let ambient = |own: Option<f32>| {
+            let material = Material { shading: Shading { ambient: own, ..Shading::default() }, ..Material::default() };
+            let uniforms = Uniforms { frame, object: ObjectUniforms { material, ..ObjectUniforms::new(Matrix::identity(), &frame) } };
+            sky_ambient(Vector3::new(0.0, 1.0, 0.0), &uniforms).x
+        };
+        assert!((ambient(None) - 0.3).abs() < 1e-4, "{}", ambient(None));
+        assert!((ambient(Some(1.2)) - 0.6).abs() < 1e-4, "{}", ambient(Some(1.2)));
+        assert_eq!(ambient(Some(0.0)), 0.0);
+    }
+}
@@ -0,0 +1,290 @@
+#![allow(dead_code)]
+
+use std::f32::consts::PI;
+
+use raylib::prelude::*;
+
+use crate::camera::Camera;
+use crate::input::InputState;
+use crate::light::ContactShadow;
+use crate::scene::SceneObject;
+
+/// Key that switches between the free camera and piloting the shuttle.
+pub const PILOT_KEY: KeyboardKey = KeyboardKey::KEY_P;
+/// Key that toggles Newtonian gravity while piloting.
+pub const PHYSICS_KEY: KeyboardKey = KeyboardKey::KEY_G;
+
+/// The shuttle casts a contact shadow below this altitude, in shuttle radii.
+const CONTACT_SHADOW_ALTITUDE: f32 = 8.0;
+
+/// A sphere the shuttle can bump into and that pulls on it when physics is on.
+pub struct Collider {
+    pub center: Vector3,
+    pub radius: f32,
+    pub mass: f32,
+}
+
+/// Player-controlled shuttle state for piloting mode.
+pub struct Shuttle {
+    pub piloting: bool,
+    pub position: Vector3,
+    pub velocity: Vector3,
+    pub yaw: f32,   // Heading around Y; 0 faces -Z
+    pub pitch: f32,
+    pub radius: f32, // Bounding sphere, world units
+
+    pub throttle: f32, // Current thrust input in [-1, 1]
+    pub acceleration: f32,
+    pub turn_rate: f32, // Radians per second
+    pub drag: f32,      // Fraction of velocity lost per second
+    pub restitution: f32, // Bounciness of collisions, 0 = stick, 1 = perfect bounce
+
+    // Gravity mode: no drag, bodies pull on the shuttle
+    pub physics: bool,
+    pub gravity_constant: f32,
+    pub substep: f32,        // Fixed integration step, seconds
+    pub prediction_time: f32, // Seconds of coast path to predict
+    pub predicted_path: Vec<Vector3>,
+
+    pub impact: f32, // Seconds of collision feedback left (screen flash)
+}
+
+impl Shuttle {
+    pub fn new() -> Self {
+        Shuttle {
+            piloting: false,
+            position: Vector3::zero(),
+            velocity: Vector3::zero(),
+            yaw: 0.0,
+            pitch: 0.0,
+            radius: 1.0,
+            throttle: 0.0,
+            acceleration: 12.0,
+            turn_rate: 1.5,
+            drag: 0.6,
+            restitution: 0.4,
+            physics: false,
+            gravity_constant: 40.0,
+            substep: 1.0 / 240.0,
+            prediction_time: 10.0,
+            predicted_path: Vec::new(),
+            impact: 0.0,
+        }
+    }
+
+    /// Blob shadow on the body under the shuttle when it flies low, as that body's index
+    /// and the shadow. The body is the one with the nearest surface straight below
+    /// (towards its center); the blob widens and fades as the shuttle climbs.
+    pub fn contact_shadow(&self, objects: &[SceneObject]) -> Option<(usize, ContactShadow)> {
+        let max_altitude = CONTACT_SHADOW_ALTITUDE * self.radius;
+        let (index, body, altitude) = objects
+            .iter()
+            .enumerate()
+            .filter(|(_, o)| o.sphere && o.flags.visible && o.flags.receive_shadows && !matches!(o.object_type.as_str(), "sun" | "shuttle" | "ring"))
+            .map(|(i, o)| (i, o, (self.position - o.translation).length() - o.radius * o.scale))
+            .min_by(|a, b| a.2.total_cmp(&b.2))?;
+        if altitude >= max_altitude {
+            return None;
+        }
+
+        let body_radius = body.radius * body.scale;
+        let height = altitude.max(0.0) / max_altitude; // 0 on the ground, 1 at the cutoff
+        let blob_radius = self.radius * (0.8 + 1.5 * height);
+        let shadow = ContactShadow {
+            center: body.translation,
+            direction: (self.position - body.translation).normalized(),
+            angular_radius: (blob_radius / body_radius).min(1.0),
+            strength: 0.7 * (1.0 - height) * (1.0 - height),
+        };
+        Some((index, shadow))
+    }
+
+    /// Unit vector the nose points along.
+    pub fn forward(&self) -> Vector3 {
+        Vector3::new(
+            -self.yaw.sin() * self.pitch.cos(),
+            self.pitch.sin(),
+            -self.yaw.cos() * self.pitch.cos(),
+        )
+    }
+
+    /// Model rotation for the shuttle mesh, whose nose points down -Z.
+    pub fn rotation(&self) -> Vector3 {
+        Vector3::new(self.pitch, self.yaw, 0.0)
+    }
+
+    /// Starts piloting from where the shuttle currently is, facing the camera's direction.
+    pub fn take_control(&mut self, position: Vector3, camera: &Camera) {
+        let dir = (camera.target - camera.eye).normalized();
+        self.piloting = true;
+        self.position = position;
+        self.velocity = Vector3::zero();
+        self.yaw = (-dir.x).atan2(-dir.z);
+        self.pitch = dir.y.clamp(-1.0, 1.0).asin();
+    }
+
+    /// W/S throttle, A/D yaw, Up/Down pitch.
+    pub fn process_input(&mut self, input: &InputState, dt: f32) {
+        self.throttle = 0.0;
+        if input.is_key_down(KeyboardKey::KEY_W) {
+            self.throttle += 1.0;
+        }
+        if input.is_key_down(KeyboardKey::KEY_S) {
+            self.throttle -= 1.0;
+        }
+        if input.is_key_down(KeyboardKey::KEY_A) {
+            self.yaw += self.turn_rate * dt;
+        }
+        if input.is_key_down(KeyboardKey::KEY_D) {
+            self.yaw -= self.turn_rate * dt;
+        }
+        if input.is_key_down(KeyboardKey::KEY_UP) {
+            self.pitch += self.turn_rate * dt;
+        }
+        if input.is_key_down(KeyboardKey::KEY_DOWN) {
+            self.pitch -= self.turn_rate * dt;
+        }
+        self.pitch = self.pitch.clamp(-PI / 2.0 + 0.1, PI / 2.0 - 0.1);
+    }
+
+    /// Advances the shuttle by `dt`: arcade flight (thrust and drag) by default, or
+    /// thrust plus gravity integrated at a fixed substep when `physics` is on.
+    pub fn step(&mut self, dt: f32, colliders: &[Collider]) {
+        if self.physics {
+            let thrust = self.forward() * (self.throttle * self.acceleration);
+            let steps = (dt / self.substep).ceil().max(1.0) as usize;
+            let h = dt / steps as f32;
+            for _ in 0..steps {
+                // Semi-implicit Euler: update velocity first, then move with the new velocity
+                self.velocity += (thrust + gravity_at(self.position, colliders, self.gravity_constant)) * h;
+                let start = self.position;
+                let end = start + self.velocity * h;
+                self.position = self.resolve_collisions(start, end, colliders);
+            }
+            self.predict_path(colliders);
+        } else {
+            self.velocity += self.forward() * (self.throttle * self.acceleration * dt);
+            self.velocity *= (1.0 - self.drag * dt).max(0.0);
+
+            let start = self.position;
+            let end = start + self.velocity * dt;
+            self.position = self.resolve_collisions(start, end, colliders);
+            self.predicted_path.clear();
+        }
+        self.impact = (self.impact - dt).max(0.0);
+    }
+
+    /// Coast path (no thrust) for the next `prediction_time` seconds, with the bodies
+    /// held where they are now. Stops early at the first collision.
+    fn predict_path(&mut self, colliders: &[Collider]) {
+        self.predicted_path.clear();
+        let mut position = self.position;
+        let mut velocity = self.velocity;
+        let h = self.substep * 4.0; // Coarser than the real step; it's only a guide
+        let steps = (self.prediction_time / h) as usize;
+
+        self.predicted_path.push(position);
+        for i in 0..steps {
+            velocity += gravity_at(position, colliders, self.gravity_constant) * h;
+            let next = position + velocity * h;
+            let blocked = colliders
+                .iter()
+                .any(|c| sweep_sphere(position, next, c.center, c.radius + self.radius).is_some());
+            position = next;
+            if blocked {
+                self.predicted_path.push(position);
+                break;
+            }
+            if i % 8 == 0 {
+                self.predicted_path.push(position);
+            }
+        }
+    }
+
+    /// Sweeps the move from `start` to `end` against every collider and stops at the
+    /// first contact, so fast moves can't tunnel through thin or small bodies.
+    fn resolve_collisions(&mut self, start: Vector3, end: Vector3, colliders: &[Collider]) -> Vector3 {
+        let mut hit: Option<(f32, &Collider)> = None;
+        for collider in colliders {
+            let reach = collider.radius + self.radius;
+
+            // Already overlapping (e.g. a planet moved into us): push straight out
+            if (start - collider.center).length() < reach {
+                let normal = normal_from(collider.center, start);
+                self.bounce(normal);
+                return collider.center + normal * reach;
+            }
+
+            if let Some(t) = sweep_sphere(start, end, collider.center, reach)
+                && hit.is_none_or(|(best, _)| t < best)
+            {
+                hit = Some((t, collider));
+            }
+        }
+
+        match hit {
+            Some((t, collider)) => {
+                let contact = start + (end - start) * t;
+                let normal = normal_from(collider.center, contact);
+                self.bounce(normal);
+                // Sit exactly on the surface
+                collider.center + normal * (collider.radius + self.radius)
+            }
+            None => end,
+        }
+    }
+
+    /// Reflects and damps the velocity component going into the surface.
+    fn bounce(&mut self, normal: Vector3) {
+        let into = self.velocity.dot(normal);
+        if into < 0.0 {
+            self.velocity -= normal * ((1.0 + self.restitution) * into);
+            self.impact = 0.25;
+        }
+    }
+
+    /// Chase camera behind and slightly above the shuttle.
+    pub fn follow_camera(&self, camera: &mut Camera) {
+        let forward = self.forward();
+        camera.target = self.position + forward * 4.0;
+        camera.eye = self.position - forward * 8.0 + Vector3::new(0.0, 2.5, 0.0);
+    }
+}
+
+/// Newtonian acceleration at `point`, softened near the centers to keep it finite.
+pub fn gravity_at(point: Vector3, bodies: &[Collider], gravity_constant: f32) -> Vector3 {
+    let mut acceleration = Vector3::zero();
+    for body in bodies {
+        let offset = body.center - point;
+        let dist_sq = offset.dot(offset) + 0.25;
+        acceleration += offset * (gravity_constant * body.mass / (dist_sq * dist_sq.sqrt()));
+    }
+    acceleration
+}
+
+fn normal_from(center: Vector3, point: Vector3) -> Vector3 {
+    let offset = point - center;
+    if offset.length() > 1e-6 {
+        offset.normalized()
+    } else {
+        Vector3::new(0.0, 1.0, 0.0)
+    }
+}
+
+/// Earliest fraction t in [0, 1] at which the segment start->end touches the sphere.
+pub fn sweep_sphere(start: Vector3, end: Vector3, center: Vector3, radius: f32) -> Option<f32> {
+    let d = end - start;
+    let f = start - center;
+    let a = d.dot(d);
+    if a < 1e-12 {
+        return None;
+    }
+    let b = 2.0 * f.dot(d);
+    let c = f.dot(f) - radius * radius;
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let t = (-b - discriminant.sqrt()) / (2.0 * a);
+    (0.0..=1.0).contains(&t).then_some(t)
+}
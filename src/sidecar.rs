@@ -0,0 +1,42 @@
+#![allow(dead_code)]
+
+use std::fs;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::error::AppError;
+
+/// Where a scene's `kind` file is kept beside it: `default.toml` uses `default.<kind>.toml`.
+pub fn sidecar_path(scene_path: &str, kind: &str) -> String {
+    match scene_path.strip_suffix(".toml") {
+        Some(stem) => format!("{}.{}.toml", stem, kind),
+        None => format!("{}.{}.toml", scene_path, kind),
+    }
+}
+
+/// The state saved in `path`; the default when there's none or it doesn't parse.
+pub fn load_state<T: DeserializeOwned + Default>(path: &str) -> T {
+    fs::read_to_string(path).ok().and_then(|text| toml::from_str(&text).ok()).unwrap_or_default()
+}
+
+/// Writes `state` to `path` through a temporary file renamed over it, so a crash
+/// mid-write leaves the previous state whole. `what` names it in the error.
+pub fn save_state<T: Serialize>(state: &T, path: &str, what: &str) -> Result<(), AppError> {
+    let error = || AppError::StateWrite { what: what.to_string(), path: path.to_string() };
+    let text = toml::to_string(state).map_err(|_| error())?;
+    let temporary = format!("{}.tmp", path);
+    fs::write(&temporary, text).map_err(|_| error())?;
+    fs::rename(&temporary, path).map_err(|_| error())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sidecars_sit_beside_the_scene() {
+        assert_eq!(sidecar_path("scenes/default.toml", "bookmarks"), "scenes/default.bookmarks.toml");
+        assert_eq!(sidecar_path("scenes/default", "discovery"), "scenes/default.discovery.toml");
+    }
+}
@@ -1,10 +1,146 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use raylib::math::Vector3;
-use raylib::prelude::{Image, Color};
+use raylib::prelude::{Image, Matrix};
+use serde::{Deserialize, Serialize};
+use crate::light::kelvin_to_rgb;
 
+/// Opciones de carga del cielo, leídas de la tabla `[skybox]` del archivo de escena.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SkyboxConfig {
+    pub max_face_size: i32, // Caras más grandes (lado mayor, en píxeles) se reducen al cargar; 0 = sin límite
+    pub stars: StarfieldConfig,
+}
+
+impl Default for SkyboxConfig {
+    fn default() -> Self {
+        SkyboxConfig { max_face_size: 1024, stars: StarfieldConfig::default() }
+    }
+}
+
+/// Cielo procedural que reemplaza a las caras que faltan, de la tabla `[skybox.stars]`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StarfieldConfig {
+    pub count: usize,               // Estrellas por cara
+    pub temperature_min: f32,       // Kelvin de las más frías (rojas)
+    pub temperature_max: f32,       // ... y de las más calientes (azules)
+    pub cool_bias: f32,             // > 1 da más estrellas frías que calientes
+    pub named: usize,               // Estrellas muy brillantes por cara
+    pub spikes: bool,               // Cruz de difracción en las estrellas brillantes
+}
+
+impl Default for StarfieldConfig {
+    fn default() -> Self {
+        StarfieldConfig {
+            count: 1200,
+            temperature_min: 2800.0,
+            temperature_max: 25000.0,
+            cool_bias: 2.5,
+            named: 1,
+            spikes: true,
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct SkyboxFace {
     pub width: i32,
     pub height: i32,
-    pub pixels: Vec<Vector3>, // RGB en [0,1]
+    pub pixels: Vec<[u8; 3]>, // RGB de 8 bits; se pasa a [0,1] al muestrear
+}
+
+impl SkyboxFace {
+    /// Cara a partir de una imagen, leída directo de sus datos. Si pasa de `max_size`
+    /// se reduce por un factor entero promediando bloques de factor×factor píxeles.
+    pub fn from_image(image: &Image, max_size: i32) -> Self {
+        let (width, height) = (image.width.max(1), image.height.max(1));
+        let longest = width.max(height);
+        let factor = if max_size > 0 && longest > max_size { (longest + max_size - 1) / max_size } else { 1 };
+        let (out_width, out_height) = ((width / factor).max(1), (height / factor).max(1));
+
+        let data = image.get_image_data();
+        let mut pixels = Vec::with_capacity((out_width * out_height) as usize);
+        for by in 0..out_height {
+            for bx in 0..out_width {
+                let mut sum = [0u32; 3];
+                let mut count = 0;
+                for y in (by * factor)..((by + 1) * factor).min(height) {
+                    for x in (bx * factor)..((bx + 1) * factor).min(width) {
+                        let c = data[(y * width + x) as usize];
+                        sum[0] += c.r as u32;
+                        sum[1] += c.g as u32;
+                        sum[2] += c.b as u32;
+                        count += 1;
+                    }
+                }
+                let count = count.max(1);
+                pixels.push(sum.map(|s| ((s + count / 2) / count) as u8));
+            }
+        }
+        SkyboxFace { width: out_width, height: out_height, pixels }
+    }
+
+    /// Color del píxel `idx` en [0,1].
+    fn texel(&self, idx: usize) -> Vector3 {
+        let [r, g, b] = self.pixels[idx];
+        Vector3::new(r as f32, g as f32, b as f32) / 255.0
+    }
+
+    /// La cara a la mitad de resolución, promediando bloques de 2×2 píxeles.
+    fn half(&self) -> SkyboxFace {
+        let (width, height) = ((self.width / 2).max(1), (self.height / 2).max(1));
+        let mut pixels = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = Vector3::zero();
+                for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let (sx, sy) = ((2 * x + dx).min(self.width - 1), (2 * y + dy).min(self.height - 1));
+                    sum += self.texel((sy * self.width + sx) as usize);
+                }
+                pixels.push(to_rgb8(sum * 0.25));
+            }
+        }
+        SkyboxFace { width, height, pixels }
+    }
+
+    /// Color del píxel más cercano a (u, v) de [-1, 1].
+    fn nearest(&self, u: f32, v: f32) -> Vector3 {
+        // De [-1, 1] a [0, 1]
+        let u_tex = (u + 1.0) * 0.5;
+        let v_tex = (v + 1.0) * 0.5;
+
+        let w = self.width.max(1) as f32;
+        let h = self.height.max(1) as f32;
+
+        let ix = (u_tex * w).clamp(0.0, w - 1.0) as i32;
+        let iy = (v_tex * h).clamp(0.0, h - 1.0) as i32;
+
+        let idx = (iy * self.width + ix)
+            .clamp(0, self.width * self.height - 1) as usize;
+
+        self.texel(idx)
+    }
+
+    /// Color en (u, v) de [-1, 1], interpolado entre los cuatro píxeles más cercanos.
+    fn bilinear(&self, u: f32, v: f32) -> Vector3 {
+        let (w, h) = (self.width.max(1), self.height.max(1));
+        let x = ((u + 1.0) * 0.5 * w as f32 - 0.5).clamp(0.0, (w - 1) as f32);
+        let y = ((v + 1.0) * 0.5 * h as f32 - 0.5).clamp(0.0, (h - 1) as f32);
+        let (x0, y0) = (x as i32, y as i32);
+        let (x1, y1) = ((x0 + 1).min(w - 1), (y0 + 1).min(h - 1));
+        let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+        let at = |x: i32, y: i32| self.texel((y * w + x) as usize);
+        let top = at(x0, y0) * (1.0 - fx) + at(x1, y0) * fx;
+        let bottom = at(x0, y1) * (1.0 - fx) + at(x1, y1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+}
+
+/// Color en [0,1] a 8 bits por canal.
+fn to_rgb8(color: Vector3) -> [u8; 3] {
+    [color.x, color.y, color.z].map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
 }
 
 pub struct Skybox {
@@ -14,97 +150,567 @@ pub struct Skybox {
     pub bottom: SkyboxFace,
     pub front: SkyboxFace,
     pub back: SkyboxFace,
+    pub irradiance: SkyIrradiance, // Luz ambiente del cielo, calculada al cargar
+    pub average: Vector3,          // Color promedio de todo el cielo, para el fondo cuando no se dibuja
+    pub up_average: Vector3,       // ... de la mitad que mira hacia arriba (+Y)
+    pub down_average: Vector3,     // ... y de la que mira hacia abajo
+    mips: Vec<[SkyboxFace; 6]>,    // Las caras a 1/2, 1/4, ... de resolución, para cuando se ven chicas en pantalla
+}
+
+/// Lado de la reducción más chica de las caras.
+const MIN_MIP_SIZE: i32 = 16;
+
+impl Skybox {
+    /// Arma el skybox, sus reducciones, su irradiancia y sus promedios. El cielo no rota,
+    /// así que basta con hacerlo una vez por escena; si algún día rota hay que volver a
+    /// llamar a `SkyIrradiance::from_skybox` y `hemisphere_averages`.
+    pub fn new(right: SkyboxFace, left: SkyboxFace, top: SkyboxFace, bottom: SkyboxFace, front: SkyboxFace, back: SkyboxFace) -> Self {
+        let mut skybox = Skybox {
+            right,
+            left,
+            top,
+            bottom,
+            front,
+            back,
+            irradiance: SkyIrradiance::constant(Vector3::zero()),
+            average: Vector3::zero(),
+            up_average: Vector3::zero(),
+            down_average: Vector3::zero(),
+            mips: Vec::new(),
+        };
+        skybox.irradiance = SkyIrradiance::from_skybox(&skybox);
+        (skybox.up_average, skybox.down_average) = hemisphere_averages(&skybox);
+        skybox.average = (skybox.up_average + skybox.down_average) * 0.5;
+        let mut level = skybox.faces().map(|face| face.clone());
+        while level.iter().any(|face| face.width.max(face.height) > MIN_MIP_SIZE) {
+            level = level.each_ref().map(|face| face.half());
+            skybox.mips.push(level.clone());
+        }
+        skybox
+    }
+
+    /// Luz ambiente de los cuerpos: la irradiancia completa, o sin ella (`irradiance = false`
+    /// en `[light]`) un relleno en dos tonos entre el promedio de arriba y el de abajo.
+    pub fn ambient(&self, irradiance: bool) -> SkyIrradiance {
+        if irradiance {
+            self.irradiance
+        } else {
+            SkyIrradiance::hemisphere(self.up_average, self.down_average)
+        }
+    }
+
+    /// Memoria de los píxeles de las seis caras y sus reducciones, para el HUD.
+    pub fn memory_bytes(&self) -> usize {
+        let faces = self.faces().into_iter().chain(self.mips.iter().flatten());
+        faces.map(|face| face.pixels.len() * std::mem::size_of::<[u8; 3]>()).sum()
+    }
+
+    /// Las seis caras en el orden de `face_coords`.
+    fn faces(&self) -> [&SkyboxFace; 6] {
+        [&self.right, &self.left, &self.top, &self.bottom, &self.front, &self.back]
+    }
+
+    /// Reducciones disponibles además de las caras originales.
+    pub fn mip_levels(&self) -> usize {
+        self.mips.len()
+    }
+
+    /// Reducción con la que muestrear el fondo de todo un cuadro: la que deja cerca de un
+    /// píxel de cara por píxel de pantalla en el centro de la vista. Depende solo de la
+    /// resolución y del campo de visión de `projection`, así que cambia al redimensionar
+    /// o hacer zoom. Muestrear más fino que la pantalla no agrega detalle, solo saltos
+    /// por memoria que el caché no alcanza a seguir.
+    pub fn level_for(&self, projection: &Matrix, render_height: i32) -> usize {
+        // Píxeles de pantalla que ocupa una cara vista de frente (de -1 a 1 a distancia 1)
+        let screen = projection.m5.abs() * render_height as f32;
+        let texels = self.right.width.max(self.right.height) as f32;
+        if screen <= 0.0 || !screen.is_finite() {
+            return 0;
+        }
+        ((texels / screen).log2().round().max(0.0) as usize).min(self.mips.len())
+    }
 }
 
-/// Convierte una `Image` de raylib en un vector de colores RGB normalizados ([0,1])
-/// en el mismo orden de escaneo que los datos de la imagen (row-major).
-pub fn image_to_colors(image: &Image) -> Vec<Vector3> {
-    // `get_image_data` devuelve un slice de `Color` con todos los píxeles de la imagen.
-    // Lo clonamos a un Vec para poder transformarlo.
-    let pixels: Vec<Color> = image.get_image_data().to_vec();
+/// (u, v) en [-1, 1] de cada cara a dirección, lo inverso de `face_coords`.
+const FACE_DIRECTIONS: [fn(f32, f32) -> Vector3; 6] = [
+    |u, v| Vector3::new(1.0, -v, u),
+    |u, v| Vector3::new(-1.0, -v, -u),
+    |u, v| Vector3::new(-u, 1.0, v),
+    |u, v| Vector3::new(-u, -1.0, -v),
+    |u, v| Vector3::new(-u, -v, 1.0),
+    |u, v| Vector3::new(u, -v, -1.0),
+];
+
+/// Muestras por lado de cada cara al proyectar el cielo.
+const IRRADIANCE_SAMPLES: usize = 48;
 
-    pixels
-        .into_iter()
-        .map(|c| {
-            Vector3::new(
-                c.r as f32 / 255.0,
-                c.g as f32 / 255.0,
-                c.b as f32 / 255.0,
-            )
-        })
-        .collect()
+/// Recorre la grilla de muestras de las seis caras con la dirección (sin normalizar) y
+/// el ángulo sólido que cubre cada celda.
+fn for_each_sample(mut f: impl FnMut(Vector3, f32)) {
+    let cell = 2.0 / IRRADIANCE_SAMPLES as f32;
+    for face in FACE_DIRECTIONS {
+        for j in 0..IRRADIANCE_SAMPLES {
+            for i in 0..IRRADIANCE_SAMPLES {
+                let u = -1.0 + (i as f32 + 0.5) * cell;
+                let v = -1.0 + (j as f32 + 0.5) * cell;
+                // Ángulo sólido de la celda: dA / r³ sobre el cubo
+                let r2 = 1.0 + u * u + v * v;
+                f(face(u, v), cell * cell / (r2 * r2.sqrt()));
+            }
+        }
+    }
 }
 
-// ... SkyboxFace, Skybox, image_to_colors ...
+/// Color promedio de las mitades del cielo que miran hacia arriba y hacia abajo,
+/// pesado por ángulo sólido. Con una cantidad par de muestras ninguna cae justo en el
+/// horizonte.
+fn hemisphere_averages(skybox: &Skybox) -> (Vector3, Vector3) {
+    let (mut up, mut down) = ((Vector3::zero(), 0.0), (Vector3::zero(), 0.0));
+    for_each_sample(|dir, weight| {
+        let half = if dir.y > 0.0 { &mut up } else { &mut down };
+        half.0 += sample_cubemap(skybox, dir) * weight;
+        half.1 += weight;
+    });
+    (up.0 / up.1, down.0 / down.1)
+}
 
-/// Samplea el skybox como un cubemap usando una dirección 3D.
-/// `dir` debe ser un vector de dirección en espacio mundo.
-pub fn sample_cubemap(skybox: &Skybox, dir: Vector3) -> Vector3 {
-    // Normalizar la dirección
-    let mut d = dir;
-    d.normalize();
-    let x = d.x;
-    let y = d.y;
-    let z = d.z;
-
-    let ax = x.abs();
-    let ay = y.abs();
-    let az = z.abs();
-
-    // Elegir cara y coords de textura en [-1, 1]
-    let (face, u, v) = if ax >= ay && ax >= az {
-        // ±X
+/// Irradiancia difusa del cielo como armónicos esféricos de orden 2 (9 coeficientes RGB).
+/// `at(n)` da la luz que recibe una superficie con normal `n`, en las mismas unidades
+/// que los colores del cielo: un cielo uniforme de color `c` da `c` en toda dirección.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SkyIrradiance {
+    coefficients: [Vector3; 9],
+}
+
+/// Base de armónicos esféricos reales hasta l = 2, en una dirección unitaria.
+fn sh_basis(d: Vector3) -> [f32; 9] {
+    [
+        0.282_095,
+        0.488_603 * d.y,
+        0.488_603 * d.z,
+        0.488_603 * d.x,
+        1.092_548 * d.x * d.y,
+        1.092_548 * d.y * d.z,
+        0.315_392 * (3.0 * d.z * d.z - 1.0),
+        1.092_548 * d.x * d.z,
+        0.546_274 * (d.x * d.x - d.y * d.y),
+    ]
+}
+
+impl SkyIrradiance {
+    /// Cielo uniforme de color `color`.
+    pub fn constant(color: Vector3) -> Self {
+        let mut coefficients = [Vector3::zero(); 9];
+        coefficients[0] = color * (0.282_095 * 4.0 * std::f32::consts::PI);
+        SkyIrradiance { coefficients }
+    }
+
+    /// Relleno en dos tonos: `up` en las normales que miran hacia arriba, `down` en las
+    /// que miran hacia abajo y la mezcla lineal según n.y entre medio. Sale exacto de las
+    /// bandas 0 y 1, deshaciendo la convolución que aplica `at`.
+    pub fn hemisphere(up: Vector3, down: Vector3) -> Self {
+        let mut coefficients = [Vector3::zero(); 9];
+        coefficients[0] = (up + down) * (0.5 / 0.282_095);
+        coefficients[1] = (up - down) * (0.5 / (0.488_603 * 2.0 / 3.0));
+        SkyIrradiance { coefficients }
+    }
+
+    /// Proyecta el cielo sobre la base, muestreando cada cara en una grilla y pesando
+    /// cada muestra por el ángulo sólido que cubre.
+    pub fn from_skybox(skybox: &Skybox) -> Self {
+        let mut coefficients = [Vector3::zero(); 9];
+        let mut total_weight = 0.0;
+        for_each_sample(|dir, weight| {
+            let color = sample_cubemap(skybox, dir);
+            for (c, y) in coefficients.iter_mut().zip(sh_basis(dir.normalized())) {
+                *c += color * (y * weight);
+            }
+            total_weight += weight;
+        });
+        // La suma de pesos aproxima 4π; se normaliza para que sea exacta
+        let scale = 4.0 * std::f32::consts::PI / total_weight;
+        for c in coefficients.iter_mut() {
+            *c *= scale;
+        }
+        SkyIrradiance { coefficients }
+    }
+
+    /// Irradiancia (dividida por π) sobre una superficie con normal `n`: la convolución
+    /// coseno de Ramamoorthi y Hanrahan, nunca negativa.
+    pub fn at(&self, n: Vector3) -> Vector3 {
+        // A_l / π por banda: 1, 2/3, 1/4
+        const BAND: [f32; 9] = [1.0, 2.0 / 3.0, 2.0 / 3.0, 2.0 / 3.0, 0.25, 0.25, 0.25, 0.25, 0.25];
+        let mut e = Vector3::zero();
+        for ((c, y), a) in self.coefficients.iter().zip(sh_basis(n.normalized())).zip(BAND) {
+            e += *c * (y * a);
+        }
+        Vector3::new(e.x.max(0.0), e.y.max(0.0), e.z.max(0.0))
+    }
+
+    /// Irradiancia promedio sobre todas las direcciones, para superficies sin normal.
+    pub fn average(&self) -> Vector3 {
+        self.coefficients[0] * 0.282_095
+    }
+}
+
+/// Cara que ve la dirección `dir` (en el orden de `Skybox::faces`) y coords de textura
+/// en [-1, 1]. Mundo diestro: cada cara se ve desde dentro sin espejar, u hacia la
+/// derecha y v hacia abajo de la imagen.
+fn face_coords(dir: Vector3) -> (usize, f32, f32) {
+    let (x, y, z) = (dir.x, dir.y, dir.z);
+    let (ax, ay, az) = (x.abs(), y.abs(), z.abs());
+    if ax >= ay && ax >= az {
         if x > 0.0 {
-            // +X → right
-            let uc = -z / ax;
-            let vc = -y / ax;
-            (&skybox.right, uc, vc)
+            // +X → right, su derecha es +Z
+            (0, z / ax, -y / ax)
         } else {
-            // -X → left
-            let uc = z / ax;
-            let vc = -y / ax;
-            (&skybox.left, uc, vc)
+            // -X → left, su derecha es -Z
+            (1, -z / ax, -y / ax)
         }
     } else if ay >= ax && ay >= az {
-        // ±Y
         if y > 0.0 {
-            // +Y → top
-            let uc = x / ay;
-            let vc = z / ay;
-            (&skybox.top, uc, vc)
+            // +Y → top, mirando hacia arriba con -Z arriba de la imagen
+            (2, -x / ay, z / ay)
         } else {
-            // -Y → bottom
-            let uc = x / ay;
-            let vc = -z / ay;
-            (&skybox.bottom, uc, vc)
+            // -Y → bottom, mirando hacia abajo con +Z arriba de la imagen
+            (3, -x / ay, -z / ay)
         }
+    } else if z > 0.0 {
+        // +Z → front, su derecha es -X
+        (4, -x / az, -y / az)
     } else {
-        // ±Z
-        if z > 0.0 {
-            // +Z → front
-            let uc = x / az;
-            let vc = -y / az;
-            (&skybox.front, uc, vc)
+        // -Z → back, su derecha es +X
+        (5, x / az, -y / az)
+    }
+}
+
+/// Samplea el skybox como un cubemap usando una dirección 3D.
+/// `dir` debe ser un vector de dirección en espacio mundo.
+pub fn sample_cubemap(skybox: &Skybox, dir: Vector3) -> Vector3 {
+    sample_cubemap_level(skybox, dir, 0)
+}
+
+/// Como `sample_cubemap`, en la reducción `level` (0 = las caras originales, ver `Skybox::level_for`).
+pub fn sample_cubemap_level(skybox: &Skybox, dir: Vector3, level: usize) -> Vector3 {
+    let (index, u, v) = face_coords(dir.normalized());
+    match level.checked_sub(1).and_then(|mip| skybox.mips.get(mip)) {
+        Some(faces) => faces[index].nearest(u, v),
+        None => skybox.faces()[index].nearest(u, v),
+    }
+}
+
+/// Niveles del reflejo, del más nítido al más borroso: lado de cada cara en píxeles y
+/// radio del cono promediado, en grados.
+const REFLECTION_LEVELS: [(i32, f32); 4] = [(128, 1.0), (64, 5.0), (32, 14.0), (16, 35.0)];
+/// Direcciones promediadas por píxel de un nivel.
+const REFLECTION_SAMPLES: usize = 24;
+
+/// El cielo prefiltrado para reflejos: versiones cada vez más borrosas, de las que una
+/// superficie pulida lee la más nítida y una áspera la más borrosa. Se calcula de a una
+/// cara por llamada a `refine`, de la más borrosa a la más nítida, para no frenar la
+/// carga; mientras tanto los reflejos usan los niveles que ya están.
+#[derive(Clone, Default)]
+pub struct SkyReflection {
+    levels: Vec<Vec<SkyboxFace>>, // Caras calculadas de cada nivel, en el orden de `REFLECTION_LEVELS`
+}
+
+impl SkyReflection {
+    pub fn new() -> Self {
+        SkyReflection { levels: vec![Vec::new(); REFLECTION_LEVELS.len()] }
+    }
+
+    /// Ya están todos los niveles.
+    pub fn is_complete(&self) -> bool {
+        self.levels.iter().all(|faces| faces.len() == 6)
+    }
+
+    /// Calcula la próxima cara que falta de `sky`. Devuelve false si no faltaba ninguna.
+    pub fn refine(&mut self, sky: &Skybox) -> bool {
+        let Some(level) = (0..self.levels.len()).rev().find(|&l| self.levels[l].len() < 6) else {
+            return false;
+        };
+        let (size, cone) = REFLECTION_LEVELS[level];
+        let face = self.levels[level].len();
+        let cell = 2.0 / size as f32;
+        let mut pixels = Vec::with_capacity((size * size) as usize);
+        for j in 0..size {
+            for i in 0..size {
+                let u = -1.0 + (i as f32 + 0.5) * cell;
+                let v = -1.0 + (j as f32 + 0.5) * cell;
+                let dir = FACE_DIRECTIONS[face](u, v).normalized();
+                pixels.push(to_rgb8(cone_average(sky, dir, cone.to_radians())));
+            }
+        }
+        self.levels[level].push(SkyboxFace { width: size, height: size, pixels });
+        true
+    }
+
+    /// Color del cielo reflejado en la dirección `dir` por una superficie de `roughness`
+    /// (0 espejo, 1 mate), interpolando entre los dos niveles más cercanos que ya
+    /// están. None si todavía no hay ninguno.
+    pub fn sample(&self, dir: Vector3, roughness: f32) -> Option<Vector3> {
+        let ready: Vec<usize> = (0..self.levels.len()).filter(|&l| self.levels[l].len() == 6).collect();
+        let (&first, &last) = (ready.first()?, ready.last()?);
+        let (index, u, v) = face_coords(dir.normalized());
+        let at = |level: usize| self.levels[level][index].bilinear(u, v);
+
+        let wanted = roughness.clamp(0.0, 1.0) * (REFLECTION_LEVELS.len() - 1) as f32;
+        if wanted <= first as f32 {
+            return Some(at(first));
+        }
+        if wanted >= last as f32 {
+            return Some(at(last));
+        }
+        let below = *ready.iter().rev().find(|&&l| l as f32 <= wanted)?;
+        let above = *ready.iter().find(|&&l| l as f32 > wanted)?;
+        let t = (wanted - below as f32) / (above - below) as f32;
+        Some(at(below) * (1.0 - t) + at(above) * t)
+    }
+
+    /// Memoria de los niveles calculados, para el HUD.
+    pub fn memory_bytes(&self) -> usize {
+        self.levels.iter().flatten().map(|face| face.pixels.len() * std::mem::size_of::<[u8; 3]>()).sum()
+    }
+}
+
+/// Promedio del cielo en un cono de `radius` radianes alrededor de `dir`, con muestras
+/// en espiral de Fibonacci para cubrirlo parejo sin azar.
+fn cone_average(sky: &Skybox, dir: Vector3, radius: f32) -> Vector3 {
+    let any = if dir.x.abs() < 0.9 { Vector3::new(1.0, 0.0, 0.0) } else { Vector3::new(0.0, 1.0, 0.0) };
+    let tangent = dir.cross(any).normalized();
+    let bitangent = dir.cross(tangent);
+    let golden = std::f32::consts::PI * (3.0 - 5.0_f32.sqrt());
+    let mut sum = Vector3::zero();
+    for k in 0..REFLECTION_SAMPLES {
+        let angle = radius * ((k as f32 + 0.5) / REFLECTION_SAMPLES as f32).sqrt();
+        let (sin, cos) = (golden * k as f32).sin_cos();
+        let offset = (tangent * cos + bitangent * sin) * angle.tan();
+        sum += sample_cubemap(sky, dir + offset);
+    }
+    sum / REFLECTION_SAMPLES as f32
+}
+
+/// Temperatura de una estrella a partir de `u` en [0, 1): repartida en escala
+/// logarítmica y sesgada hacia las frías, como las enanas rojas que abundan.
+fn star_temperature(config: &StarfieldConfig, u: f32) -> f32 {
+    let (min, max) = (config.temperature_min.max(1000.0), config.temperature_max.max(config.temperature_min));
+    min * (max / min).powf(u.powf(config.cool_bias.max(0.1)))
+}
+
+/// Color de una estrella a partir de su temperatura, normalizado para que el brillo
+/// lo dé la magnitud y no el tono: una estrella roja no es más oscura por ser roja.
+fn star_color(kelvin: f32) -> Vector3 {
+    let c = kelvin_to_rgb(kelvin);
+    c / c.x.max(c.y).max(c.z).max(1e-3)
+}
+
+/// Suma una mancha gaussiana de desvío `sigma` píxeles centrada en (x, y).
+fn splat(buffer: &mut [Vector3], size: i32, x: f32, y: f32, sigma: f32, color: Vector3) {
+    let reach = (sigma * 3.0).ceil() as i32;
+    let (cx, cy) = (x.floor() as i32, y.floor() as i32);
+    for py in (cy - reach).max(0)..=(cy + reach).min(size - 1) {
+        for px in (cx - reach).max(0)..=(cx + reach).min(size - 1) {
+            let (dx, dy) = (px as f32 + 0.5 - x, py as f32 + 0.5 - y);
+            let weight = (-(dx * dx + dy * dy) / (2.0 * sigma * sigma)).exp();
+            buffer[(py * size + px) as usize] += color * weight;
+        }
+    }
+}
+
+/// Cruz de difracción: dos rayas finas, horizontal y vertical, que se apagan al alejarse.
+fn spikes(buffer: &mut [Vector3], size: i32, x: f32, y: f32, length: f32, color: Vector3) {
+    let (cx, cy) = (x as i32, y as i32);
+    for d in 1..=length as i32 {
+        let fade = (1.0 - d as f32 / length).powi(2) * 0.35;
+        for (px, py) in [(cx + d, cy), (cx - d, cy), (cx, cy + d), (cx, cy - d)] {
+            if (0..size).contains(&px) && (0..size).contains(&py) {
+                buffer[(py * size + px) as usize] += color * fade;
+            }
+        }
+    }
+}
+
+/// Cara de reemplazo cuando falta la imagen: fondo casi negro con estrellas de colores
+/// de cuerpo negro, muchas rojas y tenues y pocas azules y brillantes. Las más brillantes
+/// ocupan unos píxeles y las `named`, muy brillantes, llevan cruz de difracción. Cada
+/// `seed` da otro patrón de estrellas.
+pub fn procedural_face(size: i32, seed: u64, config: &StarfieldConfig) -> SkyboxFace {
+    let size = size.max(1);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut buffer = vec![Vector3::new(0.01, 0.012, 0.025); (size * size) as usize];
+    // Tamaños pensados para caras de 512 píxeles
+    let scale = size as f32 / 512.0;
+
+    for _ in 0..config.count {
+        let (x, y) = (rng.random_range(0.0..size as f32), rng.random_range(0.0..size as f32));
+        let u: f32 = rng.random();
+        let kelvin = star_temperature(config, u);
+        // Magnitud al azar, con muchas más tenues que brillantes; las calientes brillan más
+        let brightness = (0.15 + 0.85 * rng.random::<f32>().powi(4)) * (0.6 + 0.4 * u);
+        let color = star_color(kelvin) * brightness;
+        if brightness > 0.45 {
+            // Las brillantes se reparten en 2-3 píxeles en lugar de un texel
+            let sigma = (0.5 + 0.8 * (brightness - 0.45)) * scale.max(0.5);
+            splat(&mut buffer, size, x, y, sigma, color * 2.0);
         } else {
-            // -Z → back
-            let uc = -x / az;
-            let vc = -y / az;
-            (&skybox.back, uc, vc)
+            buffer[(y as i32 * size + x as i32) as usize] += color;
+        }
+    }
+
+    // Unas pocas muy brillantes, siempre en el mismo lugar para la misma semilla
+    for _ in 0..config.named {
+        let margin = size as f32 * 0.1;
+        let (x, y) = (rng.random_range(margin..size as f32 - margin), rng.random_range(margin..size as f32 - margin));
+        let kelvin = star_temperature(config, rng.random_range(0.4..1.0));
+        let color = star_color(kelvin);
+        splat(&mut buffer, size, x, y, 1.2 * scale.max(0.5), color * 3.0);
+        if config.spikes {
+            spikes(&mut buffer, size, x, y, 14.0 * scale.max(0.5), color);
         }
-    };
+    }
+
+    SkyboxFace { width: size, height: size, pixels: buffer.into_iter().map(to_rgb8).collect() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flat_face(color: Vector3) -> SkyboxFace {
+        SkyboxFace { width: 4, height: 4, pixels: vec![to_rgb8(color); 16] }
+    }
+
+    #[test]
+    fn uniform_sky_gives_its_color_in_every_direction() {
+        let c = Vector3::new(0.2, 0.4, 0.6); // Exacto en 8 bits
+        let sky = Skybox::new(flat_face(c), flat_face(c), flat_face(c), flat_face(c), flat_face(c), flat_face(c));
+        for n in [Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, -1.0, 0.0), Vector3::new(0.3, 0.5, -0.8)] {
+            let e = sky.irradiance.at(n);
+            assert!((e - c).length() < 1e-3, "{:?} at {:?}", e, n);
+        }
+    }
+
+    #[test]
+    fn bright_side_tints_the_normals_facing_it() {
+        let dark = Vector3::zero();
+        let nebula = Vector3::new(0.8, 0.2, 0.6);
+        let sky = Skybox::new(flat_face(nebula), flat_face(dark), flat_face(dark), flat_face(dark), flat_face(dark), flat_face(dark));
+        let facing = sky.irradiance.at(Vector3::new(1.0, 0.0, 0.0));
+        let away = sky.irradiance.at(Vector3::new(-1.0, 0.0, 0.0));
+        assert!(facing.x > 0.2 && facing.x > facing.y);
+        assert!(away.x < 0.02);
 
-    // De [-1, 1] a [0, 1]
-    let u_tex = (u + 1.0) * 0.5;
-    let v_tex = (v + 1.0) * 0.5;
+        let black = Skybox::new(flat_face(dark), flat_face(dark), flat_face(dark), flat_face(dark), flat_face(dark), flat_face(dark));
+        assert_eq!(black.irradiance.at(Vector3::new(0.0, 1.0, 0.0)), Vector3::zero());
+    }
 
-    let w = face.width.max(1) as f32;
-    let h = face.height.max(1) as f32;
+    #[test]
+    fn hemisphere_fill_blends_the_upper_and_lower_averages() {
+        let dark = Vector3::zero();
+        let warm = Vector3::new(0.8, 0.4, 0.2);
+        let sky = Skybox::new(flat_face(dark), flat_face(dark), flat_face(warm), flat_face(dark), flat_face(dark), flat_face(dark));
+        // El techo es un tercio del ángulo sólido de arriba
+        assert!((sky.up_average - warm / 3.0).length() < 0.01, "{:?}", sky.up_average);
+        assert!(sky.down_average.length() < 1e-6);
+        assert!((sky.average - warm / 6.0).length() < 0.01, "{:?}", sky.average);
 
-    let ix = (u_tex * (w - 1.0)).clamp(0.0, w - 1.0) as i32;
-    let iy = ((1.0 - v_tex) * (h - 1.0)).clamp(0.0, h - 1.0) as i32;
+        let fill = sky.ambient(false);
+        assert!((fill.at(Vector3::new(0.0, 1.0, 0.0)) - sky.up_average).length() < 1e-4);
+        assert!(fill.at(Vector3::new(0.0, -1.0, 0.0)).length() < 1e-4);
+        assert!((fill.at(Vector3::new(1.0, 0.0, 0.0)) - sky.average).length() < 1e-4);
+        assert_eq!(sky.ambient(true), sky.irradiance);
+    }
 
-    let idx = (iy * face.width + ix)
-        .clamp(0, face.width * face.height - 1) as usize;
+    /// Cara de 2×2 cuyos píxeles codifican la cara (rojo) y el cuadrante (verde):
+    /// 0 arriba a la izquierda, 1 arriba a la derecha, 2 abajo a la izquierda, 3 abajo a la derecha.
+    fn marked_face(face: u8) -> SkyboxFace {
+        SkyboxFace { width: 2, height: 2, pixels: (0..4).map(|q| [face * 40, q * 60, 0]).collect() }
+    }
 
-    face.pixels[idx]
-}
\ No newline at end of file
+    #[test]
+    fn faces_read_unmirrored_from_inside() {
+        let sky = Skybox::new(marked_face(0), marked_face(1), marked_face(2), marked_face(3), marked_face(4), marked_face(5));
+        let (x, y, z) = (Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        // (cara, hacia dónde se mira, arriba de la imagen); la derecha es adelante × arriba
+        let views = [(0, x, y), (1, -x, y), (2, y, -z), (3, -y, z), (4, z, y), (5, -z, y)];
+        for (face, forward, up) in views {
+            let right = forward.cross(up);
+            for (quadrant, (r, u)) in [(-1.0, 1.0), (1.0, 1.0), (-1.0, -1.0), (1.0, -1.0)].into_iter().enumerate() {
+                let dir = forward + right * (0.5 * r) + up * (0.5 * u);
+                let expected = Vector3::new(face as f32 * 40.0, quadrant as f32 * 60.0, 0.0) / 255.0;
+                let got = sample_cubemap(&sky, dir);
+                assert!((got - expected).length() < 1e-4, "cara {} cuadrante {}: {:?}", face, quadrant, got * 255.0);
+            }
+        }
+    }
+
+    #[test]
+    fn background_level_follows_the_screen_size_of_a_face() {
+        let face = |size: i32| SkyboxFace { width: size, height: size, pixels: vec![[200, 100, 50]; (size * size) as usize] };
+        let sky = |size: i32| Skybox::new(face(size), face(size), face(size), face(size), face(size), face(size));
+        let projection = |fov: f32| crate::matrix::create_projection_matrix(fov, 4.0 / 3.0, 0.1, 100.0);
+        let wide = projection(std::f32::consts::PI / 3.0);
+
+        // A 200×150, 60°: una cara de frente ocupa unos 260 píxeles
+        let big = sky(1024);
+        assert_eq!(big.mip_levels(), 6);
+        assert_eq!(big.level_for(&wide, 150), 2);
+        assert_eq!(sky(256).level_for(&wide, 150), 0);
+        assert_eq!(big.level_for(&wide, 600), 0, "a pantalla grande, las caras originales");
+        assert!(big.level_for(&projection(0.2), 150) < 2, "con zoom, más detalle");
+
+        // Cada reducción promedia bien y mide la mitad
+        let dir = Vector3::new(0.2, 0.3, -1.0);
+        assert_eq!(sample_cubemap_level(&big, dir, 2), sample_cubemap(&big, dir));
+        assert_eq!(big.mips[1][0].width, 256);
+    }
+
+    #[test]
+    fn reflection_blurs_more_with_roughness() {
+        let dark = Vector3::zero();
+        let lit = Vector3::new(0.8, 0.8, 0.8);
+        let sky = Skybox::new(flat_face(lit), flat_face(dark), flat_face(dark), flat_face(dark), flat_face(dark), flat_face(dark));
+        let mut reflection = SkyReflection::new();
+        assert!(reflection.sample(Vector3::new(1.0, 0.0, 0.0), 0.0).is_none());
+
+        // El nivel más borroso llega primero y sirve para cualquier rugosidad
+        for _ in 0..6 {
+            assert!(reflection.refine(&sky));
+        }
+        assert!(reflection.sample(Vector3::new(1.0, 0.0, 0.0), 0.0).is_some());
+        while reflection.refine(&sky) {}
+        assert!(reflection.is_complete());
+
+        // Justo del lado oscuro del borde de +X: el espejo lo ve oscuro, la superficie áspera
+        // recibe algo del lado iluminado
+        let dir = Vector3::new(0.9, 0.0, 1.0);
+        let mirror = reflection.sample(dir, 0.0).unwrap();
+        let rough = reflection.sample(dir, 1.0).unwrap();
+        assert!(mirror.x < 0.05 && rough.x > mirror.x + 0.05, "{:?} {:?}", mirror, rough);
+        let center = reflection.sample(Vector3::new(1.0, 0.0, 0.0), 0.5).unwrap();
+        assert!((center - lit).length() < 0.05, "{:?}", center);
+    }
+
+    #[test]
+    fn procedural_stars_vary_in_color_and_size() {
+        let config = StarfieldConfig::default();
+        let face = procedural_face(256, 7, &config);
+        assert_eq!(face.pixels, procedural_face(256, 7, &config).pixels, "la misma semilla da el mismo cielo");
+        assert_ne!(face.pixels, procedural_face(256, 8, &config).pixels);
+
+        let lit: Vec<&[u8; 3]> = face.pixels.iter().filter(|p| p.iter().any(|&c| c > 40)).collect();
+        let red = lit.iter().filter(|p| p[0] as i32 > p[2] as i32 + 20).count();
+        let blue = lit.iter().filter(|p| p[2] as i32 > p[0] as i32 + 20).count();
+        assert!(red > blue && blue > 0, "{} rojas, {} azules", red, blue);
+
+        // Estrellas que ocupan más de un píxel: píxeles encendidos con un vecino encendido
+        let at = |x: i32, y: i32| face.pixels[(y * 256 + x) as usize].iter().any(|&c| c > 40);
+        let spread = (1..255).flat_map(|y| (1..255).map(move |x| (x, y))).filter(|&(x, y)| at(x, y) && at(x + 1, y)).count();
+        assert!(spread > 10, "{} píxeles con vecino", spread);
+
+        // Sin cruces las estrellas con nombre encienden menos píxeles sobre el fondo (3, 3, 6)
+        let plain = procedural_face(256, 7, &StarfieldConfig { spikes: false, ..config.clone() });
+        let count_lit = |f: &SkyboxFace| f.pixels.iter().filter(|p| p.iter().any(|&c| c > 8)).count();
+        assert!(count_lit(&plain) < count_lit(&face));
+    }
+}
@@ -0,0 +1,219 @@
+#![allow(dead_code)]
+
+use rodio::mixer::Mixer;
+use serde::{Deserialize, Serialize};
+
+/// Seconds the looping sounds take to get most of the way to a new level.
+const EASE: f32 = 0.25;
+
+/// Ambient sound settings, read from the `[soundscape]` table of the scene file. Only
+/// heard in builds with the `audio` feature; volumes go from 0 to 1.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SoundscapeConfig {
+    pub volume: f32,      // Scales every sound below
+    pub drone: f32,       // Space drone at its loudest, next to the sun
+    pub drone_floor: f32, // Fraction of `drone` still heard far from the sun
+    pub near: f32,        // Distance from the sun at which the drone is loudest
+    pub far: f32,         // ... and past which it stays at its floor
+    pub rumble: f32,      // Shuttle engine at full throttle
+    pub chime: f32,       // A body discovered or isolated
+    pub whoosh: f32,      // Camera warps
+}
+
+impl Default for SoundscapeConfig {
+    fn default() -> Self {
+        SoundscapeConfig {
+            volume: 0.8,
+            drone: 0.5,
+            drone_floor: 0.15,
+            near: 6.0,
+            far: 60.0,
+            rumble: 0.6,
+            chime: 0.4,
+            whoosh: 0.5,
+        }
+    }
+}
+
+/// Something that happened this frame and has a sound.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SoundEvent {
+    WarpStart,
+    WarpEnd,
+    Discovered,
+    Selected,
+}
+
+/// Volumes of the looping sounds, before the master volume.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Levels {
+    drone: f32,
+    rumble: f32,
+    whoosh: f32,
+}
+
+/// The soundscape: a space drone that swells near the sun, the shuttle's engine rumble,
+/// a chime for discoveries and selections and a whoosh while the camera warps. The app
+/// reports events and levels the same way with or without the `audio` feature; without
+/// it nothing is played.
+pub struct Soundscape {
+    pub config: SoundscapeConfig,
+    levels: Levels,
+    warping: bool,
+    #[cfg(feature = "audio")]
+    voices: voices::Voices,
+}
+
+impl Soundscape {
+    pub fn new(mixer: &Mixer, config: SoundscapeConfig) -> Self {
+        #[cfg(not(feature = "audio"))]
+        let _ = mixer;
+        Soundscape {
+            config,
+            levels: Levels::default(),
+            warping: false,
+            #[cfg(feature = "audio")]
+            voices: voices::Voices::new(mixer),
+        }
+    }
+
+    /// Takes a new scene's settings; a warp cut short by the switch stops whooshing.
+    pub fn reset(&mut self, config: SoundscapeConfig) {
+        self.config = config;
+        self.warping = false;
+    }
+
+    pub fn emit(&mut self, event: SoundEvent) {
+        match event {
+            SoundEvent::WarpStart => self.warping = true,
+            SoundEvent::WarpEnd => self.warping = false,
+            SoundEvent::Discovered | SoundEvent::Selected => {}
+        }
+        #[cfg(feature = "audio")]
+        self.voices.play(event, &self.config);
+    }
+
+    /// Eases the looping sounds towards their targets, so none of them clicks in or out.
+    pub fn update(&mut self, sun_distance: Option<f32>, thrust: f32, dt: f32) {
+        let target = targets(&self.config, sun_distance, thrust, self.warping);
+        let k = 1.0 - (-dt.max(0.0) / EASE).exp();
+        let ease = |level: f32, target: f32| level + (target - level) * k;
+        self.levels = Levels {
+            drone: ease(self.levels.drone, target.drone),
+            rumble: ease(self.levels.rumble, target.rumble),
+            whoosh: ease(self.levels.whoosh, target.whoosh),
+        };
+        #[cfg(feature = "audio")]
+        self.voices.set_levels(&self.levels, self.config.volume);
+    }
+}
+
+/// Levels the looping sounds should be at: the drone by how close the camera is to the
+/// sun (at its floor with no sun), the rumble by the throttle, the whoosh while warping.
+fn targets(c: &SoundscapeConfig, sun_distance: Option<f32>, thrust: f32, warping: bool) -> Levels {
+    let closeness = sun_distance.map_or(0.0, |d| {
+        let t = ((c.far - d) / (c.far - c.near).max(1e-3)).clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    });
+    Levels {
+        drone: c.drone * (c.drone_floor + (1.0 - c.drone_floor) * closeness),
+        rumble: c.rumble * thrust.abs().min(1.0),
+        whoosh: if warping { c.whoosh } else { 0.0 },
+    }
+}
+
+/// The sounds themselves, synthesized so there are no sound files to ship: looping
+/// voices whose volume follows the levels, and one-shot chimes added to the mixer.
+#[cfg(feature = "audio")]
+mod voices {
+    use std::time::Duration;
+
+    use rodio::mixer::Mixer;
+    use rodio::source::{SawtoothWave, SineWave, Source};
+    use rodio::Sink;
+
+    use super::{Levels, SoundEvent, SoundscapeConfig};
+
+    pub struct Voices {
+        mixer: Mixer,
+        drone: Sink,
+        rumble: Sink,
+        whoosh: Sink,
+    }
+
+    /// A sink playing `source` forever, silent until its volume is raised.
+    fn looping(mixer: &Mixer, source: impl Source + Send + 'static) -> Sink {
+        let sink = Sink::connect_new(mixer);
+        sink.set_volume(0.0);
+        sink.append(source);
+        sink
+    }
+
+    impl Voices {
+        pub fn new(mixer: &Mixer) -> Self {
+            // A low fifth, slightly detuned so it beats slowly
+            let drone = SineWave::new(55.0).mix(SineWave::new(82.6)).mix(SineWave::new(110.3).amplify(0.3)).amplify(0.35);
+            // Two close saws under a low-pass: a rough engine growl
+            let rumble = SawtoothWave::new(38.0).mix(SawtoothWave::new(41.5)).low_pass(160).amplify(0.5);
+            // Brighter and airier than the engine
+            let whoosh = SawtoothWave::new(70.0).mix(SawtoothWave::new(103.0)).low_pass(900).amplify(0.25);
+            Voices {
+                mixer: mixer.clone(),
+                drone: looping(mixer, drone),
+                rumble: looping(mixer, rumble),
+                whoosh: looping(mixer, whoosh),
+            }
+        }
+
+        pub fn set_levels(&self, levels: &Levels, volume: f32) {
+            self.drone.set_volume(volume * levels.drone);
+            self.rumble.set_volume(volume * levels.rumble);
+            self.whoosh.set_volume(volume * levels.whoosh);
+        }
+
+        /// One-shot sounds: a rising two-note chime for a discovery, a single note for a
+        /// selection. Warps are covered by the whoosh voice.
+        pub fn play(&self, event: SoundEvent, config: &SoundscapeConfig) {
+            let (notes, level): (&[f32], f32) = match event {
+                SoundEvent::Discovered => (&[1046.5, 1568.0], config.chime),
+                SoundEvent::Selected => (&[1318.5], config.chime * 0.6),
+                SoundEvent::WarpStart | SoundEvent::WarpEnd => return,
+            };
+            let length = Duration::from_millis(900);
+            for (i, frequency) in notes.iter().enumerate() {
+                let note = SineWave::new(*frequency)
+                    .fade_out(length)
+                    .take_duration(length)
+                    .amplify(config.volume * level * 0.5)
+                    .delay(Duration::from_millis(120 * i as u64));
+                self.mixer.add(note);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drone_swells_towards_the_sun() {
+        let c = SoundscapeConfig::default();
+        let drone = |distance: Option<f32>| targets(&c, distance, 0.0, false).drone;
+        let (far, mid, near) = (drone(Some(c.far * 2.0)), drone(Some((c.near + c.far) / 2.0)), drone(Some(c.near * 0.5)));
+        assert!((far - c.drone * c.drone_floor).abs() < 1e-6);
+        assert!(far < mid && mid < near);
+        assert!((near - c.drone).abs() < 1e-6);
+        assert_eq!(drone(None), far);
+    }
+
+    #[test]
+    fn rumble_follows_the_throttle_and_whoosh_the_warp() {
+        let c = SoundscapeConfig::default();
+        assert_eq!(targets(&c, None, 0.0, false).rumble, 0.0);
+        assert_eq!(targets(&c, None, -1.0, false).rumble, c.rumble);
+        assert_eq!(targets(&c, None, 0.0, false).whoosh, 0.0);
+        assert_eq!(targets(&c, None, 0.0, true).whoosh, c.whoosh);
+    }
+}
@@ -0,0 +1,162 @@
+#![allow(dead_code)]
+
+use std::f32::consts::PI;
+
+use raylib::prelude::*;
+
+use crate::fragment::Fragment;
+use crate::framebuffer::PixelTarget;
+use crate::matrix::{multiply_matrix_vector4, view_ray};
+use crate::planetshaders::spherical_uv;
+use crate::vertex::Vertex;
+use crate::{planet_vertex_shader, shade_fragment, Uniforms};
+
+/// Spheres whose projected radius is below this many pixels are ray-cast
+/// analytically instead of rasterized from their mesh.
+pub const RAYCAST_MAX_RADIUS_PX: f32 = 10.0;
+
+/// A world-space sphere projected onto the screen.
+#[derive(Clone, Copy)]
+pub struct ScreenSphere {
+    pub center_world: Vector3,
+    pub center_view: Vector3,
+    pub radius: f32,
+    pub center_px: Vector2,
+    pub radius_px: f32,
+    pub front_depth: f32, // NDC depth of the point nearest the camera
+}
+
+fn transform(m: &Matrix, v: Vector3, w: f32) -> Vector4 {
+    multiply_matrix_vector4(m, &Vector4::new(v.x, v.y, v.z, w))
+}
+
+/// Projects a sphere using the frame's matrices. Returns None when the sphere
+/// touches the near plane, where the mesh path handles clipping better.
+pub fn project_sphere(center: Vector3, radius: f32, uniforms: &Uniforms) -> Option<ScreenSphere> {
+    let p = &uniforms.frame.projection;
+    let near = p.m14 / (p.m10 + uniforms.frame.depth_mode.near_ndc());
+
+    let view = transform(&uniforms.frame.view, center, 1.0);
+    let center_view = Vector3::new(view.x, view.y, view.z);
+    let distance = -center_view.z;
+    if distance - radius <= near {
+        return None;
+    }
+
+    let clip = transform(p, center_view, 1.0);
+    let ndc = Vector3::new(clip.x / clip.w, clip.y / clip.w, clip.z / clip.w);
+    let screen = transform(&uniforms.frame.viewport, ndc, 1.0);
+
+    let half_height_px = uniforms.frame.viewport.m5.abs();
+    let front = transform(p, center_view + Vector3::new(0.0, 0.0, radius), 1.0);
+    Some(ScreenSphere {
+        center_world: center,
+        center_view,
+        radius,
+        center_px: Vector2::new(screen.x, screen.y),
+        radius_px: radius / distance * p.m5 * half_height_px,
+        front_depth: front.z / front.w,
+    })
+}
+
+/// Ray-casts a sphere against every pixel of its screen-space bounding square,
+/// shading hits with the same vertex and fragment shaders as the mesh path.
+/// Silhouette pixels are blended by coverage, so small bodies come out as
+/// antialiased discs with exact per-pixel depth.
+pub fn render_sphere<T: PixelTarget>(target: &mut T, uniforms: &Uniforms, sphere: &ScreenSphere, object_type: &str) {
+    let p = &uniforms.frame.projection;
+    let v = &uniforms.frame.view;
+    let m = &uniforms.object.model;
+    let vp = &uniforms.frame.viewport;
+
+    // Rows of the view rotation: camera right, up and back in world space
+    let right = Vector3::new(v.m0, v.m4, v.m8);
+    let up = Vector3::new(v.m1, v.m5, v.m9);
+    let back = Vector3::new(v.m2, v.m6, v.m10);
+    let to_world = |d: Vector3| right * d.x + up * d.y + back * d.z;
+
+    let c = sphere.center_view;
+    let r = sphere.radius;
+    let (width, height) = target.size();
+    let extent = sphere.radius_px + 1.5;
+    let min_x = (sphere.center_px.x - extent).floor().max(0.0) as i32;
+    let max_x = (sphere.center_px.x + extent).ceil().min(width as f32 - 1.0) as i32;
+    let min_y = (sphere.center_px.y - extent).floor().max(0.0) as i32;
+    let max_y = (sphere.center_px.y + extent).ceil().min(height as f32 - 1.0) as i32;
+
+    for y in min_y..=max_y {
+        for x in min_x..=max_x {
+            let px = x as f32 + 0.5;
+            let py = y as f32 + 0.5;
+
+            // View-space ray through the pixel center
+            let ndc_x = (px - vp.m12) / vp.m0;
+            let ndc_y = (py - vp.m13) / vp.m5;
+            let dir = view_ray(p, ndc_x, ndc_y).normalized();
+
+            // Closest approach of the ray to the center
+            let t_closest = dir.dot(c);
+            if t_closest <= 0.0 {
+                continue;
+            }
+            let miss_sq = (c.dot(c) - t_closest * t_closest).max(0.0);
+            let miss = miss_sq.sqrt();
+
+            // Coverage from the distance to the silhouette, in pixels
+            let pixel_size = t_closest / (p.m5 * vp.m5.abs());
+            let coverage = ((r - miss) / pixel_size + 0.5).clamp(0.0, 1.0);
+            if coverage <= 0.0 {
+                continue;
+            }
+
+            // Front hit, or the nearest silhouette point for edge pixels the ray just misses
+            let hit = if miss < r {
+                dir * (t_closest - (r * r - miss_sq).sqrt())
+            } else {
+                c + (dir * t_closest - c).normalized() * r
+            };
+            let normal_view = (hit - c) / r;
+
+            let clip = transform(p, hit, 1.0);
+            let depth = clip.z / clip.w;
+
+            let world_normal = to_world(normal_view);
+            let world_position = sphere.center_world + to_world(hit - c);
+
+            // Undo the model rotation to get the object-space normal the planet shaders expect
+            let object_normal = Vector3::new(
+                m.m0 * world_normal.x + m.m1 * world_normal.y + m.m2 * world_normal.z,
+                m.m4 * world_normal.x + m.m5 * world_normal.y + m.m6 * world_normal.z,
+                m.m8 * world_normal.x + m.m9 * world_normal.y + m.m10 * world_normal.z,
+            )
+            .normalized();
+
+            let mut vertex = Vertex {
+                normal: object_normal,
+                transformed_position: Vector3::new(px, py, depth),
+                transformed_normal: world_normal,
+                world_position,
+                ..Vertex::default()
+            };
+            planet_vertex_shader(&mut vertex, uniforms, object_type);
+
+            // One pixel covers this much of the surface, stretched at grazing angles
+            let facing = (-dir.dot(normal_view)).max(0.2);
+            let footprint = pixel_size / (r * facing);
+
+            let fragment = Fragment {
+                position: Vector2::new(px, py),
+                color: vertex.color,
+                depth,
+                normal: world_normal,
+                world_position,
+                tex_coords: vertex.tex_coords,
+                uv: spherical_uv(object_normal),
+                duvdx: Vector2::new(footprint / (2.0 * PI), 0.0),
+                duvdy: Vector2::new(0.0, footprint / PI),
+            };
+            let color = shade_fragment(&fragment, uniforms, object_type);
+            target.blend_point(x, y, color, coverage, depth);
+        }
+    }
+}
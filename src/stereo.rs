@@ -0,0 +1,145 @@
+#![allow(dead_code)]
+
+use raylib::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::input::InputState;
+
+/// Key that cycles stereo off → side-by-side → anaglyph.
+pub const STEREO_KEY: KeyboardKey = KeyboardKey::KEY_F9;
+/// Keys that narrow and widen the distance between the eyes.
+pub const IPD_DOWN_KEY: KeyboardKey = KeyboardKey::KEY_LEFT_BRACKET;
+pub const IPD_UP_KEY: KeyboardKey = KeyboardKey::KEY_RIGHT_BRACKET;
+
+const IPD_STEP: f32 = 0.05;
+const IPD_MAX: f32 = 5.0;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StereoMode {
+    #[default]
+    Off,
+    SideBySide, // Left eye on the left half, right eye on the right, each squeezed to half width
+    Anaglyph,   // Red/cyan glasses: red from the left eye, green and blue from the right
+}
+
+impl StereoMode {
+    pub fn next(self) -> Self {
+        match self {
+            StereoMode::Off => StereoMode::SideBySide,
+            StereoMode::SideBySide => StereoMode::Anaglyph,
+            StereoMode::Anaglyph => StereoMode::Off,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            StereoMode::Off => "off",
+            StereoMode::SideBySide => "side-by-side",
+            StereoMode::Anaglyph => "anaglyph",
+        }
+    }
+}
+
+/// Renders the scene once per eye, the eyes `ipd` apart along the camera's right vector
+/// and both aimed at the camera target, then merges the two images into one frame.
+pub struct Stereo {
+    pub mode: StereoMode,
+    pub ipd: f32, // Interpupillary distance in scene units
+    left: Vec<Color>, // Left eye's image while the right one renders
+}
+
+impl Stereo {
+    pub fn new() -> Self {
+        Stereo { mode: StereoMode::Off, ipd: 0.3, left: Vec::new() }
+    }
+
+    /// F9 cycles the mode; [ and ] change the eye distance.
+    pub fn process_input(&mut self, input: &InputState) {
+        if input.is_key_pressed(STEREO_KEY) {
+            self.mode = self.mode.next();
+        }
+        if input.is_key_pressed(IPD_DOWN_KEY) {
+            self.ipd = (self.ipd - IPD_STEP).max(0.0);
+        }
+        if input.is_key_pressed(IPD_UP_KEY) {
+            self.ipd = (self.ipd + IPD_STEP).min(IPD_MAX);
+        }
+    }
+
+    /// Offsets along the camera's right vector of the eyes to render this frame, left first.
+    pub fn eye_offsets(&self) -> Vec<f32> {
+        match self.mode {
+            StereoMode::Off => vec![0.0],
+            _ => vec![-self.ipd * 0.5, self.ipd * 0.5],
+        }
+    }
+
+    /// Position of the eye `offset` to the right of `eye`, looking at `target`.
+    pub fn eye_position(eye: Vector3, target: Vector3, up: Vector3, offset: f32) -> Vector3 {
+        let right = (target - eye).cross(up).normalized();
+        eye + right * offset
+    }
+
+    /// Eye (an index into `eye_offsets`) whose image shows at column `x` of the
+    /// composited frame `width` pixels wide, and the column it comes from in that eye's
+    /// own buffer. Side by side each half is its eye squeezed; otherwise the eyes overlap
+    /// and the first one is used.
+    pub fn pick(&self, x: f32, width: u32) -> (usize, f32) {
+        let half = (width / 2) as f32;
+        match self.mode {
+            StereoMode::SideBySide if x >= half => (1, (x - half) * 2.0),
+            StereoMode::SideBySide => (0, x * 2.0),
+            _ => (0, x),
+        }
+    }
+
+    /// Keeps the finished left-eye image for `composite`.
+    pub fn store_left(&mut self, colors: Vec<Color>) {
+        self.left = colors;
+    }
+
+    /// Merges the stored left-eye image with the right-eye image `right` (both
+    /// `width`×`height`). `None` when stereo is off or the left eye is missing.
+    pub fn composite(&self, right: &[Color], width: usize, height: usize) -> Option<Vec<Color>> {
+        if self.left.len() != right.len() || right.len() != width * height {
+            return None;
+        }
+        match self.mode {
+            StereoMode::Off => None,
+            StereoMode::SideBySide => Some(side_by_side(&self.left, right, width, height)),
+            StereoMode::Anaglyph => Some(anaglyph(&self.left, right)),
+        }
+    }
+
+    /// Status line for the overlay while stereo is on.
+    pub fn status(&self) -> Option<String> {
+        (self.mode != StereoMode::Off).then(|| format!("Stereo: {} (IPD {:.2}, [ ] to change)", self.mode.name(), self.ipd))
+    }
+}
+
+/// Each eye sampled at every other column into its half of the frame.
+fn side_by_side(left: &[Color], right: &[Color], width: usize, height: usize) -> Vec<Color> {
+    let half = width / 2;
+    let mut out = vec![Color::BLACK; width * height];
+    for y in 0..height {
+        let row = y * width;
+        for x in 0..width {
+            let (eye, sx) = if x < half { (left, x * 2) } else { (right, (x - half) * 2) };
+            out[row + x] = eye[row + sx.min(width - 1)];
+        }
+    }
+    out
+}
+
+/// Half-color anaglyph: the left eye goes in as grey on the red channel so saturated
+/// reds don't show to one eye only, the right eye keeps its green and blue.
+fn anaglyph(left: &[Color], right: &[Color]) -> Vec<Color> {
+    left.iter()
+        .zip(right)
+        .map(|(l, r)| {
+            let luma = 0.299 * l.r as f32 + 0.587 * l.g as f32 + 0.114 * l.b as f32;
+            Color::new(luma as u8, r.g, r.b, 255)
+        })
+        .collect()
+}
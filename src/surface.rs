@@ -0,0 +1,226 @@
+#![allow(dead_code)]
+
+use std::f32::consts::PI;
+
+use raylib::prelude::*;
+
+use crate::camera::Camera;
+use crate::input::InputState;
+use crate::matrix::multiply_matrix_vector4;
+use crate::scene::SceneObject;
+
+/// Key that lands the camera on the selected body, and takes off again.
+pub const SURFACE_KEY: KeyboardKey = KeyboardKey::KEY_H;
+
+/// Eye height above the ground, as a fraction of the body's radius.
+const EYE_HEIGHT: f32 = 0.03;
+/// Walking speed, in body radii per second.
+const WALK_SPEED: f32 = 0.15;
+/// Turning speed with the arrow keys, in radians per second.
+const LOOK_SPEED: f32 = 1.2;
+/// Radians turned per pixel the mouse moves while dragging with the right button.
+const MOUSE_LOOK: f32 = 0.004;
+
+/// Surface mode: the camera stands on a body, up along the ground's normal, and turns
+/// with the body so the sun, the other planets and the sky rise and set as it spins.
+pub struct SurfaceMode {
+    body: Option<String>, // Name of the body stood on; None in orbit mode
+    ground: Vector3,      // Where the camera stands, as a unit direction in the body's own spinning frame
+    heading: f32,         // Radians from local north towards east
+    pitch: f32,           // Radians above the horizon
+    last_mouse: Option<Vector2>, // Mouse position last frame while dragging, for the look delta
+}
+
+impl SurfaceMode {
+    pub fn new() -> Self {
+        SurfaceMode { body: None, ground: Vector3::new(0.0, 1.0, 0.0), heading: 0.0, pitch: 0.1, last_mouse: None }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.body.is_some()
+    }
+
+    /// Overlay line while standing on a body.
+    pub fn status(&self) -> Option<String> {
+        self.body.as_ref().map(|name| format!("Standing on {} (right-drag or arrows to look, H to take off)", name))
+    }
+
+    /// Lands on `obj` at the point right below the camera, facing the way the camera looked.
+    /// Refused for the sun and for bodies that aren't spheres.
+    pub fn land(&mut self, obj: &SceneObject, camera: &Camera) -> Result<(), String> {
+        if obj.object_type == "sun" || !obj.sphere {
+            return Err(format!("can't land on {}", obj.name));
+        }
+        let below = (camera.eye - obj.translation).normalized();
+        let model = obj.model_matrix();
+        self.ground = direction(&model.inverted(), below);
+        self.body = Some(obj.name.clone());
+        let (north, east) = local_frame(obj, below);
+        let forward = camera.target - camera.eye;
+        let along = forward - below * forward.dot(below);
+        self.heading = if along.length() > 1e-4 { along.dot(east).atan2(along.dot(north)) } else { 0.0 };
+        self.pitch = 0.1;
+        self.last_mouse = None;
+        Ok(())
+    }
+
+    /// Back to orbit mode: the camera pulls back out to look at the body from above the
+    /// spot it stood on.
+    pub fn take_off(&mut self, objects: &[SceneObject], camera: &mut Camera) {
+        let body = self.body.as_ref().and_then(|name| objects.iter().find(|o| &o.name == name));
+        let above = body.map(|obj| {
+            let up = direction(&obj.model_matrix(), self.ground);
+            (obj.translation + up * (obj.radius * obj.scale * 4.0), obj.translation)
+        });
+        self.leave(camera);
+        if let Some((eye, target)) = above {
+            camera.warp_to_pose(eye, target);
+        }
+    }
+
+    /// Back to orbit mode right where the camera is, for when something else takes over
+    /// the camera (a warp, a bookmark, the pilot).
+    pub fn leave(&mut self, camera: &mut Camera) {
+        if self.body.take().is_some() {
+            camera.up = Vector3::new(0.0, 1.0, 0.0);
+            camera.sync_orbit();
+        }
+    }
+
+    /// Turns with the arrow keys or by dragging with the right mouse button, and walks
+    /// with W/A/S/D along great circles.
+    pub fn process_input(&mut self, input: &InputState, objects: &[SceneObject], dt: f32) {
+        let key = |k: KeyboardKey| input.is_key_down(k) as i32 as f32;
+        self.heading += (key(KeyboardKey::KEY_RIGHT) - key(KeyboardKey::KEY_LEFT)) * LOOK_SPEED * dt;
+        self.pitch += (key(KeyboardKey::KEY_UP) - key(KeyboardKey::KEY_DOWN)) * LOOK_SPEED * dt;
+        if input.is_mouse_button_down(MouseButton::MOUSE_BUTTON_RIGHT) {
+            let mouse = input.get_mouse_position();
+            if let Some(last) = self.last_mouse {
+                self.heading += (mouse.x - last.x) * MOUSE_LOOK;
+                self.pitch -= (mouse.y - last.y) * MOUSE_LOOK;
+            }
+            self.last_mouse = Some(mouse);
+        } else {
+            self.last_mouse = None;
+        }
+        self.pitch = self.pitch.clamp(-PI / 2.0 + 0.05, PI / 2.0 - 0.05);
+
+        let forward = key(KeyboardKey::KEY_W) - key(KeyboardKey::KEY_S);
+        let sideways = key(KeyboardKey::KEY_D) - key(KeyboardKey::KEY_A);
+        if let Some(obj) = self.body.as_ref().and_then(|name| objects.iter().find(|o| &o.name == name)) {
+            self.walk(obj, forward, sideways, dt);
+        }
+    }
+
+    /// Steps along the great circle through the standing point, `forward` and `sideways`
+    /// (each -1..1) relative to the heading.
+    fn walk(&mut self, obj: &SceneObject, forward: f32, sideways: f32, dt: f32) {
+        if forward == 0.0 && sideways == 0.0 {
+            return;
+        }
+        let model = obj.model_matrix();
+        let up = direction(&model, self.ground);
+        let (north, east) = local_frame(obj, up);
+        let (ahead, right) = (heading_direction(north, east, self.heading), heading_direction(north, east, self.heading + PI / 2.0));
+        let walk = (ahead * forward + right * sideways).normalized();
+        let angle = WALK_SPEED * dt;
+        let moved = up * angle.cos() + walk * angle.sin();
+        self.ground = direction(&model.inverted(), moved);
+    }
+
+    /// Puts the camera on the ground for this frame, after the body has moved and spun.
+    /// Leaves orbit mode if the body is gone.
+    pub fn place_camera(&mut self, objects: &[SceneObject], camera: &mut Camera) {
+        let Some(obj) = self.body.as_ref().and_then(|name| objects.iter().find(|o| &o.name == name)) else {
+            self.leave(camera);
+            return;
+        };
+        let up = direction(&obj.model_matrix(), self.ground);
+        let (north, east) = local_frame(obj, up);
+        let level = heading_direction(north, east, self.heading);
+        let look = level * self.pitch.cos() + up * self.pitch.sin();
+        camera.eye = obj.translation + up * (obj.radius * obj.scale * (1.0 + EYE_HEIGHT));
+        camera.target = camera.eye + look;
+        camera.up = up;
+    }
+}
+
+/// `v` turned by `matrix`'s rotation (its translation and scale left out).
+fn direction(matrix: &Matrix, v: Vector3) -> Vector3 {
+    let turned = multiply_matrix_vector4(matrix, &Vector4::new(v.x, v.y, v.z, 0.0));
+    Vector3::new(turned.x, turned.y, turned.z).normalized()
+}
+
+/// Local north and east on `obj` where the ground faces `up`: north points along the
+/// ground towards the body's north pole. At the poles, where that's undefined, any
+/// direction along the ground will do.
+fn local_frame(obj: &SceneObject, up: Vector3) -> (Vector3, Vector3) {
+    let axis = obj.spin_axis();
+    let mut north = axis - up * axis.dot(up);
+    if north.length() < 1e-4 {
+        let any = if up.x.abs() < 0.9 { Vector3::new(1.0, 0.0, 0.0) } else { Vector3::new(0.0, 0.0, 1.0) };
+        north = any - up * any.dot(up);
+    }
+    let north = north.normalized();
+    (north, north.cross(up))
+}
+
+/// Direction along the ground `heading` radians from north towards east.
+fn heading_direction(north: Vector3, east: Vector3, heading: f32) -> Vector3 {
+    north * heading.cos() + east * heading.sin()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn planet(position: Vector3, radius: f32, tilt: f32) -> SceneObject {
+        SceneObject { axial_tilt: tilt, ..SceneObject::test_sphere("planet", "planet", position, radius) }
+    }
+
+    #[test]
+    fn standing_point_turns_with_the_body() {
+        let mut body = planet(Vector3::new(10.0, 0.0, -4.0), 2.0, 0.4);
+        let mut camera = Camera::new(body.translation + Vector3::new(0.0, 0.0, 8.0), body.translation, Vector3::new(0.0, 1.0, 0.0));
+        let mut surface = SurfaceMode::new();
+        surface.land(&body, &camera).unwrap();
+        surface.place_camera(std::slice::from_ref(&body), &mut camera);
+
+        // Right below where the camera was, at eye height, up along the ground's normal
+        let normal = (camera.eye - body.translation).normalized();
+        assert!((normal - Vector3::new(0.0, 0.0, 1.0)).length() < 1e-4, "{:?}", normal);
+        assert!(((camera.eye - body.translation).length() - 2.0 * (1.0 + EYE_HEIGHT)).abs() < 1e-4);
+        assert!((camera.up - normal).length() < 1e-4);
+        assert!((camera.target - camera.eye).dot(normal).abs() < 0.2, "looking about level");
+
+        // A quarter turn later the ground has carried the camera round the spin axis
+        body.spin_angle = PI / 2.0;
+        surface.place_camera(std::slice::from_ref(&body), &mut camera);
+        let turned = (camera.eye - body.translation).normalized();
+        let axis = body.spin_axis();
+        assert!((turned - normal).length() > 0.5);
+        assert!((turned.dot(axis) - normal.dot(axis)).abs() < 1e-4, "same latitude");
+        assert!((camera.up - turned).length() < 1e-4);
+
+        surface.leave(&mut camera);
+        assert!(!surface.is_active() && camera.up == Vector3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn walking_follows_a_great_circle() {
+        let body = planet(Vector3::zero(), 3.0, 0.0);
+        let camera = Camera::new(Vector3::new(9.0, 0.0, 0.0), Vector3::zero(), Vector3::new(0.0, 1.0, 0.0));
+        let mut surface = SurfaceMode::new();
+        surface.land(&body, &camera).unwrap();
+        surface.heading = 0.0; // North, along the meridian
+        for _ in 0..10 {
+            surface.walk(&body, 1.0, 0.0, 0.1);
+        }
+        let ground = direction(&body.model_matrix(), surface.ground);
+        assert!((ground.y.asin() - WALK_SPEED).abs() < 1e-3, "{:?}", ground);
+        assert!(ground.z.abs() < 1e-4);
+
+        let sun = SceneObject { object_type: "sun".to_string(), ..planet(Vector3::zero(), 3.0, 0.0) };
+        assert!(SurfaceMode::new().land(&sun, &camera).is_err());
+    }
+}
@@ -0,0 +1,386 @@
+#![allow(dead_code)]
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use raylib::prelude::*;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::error::AppError;
+use crate::fragment::Fragment;
+
+/// Texture memory limits, read from the `[textures]` table of the scene file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TextureConfig {
+    pub budget_mb: f32, // Least recently used textures are evicted past this
+    pub max_size: i32,  // Sources larger than this (longer side, in pixels) are downsampled on load
+}
+
+impl Default for TextureConfig {
+    fn default() -> Self {
+        TextureConfig { budget_mb: 256.0, max_size: 2048 }
+    }
+}
+
+/// How a body reflects the sun.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShadingModel {
+    #[default]
+    Phong, // Lambert diffuse plus a Blinn-Phong highlight
+    Ggx,   // Cook-Torrance specular (GGX, Smith, Fresnel-Schlick) with energy-conserving diffuse
+}
+
+impl ShadingModel {
+    /// The other model, for comparing the two.
+    pub fn other(self) -> Self {
+        match self {
+            ShadingModel::Phong => ShadingModel::Ggx,
+            ShadingModel::Ggx => ShadingModel::Phong,
+        }
+    }
+}
+
+/// Surface response of a body, resolved from its material table and its kind.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Shading {
+    pub model: ShadingModel,
+    pub roughness: f32, // 0 = mirror, 1 = chalk
+    pub metalness: f32, // 0 = dielectric (4% reflectance), 1 = metal tinted by its color
+    pub ambient: Option<f32>, // Scale of the sky's light on its unlit side, in place of the scene's
+}
+
+impl Default for Shading {
+    fn default() -> Self {
+        Shading { model: ShadingModel::Phong, roughness: 0.9, metalness: 0.0, ambient: None }
+    }
+}
+
+impl Shading {
+    /// Defaults per body kind: planets and moons are rough rock or gas, the shuttle's
+    /// panels are smooth chrome that mirrors the sky.
+    pub fn for_kind(object_type: &str) -> Self {
+        match object_type {
+            "shuttle" => Shading { roughness: 0.3, metalness: 0.7, ..Shading::default() },
+            _ => Shading::default(),
+        }
+    }
+}
+
+/// What happens to texture coordinates outside [0, 1] along one axis.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WrapMode {
+    Repeat,         // Tiles: 1.25 reads like 0.25
+    MirroredRepeat, // Tiles, every other copy flipped: 1.25 reads like 0.75
+    ClampToEdge,    // Stretches the edge texels outwards
+}
+
+impl WrapMode {
+    /// Texel `i` of a row or column `size` texels long, brought back inside it.
+    fn texel(self, i: i32, size: i32) -> i32 {
+        match self {
+            WrapMode::Repeat => i.rem_euclid(size),
+            WrapMode::MirroredRepeat => {
+                let i = i.rem_euclid(2 * size);
+                if i < size { i } else { 2 * size - 1 - i }
+            }
+            WrapMode::ClampToEdge => i.clamp(0, size - 1),
+        }
+    }
+}
+
+/// Wrap modes of a material's maps along u and v.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Wrap {
+    pub u: WrapMode,
+    pub v: WrapMode,
+}
+
+impl Default for Wrap {
+    /// Around a sphere: u wraps past the date line, v stops at the poles.
+    fn default() -> Self {
+        Wrap { u: WrapMode::Repeat, v: WrapMode::ClampToEdge }
+    }
+}
+
+/// Which texture coordinates a material's maps are read with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UvSource {
+    #[default]
+    Sphere, // Longitude and latitude of the surface point, for equirectangular maps
+    Mesh,   // The model's own texture coordinates
+}
+
+/// Texture maps of one body, by path. Every map is optional; without a `day` map the
+/// body keeps its procedural shader.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaterialConfig {
+    pub day: Option<String>,      // Surface color (equirectangular)
+    pub night: Option<String>,    // Lights shown on the night side
+    pub clouds: Option<String>,   // Cloud cover, brightness = opacity
+    pub specular: Option<String>, // Shininess mask, e.g. oceans
+    pub shading: Option<ShadingModel>,
+    pub roughness: Option<f32>,
+    pub metalness: Option<f32>,
+    pub ambient: Option<f32>, // The [light] ambient by default
+    pub uv: UvSource,
+    pub wrap_u: Option<WrapMode>, // Repeat by default
+    pub wrap_v: Option<WrapMode>, // ClampToEdge by default
+}
+
+impl MaterialConfig {
+    /// The body kind's default shading with whatever this table overrides.
+    pub fn shading(&self, object_type: &str) -> Shading {
+        let default = Shading::for_kind(object_type);
+        Shading {
+            model: self.shading.unwrap_or(default.model),
+            roughness: self.roughness.unwrap_or(default.roughness).clamp(0.02, 1.0),
+            metalness: self.metalness.unwrap_or(default.metalness).clamp(0.0, 1.0),
+            ambient: self.ambient.map(|ambient| ambient.max(0.0)),
+        }
+    }
+
+    pub fn wrap(&self) -> Wrap {
+        let default = Wrap::default();
+        Wrap { u: self.wrap_u.unwrap_or(default.u), v: self.wrap_v.unwrap_or(default.v) }
+    }
+}
+
+/// The maps of a body that are loaded right now, handed to the shaders through `Uniforms`.
+#[derive(Clone, Default)]
+pub struct Material {
+    pub day: Option<Arc<Texture>>,
+    pub night: Option<Arc<Texture>>,
+    pub clouds: Option<Arc<Texture>>,
+    pub specular: Option<Arc<Texture>>,
+    pub shading: Shading,
+    pub uv: UvSource,
+    pub wrap: Wrap,
+}
+
+impl Material {
+    /// Where `fragment` reads this material's maps.
+    pub fn uv(&self, fragment: &Fragment) -> Vector2 {
+        match self.uv {
+            UvSource::Sphere => fragment.uv,
+            UvSource::Mesh => fragment.tex_coords,
+        }
+    }
+}
+
+pub struct Texture {
+    pub width: i32,
+    pub height: i32,
+    pixels: Vec<Color>,
+}
+
+impl Texture {
+    pub fn load(path: &str, max_size: i32) -> Result<Self, AppError> {
+        let mut image = Image::load_image(path).map_err(|_| AppError::AssetMissing { path: path.to_string() })?;
+
+        let longest = image.width.max(image.height);
+        if max_size > 0 && longest > max_size {
+            let scale = max_size as f32 / longest as f32;
+            let width = ((image.width as f32 * scale) as i32).max(1);
+            let height = ((image.height as f32 * scale) as i32).max(1);
+            debug!(path, from = longest, to = max_size, "downsampling texture");
+            image.resize(width, height);
+        }
+
+        Ok(Texture {
+            width: image.width,
+            height: image.height,
+            pixels: image.get_image_data().to_vec(),
+        })
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.pixels.len() * std::mem::size_of::<Color>()
+    }
+
+    fn texel(&self, x: i32, y: i32, wrap: Wrap) -> Vector3 {
+        let x = wrap.u.texel(x, self.width);
+        let y = wrap.v.texel(y, self.height);
+        let c = self.pixels[(y * self.width + x) as usize];
+        Vector3::new(c.r as f32 / 255.0, c.g as f32 / 255.0, c.b as f32 / 255.0)
+    }
+
+    /// Bilinear sample at `uv`, RGB in [0, 1]. Outside [0, 1]² the coordinates `wrap`;
+    /// so do the four texels blended, so a repeating map has no seam at its edges.
+    pub fn sample(&self, uv: Vector2, wrap: Wrap) -> Vector3 {
+        let x = uv.x * self.width as f32 - 0.5;
+        let y = uv.y * self.height as f32 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i32, y0 as i32);
+
+        let top = self.texel(x0, y0, wrap) * (1.0 - fx) + self.texel(x0 + 1, y0, wrap) * fx;
+        let bottom = self.texel(x0, y0 + 1, wrap) * (1.0 - fx) + self.texel(x0 + 1, y0 + 1, wrap) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+}
+
+struct Entry {
+    texture: Arc<Texture>,
+    last_used: u64, // Frame it was last requested
+}
+
+/// Textures keyed by path, loaded the first time a body asks for them and evicted
+/// least-recently-used first once they add up to more than the budget.
+pub struct TextureCache {
+    config: TextureConfig,
+    entries: HashMap<String, Entry>,
+    failed: HashSet<String>, // Paths that failed to load; not retried
+    errors: Vec<AppError>,   // Failures not yet reported
+    frame: u64,
+}
+
+impl TextureCache {
+    pub fn new(config: TextureConfig) -> Self {
+        TextureCache {
+            config,
+            entries: HashMap::new(),
+            failed: HashSet::new(),
+            errors: Vec::new(),
+            frame: 0,
+        }
+    }
+
+    /// New limits (e.g. after a scene reload). A smaller `max_size` only applies to textures loaded afterwards.
+    pub fn set_config(&mut self, config: TextureConfig) {
+        self.config = config;
+        self.evict();
+    }
+
+    pub fn begin_frame(&mut self) {
+        self.frame += 1;
+    }
+
+    pub fn used_bytes(&self) -> usize {
+        self.entries.values().map(|e| e.texture.bytes()).sum()
+    }
+
+    fn budget_bytes(&self) -> usize {
+        (self.config.budget_mb.max(0.0) * 1024.0 * 1024.0) as usize
+    }
+
+    pub fn get(&mut self, path: &str) -> Option<Arc<Texture>> {
+        if let Some(entry) = self.entries.get_mut(path) {
+            entry.last_used = self.frame;
+            return Some(entry.texture.clone());
+        }
+        if self.failed.contains(path) {
+            return None;
+        }
+
+        match Texture::load(path, self.config.max_size) {
+            Ok(texture) => {
+                let texture = Arc::new(texture);
+                debug!(path, width = texture.width, height = texture.height, "texture loaded");
+                self.entries.insert(path.to_string(), Entry { texture: texture.clone(), last_used: self.frame });
+                self.evict();
+                Some(texture)
+            }
+            Err(error) => {
+                self.failed.insert(path.to_string());
+                self.errors.push(error);
+                None
+            }
+        }
+    }
+
+    /// Looks up (loading as needed) every map of a body, and resolves its shading.
+    pub fn material(&mut self, config: &MaterialConfig, object_type: &str) -> Material {
+        let mut get = |path: &Option<String>| path.as_deref().and_then(|p| self.get(p));
+        Material {
+            day: get(&config.day),
+            night: get(&config.night),
+            clouds: get(&config.clouds),
+            specular: get(&config.specular),
+            shading: config.shading(object_type),
+            uv: config.uv,
+            wrap: config.wrap(),
+        }
+    }
+
+    /// Load failures since the last call.
+    pub fn take_errors(&mut self) -> Vec<AppError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    /// Drops least recently used textures until the cache fits its budget. Textures
+    /// requested this frame are never evicted: when one frame's maps don't fit, the
+    /// budget is exceeded for it rather than reloading them from disk every frame.
+    fn evict(&mut self) {
+        let budget = self.budget_bytes();
+        let mut used = self.used_bytes();
+        while used > budget {
+            let oldest = self
+                .entries
+                .iter()
+                .filter(|(_, entry)| entry.last_used != self.frame)
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(path, _)| path.clone());
+            let Some(path) = oldest else {
+                break;
+            };
+            if let Some(entry) = self.entries.remove(&path) {
+                used -= entry.texture.bytes();
+                debug!(path, used, budget, "texture evicted");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 4×4 black and white checker, white in the top-left corner.
+    fn checker() -> Texture {
+        let pixels = (0..16).map(|i| if (i % 4 + i / 4) % 2 == 0 { Color::WHITE } else { Color::BLACK }).collect();
+        Texture { width: 4, height: 4, pixels }
+    }
+
+    #[test]
+    fn wrap_modes_past_the_edge() {
+        let texture = checker();
+        let wrap = |u| Wrap { u, v: WrapMode::ClampToEdge };
+        // At texel centers of the first row, where the bilinear filter reads one texel
+        let at = |u: f32, mode| texture.sample(Vector2::new(u, 0.125), wrap(mode)).x;
+
+        assert_eq!(at(1.375, WrapMode::Repeat), at(0.375, WrapMode::Repeat));
+        assert_eq!(at(1.125, WrapMode::Repeat), 1.0, "reads the first column again");
+        assert_eq!(at(1.375, WrapMode::MirroredRepeat), at(0.625, WrapMode::MirroredRepeat));
+        assert_eq!(at(1.125, WrapMode::MirroredRepeat), 0.0, "reads the last column again");
+        assert_eq!(at(1.375, WrapMode::ClampToEdge), 0.0, "stays on the last column");
+        assert_eq!(at(-0.375, WrapMode::ClampToEdge), 1.0, "stays on the first column");
+
+        // Between the last texel and the first, repeat blends across the seam, clamp doesn't
+        assert_eq!(at(1.0, WrapMode::Repeat), 0.5);
+        assert_eq!(at(0.0, WrapMode::Repeat), at(1.0, WrapMode::Repeat));
+        assert_eq!(at(1.0, WrapMode::ClampToEdge), 0.0);
+        assert_eq!(at(1.0, WrapMode::MirroredRepeat), 0.0);
+    }
+
+    #[test]
+    fn textures_in_use_this_frame_are_kept_over_budget() {
+        let mut cache = TextureCache::new(TextureConfig { budget_mb: 0.0, ..TextureConfig::default() });
+        let entry = |frame| Entry { texture: Arc::new(checker()), last_used: frame };
+        cache.entries.insert("a.png".to_string(), entry(0));
+        cache.entries.insert("b.png".to_string(), entry(0));
+        cache.evict();
+        assert_eq!(cache.entries.len(), 2, "both were requested this frame");
+
+        // Next frame only one is asked for; the other goes
+        cache.begin_frame();
+        cache.get("a.png").expect("still cached");
+        cache.evict();
+        assert!(cache.entries.contains_key("a.png") && !cache.entries.contains_key("b.png"));
+    }
+}
@@ -0,0 +1,89 @@
+#![allow(dead_code)]
+
+use rayon::prelude::*;
+
+/// Environment variable read when `--threads` isn't given.
+pub const THREADS_ENV: &str = "SOLAR_THREADS";
+/// Machines with this many logical cores or fewer render serially by default.
+const SERIAL_MAX_CORES: usize = 2;
+
+/// Worker threads for the parallel render stages. One thread means no pool at all:
+/// work runs inline on the main thread, exactly as before the stages were parallel.
+pub struct RenderThreads {
+    pool: Option<rayon::ThreadPool>,
+    count: usize,
+}
+
+impl RenderThreads {
+    /// Threads from `--threads <n>`, else `SOLAR_THREADS`, else `default_thread_count`.
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let requested = match args.iter().position(|a| a == "--threads") {
+            Some(i) => Some(parse_count(args.get(i + 1).ok_or("--threads needs a thread count")?)?),
+            None => match std::env::var(THREADS_ENV) {
+                Ok(value) => Some(parse_count(&value).map_err(|e| format!("{}: {}", THREADS_ENV, e))?),
+                Err(_) => None,
+            },
+        };
+        RenderThreads::new(requested.unwrap_or_else(default_thread_count))
+    }
+
+    pub fn new(count: usize) -> Result<Self, String> {
+        let mut threads = RenderThreads { pool: None, count: 1 };
+        threads.set_thread_count(count)?;
+        Ok(threads)
+    }
+
+    /// Rebuilds the pool with `count` threads; 1 switches to the serial path.
+    pub fn set_thread_count(&mut self, count: usize) -> Result<(), String> {
+        if count == 0 {
+            return Err("thread count must be at least 1".to_string());
+        }
+        self.pool = if count == 1 {
+            None
+        } else {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(count)
+                .thread_name(|i| format!("render-{}", i))
+                .build()
+                .map_err(|e| e.to_string())?;
+            Some(pool)
+        };
+        self.count = count;
+        Ok(())
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Runs `f` on every item, spread over the pool. Items don't see each other, so
+    /// the result is the same whatever the thread count.
+    pub fn for_each_mut<T, F>(&self, items: &mut [T], f: F)
+    where
+        T: Send,
+        F: Fn(&mut T) + Send + Sync,
+    {
+        match &self.pool {
+            Some(pool) => pool.install(|| items.par_iter_mut().for_each(f)),
+            None => items.iter_mut().for_each(f),
+        }
+    }
+}
+
+/// One thread fewer than the logical cores, leaving one for the window and input;
+/// serial on machines with two cores or fewer, where the pool costs more than it saves.
+pub fn default_thread_count() -> usize {
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+    if cores <= SERIAL_MAX_CORES {
+        1
+    } else {
+        cores - 1
+    }
+}
+
+fn parse_count(value: &str) -> Result<usize, String> {
+    match value.trim().parse::<usize>() {
+        Ok(count) if count > 0 => Ok(count),
+        _ => Err(format!("invalid thread count '{}'", value)),
+    }
+}
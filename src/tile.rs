@@ -0,0 +1,55 @@
+#![allow(dead_code)]
+
+use raylib::prelude::*;
+
+use crate::matrix::create_frustum_matrix;
+
+/// One window of a video wall: column `column` and row `row` (from 0, top left first) of
+/// a `columns`×`rows` grid of equal windows that together show a single view.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Tile {
+    pub column: u32,
+    pub row: u32,
+    pub columns: u32,
+    pub rows: u32,
+}
+
+impl Tile {
+    /// Reads `--tile <c> <r> of <C>x<R>`, or None when it isn't given.
+    pub fn from_args(args: &[String]) -> Result<Option<Self>, String> {
+        let Some(i) = args.iter().position(|a| a == "--tile") else {
+            return Ok(None);
+        };
+        let usage = "--tile needs a column, a row and a grid: --tile <c> <r> of <C>x<R>";
+        let words: Vec<&str> = args.iter().skip(i + 1).take(4).map(String::as_str).collect();
+        let [column, row, "of", grid] = words[..] else {
+            return Err(usage.to_string());
+        };
+        let (columns, rows) = grid.split_once('x').ok_or(usage)?;
+        let number = |s: &str| s.parse::<u32>().map_err(|_| format!("invalid tile number '{}'", s));
+        let tile = Tile { column: number(column)?, row: number(row)?, columns: number(columns)?, rows: number(rows)? };
+        if tile.columns == 0 || tile.rows == 0 || tile.column >= tile.columns || tile.row >= tile.rows {
+            return Err(format!("tile {} {} is outside a {}x{} grid", tile.column, tile.row, tile.columns, tile.rows));
+        }
+        Ok(Some(tile))
+    }
+
+    /// This tile's share of the whole wall's view window on the near plane, as
+    /// (left, right, bottom, top). `fov_y` is the wall's vertical field of view and
+    /// `aspect` this tile's own width over height.
+    pub fn window(&self, fov_y: f32, aspect: f32, near: f32) -> (f32, f32, f32, f32) {
+        let half_height = near * (fov_y * 0.5).tan();
+        let half_width = half_height * aspect * self.columns as f32 / self.rows as f32;
+        let width = 2.0 * half_width / self.columns as f32;
+        let height = 2.0 * half_height / self.rows as f32;
+        let left = -half_width + width * self.column as f32;
+        let top = half_height - height * self.row as f32;
+        (left, left + width, top - height, top)
+    }
+
+    /// Off-axis projection of this tile, seamless with its neighbours' along shared edges.
+    pub fn projection(&self, fov_y: f32, aspect: f32, near: f32, far: f32) -> Matrix {
+        let (left, right, bottom, top) = self.window(fov_y, aspect, near);
+        create_frustum_matrix(left, right, bottom, top, near, far)
+    }
+}
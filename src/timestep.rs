@@ -0,0 +1,199 @@
+#![allow(dead_code)]
+
+use raylib::prelude::*;
+
+use crate::input::InputState;
+use crate::scene::{update_scene, SceneObject};
+
+/// Keys that halve and double the time scale.
+pub const SLOWER_KEY: KeyboardKey = KeyboardKey::KEY_COMMA;
+pub const FASTER_KEY: KeyboardKey = KeyboardKey::KEY_PERIOD;
+
+/// Simulated seconds per orbit update (120 steps per simulated second).
+pub const SIM_STEP: f32 = 1.0 / 120.0;
+const MIN_TIME_SCALE: f32 = 1.0 / 16.0;
+const MAX_TIME_SCALE: f32 = 256.0;
+/// Most steps run in one frame; past that the simulation falls behind instead of stalling the frame.
+const MAX_STEPS_PER_FRAME: u32 = 4096;
+
+/// What the orbit update moves, kept per body to interpolate between steps.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct BodyState {
+    translation: Vector3,
+    barycenter: Vector3,
+    spin_angle: f32,
+    precession_angle: f32,
+}
+
+fn capture(objects: &[SceneObject]) -> Vec<BodyState> {
+    objects
+        .iter()
+        .map(|o| BodyState {
+            translation: o.translation,
+            barycenter: o.barycenter,
+            spin_angle: o.spin_angle,
+            precession_angle: o.precession_angle,
+        })
+        .collect()
+}
+
+fn apply(objects: &mut [SceneObject], states: &[BodyState]) {
+    for (obj, state) in objects.iter_mut().zip(states) {
+        obj.translation = state.translation;
+        obj.barycenter = state.barycenter;
+        obj.spin_angle = state.spin_angle;
+        obj.precession_angle = state.precession_angle;
+    }
+}
+
+/// Fixed-timestep simulation: orbits and spins advance in whole steps of `SIM_STEP`
+/// simulated seconds, however long frames take and whatever the time scale, and each
+/// frame shows the bodies interpolated between the last two steps.
+pub struct SimClock {
+    pub time_scale: f32,
+    pub time: f32,            // Simulated time of the latest step
+    pub render_time: f32,     // Simulated time of the rendered frame, for shaders and the sun's activity
+    accumulator: f32,         // Simulated seconds not stepped yet, always under one step
+    previous: Vec<BodyState>, // The step before the latest
+    current: Vec<BodyState>,  // The latest step; between frames the objects hold an interpolated state
+}
+
+impl SimClock {
+    pub fn new() -> Self {
+        SimClock { time_scale: 1.0, time: 0.0, render_time: 0.0, accumulator: 0.0, previous: Vec::new(), current: Vec::new() }
+    }
+
+    /// `,` and `.` halve and double the time scale.
+    pub fn process_input(&mut self, input: &InputState) {
+        if input.is_key_pressed(SLOWER_KEY) {
+            self.time_scale = (self.time_scale * 0.5).max(MIN_TIME_SCALE);
+        }
+        if input.is_key_pressed(FASTER_KEY) {
+            self.time_scale = (self.time_scale * 2.0).min(MAX_TIME_SCALE);
+        }
+    }
+
+    /// Runs the steps `dt` real seconds add up to and leaves `objects` interpolated for
+    /// rendering. While paused nothing is added and the bodies sit exactly on the latest step.
+    pub fn advance(&mut self, objects: &mut [SceneObject], dt: f32, paused: bool) {
+        self.advance_with(objects, dt, paused, |_, _| true);
+    }
+
+    /// `advance`, calling `on_step` with the bodies and the simulated time after every step.
+    /// When it returns false the steps left this frame are dropped and the bodies stay on
+    /// that step, as if paused right there.
+    pub fn advance_with<F: FnMut(&[SceneObject], f32) -> bool>(&mut self, objects: &mut [SceneObject], dt: f32, paused: bool, mut on_step: F) {
+        // Back to the simulated state; bodies added or removed since start over from here
+        if self.current.len() == objects.len() {
+            apply(objects, &self.current);
+        }
+        if !paused {
+            self.accumulator += dt * self.time_scale;
+        }
+
+        let steps = (self.accumulator / SIM_STEP) as u32;
+        if steps == 0 {
+            // Still picks up edits (console, scene reloads) without moving time
+            update_scene(objects, 0.0);
+        }
+        let run = steps.min(MAX_STEPS_PER_FRAME);
+        let mut stopped = false;
+        for i in 0..run {
+            if i + 1 == run {
+                self.previous = capture(objects);
+            }
+            update_scene(objects, SIM_STEP);
+            self.time += SIM_STEP;
+            if !on_step(objects, self.time) {
+                stopped = true;
+                break;
+            }
+        }
+        self.accumulator = if steps > MAX_STEPS_PER_FRAME { 0.0 } else { self.accumulator - steps as f32 * SIM_STEP };
+
+        self.current = capture(objects);
+        let paused = paused || stopped;
+        if paused || self.previous.len() != self.current.len() {
+            self.accumulator = 0.0;
+            self.previous = self.current.clone();
+        }
+
+        let alpha = self.alpha();
+        for ((obj, a), b) in objects.iter_mut().zip(&self.previous).zip(&self.current) {
+            obj.translation = a.translation + (b.translation - a.translation) * alpha;
+            obj.barycenter = a.barycenter + (b.barycenter - a.barycenter) * alpha;
+            obj.spin_angle = a.spin_angle + (b.spin_angle - a.spin_angle) * alpha;
+            obj.precession_angle = a.precession_angle + (b.precession_angle - a.precession_angle) * alpha;
+        }
+        self.render_time = if paused { self.time } else { self.time - SIM_STEP * (1.0 - alpha) };
+    }
+
+    /// Jumps to simulated time `time` (forward or back) in one go, leaving the bodies on
+    /// it with nothing to interpolate.
+    pub fn seek(&mut self, objects: &mut [SceneObject], time: f32) {
+        if self.current.len() == objects.len() {
+            apply(objects, &self.current);
+        }
+        update_scene(objects, time - self.time);
+        self.time = time;
+        self.render_time = time;
+        self.accumulator = 0.0;
+        self.current = capture(objects);
+        self.previous = self.current.clone();
+    }
+
+    /// How far the rendered frame is from the previous step to the latest, in [0, 1).
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / SIM_STEP).clamp(0.0, 1.0)
+    }
+
+    /// Overlay line while time runs at anything but normal speed.
+    pub fn status(&self) -> Option<String> {
+        (self.time_scale != 1.0).then(|| format!("Time x{} (, and . to change)", self.time_scale))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spinning() -> Vec<SceneObject> {
+        let mut body = SceneObject::test_sphere("mars", "rocky1", Vector3::zero(), 1.0);
+        body.spin = 12.0; // 0.1 radians a step
+        vec![body]
+    }
+
+    #[test]
+    fn a_frame_on_a_step_boundary_shows_the_step_before_the_latest() {
+        // Frames trail the simulation by a step, so one landing on a boundary shows the
+        // previous step whole and the next frame carries on from it with no jump
+        let (mut clock, mut objects) = (SimClock::new(), spinning());
+        clock.advance(&mut objects, SIM_STEP * 2.0, false);
+        assert_eq!(clock.alpha(), 0.0);
+        assert!((objects[0].spin_angle - 0.1).abs() < 1e-5, "spin {}", objects[0].spin_angle);
+        assert!((clock.time - SIM_STEP * 2.0).abs() < 1e-6);
+        assert!((clock.render_time - SIM_STEP).abs() < 1e-6);
+    }
+
+    #[test]
+    fn frames_between_steps_are_interpolated() {
+        let (mut clock, mut objects) = (SimClock::new(), spinning());
+        clock.advance(&mut objects, SIM_STEP * 2.5, false);
+        assert!((clock.alpha() - 0.5).abs() < 1e-3, "alpha {}", clock.alpha());
+        // Halfway from the first step (0.1) to the second (0.2)
+        assert!((objects[0].spin_angle - 0.15).abs() < 1e-4, "spin {}", objects[0].spin_angle);
+        assert!((clock.render_time - SIM_STEP * 1.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn pausing_leaves_the_bodies_on_the_latest_step() {
+        let (mut clock, mut objects) = (SimClock::new(), spinning());
+        clock.advance(&mut objects, SIM_STEP * 2.5, false);
+        let time = clock.time;
+        clock.advance(&mut objects, 1.0, true);
+        assert_eq!(clock.alpha(), 0.0);
+        assert_eq!(clock.time, time, "time moved while paused");
+        assert_eq!(objects[0].spin_angle, clock.current[0].spin_angle);
+        assert_eq!(clock.render_time, time);
+    }
+}
@@ -0,0 +1,110 @@
+#![allow(dead_code)]
+
+use std::collections::VecDeque;
+
+use raylib::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::framebuffer::Framebuffer;
+use crate::line::polyline_fading;
+use crate::minimap::kind_color;
+use crate::scene::SceneObject;
+use crate::Uniforms;
+use crate::input::InputState;
+
+/// Key that shows and hides the trails.
+pub const TRAILS_KEY: KeyboardKey = KeyboardKey::KEY_T;
+
+/// Trail settings, read from the `[trails]` table of the scene file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrailConfig {
+    pub enabled: bool,  // Shown at startup; T toggles them either way
+    pub length: f32,    // Simulated seconds of history each trail covers
+    pub samples: usize, // Positions kept per trail
+}
+
+impl Default for TrailConfig {
+    fn default() -> Self {
+        TrailConfig { enabled: true, length: 30.0, samples: 240 }
+    }
+}
+
+/// Recent positions of one body, oldest first.
+struct Trail {
+    name: String,
+    points: VecDeque<Vector3>,
+}
+
+/// Where each orbiting body has actually been lately, sampled at even steps of
+/// simulated time (not frames), so a trail covers the same stretch of orbit at any
+/// time scale and shows barycenter wobbles and perturbations the ellipse doesn't.
+pub struct Trails {
+    pub visible: bool,
+    pub config: TrailConfig,
+    trails: Vec<Trail>,
+    next_sample: f32, // Simulated time of the next sample
+}
+
+impl Trails {
+    pub fn new(config: TrailConfig) -> Self {
+        Trails { visible: config.enabled, config, trails: Vec::new(), next_sample: 0.0 }
+    }
+
+    pub fn process_input(&mut self, input: &InputState) {
+        if input.is_key_pressed(TRAILS_KEY) {
+            self.visible = !self.visible;
+        }
+    }
+
+    /// Simulated seconds between samples.
+    fn interval(&self) -> f32 {
+        self.config.length.max(0.0) / self.config.samples.max(1) as f32
+    }
+
+    /// Forgets every trail, e.g. when the scene is reloaded.
+    pub fn clear(&mut self) {
+        self.trails.clear();
+    }
+
+    /// Called after every simulation step with the simulated `time`: samples the
+    /// orbiting bodies whenever a sample is due. Starts over if the bodies changed.
+    pub fn record(&mut self, objects: &[SceneObject], time: f32) {
+        let orbiting = objects.iter().filter(|o| o.orbit.is_some());
+        if !orbiting.clone().map(|o| &o.name).eq(self.trails.iter().map(|t| &t.name)) {
+            self.trails = orbiting.map(|o| Trail { name: o.name.clone(), points: VecDeque::new() }).collect();
+            self.next_sample = time;
+        }
+        if time < self.next_sample {
+            return;
+        }
+        self.next_sample = time + self.interval();
+
+        let capacity = self.config.samples.max(1);
+        for (trail, obj) in self.trails.iter_mut().zip(objects.iter().filter(|o| o.orbit.is_some())) {
+            if trail.points.len() == capacity {
+                trail.points.pop_front();
+            }
+            trail.points.push_back(obj.translation);
+        }
+    }
+
+    /// Draws each trail from its oldest sample (transparent) up to the body's current
+    /// position (opaque), in the body's minimap color. Bodies for which `shown` is false
+    /// are skipped.
+    pub fn draw<F: Fn(&SceneObject) -> bool>(&self, framebuffer: &mut Framebuffer, uniforms: &Uniforms, objects: &[SceneObject], shown: F) {
+        if !self.visible {
+            return;
+        }
+        for trail in &self.trails {
+            let Some(obj) = objects.iter().find(|o| o.name == trail.name) else {
+                continue;
+            };
+            if !shown(obj) || trail.points.is_empty() {
+                continue;
+            }
+            let points: Vec<Vector3> = trail.points.iter().copied().chain(std::iter::once(obj.translation)).collect();
+            polyline_fading(framebuffer, uniforms, &points, kind_color(&obj.object_type) * 0.8);
+        }
+    }
+}
@@ -1,34 +1,92 @@
+#![allow(dead_code)]
+
 use raylib::prelude::{Vector2, Vector3};
 
 use crate::vertex::Vertex;
 use crate::fragment::Fragment;
 use crate::light::Light;
+use crate::planetshaders::spherical_uv;
+
+/// Largest target rasterized into, in pixels along each side: a sanity limit on top of
+/// the target's own size, for vertices projected out to absurd coordinates.
+pub const MAX_TARGET_SIZE: u32 = 16384;
 
 // Simple CPU triangle rasterizer that interpolates vertex.color
-pub fn triangle(v0: &Vertex, v1: &Vertex, v2: &Vertex, _light: &Light) -> Vec<Fragment> {
+pub fn triangle(v0: &Vertex, v1: &Vertex, v2: &Vertex, light: &Light) -> Vec<Fragment> {
     let mut fragments = Vec::new();
+    triangle_into(v0, v1, v2, light, None, (MAX_TARGET_SIZE, MAX_TARGET_SIZE), &mut fragments);
+    fragments
+}
+
+// Same as `triangle`, but appends into a caller-owned buffer so it can be reused.
+// `centroid_color` is the vertex color evaluated again at the triangle's centroid: the
+// color then bulges from the linear blend towards it inside the triangle, so large
+// triangles don't show pure linear gradients. Only pixels inside a `target`-sized
+// frame are visited; returns whether the triangle was larger than the whole frame and
+// got clipped to it.
+pub fn triangle_into(
+    v0: &Vertex,
+    v1: &Vertex,
+    v2: &Vertex,
+    _light: &Light,
+    centroid_color: Option<Vector3>,
+    target: (u32, u32),
+    fragments: &mut Vec<Fragment>,
+) -> bool {
 
     // Use transformed_position as screen-space
     let p0 = v0.transformed_position;
     let p1 = v1.transformed_position;
     let p2 = v2.transformed_position;
 
-    // Bounding box
-    let min_x = p0.x.min(p1.x).min(p2.x).floor() as i32;
-    let max_x = p0.x.max(p1.x).max(p2.x).ceil() as i32;
-    let min_y = p0.y.min(p1.y).min(p2.y).floor() as i32;
-    let max_y = p0.y.max(p1.y).max(p2.y).ceil() as i32;
-
-    // Helper for barycentric coordinates
-    fn edge(a: Vector3, b: Vector3, c: Vector3) -> f32 {
-        (c.x - a.x) * (b.y - a.y) - (c.y - a.y) * (b.x - a.x)
-    }
-
     let area = edge(p0, p1, p2);
-    if area == 0.0 {
-        return fragments; // Degenerate triangle
+    if area == 0.0 || !area.is_finite() {
+        return false; // Degenerate triangle
     }
 
+    // Bounding box, cut down to the target so a huge triangle costs no more than a
+    // full-screen one
+    let (width, height) = (target.0.min(MAX_TARGET_SIZE) as f32, target.1.min(MAX_TARGET_SIZE) as f32);
+    let (low_x, high_x) = (p0.x.min(p1.x).min(p2.x).floor(), p0.x.max(p1.x).max(p2.x).ceil());
+    let (low_y, high_y) = (p0.y.min(p1.y).min(p2.y).floor(), p0.y.max(p1.y).max(p2.y).ceil());
+    let oversized = high_x - low_x > width || high_y - low_y > height;
+    let min_x = low_x.max(0.0) as i32;
+    let max_x = high_x.min(width - 1.0) as i32;
+    let min_y = low_y.max(0.0) as i32;
+    let max_y = high_y.min(height - 1.0) as i32;
+
+    // Orient every edge so the interior is on the positive side, then decide once
+    // per edge whether pixel centers exactly on it belong to this triangle
+    let sign = area.signum();
+    let owns0 = is_top_left(p1, p2, sign);
+    let owns1 = is_top_left(p2, p0, sign);
+    let owns2 = is_top_left(p0, p1, sign);
+
+    // Barycentric weights are affine in screen space, so their per-pixel gradients are
+    // constant over the triangle. Used to step the object-space normal one pixel in x
+    // and y for the UV derivatives below.
+    let o0 = v0.normal;
+    let o1 = v1.normal;
+    let o2 = v2.normal;
+    let (dw0dx, dw0dy) = ((p2.y - p1.y) / area, -(p2.x - p1.x) / area);
+    let (dw1dx, dw1dy) = ((p0.y - p2.y) / area, -(p0.x - p2.x) / area);
+    let (dw2dx, dw2dy) = ((p1.y - p0.y) / area, -(p1.x - p0.x) / area);
+    let dndx = Vector3::new(
+        o0.x * dw0dx + o1.x * dw1dx + o2.x * dw2dx,
+        o0.y * dw0dx + o1.y * dw1dx + o2.y * dw2dx,
+        o0.z * dw0dx + o1.z * dw1dx + o2.z * dw2dx,
+    );
+    let dndy = Vector3::new(
+        o0.x * dw0dy + o1.x * dw1dy + o2.x * dw2dy,
+        o0.y * dw0dy + o1.y * dw1dy + o2.y * dw2dy,
+        o0.z * dw0dy + o1.z * dw1dy + o2.z * dw2dy,
+    );
+
+    // What the centroid's color adds over the linear blend there. Spread with the bubble
+    // 27·w0·w1·w2, which is 1 at the centroid and 0 along the edges, so neighbouring
+    // triangles still meet with the same colors.
+    let bulge = centroid_color.map_or(Vector3::zero(), |c| c - (v0.color + v1.color + v2.color) * (1.0 / 3.0));
+
     for y in min_y..=max_y {
         for x in min_x..=max_x {
             let px = x as f32 + 0.5;
@@ -39,10 +97,8 @@ pub fn triangle(v0: &Vertex, v1: &Vertex, v2: &Vertex, _light: &Light) -> Vec<Fr
             let w1 = edge(p2, p0, p);
             let w2 = edge(p0, p1, p);
 
-            // Same sign as area => inside triangle
-            if (w0 >= 0.0 && w1 >= 0.0 && w2 >= 0.0 && area > 0.0)
-                || (w0 <= 0.0 && w1 <= 0.0 && w2 <= 0.0 && area < 0.0)
-            {
+            // Same sign as area => inside triangle; on an edge => top-left rule
+            if covers(w0 * sign, owns0) && covers(w1 * sign, owns1) && covers(w2 * sign, owns2) {
                 let w0n = w0 / area;
                 let w1n = w1 / area;
                 let w2n = w2 / area;
@@ -59,16 +115,356 @@ pub fn triangle(v0: &Vertex, v1: &Vertex, v2: &Vertex, _light: &Light) -> Vec<Fr
                     c0.x * w0n + c1.x * w1n + c2.x * w2n,
                     c0.y * w0n + c1.y * w1n + c2.y * w2n,
                     c0.z * w0n + c1.z * w1n + c2.z * w2n,
+                ) + bulge * (27.0 * w0n * w1n * w2n);
+
+                // Interpolate world-space normal and position for lighting
+                let n0 = v0.transformed_normal;
+                let n1 = v1.transformed_normal;
+                let n2 = v2.transformed_normal;
+                let normal = Vector3::new(
+                    n0.x * w0n + n1.x * w1n + n2.x * w2n,
+                    n0.y * w0n + n1.y * w1n + n2.y * w2n,
+                    n0.z * w0n + n1.z * w1n + n2.z * w2n,
+                );
+
+                let q0 = v0.world_position;
+                let q1 = v1.world_position;
+                let q2 = v2.world_position;
+                let world_position = Vector3::new(
+                    q0.x * w0n + q1.x * w1n + q2.x * w2n,
+                    q0.y * w0n + q1.y * w1n + q2.y * w2n,
+                    q0.z * w0n + q1.z * w1n + q2.z * w2n,
+                );
+
+                // Surface UV and its screen-space derivatives, by evaluating the
+                // parameterization at the neighboring pixels
+                let object_normal = Vector3::new(
+                    o0.x * w0n + o1.x * w1n + o2.x * w2n,
+                    o0.y * w0n + o1.y * w1n + o2.y * w2n,
+                    o0.z * w0n + o1.z * w1n + o2.z * w2n,
+                );
+                let uv = spherical_uv(object_normal);
+                let duvdx = uv_delta(uv, spherical_uv(object_normal + dndx));
+                let duvdy = uv_delta(uv, spherical_uv(object_normal + dndy));
+
+                let t0 = v0.tex_coords;
+                let t1 = v1.tex_coords;
+                let t2 = v2.tex_coords;
+                let tex_coords = Vector2::new(
+                    t0.x * w0n + t1.x * w1n + t2.x * w2n,
+                    t0.y * w0n + t1.y * w1n + t2.y * w2n,
                 );
 
                 fragments.push(Fragment {
                     position: Vector2::new(px, py),
                     color,
                     depth,
+                    normal,
+                    world_position,
+                    tex_coords,
+                    uv,
+                    duvdx,
+                    duvdy,
                 });
             }
         }
     }
+    oversized
+}
 
-    fragments
-}
\ No newline at end of file
+// Edge function for the pixel c against edge a->b. The endpoints are put in a fixed
+// order before evaluating, so two triangles sharing an edge compute bit-identical
+// (negated) values and the fill rule can't leave cracks or double-draw pixels.
+fn edge(a: Vector3, b: Vector3, c: Vector3) -> f32 {
+    if (a.x, a.y) <= (b.x, b.y) {
+        (c.x - a.x) * (b.y - a.y) - (c.y - a.y) * (b.x - a.x)
+    } else {
+        -((c.x - b.x) * (a.y - b.y) - (c.y - b.y) * (a.x - b.x))
+    }
+}
+
+/// Whether a screen-space triangle (after the viewport, y down) faces the camera. The
+/// world is right-handed and front faces wind counter-clockwise seen from outside; the
+/// viewport's y flip turns that into a positive `edge` area.
+pub fn is_front_facing(p0: Vector3, p1: Vector3, p2: Vector3) -> bool {
+    edge(p0, p1, p2) > 0.0
+}
+
+// Top-left fill rule. `sign` flips the edge direction for clockwise triangles so
+// every triangle is treated as if wound the same way. Exactly one of the two
+// triangles sharing an edge (traversing it in opposite directions) owns it.
+fn is_top_left(a: Vector3, b: Vector3, sign: f32) -> bool {
+    let dx = (b.x - a.x) * sign;
+    let dy = (b.y - a.y) * sign;
+    dy > 0.0 || (dy == 0.0 && dx < 0.0)
+}
+
+fn covers(w: f32, owns_edge: bool) -> bool {
+    w > 0.0 || (w == 0.0 && owns_edge)
+}
+
+// Difference between two spherical UVs, taking the short way around the u seam
+fn uv_delta(from: Vector2, to: Vector2) -> Vector2 {
+    let mut du = to.x - from.x;
+    if du > 0.5 {
+        du -= 1.0;
+    } else if du < -0.5 {
+        du += 1.0;
+    }
+    Vector2::new(du, to.y - from.y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::collections::HashSet;
+
+    const CASES: usize = 500;
+
+    fn vertex(x: f32, y: f32, z: f32) -> Vertex {
+        Vertex {
+            transformed_position: Vector3::new(x, y, z),
+            ..Vertex::default()
+        }
+    }
+
+    fn light() -> Light {
+        Light::new(Vector3::zero())
+    }
+
+    // Small range snapped to quarter pixels, so exact edge hits are common
+    fn coord(rng: &mut StdRng) -> f32 {
+        rng.random_range(0..=96) as f32 * 0.25
+    }
+
+    fn random_vertex(rng: &mut StdRng) -> Vertex {
+        vertex(coord(rng), coord(rng), rng.random_range(-1.0..1.0))
+    }
+
+    fn pixels(fragments: &[Fragment]) -> Vec<(i32, i32)> {
+        fragments
+            .iter()
+            .map(|f| (f.position.x.floor() as i32, f.position.y.floor() as i32))
+            .collect()
+    }
+
+    fn rasterize(a: &Vertex, b: &Vertex, c: &Vertex) -> Vec<Fragment> {
+        triangle(a, b, c, &light())
+    }
+
+    #[test]
+    fn huge_triangles_are_clipped_to_the_target() {
+        let (a, b, c) = (vertex(-1e7, -1e7, 0.0), vertex(1e7, -1e7, 0.0), vertex(0.0, 1e7, 0.0));
+        let mut fragments = Vec::new();
+        assert!(triangle_into(&a, &b, &c, &light(), None, (64, 48), &mut fragments));
+        assert_eq!(fragments.len(), 64 * 48, "covers the whole target, once per pixel");
+        assert!(fragments.iter().all(|f| (0.0..64.0).contains(&f.position.x) && (0.0..48.0).contains(&f.position.y)));
+
+        fragments.clear();
+        let (a, b, c) = (vertex(0.0, 0.0, 0.0), vertex(10.0, 0.0, 0.0), vertex(0.0, 10.0, 0.0));
+        assert!(!triangle_into(&a, &b, &c, &light(), None, (64, 48), &mut fragments));
+        assert!(!fragments.is_empty());
+    }
+
+    #[test]
+    fn fragments_lie_inside_the_triangle() {
+        let mut rng = StdRng::seed_from_u64(113);
+        for _ in 0..CASES {
+            let (a, b, c) = (random_vertex(&mut rng), random_vertex(&mut rng), random_vertex(&mut rng));
+            let (p0, p1, p2) = (a.transformed_position, b.transformed_position, c.transformed_position);
+            let area = edge(p0, p1, p2);
+
+            for f in rasterize(&a, &b, &c) {
+                let p = Vector3::new(f.position.x, f.position.y, 0.0);
+                for w in [edge(p1, p2, p), edge(p2, p0, p), edge(p0, p1, p)] {
+                    assert!(w * area.signum() >= 0.0, "fragment {:?} outside {:?} {:?} {:?}", p, p0, p1, p2);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fragments_stay_within_bounding_box() {
+        let mut rng = StdRng::seed_from_u64(1130);
+        for _ in 0..CASES {
+            let (a, b, c) = (random_vertex(&mut rng), random_vertex(&mut rng), random_vertex(&mut rng));
+            let ps = [a.transformed_position, b.transformed_position, c.transformed_position];
+            let min_x = ps.iter().map(|p| p.x).fold(f32::INFINITY, f32::min);
+            let max_x = ps.iter().map(|p| p.x).fold(f32::NEG_INFINITY, f32::max);
+            let min_y = ps.iter().map(|p| p.y).fold(f32::INFINITY, f32::min);
+            let max_y = ps.iter().map(|p| p.y).fold(f32::NEG_INFINITY, f32::max);
+
+            for f in rasterize(&a, &b, &c) {
+                assert!(f.position.x >= min_x && f.position.x <= max_x);
+                assert!(f.position.y >= min_y && f.position.y <= max_y);
+            }
+        }
+    }
+
+    #[test]
+    fn no_pixel_is_emitted_twice() {
+        let mut rng = StdRng::seed_from_u64(1131);
+        for _ in 0..CASES {
+            let (a, b, c) = (random_vertex(&mut rng), random_vertex(&mut rng), random_vertex(&mut rng));
+            let emitted = pixels(&rasterize(&a, &b, &c));
+            let unique: HashSet<_> = emitted.iter().collect();
+            assert_eq!(unique.len(), emitted.len());
+        }
+    }
+
+    // Random convex quad: four points at increasing angles around a center
+    fn random_convex_quad(rng: &mut StdRng) -> Option<[Vertex; 4]> {
+        let (cx, cy) = (12.0, 12.0);
+        let mut angles: Vec<f32> = (0..4).map(|_| rng.random_range(0.0..std::f32::consts::TAU)).collect();
+        angles.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let quad: Vec<Vertex> = angles
+            .iter()
+            .map(|t| {
+                let r = rng.random_range(4.0..12.0);
+                let snap = |v: f32| (v * 4.0).round() / 4.0;
+                vertex(snap(cx + r * t.cos()), snap(cy + r * t.sin()), rng.random_range(-1.0..1.0))
+            })
+            .collect();
+
+        // Snapping can break convexity; skip those cases
+        let p: Vec<Vector3> = quad.iter().map(|v| v.transformed_position).collect();
+        let turns: Vec<f32> = (0..4).map(|i| edge(p[i], p[(i + 1) % 4], p[(i + 2) % 4])).collect();
+        let convex = turns.iter().all(|&t| t > 0.0) || turns.iter().all(|&t| t < 0.0);
+        convex.then(|| [quad[0].clone(), quad[1].clone(), quad[2].clone(), quad[3].clone()])
+    }
+
+    fn split(first: [&Vertex; 3], second: [&Vertex; 3]) -> Vec<(i32, i32)> {
+        let a = pixels(&rasterize(first[0], first[1], first[2]));
+        let b = pixels(&rasterize(second[0], second[1], second[2]));
+        let a_set: HashSet<_> = a.iter().copied().collect();
+        for p in &b {
+            assert!(!a_set.contains(p), "pixel {:?} drawn by both triangles", p);
+        }
+        let mut union: Vec<_> = a.into_iter().chain(b).collect();
+        union.sort();
+        union
+    }
+
+    #[test]
+    fn quad_split_is_watertight() {
+        let mut rng = StdRng::seed_from_u64(1132);
+        let mut checked = 0;
+        while checked < CASES {
+            let Some([q0, q1, q2, q3]) = random_convex_quad(&mut rng) else { continue };
+            checked += 1;
+
+            // Both diagonals must cover exactly the same pixels with no overlap
+            let diagonal_02 = split([&q0, &q1, &q2], [&q0, &q2, &q3]);
+            let diagonal_13 = split([&q1, &q2, &q3], [&q1, &q3, &q0]);
+            assert_eq!(diagonal_02, diagonal_13);
+        }
+    }
+
+    #[test]
+    fn shared_edge_is_owned_once_for_both_windings() {
+        // Vertical, horizontal and diagonal shared edges through pixel centers
+        let edges = [((4.5, 0.5), (4.5, 9.5)), ((0.5, 4.5), (9.5, 4.5)), ((0.5, 0.5), (9.5, 9.5))];
+        for ((ax, ay), (bx, by)) in edges {
+            let a = vertex(ax, ay, 0.0);
+            let b = vertex(bx, by, 0.0);
+            // Apexes on opposite sides of the edge
+            let (dx, dy) = (by - ay, ax - bx);
+            let left = vertex(ax + dx, ay + dy, 0.0);
+            let right = vertex(ax - dx, ay - dy, 0.0);
+
+            for (first, second) in [([&a, &b, &left], [&b, &a, &right]), ([&b, &a, &left], [&a, &b, &right])] {
+                split(first, second);
+            }
+        }
+    }
+
+    #[test]
+    fn depth_matches_vertex_depth_at_vertex_pixels() {
+        let mut rng = StdRng::seed_from_u64(1133);
+        for _ in 0..CASES {
+            // Vertices on pixel centers
+            let mut at_center = || {
+                vertex(
+                    rng.random_range(0..24) as f32 + 0.5,
+                    rng.random_range(0..24) as f32 + 0.5,
+                    rng.random_range(-1.0..1.0),
+                )
+            };
+            let (a, b, c) = (at_center(), at_center(), at_center());
+            let fragments = rasterize(&a, &b, &c);
+
+            for v in [&a, &b, &c] {
+                let p = v.transformed_position;
+                if let Some(f) = fragments.iter().find(|f| f.position.x == p.x && f.position.y == p.y) {
+                    assert!((f.depth - p.z).abs() < 1e-4, "depth {} at vertex with z {}", f.depth, p.z);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn degenerate_triangles_emit_nothing() {
+        let cases = [
+            // Collinear
+            (vertex(0.0, 0.0, 0.0), vertex(5.0, 5.0, 0.0), vertex(10.0, 10.0, 0.0)),
+            // Repeated vertex
+            (vertex(3.0, 3.0, 0.0), vertex(3.0, 3.0, 0.0), vertex(8.0, 1.0, 0.0)),
+            // Single point
+            (vertex(2.5, 2.5, 0.0), vertex(2.5, 2.5, 0.0), vertex(2.5, 2.5, 0.0)),
+            // Non-finite
+            (vertex(f32::NAN, 0.0, 0.0), vertex(5.0, 0.0, 0.0), vertex(0.0, 5.0, 0.0)),
+        ];
+        for (a, b, c) in cases {
+            assert!(rasterize(&a, &b, &c).is_empty());
+        }
+    }
+
+    #[test]
+    fn sliver_triangles_do_not_panic() {
+        let mut rng = StdRng::seed_from_u64(1134);
+        for _ in 0..CASES {
+            let a = vertex(coord(&mut rng), coord(&mut rng), 0.0);
+            let b = vertex(coord(&mut rng), coord(&mut rng), 0.0);
+            let pa = a.transformed_position;
+            let pb = b.transformed_position;
+            // Third vertex a hair off the line through a and b
+            let t: f32 = rng.random_range(0.0..1.0);
+            let c = vertex(pa.x + (pb.x - pa.x) * t + 1e-3, pa.y + (pb.y - pa.y) * t, 0.0);
+
+            for f in rasterize(&a, &b, &c) {
+                assert!(f.depth.is_finite());
+            }
+        }
+    }
+
+    #[test]
+    fn centroid_color_bulges_inside_and_keeps_the_edges() {
+        let (a, b, c) = (vertex(0.0, 0.0, 0.0), vertex(60.0, 0.0, 0.0), vertex(0.0, 60.0, 0.0));
+        let mut fragments = Vec::new();
+        triangle_into(&a, &b, &c, &light(), Some(Vector3::one()), (64, 64), &mut fragments);
+
+        let middle = Vector2::new(20.0, 20.0);
+        let nearest = fragments.iter().min_by(|f, g| (f.position - middle).length().total_cmp(&(g.position - middle).length()));
+        assert!(nearest.expect("the triangle is drawn").color.x > 0.95);
+        for f in &fragments {
+            // Distance to the nearest edge, in pixels
+            let (x, y) = (f.position.x, f.position.y);
+            if x.min(y).min((60.0 - x - y) / 2f32.sqrt()) < 1.0 {
+                assert!(f.color.x < 0.2, "edge fragment at ({}, {}) took {}", x, y, f.color.x);
+            }
+        }
+    }
+
+    #[test]
+    fn counter_clockwise_faces_the_camera_after_the_y_flip() {
+        // Counter-clockwise with y up, as seen by the camera; the viewport flips y
+        let screen = |x: f32, y: f32| Vector3::new(50.0 + 40.0 * x, 50.0 - 40.0 * y, 0.0);
+        let (a, b, c) = (screen(0.0, 0.0), screen(1.0, 0.0), screen(0.0, 1.0));
+        assert!(is_front_facing(a, b, c));
+        assert!(is_front_facing(b, c, a));
+        assert!(!is_front_facing(a, c, b));
+    }
+}
@@ -0,0 +1,200 @@
+#![allow(dead_code)]
+
+use raylib::prelude::*;
+
+use crate::input::InputState;
+use crate::params::{shader_params, ShaderParamsFile};
+use crate::scene::SceneObject;
+
+/// Key that shows and hides the tweak panel. F10 is taken by the discovery reset and
+/// F12 by raylib's screenshot.
+pub const TWEAK_KEY: KeyboardKey = KeyboardKey::KEY_F11;
+
+const PANEL_X: i32 = 10;
+const PANEL_Y: i32 = 180;
+const PANEL_WIDTH: i32 = 320;
+const ROW_HEIGHT: i32 = 22;
+const LABEL_WIDTH: i32 = 130;
+const SLIDER_WIDTH: i32 = 120;
+
+/// One slider: a number of the body, where it can be read and written.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tweak {
+    pub name: String,
+    pub value: f32,
+    pub min: f32,
+    pub max: f32,
+}
+
+fn tweak(name: &str, value: f32, min: f32, max: f32) -> Tweak {
+    Tweak { name: name.to_string(), value, min, max }
+}
+
+/// The numbers of `obj` the panel offers, in order: its bespoke shader's parameters (as the
+/// shader sees them, `file`'s values under the body's own), the generator parameters of a
+/// generated kind, then its material.
+pub fn tweaks(obj: &SceneObject, file: &ShaderParamsFile) -> Vec<Tweak> {
+    let values = file.for_body(&obj.object_type, &obj.params);
+    let mut list: Vec<Tweak> = shader_params(&obj.object_type)
+        .iter()
+        .map(|p| tweak(p.name, values.get(p.name, p.default), p.min, p.max))
+        .collect();
+    if let Some(planet) = &obj.planet {
+        list.push(tweak("band_freq", planet.band_freq, 0.0, 40.0));
+        list.push(tweak("noise_scale", planet.noise_scale, 0.1, 10.0));
+        list.push(tweak("crater_density", planet.crater_density, 0.0, 1.0));
+        list.push(tweak("cloud_amount", planet.cloud_amount, 0.0, 1.0));
+        for (i, color) in planet.base_palette.iter().enumerate() {
+            for (channel, value) in ["r", "g", "b"].iter().zip([color.x, color.y, color.z]) {
+                list.push(tweak(&format!("palette{}.{}", i, channel), value, 0.0, 1.0));
+            }
+        }
+    }
+    let shading = obj.material.shading(&obj.object_type);
+    list.push(tweak("roughness", shading.roughness, 0.02, 1.0));
+    list.push(tweak("metalness", shading.metalness, 0.0, 1.0));
+    if obj.sphere {
+        // A switch: dragged past the middle turns it on
+        list.push(tweak("graticule", obj.flags.graticule as i32 as f32, 0.0, 1.0));
+    }
+    list
+}
+
+/// Writes `value` back to where `tweaks` read `name` from.
+pub fn apply(obj: &mut SceneObject, name: &str, value: f32) {
+    if shader_params(&obj.object_type).iter().any(|p| p.name == name) {
+        obj.params.set(name, value);
+        return;
+    }
+    match name {
+        "roughness" => obj.material.roughness = Some(value),
+        "metalness" => obj.material.metalness = Some(value),
+        "graticule" => obj.flags.graticule = value >= 0.5,
+        _ => {}
+    }
+    let Some(planet) = obj.planet.as_mut() else {
+        return;
+    };
+    match name {
+        "band_freq" => planet.band_freq = value,
+        "noise_scale" => planet.noise_scale = value,
+        "crater_density" => planet.crater_density = value,
+        "cloud_amount" => planet.cloud_amount = value,
+        _ => {
+            let Some((i, channel)) = name.strip_prefix("palette").and_then(|rest| rest.split_once('.')) else {
+                return;
+            };
+            let Some(color) = i.parse::<usize>().ok().and_then(|i| planet.base_palette.get_mut(i)) else {
+                return;
+            };
+            match channel {
+                "r" => color.x = value,
+                "g" => color.y = value,
+                "b" => color.z = value,
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Immediate-mode sliders for the selected body's shader and material numbers. Values
+/// are written straight into the body, so `SAVE_SCENE_KEY` keeps them.
+pub struct TweakPanel {
+    pub visible: bool,
+    dragging: Option<usize>, // Row of the slider held by the mouse
+}
+
+impl TweakPanel {
+    pub fn new() -> Self {
+        TweakPanel { visible: false, dragging: None }
+    }
+
+    pub fn process_input(&mut self, input: &InputState) {
+        if input.is_key_pressed(TWEAK_KEY) {
+            self.visible = !self.visible;
+            self.dragging = None;
+        }
+    }
+
+    fn panel_height(rows: usize) -> i32 {
+        ROW_HEIGHT * (rows as i32 + 1)
+    }
+
+    /// Whether the mouse is over the panel, so clicks on it don't reach the scene.
+    pub fn hovered(&self, input: &InputState, obj: Option<&SceneObject>, file: &ShaderParamsFile) -> bool {
+        let Some(obj) = obj.filter(|_| self.visible) else {
+            return false;
+        };
+        let mouse = input.get_mouse_position();
+        let height = Self::panel_height(tweaks(obj, file).len());
+        self.dragging.is_some()
+            || (mouse.x >= PANEL_X as f32
+                && mouse.x < (PANEL_X + PANEL_WIDTH) as f32
+                && mouse.y >= PANEL_Y as f32
+                && mouse.y < (PANEL_Y + height) as f32)
+    }
+
+    /// Drags the slider under the mouse. Returns true when a value of `obj` changed.
+    pub fn update(&mut self, input: &InputState, obj: Option<&mut SceneObject>, file: &ShaderParamsFile) -> bool {
+        let Some(obj) = obj.filter(|_| self.visible) else {
+            self.dragging = None;
+            return false;
+        };
+        if !input.is_mouse_button_down(MouseButton::MOUSE_BUTTON_LEFT) {
+            self.dragging = None;
+            return false;
+        }
+        let list = tweaks(obj, file);
+        let mouse = input.get_mouse_position();
+        let slider_x = (PANEL_X + LABEL_WIDTH) as f32;
+        if input.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
+            let row = ((mouse.y - (PANEL_Y + ROW_HEIGHT) as f32) / ROW_HEIGHT as f32).floor();
+            let on_slider = mouse.x >= slider_x && mouse.x <= slider_x + SLIDER_WIDTH as f32;
+            self.dragging = (on_slider && row >= 0.0 && (row as usize) < list.len()).then_some(row as usize);
+        }
+        let Some(t) = self.dragging.and_then(|row| list.get(row)) else {
+            return false;
+        };
+        let f = ((mouse.x - slider_x) / SLIDER_WIDTH as f32).clamp(0.0, 1.0);
+        let value = t.min + (t.max - t.min) * f;
+        if value == t.value {
+            return false;
+        }
+        apply(obj, &t.name, value);
+        true
+    }
+
+    pub fn draw(&self, d: &mut RaylibDrawHandle, obj: Option<&SceneObject>, file: &ShaderParamsFile) {
+        if !self.visible {
+            return;
+        }
+        let Some(obj) = obj else {
+            d.draw_text("Tweak (F11): no body selected", PANEL_X, PANEL_Y, 16, Color::LIGHTGRAY);
+            return;
+        };
+        let list = tweaks(obj, file);
+        let height = Self::panel_height(list.len());
+        d.draw_rectangle(PANEL_X - 4, PANEL_Y - 4, PANEL_WIDTH, height + 4, Color::new(0, 0, 0, 170));
+        d.draw_text(&format!("Tweak (F11): {}", obj.name), PANEL_X, PANEL_Y, 16, Color::WHITE);
+        for (row, t) in list.iter().enumerate() {
+            let y = PANEL_Y + ROW_HEIGHT * (row as i32 + 1);
+            let f = ((t.value - t.min) / (t.max - t.min)).clamp(0.0, 1.0);
+            let color = if self.dragging == Some(row) { Color::YELLOW } else { Color::LIGHTGRAY };
+            d.draw_text(&t.name, PANEL_X, y + 4, 10, color);
+            d.draw_rectangle(PANEL_X + LABEL_WIDTH, y + 6, SLIDER_WIDTH, 6, Color::DARKGRAY);
+            d.draw_rectangle(PANEL_X + LABEL_WIDTH, y + 6, (SLIDER_WIDTH as f32 * f) as i32, 6, color);
+            d.draw_text(&format!("{:.3}", t.value), PANEL_X + LABEL_WIDTH + SLIDER_WIDTH + 10, y + 4, 10, color);
+        }
+    }
+}
+
+/// The body the panel edits: the isolated one, or else the one nearest the camera's target.
+pub fn selected(objects: &[SceneObject], isolate: Option<usize>, target: Vector3) -> Option<usize> {
+    isolate.filter(|&i| i < objects.len()).or_else(|| {
+        (0..objects.len()).min_by(|&a, &b| {
+            let da = (objects[a].translation - target).length();
+            let db = (objects[b].translation - target).length();
+            da.total_cmp(&db)
+        })
+    })
+}
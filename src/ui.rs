@@ -0,0 +1,156 @@
+#![allow(dead_code)]
+
+use raylib::prelude::*;
+
+use crate::framebuffer::GlowTarget;
+use crate::matrix::world_to_screen;
+use crate::Uniforms;
+
+// UI comes in two layers. World-anchored elements (body names, measurement labels) are
+// drawn into the framebuffer before present, depth-tested so nearer bodies hide them,
+// with the bitmap font below. Screen elements (FPS, HUD, console) are drawn after
+// present with raylib and are never hidden.
+
+pub const GLYPH_WIDTH: i32 = 5;
+pub const GLYPH_HEIGHT: i32 = 7;
+const ADVANCE: i32 = GLYPH_WIDTH + 1;
+/// Pixels between a world label's anchor and the top of its text.
+const LABEL_OFFSET: i32 = 6;
+
+/// Rows of a 5×7 glyph, top first, leftmost pixel in bit 4. Lowercase letters are drawn
+/// as capitals; characters with no glyph as `?`.
+pub fn glyph(c: char) -> [u8; 7] {
+    match c.to_ascii_uppercase() {
+        'A' => [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001],
+        'B' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110],
+        'C' => [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110],
+        'D' => [0b11110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b11110],
+        'E' => [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111],
+        'F' => [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000],
+        'G' => [0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111],
+        'H' => [0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001],
+        'I' => [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+        'J' => [0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100],
+        'K' => [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001],
+        'L' => [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111],
+        'M' => [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001],
+        'N' => [0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001],
+        'O' => [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110],
+        'P' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000],
+        'Q' => [0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101],
+        'R' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001],
+        'S' => [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110],
+        'T' => [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100],
+        'U' => [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110],
+        'V' => [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100],
+        'W' => [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010],
+        'X' => [0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001],
+        'Y' => [0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100, 0b00100],
+        'Z' => [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111],
+        '0' => [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110],
+        '1' => [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+        '2' => [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111],
+        '3' => [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110],
+        '4' => [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010],
+        '5' => [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110],
+        '6' => [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110],
+        '7' => [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000],
+        '8' => [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110],
+        '9' => [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100],
+        ' ' => [0; 7],
+        '.' => [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100],
+        ',' => [0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b00100, 0b01000],
+        '-' => [0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000],
+        '+' => [0b00000, 0b00100, 0b00100, 0b11111, 0b00100, 0b00100, 0b00000],
+        '(' => [0b00010, 0b00100, 0b01000, 0b01000, 0b01000, 0b00100, 0b00010],
+        ')' => [0b01000, 0b00100, 0b00010, 0b00010, 0b00010, 0b00100, 0b01000],
+        ':' => [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000],
+        '/' => [0b00000, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b00000],
+        '%' => [0b11000, 0b11001, 0b00010, 0b00100, 0b01000, 0b10011, 0b00011],
+        '_' => [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b11111],
+        '\'' => [0b00100, 0b00100, 0b01000, 0b00000, 0b00000, 0b00000, 0b00000],
+        _ => [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b00000, 0b00100],
+    }
+}
+
+/// Width in pixels of `text` in the bitmap font.
+pub fn text_width(text: &str) -> i32 {
+    (text.chars().count() as i32 * ADVANCE - 1).max(0)
+}
+
+/// Rasterizes `text` with its top-left corner at (x, y), at NDC depth `depth`: each
+/// pixel, and its dark shadow one pixel down and right, is only drawn where nothing
+/// nearer is. Leaves the depth buffer untouched.
+pub fn draw_text_depth(target: &mut impl GlowTarget, text: &str, x: i32, y: i32, color: Vector3, depth: f32) {
+    let mut plot = |px: i32, py: i32, c: Vector3| {
+        if target.depth_mode().closer(depth, target.depth_at(px, py)) {
+            target.set_color(px, py, c);
+        }
+    };
+    for pass in [0, 1] {
+        let (offset, c) = if pass == 0 { (1, Vector3::zero()) } else { (0, color) };
+        for (i, ch) in text.chars().enumerate() {
+            let left = x + i as i32 * ADVANCE;
+            for (row, bits) in glyph(ch).iter().enumerate() {
+                for column in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - column)) != 0 {
+                        plot(left + column + offset, y + row as i32 + offset, c);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// World-anchored label: `text` centered just below where `world_position` lands on
+/// screen, hidden wherever nearer geometry covers it. Returns false when the anchor is
+/// behind the camera.
+pub fn draw_world_label(target: &mut impl GlowTarget, world_position: Vector3, text: &str, uniforms: &Uniforms, color: Vector3) -> bool {
+    let frame = &uniforms.frame;
+    let Some(screen) = world_to_screen(world_position, &frame.view, &frame.projection, &frame.viewport) else {
+        return false;
+    };
+    let x = screen.x.round() as i32 - text_width(text) / 2;
+    draw_text_depth(target, text, x, screen.y.round() as i32 + LABEL_OFFSET, color, screen.z);
+    true
+}
+
+/// Screen UI: text drawn over the presented frame with raylib, never occluded.
+pub fn draw_screen_text(d: &mut RaylibDrawHandle, text: &str, x: i32, y: i32, size: i32, color: Color) {
+    d.draw_text(text, x, y, size, color);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::{render_scene, HeadlessCamera, RenderOptions};
+    use crate::light::LightConfig;
+    use crate::scene::SceneObject;
+    use crate::{FrameUniforms, ObjectUniforms};
+
+    const SIZE: u32 = 128;
+
+    /// Pixels the label for `body` changes once the sun and `body` are rendered, anchored
+    /// on the side facing the camera the way discovery labels are.
+    fn label_pixels(body: SceneObject) -> usize {
+        let sun = SceneObject::test_sphere("sun", "sun", Vector3::zero(), 4.0);
+        let camera = HeadlessCamera::looking_at(Vector3::new(0.0, 0.0, 20.0), Vector3::zero());
+        let mut target = render_scene(&[sun, body.clone()], &camera, &LightConfig::default(), SIZE, SIZE, 0.0, &RenderOptions::default());
+        let before = target.colors.clone();
+
+        let (view, projection, viewport) = camera.matrices(SIZE, SIZE);
+        let frame = FrameUniforms::new(view, projection, viewport, camera.eye);
+        let uniforms = Uniforms { frame, object: ObjectUniforms::new(Matrix::identity(), &frame) };
+        let front = body.translation + (camera.eye - body.translation).normalized() * (body.radius * 1.05);
+        assert!(draw_world_label(&mut target, front, "MARS", &uniforms, Vector3::new(1.0, 0.0, 1.0)));
+        target.colors.iter().zip(&before).filter(|(a, b)| a != b).count()
+    }
+
+    #[test]
+    fn a_label_behind_the_sun_is_hidden() {
+        let behind = SceneObject::test_sphere("mars", "rocky1", Vector3::new(0.0, 0.0, -10.0), 1.0);
+        assert_eq!(label_pixels(behind), 0);
+        let beside = SceneObject::test_sphere("mars", "rocky1", Vector3::new(14.0, 0.0, -10.0), 1.0);
+        assert!(label_pixels(beside) > 20);
+    }
+}
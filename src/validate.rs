@@ -0,0 +1,218 @@
+#![allow(dead_code)]
+
+use std::fmt;
+
+use crate::scene::SceneObject;
+
+/// A likely mistake in a scene: which bodies, what's wrong, and how to fix it. None of
+/// them stop the scene from rendering; they're listed in the error panel on load and by
+/// the console's `validate`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SceneWarning {
+    pub bodies: Vec<String>,
+    pub problem: String,
+    pub fix: String,
+}
+
+impl SceneWarning {
+    fn new(bodies: &[&str], problem: String, fix: String) -> Self {
+        SceneWarning { bodies: bodies.iter().map(|b| b.to_string()).collect(), problem, fix }
+    }
+}
+
+impl fmt::Display for SceneWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}; {}", self.problem, self.fix)
+    }
+}
+
+/// Checks the live bodies for mistakes that render without complaint but look wrong:
+/// duplicate names, missing parents, bodies overlapping each other where they are now,
+/// moons orbiting inside their planet, rings wider than their planet's Hill sphere, and
+/// orbits reaching past `far`, the farthest the depth range can see.
+pub fn validate(objects: &[SceneObject], far: f32) -> Vec<SceneWarning> {
+    let mut warnings = Vec::new();
+    let find = |name: &str| objects.iter().find(|o| o.name == name);
+    let radius = |o: &SceneObject| o.radius * o.scale;
+
+    for (i, obj) in objects.iter().enumerate() {
+        if objects[..i].iter().any(|o| o.name == obj.name) {
+            warnings.push(SceneWarning::new(
+                &[&obj.name],
+                format!("two bodies are named '{}'", obj.name),
+                "rename one; children and bookmarks only find the first".to_string(),
+            ));
+        }
+        if let Some(parent) = &obj.parent
+            && find(parent).is_none()
+        {
+            warnings.push(SceneWarning::new(
+                &[&obj.name],
+                format!("{}'s parent '{}' doesn't exist", obj.name, parent),
+                "fix the parent's name, or remove `parent` to orbit the origin".to_string(),
+            ));
+        }
+    }
+
+    // Free bodies sitting inside each other at their current positions, not over their
+    // orbits; the shuttle and rings come and go on their own
+    let free: Vec<&SceneObject> = objects
+        .iter()
+        .filter(|o| o.parent.is_none() && o.object_type != "shuttle" && o.object_type != "ring" && o.flags.visible)
+        .collect();
+    for (i, a) in free.iter().enumerate() {
+        for b in &free[i + 1..] {
+            let distance = (a.translation - b.translation).length();
+            let reach = radius(a) + radius(b);
+            if distance < reach {
+                warnings.push(SceneWarning::new(
+                    &[&a.name, &b.name],
+                    format!("{} and {} overlap where they are now ({:.2} apart, radii add up to {:.2})", a.name, b.name, distance, reach),
+                    format!("move them at least {:.2} apart", reach),
+                ));
+            }
+        }
+    }
+
+    for obj in objects {
+        let Some(parent) = obj.parent.as_deref().and_then(find) else {
+            continue;
+        };
+        if obj.object_type == "ring" {
+            if let Some(hill) = hill_radius(parent, objects)
+                && let Some(ring) = &obj.ring
+                && ring.outer_radius * obj.scale > hill
+            {
+                warnings.push(SceneWarning::new(
+                    &[&obj.name, &parent.name],
+                    format!(
+                        "{} reaches {:.2} out, past {}'s Hill sphere ({:.2}), where the sun would strip it away",
+                        obj.name,
+                        ring.outer_radius * obj.scale,
+                        parent.name,
+                        hill
+                    ),
+                    format!("shrink its outer_radius or scale, or move {} farther out", parent.name),
+                ));
+            }
+            continue;
+        }
+        let closest = periapsis(obj);
+        let clearance = radius(parent) + radius(obj);
+        if closest < clearance {
+            let fix = match obj.orbit {
+                Some(_) => format!("raise its semi_major_axis to at least {:.2}", clearance / (1.0 - eccentricity(obj)).max(1e-3)),
+                None => format!("move its offset at least {:.2} from {}", clearance, parent.name),
+            };
+            warnings.push(SceneWarning::new(
+                &[&obj.name, &parent.name],
+                format!("{} orbits inside {} (closest {:.2}, radii add up to {:.2})", obj.name, parent.name, closest, clearance),
+                fix,
+            ));
+        }
+    }
+
+    for obj in objects.iter().filter(|o| o.orbit.is_some()) {
+        let farthest = apoapsis_from_origin(obj, objects, 0) + radius(obj);
+        if farthest > far {
+            warnings.push(SceneWarning::new(
+                &[&obj.name],
+                format!("{}'s orbit reaches {:.0} from the center, past the far plane at {:.0}", obj.name, farthest, far),
+                "raise [depth_range] max_far (or far), or shrink the orbit".to_string(),
+            ));
+        }
+    }
+    warnings
+}
+
+fn eccentricity(obj: &SceneObject) -> f32 {
+    obj.orbit.as_ref().map_or(0.0, |orbit| orbit.eccentricity.clamp(0.0, 0.99))
+}
+
+/// Closest `obj` gets to its parent: the periapsis of its orbit, or the distance it's
+/// placed at when it doesn't orbit.
+fn periapsis(obj: &SceneObject) -> f32 {
+    match &obj.orbit {
+        Some(orbit) => ((obj.offset.length() - orbit.semi_major_axis).abs() - orbit.semi_major_axis * eccentricity(obj)).max(0.0),
+        None => obj.offset.length(),
+    }
+}
+
+/// Farthest `obj` gets from the origin, following its parents' orbits out.
+fn apoapsis_from_origin(obj: &SceneObject, objects: &[SceneObject], depth: usize) -> f32 {
+    let own = obj.offset.length() + obj.orbit.as_ref().map_or(0.0, |orbit| orbit.semi_major_axis * (1.0 + eccentricity(obj)));
+    let parent = obj.parent.as_deref().and_then(|name| objects.iter().find(|o| o.name == name));
+    match parent {
+        // The depth guards against parents that loop back on themselves
+        Some(parent) if depth < objects.len() => own + apoapsis_from_origin(parent, objects, depth + 1),
+        _ => own,
+    }
+}
+
+/// Radius around `planet` inside which its own gravity beats its central body's tides:
+/// a(1 - e)·∛(m / 3M) at periapsis. The central body is its parent, or for a free body
+/// the heaviest other free one (the sun). None when it doesn't orbit anything.
+fn hill_radius(planet: &SceneObject, objects: &[SceneObject]) -> Option<f32> {
+    let orbit = planet.orbit.as_ref()?;
+    let central = match &planet.parent {
+        Some(name) => objects.iter().find(|o| &o.name == name)?,
+        None => objects
+            .iter()
+            .filter(|o| o.parent.is_none() && o.name != planet.name && o.object_type != "shuttle")
+            .max_by(|a, b| a.mass().total_cmp(&b.mass()))?,
+    };
+    let ratio = planet.mass() / (3.0 * central.mass()).max(1e-12);
+    Some(orbit.semi_major_axis * (1.0 - eccentricity(planet)) * ratio.cbrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use raylib::prelude::Vector3;
+    use crate::scene::default_scene_objects;
+
+    #[test]
+    fn default_scene_is_clean() {
+        let warnings = validate(&default_scene_objects(), 10000.0);
+        assert!(warnings.is_empty(), "{:?}", warnings);
+    }
+
+    #[test]
+    fn moon_inside_its_planet_is_reported() {
+        let mut objects = default_scene_objects();
+        let moon = objects.iter_mut().find(|o| o.object_type == "moon").expect("the default scene has a moon");
+        let (name, parent) = (moon.name.clone(), moon.parent.clone().expect("the moon has a parent"));
+        match moon.orbit.as_mut() {
+            Some(orbit) => orbit.semi_major_axis = 0.1,
+            None => moon.offset = Vector3::new(0.1, 0.0, 0.0),
+        }
+
+        let warnings = validate(&objects, 10000.0);
+        let inside = warnings.iter().find(|w| w.problem.contains("orbits inside")).expect("a warning about the moon");
+        assert_eq!(inside.bodies, vec![name, parent]);
+        assert!(inside.fix.contains("semi_major_axis") || inside.fix.contains("offset"));
+
+        assert!(validate(&objects, 1.0).iter().any(|w| w.problem.contains("far plane")));
+    }
+
+    #[test]
+    fn a_child_placed_inside_its_parent_is_told_to_move_its_offset() {
+        let planet = SceneObject::test_sphere("planet", "rocky1", Vector3::zero(), 2.0);
+        let mut moon = SceneObject::test_sphere("moon", "moon", Vector3::zero(), 0.5);
+        moon.parent = Some("planet".to_string());
+        moon.offset = Vector3::new(1.0, 0.0, 0.0);
+        moon.orbit = None;
+
+        let warnings = validate(&[planet, moon], 10000.0);
+        let inside = warnings.iter().find(|w| w.problem.contains("orbits inside")).expect("a warning about the moon");
+        assert!(inside.fix.contains("offset at least 2.50"), "{}", inside.fix);
+    }
+
+    #[test]
+    fn overlaps_say_they_are_where_the_bodies_are_now() {
+        let a = SceneObject::test_sphere("a", "rocky1", Vector3::zero(), 1.0);
+        let b = SceneObject::test_sphere("b", "rocky1", Vector3::new(1.5, 0.0, 0.0), 1.0);
+        let warnings = validate(&[a, b], 10000.0);
+        assert!(warnings.iter().any(|w| w.problem.contains("overlap where they are now")), "{:?}", warnings);
+    }
+}
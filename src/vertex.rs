@@ -10,6 +10,7 @@ pub struct Vertex {
   pub color: Vector3,
   pub transformed_position: Vector3,
   pub transformed_normal: Vector3,
+  pub world_position: Vector3,
 }
 
 impl Vertex {
@@ -21,6 +22,7 @@ impl Vertex {
       color: Vector3::new(0.0, 0.0, 0.0), // Black
       transformed_position: position,
       transformed_normal: normal,
+      world_position: position,
     }
   }
 
@@ -32,6 +34,7 @@ impl Vertex {
       color,
       transformed_position: Vector3::new(0.0, 0.0, 0.0),
       transformed_normal: Vector3::new(0.0, 0.0, 0.0),
+      world_position: position,
     }
   }
 
@@ -50,6 +53,7 @@ impl Default for Vertex {
       color: Vector3::new(0.0, 0.0, 0.0), // Black
       transformed_position: Vector3::new(0.0, 0.0, 0.0),
       transformed_normal: Vector3::new(0.0, 1.0, 0.0),
+      world_position: Vector3::new(0.0, 0.0, 0.0),
     }
   }
 }
\ No newline at end of file